@@ -0,0 +1,94 @@
+use p2d::bounding_volume::{BoundingVolume, AABB};
+use piet::RenderContext;
+
+use crate::penhelpers::{PenEvent, PenState};
+use crate::penpath::Element;
+use crate::shapes::{Arrow, ArrowHead};
+use crate::style::{drawhelpers, Composer};
+use crate::{Shape, Style};
+
+use super::shapebuilderbehaviour::{BuilderProgress, ShapeBuilderCreator};
+use super::{ConstraintRatio, Constraints, ShapeBuilderBehaviour};
+
+/// arrow builder
+#[derive(Debug, Clone)]
+pub struct ArrowBuilder {
+    /// the start position
+    pub start: na::Vector2<f64>,
+    /// the current position
+    pub current: na::Vector2<f64>,
+    /// the arrow head
+    pub head: ArrowHead,
+    /// Wether the arrow has a head at the start as well
+    pub double_headed: bool,
+}
+
+impl ShapeBuilderCreator for ArrowBuilder {
+    fn start(element: Element) -> Self {
+        Self {
+            start: element.pos,
+            current: element.pos,
+            head: ArrowHead::default(),
+            double_headed: false,
+        }
+    }
+}
+
+impl ShapeBuilderBehaviour for ArrowBuilder {
+    fn handle_event(&mut self, event: PenEvent, mut constraints: Constraints) -> BuilderProgress {
+        // we always want to allow horizontal and vertical constraints while building an arrow
+        constraints.ratios.insert(ConstraintRatio::Horizontal);
+        constraints.ratios.insert(ConstraintRatio::Vertical);
+
+        match event {
+            PenEvent::Down { element, .. } => {
+                self.current = constraints.constrain(element.pos - self.start) + self.start;
+            }
+            PenEvent::Up { .. } => {
+                return BuilderProgress::Finished(vec![Shape::Arrow(self.state_as_arrow())]);
+            }
+            _ => {}
+        }
+
+        BuilderProgress::InProgress
+    }
+
+    fn bounds(&self, style: &Style, zoom: f64) -> Option<AABB> {
+        Some(
+            self.state_as_arrow()
+                .composed_bounds(style)
+                .loosened(drawhelpers::POS_INDICATOR_RADIUS / zoom),
+        )
+    }
+
+    fn draw_styled(&self, cx: &mut piet_cairo::CairoRenderContext, style: &Style, zoom: f64) {
+        cx.save().unwrap();
+        let arrow = self.state_as_arrow();
+        arrow.draw_composed(cx, style);
+
+        drawhelpers::draw_pos_indicator(cx, PenState::Up, self.start, zoom);
+        drawhelpers::draw_pos_indicator(cx, PenState::Down, self.current, zoom);
+        cx.restore().unwrap();
+    }
+}
+
+impl ArrowBuilder {
+    /// Starts building an arrow with the given head
+    pub fn start_w_head(element: Element, head: ArrowHead, double_headed: bool) -> Self {
+        Self {
+            head,
+            double_headed,
+            ..Self::start(element)
+        }
+    }
+
+    /// The current state as arrow
+    pub fn state_as_arrow(&self) -> Arrow {
+        Arrow {
+            start: self.start,
+            end: self.current,
+            head: self.head,
+            double_headed: self.double_headed,
+        }
+    }
+}
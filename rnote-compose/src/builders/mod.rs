@@ -1,3 +1,5 @@
+/// arrow builder
+pub mod arrowbuilder;
 /// cubic bezier builder
 pub mod cubbezbuilder;
 /// ellipse builder
@@ -12,12 +14,17 @@ pub mod penpathbuilder;
 pub mod quadbezbuilder;
 /// rectangle builder
 pub mod rectanglebuilder;
+/// regular polygon builder
+pub mod regularpolygonbuilder;
 /// shape builder behaviour
 pub mod shapebuilderbehaviour;
+/// star builder
+pub mod starbuilder;
 
 use std::collections::HashSet;
 
 // Re-exports
+pub use arrowbuilder::ArrowBuilder;
 pub use cubbezbuilder::CubBezBuilder;
 pub use ellipsebuilder::EllipseBuilder;
 pub use fociellipsebuilder::FociEllipseBuilder;
@@ -25,7 +32,9 @@ pub use linebuilder::LineBuilder;
 pub use penpathbuilder::PenPathBuilder;
 pub use quadbezbuilder::QuadBezBuilder;
 pub use rectanglebuilder::RectangleBuilder;
+pub use regularpolygonbuilder::RegularPolygonBuilder;
 pub use shapebuilderbehaviour::ShapeBuilderBehaviour;
+pub use starbuilder::StarBuilder;
 
 use serde::{Deserialize, Serialize};
 
@@ -53,6 +62,18 @@ pub enum ShapeBuilderType {
     #[serde(rename = "cubbez")]
     /// An cubic bezier builder
     CubBez,
+    #[serde(rename = "arrow")]
+    /// An arrow builder
+    Arrow,
+    #[serde(rename = "double_arrow")]
+    /// A double headed arrow builder
+    DoubleArrow,
+    #[serde(rename = "regular_polygon")]
+    /// A regular polygon builder
+    RegularPolygon,
+    #[serde(rename = "star")]
+    /// A star builder
+    Star,
 }
 
 impl Default for ShapeBuilderType {
@@ -0,0 +1,96 @@
+use p2d::bounding_volume::{BoundingVolume, AABB};
+use piet::RenderContext;
+
+use crate::penhelpers::{PenEvent, PenState};
+use crate::penpath::Element;
+use crate::shapes::Polygon;
+use crate::style::{drawhelpers, Composer};
+use crate::{Shape, Style};
+
+use super::shapebuilderbehaviour::{BuilderProgress, ShapeBuilderCreator};
+use super::{ConstraintRatio, Constraints, ShapeBuilderBehaviour};
+
+/// regular polygon builder. The start is the center, the current position the first outer vertex
+#[derive(Debug, Clone)]
+pub struct RegularPolygonBuilder {
+    /// the start position
+    pub start: na::Vector2<f64>,
+    /// the current position
+    pub current: na::Vector2<f64>,
+    /// the number of sides
+    pub n_sides: u32,
+}
+
+impl ShapeBuilderCreator for RegularPolygonBuilder {
+    fn start(element: Element) -> Self {
+        Self {
+            start: element.pos,
+            current: element.pos,
+            n_sides: Self::SIDES_DEFAULT,
+        }
+    }
+}
+
+impl ShapeBuilderBehaviour for RegularPolygonBuilder {
+    fn handle_event(&mut self, event: PenEvent, mut constraints: Constraints) -> BuilderProgress {
+        // allows aligning the first vertex horizontally or vertically to the center
+        constraints.ratios.insert(ConstraintRatio::Horizontal);
+        constraints.ratios.insert(ConstraintRatio::Vertical);
+
+        match event {
+            PenEvent::Down { element, .. } => {
+                self.current = constraints.constrain(element.pos - self.start) + self.start;
+            }
+            PenEvent::Up { .. } => {
+                return BuilderProgress::Finished(vec![Shape::Polygon(self.state_as_polygon())]);
+            }
+            _ => {}
+        }
+
+        BuilderProgress::InProgress
+    }
+
+    fn bounds(&self, style: &Style, zoom: f64) -> Option<AABB> {
+        Some(
+            self.state_as_polygon()
+                .composed_bounds(style)
+                .loosened(drawhelpers::POS_INDICATOR_RADIUS / zoom),
+        )
+    }
+
+    fn draw_styled(&self, cx: &mut piet_cairo::CairoRenderContext, style: &Style, zoom: f64) {
+        cx.save().unwrap();
+        let polygon = self.state_as_polygon();
+        polygon.draw_composed(cx, style);
+
+        drawhelpers::draw_pos_indicator(cx, PenState::Up, self.start, zoom);
+        drawhelpers::draw_pos_indicator(cx, PenState::Down, self.current, zoom);
+        cx.restore().unwrap();
+    }
+}
+
+impl RegularPolygonBuilder {
+    /// The min number of sides
+    pub const SIDES_MIN: u32 = 3;
+    /// The max number of sides
+    pub const SIDES_MAX: u32 = 64;
+    /// The default number of sides
+    pub const SIDES_DEFAULT: u32 = 6;
+
+    /// Starts building with the given number of sides
+    pub fn start_w_sides(element: Element, n_sides: u32) -> Self {
+        Self {
+            n_sides,
+            ..Self::start(element)
+        }
+    }
+
+    /// The current state as polygon
+    pub fn state_as_polygon(&self) -> Polygon {
+        Polygon::new_regular(
+            self.start,
+            self.current,
+            self.n_sides.clamp(Self::SIDES_MIN, Self::SIDES_MAX),
+        )
+    }
+}
@@ -0,0 +1,99 @@
+use p2d::bounding_volume::{BoundingVolume, AABB};
+use piet::RenderContext;
+
+use crate::penhelpers::{PenEvent, PenState};
+use crate::penpath::Element;
+use crate::shapes::Polygon;
+use crate::style::{drawhelpers, Composer};
+use crate::{Shape, Style};
+
+use super::shapebuilderbehaviour::{BuilderProgress, ShapeBuilderCreator};
+use super::{ConstraintRatio, Constraints, ShapeBuilderBehaviour};
+
+/// star builder. The start is the center, the current position the first outer vertex
+#[derive(Debug, Clone)]
+pub struct StarBuilder {
+    /// the start position
+    pub start: na::Vector2<f64>,
+    /// the current position
+    pub current: na::Vector2<f64>,
+    /// the number of points
+    pub n_points: u32,
+}
+
+impl ShapeBuilderCreator for StarBuilder {
+    fn start(element: Element) -> Self {
+        Self {
+            start: element.pos,
+            current: element.pos,
+            n_points: Self::POINTS_DEFAULT,
+        }
+    }
+}
+
+impl ShapeBuilderBehaviour for StarBuilder {
+    fn handle_event(&mut self, event: PenEvent, mut constraints: Constraints) -> BuilderProgress {
+        // allows aligning the first vertex horizontally or vertically to the center
+        constraints.ratios.insert(ConstraintRatio::Horizontal);
+        constraints.ratios.insert(ConstraintRatio::Vertical);
+
+        match event {
+            PenEvent::Down { element, .. } => {
+                self.current = constraints.constrain(element.pos - self.start) + self.start;
+            }
+            PenEvent::Up { .. } => {
+                return BuilderProgress::Finished(vec![Shape::Polygon(self.state_as_polygon())]);
+            }
+            _ => {}
+        }
+
+        BuilderProgress::InProgress
+    }
+
+    fn bounds(&self, style: &Style, zoom: f64) -> Option<AABB> {
+        Some(
+            self.state_as_polygon()
+                .composed_bounds(style)
+                .loosened(drawhelpers::POS_INDICATOR_RADIUS / zoom),
+        )
+    }
+
+    fn draw_styled(&self, cx: &mut piet_cairo::CairoRenderContext, style: &Style, zoom: f64) {
+        cx.save().unwrap();
+        let polygon = self.state_as_polygon();
+        polygon.draw_composed(cx, style);
+
+        drawhelpers::draw_pos_indicator(cx, PenState::Up, self.start, zoom);
+        drawhelpers::draw_pos_indicator(cx, PenState::Down, self.current, zoom);
+        cx.restore().unwrap();
+    }
+}
+
+impl StarBuilder {
+    /// The min number of points
+    pub const POINTS_MIN: u32 = 3;
+    /// The max number of points
+    pub const POINTS_MAX: u32 = 64;
+    /// The default number of points
+    pub const POINTS_DEFAULT: u32 = 5;
+    /// The ratio of the inner radius to the outer radius
+    pub const INNER_RADIUS_RATIO: f64 = 0.45;
+
+    /// Starts building with the given number of points
+    pub fn start_w_points(element: Element, n_points: u32) -> Self {
+        Self {
+            n_points,
+            ..Self::start(element)
+        }
+    }
+
+    /// The current state as polygon
+    pub fn state_as_polygon(&self) -> Polygon {
+        Polygon::new_star(
+            self.start,
+            self.current,
+            self.n_points.clamp(Self::POINTS_MIN, Self::POINTS_MAX),
+            Self::INNER_RADIUS_RATIO,
+        )
+    }
+}
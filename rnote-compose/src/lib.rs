@@ -16,6 +16,8 @@ pub mod penhelpers;
 pub mod penpath;
 /// module for shapes
 pub mod shapes;
+/// module for stabilizing pen input
+pub mod stabilizer;
 /// module for styles, that can be applied onto shapes
 pub mod style;
 /// module for transformation
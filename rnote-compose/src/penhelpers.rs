@@ -34,6 +34,13 @@ pub enum PenEvent {
         /// pressed shortcut keys during the keyboard key event
         shortcut_keys: Vec<ShortcutKey>,
     },
+    /// A scroll event, e.g. from the mouse wheel. Pens receive it only while a shortcut key is held which is mapped to adjusting the pen with scrolling
+    Scroll {
+        /// the scroll delta. Negative y values are scrolling up
+        delta: na::Vector2<f64>,
+        /// pressed shortcut keys during the scroll event
+        shortcut_keys: Vec<ShortcutKey>,
+    },
     /// event when the pen vanishes unexpected. should reset all pending actions and state
     Cancel,
 }
@@ -1,4 +1,5 @@
 mod element;
+mod recognition;
 mod segment;
 
 // Re exports
@@ -11,7 +12,7 @@ use std::ops::{Deref, DerefMut};
 use p2d::bounding_volume::{BoundingVolume, AABB};
 use serde::{Deserialize, Serialize};
 
-use crate::shapes::ShapeBehaviour;
+use crate::shapes::{CubicBezier, ShapeBehaviour};
 use crate::transform::TransformBehaviour;
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -66,6 +67,9 @@ impl TransformBehaviour for PenPath {
 }
 
 impl PenPath {
+    /// The maximum deviation of the pressure of elements which are merged away when compacting or simplifying
+    pub const PRESSURE_EPSILON: f64 = 0.05;
+
     /// A new pen path with a first dot segment
     pub fn new_w_dot(element: Element) -> Self {
         Self::new_w_segment(Segment::Dot { element })
@@ -79,6 +83,208 @@ impl PenPath {
         Self(segment_vec)
     }
 
+    /// Compacts the path by merging consecutive flat segments into single lines. Each merged away element needs to be within `epsilon` distance to the running line from the start of the merged segments to the new end,
+    /// and its pressure may deviate at most by `Self::PRESSURE_EPSILON` from the interpolated pressure.
+    /// Raw stylus input produces many nearly collinear elements, so this reduces the size of the path considerably.
+    pub fn compact(&mut self, epsilon: f64) {
+        // the start and the end of the current run of flat segments
+        let mut run: Option<(Element, Element)> = None;
+        let mut compacted = VecDeque::with_capacity(self.0.len());
+
+        for segment in self.0.drain(..) {
+            if !segment.is_flat(epsilon) {
+                if let Some((start, end)) = run.take() {
+                    compacted.push_back(Segment::Line { start, end });
+                }
+                compacted.push_back(segment);
+                continue;
+            }
+
+            run = match run.take() {
+                None => Some((segment.start(), segment.end())),
+                Some((start, end)) => {
+                    // Only the element that would be merged away with this step is checked, to stay linear in the number of segments
+                    if Self::can_merge(start, end, segment.end(), epsilon) {
+                        Some((start, segment.end()))
+                    } else {
+                        compacted.push_back(Segment::Line { start, end });
+                        Some((segment.start(), segment.end()))
+                    }
+                }
+            };
+        }
+
+        if let Some((start, end)) = run {
+            compacted.push_back(Segment::Line { start, end });
+        }
+
+        self.0 = compacted;
+    }
+
+    /// Wether the element can be merged away from the line between the start and the end
+    fn can_merge(start: Element, element: Element, end: Element, epsilon: f64) -> bool {
+        segment::distance_to_line(element.pos, start.pos, end.pos) <= epsilon
+            && (element.pressure - Self::interpolated_pressure(start, end, element.pos)).abs()
+                <= Self::PRESSURE_EPSILON
+    }
+
+    /// The pressure at the position, linearly interpolated between the start and the end by its projection onto the line between them
+    fn interpolated_pressure(start: Element, end: Element, pos: nalgebra::Vector2<f64>) -> f64 {
+        let direction = end.pos - start.pos;
+        let len_squared = direction.norm_squared();
+        let t = if len_squared > 0.0 {
+            ((pos - start.pos).dot(&direction) / len_squared).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        start.pressure + (end.pressure - start.pressure) * t
+    }
+
+    /// Simplifies the path with the Ramer-Douglas-Peucker algorithm, only keeping the elements which deviate more than `epsilon` from the simplified polyline,
+    /// or whose pressure deviates more than `Self::PRESSURE_EPSILON` from the pressure interpolated along it.
+    /// The kept elements are then fitted with catmull-rom splines, so that the simplified path stays smooth.
+    pub fn simplify(&mut self, epsilon: f64) {
+        let mut elements = self
+            .0
+            .front()
+            .map(|first| first.start())
+            .into_iter()
+            .chain(self.0.iter().map(|segment| segment.end()))
+            .collect::<Vec<Element>>();
+        // A leading dot has the same start and end, and would result in a zero length segment
+        elements.dedup_by(|next, prev| next.pos == prev.pos);
+
+        if elements.len() <= 2 {
+            return;
+        }
+
+        let last = elements.len() - 1;
+        let mut keep = vec![false; elements.len()];
+        keep[0] = true;
+        keep[last] = true;
+
+        // The deviation of the element from the line between the first and the last element, relative to the allowed deviation.
+        // Elements with a deviation above 1.0 need to be kept
+        let relative_deviation = |i: usize, first: usize, last: usize| {
+            let dist =
+                segment::distance_to_line(elements[i].pos, elements[first].pos, elements[last].pos);
+            let pressure_dev = (elements[i].pressure
+                - Self::interpolated_pressure(elements[first], elements[last], elements[i].pos))
+            .abs();
+
+            (dist / epsilon.max(f64::EPSILON)).max(pressure_dev / Self::PRESSURE_EPSILON)
+        };
+
+        // Iterative instead of recursive, because paths can have a lot of elements
+        let mut ranges = vec![(0, last)];
+        while let Some((first, last)) = ranges.pop() {
+            let (max_index, max_deviation) = (first + 1..last)
+                .map(|i| (i, relative_deviation(i, first, last)))
+                .fold(
+                    (first, 0.0),
+                    |max, next| if next.1 > max.1 { next } else { max },
+                );
+
+            if max_deviation > 1.0 {
+                keep[max_index] = true;
+                ranges.push((first, max_index));
+                ranges.push((max_index, last));
+            }
+        }
+
+        let kept = elements
+            .into_iter()
+            .zip(keep)
+            .filter_map(|(element, keep)| if keep { Some(element) } else { None })
+            .collect::<Vec<Element>>();
+
+        self.0 = (0..kept.len() - 1)
+            .map(|i| {
+                let first = kept[i.saturating_sub(1)];
+                let second = kept[i];
+                let third = kept[i + 1];
+                let forth = kept[(i + 2).min(kept.len() - 1)];
+
+                match CubicBezier::new_w_catmull_rom(first.pos, second.pos, third.pos, forth.pos) {
+                    Some(cubbez) => Segment::CubBez {
+                        start: Element {
+                            pos: cubbez.start,
+                            ..second
+                        },
+                        cp1: cubbez.cp1,
+                        cp2: cubbez.cp2,
+                        end: Element {
+                            pos: cubbez.end,
+                            ..third
+                        },
+                    },
+                    None => Segment::Line {
+                        start: second,
+                        end: third,
+                    },
+                }
+            })
+            .collect();
+    }
+
+    /// Straightens the path into a single line from its start to its end,
+    /// if no element deviates more from that line than the given ratio of its length. Returns true if the path was straightened.
+    pub fn straighten(&mut self, max_deviation_ratio: f64) -> bool {
+        let (start, end) = match (self.0.front(), self.0.back()) {
+            (Some(first), Some(last)) => (first.start(), last.end()),
+            _ => return false,
+        };
+        let length = (end.pos - start.pos).norm();
+
+        if length == 0.0
+            || self.0.iter().any(|segment| {
+                segment::distance_to_line(segment.end().pos, start.pos, end.pos)
+                    > length * max_deviation_ratio
+            })
+        {
+            return false;
+        }
+
+        self.0 = VecDeque::from(vec![Segment::Line { start, end }]);
+        true
+    }
+
+    /// Cuts away the parts of the path inside the given bounds. Returns the remaining parts as separate paths, in order.
+    /// The pressures at the cuts are interpolated, so the remaining parts keep their shape and width.
+    pub fn split_outside_bounds(&self, bounds: AABB) -> Vec<PenPath> {
+        let mut paths = vec![];
+        let mut current = PenPath::default();
+
+        for segment in self.0.iter() {
+            let ranges = segment.ranges_outside_bounds(bounds);
+
+            // The segment is entirely inside the bounds
+            if ranges.is_empty() && !current.is_empty() {
+                paths.push(std::mem::take(&mut current));
+            }
+
+            for (t0, t1) in ranges {
+                // The range does not continue the current path from the previous segment
+                if t0 > 0.0 && !current.is_empty() {
+                    paths.push(std::mem::take(&mut current));
+                }
+                if t1 > t0 || matches!(segment, Segment::Dot { .. }) {
+                    current.push_back(segment.sub_segment(t0, t1));
+                }
+                if t1 < 1.0 && !current.is_empty() {
+                    paths.push(std::mem::take(&mut current));
+                }
+            }
+        }
+
+        if !current.is_empty() {
+            paths.push(current);
+        }
+
+        paths
+    }
+
     /// extracts the elements from the path. the path shape will be lost, as only the actual input elements are returned.
     pub fn into_elements(self) -> Vec<Element> {
         self.0
@@ -103,3 +309,135 @@ impl std::iter::FromIterator<Segment> for PenPath {
         Self(VecDeque::from_iter(iter))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A path of lines through the given positions and pressures
+    fn path_through(elements: &[(f64, f64, f64)]) -> PenPath {
+        elements
+            .windows(2)
+            .map(|window| Segment::Line {
+                start: Element::new(na::vector![window[0].0, window[0].1], window[0].2),
+                end: Element::new(na::vector![window[1].0, window[1].1], window[1].2),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compact_flat_run() {
+        let mut path = path_through(
+            &(0..=100)
+                .map(|i| (f64::from(i), 0.0, 0.5 + f64::from(i) * 0.001))
+                .collect::<Vec<_>>(),
+        );
+        path.compact(0.5);
+
+        assert_eq!(path.len(), 1);
+        assert_eq!(path[0].start().pos, na::vector![0.0, 0.0]);
+        assert_eq!(path[0].end().pos, na::vector![100.0, 0.0]);
+    }
+
+    #[test]
+    fn compact_keeps_corners_and_pressure() {
+        let mut path = path_through(&[
+            (0.0, 0.0, 0.5),
+            (10.0, 0.0, 0.5),
+            (20.0, 0.0, 0.5),
+            (20.0, 10.0, 0.5),
+            (20.0, 20.0, 0.5),
+            (20.0, 30.0, 1.0),
+            (20.0, 40.0, 0.5),
+        ]);
+        path.compact(0.5);
+
+        let ends = path
+            .iter()
+            .map(|segment| segment.end().pos)
+            .collect::<Vec<_>>();
+        assert!(ends.contains(&na::vector![20.0, 0.0]));
+        assert!(ends.contains(&na::vector![20.0, 30.0]));
+        assert!(!ends.contains(&na::vector![10.0, 0.0]));
+        assert_eq!(path.back().unwrap().end().pos, na::vector![20.0, 40.0]);
+    }
+
+    #[test]
+    fn simplify_straight_line() {
+        let mut path = path_through(
+            &(0..=10)
+                .map(|i| (f64::from(i) * 10.0, 0.0, 0.5))
+                .collect::<Vec<_>>(),
+        );
+        path.simplify(1.0);
+
+        assert_eq!(path.len(), 1);
+        assert_eq!(path[0].start().pos, na::vector![0.0, 0.0]);
+        assert_eq!(path[0].end().pos, na::vector![100.0, 0.0]);
+    }
+
+    #[test]
+    fn simplify_keeps_pressure_variation() {
+        let mut path = path_through(
+            &(0..=10)
+                .map(|i| {
+                    let pressure = if i == 5 { 1.0 } else { 0.2 };
+                    (f64::from(i) * 10.0, 0.0, pressure)
+                })
+                .collect::<Vec<_>>(),
+        );
+        path.simplify(1.0);
+
+        assert!(path.len() > 1);
+        assert!(path
+            .iter()
+            .any(|segment| segment.end().pos == na::vector![50.0, 0.0]
+                && segment.end().pressure == 1.0));
+    }
+
+    #[test]
+    fn simplify_leading_dot() {
+        let mut path = PenPath::new_w_dot(Element::new(na::vector![0.0, 0.0], 0.5));
+        path.extend(path_through(&[(0.0, 0.0, 0.5), (10.0, 10.0, 0.5), (20.0, 0.0, 0.5)]).0);
+        path.simplify(1.0);
+
+        assert_eq!(path.len(), 2);
+        assert!(path
+            .iter()
+            .all(|segment| segment.start().pos != segment.end().pos));
+    }
+
+    #[test]
+    fn split_line_through_middle() {
+        let path = path_through(&[(0.0, 0.0, 0.0), (50.0, 0.0, 0.5), (100.0, 0.0, 1.0)]);
+        let eraser_bounds = AABB::new(na::point![40.0, -10.0], na::point![60.0, 10.0]);
+
+        let paths = path.split_outside_bounds(eraser_bounds);
+        assert_eq!(paths.len(), 2);
+
+        let first_end = paths[0].back().unwrap().end();
+        assert!((first_end.pos - na::vector![40.0, 0.0]).norm() < 1e-3);
+        assert!((first_end.pressure - 0.4).abs() < 1e-3);
+
+        let second_start = paths[1].front().unwrap().start();
+        assert!((second_start.pos - na::vector![60.0, 0.0]).norm() < 1e-3);
+        assert!((second_start.pressure - 0.6).abs() < 1e-3);
+        assert_eq!(paths[1].back().unwrap().end().pos, na::vector![100.0, 0.0]);
+    }
+
+    #[test]
+    fn split_path_inside_bounds() {
+        let mut path = PenPath::new_w_dot(Element::new(na::vector![10.0, 10.0], 0.5));
+        path.extend(path_through(&[(10.0, 10.0, 0.5), (20.0, 20.0, 0.5)]).0);
+
+        assert!(path
+            .split_outside_bounds(AABB::new(na::point![0.0, 0.0], na::point![30.0, 30.0]))
+            .is_empty());
+
+        // Entirely outside, so the path is kept as a whole
+        let paths =
+            path.split_outside_bounds(AABB::new(na::point![50.0, 50.0], na::point![60.0, 60.0]));
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].len(), 2);
+    }
+}
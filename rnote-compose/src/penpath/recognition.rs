@@ -0,0 +1,284 @@
+use p2d::bounding_volume::AABB;
+
+use super::{segment, PenPath};
+use crate::helpers::Vector2Helpers;
+use crate::shapes::{CubicBezier, Ellipse, Line, Polygon, Rectangle};
+use crate::{Shape, Transform};
+
+impl PenPath {
+    /// The max distance between the start and the end, as ratio of the bounds diagonal, for the path to be considered closed
+    const RECOGNITION_CLOSED_RATIO: f64 = 0.2;
+    /// The max ratio the radii of a recognized ellipse may differ to be snapped to a circle
+    const RECOGNITION_CIRCLE_RATIO: f64 = 0.15;
+    /// The max deviation of the corners of a recognized rectangle from a right angle
+    const RECOGNITION_RIGHT_ANGLE_TOLERANCE: f64 = std::f64::consts::PI / 9.0;
+    /// The max deviation of a recognized rectangle from being axis aligned, for it to be snapped to the axes
+    const RECOGNITION_AXIS_SNAP_ANGLE: f64 = std::f64::consts::PI / 36.0;
+
+    /// Recognizes a line, circle, ellipse, rectangle or triangle which the path closely matches.
+    /// The elements of the path may deviate at most by the given ratio of the path bounds diagonal from the recognized shape.
+    pub fn recognize_shape(&self, max_deviation_ratio: f64) -> Option<Shape> {
+        let points = self.element_positions();
+
+        if points.len() < 3 {
+            return None;
+        }
+
+        let bounds = Self::points_bounds(&points);
+        let diagonal = bounds.extents().norm();
+        if diagonal == 0.0 {
+            return None;
+        }
+        let epsilon = diagonal * max_deviation_ratio;
+
+        let start = points[0];
+        let end = points[points.len() - 1];
+
+        if (end - start).norm() > diagonal * Self::RECOGNITION_CLOSED_RATIO {
+            // An open path can only be a line
+            return if points
+                .iter()
+                .all(|point| segment::distance_to_line(*point, start, end) <= epsilon)
+            {
+                Some(Shape::Line(Line { start, end }))
+            } else {
+                None
+            };
+        }
+
+        Self::recognize_ellipse(&points, bounds, max_deviation_ratio)
+            .or_else(|| Self::recognize_polygon(&points, epsilon))
+    }
+
+    /// Fits a single cubic bezier curve through the path, with its start and end at the start and end of the path.
+    /// The elements of the path may deviate at most by the given ratio of the path bounds diagonal from the curve.
+    pub fn fit_cubic_bezier(&self, max_deviation_ratio: f64) -> Option<CubicBezier> {
+        let points = self.element_positions();
+
+        if points.len() < 3 {
+            return None;
+        }
+
+        let diagonal = Self::points_bounds(&points).extents().norm();
+        if diagonal == 0.0 {
+            return None;
+        }
+
+        // Parametrize the points by their distance along the path
+        let mut lengths = Vec::with_capacity(points.len());
+        let mut length = 0.0;
+        lengths.push(0.0);
+        for window in points.windows(2) {
+            length += (window[1] - window[0]).norm();
+            lengths.push(length);
+        }
+        if length == 0.0 {
+            return None;
+        }
+        let params = lengths
+            .into_iter()
+            .map(|l| l / length)
+            .collect::<Vec<f64>>();
+
+        let start = points[0];
+        let end = points[points.len() - 1];
+        let bernstein = |t: f64| {
+            let mt = 1.0 - t;
+            [mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t]
+        };
+
+        // Least squares for the two control points, with the start and end fixed
+        let (mut c11, mut c12, mut c22) = (0.0, 0.0, 0.0);
+        let (mut x1, mut x2) = (na::Vector2::<f64>::zeros(), na::Vector2::<f64>::zeros());
+        for (point, &t) in points.iter().zip(params.iter()) {
+            let b = bernstein(t);
+            let residual = point - start * b[0] - end * b[3];
+
+            c11 += b[1] * b[1];
+            c12 += b[1] * b[2];
+            c22 += b[2] * b[2];
+            x1 += residual * b[1];
+            x2 += residual * b[2];
+        }
+        let det = c11 * c22 - c12 * c12;
+        if det.abs() < 1e-12 {
+            return None;
+        }
+
+        let cubbez = CubicBezier {
+            start,
+            cp1: (x1 * c22 - x2 * c12) / det,
+            cp2: (x2 * c11 - x1 * c12) / det,
+            end,
+        };
+
+        let epsilon = diagonal * max_deviation_ratio;
+        let fits = points.iter().zip(params.iter()).all(|(point, &t)| {
+            let b = bernstein(t);
+            let on_curve =
+                cubbez.start * b[0] + cubbez.cp1 * b[1] + cubbez.cp2 * b[2] + cubbez.end * b[3];
+
+            (on_curve - point).norm() <= epsilon
+        });
+
+        if fits {
+            Some(cubbez)
+        } else {
+            None
+        }
+    }
+
+    /// The positions of the elements of the path, without the control points
+    fn element_positions(&self) -> Vec<na::Vector2<f64>> {
+        self.0
+            .front()
+            .map(|first| first.start().pos)
+            .into_iter()
+            .chain(self.0.iter().map(|segment| segment.end().pos))
+            .collect()
+    }
+
+    fn points_bounds(points: &[na::Vector2<f64>]) -> AABB {
+        points
+            .iter()
+            .fold(AABB::new_invalid(), |mut bounds, point| {
+                bounds.take_point(na::Point2::from(*point));
+                bounds
+            })
+    }
+
+    fn recognize_ellipse(
+        points: &[na::Vector2<f64>],
+        bounds: AABB,
+        max_deviation_ratio: f64,
+    ) -> Option<Shape> {
+        let center = bounds.center().coords;
+        let mut radii = bounds.half_extents();
+        if radii.x == 0.0 || radii.y == 0.0 {
+            return None;
+        }
+
+        // Points on the ellipse have a normalized distance of 1 to the center
+        if points.iter().any(|point| {
+            ((point - center).component_div(&radii).norm() - 1.0).abs() > max_deviation_ratio * 2.0
+        }) {
+            return None;
+        }
+
+        if (radii.x - radii.y).abs() <= radii.x.max(radii.y) * Self::RECOGNITION_CIRCLE_RATIO {
+            let radius = (radii.x + radii.y) * 0.5;
+            radii = na::vector![radius, radius];
+        }
+
+        Some(Shape::Ellipse(Ellipse {
+            radii,
+            transform: Transform::new_w_isometry(na::Isometry2::new(center, 0.0)),
+        }))
+    }
+
+    fn recognize_polygon(points: &[na::Vector2<f64>], epsilon: f64) -> Option<Shape> {
+        let vertices = Self::closed_polyline_corners(points, epsilon);
+
+        match vertices.len() {
+            3 => Some(Shape::Polygon(Polygon::new(vertices))),
+            4 => {
+                let right_angled = (0..4).all(|i| {
+                    let prev = vertices[(i + 3) % 4] - vertices[i];
+                    let next = vertices[(i + 1) % 4] - vertices[i];
+
+                    (prev.angle(&next) - std::f64::consts::FRAC_PI_2).abs()
+                        <= Self::RECOGNITION_RIGHT_ANGLE_TOLERANCE
+                });
+                if !right_angled {
+                    return None;
+                }
+
+                let center = vertices.iter().sum::<na::Vector2<f64>>() / 4.0;
+                let width =
+                    ((vertices[1] - vertices[0]).norm() + (vertices[3] - vertices[2]).norm()) * 0.5;
+                let height =
+                    ((vertices[2] - vertices[1]).norm() + (vertices[0] - vertices[3]).norm()) * 0.5;
+                let mut angle = na::Vector2::x().angle_ahead(&(vertices[1] - vertices[0]));
+
+                // Snap to the axes if nearly aligned
+                let quarter_turns = (angle / std::f64::consts::FRAC_PI_2).round();
+                if (angle - quarter_turns * std::f64::consts::FRAC_PI_2).abs()
+                    <= Self::RECOGNITION_AXIS_SNAP_ANGLE
+                {
+                    angle = quarter_turns * std::f64::consts::FRAC_PI_2;
+                }
+
+                Some(Shape::Rectangle(Rectangle {
+                    cuboid: p2d::shape::Cuboid::new(na::vector![width * 0.5, height * 0.5]),
+                    transform: Transform::new_w_isometry(na::Isometry2::new(center, angle)),
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    /// The corners of the closed polyline through the points, found with the Ramer-Douglas-Peucker algorithm
+    fn closed_polyline_corners(points: &[na::Vector2<f64>], epsilon: f64) -> Vec<na::Vector2<f64>> {
+        // Split the loop at the point farthest away from the start
+        let split = (0..points.len())
+            .max_by(|&a, &b| {
+                (points[a] - points[0])
+                    .norm()
+                    .partial_cmp(&(points[b] - points[0]).norm())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .unwrap_or(0);
+
+        let mut keep = vec![false; points.len()];
+        keep[0] = true;
+        keep[split] = true;
+
+        let mut ranges = vec![(0, split), (split, points.len() - 1)];
+        while let Some((first, last)) = ranges.pop() {
+            let (max_index, max_dist) = (first + 1..last)
+                .map(|i| {
+                    (
+                        i,
+                        segment::distance_to_line(points[i], points[first], points[last]),
+                    )
+                })
+                .fold(
+                    (first, 0.0),
+                    |max, next| if next.1 > max.1 { next } else { max },
+                );
+
+            if max_dist > epsilon {
+                keep[max_index] = true;
+                ranges.push((first, max_index));
+                ranges.push((max_index, last));
+            }
+        }
+
+        let mut corners = points
+            .iter()
+            .zip(keep)
+            .filter_map(|(point, keep)| if keep { Some(*point) } else { None })
+            .collect::<Vec<na::Vector2<f64>>>();
+
+        // The start of the loop is not necessarily a corner, so remove corners which lie on the line between their neighbours
+        while corners.len() > 2 {
+            let n = corners.len();
+            let flat = (0..n).find(|&i| {
+                segment::distance_to_line(
+                    corners[i],
+                    corners[(i + n - 1) % n],
+                    corners[(i + 1) % n],
+                ) <= epsilon
+            });
+
+            match flat {
+                Some(i) => {
+                    corners.remove(i);
+                }
+                None => break,
+            }
+        }
+
+        corners
+    }
+}
@@ -1,4 +1,5 @@
 use crate::helpers::{AABBHelpers, KurboHelpers};
+use crate::shapes::cubbez::cubbez_calc;
 use crate::shapes::{CubicBezier, Line, QuadraticBezier, ShapeBehaviour};
 use crate::transform::TransformBehaviour;
 
@@ -267,6 +268,266 @@ impl Segment {
             Segment::CubBez { end, .. } => *end,
         }
     }
+
+    /// Moves the start of the segment to the given position.
+    /// The first control point of cubic curves is moved along, so that the curve keeps its tangent at the start.
+    pub fn set_start_pos(&mut self, pos: na::Vector2<f64>) {
+        match self {
+            Segment::Dot { element } => element.pos = pos,
+            Segment::Line { start, .. } => start.pos = pos,
+            Segment::QuadBez { start, .. } => start.pos = pos,
+            Segment::CubBez { start, cp1, .. } => {
+                *cp1 += pos - start.pos;
+                start.pos = pos;
+            }
+        }
+    }
+
+    /// Moves the end of the segment to the given position.
+    /// The second control point of cubic curves is moved along, so that the curve keeps its tangent at the end.
+    pub fn set_end_pos(&mut self, pos: na::Vector2<f64>) {
+        match self {
+            Segment::Dot { element } => element.pos = pos,
+            Segment::Line { end, .. } => end.pos = pos,
+            Segment::QuadBez { end, .. } => end.pos = pos,
+            Segment::CubBez { cp2, end, .. } => {
+                *cp2 += pos - end.pos;
+                end.pos = pos;
+            }
+        }
+    }
+
+    /// The parameter t, ranging [0.0, 1.0], of the position on the segment which is nearest to the given position, and its distance.
+    /// Approximated by sampling the segment.
+    pub fn nearest_t(&self, pos: na::Vector2<f64>) -> (f64, f64) {
+        const N_SAMPLES: i32 = 32;
+
+        (0..=N_SAMPLES)
+            .map(|i| {
+                let t = f64::from(i) / f64::from(N_SAMPLES);
+                (t, (self.pos_at(t) - pos).magnitude())
+            })
+            .fold((0.0, f64::MAX), |nearest, current| {
+                if current.1 < nearest.1 {
+                    current
+                } else {
+                    nearest
+                }
+            })
+    }
+
+    /// Wether the segment is flat, meaning all its control points are within the given distance to the straight line from start to end.
+    /// Dots are never flat.
+    pub fn is_flat(&self, epsilon: f64) -> bool {
+        match self {
+            Segment::Dot { .. } => false,
+            Segment::Line { .. } => true,
+            Segment::QuadBez { start, cp, end } => {
+                distance_to_line(*cp, start.pos, end.pos) <= epsilon
+            }
+            Segment::CubBez {
+                start,
+                cp1,
+                cp2,
+                end,
+            } => {
+                distance_to_line(*cp1, start.pos, end.pos) <= epsilon
+                    && distance_to_line(*cp2, start.pos, end.pos) <= epsilon
+            }
+        }
+    }
+
+    /// Approximates the segment with lines. Curves are split into the given number of lines, dots have no lines.
+    pub fn approx_with_lines(&self, n_splits: i32) -> Vec<Line> {
+        match self {
+            Segment::Dot { .. } => vec![],
+            Segment::Line { start, end } => vec![Line {
+                start: start.pos,
+                end: end.pos,
+            }],
+            Segment::QuadBez { start, cp, end } => QuadraticBezier {
+                start: start.pos,
+                cp: *cp,
+                end: end.pos,
+            }
+            .approx_with_lines(n_splits),
+            Segment::CubBez {
+                start,
+                cp1,
+                cp2,
+                end,
+            } => CubicBezier {
+                start: start.pos,
+                cp1: *cp1,
+                cp2: *cp2,
+                end: end.pos,
+            }
+            .approx_with_lines(n_splits),
+        }
+    }
+
+    /// The position on the segment at t, ranging [0.0, 1.0]
+    pub fn pos_at(&self, t: f64) -> na::Vector2<f64> {
+        match self {
+            Segment::Dot { element } => element.pos,
+            Segment::Line { start, end } => start.pos.lerp(&end.pos, t),
+            Segment::QuadBez { start, cp, end } => {
+                let first = start.pos.lerp(cp, t);
+                let second = cp.lerp(&end.pos, t);
+
+                first.lerp(&second, t)
+            }
+            Segment::CubBez {
+                start,
+                cp1,
+                cp2,
+                end,
+            } => cubbez_calc(start.pos, *cp1, *cp2, end.pos, t),
+        }
+    }
+
+    /// The part of the segment between t0 and t1, ranging [0.0, 1.0]. The pressure of the new start and end is interpolated.
+    pub fn sub_segment(&self, t0: f64, t1: f64) -> Segment {
+        let element_at = |t: f64| {
+            let start = self.start();
+            let end = self.end();
+
+            Element::new(
+                self.pos_at(t),
+                start.pressure + (end.pressure - start.pressure) * t,
+            )
+        };
+
+        match self {
+            Segment::Dot { .. } => self.clone(),
+            Segment::Line { .. } => Segment::Line {
+                start: element_at(t0),
+                end: element_at(t1),
+            },
+            Segment::QuadBez { start, cp, end } => {
+                let quadbez = QuadraticBezier {
+                    start: start.pos,
+                    cp: *cp,
+                    end: end.pos,
+                };
+                // Split off the end first, then the start from the remaining part with its parameter rescaled
+                let quadbez = if t1 < 1.0 {
+                    quadbez.split(t1).0
+                } else {
+                    quadbez
+                };
+                let quadbez = if t0 > 0.0 && t1 > 0.0 {
+                    quadbez.split(t0 / t1).1
+                } else {
+                    quadbez
+                };
+
+                Segment::QuadBez {
+                    start: element_at(t0),
+                    cp: quadbez.cp,
+                    end: element_at(t1),
+                }
+            }
+            Segment::CubBez {
+                start,
+                cp1,
+                cp2,
+                end,
+            } => {
+                let cubbez = CubicBezier {
+                    start: start.pos,
+                    cp1: *cp1,
+                    cp2: *cp2,
+                    end: end.pos,
+                };
+                // Split off the end first, then the start from the remaining part with its parameter rescaled
+                let cubbez = if t1 < 1.0 { cubbez.split(t1).0 } else { cubbez };
+                let cubbez = if t0 > 0.0 && t1 > 0.0 {
+                    cubbez.split(t0 / t1).1
+                } else {
+                    cubbez
+                };
+
+                Segment::CubBez {
+                    start: element_at(t0),
+                    cp1: cubbez.cp1,
+                    cp2: cubbez.cp2,
+                    end: element_at(t1),
+                }
+            }
+        }
+    }
+
+    /// The parameter ranges of the segment which are outside of the given bounds, in order.
+    /// The boundaries are approximated by sampling the segment and refining the crossings with bisection.
+    pub fn ranges_outside_bounds(&self, bounds: AABB) -> Vec<(f64, f64)> {
+        const SAMPLES_PER_HITBOX: i32 = 4;
+        const BISECTION_ITERATIONS: usize = 12;
+
+        let outside = |t: f64| !bounds.contains_local_point(&na::Point2::from(self.pos_at(t)));
+
+        if let Segment::Dot { element } = self {
+            return if bounds.contains_local_point(&na::Point2::from(element.pos)) {
+                vec![]
+            } else {
+                vec![(0.0, 1.0)]
+            };
+        }
+
+        let n_samples = self.hitboxes().len() as i32 * SAMPLES_PER_HITBOX;
+        let mut ranges = vec![];
+        let mut range_start = if outside(0.0) { Some(0.0) } else { None };
+        let mut prev_t = 0.0;
+
+        for i in 1..=n_samples {
+            let t = f64::from(i) / f64::from(n_samples);
+            let t_outside = outside(t);
+
+            if t_outside != range_start.is_some() {
+                // Refine the crossing between the previous and the current sample
+                let (mut inner, mut outer) = if t_outside { (prev_t, t) } else { (t, prev_t) };
+                for _ in 0..BISECTION_ITERATIONS {
+                    let mid = (inner + outer) * 0.5;
+                    if outside(mid) {
+                        outer = mid;
+                    } else {
+                        inner = mid;
+                    }
+                }
+
+                match range_start.take() {
+                    Some(start) => ranges.push((start, outer)),
+                    None => range_start = Some(outer),
+                }
+            }
+
+            prev_t = t;
+        }
+
+        if let Some(start) = range_start {
+            ranges.push((start, 1.0));
+        }
+
+        ranges
+    }
+}
+
+/// The distance of a point to the line segment from start to end
+pub(crate) fn distance_to_line(
+    point: na::Vector2<f64>,
+    start: na::Vector2<f64>,
+    end: na::Vector2<f64>,
+) -> f64 {
+    let line = end - start;
+    let len_squared = line.norm_squared();
+
+    if len_squared == 0.0 {
+        return (point - start).norm();
+    }
+
+    let t = ((point - start).dot(&line) / len_squared).clamp(0.0, 1.0);
+
+    (point - (start + line * t)).norm()
 }
 
 /// Calculates the number hitbox elems for the given length capped with a maximum no of hitbox elements
@@ -282,3 +543,88 @@ fn hitbox_elems_for_segment_len(len: f64) -> i32 {
         MAX_ELEMS
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: f64 = 1e-3;
+
+    fn bounds(mins: (f64, f64), maxs: (f64, f64)) -> AABB {
+        AABB::new(na::point![mins.0, mins.1], na::point![maxs.0, maxs.1])
+    }
+
+    #[test]
+    fn line_ranges_outside_bounds() {
+        let line = Segment::Line {
+            start: Element::new(na::vector![0.0, 0.0], 0.0),
+            end: Element::new(na::vector![100.0, 0.0], 1.0),
+        };
+
+        let ranges = line.ranges_outside_bounds(bounds((40.0, -10.0), (60.0, 10.0)));
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges[0].0, 0.0);
+        assert!((ranges[0].1 - 0.4).abs() < TOLERANCE);
+        assert!((ranges[1].0 - 0.6).abs() < TOLERANCE);
+        assert_eq!(ranges[1].1, 1.0);
+
+        assert!(line
+            .ranges_outside_bounds(bounds((-10.0, -10.0), (110.0, 10.0)))
+            .is_empty());
+    }
+
+    #[test]
+    fn line_sub_segment_pressure() {
+        let line = Segment::Line {
+            start: Element::new(na::vector![0.0, 0.0], 0.0),
+            end: Element::new(na::vector![100.0, 0.0], 1.0),
+        };
+
+        let sub_segment = line.sub_segment(0.25, 0.75);
+        assert!((sub_segment.start().pos - na::vector![25.0, 0.0]).norm() < TOLERANCE);
+        assert!((sub_segment.start().pressure - 0.25).abs() < TOLERANCE);
+        assert!((sub_segment.end().pos - na::vector![75.0, 0.0]).norm() < TOLERANCE);
+        assert!((sub_segment.end().pressure - 0.75).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn cubbez_ranges_outside_bounds() {
+        let cubbez = Segment::CubBez {
+            start: Element::new(na::vector![0.0, 0.0], 0.5),
+            cp1: na::vector![0.0, 50.0],
+            cp2: na::vector![100.0, 50.0],
+            end: Element::new(na::vector![100.0, 0.0], 0.5),
+        };
+        let eraser_bounds = bounds((40.0, 0.0), (60.0, 100.0));
+
+        let ranges = cubbez.ranges_outside_bounds(eraser_bounds);
+        assert_eq!(ranges.len(), 2);
+        // The curve is symmetric
+        assert!((ranges[0].1 - (1.0 - ranges[1].0)).abs() < TOLERANCE);
+
+        for (t0, t1) in ranges {
+            let sub_segment = cubbez.sub_segment(t0, t1);
+            assert!((sub_segment.start().pos - cubbez.pos_at(t0)).norm() < TOLERANCE);
+            assert!((sub_segment.end().pos - cubbez.pos_at(t1)).norm() < TOLERANCE);
+            // The midpoint of the remaining part is on the original curve, outside of the bounds
+            let mid = sub_segment.pos_at(0.5);
+            assert!((mid - cubbez.pos_at((t0 + t1) * 0.5)).norm() < TOLERANCE);
+            assert!(!eraser_bounds.contains_local_point(&na::Point2::from(mid)));
+        }
+    }
+
+    #[test]
+    fn dot_ranges_outside_bounds() {
+        let dot = Segment::Dot {
+            element: Element::new(na::vector![10.0, 10.0], 0.5),
+        };
+
+        assert!(dot
+            .ranges_outside_bounds(bounds((0.0, 0.0), (20.0, 20.0)))
+            .is_empty());
+        assert_eq!(
+            dot.ranges_outside_bounds(bounds((30.0, 30.0), (40.0, 40.0))),
+            vec![(0.0, 1.0)]
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use p2d::bounding_volume::AABB;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::{AABBHelpers, Vector2Helpers};
+use crate::shapes::{Line, Polygon, ShapeBehaviour};
+use crate::transform::TransformBehaviour;
+
+/// The style of arrow heads
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    num_derive::FromPrimitive,
+    num_derive::ToPrimitive,
+)]
+#[serde(rename = "arrow_head_style")]
+pub enum ArrowHeadStyle {
+    /// An open head, made of two lines
+    #[serde(rename = "open")]
+    Open = 0,
+    /// A filled triangle head
+    #[serde(rename = "filled")]
+    Filled,
+}
+
+impl Default for ArrowHeadStyle {
+    fn default() -> Self {
+        Self::Open
+    }
+}
+
+impl TryFrom<u32> for ArrowHeadStyle {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        num_traits::FromPrimitive::from_u32(value).ok_or_else(|| {
+            anyhow::anyhow!(
+                "ArrowHeadStyle try_from::<u32>() for value {} failed",
+                value
+            )
+        })
+    }
+}
+
+/// The head of an arrow
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, rename = "arrow_head")]
+pub struct ArrowHead {
+    /// The head style
+    #[serde(rename = "style")]
+    pub style: ArrowHeadStyle,
+    /// The length of the head sides
+    #[serde(rename = "size")]
+    pub size: f64,
+}
+
+impl Default for ArrowHead {
+    fn default() -> Self {
+        Self {
+            style: ArrowHeadStyle::default(),
+            size: Self::SIZE_DEFAULT,
+        }
+    }
+}
+
+impl ArrowHead {
+    /// The min size
+    pub const SIZE_MIN: f64 = 2.0;
+    /// The max size
+    pub const SIZE_MAX: f64 = 200.0;
+    /// The default size
+    pub const SIZE_DEFAULT: f64 = 16.0;
+    /// The angle between the shaft and the head sides
+    pub const ANGLE: f64 = std::f64::consts::PI / 6.0;
+
+    /// The vertices of the head pointing at the tip from the given position, in the order left side end, tip, right side end.
+    /// None if the tip and the position are at the same spot, so that there is no direction.
+    pub fn vertices(
+        &self,
+        tip: na::Vector2<f64>,
+        from: na::Vector2<f64>,
+    ) -> Option<[na::Vector2<f64>; 3]> {
+        let direction = from - tip;
+        if direction.magnitude() == 0.0 {
+            return None;
+        }
+        let side = direction.normalize() * self.size;
+
+        Some([
+            tip + na::Rotation2::new(Self::ANGLE) * side,
+            tip,
+            tip + na::Rotation2::new(-Self::ANGLE) * side,
+        ])
+    }
+}
+
+/// An arrow, with a head at the end and optionally also at the start
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, rename = "arrow")]
+pub struct Arrow {
+    /// The arrow start
+    #[serde(rename = "start")]
+    pub start: na::Vector2<f64>,
+    /// The arrow end, where the head points to
+    #[serde(rename = "end")]
+    pub end: na::Vector2<f64>,
+    /// The head
+    #[serde(rename = "head")]
+    pub head: ArrowHead,
+    /// Wether the arrow has a head at the start as well
+    #[serde(rename = "double_headed")]
+    pub double_headed: bool,
+}
+
+impl TransformBehaviour for Arrow {
+    fn translate(&mut self, offset: nalgebra::Vector2<f64>) {
+        self.start += offset;
+        self.end += offset;
+    }
+
+    fn rotate(&mut self, angle: f64, center: nalgebra::Point2<f64>) {
+        let mut isometry = na::Isometry2::identity();
+        isometry.append_rotation_wrt_point_mut(&na::UnitComplex::new(angle), &center);
+
+        self.start = (isometry * na::Point2::from(self.start)).coords;
+        self.end = (isometry * na::Point2::from(self.end)).coords;
+    }
+
+    fn scale(&mut self, scale: nalgebra::Vector2<f64>) {
+        self.start = self.start.component_mul(&scale);
+        self.end = self.end.component_mul(&scale);
+    }
+}
+
+impl ShapeBehaviour for Arrow {
+    fn bounds(&self) -> AABB {
+        let mut bounds =
+            AABBHelpers::new_positive(na::Point2::from(self.start), na::Point2::from(self.end));
+
+        for vertex in self.heads().iter().flatten() {
+            bounds.take_point(na::Point2::from(*vertex));
+        }
+
+        bounds
+    }
+
+    fn hitboxes(&self) -> Vec<AABB> {
+        self.outline_lines()
+            .into_iter()
+            .flat_map(|line| line.hitboxes())
+            .collect()
+    }
+}
+
+impl Arrow {
+    /// The shaft
+    pub fn shaft(&self) -> Line {
+        Line {
+            start: self.start,
+            end: self.end,
+        }
+    }
+
+    /// The vertices of the heads, see `ArrowHead::vertices()`
+    pub fn heads(&self) -> Vec<[na::Vector2<f64>; 3]> {
+        let mut heads = vec![];
+
+        if let Some(end_head) = self.head.vertices(self.end, self.start) {
+            heads.push(end_head);
+        }
+        if self.double_headed {
+            if let Some(start_head) = self.head.vertices(self.start, self.end) {
+                heads.push(start_head);
+            }
+        }
+
+        heads
+    }
+
+    /// The lines of the head sides
+    pub fn head_lines(&self) -> Vec<Line> {
+        self.heads()
+            .into_iter()
+            .flat_map(|[left, tip, right]| {
+                [
+                    Line {
+                        start: tip,
+                        end: left,
+                    },
+                    Line {
+                        start: tip,
+                        end: right,
+                    },
+                ]
+            })
+            .collect()
+    }
+
+    /// The heads as closed triangles
+    pub fn head_polygons(&self) -> Vec<Polygon> {
+        self.heads()
+            .into_iter()
+            .map(|vertices| Polygon::new(vertices.to_vec()))
+            .collect()
+    }
+
+    /// The lines of the shaft and the head sides
+    pub fn outline_lines(&self) -> Vec<Line> {
+        let mut lines = vec![self.shaft()];
+        lines.append(&mut self.head_lines());
+
+        lines
+    }
+
+    /// The heads as kurbo path. Open heads are two connected lines, filled heads closed triangles
+    pub fn heads_to_kurbo(&self) -> kurbo::BezPath {
+        let mut bez_path = kurbo::BezPath::new();
+
+        for [left, tip, right] in self.heads() {
+            bez_path.move_to(left.to_kurbo_point());
+            bez_path.line_to(tip.to_kurbo_point());
+            bez_path.line_to(right.to_kurbo_point());
+
+            if self.head.style == ArrowHeadStyle::Filled {
+                bez_path.close_path();
+            }
+        }
+
+        bez_path
+    }
+}
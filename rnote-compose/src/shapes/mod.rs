@@ -1,7 +1,9 @@
+mod arrow;
 /// Cubic bezier curves
 pub mod cubbez;
 mod ellipse;
 mod line;
+mod polygon;
 /// Quadratic bezier curves
 pub mod quadbez;
 mod rectangle;
@@ -9,9 +11,11 @@ mod shape;
 mod shapebehaviour;
 
 // Re-exports
+pub use arrow::{Arrow, ArrowHead, ArrowHeadStyle};
 pub use cubbez::CubicBezier;
 pub use ellipse::Ellipse;
 pub use line::Line;
+pub use polygon::Polygon;
 pub use quadbez::QuadraticBezier;
 pub use rectangle::Rectangle;
 pub use shape::Shape;
@@ -0,0 +1,141 @@
+use p2d::bounding_volume::AABB;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::{AABBHelpers, Vector2Helpers};
+use crate::shapes::Line;
+use crate::shapes::ShapeBehaviour;
+use crate::transform::TransformBehaviour;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, rename = "polygon")]
+/// A closed polygon
+pub struct Polygon {
+    #[serde(rename = "vertices")]
+    /// The polygon vertices. The last vertex is connected to the first
+    pub vertices: Vec<na::Vector2<f64>>,
+}
+
+impl TransformBehaviour for Polygon {
+    fn translate(&mut self, offset: nalgebra::Vector2<f64>) {
+        for vertex in self.vertices.iter_mut() {
+            *vertex += offset;
+        }
+    }
+
+    fn rotate(&mut self, angle: f64, center: nalgebra::Point2<f64>) {
+        let mut isometry = na::Isometry2::identity();
+        isometry.append_rotation_wrt_point_mut(&na::UnitComplex::new(angle), &center);
+
+        for vertex in self.vertices.iter_mut() {
+            *vertex = (isometry * na::Point2::from(*vertex)).coords;
+        }
+    }
+
+    fn scale(&mut self, scale: nalgebra::Vector2<f64>) {
+        for vertex in self.vertices.iter_mut() {
+            *vertex = vertex.component_mul(&scale);
+        }
+    }
+}
+
+impl ShapeBehaviour for Polygon {
+    fn bounds(&self) -> AABB {
+        let mut vertices = self.vertices.iter();
+
+        let mut bounds = match vertices.next() {
+            Some(first) => AABB::new(na::Point2::from(*first), na::Point2::from(*first)),
+            None => return AABB::new_zero(),
+        };
+        for vertex in vertices {
+            bounds.take_point(na::Point2::from(*vertex));
+        }
+
+        bounds
+    }
+
+    fn hitboxes(&self) -> Vec<AABB> {
+        self.outline_lines()
+            .into_iter()
+            .flat_map(|line| line.hitboxes())
+            .collect()
+    }
+}
+
+impl Polygon {
+    /// A new polygon from the given vertices
+    pub fn new(vertices: Vec<na::Vector2<f64>>) -> Self {
+        Self { vertices }
+    }
+
+    /// A new regular polygon around the center, with its first vertex at the given position
+    pub fn new_regular(
+        center: na::Vector2<f64>,
+        first_vertex: na::Vector2<f64>,
+        n_sides: u32,
+    ) -> Self {
+        let radius_vec = first_vertex - center;
+
+        Self {
+            vertices: (0..n_sides)
+                .map(|i| {
+                    let angle = std::f64::consts::TAU * f64::from(i) / f64::from(n_sides);
+                    center + na::Rotation2::new(angle) * radius_vec
+                })
+                .collect(),
+        }
+    }
+
+    /// A new star around the center, with its first outer point at the given position.
+    /// The inner vertices are at the inner radius ratio of the outer radius
+    pub fn new_star(
+        center: na::Vector2<f64>,
+        first_point: na::Vector2<f64>,
+        n_points: u32,
+        inner_radius_ratio: f64,
+    ) -> Self {
+        let radius_vec = first_point - center;
+
+        Self {
+            vertices: (0..n_points * 2)
+                .map(|i| {
+                    let angle = std::f64::consts::PI * f64::from(i) / f64::from(n_points);
+                    let ratio = if i % 2 == 0 { 1.0 } else { inner_radius_ratio };
+
+                    center + na::Rotation2::new(angle) * radius_vec * ratio
+                })
+                .collect(),
+        }
+    }
+
+    /// The lines of the outline, including the closing line from the last to the first vertex
+    pub fn outline_lines(&self) -> Vec<Line> {
+        let n_vertices = self.vertices.len();
+        if n_vertices < 2 {
+            return vec![];
+        }
+
+        (0..n_vertices)
+            .map(|i| Line {
+                start: self.vertices[i],
+                end: self.vertices[(i + 1) % n_vertices],
+            })
+            .collect()
+    }
+
+    /// to kurbo
+    pub fn to_kurbo(&self) -> kurbo::BezPath {
+        let mut bez_path = kurbo::BezPath::new();
+        let mut vertices = self.vertices.iter();
+
+        if let Some(first) = vertices.next() {
+            bez_path.move_to(first.to_kurbo_point());
+
+            for vertex in vertices {
+                bez_path.line_to(vertex.to_kurbo_point());
+            }
+            bez_path.close_path();
+        }
+
+        bez_path
+    }
+}
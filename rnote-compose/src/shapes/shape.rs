@@ -1,7 +1,9 @@
 use p2d::bounding_volume::AABB;
 use serde::{Deserialize, Serialize};
 
-use super::{CubicBezier, Ellipse, Line, QuadraticBezier, Rectangle, ShapeBehaviour};
+use super::{
+    Arrow, CubicBezier, Ellipse, Line, Polygon, QuadraticBezier, Rectangle, ShapeBehaviour,
+};
 use crate::penpath::Segment;
 use crate::transform::TransformBehaviour;
 
@@ -28,6 +30,12 @@ pub enum Shape {
     #[serde(rename = "segment")]
     /// A segment
     Segment(Segment),
+    #[serde(rename = "polygon")]
+    /// A closed polygon
+    Polygon(Polygon),
+    #[serde(rename = "arrow")]
+    /// An arrow
+    Arrow(Arrow),
 }
 
 impl Default for Shape {
@@ -57,6 +65,12 @@ impl TransformBehaviour for Shape {
             Self::Segment(segment) => {
                 segment.translate(offset);
             }
+            Self::Polygon(polygon) => {
+                polygon.translate(offset);
+            }
+            Self::Arrow(arrow) => {
+                arrow.translate(offset);
+            }
         }
     }
 
@@ -80,6 +94,12 @@ impl TransformBehaviour for Shape {
             Self::Segment(segment) => {
                 segment.rotate(angle, center);
             }
+            Self::Polygon(polygon) => {
+                polygon.rotate(angle, center);
+            }
+            Self::Arrow(arrow) => {
+                arrow.rotate(angle, center);
+            }
         }
     }
 
@@ -103,6 +123,12 @@ impl TransformBehaviour for Shape {
             Self::Segment(segment) => {
                 segment.scale(scale);
             }
+            Self::Polygon(polygon) => {
+                polygon.scale(scale);
+            }
+            Self::Arrow(arrow) => {
+                arrow.scale(scale);
+            }
         }
     }
 }
@@ -116,6 +142,8 @@ impl ShapeBehaviour for Shape {
             Self::QuadraticBezier(quadbez) => quadbez.bounds(),
             Self::CubicBezier(cubbez) => cubbez.bounds(),
             Self::Segment(segment) => segment.bounds(),
+            Self::Polygon(polygon) => polygon.bounds(),
+            Self::Arrow(arrow) => arrow.bounds(),
         }
     }
     fn hitboxes(&self) -> Vec<AABB> {
@@ -126,6 +154,24 @@ impl ShapeBehaviour for Shape {
             Self::QuadraticBezier(quadbez) => quadbez.hitboxes(),
             Self::CubicBezier(cubbez) => cubbez.hitboxes(),
             Self::Segment(segment) => segment.hitboxes(),
+            Self::Polygon(polygon) => polygon.hitboxes(),
+            Self::Arrow(arrow) => arrow.hitboxes(),
+        }
+    }
+}
+
+impl Shape {
+    /// Approximates the shape with lines. Curves are split into the given number of lines.
+    pub fn approx_with_lines(&self, n_splits: i32) -> Vec<Line> {
+        match self {
+            Self::Line(line) => vec![*line],
+            Self::Rectangle(rectangle) => rectangle.outline_lines().to_vec(),
+            Self::Ellipse(ellipse) => ellipse.approx_with_lines(),
+            Self::QuadraticBezier(quadbez) => quadbez.approx_with_lines(n_splits),
+            Self::CubicBezier(cubbez) => cubbez.approx_with_lines(n_splits),
+            Self::Segment(segment) => segment.approx_with_lines(n_splits),
+            Self::Polygon(polygon) => polygon.outline_lines(),
+            Self::Arrow(arrow) => arrow.outline_lines(),
         }
     }
 }
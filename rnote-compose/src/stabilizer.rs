@@ -0,0 +1,174 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::penhelpers::PenEvent;
+use crate::penpath::Element;
+
+#[derive(
+    Debug,
+    Eq,
+    PartialEq,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    num_derive::FromPrimitive,
+    num_derive::ToPrimitive,
+)]
+#[serde(rename = "stabilizer_mode")]
+/// The algorithm used for stabilizing the pen input
+pub enum StabilizerMode {
+    /// The input is not stabilized
+    #[serde(rename = "none")]
+    None = 0,
+    /// The elements are averaged with the preceding elements in a window
+    #[serde(rename = "moving_average")]
+    MovingAverage,
+    /// The stroke is pulled behind the pen on a string. It only follows when the pen is further away than the string length
+    #[serde(rename = "pull_string")]
+    PullString,
+}
+
+impl Default for StabilizerMode {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+impl TryFrom<u32> for StabilizerMode {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        num_traits::FromPrimitive::from_u32(value).ok_or_else(|| {
+            anyhow::anyhow!(
+                "StabilizerMode try_from::<u32>() for value {} failed",
+                value
+            )
+        })
+    }
+}
+
+/// A stabilizer, smoothing the pen input before it is committed to help drawing clean lines with shaky hands
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, rename = "stabilizer")]
+pub struct Stabilizer {
+    /// The stabilizer mode
+    #[serde(rename = "mode")]
+    pub mode: StabilizerMode,
+    /// The number of elements which are averaged in the moving average mode
+    #[serde(rename = "window_size")]
+    pub window_size: usize,
+    /// The length of the string in the pull string mode
+    #[serde(rename = "string_length")]
+    pub string_length: f64,
+}
+
+impl Default for Stabilizer {
+    fn default() -> Self {
+        Self {
+            mode: StabilizerMode::default(),
+            window_size: Self::WINDOW_SIZE_DEFAULT,
+            string_length: Self::STRING_LENGTH_DEFAULT,
+        }
+    }
+}
+
+impl Stabilizer {
+    /// The min window size for the moving average mode
+    pub const WINDOW_SIZE_MIN: usize = 2;
+    /// The max window size for the moving average mode
+    pub const WINDOW_SIZE_MAX: usize = 32;
+    /// The default window size for the moving average mode
+    pub const WINDOW_SIZE_DEFAULT: usize = 6;
+    /// The min string length for the pull string mode
+    pub const STRING_LENGTH_MIN: f64 = 1.0;
+    /// The max string length for the pull string mode
+    pub const STRING_LENGTH_MAX: f64 = 100.0;
+    /// The default string length for the pull string mode
+    pub const STRING_LENGTH_DEFAULT: f64 = 12.0;
+
+    /// Starts stabilizing the input of a new stroke, beginning at the given element
+    pub fn start(&self, element: Element) -> StabilizerState {
+        StabilizerState {
+            stabilizer: *self,
+            window: VecDeque::from(vec![element]),
+            anchor: element,
+        }
+    }
+}
+
+/// The state of a stabilizer while a stroke is drawn
+#[derive(Debug, Clone)]
+pub struct StabilizerState {
+    stabilizer: Stabilizer,
+    window: VecDeque<Element>,
+    anchor: Element,
+}
+
+impl StabilizerState {
+    /// Stabilizes the element of the pen event. Down events are dropped (returning None) when the stabilized element did not move.
+    /// The element of the up event ends the stroke at the last stabilized position. Other events are passed through unchanged.
+    pub fn stabilize_event(&mut self, event: PenEvent) -> Option<PenEvent> {
+        match event {
+            PenEvent::Down {
+                element,
+                shortcut_keys,
+            } => self.stabilize(element).map(|element| PenEvent::Down {
+                element,
+                shortcut_keys,
+            }),
+            PenEvent::Up {
+                element,
+                shortcut_keys,
+            } => Some(PenEvent::Up {
+                element: self.stabilize(element).unwrap_or(self.anchor),
+                shortcut_keys,
+            }),
+            event => Some(event),
+        }
+    }
+
+    /// Stabilizes a new element. Returns None when the stabilized position did not change.
+    fn stabilize(&mut self, element: Element) -> Option<Element> {
+        match self.stabilizer.mode {
+            StabilizerMode::None => {
+                self.anchor = element;
+                Some(element)
+            }
+            StabilizerMode::MovingAverage => {
+                self.window.push_back(element);
+                while self.window.len() > self.stabilizer.window_size.max(1) {
+                    self.window.pop_front();
+                }
+
+                let n_elements = self.window.len() as f64;
+                let (pos_sum, pressure_sum) = self.window.iter().fold(
+                    (na::Vector2::<f64>::zeros(), 0.0),
+                    |(pos_sum, pressure_sum), element| {
+                        (pos_sum + element.pos, pressure_sum + element.pressure)
+                    },
+                );
+
+                self.anchor = Element::new(pos_sum / n_elements, pressure_sum / n_elements);
+                Some(self.anchor)
+            }
+            StabilizerMode::PullString => {
+                let string_length = self.stabilizer.string_length;
+                let offset = element.pos - self.anchor.pos;
+                let distance = offset.magnitude();
+
+                if distance <= string_length {
+                    return None;
+                }
+
+                // The anchor is pulled towards the pen until it is at the string length
+                self.anchor = Element::new(
+                    self.anchor.pos + offset * ((distance - string_length) / distance),
+                    element.pressure,
+                );
+                Some(self.anchor)
+            }
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+use crate::style::{PressureCurve, PressureResponse};
+use crate::Color;
+
+/// The options for a calligraphy style, drawing with a flat nib held at a fixed angle.
+/// The width of the stroke depends on its direction relative to the nib.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename = "calligraphy_options")]
+pub struct CalligraphyOptions {
+    /// The width of the nib
+    #[serde(rename = "stroke_width")]
+    pub stroke_width: f64,
+    /// The color of the stroke
+    #[serde(rename = "stroke_color")]
+    pub stroke_color: Option<Color>,
+    /// The angle of the nib in degrees, clockwise from the x-axis
+    #[serde(rename = "nib_angle")]
+    pub nib_angle: f64,
+    /// The thickness of the nib, as ratio of its width
+    #[serde(rename = "nib_thickness")]
+    pub nib_thickness: f64,
+    /// Pressure curve
+    #[serde(rename = "pressure_curve")]
+    pub pressure_curve: PressureCurve,
+    /// Pressure response, mapping the reported pressure before the pressure curve is applied
+    #[serde(rename = "pressure_response")]
+    pub pressure_response: PressureResponse,
+}
+
+impl Default for CalligraphyOptions {
+    fn default() -> Self {
+        Self {
+            stroke_width: Self::WIDTH_DEFAULT,
+            stroke_color: Some(Color::BLACK),
+            nib_angle: Self::NIB_ANGLE_DEFAULT,
+            nib_thickness: Self::NIB_THICKNESS_DEFAULT,
+            pressure_curve: PressureCurve::Const,
+            pressure_response: PressureResponse::default(),
+        }
+    }
+}
+
+impl CalligraphyOptions {
+    /// The default width
+    pub const WIDTH_DEFAULT: f64 = 8.0;
+    /// The default nib angle, as used for italic handwriting
+    pub const NIB_ANGLE_DEFAULT: f64 = -45.0;
+    /// The min nib thickness ratio
+    pub const NIB_THICKNESS_MIN: f64 = 0.02;
+    /// The max nib thickness ratio
+    pub const NIB_THICKNESS_MAX: f64 = 1.0;
+    /// The default nib thickness ratio
+    pub const NIB_THICKNESS_DEFAULT: f64 = 0.1;
+
+    /// The half vectors along the nib and along its thickness for the given nib width
+    pub(super) fn nib_half_vecs(&self, width: f64) -> (na::Vector2<f64>, na::Vector2<f64>) {
+        let nib_dir = na::Rotation2::new(self.nib_angle.to_radians()) * na::Vector2::x();
+        let nib_thickness = self
+            .nib_thickness
+            .clamp(Self::NIB_THICKNESS_MIN, Self::NIB_THICKNESS_MAX);
+
+        (
+            nib_dir * width * 0.5,
+            na::vector![-nib_dir[1], nib_dir[0]] * width * nib_thickness * 0.5,
+        )
+    }
+
+    /// The width for the given reported pressure, with the pressure response and curve applied
+    pub fn pressure_width(&self, width: f64, pressure: f64) -> f64 {
+        self.pressure_curve
+            .apply(width, self.pressure_response.apply(pressure))
+    }
+}
@@ -0,0 +1,359 @@
+mod calligraphyoptions;
+
+// Re-exports
+pub use calligraphyoptions::CalligraphyOptions;
+
+use super::Composer;
+use crate::helpers::Vector2Helpers;
+use crate::penpath::{Element, Segment};
+use crate::shapes::{
+    Arrow, ArrowHeadStyle, CubicBezier, Ellipse, Line, Polygon, QuadraticBezier, Rectangle,
+    ShapeBehaviour,
+};
+use crate::PenPath;
+
+use p2d::bounding_volume::{BoundingVolume, AABB};
+
+/// The number of lines that curves are approximated with
+const N_SPLITS: i32 = 5;
+
+/// The corners of the nib at the position with the given width
+fn nib_corners(
+    pos: na::Vector2<f64>,
+    width: f64,
+    options: &CalligraphyOptions,
+) -> [na::Vector2<f64>; 4] {
+    let (nib_half, thickness_half) = options.nib_half_vecs(width);
+
+    [
+        pos - nib_half - thickness_half,
+        pos + nib_half - thickness_half,
+        pos + nib_half + thickness_half,
+        pos - nib_half + thickness_half,
+    ]
+}
+
+/// The convex hull of the points in counter-clockwise order, with Andrew's monotone chain algorithm
+fn convex_hull(mut points: Vec<na::Vector2<f64>>) -> Vec<na::Vector2<f64>> {
+    if points.len() < 3 {
+        return points;
+    }
+    points.sort_unstable_by(|a, b| {
+        a[0].partial_cmp(&b[0])
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a[1].partial_cmp(&b[1]).unwrap_or(std::cmp::Ordering::Equal))
+    });
+
+    let cross =
+        |o: na::Vector2<f64>, a: na::Vector2<f64>, b: na::Vector2<f64>| (a - o).perp(&(b - o));
+
+    let mut hull: Vec<na::Vector2<f64>> = Vec::with_capacity(points.len() * 2);
+    // Lower hull
+    for &point in points.iter() {
+        while hull.len() >= 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(point);
+    }
+    // Upper hull, without removing points of the lower hull
+    let lower_len = hull.len() + 1;
+    for &point in points.iter().rev().skip(1) {
+        while hull.len() >= lower_len
+            && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0
+        {
+            hull.pop();
+        }
+        hull.push(point);
+    }
+    hull.pop();
+
+    hull
+}
+
+/// Appends the polygon as closed sub path
+fn push_polygon(bez_path: &mut kurbo::BezPath, polygon: &[na::Vector2<f64>]) {
+    if let Some(first) = polygon.first() {
+        bez_path.move_to(first.to_kurbo_point());
+
+        for point in polygon.iter().skip(1) {
+            bez_path.line_to(point.to_kurbo_point());
+        }
+        bez_path.close_path();
+    }
+}
+
+/// Composes the area that the nib sweeps along the lines, interpolating the nib width from start to end.
+/// Must be drawn with only a fill. The sub paths all have the same orientation, so they are unioned with the nonzero fill rule.
+fn compose_lines_nib(
+    lines: &[Line],
+    width_start: f64,
+    width_end: f64,
+    options: &CalligraphyOptions,
+) -> kurbo::BezPath {
+    let mut bez_path = kurbo::BezPath::new();
+    let n_lines = lines.len() as f64;
+
+    for (i, line) in lines.iter().enumerate() {
+        let line_start_width = width_start + (width_end - width_start) * (i as f64 / n_lines);
+        let line_end_width = width_start + (width_end - width_start) * ((i + 1) as f64 / n_lines);
+
+        let hull = convex_hull(
+            nib_corners(line.start, line_start_width, options)
+                .into_iter()
+                .chain(nib_corners(line.end, line_end_width, options))
+                .collect(),
+        );
+
+        push_polygon(&mut bez_path, &hull);
+    }
+
+    bez_path
+}
+
+fn draw_bez_path(
+    cx: &mut impl piet::RenderContext,
+    bez_path: kurbo::BezPath,
+    options: &CalligraphyOptions,
+) {
+    if let Some(fill_color) = options.stroke_color {
+        let fill_brush = cx.solid_brush(fill_color.into());
+        cx.fill(bez_path, &fill_brush);
+    }
+}
+
+impl Composer<CalligraphyOptions> for Line {
+    fn composed_bounds(&self, options: &CalligraphyOptions) -> AABB {
+        self.bounds().loosened(options.stroke_width * 0.5)
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &CalligraphyOptions) {
+        cx.save().unwrap();
+        let bez_path = compose_lines_nib(
+            &[*self],
+            options.stroke_width,
+            options.stroke_width,
+            options,
+        );
+        draw_bez_path(cx, bez_path, options);
+        cx.restore().unwrap();
+    }
+}
+
+impl Composer<CalligraphyOptions> for Rectangle {
+    fn composed_bounds(&self, options: &CalligraphyOptions) -> AABB {
+        self.bounds().loosened(options.stroke_width * 0.5)
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &CalligraphyOptions) {
+        cx.save().unwrap();
+        let bez_path = compose_lines_nib(
+            &self.outline_lines(),
+            options.stroke_width,
+            options.stroke_width,
+            options,
+        );
+        draw_bez_path(cx, bez_path, options);
+        cx.restore().unwrap();
+    }
+}
+
+impl Composer<CalligraphyOptions> for Polygon {
+    fn composed_bounds(&self, options: &CalligraphyOptions) -> AABB {
+        self.bounds().loosened(options.stroke_width * 0.5)
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &CalligraphyOptions) {
+        cx.save().unwrap();
+        let bez_path = compose_lines_nib(
+            &self.outline_lines(),
+            options.stroke_width,
+            options.stroke_width,
+            options,
+        );
+        draw_bez_path(cx, bez_path, options);
+        cx.restore().unwrap();
+    }
+}
+
+impl Composer<CalligraphyOptions> for Arrow {
+    fn composed_bounds(&self, options: &CalligraphyOptions) -> AABB {
+        self.bounds().loosened(options.stroke_width * 0.5)
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &CalligraphyOptions) {
+        cx.save().unwrap();
+        let mut bez_path = compose_lines_nib(
+            &self.outline_lines(),
+            options.stroke_width,
+            options.stroke_width,
+            options,
+        );
+        if self.head.style == ArrowHeadStyle::Filled {
+            for head_polygon in self.head_polygons() {
+                bez_path.extend(head_polygon.to_kurbo());
+            }
+        }
+        draw_bez_path(cx, bez_path, options);
+        cx.restore().unwrap();
+    }
+}
+
+impl Composer<CalligraphyOptions> for Ellipse {
+    fn composed_bounds(&self, options: &CalligraphyOptions) -> AABB {
+        self.bounds().loosened(options.stroke_width * 0.5)
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &CalligraphyOptions) {
+        cx.save().unwrap();
+        let bez_path = compose_lines_nib(
+            &self.approx_with_lines(),
+            options.stroke_width,
+            options.stroke_width,
+            options,
+        );
+        draw_bez_path(cx, bez_path, options);
+        cx.restore().unwrap();
+    }
+}
+
+impl Composer<CalligraphyOptions> for QuadraticBezier {
+    fn composed_bounds(&self, options: &CalligraphyOptions) -> AABB {
+        self.bounds().loosened(options.stroke_width * 0.5)
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &CalligraphyOptions) {
+        cx.save().unwrap();
+        let bez_path = compose_lines_nib(
+            &self.approx_with_lines(N_SPLITS),
+            options.stroke_width,
+            options.stroke_width,
+            options,
+        );
+        draw_bez_path(cx, bez_path, options);
+        cx.restore().unwrap();
+    }
+}
+
+impl Composer<CalligraphyOptions> for CubicBezier {
+    fn composed_bounds(&self, options: &CalligraphyOptions) -> AABB {
+        self.bounds().loosened(options.stroke_width * 0.5)
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &CalligraphyOptions) {
+        cx.save().unwrap();
+        let bez_path = compose_lines_nib(
+            &self.approx_with_lines(N_SPLITS),
+            options.stroke_width,
+            options.stroke_width,
+            options,
+        );
+        draw_bez_path(cx, bez_path, options);
+        cx.restore().unwrap();
+    }
+}
+
+impl Composer<CalligraphyOptions> for Segment {
+    fn composed_bounds(&self, options: &CalligraphyOptions) -> AABB {
+        self.bounds().loosened(options.stroke_width * 0.5)
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &CalligraphyOptions) {
+        cx.save().unwrap();
+
+        let width =
+            |element: &Element| options.pressure_width(options.stroke_width, element.pressure);
+
+        let bez_path = match self {
+            Segment::Dot { element } => {
+                let mut bez_path = kurbo::BezPath::new();
+                push_polygon(
+                    &mut bez_path,
+                    &nib_corners(element.pos, width(element), options),
+                );
+                bez_path
+            }
+            Segment::Line { start, end } => compose_lines_nib(
+                &[Line {
+                    start: start.pos,
+                    end: end.pos,
+                }],
+                width(start),
+                width(end),
+                options,
+            ),
+            Segment::QuadBez { start, cp, end } => {
+                let lines = QuadraticBezier {
+                    start: start.pos,
+                    cp: *cp,
+                    end: end.pos,
+                }
+                .approx_with_lines(N_SPLITS);
+
+                compose_lines_nib(&lines, width(start), width(end), options)
+            }
+            Segment::CubBez {
+                start,
+                cp1,
+                cp2,
+                end,
+            } => {
+                let lines = CubicBezier {
+                    start: start.pos,
+                    cp1: *cp1,
+                    cp2: *cp2,
+                    end: end.pos,
+                }
+                .approx_with_lines(N_SPLITS);
+
+                compose_lines_nib(&lines, width(start), width(end), options)
+            }
+        };
+
+        draw_bez_path(cx, bez_path, options);
+        cx.restore().unwrap();
+    }
+}
+
+impl Composer<CalligraphyOptions> for PenPath {
+    fn composed_bounds(&self, options: &CalligraphyOptions) -> AABB {
+        self.iter()
+            .map(|segment| segment.composed_bounds(options))
+            .fold(AABB::new_invalid(), |acc, x| acc.merged(&x))
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &CalligraphyOptions) {
+        cx.save().unwrap();
+        for segment in self.iter() {
+            segment.draw_composed(cx, options);
+        }
+        cx.restore().unwrap();
+    }
+}
+
+impl Composer<CalligraphyOptions> for crate::Shape {
+    fn composed_bounds(&self, options: &CalligraphyOptions) -> AABB {
+        match self {
+            crate::Shape::Line(line) => line.composed_bounds(options),
+            crate::Shape::Rectangle(rectangle) => rectangle.composed_bounds(options),
+            crate::Shape::Ellipse(ellipse) => ellipse.composed_bounds(options),
+            crate::Shape::QuadraticBezier(quadbez) => quadbez.composed_bounds(options),
+            crate::Shape::CubicBezier(cubbez) => cubbez.composed_bounds(options),
+            crate::Shape::Segment(segment) => segment.composed_bounds(options),
+            crate::Shape::Polygon(polygon) => polygon.composed_bounds(options),
+            crate::Shape::Arrow(arrow) => arrow.composed_bounds(options),
+        }
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &CalligraphyOptions) {
+        match self {
+            crate::Shape::Line(line) => line.draw_composed(cx, options),
+            crate::Shape::Rectangle(rectangle) => rectangle.draw_composed(cx, options),
+            crate::Shape::Ellipse(ellipse) => ellipse.draw_composed(cx, options),
+            crate::Shape::QuadraticBezier(quadbez) => quadbez.draw_composed(cx, options),
+            crate::Shape::CubicBezier(cubbez) => cubbez.draw_composed(cx, options),
+            crate::Shape::Segment(segment) => segment.draw_composed(cx, options),
+            crate::Shape::Polygon(polygon) => polygon.draw_composed(cx, options),
+            crate::Shape::Arrow(arrow) => arrow.draw_composed(cx, options),
+        }
+    }
+}
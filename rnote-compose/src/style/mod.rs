@@ -1,3 +1,5 @@
+/// The calligraphy module for calligraphy styles
+pub mod calligraphy;
 mod composer;
 /// Draw helpers
 pub mod drawhelpers;
@@ -5,18 +7,22 @@ pub mod drawhelpers;
 pub mod rough;
 /// The smooth module for smooth styles
 pub mod smooth;
+/// The stamped module for stamped styles
+pub mod stamped;
 /// The textured module for textured styles
 pub mod textured;
 
 // Re exports
+use self::calligraphy::CalligraphyOptions;
 use self::rough::RoughOptions;
 use self::smooth::SmoothOptions;
+use self::stamped::StampedOptions;
 use self::textured::TexturedOptions;
 pub use composer::Composer;
 
 use crate::penpath::Segment;
-use crate::shapes::{CubicBezier, Ellipse, Line, QuadraticBezier, Rectangle};
-use crate::{PenPath, Shape};
+use crate::shapes::{Arrow, CubicBezier, Ellipse, Line, Polygon, QuadraticBezier, Rectangle};
+use crate::{Color, PenPath, Shape};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +38,12 @@ pub enum Style {
     /// A textured style
     #[serde(rename = "textured")]
     Textured(TexturedOptions),
+    /// A calligraphy style
+    #[serde(rename = "calligraphy")]
+    Calligraphy(CalligraphyOptions),
+    /// A stamped style
+    #[serde(rename = "stamped")]
+    Stamped(StampedOptions),
 }
 
 impl Default for Style {
@@ -47,6 +59,41 @@ impl Style {
             Style::Smooth(options) => options.stroke_width,
             Style::Rough(options) => options.stroke_width,
             Style::Textured(options) => options.stroke_width,
+            Style::Calligraphy(options) => options.stroke_width,
+            Style::Stamped(options) => options.stroke_width,
+        }
+    }
+
+    /// returns the stroke color. available on all styles
+    pub fn stroke_color(&self) -> Option<Color> {
+        match self {
+            Style::Smooth(options) => options.stroke_color,
+            Style::Rough(options) => options.stroke_color,
+            Style::Textured(options) => options.stroke_color,
+            Style::Calligraphy(options) => options.stroke_color,
+            Style::Stamped(options) => options.stroke_color,
+        }
+    }
+
+    /// sets the stroke width. available on all styles
+    pub fn set_stroke_width(&mut self, stroke_width: f64) {
+        match self {
+            Style::Smooth(options) => options.stroke_width = stroke_width,
+            Style::Rough(options) => options.stroke_width = stroke_width,
+            Style::Textured(options) => options.stroke_width = stroke_width,
+            Style::Calligraphy(options) => options.stroke_width = stroke_width,
+            Style::Stamped(options) => options.stroke_width = stroke_width,
+        }
+    }
+
+    /// sets the stroke color. available on all styles
+    pub fn set_stroke_color(&mut self, stroke_color: Option<Color>) {
+        match self {
+            Style::Smooth(options) => options.stroke_color = stroke_color,
+            Style::Rough(options) => options.stroke_color = stroke_color,
+            Style::Textured(options) => options.stroke_color = stroke_color,
+            Style::Calligraphy(options) => options.stroke_color = stroke_color,
+            Style::Stamped(options) => options.stroke_color = stroke_color,
         }
     }
 }
@@ -57,6 +104,8 @@ impl Composer<Style> for Line {
             Style::Smooth(options) => self.composed_bounds(options),
             Style::Rough(options) => self.composed_bounds(options),
             Style::Textured(options) => self.composed_bounds(options),
+            Style::Calligraphy(options) => self.composed_bounds(options),
+            Style::Stamped(options) => self.composed_bounds(options),
         }
     }
 
@@ -65,6 +114,8 @@ impl Composer<Style> for Line {
             Style::Smooth(options) => self.draw_composed(cx, options),
             Style::Rough(options) => self.draw_composed(cx, options),
             Style::Textured(options) => self.draw_composed(cx, options),
+            Style::Calligraphy(options) => self.draw_composed(cx, options),
+            Style::Stamped(options) => self.draw_composed(cx, options),
         }
     }
 }
@@ -75,6 +126,8 @@ impl Composer<Style> for Rectangle {
             Style::Smooth(options) => self.composed_bounds(options),
             Style::Rough(options) => self.composed_bounds(options),
             Style::Textured(_options) => unimplemented!(),
+            Style::Calligraphy(options) => self.composed_bounds(options),
+            Style::Stamped(options) => self.composed_bounds(&options.outline_options()),
         }
     }
 
@@ -83,6 +136,52 @@ impl Composer<Style> for Rectangle {
             Style::Smooth(options) => self.draw_composed(cx, options),
             Style::Rough(options) => self.draw_composed(cx, options),
             Style::Textured(_options) => unimplemented!(),
+            Style::Calligraphy(options) => self.draw_composed(cx, options),
+            Style::Stamped(options) => self.draw_composed(cx, &options.outline_options()),
+        }
+    }
+}
+
+impl Composer<Style> for Polygon {
+    fn composed_bounds(&self, options: &Style) -> p2d::bounding_volume::AABB {
+        match options {
+            Style::Smooth(options) => self.composed_bounds(options),
+            Style::Rough(options) => self.composed_bounds(options),
+            Style::Textured(_options) => unimplemented!(),
+            Style::Calligraphy(options) => self.composed_bounds(options),
+            Style::Stamped(options) => self.composed_bounds(&options.outline_options()),
+        }
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &Style) {
+        match options {
+            Style::Smooth(options) => self.draw_composed(cx, options),
+            Style::Rough(options) => self.draw_composed(cx, options),
+            Style::Textured(_options) => unimplemented!(),
+            Style::Calligraphy(options) => self.draw_composed(cx, options),
+            Style::Stamped(options) => self.draw_composed(cx, &options.outline_options()),
+        }
+    }
+}
+
+impl Composer<Style> for Arrow {
+    fn composed_bounds(&self, options: &Style) -> p2d::bounding_volume::AABB {
+        match options {
+            Style::Smooth(options) => self.composed_bounds(options),
+            Style::Rough(options) => self.composed_bounds(options),
+            Style::Textured(_options) => unimplemented!(),
+            Style::Calligraphy(options) => self.composed_bounds(options),
+            Style::Stamped(options) => self.composed_bounds(&options.outline_options()),
+        }
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &Style) {
+        match options {
+            Style::Smooth(options) => self.draw_composed(cx, options),
+            Style::Rough(options) => self.draw_composed(cx, options),
+            Style::Textured(_options) => unimplemented!(),
+            Style::Calligraphy(options) => self.draw_composed(cx, options),
+            Style::Stamped(options) => self.draw_composed(cx, &options.outline_options()),
         }
     }
 }
@@ -93,6 +192,8 @@ impl Composer<Style> for Ellipse {
             Style::Smooth(options) => self.composed_bounds(options),
             Style::Rough(options) => self.composed_bounds(options),
             Style::Textured(_options) => unimplemented!(),
+            Style::Calligraphy(options) => self.composed_bounds(options),
+            Style::Stamped(options) => self.composed_bounds(&options.outline_options()),
         }
     }
 
@@ -101,6 +202,8 @@ impl Composer<Style> for Ellipse {
             Style::Smooth(options) => self.draw_composed(cx, options),
             Style::Rough(options) => self.draw_composed(cx, options),
             Style::Textured(_options) => unimplemented!(),
+            Style::Calligraphy(options) => self.draw_composed(cx, options),
+            Style::Stamped(options) => self.draw_composed(cx, &options.outline_options()),
         }
     }
 }
@@ -111,6 +214,8 @@ impl Composer<Style> for QuadraticBezier {
             Style::Smooth(options) => self.composed_bounds(options),
             Style::Rough(options) => self.composed_bounds(options),
             Style::Textured(_options) => unimplemented!(),
+            Style::Calligraphy(options) => self.composed_bounds(options),
+            Style::Stamped(options) => self.composed_bounds(&options.outline_options()),
         }
     }
 
@@ -119,6 +224,8 @@ impl Composer<Style> for QuadraticBezier {
             Style::Smooth(options) => self.draw_composed(cx, options),
             Style::Rough(options) => self.draw_composed(cx, options),
             Style::Textured(_options) => unimplemented!(),
+            Style::Calligraphy(options) => self.draw_composed(cx, options),
+            Style::Stamped(options) => self.draw_composed(cx, &options.outline_options()),
         }
     }
 }
@@ -129,6 +236,8 @@ impl Composer<Style> for CubicBezier {
             Style::Smooth(options) => self.composed_bounds(options),
             Style::Rough(options) => self.composed_bounds(options),
             Style::Textured(_options) => unimplemented!(),
+            Style::Calligraphy(options) => self.composed_bounds(options),
+            Style::Stamped(options) => self.composed_bounds(&options.outline_options()),
         }
     }
 
@@ -137,6 +246,8 @@ impl Composer<Style> for CubicBezier {
             Style::Smooth(options) => self.draw_composed(cx, options),
             Style::Rough(options) => self.draw_composed(cx, options),
             Style::Textured(_options) => unimplemented!(),
+            Style::Calligraphy(options) => self.draw_composed(cx, options),
+            Style::Stamped(options) => self.draw_composed(cx, &options.outline_options()),
         }
     }
 }
@@ -147,6 +258,8 @@ impl Composer<Style> for Segment {
             Style::Smooth(options) => self.composed_bounds(options),
             Style::Rough(options) => self.composed_bounds(options),
             Style::Textured(options) => self.composed_bounds(options),
+            Style::Calligraphy(options) => self.composed_bounds(options),
+            Style::Stamped(options) => self.composed_bounds(options),
         }
     }
 
@@ -155,6 +268,8 @@ impl Composer<Style> for Segment {
             Style::Smooth(options) => self.draw_composed(cx, options),
             Style::Rough(options) => self.draw_composed(cx, options),
             Style::Textured(options) => self.draw_composed(cx, options),
+            Style::Calligraphy(options) => self.draw_composed(cx, options),
+            Style::Stamped(options) => self.draw_composed(cx, options),
         }
     }
 }
@@ -165,6 +280,8 @@ impl Composer<Style> for PenPath {
             Style::Smooth(options) => self.composed_bounds(options),
             Style::Rough(options) => self.composed_bounds(options),
             Style::Textured(options) => self.composed_bounds(options),
+            Style::Calligraphy(options) => self.composed_bounds(options),
+            Style::Stamped(options) => self.composed_bounds(options),
         }
     }
 
@@ -173,6 +290,8 @@ impl Composer<Style> for PenPath {
             Style::Smooth(options) => self.draw_composed(cx, options),
             Style::Rough(options) => self.draw_composed(cx, options),
             Style::Textured(options) => self.draw_composed(cx, options),
+            Style::Calligraphy(options) => self.draw_composed(cx, options),
+            Style::Stamped(options) => self.draw_composed(cx, options),
         }
     }
 }
@@ -186,6 +305,8 @@ impl Composer<Style> for Shape {
             Shape::QuadraticBezier(quadratic_bezier) => quadratic_bezier.composed_bounds(options),
             Shape::CubicBezier(cubic_bezier) => cubic_bezier.composed_bounds(options),
             Shape::Segment(segment) => segment.composed_bounds(options),
+            Shape::Polygon(polygon) => polygon.composed_bounds(options),
+            Shape::Arrow(arrow) => arrow.composed_bounds(options),
         }
     }
 
@@ -197,6 +318,8 @@ impl Composer<Style> for Shape {
             Shape::QuadraticBezier(quadratic_bezier) => quadratic_bezier.draw_composed(cx, options),
             Shape::CubicBezier(cubic_bezier) => cubic_bezier.draw_composed(cx, options),
             Shape::Segment(segment) => segment.draw_composed(cx, options),
+            Shape::Polygon(polygon) => polygon.draw_composed(cx, options),
+            Shape::Arrow(arrow) => arrow.draw_composed(cx, options),
         }
     }
 }
@@ -247,6 +370,165 @@ impl PressureCurve {
     }
 }
 
+/// Maps the pressure reported by the pen before the pressure curve is applied, because different tablets report very different pressure ranges.
+/// The reported range between the min and max pressure is stretched to the full range, and then shaped by the gamma.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, rename = "pressure_response")]
+pub struct PressureResponse {
+    /// The reported pressure which is mapped to no pressure
+    #[serde(rename = "min_pressure")]
+    pub min_pressure: f64,
+    /// The reported pressure which is mapped to the full pressure
+    #[serde(rename = "max_pressure")]
+    pub max_pressure: f64,
+    /// The gamma. Values below 1.0 make the response more sensitive to light pressure, above 1.0 less sensitive.
+    #[serde(rename = "gamma")]
+    pub gamma: f64,
+}
+
+impl Default for PressureResponse {
+    fn default() -> Self {
+        Self {
+            min_pressure: 0.0,
+            max_pressure: 1.0,
+            gamma: Self::GAMMA_DEFAULT,
+        }
+    }
+}
+
+impl PressureResponse {
+    /// The min gamma
+    pub const GAMMA_MIN: f64 = 0.1;
+    /// The max gamma
+    pub const GAMMA_MAX: f64 = 10.0;
+    /// The default gamma
+    pub const GAMMA_DEFAULT: f64 = 1.0;
+
+    /// Maps the reported pressure. Expects pressure to be between range 0.0 to 1.0
+    pub fn apply(&self, pressure: f64) -> f64 {
+        let range = self.max_pressure - self.min_pressure;
+        if range <= 0.0 {
+            return if pressure >= self.max_pressure {
+                1.0
+            } else {
+                0.0
+            };
+        }
+
+        ((pressure - self.min_pressure) / range)
+            .clamp(0.0, 1.0)
+            .powf(self.gamma.clamp(Self::GAMMA_MIN, Self::GAMMA_MAX))
+    }
+}
+
+/// The kind of a line style
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    num_derive::FromPrimitive,
+    num_derive::ToPrimitive,
+)]
+#[serde(rename = "line_style_kind")]
+pub enum LineStyleKind {
+    /// A solid line
+    #[serde(rename = "solid")]
+    Solid = 0,
+    /// A dashed line
+    #[serde(rename = "dashed")]
+    Dashed,
+    /// A dotted line
+    #[serde(rename = "dotted")]
+    Dotted,
+}
+
+impl Default for LineStyleKind {
+    fn default() -> Self {
+        Self::Solid
+    }
+}
+
+impl TryFrom<u32> for LineStyleKind {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        num_traits::FromPrimitive::from_u32(value).ok_or_else(|| {
+            anyhow::anyhow!("LineStyleKind try_from::<u32>() for value {} failed", value)
+        })
+    }
+}
+
+/// The line style of stroked shape outlines.
+/// The dash pattern lengths are ratios of the stroke width, so that the pattern scales with it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, rename = "line_style")]
+pub struct LineStyle {
+    /// The kind
+    #[serde(rename = "kind")]
+    pub kind: LineStyleKind,
+    /// The length of the dashes, as ratio of the stroke width. Ignored for dotted lines
+    #[serde(rename = "dash_length")]
+    pub dash_length: f64,
+    /// The length of the gaps between the dashes or dots, as ratio of the stroke width
+    #[serde(rename = "gap_length")]
+    pub gap_length: f64,
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        Self {
+            kind: LineStyleKind::default(),
+            dash_length: Self::DASH_LENGTH_DEFAULT,
+            gap_length: Self::GAP_LENGTH_DEFAULT,
+        }
+    }
+}
+
+impl LineStyle {
+    /// The min dash length
+    pub const DASH_LENGTH_MIN: f64 = 0.5;
+    /// The max dash length
+    pub const DASH_LENGTH_MAX: f64 = 50.0;
+    /// The default dash length
+    pub const DASH_LENGTH_DEFAULT: f64 = 4.0;
+    /// The min gap length
+    pub const GAP_LENGTH_MIN: f64 = 0.5;
+    /// The max gap length
+    pub const GAP_LENGTH_MAX: f64 = 50.0;
+    /// The default gap length
+    pub const GAP_LENGTH_DEFAULT: f64 = 2.0;
+
+    /// The piet stroke style for the given stroke width
+    pub fn stroke_style(&self, stroke_width: f64) -> piet::StrokeStyle {
+        let mut stroke_style = piet::StrokeStyle::new();
+        let dash_length = self
+            .dash_length
+            .clamp(Self::DASH_LENGTH_MIN, Self::DASH_LENGTH_MAX);
+        let gap_length = self
+            .gap_length
+            .clamp(Self::GAP_LENGTH_MIN, Self::GAP_LENGTH_MAX);
+
+        match self.kind {
+            LineStyleKind::Solid => {}
+            LineStyleKind::Dashed => {
+                stroke_style
+                    .set_dash_pattern(vec![dash_length * stroke_width, gap_length * stroke_width]);
+            }
+            LineStyleKind::Dotted => {
+                // Zero length dashes with round caps are drawn as dots. The gap needs to include the dot diameter
+                stroke_style.set_line_cap(piet::LineCap::Round);
+                stroke_style.set_dash_pattern(vec![0.0, (gap_length + 1.0) * stroke_width]);
+            }
+        }
+
+        stroke_style
+    }
+}
+
 impl TryFrom<u32> for PressureCurve {
     type Error = anyhow::Error;
 
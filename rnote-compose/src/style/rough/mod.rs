@@ -10,7 +10,9 @@ use super::Composer;
 use crate::helpers::{Affine2Helpers, Vector2Helpers};
 use crate::penpath::Segment;
 use crate::shapes::Line;
+use crate::shapes::Polygon;
 use crate::shapes::Rectangle;
+use crate::shapes::{Arrow, ArrowHeadStyle};
 use crate::shapes::{CubicBezier, ShapeBehaviour};
 use crate::shapes::{Ellipse, QuadraticBezier};
 use crate::PenPath;
@@ -46,7 +48,12 @@ impl Composer<RoughOptions> for Line {
         if let Some(stroke_color) = options.stroke_color {
             let stroke_brush = cx.solid_brush(stroke_color.into());
 
-            cx.stroke(bez_path, &stroke_brush, options.stroke_width)
+            cx.stroke_styled(
+                bez_path,
+                &stroke_brush,
+                options.stroke_width,
+                &options.line_style.stroke_style(options.stroke_width),
+            )
         }
 
         cx.restore().unwrap();
@@ -165,7 +172,101 @@ impl Composer<RoughOptions> for Rectangle {
         if let Some(stroke_color) = options.stroke_color {
             let stroke_brush = cx.solid_brush(stroke_color.into());
 
-            cx.stroke(rect_path, &stroke_brush, options.stroke_width)
+            cx.stroke_styled(
+                rect_path,
+                &stroke_brush,
+                options.stroke_width,
+                &options.line_style.stroke_style(options.stroke_width),
+            )
+        }
+
+        cx.restore().unwrap();
+    }
+}
+
+impl Composer<RoughOptions> for Polygon {
+    fn composed_bounds(&self, options: &RoughOptions) -> p2d::bounding_volume::AABB {
+        self.bounds()
+            .loosened(options.stroke_width * 0.5 + RoughOptions::ROUGH_BOUNDS_MARGIN)
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &RoughOptions) {
+        cx.save().unwrap();
+        let mut rng = crate::utils::new_rng_default_pcg64(options.seed);
+
+        let mut outline_path = kurbo::BezPath::new();
+        for line in self.outline_lines() {
+            outline_path.extend(if !options.disable_multistroke {
+                roughgenerator::doubleline(line.start, line.end, options, &mut rng)
+            } else {
+                roughgenerator::line(line.start, line.end, true, false, options, &mut rng)
+            });
+        }
+
+        if let Some(fill_color) = options.fill_color {
+            let fill_polygon = fill_polygon(self.vertices.clone(), options);
+
+            let fill_brush = cx.solid_brush(fill_color.into());
+            cx.fill(fill_polygon, &fill_brush);
+        }
+
+        if let Some(stroke_color) = options.stroke_color {
+            let stroke_brush = cx.solid_brush(stroke_color.into());
+
+            cx.stroke_styled(
+                outline_path,
+                &stroke_brush,
+                options.stroke_width,
+                &options.line_style.stroke_style(options.stroke_width),
+            )
+        }
+
+        cx.restore().unwrap();
+    }
+}
+
+impl Composer<RoughOptions> for Arrow {
+    fn composed_bounds(&self, options: &RoughOptions) -> p2d::bounding_volume::AABB {
+        self.bounds()
+            .loosened(options.stroke_width * 0.5 + RoughOptions::ROUGH_BOUNDS_MARGIN)
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &RoughOptions) {
+        cx.save().unwrap();
+        let mut rng = crate::utils::new_rng_default_pcg64(options.seed);
+
+        let shaft_path = if !options.disable_multistroke {
+            roughgenerator::doubleline(self.start, self.end, options, &mut rng)
+        } else {
+            roughgenerator::line(self.start, self.end, true, false, options, &mut rng)
+        };
+
+        let mut heads_path = kurbo::BezPath::new();
+        for line in self.head_lines() {
+            heads_path.extend(if !options.disable_multistroke {
+                roughgenerator::doubleline(line.start, line.end, options, &mut rng)
+            } else {
+                roughgenerator::line(line.start, line.end, true, false, options, &mut rng)
+            });
+        }
+
+        if let Some(stroke_color) = options.stroke_color {
+            let stroke_brush = cx.solid_brush(stroke_color.into());
+
+            if self.head.style == ArrowHeadStyle::Filled {
+                for head in self.heads() {
+                    cx.fill(fill_polygon(head.to_vec(), options), &stroke_brush);
+                }
+            }
+
+            cx.stroke_styled(
+                shaft_path,
+                &stroke_brush,
+                options.stroke_width,
+                &options.line_style.stroke_style(options.stroke_width),
+            );
+            // The heads are always drawn solid, dashes would make them unrecognizable
+            cx.stroke(heads_path, &stroke_brush, options.stroke_width);
         }
 
         cx.restore().unwrap();
@@ -203,7 +304,12 @@ impl Composer<RoughOptions> for Ellipse {
         if let Some(stroke_color) = options.stroke_color {
             let stroke_brush = cx.solid_brush(stroke_color.into());
 
-            cx.stroke(ellipse_result.bez_path, &stroke_brush, options.stroke_width)
+            cx.stroke_styled(
+                ellipse_result.bez_path,
+                &stroke_brush,
+                options.stroke_width,
+                &options.line_style.stroke_style(options.stroke_width),
+            )
         }
 
         cx.restore().unwrap();
@@ -226,7 +332,12 @@ impl Composer<RoughOptions> for QuadraticBezier {
         if let Some(stroke_color) = options.stroke_color {
             let stroke_brush = cx.solid_brush(stroke_color.into());
 
-            cx.stroke(bez_path, &stroke_brush, options.stroke_width)
+            cx.stroke_styled(
+                bez_path,
+                &stroke_brush,
+                options.stroke_width,
+                &options.line_style.stroke_style(options.stroke_width),
+            )
         }
 
         cx.restore().unwrap();
@@ -250,7 +361,12 @@ impl Composer<RoughOptions> for CubicBezier {
         if let Some(stroke_color) = options.stroke_color {
             let stroke_brush = cx.solid_brush(stroke_color.into());
 
-            cx.stroke(bez_path, &stroke_brush, options.stroke_width)
+            cx.stroke_styled(
+                bez_path,
+                &stroke_brush,
+                options.stroke_width,
+                &options.line_style.stroke_style(options.stroke_width),
+            )
         }
 
         cx.restore().unwrap();
@@ -356,6 +472,8 @@ impl Composer<RoughOptions> for crate::Shape {
             crate::Shape::QuadraticBezier(quadbez) => quadbez.composed_bounds(options),
             crate::Shape::CubicBezier(cubbez) => cubbez.composed_bounds(options),
             crate::Shape::Segment(segment) => segment.composed_bounds(options),
+            crate::Shape::Polygon(polygon) => polygon.composed_bounds(options),
+            crate::Shape::Arrow(arrow) => arrow.composed_bounds(options),
         }
     }
 
@@ -367,6 +485,8 @@ impl Composer<RoughOptions> for crate::Shape {
             crate::Shape::QuadraticBezier(quadbez) => quadbez.draw_composed(cx, options),
             crate::Shape::CubicBezier(cubbez) => cubbez.draw_composed(cx, options),
             crate::Shape::Segment(segment) => segment.draw_composed(cx, options),
+            crate::Shape::Polygon(polygon) => polygon.draw_composed(cx, options),
+            crate::Shape::Arrow(arrow) => arrow.draw_composed(cx, options),
         }
     }
 }
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 
+use crate::style::LineStyle;
 use crate::Color;
 
 /// The rough options
@@ -71,6 +72,9 @@ pub struct RoughOptions {
     /// The offset of the dashs, when they exist
     #[serde(rename = "stroke_line_dash_offset")]
     pub stroke_line_dash_offset: f64,
+    /// The line style of the stroke outline
+    #[serde(rename = "line_style")]
+    pub line_style: LineStyle,
     /// like stroke line dash, but for the fill
     #[serde(rename = "fill_line_dash")]
     pub fill_line_dash: Vec<f64>,
@@ -114,6 +118,7 @@ impl Default for RoughOptions {
             seed: None,
             stroke_line_dash: Vec::new(),
             stroke_line_dash_offset: 0.0,
+            line_style: LineStyle::default(),
             fill_line_dash: Vec::new(),
             fill_line_dash_offset: 0.0,
             disable_multistroke: false,
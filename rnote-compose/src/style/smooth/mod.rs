@@ -1,7 +1,7 @@
 mod smoothoptions;
 
 // Re-exports
-pub use smoothoptions::SmoothOptions;
+pub use smoothoptions::{LineCap, SmoothOptions};
 
 use super::Composer;
 use crate::helpers::Vector2Helpers;
@@ -9,9 +9,11 @@ use crate::penpath::Segment;
 use crate::shapes::CubicBezier;
 use crate::shapes::Ellipse;
 use crate::shapes::Line;
+use crate::shapes::Polygon;
 use crate::shapes::QuadraticBezier;
 use crate::shapes::Rectangle;
 use crate::shapes::ShapeBehaviour;
+use crate::shapes::{Arrow, ArrowHeadStyle};
 use crate::PenPath;
 
 use kurbo::Shape;
@@ -191,7 +193,12 @@ impl Composer<SmoothOptions> for Line {
 
         if let Some(stroke_color) = options.stroke_color {
             let stroke_brush = cx.solid_brush(stroke_color.into());
-            cx.stroke(line, &stroke_brush, options.stroke_width);
+            cx.stroke_styled(
+                line,
+                &stroke_brush,
+                options.stroke_width,
+                &options.line_style.stroke_style(options.stroke_width),
+            );
         }
         cx.restore().unwrap();
     }
@@ -213,7 +220,76 @@ impl Composer<SmoothOptions> for Rectangle {
 
         if let Some(stroke_color) = options.stroke_color {
             let stroke_brush = cx.solid_brush(stroke_color.into());
-            cx.stroke(shape, &stroke_brush, options.stroke_width);
+            cx.stroke_styled(
+                shape,
+                &stroke_brush,
+                options.stroke_width,
+                &options.line_style.stroke_style(options.stroke_width),
+            );
+        }
+        cx.restore().unwrap();
+    }
+}
+
+impl Composer<SmoothOptions> for Polygon {
+    fn composed_bounds(&self, options: &SmoothOptions) -> AABB {
+        self.bounds().loosened(options.stroke_width * 0.5)
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &SmoothOptions) {
+        cx.save().unwrap();
+        let polygon = self.to_kurbo();
+
+        if let Some(fill_color) = options.fill_color {
+            let fill_brush = cx.solid_brush(fill_color.into());
+            cx.fill(polygon.clone(), &fill_brush);
+        }
+
+        if let Some(stroke_color) = options.stroke_color {
+            let stroke_brush = cx.solid_brush(stroke_color.into());
+            cx.stroke_styled(
+                polygon,
+                &stroke_brush,
+                options.stroke_width,
+                &options.line_style.stroke_style(options.stroke_width),
+            );
+        }
+        cx.restore().unwrap();
+    }
+}
+
+impl Composer<SmoothOptions> for Arrow {
+    fn composed_bounds(&self, options: &SmoothOptions) -> AABB {
+        self.bounds().loosened(options.stroke_width * 0.5)
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &SmoothOptions) {
+        cx.save().unwrap();
+
+        if let Some(stroke_color) = options.stroke_color {
+            let stroke_brush = cx.solid_brush(stroke_color.into());
+            cx.stroke_styled(
+                self.shaft().to_kurbo(),
+                &stroke_brush,
+                options.stroke_width,
+                &options.line_style.stroke_style(options.stroke_width),
+            );
+
+            // The heads are always drawn solid, dashes would make them unrecognizable
+            let heads = self.heads_to_kurbo();
+            let heads_stroke_style = piet::StrokeStyle::new()
+                .line_cap(piet::LineCap::Round)
+                .line_join(piet::LineJoin::Round);
+
+            if self.head.style == ArrowHeadStyle::Filled {
+                cx.fill(heads.clone(), &stroke_brush);
+            }
+            cx.stroke_styled(
+                heads,
+                &stroke_brush,
+                options.stroke_width,
+                &heads_stroke_style,
+            );
         }
         cx.restore().unwrap();
     }
@@ -235,7 +311,12 @@ impl Composer<SmoothOptions> for Ellipse {
 
         if let Some(stroke_color) = options.stroke_color {
             let stroke_brush = cx.solid_brush(stroke_color.into());
-            cx.stroke(ellipse, &stroke_brush, options.stroke_width);
+            cx.stroke_styled(
+                ellipse,
+                &stroke_brush,
+                options.stroke_width,
+                &options.line_style.stroke_style(options.stroke_width),
+            );
         }
         cx.restore().unwrap();
     }
@@ -257,7 +338,12 @@ impl Composer<SmoothOptions> for QuadraticBezier {
 
         if let Some(stroke_color) = options.stroke_color {
             let stroke_brush = cx.solid_brush(stroke_color.into());
-            cx.stroke(quadbez, &stroke_brush, options.stroke_width);
+            cx.stroke_styled(
+                quadbez,
+                &stroke_brush,
+                options.stroke_width,
+                &options.line_style.stroke_style(options.stroke_width),
+            );
         }
         cx.restore().unwrap();
     }
@@ -279,7 +365,12 @@ impl Composer<SmoothOptions> for CubicBezier {
 
         if let Some(stroke_color) = options.stroke_color {
             let stroke_brush = cx.solid_brush(stroke_color.into());
-            cx.stroke(cubbez, &stroke_brush, options.stroke_width);
+            cx.stroke_styled(
+                cubbez,
+                &stroke_brush,
+                options.stroke_width,
+                &options.line_style.stroke_style(options.stroke_width),
+            );
         }
         cx.restore().unwrap();
     }
@@ -297,9 +388,7 @@ impl Composer<SmoothOptions> for Segment {
             match self {
                 Segment::Dot { element } => {
                     let radii = na::Vector2::from_element(
-                        options
-                            .pressure_curve
-                            .apply(options.stroke_width * 0.5, element.pressure),
+                        options.pressure_width(options.stroke_width * 0.5, element.pressure),
                     );
 
                     kurbo::Ellipse::new(element.pos.to_kurbo_point(), radii.to_kurbo_vec(), 0.0)
@@ -307,12 +396,8 @@ impl Composer<SmoothOptions> for Segment {
                 }
                 Segment::Line { start, end } => {
                     let (width_start, width_end) = (
-                        options
-                            .pressure_curve
-                            .apply(options.stroke_width, start.pressure),
-                        options
-                            .pressure_curve
-                            .apply(options.stroke_width, end.pressure),
+                        options.pressure_width(options.stroke_width, start.pressure),
+                        options.pressure_width(options.stroke_width, end.pressure),
                     );
 
                     compose_line_variable_width(
@@ -327,12 +412,8 @@ impl Composer<SmoothOptions> for Segment {
                 }
                 Segment::QuadBez { start, cp, end } => {
                     let (width_start, width_end) = (
-                        options
-                            .pressure_curve
-                            .apply(options.stroke_width, start.pressure),
-                        options
-                            .pressure_curve
-                            .apply(options.stroke_width, end.pressure),
+                        options.pressure_width(options.stroke_width, start.pressure),
+                        options.pressure_width(options.stroke_width, end.pressure),
                     );
 
                     let n_splits = 5;
@@ -354,12 +435,8 @@ impl Composer<SmoothOptions> for Segment {
                     end,
                 } => {
                     let (width_start, width_end) = (
-                        options
-                            .pressure_curve
-                            .apply(options.stroke_width, start.pressure),
-                        options
-                            .pressure_curve
-                            .apply(options.stroke_width, end.pressure),
+                        options.pressure_width(options.stroke_width, start.pressure),
+                        options.pressure_width(options.stroke_width, end.pressure),
                     );
 
                     let n_splits = 5;
@@ -399,13 +476,66 @@ impl Composer<SmoothOptions> for PenPath {
 
     fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &SmoothOptions) {
         cx.save().unwrap();
-        for segment in self.iter() {
-            segment.draw_composed(cx, options);
+        match options.line_cap {
+            LineCap::Round => {
+                for segment in self.iter() {
+                    segment.draw_composed(cx, options);
+                }
+            }
+            LineCap::Flat => {
+                if let Some(stroke_color) = options.stroke_color {
+                    let stroke_brush = cx.solid_brush(stroke_color.into());
+                    let stroke_style = piet::StrokeStyle::new()
+                        .line_cap(piet::LineCap::Butt)
+                        .line_join(piet::LineJoin::Round);
+
+                    cx.stroke_styled(
+                        self.to_kurbo_flat_caps(),
+                        &stroke_brush,
+                        options.stroke_width,
+                        &stroke_style,
+                    );
+                }
+            }
         }
         cx.restore().unwrap();
     }
 }
 
+impl PenPath {
+    /// The path as a single kurbo path, used for drawing it with flat caps
+    fn to_kurbo_flat_caps(&self) -> kurbo::BezPath {
+        let mut bez_path = kurbo::BezPath::new();
+
+        if let Some(first) = self.front() {
+            bez_path.move_to(first.start().pos.to_kurbo_point());
+        }
+
+        for segment in self.iter() {
+            match segment {
+                Segment::Dot { element } => {
+                    bez_path.line_to(element.pos.to_kurbo_point());
+                }
+                Segment::Line { end, .. } => {
+                    bez_path.line_to(end.pos.to_kurbo_point());
+                }
+                Segment::QuadBez { cp, end, .. } => {
+                    bez_path.quad_to(cp.to_kurbo_point(), end.pos.to_kurbo_point());
+                }
+                Segment::CubBez { cp1, cp2, end, .. } => {
+                    bez_path.curve_to(
+                        cp1.to_kurbo_point(),
+                        cp2.to_kurbo_point(),
+                        end.pos.to_kurbo_point(),
+                    );
+                }
+            }
+        }
+
+        bez_path
+    }
+}
+
 impl Composer<SmoothOptions> for crate::Shape {
     fn composed_bounds(&self, options: &SmoothOptions) -> AABB {
         match self {
@@ -415,6 +545,8 @@ impl Composer<SmoothOptions> for crate::Shape {
             crate::Shape::QuadraticBezier(quadbez) => quadbez.composed_bounds(options),
             crate::Shape::CubicBezier(cubbez) => cubbez.composed_bounds(options),
             crate::Shape::Segment(segment) => segment.composed_bounds(options),
+            crate::Shape::Polygon(polygon) => polygon.composed_bounds(options),
+            crate::Shape::Arrow(arrow) => arrow.composed_bounds(options),
         }
     }
 
@@ -426,6 +558,8 @@ impl Composer<SmoothOptions> for crate::Shape {
             crate::Shape::QuadraticBezier(quadbez) => quadbez.draw_composed(cx, options),
             crate::Shape::CubicBezier(cubbez) => cubbez.draw_composed(cx, options),
             crate::Shape::Segment(segment) => segment.draw_composed(cx, options),
+            crate::Shape::Polygon(polygon) => polygon.draw_composed(cx, options),
+            crate::Shape::Arrow(arrow) => arrow.draw_composed(cx, options),
         }
     }
 }
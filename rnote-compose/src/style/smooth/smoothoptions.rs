@@ -1,8 +1,26 @@
-use crate::style::PressureCurve;
+use crate::style::{LineStyle, PressureCurve, PressureResponse};
 use crate::Color;
 
 use serde::{Deserialize, Serialize};
 
+/// The cap at the ends of smooth pen paths
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename = "line_cap")]
+pub enum LineCap {
+    /// Round caps
+    #[serde(rename = "round")]
+    Round,
+    /// Flat caps, e.g. for highlighters. Pen paths with flat caps are drawn as a single path with constant width
+    #[serde(rename = "flat")]
+    Flat,
+}
+
+impl Default for LineCap {
+    fn default() -> Self {
+        Self::Round
+    }
+}
+
 /// Options for shapes that can be drawn smoothly
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default, rename = "smooth_options")]
@@ -19,6 +37,15 @@ pub struct SmoothOptions {
     /// Pressure curve
     #[serde(rename = "pressure_curve")]
     pub pressure_curve: PressureCurve,
+    /// Pressure response, mapping the reported pressure before the pressure curve is applied
+    #[serde(rename = "pressure_response")]
+    pub pressure_response: PressureResponse,
+    /// The cap of pen paths
+    #[serde(rename = "line_cap")]
+    pub line_cap: LineCap,
+    /// The line style of stroked shape outlines
+    #[serde(rename = "line_style")]
+    pub line_style: LineStyle,
 }
 
 impl Default for SmoothOptions {
@@ -28,6 +55,9 @@ impl Default for SmoothOptions {
             stroke_color: Some(Color::BLACK),
             fill_color: None,
             pressure_curve: PressureCurve::default(),
+            pressure_response: PressureResponse::default(),
+            line_cap: LineCap::default(),
+            line_style: LineStyle::default(),
         }
     }
 }
@@ -39,4 +69,10 @@ impl SmoothOptions {
     pub const WIDTH_MIN: f64 = 0.1;
     /// The max width
     pub const WIDTH_MAX: f64 = 1000.0;
+
+    /// The width for the given reported pressure, with the pressure response and curve applied
+    pub fn pressure_width(&self, width: f64, pressure: f64) -> f64 {
+        self.pressure_curve
+            .apply(width, self.pressure_response.apply(pressure))
+    }
 }
@@ -0,0 +1,176 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::Color;
+
+#[derive(
+    Debug,
+    Eq,
+    PartialEq,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    num_derive::FromPrimitive,
+    num_derive::ToPrimitive,
+)]
+#[serde(rename = "brush_texture_kind")]
+/// The kind of a brush texture
+pub enum BrushTextureKind {
+    /// A round stamp with fine grain, like a pencil on paper
+    #[serde(rename = "pencil_grain")]
+    PencilGrain = 0,
+    /// A round stamp with ragged edges and coarse grain, like charcoal
+    #[serde(rename = "charcoal")]
+    Charcoal,
+    /// A flat stamp with streaks along the stroke, like a drying marker
+    #[serde(rename = "marker_streaks")]
+    MarkerStreaks,
+}
+
+impl Default for BrushTextureKind {
+    fn default() -> Self {
+        Self::PencilGrain
+    }
+}
+
+impl TryFrom<u32> for BrushTextureKind {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        num_traits::FromPrimitive::from_u32(value).ok_or_else(|| {
+            anyhow::anyhow!(
+                "BrushTextureKind try_from::<u32>() for value {} failed",
+                value
+            )
+        })
+    }
+}
+
+/// The definition of a brush texture. The texture mask is generated from it deterministically,
+/// so only the definition needs to be stored and not the texture itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename = "brush_texture")]
+pub struct BrushTexture {
+    /// The kind of texture
+    #[serde(rename = "kind")]
+    pub kind: BrushTextureKind,
+    /// The width and height of the square texture mask in pixels
+    #[serde(rename = "resolution")]
+    pub resolution: u32,
+    /// The strength of the grain, between 0.0 and 1.0
+    #[serde(rename = "grain")]
+    pub grain: f64,
+    /// The seed for the grain
+    #[serde(rename = "seed")]
+    pub seed: u64,
+}
+
+impl Default for BrushTexture {
+    fn default() -> Self {
+        Self {
+            kind: BrushTextureKind::default(),
+            resolution: Self::RESOLUTION_DEFAULT,
+            grain: Self::GRAIN_DEFAULT,
+            seed: 0,
+        }
+    }
+}
+
+impl BrushTexture {
+    /// The default resolution
+    pub const RESOLUTION_DEFAULT: u32 = 64;
+    /// The max resolution
+    pub const RESOLUTION_MAX: u32 = 512;
+    /// The default grain
+    pub const GRAIN_DEFAULT: f64 = 0.6;
+
+    /// Generates the texture mask, with alpha values between 0.0 and 1.0 in row-major order.
+    /// The x axis of the mask is along the stroke direction, the y axis across it.
+    pub fn gen_mask(&self) -> Vec<f64> {
+        let resolution = self.clamped_resolution();
+        let grain = self.grain.clamp(0.0, 1.0);
+        let mut rng = crate::utils::new_rng_default_pcg64(Some(self.seed));
+
+        let fine_noise = (0..resolution * resolution)
+            .map(|_| rng.gen::<f64>())
+            .collect::<Vec<f64>>();
+        // Coarse noise on a grid of 8x8 cells, interpolated between the cells
+        let coarse_cells = (0..9 * 9).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>();
+        let coarse_noise = |x: f64, y: f64| -> f64 {
+            let (x, y) = (x * 8.0, y * 8.0);
+            let (x0, y0) = (x.floor().min(7.0), y.floor().min(7.0));
+            let (tx, ty) = (x - x0, y - y0);
+            let cell = |i: f64, j: f64| coarse_cells[(j as usize) * 9 + i as usize];
+
+            let top = cell(x0, y0) * (1.0 - tx) + cell(x0 + 1.0, y0) * tx;
+            let bottom = cell(x0, y0 + 1.0) * (1.0 - tx) + cell(x0 + 1.0, y0 + 1.0) * tx;
+            top * (1.0 - ty) + bottom * ty
+        };
+        // Streaks across the stroke, interpolated between 16 rows
+        let streak_rows = (0..17).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>();
+        let streak_noise = |y: f64| -> f64 {
+            let y = y * 16.0;
+            let y0 = y.floor().min(15.0);
+            let t = y - y0;
+            streak_rows[y0 as usize] * (1.0 - t) + streak_rows[y0 as usize + 1] * t
+        };
+
+        (0..resolution * resolution)
+            .map(|i| {
+                let (col, row) = (i % resolution, i / resolution);
+                // Normalized to 0.0..1.0 and -1.0..1.0
+                let (x, y) = (
+                    (col as f64 + 0.5) / resolution as f64,
+                    (row as f64 + 0.5) / resolution as f64,
+                );
+                let (u, v) = (x * 2.0 - 1.0, y * 2.0 - 1.0);
+                let radius = (u * u + v * v).sqrt();
+
+                match self.kind {
+                    BrushTextureKind::PencilGrain => {
+                        smoothstep(1.0, 0.8, radius) * (1.0 - grain * fine_noise[i])
+                    }
+                    BrushTextureKind::Charcoal => {
+                        let coarse = coarse_noise(x, y);
+                        let ragged_radius = radius + 0.3 * grain * (coarse - 0.5);
+
+                        smoothstep(1.0, 0.7, ragged_radius)
+                            * (1.0 - grain * (0.6 * coarse + 0.4 * fine_noise[i]))
+                    }
+                    BrushTextureKind::MarkerStreaks => {
+                        smoothstep(1.0, 0.85, v.abs())
+                            * smoothstep(1.0, 0.6, u.abs())
+                            * (1.0 - grain * streak_noise(y))
+                    }
+                }
+                .clamp(0.0, 1.0)
+            })
+            .collect()
+    }
+
+    /// Generates the texture as non-premultiplied RGBA pixels in the given color
+    pub fn gen_rgba(&self, color: Color) -> Vec<u8> {
+        let (r, g, b) = (
+            (color.r * 255.0).round() as u8,
+            (color.g * 255.0).round() as u8,
+            (color.b * 255.0).round() as u8,
+        );
+
+        self.gen_mask()
+            .into_iter()
+            .flat_map(|alpha| [r, g, b, (alpha * color.a * 255.0).round() as u8])
+            .collect()
+    }
+
+    /// The resolution of the generated texture
+    pub fn clamped_resolution(&self) -> usize {
+        self.resolution.clamp(1, Self::RESOLUTION_MAX) as usize
+    }
+}
+
+/// Hermite interpolation between the edges. The edges can be reversed for a falling step.
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
@@ -0,0 +1,185 @@
+mod brushtexture;
+mod stampedoptions;
+
+// Re-exports
+pub use brushtexture::{BrushTexture, BrushTextureKind};
+pub use stampedoptions::StampedOptions;
+
+use super::Composer;
+use crate::helpers::Vector2Helpers;
+use crate::penpath::{Element, Segment};
+use crate::shapes::{Line, ShapeBehaviour};
+use crate::PenPath;
+
+use p2d::bounding_volume::{BoundingVolume, AABB};
+
+/// The number of lines that curves are approximated with
+const N_SPLITS: i32 = 5;
+
+/// A single stamp of the texture
+#[derive(Debug, Clone, Copy)]
+struct Stamp {
+    pos: na::Vector2<f64>,
+    /// the angle of the stroke direction
+    angle: f64,
+    width: f64,
+}
+
+/// The stamps along the lines between the elements. The stamps are placed at the half distances,
+/// so that stamps of consecutive segments don't overlap at their joints.
+fn stamps_along(lines: &[(Element, Element)], options: &StampedOptions) -> Vec<Stamp> {
+    let mut stamps = vec![];
+
+    for (start, end) in lines {
+        let line_vec = end.pos - start.pos;
+        let length = line_vec.magnitude();
+        if length <= 0.0 {
+            continue;
+        }
+        let angle = line_vec[1].atan2(line_vec[0]);
+
+        let mut dist = options
+            .stamp_distance(options.pressure_width(options.stroke_width, start.pressure))
+            * 0.5;
+        while dist < length {
+            let t = dist / length;
+            let pressure = start.pressure + (end.pressure - start.pressure) * t;
+            let width = options.pressure_width(options.stroke_width, pressure);
+
+            stamps.push(Stamp {
+                pos: start.pos + line_vec * t,
+                angle,
+                width,
+            });
+
+            dist += options.stamp_distance(width);
+        }
+    }
+
+    stamps
+}
+
+/// The lines approximating the segment, with the interpolated elements at their ends
+fn segment_element_lines(segment: &Segment) -> Vec<(Element, Element)> {
+    let (start, end) = (segment.start(), segment.end());
+    let lines = segment.approx_with_lines(N_SPLITS);
+    let n_lines = lines.len() as f64;
+
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let (t_start, t_end) = (i as f64 / n_lines, (i + 1) as f64 / n_lines);
+
+            (
+                Element::new(
+                    line.start,
+                    start.pressure + (end.pressure - start.pressure) * t_start,
+                ),
+                Element::new(
+                    line.end,
+                    start.pressure + (end.pressure - start.pressure) * t_end,
+                ),
+            )
+        })
+        .collect()
+}
+
+/// The stamps for the segment
+fn segment_stamps(segment: &Segment, options: &StampedOptions) -> Vec<Stamp> {
+    match segment {
+        Segment::Dot { element } => vec![Stamp {
+            pos: element.pos,
+            angle: 0.0,
+            width: options.pressure_width(options.stroke_width, element.pressure),
+        }],
+        segment => stamps_along(&segment_element_lines(segment), options),
+    }
+}
+
+/// Draws the stamps with the texture of the options
+fn draw_stamps(cx: &mut impl piet::RenderContext, stamps: &[Stamp], options: &StampedOptions) {
+    let color = match options.stroke_color {
+        Some(color) => color,
+        None => return,
+    };
+    if stamps.is_empty() {
+        return;
+    }
+
+    let resolution = options.texture.clamped_resolution();
+    let image = match cx.make_image(
+        resolution,
+        resolution,
+        &options.texture.gen_rgba(color),
+        piet::ImageFormat::RgbaSeparate,
+    ) {
+        Ok(image) => image,
+        Err(e) => {
+            log::error!("make_image() failed while drawing stamps, Err {}", e);
+            return;
+        }
+    };
+
+    for stamp in stamps {
+        let half_width = stamp.width * 0.5;
+
+        cx.save().unwrap();
+        cx.transform(
+            kurbo::Affine::translate(stamp.pos.to_kurbo_vec()) * kurbo::Affine::rotate(stamp.angle),
+        );
+        cx.draw_image(
+            &image,
+            kurbo::Rect::new(-half_width, -half_width, half_width, half_width),
+            piet::InterpolationMode::Bilinear,
+        );
+        cx.restore().unwrap();
+    }
+}
+
+impl Composer<StampedOptions> for Line {
+    fn composed_bounds(&self, options: &StampedOptions) -> AABB {
+        // The stamps are rotated, so their diagonal needs to fit
+        self.bounds()
+            .loosened(options.stroke_width * std::f64::consts::FRAC_1_SQRT_2)
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &StampedOptions) {
+        // Lines have no pressure, so they are stamped with the full width
+        let stamps = stamps_along(
+            &[(Element::new(self.start, 1.0), Element::new(self.end, 1.0))],
+            options,
+        );
+
+        draw_stamps(cx, &stamps, options);
+    }
+}
+
+impl Composer<StampedOptions> for Segment {
+    fn composed_bounds(&self, options: &StampedOptions) -> AABB {
+        self.bounds()
+            .loosened(options.stroke_width * std::f64::consts::FRAC_1_SQRT_2)
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &StampedOptions) {
+        draw_stamps(cx, &segment_stamps(self, options), options);
+    }
+}
+
+impl Composer<StampedOptions> for PenPath {
+    fn composed_bounds(&self, options: &StampedOptions) -> AABB {
+        self.iter()
+            .map(|segment| segment.composed_bounds(options))
+            .fold(AABB::new_invalid(), |acc, x| acc.merged(&x))
+    }
+
+    fn draw_composed(&self, cx: &mut impl piet::RenderContext, options: &StampedOptions) {
+        // Collect the stamps of all segments, so that the texture image is only created once
+        let stamps = self
+            .iter()
+            .flat_map(|segment| segment_stamps(segment, options))
+            .collect::<Vec<Stamp>>();
+
+        draw_stamps(cx, &stamps, options);
+    }
+}
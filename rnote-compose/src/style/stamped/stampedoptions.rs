@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+use crate::style::smooth::SmoothOptions;
+use crate::style::{PressureCurve, PressureResponse};
+use crate::Color;
+
+use super::BrushTexture;
+
+/// The options for a stamped style, stamping a brush texture along the path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename = "stamped_options")]
+pub struct StampedOptions {
+    /// The width of the stamps
+    #[serde(rename = "stroke_width")]
+    pub stroke_width: f64,
+    /// The color of the stroke
+    #[serde(rename = "stroke_color")]
+    pub stroke_color: Option<Color>,
+    /// The texture which is stamped
+    #[serde(rename = "texture")]
+    pub texture: BrushTexture,
+    /// The spacing between the stamps, as ratio of the stamp width
+    #[serde(rename = "spacing")]
+    pub spacing: f64,
+    /// Pressure curve
+    #[serde(rename = "pressure_curve")]
+    pub pressure_curve: PressureCurve,
+    /// Pressure response, mapping the reported pressure before the pressure curve is applied
+    #[serde(rename = "pressure_response")]
+    pub pressure_response: PressureResponse,
+}
+
+impl Default for StampedOptions {
+    fn default() -> Self {
+        Self {
+            stroke_width: Self::WIDTH_DEFAULT,
+            stroke_color: Some(Color::BLACK),
+            texture: BrushTexture::default(),
+            spacing: Self::SPACING_DEFAULT,
+            pressure_curve: PressureCurve::default(),
+            pressure_response: PressureResponse::default(),
+        }
+    }
+}
+
+impl StampedOptions {
+    /// The default width
+    pub const WIDTH_DEFAULT: f64 = 6.0;
+    /// The min spacing ratio
+    pub const SPACING_MIN: f64 = 0.05;
+    /// The max spacing ratio
+    pub const SPACING_MAX: f64 = 2.0;
+    /// The default spacing ratio
+    pub const SPACING_DEFAULT: f64 = 0.2;
+    /// The min distance between stamps, avoiding excessive amounts of stamps for tiny widths
+    const MIN_DISTANCE: f64 = 0.25;
+
+    /// The width for the given reported pressure, with the pressure response and curve applied
+    pub fn pressure_width(&self, width: f64, pressure: f64) -> f64 {
+        self.pressure_curve
+            .apply(width, self.pressure_response.apply(pressure))
+    }
+
+    /// The options for a smooth outline with the same width and color. Used for shapes, which can't be stamped
+    pub fn outline_options(&self) -> SmoothOptions {
+        SmoothOptions {
+            stroke_width: self.stroke_width,
+            stroke_color: self.stroke_color,
+            ..SmoothOptions::default()
+        }
+    }
+
+    /// The distance between the stamps for the given stamp width
+    pub(super) fn stamp_distance(&self, width: f64) -> f64 {
+        (width * self.spacing.clamp(Self::SPACING_MIN, Self::SPACING_MAX)).max(Self::MIN_DISTANCE)
+    }
+}
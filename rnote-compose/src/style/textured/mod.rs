@@ -111,8 +111,7 @@ impl Composer<TexturedOptions> for Segment {
                 let mut options = options.clone();
 
                 options.stroke_width = options
-                    .pressure_curve
-                    .apply(options.stroke_width, (start.pressure + end.pressure) * 0.5);
+                    .pressure_width(options.stroke_width, (start.pressure + end.pressure) * 0.5);
 
                 line.draw_composed(cx, &options);
             }
@@ -125,8 +124,7 @@ impl Composer<TexturedOptions> for Segment {
                 let mut options = options.clone();
 
                 options.stroke_width = options
-                    .pressure_curve
-                    .apply(options.stroke_width, (start.pressure + end.pressure) * 0.5);
+                    .pressure_width(options.stroke_width, (start.pressure + end.pressure) * 0.5);
 
                 line.draw_composed(cx, &options);
             }
@@ -144,8 +142,7 @@ impl Composer<TexturedOptions> for Segment {
                 let mut options = options.clone();
 
                 options.stroke_width = options
-                    .pressure_curve
-                    .apply(options.stroke_width, (start.pressure + end.pressure) * 0.5);
+                    .pressure_width(options.stroke_width, (start.pressure + end.pressure) * 0.5);
 
                 line.draw_composed(cx, &options);
             }
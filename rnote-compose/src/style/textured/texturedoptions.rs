@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::style::PressureCurve;
+use crate::style::{PressureCurve, PressureResponse};
 use crate::Color;
 
 use super::textureddotsdistribution::TexturedDotsDistribution;
@@ -31,6 +31,9 @@ pub struct TexturedOptions {
     /// Pressure curve
     #[serde(rename = "pressure_curve")]
     pub pressure_curve: PressureCurve,
+    /// Pressure response, mapping the reported pressure before the pressure curve is applied
+    #[serde(rename = "pressure_response")]
+    pub pressure_response: PressureResponse,
 }
 
 impl Default for TexturedOptions {
@@ -43,6 +46,7 @@ impl Default for TexturedOptions {
             radii: Self::RADII_DEFAULT,
             distribution: TexturedDotsDistribution::default(),
             pressure_curve: PressureCurve::default(),
+            pressure_response: PressureResponse::default(),
         }
     }
 }
@@ -54,4 +58,10 @@ impl TexturedOptions {
     pub const DENSITY_DEFAULT: f64 = 5.0;
     /// Radii default
     pub const RADII_DEFAULT: na::Vector2<f64> = na::vector![2.0, 0.3];
+
+    /// The width for the given reported pressure, with the pressure response and curve applied
+    pub fn pressure_width(&self, width: f64, pressure: f64) -> f64 {
+        self.pressure_curve
+            .apply(width, self.pressure_response.apply(pressure))
+    }
 }
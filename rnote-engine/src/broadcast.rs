@@ -0,0 +1,560 @@
+use crate::document::{Background, PageNumbers};
+use crate::engine::EngineTask;
+use crate::store::{StoreSnapshot, StrokeKey};
+use crate::{render, DrawBehaviour, RnoteEngine};
+
+use anyhow::Context;
+use futures::channel::mpsc;
+use p2d::bounding_volume::{BoundingVolume, AABB};
+use rnote_compose::helpers::Vector2Helpers;
+use rnote_compose::shapes::ShapeBehaviour;
+use serde::{Deserialize, Serialize};
+use slotmap::SecondaryMap;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc as std_mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+use tungstenite::{Message, WebSocket};
+
+/// The messages sent to the subscribers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "broadcast_message")]
+pub enum BroadcastMessage {
+    /// The number of pages of the document changed
+    #[serde(rename = "page_count")]
+    PageCount(usize),
+    /// The page was rendered anew
+    #[serde(rename = "page")]
+    Page {
+        #[serde(rename = "index")]
+        index: usize,
+        /// The rendered page as svg
+        #[serde(rename = "svg")]
+        svg: String,
+    },
+}
+
+/// A page which is rendered for the subscribers
+#[derive(Debug, Clone)]
+pub struct BroadcastPage {
+    /// The index of the page
+    pub index: usize,
+    pub bounds: AABB,
+    /// The background, in page coords
+    pub background_svg: Option<render::Svg>,
+    /// The strokes intersecting the page, in the order they are exported
+    pub strokes: Vec<StrokeKey>,
+}
+
+impl BroadcastPage {
+    fn render(
+        self,
+        store_snapshot: &StoreSnapshot,
+        page_numbers: &PageNumbers,
+        pages_bounds_w_content: &[AABB],
+    ) -> anyhow::Result<(usize, String)> {
+        let page_bounds = AABB::new(
+            na::point![0.0, 0.0],
+            na::Point2::from(self.bounds.extents()),
+        );
+        let mut page_svg = self.background_svg.unwrap_or(render::Svg {
+            svg_data: String::new(),
+            bounds: page_bounds,
+        });
+
+        page_svg.merge([render::Svg::gen_with_piet_cairo_backend(
+            |piet_cx| {
+                piet_cx.transform(kurbo::Affine::translate(
+                    -self.bounds.mins.coords.to_kurbo_vec(),
+                ));
+
+                for key in self.strokes {
+                    if let Some(stroke) = store_snapshot.stroke_components.get(key) {
+                        stroke.draw(piet_cx, RnoteEngine::EXPORT_IMAGE_SCALE)?;
+                    }
+                }
+
+                page_numbers.draw(piet_cx, pages_bounds_w_content)
+            },
+            page_bounds,
+        )?]);
+
+        Ok((
+            self.index,
+            rnote_compose::utils::wrap_svg_root(
+                page_svg.svg_data.as_str(),
+                Some(page_svg.bounds),
+                Some(page_svg.bounds),
+                true,
+            ),
+        ))
+    }
+}
+
+/// The pages rendered anew, sent to the server thread
+#[derive(Debug)]
+struct PagesUpdate {
+    n_pages: usize,
+    pages: Vec<(usize, String)>,
+}
+
+/// The state the pages were last rendered with, to detect which pages changed
+#[derive(Debug)]
+struct BroadcastedState {
+    store_snapshot: Arc<StoreSnapshot>,
+    pages_bounds: Vec<AABB>,
+    background: Background,
+    page_numbers: PageNumbers,
+}
+
+/// Broadcasts the document read-only to subscribers, e.g. to students following a lecture in their browser.
+/// Serves a viewer page over HTTP, which subscribes over WebSocket on the same address to the pages rendered as svg.
+///
+/// The server runs on its own thread, which sends `EngineTask::BroadcastTick` regularly. The changed pages are then determined with `changed_pages()`
+/// and rendered on a worker thread with `render_pages()`. Connections are accepted and served on their own threads, so slow subscribers don't stall the others.
+/// There is no access control, so the number of connections being handled and of subscribers is capped to avoid exhausting the threads.
+#[derive(Debug)]
+pub struct BroadcastServer {
+    address: SocketAddr,
+    running: Arc<AtomicBool>,
+    rendering: Arc<AtomicBool>,
+    updates_tx: std_mpsc::Sender<PagesUpdate>,
+    last_broadcasted: Option<BroadcastedState>,
+}
+
+impl BroadcastServer {
+    /// The interval in which new subscribers are accepted and the engine is ticked
+    pub const TICK_INTERVAL: Duration = Duration::from_millis(500);
+    /// The timeout for reading the request of and writing to a subscriber, after which it is dropped
+    const TIMEOUT: Duration = Duration::from_secs(2);
+    /// The max size of the request headers
+    const REQUEST_MAX_SIZE: usize = 16 * 1024;
+    /// The max number of connections whose requests are handled at the same time. Further connections are closed right away
+    const PENDING_CONNECTIONS_MAX: usize = 8;
+    /// The max number of subscribers. Further subscribers are closed right away
+    const SUBSCRIBERS_MAX: usize = 64;
+    /// The page served to browsers. The pages are shown as images, so scripts embedded in the svg's are not executed
+    const VIEWER_HTML: &'static str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Rnote</title>
+<style>
+body { margin: 0; padding: 16px; background: #deddda; }
+.page { display: block; margin: 0 auto 16px auto; width: 100%; max-width: 1000px; height: auto; box-shadow: 0 2px 8px rgba(0, 0, 0, 0.3); }
+</style>
+</head>
+<body>
+<script>
+const pages = [];
+const socket = new WebSocket(`ws://${location.host}`);
+socket.onmessage = (event) => {
+  const message = JSON.parse(event.data);
+  if (message.page_count !== undefined) {
+    while (pages.length > message.page_count) {
+      const page = pages.pop();
+      URL.revokeObjectURL(page.src);
+      page.remove();
+    }
+    while (pages.length < message.page_count) {
+      const page = document.createElement("img");
+      page.className = "page";
+      page.alt = `Page ${pages.length + 1}`;
+      document.body.appendChild(page);
+      pages.push(page);
+    }
+  } else if (message.page !== undefined) {
+    const page = pages[message.page.index];
+    if (page) {
+      if (page.src) {
+        URL.revokeObjectURL(page.src);
+      }
+      page.src = URL.createObjectURL(new Blob([message.page.svg], { type: "image/svg+xml" }));
+    }
+  }
+};
+</script>
+</body>
+</html>
+"#;
+
+    /// Starts serving on the address, e.g. `0.0.0.0:8080`. Use port 0 to pick a free port, the bound address is returned by `address()`.
+    pub fn start(
+        address: &str,
+        tasks_tx: mpsc::UnboundedSender<EngineTask>,
+    ) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind(address)
+            .with_context(|| format!("binding broadcast server to `{}` failed", address))?;
+        listener
+            .set_nonblocking(true)
+            .context("setting the broadcast listener nonblocking failed")?;
+        let address = listener.local_addr()?;
+        let running = Arc::new(AtomicBool::new(true));
+        let (updates_tx, updates_rx) = std_mpsc::channel::<PagesUpdate>();
+
+        std::thread::spawn({
+            let running = Arc::clone(&running);
+            move || Self::run_server(listener, updates_rx, &running, &tasks_tx)
+        });
+
+        Ok(Self {
+            address,
+            running,
+            rendering: Arc::new(AtomicBool::new(false)),
+            updates_tx,
+            last_broadcasted: None,
+        })
+    }
+
+    /// The address the server is bound to
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Wether pages are currently rendered. No new pages should be rendered until it is finished
+    pub fn is_rendering(&self) -> bool {
+        self.rendering.load(Ordering::Relaxed)
+    }
+
+    /// The indices of the pages which changed since they were last rendered. None when nothing changed.
+    /// Only the pointers of the stroke components are compared, so this is cheap to call on every tick.
+    pub fn changed_pages(
+        &self,
+        store_snapshot: &StoreSnapshot,
+        pages_bounds: &[AABB],
+        background: &Background,
+        page_numbers: &PageNumbers,
+    ) -> Option<Vec<usize>> {
+        let last = match &self.last_broadcasted {
+            Some(last)
+                if last.pages_bounds == pages_bounds
+                    && &last.background == background
+                    && &last.page_numbers == page_numbers =>
+            {
+                last
+            }
+            _ => return Some((0..pages_bounds.len()).collect()),
+        };
+        let changed_bounds = Self::changed_strokes_bounds(&last.store_snapshot, store_snapshot);
+        if changed_bounds.is_empty() {
+            return None;
+        }
+
+        Some(
+            pages_bounds
+                .iter()
+                .enumerate()
+                .filter(|(_, page_bounds)| {
+                    changed_bounds
+                        .iter()
+                        .any(|bounds| bounds.intersects(page_bounds))
+                })
+                .map(|(i, _)| i)
+                .collect(),
+        )
+    }
+
+    /// The old and new bounds of the strokes which were added, changed, reordered, trashed or removed
+    fn changed_strokes_bounds(last: &StoreSnapshot, current: &StoreSnapshot) -> Vec<AABB> {
+        if Arc::ptr_eq(&last.stroke_components, &current.stroke_components)
+            && Arc::ptr_eq(&last.trash_components, &current.trash_components)
+            && Arc::ptr_eq(&last.chrono_components, &current.chrono_components)
+        {
+            return vec![];
+        }
+        let mut changed_bounds = vec![];
+
+        for (key, stroke) in current.stroke_components.iter() {
+            let last_stroke = last.stroke_components.get(key);
+            let unchanged = matches!(last_stroke, Some(last_stroke) if Arc::ptr_eq(last_stroke, stroke))
+                && Self::component_ptr_eq(&last.trash_components, &current.trash_components, key)
+                && Self::component_ptr_eq(&last.chrono_components, &current.chrono_components, key);
+
+            if !unchanged {
+                changed_bounds.push(stroke.bounds());
+                changed_bounds.extend(last_stroke.map(|last_stroke| last_stroke.bounds()));
+            }
+        }
+        changed_bounds.extend(
+            last.stroke_components
+                .iter()
+                .filter(|(key, _)| !current.stroke_components.contains_key(*key))
+                .map(|(_, last_stroke)| last_stroke.bounds()),
+        );
+
+        changed_bounds
+    }
+
+    fn component_ptr_eq<T>(
+        last: &SecondaryMap<StrokeKey, Arc<T>>,
+        current: &SecondaryMap<StrokeKey, Arc<T>>,
+        key: StrokeKey,
+    ) -> bool {
+        match (last.get(key), current.get(key)) {
+            (Some(last), Some(current)) => Arc::ptr_eq(last, current),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// Renders the pages on a worker thread and sends them to the subscribers.
+    /// The state is recorded to determine the changed pages on the next tick, see `changed_pages()`
+    pub fn render_pages(
+        &mut self,
+        pages: Vec<BroadcastPage>,
+        pages_bounds: Vec<AABB>,
+        pages_bounds_w_content: Vec<AABB>,
+        store_snapshot: Arc<StoreSnapshot>,
+        background: &Background,
+        page_numbers: &PageNumbers,
+    ) {
+        let n_pages = pages_bounds.len();
+        self.last_broadcasted = Some(BroadcastedState {
+            store_snapshot: Arc::clone(&store_snapshot),
+            pages_bounds,
+            background: background.clone(),
+            page_numbers: page_numbers.clone(),
+        });
+        let page_numbers = page_numbers.clone();
+        let updates_tx = self.updates_tx.clone();
+        let rendering = Arc::clone(&self.rendering);
+
+        rendering.store(true, Ordering::Relaxed);
+        rayon::spawn(move || {
+            let result = pages
+                .into_iter()
+                .map(|page| page.render(&store_snapshot, &page_numbers, &pages_bounds_w_content))
+                .collect::<anyhow::Result<Vec<(usize, String)>>>();
+
+            match result {
+                Ok(pages) => {
+                    // The server has stopped when sending fails
+                    let _ = updates_tx.send(PagesUpdate { n_pages, pages });
+                }
+                Err(e) => log::error!("rendering pages for the broadcast failed with Err {}", e),
+            }
+            rendering.store(false, Ordering::Relaxed);
+        });
+    }
+
+    /// Runs until the server is dropped
+    fn run_server(
+        listener: TcpListener,
+        updates_rx: std_mpsc::Receiver<PagesUpdate>,
+        running: &AtomicBool,
+        tasks_tx: &mpsc::UnboundedSender<EngineTask>,
+    ) {
+        let (new_subscribers_tx, new_subscribers_rx) =
+            std_mpsc::channel::<WebSocket<BufferedStream>>();
+        let mut subscribers: Vec<std_mpsc::Sender<Arc<Vec<BroadcastMessage>>>> = vec![];
+        let mut pages: Vec<String> = vec![];
+        let pending_connections = Arc::new(AtomicUsize::new(0));
+
+        while running.load(Ordering::Relaxed) {
+            loop {
+                match listener.accept() {
+                    Ok((stream, peer_address)) => {
+                        if pending_connections.load(Ordering::Relaxed)
+                            >= Self::PENDING_CONNECTIONS_MAX
+                        {
+                            log::debug!("closing broadcast connection from {}, too many pending connections", peer_address);
+                            continue;
+                        }
+                        pending_connections.fetch_add(1, Ordering::Relaxed);
+                        let pending_connections = Arc::clone(&pending_connections);
+                        let new_subscribers_tx = new_subscribers_tx.clone();
+
+                        std::thread::spawn(move || {
+                            match Self::handle_connection(stream) {
+                                Ok(Some(subscriber)) => {
+                                    let _ = new_subscribers_tx.send(subscriber);
+                                }
+                                Ok(None) => {}
+                                Err(e) => {
+                                    log::warn!(
+                                        "handling broadcast connection failed with Err {}",
+                                        e
+                                    )
+                                }
+                            }
+                            pending_connections.fetch_sub(1, Ordering::Relaxed);
+                        });
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                    Err(e) => {
+                        log::error!("accepting broadcast connection failed with Err {}", e);
+                        break;
+                    }
+                }
+            }
+
+            while let Ok(mut subscriber) = new_subscribers_rx.try_recv() {
+                // Subscribers which can't be written to anymore are only removed on the next update, so they are still counted here
+                if subscribers.len() >= Self::SUBSCRIBERS_MAX {
+                    log::debug!("closing broadcast subscriber, too many subscribers");
+                    let _ = subscriber.close(None);
+                    continue;
+                }
+                let mut messages = vec![BroadcastMessage::PageCount(pages.len())];
+                messages.extend(pages.iter().enumerate().map(|(index, svg)| {
+                    BroadcastMessage::Page {
+                        index,
+                        svg: svg.clone(),
+                    }
+                }));
+                subscribers.push(Self::spawn_subscriber(subscriber, messages));
+            }
+
+            while let Ok(update) = updates_rx.try_recv() {
+                let mut messages = vec![];
+                if update.n_pages != pages.len() {
+                    pages.resize(update.n_pages, String::new());
+                    messages.push(BroadcastMessage::PageCount(update.n_pages));
+                }
+                for (index, svg) in update.pages {
+                    if let Some(page) = pages.get_mut(index) {
+                        *page = svg.clone();
+                        messages.push(BroadcastMessage::Page { index, svg });
+                    }
+                }
+                let messages = Arc::new(messages);
+
+                // The subscriber threads end when the subscribers can't be written to anymore
+                subscribers = subscribers
+                    .into_iter()
+                    .filter(|subscriber| subscriber.send(Arc::clone(&messages)).is_ok())
+                    .collect();
+            }
+
+            if tasks_tx.unbounded_send(EngineTask::BroadcastTick).is_err() {
+                break;
+            }
+            std::thread::sleep(Self::TICK_INTERVAL);
+        }
+    }
+
+    /// Spawns the thread writing to the subscriber. Returns the sender for the messages
+    fn spawn_subscriber(
+        mut subscriber: WebSocket<BufferedStream>,
+        initial_messages: Vec<BroadcastMessage>,
+    ) -> std_mpsc::Sender<Arc<Vec<BroadcastMessage>>> {
+        let (messages_tx, messages_rx) = std_mpsc::channel::<Arc<Vec<BroadcastMessage>>>();
+        let _ = messages_tx.send(Arc::new(initial_messages));
+
+        std::thread::spawn(move || {
+            for messages in messages_rx.iter() {
+                if let Err(e) = Self::send(&mut subscriber, &messages) {
+                    log::debug!(
+                        "dropping broadcast subscriber, sending failed with Err {}",
+                        e
+                    );
+                    break;
+                }
+            }
+            let _ = subscriber.close(None);
+        });
+
+        messages_tx
+    }
+
+    /// Upgrades WebSocket requests to subscribers. Other requests are answered with the viewer page.
+    fn handle_connection(
+        mut stream: TcpStream,
+    ) -> anyhow::Result<Option<WebSocket<BufferedStream>>> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(Self::TIMEOUT))?;
+        stream.set_write_timeout(Some(Self::TIMEOUT))?;
+
+        // Read the complete request headers
+        let mut request = Vec::<u8>::new();
+        let mut buf = [0; 4096];
+        while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+            let len = stream.read(&mut buf)?;
+            if len == 0 {
+                return Err(anyhow::anyhow!(
+                    "connection closed before the request was complete"
+                ));
+            }
+            request.extend_from_slice(&buf[..len]);
+            if request.len() > Self::REQUEST_MAX_SIZE {
+                return Err(anyhow::anyhow!("request exceeds the maximum size"));
+            }
+        }
+
+        if String::from_utf8_lossy(&request)
+            .to_lowercase()
+            .contains("upgrade: websocket")
+        {
+            // The handshake reads the request from the buffer first
+            let subscriber = tungstenite::accept(BufferedStream {
+                buffer: request,
+                pos: 0,
+                stream,
+            })
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+            Ok(Some(subscriber))
+        } else {
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                Self::VIEWER_HTML.len(),
+                Self::VIEWER_HTML
+            )?;
+
+            Ok(None)
+        }
+    }
+
+    fn send(
+        subscriber: &mut WebSocket<BufferedStream>,
+        messages: &[BroadcastMessage],
+    ) -> anyhow::Result<()> {
+        for message in messages {
+            subscriber.write_message(Message::Text(serde_json::to_string(message)?))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for BroadcastServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+/// A stream which yields the already read bytes first
+#[derive(Debug)]
+struct BufferedStream {
+    buffer: Vec<u8>,
+    pos: usize,
+    stream: TcpStream,
+}
+
+impl Read for BufferedStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos < self.buffer.len() {
+            let len = (self.buffer.len() - self.pos).min(buf.len());
+            buf[..len].copy_from_slice(&self.buffer[self.pos..self.pos + len]);
+            self.pos += len;
+
+            Ok(len)
+        } else {
+            self.stream.read(buf)
+        }
+    }
+}
+
+impl Write for BufferedStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.stream.flush()
+    }
+}
@@ -3,6 +3,84 @@ use p2d::bounding_volume::AABB;
 use rnote_compose::helpers::AABBHelpers;
 use serde::{Deserialize, Serialize};
 
+/// The configurable zoom behaviour of the camera
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename = "zoom_config")]
+pub struct ZoomConfig {
+    /// The minimum zoom
+    #[serde(rename = "zoom_min")]
+    zoom_min: f64,
+    /// The maximum zoom
+    #[serde(rename = "zoom_max")]
+    zoom_max: f64,
+    /// The relative zoom change of one zoom step, e.g. when zooming in / out or for one unit of the scroll delta
+    #[serde(rename = "zoom_step")]
+    pub zoom_step: f64,
+    /// Wether zooms close to the default zoom (100%) snap to it
+    #[serde(rename = "snap_to_default")]
+    pub snap_to_default: bool,
+}
+
+impl Default for ZoomConfig {
+    fn default() -> Self {
+        Self {
+            zoom_min: Self::ZOOM_MIN_DEFAULT,
+            zoom_max: Self::ZOOM_MAX_DEFAULT,
+            zoom_step: Self::ZOOM_STEP_DEFAULT,
+            snap_to_default: false,
+        }
+    }
+}
+
+impl ZoomConfig {
+    /// The lowest zoom that can be configured as minimum
+    pub const ZOOM_LIMIT_LOWEST: f64 = 0.05;
+    /// The highest zoom that can be configured as maximum
+    pub const ZOOM_LIMIT_HIGHEST: f64 = 20.0;
+    pub const ZOOM_MIN_DEFAULT: f64 = 0.2;
+    pub const ZOOM_MAX_DEFAULT: f64 = 6.0;
+    pub const ZOOM_STEP_DEFAULT: f64 = 0.1;
+    /// The relative distance to the default zoom in which zooms snap to it
+    pub const SNAP_THRESHOLD: f64 = 0.05;
+
+    /// The minimum zoom
+    pub fn zoom_min(&self) -> f64 {
+        self.zoom_min
+    }
+
+    /// The maximum zoom
+    pub fn zoom_max(&self) -> f64 {
+        self.zoom_max
+    }
+
+    /// Sets the zoom limits. They are clamped to the lowest and highest configurable limits, and swapped if the minimum is larger than the maximum
+    pub fn set_zoom_limits(&mut self, zoom_min: f64, zoom_max: f64) {
+        let zoom_min = zoom_min.clamp(Self::ZOOM_LIMIT_LOWEST, Self::ZOOM_LIMIT_HIGHEST);
+        let zoom_max = zoom_max.clamp(Self::ZOOM_LIMIT_LOWEST, Self::ZOOM_LIMIT_HIGHEST);
+
+        self.zoom_min = zoom_min.min(zoom_max);
+        self.zoom_max = zoom_max.max(zoom_min);
+    }
+
+    /// Clamps the zoom to the limits
+    pub fn clamp_zoom(&self, zoom: f64) -> f64 {
+        zoom.clamp(self.zoom_min, self.zoom_max)
+    }
+
+    /// Clamps the zoom to the limits and snaps it to the default zoom, if enabled and close enough
+    pub fn snap_zoom(&self, zoom: f64) -> f64 {
+        let zoom = self.clamp_zoom(zoom);
+
+        if self.snap_to_default
+            && ((zoom - Camera::ZOOM_DEFAULT) / Camera::ZOOM_DEFAULT).abs() < Self::SNAP_THRESHOLD
+        {
+            self.clamp_zoom(Camera::ZOOM_DEFAULT)
+        } else {
+            zoom
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default, rename = "camera")]
 pub struct Camera {
@@ -19,6 +97,13 @@ pub struct Camera {
     #[serde(rename = "temporary_zoom")]
     temporary_zoom: f64,
 
+    /// The rotation angle in radians, around the center of the surface
+    #[serde(rename = "rotation")]
+    rotation: f64,
+    /// The zoom limits, step and snapping
+    #[serde(rename = "zoom_config")]
+    zoom_config: ZoomConfig,
+
     /// The scale factor of the surface, usually 1.0 or 2.0 for high-dpi screens. (Could become a non-integer value in the future, so it is stored as float.)
     #[serde(rename = "scale_factor")]
     pub scale_factor: f64,
@@ -31,15 +116,22 @@ impl Default for Camera {
             size: na::vector![800.0, 600.0],
             zoom: 1.0,
             temporary_zoom: 1.0,
+            rotation: 0.0,
+            zoom_config: ZoomConfig::default(),
             scale_factor: 1.0,
         }
     }
 }
 
 impl Camera {
-    pub const ZOOM_MIN: f64 = 0.2;
-    pub const ZOOM_MAX: f64 = 6.0;
     pub const ZOOM_DEFAULT: f64 = 1.0;
+    pub const ROTATION_DEFAULT: f64 = 0.0;
+    /// The margin around bounds that are zoomed to fit, in surface coords
+    pub const ZOOM_FIT_MARGIN: f64 = 24.0;
+    /// The margin at the surface border in which the camera auto-scrolls while drawing, in surface coords
+    pub const AUTOSCROLL_MARGIN: f64 = 48.0;
+    /// The max auto-scroll distance per step in surface coords, reached at the surface border
+    pub const AUTOSCROLL_MAX_STEP: f64 = 12.0;
 
     pub fn with_zoom(mut self, zoom: f64) -> Self {
         self.set_zoom(zoom);
@@ -55,14 +147,35 @@ impl Camera {
         self
     }
 
+    pub fn with_rotation(mut self, rotation: f64) -> Self {
+        self.set_rotation(rotation);
+        self
+    }
+
     /// the permanent zoom
     pub fn zoom(&self) -> f64 {
         self.zoom
     }
 
-    /// sets the zoom
+    /// sets the zoom, clamped to the zoom limits
     pub fn set_zoom(&mut self, zoom: f64) {
-        self.zoom = zoom.clamp(Self::ZOOM_MIN, Self::ZOOM_MAX)
+        self.zoom = self.zoom_config.clamp_zoom(zoom)
+    }
+
+    /// The zoom config
+    pub fn zoom_config(&self) -> &ZoomConfig {
+        &self.zoom_config
+    }
+
+    /// Sets the zoom config. The zoom and temporary zoom are clamped to the new limits
+    pub fn set_zoom_config(&mut self, zoom_config: ZoomConfig) {
+        self.zoom_config = zoom_config;
+        // Sanitize the limits, they might come from a deserialized config
+        self.zoom_config
+            .set_zoom_limits(zoom_config.zoom_min, zoom_config.zoom_max);
+
+        self.set_zoom(self.zoom);
+        self.set_temporary_zoom(self.temporary_zoom);
     }
 
     /// The temporary zoom, supposed to be overlayed on the surface when zooming with a timeout
@@ -72,8 +185,68 @@ impl Camera {
 
     /// sets the temporary zoom
     pub fn set_temporary_zoom(&mut self, temporary_zoom: f64) {
-        self.temporary_zoom =
-            temporary_zoom.clamp(Camera::ZOOM_MIN / self.zoom, Camera::ZOOM_MAX / self.zoom)
+        self.temporary_zoom = temporary_zoom.clamp(
+            self.zoom_config.zoom_min / self.zoom,
+            self.zoom_config.zoom_max / self.zoom,
+        )
+    }
+
+    /// The rotation angle in radians, in the range [0, 2π)
+    pub fn rotation(&self) -> f64 {
+        self.rotation
+    }
+
+    /// sets the rotation angle in radians. It is wrapped into the range [0, 2π)
+    pub fn set_rotation(&mut self, rotation: f64) {
+        self.rotation = rotation.rem_euclid(std::f64::consts::TAU);
+    }
+
+    /// The zoom at which the given bounds in document coords fit into the surface, including the margin.
+    /// The rotation is taken into account
+    pub fn zoom_fit_bounds(&self, bounds: AABB) -> f64 {
+        let (sin, cos) = self.rotation.sin_cos();
+        let extents = bounds.extents();
+        let rotated_extents = na::vector![
+            extents[0] * cos.abs() + extents[1] * sin.abs(),
+            extents[0] * sin.abs() + extents[1] * cos.abs()
+        ];
+        let available_size = (self.size - na::Vector2::from_element(2.0 * Self::ZOOM_FIT_MARGIN))
+            .map(|size| size.max(1.0));
+
+        (available_size[0] / rotated_extents[0])
+            .min(available_size[1] / rotated_extents[1])
+            .clamp(self.zoom_config.zoom_min, self.zoom_config.zoom_max)
+    }
+
+    /// Zooms and moves the camera so that the given bounds in document coords fit into the surface, centered.
+    /// The temporary zoom is reset
+    pub fn zoom_fit_to_bounds(&mut self, bounds: AABB) {
+        let zoom = self.zoom_fit_bounds(bounds);
+
+        self.set_temporary_zoom(1.0);
+        self.set_zoom(zoom);
+        self.offset = bounds.center().coords * self.zoom - self.size * 0.5;
+    }
+
+    /// The offset delta for auto-scrolling while the pen is at the given position in surface coords.
+    /// Grows the closer the position is to the surface border, zero outside of the auto-scroll margin
+    pub fn autoscroll_delta(&self, surface_pos: na::Vector2<f64>) -> na::Vector2<f64> {
+        let margin = Self::AUTOSCROLL_MARGIN.min(self.size.min() * 0.25);
+        if margin <= 0.0 {
+            return na::Vector2::zeros();
+        }
+
+        na::Vector2::from_fn(|i, _| {
+            let depth = if surface_pos[i] < margin {
+                surface_pos[i] - margin
+            } else if surface_pos[i] > self.size[i] - margin {
+                surface_pos[i] - (self.size[i] - margin)
+            } else {
+                0.0
+            };
+
+            (depth / margin).clamp(-1.0, 1.0) * Self::AUTOSCROLL_MAX_STEP
+        })
     }
 
     /// The total zoom of the camera, including the temporary zoom
@@ -86,24 +259,53 @@ impl Camera {
         self.zoom * self.temporary_zoom * self.scale_factor
     }
 
-    /// the viewport in document coordinate space
+    /// the viewport in document coordinate space. When the camera is rotated, it contains the entire rotated surface
     pub fn viewport(&self) -> AABB {
-        let inv_zoom = 1.0 / self.total_zoom();
+        if self.rotation == 0.0 {
+            let inv_zoom = 1.0 / self.total_zoom();
 
-        AABB::new_positive(
-            na::Point2::from(self.offset * inv_zoom),
-            na::Point2::from((self.offset + self.size) * inv_zoom),
-        )
+            return AABB::new_positive(
+                na::Point2::from(self.offset * inv_zoom),
+                na::Point2::from((self.offset + self.size) * inv_zoom),
+            );
+        }
+
+        self.transform_inv_bounds(AABB::new(na::point![0.0, 0.0], na::Point2::from(self.size)))
     }
 
     /// from document coords -> surface coords
     pub fn transform_bounds(&self, bounds: AABB) -> AABB {
-        bounds.scale(self.total_zoom()).translate(-self.offset)
+        if self.rotation == 0.0 {
+            return bounds.scale(self.total_zoom()).translate(-self.offset);
+        }
+
+        Self::transform_bounds_w_affine(bounds, self.transform())
     }
 
     /// from surface coords -> document coords
     pub fn transform_inv_bounds(&self, bounds: AABB) -> AABB {
-        bounds.translate(self.offset).scale(1.0 / self.total_zoom())
+        if self.rotation == 0.0 {
+            return bounds.translate(self.offset).scale(1.0 / self.total_zoom());
+        }
+
+        Self::transform_bounds_w_affine(bounds, self.transform().inverse())
+    }
+
+    /// The bounds containing the transformed corners of the given bounds
+    fn transform_bounds_w_affine(bounds: AABB, transform: na::Affine2<f64>) -> AABB {
+        let corners = [
+            bounds.mins,
+            na::point![bounds.maxs[0], bounds.mins[1]],
+            bounds.maxs,
+            na::point![bounds.mins[0], bounds.maxs[1]],
+        ];
+
+        corners
+            .iter()
+            .map(|&corner| transform * corner)
+            .fold(AABB::new_invalid(), |acc, corner| {
+                AABB::new(acc.mins.inf(&corner), acc.maxs.sup(&corner))
+            })
     }
 
     /// The transform from document coords -> surface coords
@@ -111,24 +313,37 @@ impl Camera {
     pub fn transform(&self) -> na::Affine2<f64> {
         let total_zoom = self.total_zoom();
 
+        let surface_center = self.size * 0.5;
+
         na::try_convert(
-            // LHS is applied onto RHS, so the order is scaling by zoom -> Translation by offset
-            na::Translation2::from(-self.offset).to_homogeneous()
+            // LHS is applied onto RHS, so the order is scaling by zoom -> Translation by offset -> Rotation around the surface center
+            na::Translation2::from(surface_center).to_homogeneous()
+                * na::Rotation2::new(self.rotation).to_homogeneous()
+                * na::Translation2::from(-surface_center).to_homogeneous()
+                * na::Translation2::from(-self.offset).to_homogeneous()
                 * na::Scale2::from(na::Vector2::from_element(total_zoom)).to_homogeneous(),
         )
         .unwrap()
     }
 
     // The gsk transform for the GTK snapshot func
-    // GTKs transformations are applied on its coordinate system, so we need to reverse the order (translate, then scale)
+    // GTKs transformations are applied on its coordinate system, so we need to reverse the order (rotate, translate, then scale)
     // To have the inverse, call .invert()
     pub fn transform_for_gtk_snapshot(&self) -> gsk::Transform {
         let total_zoom = self.total_zoom();
+        let surface_center = self.size * 0.5;
 
         gsk::Transform::new()
             .translate(&graphene::Point::new(
-                -self.offset[0] as f32,
-                -self.offset[1] as f32,
+                surface_center[0] as f32,
+                surface_center[1] as f32,
+            ))
+            .unwrap()
+            .rotate(self.rotation.to_degrees() as f32)
+            .unwrap()
+            .translate(&graphene::Point::new(
+                (-surface_center[0] - self.offset[0]) as f32,
+                (-surface_center[1] - self.offset[1]) as f32,
             ))
             .unwrap()
             .scale(total_zoom as f32, total_zoom as f32)
@@ -157,6 +372,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn transform_rotated_vec() {
+        let offset = na::vector![4.0, 2.0];
+        let size = na::vector![20.0, 10.0];
+        let camera = Camera::default()
+            .with_zoom(2.0)
+            .with_offset(offset)
+            .with_size(size)
+            .with_rotation(std::f64::consts::FRAC_PI_2);
+
+        // The document point in the center of the surface stays in place when rotating
+        let center_on_doc = na::Point2::from((offset + size * 0.5) / 2.0);
+        assert_relative_eq!(
+            (camera.transform() * center_on_doc).coords,
+            size * 0.5,
+            epsilon = 1e-9
+        );
+
+        // Input coordinates are mapped back through the inverse transform
+        let p0 = na::point![3.0, 7.0];
+        assert_relative_eq!(
+            camera.transform().inverse() * (camera.transform() * p0),
+            p0,
+            epsilon = 1e-9
+        );
+    }
+
     #[test]
     fn viewport() {
         let zoom = 2.0;
@@ -175,4 +417,34 @@ mod tests {
         assert_relative_eq!(viewport.mins, mins);
         assert_relative_eq!(viewport.maxs, maxs);
     }
+
+    #[test]
+    fn zoom_fit_to_bounds() {
+        let margin = na::Vector2::from_element(2.0 * Camera::ZOOM_FIT_MARGIN);
+        let mut camera = Camera::default().with_size(na::vector![800.0, 600.0] + margin);
+        let bounds = AABB::new(na::point![100.0, 50.0], na::point![500.0, 150.0]);
+
+        camera.zoom_fit_to_bounds(bounds);
+
+        assert_relative_eq!(camera.zoom(), 2.0);
+        assert_relative_eq!(camera.viewport().center(), bounds.center());
+    }
+
+    #[test]
+    fn autoscroll_delta() {
+        let camera = Camera::default().with_size(na::vector![800.0, 600.0]);
+
+        assert_relative_eq!(
+            camera.autoscroll_delta(na::vector![400.0, 300.0]),
+            na::Vector2::zeros()
+        );
+        assert_relative_eq!(
+            camera.autoscroll_delta(na::vector![0.0, 300.0]),
+            na::vector![-Camera::AUTOSCROLL_MAX_STEP, 0.0]
+        );
+        assert_relative_eq!(
+            camera.autoscroll_delta(na::vector![400.0, 600.0 - Camera::AUTOSCROLL_MARGIN * 0.5]),
+            na::vector![0.0, Camera::AUTOSCROLL_MAX_STEP * 0.5]
+        );
+    }
 }
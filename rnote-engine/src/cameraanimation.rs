@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+/// A single step of a running camera animation
+#[derive(Debug, Clone, Copy)]
+pub struct CameraAnimationStep {
+    /// The total zoom of the camera at this step
+    pub zoom: f64,
+    /// The camera offset at this step, in surface coords
+    pub offset: na::Vector2<f64>,
+    /// Wether this was the last step, the camera then is at the target
+    pub finished: bool,
+}
+
+/// Glides the camera smoothly to a target center and zoom. Stepped with the frame times of the frame clock.
+#[derive(Debug, Clone)]
+pub struct CameraAnimation {
+    start_center: na::Vector2<f64>,
+    start_zoom: f64,
+    target_center: na::Vector2<f64>,
+    target_zoom: f64,
+    duration: Duration,
+    /// The frame time of the first step, in microseconds. Set when the animation is stepped the first time
+    start_time: Option<i64>,
+}
+
+impl CameraAnimation {
+    /// The default duration of an animation
+    pub const DURATION_DEFAULT: Duration = Duration::from_millis(250);
+
+    /// A new animation from the start center and zoom to the target. The centers are in document coords.
+    pub fn new(
+        start_center: na::Vector2<f64>,
+        start_zoom: f64,
+        target_center: na::Vector2<f64>,
+        target_zoom: f64,
+    ) -> Self {
+        Self {
+            start_center,
+            start_zoom,
+            target_center,
+            target_zoom,
+            duration: Self::DURATION_DEFAULT,
+            start_time: None,
+        }
+    }
+
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Steps the animation to the given frame time in microseconds, for a camera with the given surface size.
+    pub fn step(&mut self, frame_time: i64, surface_size: na::Vector2<f64>) -> CameraAnimationStep {
+        let start_time = *self.start_time.get_or_insert(frame_time);
+        let elapsed = Duration::from_micros((frame_time - start_time).max(0) as u64);
+
+        let progress = if self.duration.is_zero() {
+            1.0
+        } else {
+            (elapsed.as_secs_f64() / self.duration.as_secs_f64()).min(1.0)
+        };
+        let t = Self::ease_out_cubic(progress);
+
+        // The zoom is interpolated geometrically, so that zooming in and out feels equally fast
+        let zoom = self.start_zoom * (self.target_zoom / self.start_zoom).powf(t);
+        let center = self.start_center.lerp(&self.target_center, t);
+
+        CameraAnimationStep {
+            zoom,
+            offset: center * zoom - surface_size * 0.5,
+            finished: progress >= 1.0,
+        }
+    }
+
+    fn ease_out_cubic(t: f64) -> f64 {
+        1.0 - (1.0 - t).powi(3)
+    }
+}
@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use rnote_fileformats::rnoteformat::{self, RnotefileMaj0Min6};
+use serde::{Deserialize, Serialize};
+
+use crate::store::chrono_comp::StrokeLayer;
+use crate::store::StrokeKey;
+use crate::strokes::Stroke;
+use crate::{RnoteEngine, StrokeStore, WidgetFlags};
+
+/// A stroke as it was saved in a checkpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "checkpoint_stroke")]
+struct CheckpointStroke {
+    #[serde(rename = "layer")]
+    layer: StrokeLayer,
+    /// The serialized stroke. Deferred and loaded strokes serialize to the same value, so they can be compared
+    #[serde(rename = "stroke")]
+    stroke: serde_json::Value,
+}
+
+impl CheckpointStroke {
+    fn from_store(store: &StrokeStore, key: StrokeKey) -> anyhow::Result<Option<Self>> {
+        match (store.get_stroke_ref(key), store.stroke_layer(key)) {
+            (Some(stroke), Some(layer)) => Ok(Some(Self {
+                layer,
+                stroke: serde_json::to_value(stroke)?,
+            })),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// A named checkpoint of the store, saved inside the .rnote file to revert to it later.
+/// Only the delta to the previous checkpoint is saved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "checkpoint")]
+pub struct Checkpoint {
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "created")]
+    pub created: DateTime<Utc>,
+    /// The strokes that were added or modified since the previous checkpoint
+    #[serde(rename = "changed")]
+    changed: Vec<(StrokeKey, CheckpointStroke)>,
+    /// The strokes that were removed since the previous checkpoint
+    #[serde(rename = "removed")]
+    removed: Vec<StrokeKey>,
+    /// The keys of all strokes of the checkpoint in chronological order, to insert them again in the same order
+    #[serde(rename = "order")]
+    order: Vec<StrokeKey>,
+}
+
+impl Checkpoint {
+    fn apply_to(&self, strokes: &mut HashMap<StrokeKey, CheckpointStroke>) {
+        for key in self.removed.iter() {
+            strokes.remove(key);
+        }
+        for (key, stroke) in self.changed.iter() {
+            strokes.insert(*key, stroke.clone());
+        }
+    }
+}
+
+/// Replays the deltas of the checkpoints
+fn checkpoint_strokes(checkpoints: &[Checkpoint]) -> HashMap<StrokeKey, CheckpointStroke> {
+    let mut strokes = HashMap::new();
+    for checkpoint in checkpoints {
+        checkpoint.apply_to(&mut strokes);
+    }
+
+    strokes
+}
+
+/// Takes the strokes in the chronological order of the checkpoint
+fn take_in_order(
+    strokes: &mut HashMap<StrokeKey, CheckpointStroke>,
+    checkpoint: &Checkpoint,
+) -> Vec<CheckpointStroke> {
+    checkpoint
+        .order
+        .iter()
+        .filter_map(|key| strokes.remove(key))
+        .collect()
+}
+
+impl RnoteEngine {
+    /// The checkpoints, in the order they were saved
+    pub fn checkpoints(&self) -> &[Checkpoint] {
+        &self.checkpoints
+    }
+
+    /// Loads the CBOR encoded checkpoints of a .rnote file
+    pub(crate) fn load_checkpoints(&mut self, bytes: &[u8]) -> anyhow::Result<()> {
+        self.checkpoints = if bytes.is_empty() {
+            vec![]
+        } else {
+            rnoteformat::decode_cbor(bytes)?
+        };
+
+        Ok(())
+    }
+
+    /// Encodes the checkpoints for saving them in a .rnote file
+    pub(crate) fn encode_checkpoints(&self) -> anyhow::Result<Vec<u8>> {
+        if self.checkpoints.is_empty() {
+            return Ok(vec![]);
+        }
+
+        rnoteformat::encode_cbor(&self.checkpoints)
+    }
+
+    /// Saves the current strokes as named checkpoint. Returns the index of the checkpoint.
+    pub fn save_checkpoint(&mut self, name: String) -> anyhow::Result<(usize, WidgetFlags)> {
+        let mut widget_flags = WidgetFlags::default();
+        let previous = checkpoint_strokes(&self.checkpoints);
+
+        let mut current = HashMap::new();
+        let mut order = vec![];
+        for key in self.store.stroke_keys_as_rendered() {
+            if let Some(stroke) = CheckpointStroke::from_store(&self.store, key)? {
+                current.insert(key, stroke);
+                order.push(key);
+            }
+        }
+
+        let removed = previous
+            .keys()
+            .filter(|key| !current.contains_key(key))
+            .copied()
+            .collect();
+        let changed = current
+            .into_iter()
+            .filter(|(key, stroke)| previous.get(key) != Some(stroke))
+            .collect();
+
+        self.checkpoints.push(Checkpoint {
+            name,
+            created: Utc::now(),
+            changed,
+            removed,
+            order,
+        });
+
+        widget_flags.indicate_changed_store = true;
+
+        Ok((self.checkpoints.len() - 1, widget_flags))
+    }
+
+    /// Removes the checkpoint with the given index. Its delta is merged into the next checkpoint
+    pub fn remove_checkpoint(&mut self, index: usize) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+
+        if index >= self.checkpoints.len() {
+            return Err(anyhow::anyhow!(
+                "remove_checkpoint() failed, checkpoint {} does not exist",
+                index
+            ));
+        }
+        let removed_checkpoint = self.checkpoints.remove(index);
+
+        if let Some(next) = self.checkpoints.get_mut(index) {
+            let next_keys = next
+                .changed
+                .iter()
+                .map(|(key, _)| *key)
+                .chain(next.removed.iter().copied())
+                .collect::<Vec<StrokeKey>>();
+            let merged_removed = removed_checkpoint
+                .removed
+                .into_iter()
+                .filter(|key| !next_keys.contains(key))
+                .collect::<Vec<StrokeKey>>();
+            let merged_changed = removed_checkpoint
+                .changed
+                .into_iter()
+                .filter(|(key, _)| !next_keys.contains(key))
+                .collect::<Vec<(StrokeKey, CheckpointStroke)>>();
+
+            next.removed.extend(merged_removed);
+            next.changed.extend(merged_changed);
+        }
+
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Reverts the strokes to the checkpoint with the given index. The revert can be undone.
+    /// Strokes that are unchanged since the checkpoint keep their keys, removed strokes are inserted again.
+    pub fn revert_to_checkpoint(&mut self, index: usize) -> anyhow::Result<WidgetFlags> {
+        if index >= self.checkpoints.len() {
+            return Err(anyhow::anyhow!(
+                "revert_to_checkpoint() failed, checkpoint {} does not exist",
+                index
+            ));
+        }
+        let mut checkpoint_strokes = checkpoint_strokes(&self.checkpoints[..=index]);
+        let mut widget_flags = self.store.record();
+
+        for key in self.store.keys_unordered() {
+            match checkpoint_strokes.remove(&key) {
+                Some(checkpoint_stroke) => {
+                    if self.store.trashed(key).unwrap_or(false) {
+                        self.store.set_trashed(key, false);
+                    }
+                    if CheckpointStroke::from_store(&self.store, key)?.as_ref()
+                        != Some(&checkpoint_stroke)
+                    {
+                        let stroke = serde_json::from_value::<Stroke>(checkpoint_stroke.stroke)?;
+                        if let Some(current) = self.store.get_stroke_mut(key) {
+                            *current = stroke;
+                        }
+                        self.store.update_geometry_for_stroke(key);
+                    }
+                }
+                None => {
+                    self.store.remove_stroke(key);
+                }
+            }
+        }
+        for checkpoint_stroke in take_in_order(&mut checkpoint_strokes, &self.checkpoints[index]) {
+            let stroke = serde_json::from_value::<Stroke>(checkpoint_stroke.stroke)?;
+            self.store
+                .insert_stroke(stroke, Some(checkpoint_stroke.layer));
+        }
+
+        self.resize_autoexpand();
+        self.update_pens_states();
+        self.update_rendering_current_viewport();
+
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Branches off a standalone document with the strokes of the checkpoint with the given index.
+    /// It keeps the checkpoints up to the branched one.
+    pub fn branch_from_checkpoint(&self, index: usize) -> anyhow::Result<RnotefileMaj0Min6> {
+        if index >= self.checkpoints.len() {
+            return Err(anyhow::anyhow!(
+                "branch_from_checkpoint() failed, checkpoint {} does not exist",
+                index
+            ));
+        }
+        let checkpoints = &self.checkpoints[..=index];
+
+        let mut store = StrokeStore::default();
+        for checkpoint_stroke in
+            take_in_order(&mut checkpoint_strokes(checkpoints), &checkpoints[index])
+        {
+            let stroke = serde_json::from_value::<Stroke>(checkpoint_stroke.stroke)?;
+            store.insert_stroke(stroke, Some(checkpoint_stroke.layer));
+        }
+        let mut store_snapshot = store.take_store_snapshot();
+        Arc::make_mut(&mut store_snapshot).process_before_saving();
+
+        Ok(RnotefileMaj0Min6 {
+            document: serde_json::to_value(&self.document)?,
+            store_snapshot: rnoteformat::encode_cbor(&*store_snapshot)?,
+            checkpoints: rnoteformat::encode_cbor(checkpoints)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::StoreSnapshot;
+    use approx::assert_relative_eq;
+    use rnote_compose::shapes::ShapeBehaviour;
+
+    #[test]
+    fn save_and_revert_checkpoints() {
+        let mut engine = RnoteEngine::default();
+        let line = |x: f64| Stroke::line_fixture(na::vector![x, 0.0], na::vector![x + 10.0, 10.0]);
+        let a = engine.store.insert_stroke(line(0.0), None);
+        let a_bounds = engine.store.get_stroke_ref(a).unwrap().bounds();
+        let _ = engine.save_checkpoint(String::from("first")).unwrap();
+
+        engine
+            .store
+            .translate_strokes(&[a], na::vector![100.0, 0.0]);
+        engine.store.insert_stroke(line(200.0), None);
+        let _ = engine.save_checkpoint(String::from("second")).unwrap();
+        engine.store.remove_stroke(a);
+
+        let checkpoints = engine.encode_checkpoints().unwrap();
+        engine.load_checkpoints(&checkpoints).unwrap();
+        assert_eq!(engine.checkpoints().len(), 2);
+
+        let _ = engine.revert_to_checkpoint(0).unwrap();
+        let keys = engine.store.stroke_keys_unordered();
+        assert_eq!(keys.len(), 1);
+        assert_relative_eq!(
+            engine
+                .store
+                .get_stroke_ref(keys[0])
+                .unwrap()
+                .bounds()
+                .mins
+                .coords,
+            a_bounds.mins.coords
+        );
+        let _ = engine.undo();
+        let keys = engine.store.stroke_keys_unordered();
+        assert_eq!(keys.len(), 1);
+        assert!(engine.store.get_stroke_ref(keys[0]).unwrap().bounds().mins[0] > 100.0);
+
+        let _ = engine.remove_checkpoint(0).unwrap();
+        assert_eq!(engine.checkpoints().len(), 1);
+        let branch = engine.branch_from_checkpoint(0).unwrap();
+        let branch_snapshot =
+            StoreSnapshot::from_cbor_w_deferred_strokes(&branch.store_snapshot).unwrap();
+        assert_eq!(branch_snapshot.stroke_components.len(), 2);
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::engine::{EngineTask, EngineTaskSender};
+
+/// The sink the serialized engine config is passed to when it is autosaved, e.g. writing it into the app settings.
+pub type ConfigSink = Box<dyn Fn(&str) -> anyhow::Result<()>>;
+
+/// Persists the engine config through a sink whenever it changes, so that frontends don't need to save it after every modified setting.
+/// While a sink is set, a ticker thread regularly sends `EngineTask::CheckConfigChanged` through the engine task channel.
+/// A changed config is only passed to the sink once it stayed the same for one check interval, so rapid changes are debounced.
+#[derive(Default)]
+pub struct ConfigAutosave {
+    sink: Option<ConfigSink>,
+    running: Arc<AtomicBool>,
+    last_seen: Option<u64>,
+    last_saved: Option<u64>,
+}
+
+impl std::fmt::Debug for ConfigAutosave {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfigAutosave")
+            .field("sink", &self.sink.as_ref().map(|_| "ConfigSink"))
+            .field("running", &self.running)
+            .field("last_seen", &self.last_seen)
+            .field("last_saved", &self.last_saved)
+            .finish()
+    }
+}
+
+impl Drop for ConfigAutosave {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+impl ConfigAutosave {
+    /// The interval in which the config is checked for changes
+    pub const CHECK_INTERVAL: Duration = Duration::from_millis(1000);
+
+    /// Wether a sink is set
+    pub fn enabled(&self) -> bool {
+        self.sink.is_some()
+    }
+
+    /// Sets the sink, or disables the autosave with None. The current config is regarded as already saved.
+    pub fn set_sink(
+        &mut self,
+        sink: Option<ConfigSink>,
+        current_config: &str,
+        tasks_tx: EngineTaskSender,
+    ) {
+        // Stop the ticker of the previous sink
+        self.running.store(false, Ordering::Relaxed);
+
+        let current_hash = Self::hash_config(current_config);
+        self.last_seen = Some(current_hash);
+        self.last_saved = Some(current_hash);
+        self.sink = sink;
+
+        if self.sink.is_some() {
+            self.running = Arc::new(AtomicBool::new(true));
+            self.spawn_ticker(tasks_tx);
+        }
+    }
+
+    /// Checks the current config, and passes it to the sink if it changed and is settled.
+    pub fn check(&mut self, current_config: &str) {
+        let sink = match self.sink.as_ref() {
+            Some(sink) => sink,
+            None => return,
+        };
+        let current_hash = Self::hash_config(current_config);
+
+        if self.last_saved == Some(current_hash) {
+            self.last_seen = Some(current_hash);
+            return;
+        }
+        // Wait until the config stops changing
+        if self.last_seen != Some(current_hash) {
+            self.last_seen = Some(current_hash);
+            return;
+        }
+
+        match sink(current_config) {
+            Ok(()) => self.last_saved = Some(current_hash),
+            Err(e) => log::error!("autosaving the engine config failed in the sink, Err {}", e),
+        }
+    }
+
+    fn hash_config(config: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        config.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The ticker quits when the autosave is disabled, or when the task channel is closed.
+    fn spawn_ticker(&self, tasks_tx: EngineTaskSender) {
+        let running = Arc::clone(&self.running);
+
+        let spawn_result = std::thread::Builder::new()
+            .name(String::from("rnote-config-autosave"))
+            .spawn(move || loop {
+                std::thread::sleep(Self::CHECK_INTERVAL);
+
+                if !running.load(Ordering::Relaxed) {
+                    return;
+                }
+                if tasks_tx
+                    .unbounded_send(EngineTask::CheckConfigChanged)
+                    .is_err()
+                {
+                    return;
+                }
+            });
+
+        if let Err(e) = spawn_result {
+            log::error!(
+                "spawning the ticker thread failed in ConfigAutosave::spawn_ticker(), Err {}",
+                e
+            );
+        }
+    }
+}
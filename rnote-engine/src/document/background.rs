@@ -1,11 +1,14 @@
+use std::collections::BTreeMap;
+
 use anyhow::Context;
 use gtk4::{gdk, graphene, gsk, prelude::*, Snapshot};
-use p2d::bounding_volume::AABB;
+use p2d::bounding_volume::{BoundingVolume, AABB};
 use rnote_compose::shapes::ShapeBehaviour;
 use serde::{Deserialize, Serialize};
 use svg::node::element;
 use svg::Node;
 
+use crate::strokes::VectorImage;
 use crate::utils::{GdkRGBAHelpers, GrapheneRectHelpers};
 use crate::{render, Camera};
 use rnote_compose::helpers::AABBHelpers;
@@ -32,6 +35,27 @@ pub enum PatternStyle {
     Grid,
     #[serde(rename = "dots")]
     Dots,
+    /// College ruled lines with a margin line
+    #[serde(rename = "ruled_college")]
+    RuledCollege,
+    /// Narrow ruled lines with a margin line
+    #[serde(rename = "ruled_narrow")]
+    RuledNarrow,
+    /// Lines with the cue column and summary area of the Cornell note taking system
+    #[serde(rename = "cornell")]
+    Cornell,
+    /// Music staves of five lines each
+    #[serde(rename = "music_staff")]
+    MusicStaff,
+    /// An isometric grid of equilateral triangles
+    #[serde(rename = "isometric")]
+    Isometric,
+    /// A honeycomb grid of hexagons
+    #[serde(rename = "hex_grid")]
+    HexGrid,
+    /// A custom svg tile, see `Background::custom_pattern`
+    #[serde(rename = "custom")]
+    Custom,
 }
 
 impl Default for PatternStyle {
@@ -184,6 +208,264 @@ pub fn gen_dots_pattern(
     group.into()
 }
 
+pub fn gen_music_staff_pattern(
+    bounds: AABB,
+    line_spacing: f64,
+    staff_spacing: f64,
+    color: Color,
+    line_width: f64,
+) -> svg::node::element::Element {
+    let pattern_id = rnote_compose::utils::random_id_prefix() + "_bg_music_staff_pattern";
+
+    let line_offset = line_width * 0.5;
+    let mut pattern = element::Pattern::new()
+        .set("id", pattern_id.as_str())
+        .set("x", 0_f64)
+        .set("y", 0_f64)
+        .set("width", bounds.extents()[0])
+        .set("height", staff_spacing)
+        .set("patternUnits", "userSpaceOnUse")
+        .set("patternContentUnits", "userSpaceOnUse");
+
+    for i in 0..5 {
+        let y = line_offset + i as f64 * line_spacing;
+
+        pattern = pattern.add(
+            element::Line::new()
+                .set("stroke-width", line_width)
+                .set("stroke", color.to_css_color_attr())
+                .set("x1", 0_f64)
+                .set("y1", y)
+                .set("x2", bounds.extents()[0])
+                .set("y2", y),
+        );
+    }
+
+    let rect = element::Rectangle::new()
+        .set("x", bounds.mins[0])
+        .set("y", bounds.mins[1])
+        .set("width", bounds.extents()[0])
+        .set("height", bounds.extents()[1])
+        .set("fill", format!("url(#{})", pattern_id));
+
+    let group = element::Group::new()
+        .add(element::Definitions::new().add(pattern))
+        .add(rect);
+    group.into()
+}
+
+pub fn gen_isometric_pattern(
+    bounds: AABB,
+    row_spacing: f64,
+    color: Color,
+    line_width: f64,
+) -> svg::node::element::Element {
+    let pattern_id = rnote_compose::utils::random_id_prefix() + "_bg_isometric_pattern";
+
+    // The lines are horizontal and at +-60 degrees, so the pattern repeats every triangle side horizontally and every two rows vertically
+    let width = 2.0 * row_spacing / 3_f64.sqrt();
+    let height = 2.0 * row_spacing;
+
+    let data = element::path::Data::new()
+        .move_to((0_f64, 0_f64))
+        .line_to((width, 0_f64))
+        .move_to((0_f64, row_spacing))
+        .line_to((width, row_spacing))
+        .move_to((0_f64, height))
+        .line_to((width, height))
+        .move_to((0_f64, 0_f64))
+        .line_to((width, height))
+        .move_to((width, 0_f64))
+        .line_to((0_f64, height));
+
+    let pattern = element::Definitions::new().add(
+        element::Pattern::new()
+            .set("id", pattern_id.as_str())
+            .set("x", 0_f64)
+            .set("y", 0_f64)
+            .set("width", width)
+            .set("height", height)
+            .set("patternUnits", "userSpaceOnUse")
+            .set("patternContentUnits", "userSpaceOnUse")
+            .add(
+                element::Path::new()
+                    .set("stroke-width", line_width)
+                    .set("stroke", color.to_css_color_attr())
+                    .set("fill", "none")
+                    .set("d", data),
+            ),
+    );
+
+    let rect = element::Rectangle::new()
+        .set("x", bounds.mins[0])
+        .set("y", bounds.mins[1])
+        .set("width", bounds.extents()[0])
+        .set("height", bounds.extents()[1])
+        .set("fill", format!("url(#{})", pattern_id));
+
+    let group = element::Group::new().add(pattern).add(rect);
+    group.into()
+}
+
+pub fn gen_hex_grid_pattern(
+    bounds: AABB,
+    hex_height: f64,
+    color: Color,
+    line_width: f64,
+) -> svg::node::element::Element {
+    let pattern_id = rnote_compose::utils::random_id_prefix() + "_bg_hex_grid_pattern";
+
+    // Flat topped hexagons, with the alternating columns offset by half a hexagon
+    let side = hex_height / 3_f64.sqrt();
+    let width = 3.0 * side;
+    let half_height = hex_height * 0.5;
+
+    let data = element::path::Data::new()
+        .move_to((side * 0.5, 0_f64))
+        .line_to((side * 1.5, 0_f64))
+        .line_to((side * 2.0, half_height))
+        .line_to((width, half_height))
+        .move_to((side * 2.0, half_height))
+        .line_to((side * 1.5, hex_height))
+        .line_to((side * 0.5, hex_height))
+        .line_to((0_f64, half_height))
+        .line_to((side * 0.5, 0_f64));
+
+    let pattern = element::Definitions::new().add(
+        element::Pattern::new()
+            .set("id", pattern_id.as_str())
+            .set("x", 0_f64)
+            .set("y", 0_f64)
+            .set("width", width)
+            .set("height", hex_height)
+            .set("patternUnits", "userSpaceOnUse")
+            .set("patternContentUnits", "userSpaceOnUse")
+            .add(
+                element::Path::new()
+                    .set("stroke-width", line_width)
+                    .set("stroke", color.to_css_color_attr())
+                    .set("fill", "none")
+                    .set("d", data),
+            ),
+    );
+
+    let rect = element::Rectangle::new()
+        .set("x", bounds.mins[0])
+        .set("y", bounds.mins[1])
+        .set("width", bounds.extents()[0])
+        .set("height", bounds.extents()[1])
+        .set("fill", format!("url(#{})", pattern_id));
+
+    let group = element::Group::new().add(pattern).add(rect);
+    group.into()
+}
+
+pub fn gen_custom_pattern(
+    bounds: AABB,
+    custom_pattern: &CustomPattern,
+    tile_size: na::Vector2<f64>,
+) -> svg::node::element::Element {
+    let pattern_id = rnote_compose::utils::random_id_prefix() + "_bg_custom_pattern";
+
+    let tile = element::SVG::new()
+        .set("x", 0_f64)
+        .set("y", 0_f64)
+        .set("width", tile_size[0])
+        .set("height", tile_size[1])
+        .set(
+            "viewBox",
+            format!(
+                "{:.3} {:.3} {:.3} {:.3}",
+                0.0, 0.0, custom_pattern.intrinsic_size[0], custom_pattern.intrinsic_size[1]
+            ),
+        )
+        .set("preserveAspectRatio", "none")
+        .add(svg::node::Text::new(custom_pattern.svg_data.clone()));
+
+    let pattern = element::Definitions::new().add(
+        element::Pattern::new()
+            .set("id", pattern_id.as_str())
+            .set("x", 0_f64)
+            .set("y", 0_f64)
+            .set("width", tile_size[0])
+            .set("height", tile_size[1])
+            .set("patternUnits", "userSpaceOnUse")
+            .set("patternContentUnits", "userSpaceOnUse")
+            .add(tile),
+    );
+
+    let rect = element::Rectangle::new()
+        .set("x", bounds.mins[0])
+        .set("y", bounds.mins[1])
+        .set("width", bounds.extents()[0])
+        .set("height", bounds.extents()[1])
+        .set("fill", format!("url(#{})", pattern_id));
+
+    let group = element::Group::new().add(pattern).add(rect);
+    group.into()
+}
+
+/// A user defined svg tile, which is repeated as background pattern
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename = "custom_pattern")]
+pub struct CustomPattern {
+    /// The sanitized svg data of the tile
+    #[serde(rename = "svg_data")]
+    pub svg_data: String,
+    /// The size the svg declares itself. It is scaled to the pattern size when drawn
+    #[serde(rename = "intrinsic_size")]
+    pub intrinsic_size: na::Vector2<f64>,
+}
+
+impl CustomPattern {
+    /// Creates a custom pattern from svg data. Scripts and external references are removed.
+    pub fn from_svg_data(svg_data: &str) -> anyhow::Result<Self> {
+        if svg_data.len() > VectorImage::IMPORT_MAX_DATA_SIZE {
+            return Err(anyhow::anyhow!(
+                "svg data with size {} bytes exceeds the maximum size of {} bytes",
+                svg_data.len(),
+                VectorImage::IMPORT_MAX_DATA_SIZE
+            ));
+        }
+        let xml_options = usvg::XmlOptions {
+            id_prefix: Some(rnote_compose::utils::random_id_prefix()),
+            writer_opts: xmlwriter::Options {
+                use_single_quote: false,
+                indent: xmlwriter::Indent::None,
+                attributes_indent: xmlwriter::Indent::None,
+            },
+        };
+
+        let svg_data = VectorImage::sanitize_svg_data(svg_data)
+            .context("sanitizing svg data for custom background pattern failed")?;
+        let rtree = usvg::Tree::from_str(&svg_data, &render::USVG_OPTIONS.to_ref())
+            .context("parsing svg data for custom background pattern failed")?;
+
+        let svg_node = rtree.svg_node();
+
+        Ok(Self {
+            svg_data: rtree.to_string(&xml_options),
+            intrinsic_size: na::vector![svg_node.size.width(), svg_node.size.height()],
+        })
+    }
+}
+
+/// Overrides of the background for a single page
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename = "page_background_override")]
+pub struct PageBackgroundOverride {
+    /// The paper color of the page. The background color is used when None
+    #[serde(rename = "color")]
+    pub color: Option<Color>,
+}
+
+impl PageBackgroundOverride {
+    /// Wether the override doesn't override anything
+    pub fn is_empty(&self) -> bool {
+        self.color.is_none()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default, rename = "background")]
 pub struct Background {
@@ -195,6 +477,12 @@ pub struct Background {
     pub pattern_size: na::Vector2<f64>,
     #[serde(rename = "pattern_color")]
     pub pattern_color: Color,
+    /// The tile of the custom pattern, scaled to the pattern size
+    #[serde(rename = "custom_pattern")]
+    pub custom_pattern: Option<CustomPattern>,
+    /// The per-page overrides, with the index into the document pages as key
+    #[serde(rename = "page_overrides")]
+    pub page_overrides: BTreeMap<usize, PageBackgroundOverride>,
     #[serde(skip)]
     pub image: Option<render::Image>,
     #[serde(skip)]
@@ -208,12 +496,26 @@ impl Default for Background {
             pattern: PatternStyle::default(),
             pattern_size: Self::PATTERN_SIZE_DEFAULT,
             pattern_color: Self::PATTERN_COLOR_DEFAULT,
+            custom_pattern: None,
+            page_overrides: BTreeMap::new(),
             image: None,
             rendernodes: vec![],
         }
     }
 }
 
+/// Compares the settings. The generated pattern image and render nodes are ignored
+impl PartialEq for Background {
+    fn eq(&self, other: &Self) -> bool {
+        self.color == other.color
+            && self.pattern == other.pattern
+            && self.pattern_size == other.pattern_size
+            && self.pattern_color == other.pattern_color
+            && self.custom_pattern == other.custom_pattern
+            && self.page_overrides == other.page_overrides
+    }
+}
+
 impl Background {
     const TILE_MAX_SIZE: f64 = 128.0;
     const COLOR_DEFAULT: Color = Color::WHITE;
@@ -224,27 +526,182 @@ impl Background {
         b: 1.0,
         a: 1.0,
     };
+    /// The line spacing of college ruled paper (7.1mm at 96 DPI)
+    const RULED_COLLEGE_SPACING: f64 = 26.8;
+    /// The line spacing of narrow ruled paper (6.35mm at 96 DPI)
+    const RULED_NARROW_SPACING: f64 = 24.0;
+    /// The distance of the margin line of ruled paper from the page left edge
+    const RULED_MARGIN: f64 = 120.0;
+    const RULED_MARGIN_COLOR: Color = Color {
+        r: 0.95,
+        g: 0.55,
+        b: 0.55,
+        a: 1.0,
+    };
+    /// The width of the Cornell cue column
+    const CORNELL_CUE_COLUMN_WIDTH: f64 = 240.0;
+    /// The height of the Cornell summary area at the page bottom
+    const CORNELL_SUMMARY_HEIGHT: f64 = 192.0;
+    const PATTERN_LINE_WIDTH: f64 = 0.5;
+    const PAGE_DIVIDER_LINE_WIDTH: f64 = 1.5;
+
+    /// The size after which the pattern repeats itself
+    fn pattern_period(&self) -> na::Vector2<f64> {
+        let row_spacing = self.pattern_size[1];
+
+        match self.pattern {
+            PatternStyle::None
+            | PatternStyle::Lines
+            | PatternStyle::Grid
+            | PatternStyle::Dots
+            | PatternStyle::Cornell
+            | PatternStyle::Custom => self.pattern_size,
+            PatternStyle::RuledCollege => {
+                na::vector![self.pattern_size[0], Self::RULED_COLLEGE_SPACING]
+            }
+            PatternStyle::RuledNarrow => {
+                na::vector![self.pattern_size[0], Self::RULED_NARROW_SPACING]
+            }
+            PatternStyle::MusicStaff => na::vector![self.pattern_size[0], row_spacing * 4.0],
+            PatternStyle::Isometric => {
+                na::vector![2.0 * row_spacing / 3_f64.sqrt(), 2.0 * row_spacing]
+            }
+            PatternStyle::HexGrid => na::vector![3_f64.sqrt() * row_spacing, row_spacing],
+        }
+    }
 
-    /// Calculates the tile size as multiple of pattern_size with max size TITLE_MAX_SIZE
+    /// Calculates the tile size as multiple of the pattern period with max size TITLE_MAX_SIZE
     fn tile_size(&self) -> na::Vector2<f64> {
+        let pattern_period = self.pattern_period();
         let tile_factor =
-            na::Vector2::from_element(Self::TILE_MAX_SIZE).component_div(&self.pattern_size);
+            na::Vector2::from_element(Self::TILE_MAX_SIZE).component_div(&pattern_period);
 
         let tile_width = if tile_factor[0] > 1.0 {
-            tile_factor[0].floor() * self.pattern_size[0]
+            tile_factor[0].floor() * pattern_period[0]
         } else {
-            self.pattern_size[0]
+            pattern_period[0]
         };
         let tile_height = if tile_factor[1] > 1.0 {
-            tile_factor[1].floor() * self.pattern_size[1]
+            tile_factor[1].floor() * pattern_period[1]
         } else {
-            self.pattern_size[1]
+            pattern_period[1]
         };
 
         na::vector![tile_width, tile_height]
     }
 
-    fn gen_svg_element(&self, bounds: AABB) -> svg::node::element::Element {
+    /// The paper color of the page with the given index
+    pub fn page_color(&self, page: usize) -> Color {
+        self.page_overrides
+            .get(&page)
+            .and_then(|page_override| page_override.color)
+            .unwrap_or(self.color)
+    }
+
+    /// Sets the paper color of the page with the given index. None resets it to the background color.
+    pub fn set_page_color(&mut self, page: usize, color: Option<Color>) {
+        let page_override = self.page_overrides.entry(page).or_default();
+        page_override.color = color;
+
+        if page_override.is_empty() {
+            self.page_overrides.remove(&page);
+        }
+    }
+
+    /// Inserts the override for a new page at the given index, shifting the overrides of the subsequent pages.
+    pub fn insert_page_override(&mut self, page: usize, page_override: PageBackgroundOverride) {
+        let subsequent = self.page_overrides.split_off(&page);
+        self.page_overrides.extend(
+            subsequent
+                .into_iter()
+                .map(|(page, page_override)| (page + 1, page_override)),
+        );
+
+        if !page_override.is_empty() {
+            self.page_overrides.insert(page, page_override);
+        }
+    }
+
+    /// Removes the override of a removed page at the given index, shifting the overrides of the subsequent pages.
+    pub fn remove_page_override(&mut self, page: usize) -> Option<PageBackgroundOverride> {
+        let mut subsequent = self.page_overrides.split_off(&page);
+        let removed = subsequent.remove(&page);
+        self.page_overrides.extend(
+            subsequent
+                .into_iter()
+                .map(|(page, page_override)| (page - 1, page_override)),
+        );
+
+        removed
+    }
+
+    /// The bounds and colors of the pages which have their paper color overridden and intersect the given bounds.
+    /// The page bounds are clipped to the given bounds.
+    fn overridden_page_colors(&self, bounds: AABB, pages_bounds: &[AABB]) -> Vec<(AABB, Color)> {
+        self.page_overrides
+            .iter()
+            .filter_map(|(&page, page_override)| {
+                let page_bounds = pages_bounds.get(page)?.intersection(&bounds)?;
+
+                Some((page_bounds, page_override.color?))
+            })
+            .collect()
+    }
+
+    /// The lines which are placed relative to the pages, like margins, as thin rectangles with their color.
+    /// They are clipped to the given bounds.
+    fn page_lines(&self, bounds: AABB, pages_bounds: &[AABB]) -> Vec<(AABB, Color)> {
+        pages_bounds
+            .iter()
+            .flat_map(|page_bounds| {
+                let vline = |x: f64, width: f64, maxs_y: f64| {
+                    AABB::new(
+                        na::point![x - width * 0.5, page_bounds.mins[1]],
+                        na::point![x + width * 0.5, maxs_y],
+                    )
+                };
+                let hline = |y: f64, width: f64| {
+                    AABB::new(
+                        na::point![page_bounds.mins[0], y - width * 0.5],
+                        na::point![page_bounds.maxs[0], y + width * 0.5],
+                    )
+                };
+
+                match self.pattern {
+                    PatternStyle::RuledCollege | PatternStyle::RuledNarrow => vec![(
+                        vline(
+                            page_bounds.mins[0] + Self::RULED_MARGIN,
+                            Self::PATTERN_LINE_WIDTH * 2.0,
+                            page_bounds.maxs[1],
+                        ),
+                        Self::RULED_MARGIN_COLOR,
+                    )],
+                    PatternStyle::Cornell => {
+                        let summary_y = page_bounds.maxs[1] - Self::CORNELL_SUMMARY_HEIGHT;
+
+                        vec![
+                            (
+                                vline(
+                                    page_bounds.mins[0] + Self::CORNELL_CUE_COLUMN_WIDTH,
+                                    Self::PAGE_DIVIDER_LINE_WIDTH,
+                                    summary_y,
+                                ),
+                                self.pattern_color,
+                            ),
+                            (
+                                hline(summary_y, Self::PAGE_DIVIDER_LINE_WIDTH),
+                                self.pattern_color,
+                            ),
+                        ]
+                    }
+                    _ => vec![],
+                }
+            })
+            .filter_map(|(line_bounds, color)| Some((line_bounds.intersection(&bounds)?, color)))
+            .collect()
+    }
+
+    fn gen_svg_element(&self, bounds: AABB, pages_bounds: &[AABB]) -> svg::node::element::Element {
         let mut group = element::Group::new();
 
         // background color
@@ -256,6 +713,35 @@ impl Background {
             .set("fill", self.color.to_css_color_attr());
         group = group.add(color_rect);
 
+        for (page_bounds, page_color) in self.overridden_page_colors(bounds, pages_bounds) {
+            let page_color_rect = element::Rectangle::new()
+                .set("x", page_bounds.mins[0])
+                .set("y", page_bounds.mins[1])
+                .set("width", page_bounds.extents()[0])
+                .set("height", page_bounds.extents()[1])
+                .set("fill", page_color.to_css_color_attr());
+            group = group.add(page_color_rect);
+        }
+
+        group = group.add(self.gen_pattern_svg_element(bounds));
+
+        for (line_bounds, line_color) in self.page_lines(bounds, pages_bounds) {
+            let line_rect = element::Rectangle::new()
+                .set("x", line_bounds.mins[0])
+                .set("y", line_bounds.mins[1])
+                .set("width", line_bounds.extents()[0])
+                .set("height", line_bounds.extents()[1])
+                .set("fill", line_color.to_css_color_attr());
+            group = group.add(line_rect);
+        }
+
+        group.into()
+    }
+
+    /// The pattern, without the background color
+    fn gen_pattern_svg_element(&self, bounds: AABB) -> svg::node::element::Element {
+        let mut group = element::Group::new();
+
         match self.pattern {
             PatternStyle::None => {}
             PatternStyle::Lines => {
@@ -263,7 +749,7 @@ impl Background {
                     bounds,
                     self.pattern_size[1],
                     self.pattern_color,
-                    0.5,
+                    Self::PATTERN_LINE_WIDTH,
                 ));
             }
             PatternStyle::Grid => {
@@ -272,7 +758,7 @@ impl Background {
                     self.pattern_size[1],
                     self.pattern_size[0],
                     self.pattern_color,
-                    0.5,
+                    Self::PATTERN_LINE_WIDTH,
                 ));
             }
             PatternStyle::Dots => {
@@ -284,6 +770,65 @@ impl Background {
                     1.5,
                 ));
             }
+            PatternStyle::RuledCollege => {
+                group = group.add(gen_hline_pattern(
+                    bounds,
+                    Self::RULED_COLLEGE_SPACING,
+                    self.pattern_color,
+                    Self::PATTERN_LINE_WIDTH,
+                ));
+            }
+            PatternStyle::RuledNarrow => {
+                group = group.add(gen_hline_pattern(
+                    bounds,
+                    Self::RULED_NARROW_SPACING,
+                    self.pattern_color,
+                    Self::PATTERN_LINE_WIDTH,
+                ));
+            }
+            PatternStyle::Cornell => {
+                group = group.add(gen_hline_pattern(
+                    bounds,
+                    self.pattern_size[1],
+                    self.pattern_color,
+                    Self::PATTERN_LINE_WIDTH,
+                ));
+            }
+            PatternStyle::MusicStaff => {
+                // The staff takes up half of the period, the other half is the space between the staves
+                group = group.add(gen_music_staff_pattern(
+                    bounds,
+                    self.pattern_size[1] * 0.5,
+                    self.pattern_size[1] * 4.0,
+                    self.pattern_color,
+                    Self::PATTERN_LINE_WIDTH * 2.0,
+                ));
+            }
+            PatternStyle::Isometric => {
+                group = group.add(gen_isometric_pattern(
+                    bounds,
+                    self.pattern_size[1],
+                    self.pattern_color,
+                    Self::PATTERN_LINE_WIDTH,
+                ));
+            }
+            PatternStyle::HexGrid => {
+                group = group.add(gen_hex_grid_pattern(
+                    bounds,
+                    self.pattern_size[1],
+                    self.pattern_color,
+                    Self::PATTERN_LINE_WIDTH,
+                ));
+            }
+            PatternStyle::Custom => {
+                if let Some(custom_pattern) = &self.custom_pattern {
+                    group = group.add(gen_custom_pattern(
+                        bounds,
+                        custom_pattern,
+                        self.pattern_size,
+                    ));
+                }
+            }
         }
 
         group.into()
@@ -293,14 +838,20 @@ impl Background {
         &self,
         piet_svg_cx: &mut piet_svg::RenderContext,
         bounds: AABB,
+        pages_bounds: &[AABB],
     ) -> anyhow::Result<()> {
-        piet_svg_cx.append_svg_node(self.gen_svg_element(bounds));
+        piet_svg_cx.append_svg_node(self.gen_svg_element(bounds, pages_bounds));
         Ok(())
     }
 
-    /// Generates the background svg, without xml header or svg root
-    pub fn gen_svg(&self, bounds: AABB) -> Result<render::Svg, anyhow::Error> {
-        let svg_element = self.gen_svg_element(bounds);
+    /// Generates the background svg, without xml header or svg root.
+    /// The pages bounds are needed for the per-page overrides.
+    pub fn gen_svg(
+        &self,
+        bounds: AABB,
+        pages_bounds: &[AABB],
+    ) -> Result<render::Svg, anyhow::Error> {
+        let svg_element = self.gen_svg_element(bounds, pages_bounds);
 
         let svg_data = rnote_compose::utils::svg_node_to_string(&svg_element)
             .map_err(|e| anyhow::anyhow!("node_to_string() failed for background, {}", e))?;
@@ -308,12 +859,17 @@ impl Background {
         Ok(render::Svg { svg_data, bounds })
     }
 
+    /// Generates the pattern image. It has no background color, so that the tiles can be drawn on top of the page colors
     fn gen_image(
         &self,
         bounds: AABB,
         image_scale: f64,
     ) -> Result<Option<render::Image>, anyhow::Error> {
-        let svg = self.gen_svg(bounds)?;
+        let svg_data =
+            rnote_compose::utils::svg_node_to_string(&self.gen_pattern_svg_element(bounds))
+                .map_err(|e| anyhow::anyhow!("node_to_string() failed for background, {}", e))?;
+        let svg = render::Svg { svg_data, bounds };
+
         Ok(Some(render::Image::gen_image_from_svg(
             svg,
             bounds,
@@ -321,7 +877,8 @@ impl Background {
         )?))
     }
 
-    fn gen_rendernodes(&mut self, viewport: AABB) -> Result<Vec<gsk::RenderNode>, anyhow::Error> {
+    /// Generates the rendernodes of the pattern tiles covering the viewport
+    pub fn gen_rendernodes(&self, viewport: AABB) -> Result<Vec<gsk::RenderNode>, anyhow::Error> {
         let mut rendernodes: Vec<gsk::RenderNode> = vec![];
 
         if let Some(image) = &self.image {
@@ -376,24 +933,95 @@ impl Background {
         &self,
         snapshot: &Snapshot,
         doc_bounds: AABB,
-        _camera: &Camera,
+        pages_bounds: &[AABB],
+        camera: &Camera,
     ) -> anyhow::Result<()> {
-        snapshot.push_clip(&graphene::Rect::from_p2d_aabb(doc_bounds));
+        self.draw_w_rendernodes(
+            snapshot,
+            doc_bounds,
+            pages_bounds,
+            camera.viewport(),
+            &self.rendernodes,
+        )
+    }
+
+    /// Draws the background with the given rendernodes of the pattern tiles, e.g. generated for a different viewport
+    pub fn draw_w_rendernodes(
+        &self,
+        snapshot: &Snapshot,
+        doc_bounds: AABB,
+        pages_bounds: &[AABB],
+        viewport: AABB,
+        rendernodes: &[gsk::RenderNode],
+    ) -> anyhow::Result<()> {
+        let uniform_pages = pages_bounds
+            .windows(2)
+            .all(|pages| pages[0].extents() == pages[1].extents());
+
+        if uniform_pages {
+            self.draw_clipped(snapshot, doc_bounds, doc_bounds, pages_bounds, rendernodes);
+        } else {
+            // Pages with differing sizes don't fill the document bounds, so the background is drawn clipped to each page
+            for page_bounds in pages_bounds
+                .iter()
+                .filter(|page_bounds| page_bounds.intersects(&viewport))
+            {
+                self.draw_clipped(
+                    snapshot,
+                    *page_bounds,
+                    doc_bounds,
+                    pages_bounds,
+                    rendernodes,
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn draw_clipped(
+        &self,
+        snapshot: &Snapshot,
+        clip_bounds: AABB,
+        doc_bounds: AABB,
+        pages_bounds: &[AABB],
+        rendernodes: &[gsk::RenderNode],
+    ) {
+        snapshot.push_clip(&graphene::Rect::from_p2d_aabb(clip_bounds));
 
         // Fill with background color just in case there is any space left between the tiles
         snapshot.append_node(
             &gsk::ColorNode::new(
                 &gdk::RGBA::from_compose_color(self.color),
-                &graphene::Rect::from_p2d_aabb(doc_bounds),
+                &graphene::Rect::from_p2d_aabb(clip_bounds),
             )
             .upcast(),
         );
 
-        self.rendernodes.iter().for_each(|rendernode| {
+        for (page_bounds, page_color) in self.overridden_page_colors(doc_bounds, pages_bounds) {
+            snapshot.append_node(
+                &gsk::ColorNode::new(
+                    &gdk::RGBA::from_compose_color(page_color),
+                    &graphene::Rect::from_p2d_aabb(page_bounds),
+                )
+                .upcast(),
+            );
+        }
+
+        rendernodes.iter().for_each(|rendernode| {
             snapshot.append_node(&rendernode);
         });
 
+        for (line_bounds, line_color) in self.page_lines(doc_bounds, pages_bounds) {
+            snapshot.append_node(
+                &gsk::ColorNode::new(
+                    &gdk::RGBA::from_compose_color(line_color),
+                    &graphene::Rect::from_p2d_aabb(line_bounds),
+                )
+                .upcast(),
+            );
+        }
+
         snapshot.pop();
-        Ok(())
     }
 }
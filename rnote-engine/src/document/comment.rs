@@ -0,0 +1,195 @@
+use crate::store::{Author, StrokeKey};
+use crate::StrokeStore;
+
+use chrono::{DateTime, Utc};
+use p2d::bounding_volume::AABB;
+use piet::{RenderContext, Text, TextLayout, TextLayoutBuilder};
+use rnote_compose::helpers::{AABBHelpers, Vector2Helpers};
+use serde::{Deserialize, Serialize};
+
+/// A comment for reviewing, anchored to a position or a stroke.
+/// It is not part of the ink, but drawn as marker that can be expanded to show the text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename = "comment")]
+pub struct Comment {
+    /// The text of the comment
+    #[serde(rename = "text")]
+    pub text: String,
+    #[serde(rename = "author")]
+    pub author: Option<Author>,
+    #[serde(rename = "created")]
+    pub created: DateTime<Utc>,
+    /// The position the comment is anchored at, in document coords. Used when it is not anchored to a stroke, or the stroke was removed
+    #[serde(rename = "pos")]
+    pub pos: na::Vector2<f64>,
+    /// The stroke the comment is anchored to. The marker follows it when it is moved
+    #[serde(rename = "stroke")]
+    pub stroke: Option<StrokeKey>,
+    /// Resolved comments are not drawn
+    #[serde(rename = "resolved")]
+    pub resolved: bool,
+    /// Wether the marker is expanded to show the text
+    #[serde(rename = "expanded")]
+    pub expanded: bool,
+}
+
+impl Default for Comment {
+    fn default() -> Self {
+        Self {
+            text: String::default(),
+            author: None,
+            created: Utc::now(),
+            pos: na::Vector2::zeros(),
+            stroke: None,
+            resolved: false,
+            expanded: false,
+        }
+    }
+}
+
+impl Comment {
+    /// The size of the marker, in surface coords
+    pub const MARKER_SIZE: f64 = 20.0;
+    /// The width of the expanded text box, in surface coords
+    pub const TEXT_BOX_WIDTH: f64 = 240.0;
+    const TEXT_PADDING: f64 = 8.0;
+    const FONT_SIZE: f64 = 13.0;
+    const MARKER_COLOR: piet::Color = rnote_compose::color::GNOME_YELLOWS[2];
+    const TEXT_BOX_COLOR: piet::Color = piet::Color::rgba8(0xff, 0xfb, 0xe0, 0xf0);
+    const OUTLINE_COLOR: piet::Color = rnote_compose::color::GNOME_DARKS[2];
+
+    pub fn new(text: String, author: Option<Author>, pos: na::Vector2<f64>) -> Self {
+        Self {
+            text,
+            author,
+            pos,
+            ..Self::default()
+        }
+    }
+
+    /// The position of the marker, in document coords. Comments anchored to strokes are placed at the top right of the stroke
+    pub fn marker_pos(&self, store: &StrokeStore) -> na::Vector2<f64> {
+        self.stroke
+            .and_then(|key| store.get_stroke_ref(key))
+            .map(|stroke| {
+                let bounds = stroke.bounds();
+                na::vector![bounds.maxs[0], bounds.mins[1]]
+            })
+            .unwrap_or(self.pos)
+    }
+
+    /// The bounds of the marker, in document coords
+    pub fn marker_bounds(&self, store: &StrokeStore, total_zoom: f64) -> AABB {
+        let size = Self::MARKER_SIZE / total_zoom;
+        let pos = self.marker_pos(store);
+
+        AABB::new(
+            na::Point2::from(pos - na::vector![0.0, size]),
+            na::Point2::from(pos + na::vector![size, 0.0]),
+        )
+    }
+
+    /// Draws the marker, and the text box when expanded. Expects the piet context in document coords
+    pub fn draw(
+        &self,
+        cx: &mut impl RenderContext,
+        store: &StrokeStore,
+        total_zoom: f64,
+    ) -> anyhow::Result<()> {
+        let marker_bounds = self.marker_bounds(store, total_zoom);
+        let line_width = 1.0 / total_zoom;
+
+        // A speech bubble
+        let marker_rect = kurbo::RoundedRect::from_rect(
+            marker_bounds.to_kurbo_rect(),
+            Self::MARKER_SIZE * 0.25 / total_zoom,
+        );
+        cx.fill(marker_rect, &Self::MARKER_COLOR);
+        cx.stroke(marker_rect, &Self::OUTLINE_COLOR, line_width);
+
+        if !self.expanded {
+            return Ok(());
+        }
+
+        let padding = Self::TEXT_PADDING / total_zoom;
+        let text = match &self.author {
+            Some(author) => format!("{}\n{}", author.name, self.text),
+            None => self.text.clone(),
+        };
+        let text_layout = cx
+            .text()
+            .new_text_layout(text)
+            .font(piet::FontFamily::SANS_SERIF, Self::FONT_SIZE / total_zoom)
+            .text_color(Self::OUTLINE_COLOR)
+            .max_width(Self::TEXT_BOX_WIDTH / total_zoom - 2.0 * padding)
+            .build()
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        let text_pos = na::vector![
+            marker_bounds.maxs[0] + padding * 2.0,
+            marker_bounds.mins[1] + padding
+        ];
+        let text_box = kurbo::RoundedRect::from_rect(
+            kurbo::Rect::from_origin_size(
+                (text_pos - na::Vector2::repeat(padding)).to_kurbo_point(),
+                (
+                    Self::TEXT_BOX_WIDTH / total_zoom,
+                    text_layout.size().height + 2.0 * padding,
+                ),
+            ),
+            padding * 0.5,
+        );
+        cx.fill(text_box, &Self::TEXT_BOX_COLOR);
+        cx.stroke(text_box, &Self::OUTLINE_COLOR, line_width);
+        cx.draw_text(&text_layout, text_pos.to_kurbo_point());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::strokes::Stroke;
+    use crate::RnoteEngine;
+    use approx::assert_relative_eq;
+    use rnote_compose::shapes::ShapeBehaviour;
+
+    #[test]
+    fn comment_markers() {
+        let mut engine = RnoteEngine::default();
+        let key = engine.store.insert_stroke(
+            Stroke::line_fixture(na::vector![0.0, 0.0], na::vector![100.0, 50.0]),
+            None,
+        );
+
+        let (first, _) = engine.add_comment(String::from("Typo"), na::vector![300.0, 300.0], None);
+        let (second, _) = engine.add_comment(
+            String::from("Extend the line"),
+            na::vector![0.0, 0.0],
+            Some(key),
+        );
+        assert_eq!(engine.comments().len(), 2);
+
+        engine
+            .store
+            .translate_strokes(&[key], na::vector![10.0, 20.0]);
+        let marker_pos = engine.comments()[second].marker_pos(&engine.store);
+        assert_relative_eq!(
+            marker_pos,
+            na::vector![
+                engine.store.get_stroke_ref(key).unwrap().bounds().maxs[0],
+                engine.store.get_stroke_ref(key).unwrap().bounds().mins[1]
+            ]
+        );
+        assert_eq!(
+            engine.comment_marker_at(marker_pos + na::vector![1.0, -1.0]),
+            Some(second)
+        );
+
+        let _ = engine.resolve_comment(first, true).unwrap();
+        assert_eq!(engine.comment_marker_at(na::vector![301.0, 299.0]), None);
+        assert!(engine.resolve_comment(5, true).is_err());
+
+        engine.check_state_roundtrip().unwrap();
+    }
+}
@@ -72,6 +72,18 @@ impl PredefinedFormat {
             PredefinedFormat::Custom => None,
         }
     }
+
+    /// The portrait size in the unit the format is defined in, so it can be converted exactly to any dpi.
+    /// The ISO formats are defined in millimeters, the US formats in inches.
+    pub fn size_portrait_w_unit(&self) -> Option<(na::Vector2<f64>, MeasureUnit)> {
+        match self {
+            PredefinedFormat::UsLetter => Some((na::vector![8.5, 11.0], MeasureUnit::In)),
+            PredefinedFormat::UsLegal => Some((na::vector![8.5, 14.0], MeasureUnit::In)),
+            _ => self
+                .size_portrait_mm()
+                .map(|(width, height)| (na::vector![width, height], MeasureUnit::Mm)),
+        }
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Copy, glib::Enum, Serialize, Deserialize)]
@@ -88,6 +100,9 @@ pub enum MeasureUnit {
     #[enum_value(name = "Centimeter", nick = "cm")]
     #[serde(rename = "cm")]
     Cm,
+    #[enum_value(name = "Inch", nick = "in")]
+    #[serde(rename = "in")]
+    In,
 }
 
 impl Default for MeasureUnit {
@@ -110,12 +125,14 @@ impl MeasureUnit {
             MeasureUnit::Px => value,
             MeasureUnit::Mm => (value / Self::AMOUNT_MM_IN_INCH) * value_dpi,
             MeasureUnit::Cm => ((value * 10.0) / Self::AMOUNT_MM_IN_INCH) * value_dpi,
+            MeasureUnit::In => value * value_dpi,
         };
 
         match desired_unit {
             MeasureUnit::Px => value_in_px,
             MeasureUnit::Mm => (value_in_px / desired_dpi) * Self::AMOUNT_MM_IN_INCH,
-            MeasureUnit::Cm => (value_in_px / desired_dpi) * Self::AMOUNT_MM_IN_INCH * 10.0,
+            MeasureUnit::Cm => (value_in_px / desired_dpi) * Self::AMOUNT_MM_IN_INCH / 10.0,
+            MeasureUnit::In => value_in_px / desired_dpi,
         }
     }
 }
@@ -142,18 +159,29 @@ impl Default for Orientation {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default, rename = "format")]
 pub struct Format {
+    /// The width in px
     #[serde(rename = "width")]
     pub width: f64,
+    /// The height in px
     #[serde(rename = "height")]
     pub height: f64,
     #[serde(rename = "dpi")]
     pub dpi: f64,
+    /// The unit the size was configured in. When it is not px, the size in px is derived from `unit_size` and the dpi
+    #[serde(rename = "unit")]
+    pub unit: MeasureUnit,
+    /// The configured size, in `unit`
+    #[serde(rename = "unit_size")]
+    pub unit_size: na::Vector2<f64>,
     #[serde(rename = "orientation")]
     pub orientation: Orientation,
     #[serde(rename = "border_color")]
     pub border_color: Color,
     #[serde(rename = "show_borders")]
     pub show_borders: bool,
+    /// The margin on all sides of the pages, drawn as guides for the printable area
+    #[serde(rename = "margin")]
+    pub margin: f64,
 }
 
 impl Default for Format {
@@ -162,9 +190,12 @@ impl Default for Format {
             width: Self::WIDTH_DEFAULT,
             height: Self::HEIGHT_DEFAULT,
             dpi: Self::DPI_DEFAULT,
+            unit: MeasureUnit::Px,
+            unit_size: na::vector![Self::WIDTH_DEFAULT, Self::HEIGHT_DEFAULT],
             orientation: Orientation::default(),
             border_color: Color::from(Self::BORDER_COLOR_DEFAULT),
             show_borders: true,
+            margin: Self::MARGIN_DEFAULT,
         }
     }
 }
@@ -182,7 +213,104 @@ impl Format {
     pub const DPI_MAX: f64 = 5000.0;
     pub const DPI_DEFAULT: f64 = 96.0;
 
+    pub const MARGIN_MIN: f64 = 0.0;
+    pub const MARGIN_MAX: f64 = 5000.0;
+    pub const MARGIN_DEFAULT: f64 = 0.0;
+
     pub const BORDER_COLOR_DEFAULT: piet::Color = color::GNOME_BRIGHTS[2];
+    /// The opacity of the margin guides, relative to the border color
+    const MARGIN_GUIDES_OPACITY: f64 = 0.4;
+
+    /// A format with the size of the predefined format in the given orientation. None for `PredefinedFormat::Custom`
+    pub fn from_predefined(
+        predefined_format: PredefinedFormat,
+        orientation: Orientation,
+        dpi: f64,
+    ) -> Option<Self> {
+        let (mut size, unit) = predefined_format.size_portrait_w_unit()?;
+        if orientation == Orientation::Landscape {
+            size = na::vector![size[1], size[0]];
+        }
+
+        let mut format = Self {
+            orientation,
+            dpi: dpi.clamp(Self::DPI_MIN, Self::DPI_MAX),
+            ..Self::default()
+        };
+        format.set_size_in_unit(size, unit);
+
+        Some(format)
+    }
+
+    /// Sets the size in the given unit. The size in px is derived from it with the current dpi
+    pub fn set_size_in_unit(&mut self, size: na::Vector2<f64>, unit: MeasureUnit) {
+        self.unit = unit;
+        self.unit_size = size;
+
+        if unit == MeasureUnit::Px {
+            self.width = size[0].clamp(Self::WIDTH_MIN, Self::WIDTH_MAX);
+            self.height = size[1].clamp(Self::HEIGHT_MIN, Self::HEIGHT_MAX);
+        } else {
+            self.update_size_from_unit();
+        }
+    }
+
+    /// Sets the dpi. When the size is configured in a physical unit, the size in px is recalculated from it,
+    /// so changing the dpi back and forth round-trips exactly
+    pub fn set_dpi(&mut self, dpi: f64) {
+        self.dpi = dpi.clamp(Self::DPI_MIN, Self::DPI_MAX);
+        self.update_size_from_unit();
+    }
+
+    /// The width converted to the given unit
+    pub fn width_in_unit(&self, unit: MeasureUnit) -> f64 {
+        if self.unit != MeasureUnit::Px && unit == self.unit {
+            return self.unit_size[0];
+        }
+        MeasureUnit::convert_measurement(self.width, MeasureUnit::Px, self.dpi, unit, self.dpi)
+    }
+
+    /// The height converted to the given unit
+    pub fn height_in_unit(&self, unit: MeasureUnit) -> f64 {
+        if self.unit != MeasureUnit::Px && unit == self.unit {
+            return self.unit_size[1];
+        }
+        MeasureUnit::convert_measurement(self.height, MeasureUnit::Px, self.dpi, unit, self.dpi)
+    }
+
+    fn update_size_from_unit(&mut self) {
+        // The size in px does not depend on the dpi
+        if self.unit == MeasureUnit::Px {
+            return;
+        }
+
+        self.width = MeasureUnit::convert_measurement(
+            self.unit_size[0],
+            self.unit,
+            self.dpi,
+            MeasureUnit::Px,
+            self.dpi,
+        )
+        .clamp(Self::WIDTH_MIN, Self::WIDTH_MAX);
+        self.height = MeasureUnit::convert_measurement(
+            self.unit_size[1],
+            self.unit,
+            self.dpi,
+            MeasureUnit::Px,
+            self.dpi,
+        )
+        .clamp(Self::HEIGHT_MIN, Self::HEIGHT_MAX);
+    }
+
+    /// The printable area of the page with the given bounds, inside the margins.
+    /// The margins are shrunk when they don't fit into the page.
+    pub fn printable_bounds(&self, page_bounds: AABB) -> AABB {
+        let margin = na::Vector2::repeat(self.margin)
+            .inf(&(page_bounds.half_extents() - na::Vector2::repeat(0.5)))
+            .sup(&na::Vector2::zeros());
+
+        AABB::new(page_bounds.mins + margin, page_bounds.maxs - margin)
+    }
 
     fn draw_origin_indicator(camera: &Camera) -> anyhow::Result<gsk::RenderNode> {
         const PATH_COLOR: piet::Color = color::GNOME_GREENS[4];
@@ -226,6 +354,7 @@ impl Format {
         &self,
         snapshot: &Snapshot,
         doc_bounds: AABB,
+        pages_bounds: &[AABB],
         camera: &Camera,
     ) -> anyhow::Result<()> {
         if self.show_borders {
@@ -235,9 +364,7 @@ impl Format {
 
             snapshot.push_clip(&graphene::Rect::from_p2d_aabb(doc_bounds.loosened(2.0)));
 
-            for page_bounds in
-                doc_bounds.split_extended_origin_aligned(na::vector![self.width, self.height])
-            {
+            for &page_bounds in pages_bounds {
                 if !page_bounds.intersects(&viewport) {
                     continue;
                 }
@@ -264,7 +391,37 @@ impl Format {
                         gdk::RGBA::from_compose_color(self.border_color),
                         gdk::RGBA::from_compose_color(self.border_color),
                     ],
-                )
+                );
+
+                if self.margin > 0.0 {
+                    let guides_color = Color {
+                        a: self.border_color.a * Self::MARGIN_GUIDES_OPACITY,
+                        ..self.border_color
+                    };
+                    let printable_rect = gsk::RoundedRect::new(
+                        graphene::Rect::from_p2d_aabb(self.printable_bounds(page_bounds)),
+                        graphene::Size::zero(),
+                        graphene::Size::zero(),
+                        graphene::Size::zero(),
+                        graphene::Size::zero(),
+                    );
+
+                    snapshot.append_border(
+                        &printable_rect,
+                        &[
+                            border_width as f32,
+                            border_width as f32,
+                            border_width as f32,
+                            border_width as f32,
+                        ],
+                        &[
+                            gdk::RGBA::from_compose_color(guides_color),
+                            gdk::RGBA::from_compose_color(guides_color),
+                            gdk::RGBA::from_compose_color(guides_color),
+                            gdk::RGBA::from_compose_color(guides_color),
+                        ],
+                    )
+                }
             }
 
             snapshot.pop();
@@ -0,0 +1,57 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The metadata of the document. Written into the metadata of exported PDFs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename = "metadata")]
+pub struct Metadata {
+    /// The title
+    #[serde(rename = "title")]
+    pub title: String,
+    /// The author
+    #[serde(rename = "author")]
+    pub author: String,
+    /// The tags, e.g. for searching and organizing documents
+    #[serde(rename = "tags")]
+    pub tags: Vec<String>,
+    /// When the document was first saved
+    #[serde(rename = "created")]
+    pub created: Option<DateTime<Utc>>,
+    /// When the document was last saved
+    #[serde(rename = "modified")]
+    pub modified: Option<DateTime<Utc>>,
+    /// The version of the application which last saved the document
+    #[serde(rename = "app_version")]
+    pub app_version: String,
+}
+
+impl Metadata {
+    /// Updates the timestamps and the app version for saving the document
+    pub fn touch(&mut self, app_version: &str) {
+        let now = Utc::now();
+
+        self.created.get_or_insert(now);
+        self.modified = Some(now);
+        self.app_version = app_version.to_string();
+    }
+
+    /// The tags as comma separated list
+    pub fn tags_joined(&self) -> String {
+        self.tags.join(", ")
+    }
+
+    /// Sets the tags from a comma separated list. Empty tags are skipped
+    pub fn set_tags_from_joined(&mut self, tags: &str) {
+        self.tags = tags
+            .split(',')
+            .map(|tag| tag.trim())
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| tag.to_string())
+            .collect();
+    }
+
+    /// Formats a timestamp as ISO 8601 date, as expected by the PDF metadata
+    pub fn format_pdf_date(datetime: &DateTime<Utc>) -> String {
+        datetime.to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+}
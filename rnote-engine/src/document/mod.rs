@@ -1,10 +1,25 @@
 pub mod background;
+pub mod comment;
 pub mod format;
+pub mod metadata;
+pub mod outline;
+pub mod pagenumbers;
+pub mod section;
+pub mod snapgrid;
+pub mod viewbookmark;
 
 // Re-exports
 pub use background::Background;
+pub use comment::Comment;
 pub use format::Format;
+use format::Orientation;
+pub use metadata::Metadata;
+pub use outline::OutlineEntry;
+pub use pagenumbers::PageNumbers;
 use rnote_compose::Color;
+pub use section::Section;
+pub use snapgrid::SnapGrid;
+pub use viewbookmark::ViewBookmark;
 
 use crate::utils::{GdkRGBAHelpers, GrapheneRectHelpers};
 use crate::{Camera, StrokeStore};
@@ -13,6 +28,8 @@ use rnote_compose::helpers::AABBHelpers;
 use gtk4::{gdk, graphene, gsk, Snapshot};
 use p2d::bounding_volume::{BoundingVolume, AABB};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::Range;
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[serde(rename = "layout")]
@@ -23,6 +40,8 @@ pub enum Layout {
     ContinuousVertical,
     #[serde(rename = "infinite")]
     Infinite,
+    #[serde(rename = "continuous_horizontal")]
+    ContinuousHorizontal,
 }
 
 impl Default for Layout {
@@ -31,6 +50,26 @@ impl Default for Layout {
     }
 }
 
+impl Layout {
+    /// Wether the pages are stacked vertically in a single column, so that pages can be inserted and removed
+    pub fn pages_stacked_vertically(self) -> bool {
+        matches!(self, Self::FixedSize | Self::ContinuousVertical)
+    }
+}
+
+/// The state of the document which changes with inserting, deleting and moving pages.
+/// Recorded in the history together with the strokes, see `StrokeStore::record_pages_state()`
+#[derive(Debug, Clone, Default)]
+pub struct PagesState {
+    height: f64,
+    page_orientations: BTreeMap<usize, Orientation>,
+    page_overrides: BTreeMap<usize, background::PageBackgroundOverride>,
+    sections: Vec<Section>,
+    outline: Vec<OutlineEntry>,
+    view_bookmarks: Vec<ViewBookmark>,
+    comments: Vec<Comment>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default, rename = "document")]
 pub struct Document {
@@ -48,6 +87,31 @@ pub struct Document {
     pub background: Background,
     #[serde(rename = "layout", alias = "expand_mode")]
     layout: Layout,
+    /// The section markers, ordered by their starting page
+    #[serde(rename = "sections")]
+    sections: Vec<Section>,
+    /// The metadata, like title and author
+    #[serde(rename = "metadata")]
+    pub metadata: Metadata,
+    /// The page numbers, rendered on the pages with content
+    #[serde(rename = "page_numbers")]
+    pub page_numbers: PageNumbers,
+    /// The outline entries, in their user defined order
+    #[serde(rename = "outline")]
+    outline: Vec<OutlineEntry>,
+    /// The orientations of the pages which differ from the format orientation, by page index.
+    /// Only respected in the fixed size layout
+    #[serde(rename = "page_orientations")]
+    page_orientations: BTreeMap<usize, Orientation>,
+    /// The bookmarks of camera views, in the order they were saved
+    #[serde(rename = "view_bookmarks")]
+    view_bookmarks: Vec<ViewBookmark>,
+    /// The review comments, in the order they were added
+    #[serde(rename = "comments")]
+    comments: Vec<Comment>,
+    /// The grid the selection is snapped to when moving and resizing it
+    #[serde(rename = "snap_grid")]
+    pub snap_grid: SnapGrid,
 }
 
 impl Default for Document {
@@ -60,12 +124,22 @@ impl Default for Document {
             format: Format::default(),
             background: Background::default(),
             layout: Layout::default(),
+            sections: vec![],
+            metadata: Metadata::default(),
+            page_numbers: PageNumbers::default(),
+            outline: vec![],
+            page_orientations: BTreeMap::new(),
+            view_bookmarks: vec![],
+            comments: vec![],
+            snap_grid: SnapGrid::default(),
         }
     }
 }
 
 impl Document {
     pub const SHADOW_WIDTH: f64 = 30.0;
+    /// The tolerance when stacking pages, so rounding errors don't add an extra page
+    const PAGE_STACK_EPSILON: f64 = 0.5;
     pub const SHADOW_OFFSET: na::Vector2<f64> = na::vector![8.0, 8.0];
     pub const SHADOW_COLOR: Color = Color {
         r: 0.1,
@@ -74,6 +148,65 @@ impl Document {
         a: 0.3,
     };
 
+    /// Swaps the data which belongs to the file, and is not part of the engine config, with the other document
+    pub(crate) fn swap_file_data(&mut self, other: &mut Document) {
+        std::mem::swap(&mut self.metadata, &mut other.metadata);
+        std::mem::swap(
+            &mut self.background.page_overrides,
+            &mut other.background.page_overrides,
+        );
+        std::mem::swap(&mut self.sections, &mut other.sections);
+        std::mem::swap(&mut self.outline, &mut other.outline);
+        std::mem::swap(&mut self.page_orientations, &mut other.page_orientations);
+        std::mem::swap(&mut self.view_bookmarks, &mut other.view_bookmarks);
+        std::mem::swap(&mut self.snap_grid, &mut other.snap_grid);
+        std::mem::swap(&mut self.comments, &mut other.comments);
+    }
+
+    /// Resets the data which belongs to the file, and is not part of the engine config
+    pub(crate) fn clear_file_data(&mut self) {
+        self.swap_file_data(&mut Document::default());
+    }
+
+    /// The current state of the pages, to be recorded in the history
+    pub(crate) fn pages_state(&self) -> PagesState {
+        PagesState {
+            height: self.height,
+            page_orientations: self.page_orientations.clone(),
+            page_overrides: self.background.page_overrides.clone(),
+            sections: self.sections.clone(),
+            outline: self.outline.clone(),
+            view_bookmarks: self.view_bookmarks.clone(),
+            comments: self.comments.clone(),
+        }
+    }
+
+    /// Imports the state of the pages, e.g. when undoing a page operation
+    pub(crate) fn import_pages_state(&mut self, pages_state: &PagesState) {
+        self.height = pages_state.height;
+        self.page_orientations = pages_state.page_orientations.clone();
+        self.background.page_overrides = pages_state.page_overrides.clone();
+        self.sections = pages_state.sections.clone();
+        self.outline = pages_state.outline.clone();
+        self.view_bookmarks = pages_state.view_bookmarks.clone();
+        self.comments = pages_state.comments.clone();
+
+        self.fit_width_to_pages();
+    }
+
+    /// Maps the vertical positions of the outline entries, view bookmarks and comments, so that they move together with their pages
+    pub(crate) fn map_page_anchors_y(&mut self, map: impl Fn(f64) -> f64) {
+        for entry in self.outline.iter_mut() {
+            entry.pos[1] = map(entry.pos[1]);
+        }
+        for bookmark in self.view_bookmarks.iter_mut() {
+            bookmark.pos[1] = map(bookmark.pos[1]);
+        }
+        for comment in self.comments.iter_mut() {
+            comment.pos[1] = map(comment.pos[1]);
+        }
+    }
+
     pub(crate) fn layout(&self) -> Layout {
         self.layout
     }
@@ -84,6 +217,309 @@ impl Document {
         self.resize_to_fit_strokes(store, camera);
     }
 
+    /// The section markers, ordered by their starting page
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    /// Adds a section marker, keeping the sections ordered by their starting page. Returns the index of the added section.
+    /// An existing section starting on the same page is replaced.
+    pub fn add_section(&mut self, section: Section) -> usize {
+        match self
+            .sections
+            .binary_search_by_key(&section.start_page, |s| s.start_page)
+        {
+            Ok(i) => {
+                self.sections[i] = section;
+                i
+            }
+            Err(i) => {
+                self.sections.insert(i, section);
+                i
+            }
+        }
+    }
+
+    /// Removes the section marker with the given index
+    pub fn remove_section(&mut self, index: usize) -> Option<Section> {
+        if index < self.sections.len() {
+            Some(self.sections.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /// The index of the section the page with the given index belongs to
+    pub fn section_index_for_page(&self, page: usize) -> Option<usize> {
+        self.sections
+            .iter()
+            .rposition(|section| section.start_page <= page)
+    }
+
+    /// Shifts the sections for a new page inserted at the given index
+    pub(crate) fn insert_page_in_sections(&mut self, page: usize) {
+        for section in self.sections.iter_mut() {
+            if section.start_page >= page {
+                section.start_page += 1;
+            }
+        }
+    }
+
+    /// Shifts the sections for a page removed at the given index.
+    /// A section starting on the removed page then starts on the subsequent page, and is dropped if another section already starts there.
+    pub(crate) fn remove_page_in_sections(&mut self, page: usize) {
+        if self
+            .sections
+            .iter()
+            .any(|section| section.start_page == page + 1)
+        {
+            self.sections.retain(|section| section.start_page != page);
+        }
+
+        for section in self.sections.iter_mut() {
+            if section.start_page > page {
+                section.start_page -= 1;
+            }
+        }
+    }
+
+    /// The outline entries
+    pub fn outline(&self) -> &[OutlineEntry] {
+        &self.outline
+    }
+
+    /// Mutable access to the outline entries
+    pub(crate) fn outline_mut(&mut self) -> &mut Vec<OutlineEntry> {
+        &mut self.outline
+    }
+
+    /// Snaps the point to the snap grid. The point is returned unchanged when snapping is disabled
+    pub fn snap_point(&self, point: na::Vector2<f64>) -> na::Vector2<f64> {
+        self.snap_grid.snap_point(point, &self.background)
+    }
+
+    /// The view bookmarks
+    pub fn view_bookmarks(&self) -> &[ViewBookmark] {
+        &self.view_bookmarks
+    }
+
+    /// Mutable access to the view bookmarks
+    pub(crate) fn view_bookmarks_mut(&mut self) -> &mut Vec<ViewBookmark> {
+        &mut self.view_bookmarks
+    }
+
+    /// The comments
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
+    }
+
+    /// Mutable access to the comments
+    pub(crate) fn comments_mut(&mut self) -> &mut Vec<Comment> {
+        &mut self.comments
+    }
+
+    /// The orientation of the page with the given index
+    pub fn page_orientation(&self, page: usize) -> Orientation {
+        if self.layout != Layout::FixedSize {
+            return self.format.orientation;
+        }
+        self.page_orientations
+            .get(&page)
+            .copied()
+            .unwrap_or(self.format.orientation)
+    }
+
+    /// Sets the orientation of the page with the given index. The document needs to be resized afterwards
+    pub(crate) fn set_page_orientation(&mut self, page: usize, orientation: Orientation) {
+        if orientation == self.format.orientation {
+            self.page_orientations.remove(&page);
+        } else {
+            self.page_orientations.insert(page, orientation);
+        }
+    }
+
+    /// Inserts the orientation for a new page at the given index, shifting the orientations of the subsequent pages.
+    pub(crate) fn insert_page_orientation(&mut self, page: usize, orientation: Orientation) {
+        let subsequent = self.page_orientations.split_off(&page);
+        self.page_orientations.extend(
+            subsequent
+                .into_iter()
+                .map(|(page, orientation)| (page + 1, orientation)),
+        );
+
+        self.set_page_orientation(page, orientation);
+    }
+
+    /// Removes the orientation of a removed page at the given index, shifting the orientations of the subsequent pages.
+    pub(crate) fn remove_page_orientation(&mut self, page: usize) -> Orientation {
+        let removed = self.page_orientation(page);
+
+        let mut subsequent = self.page_orientations.split_off(&page);
+        subsequent.remove(&page);
+        self.page_orientations.extend(
+            subsequent
+                .into_iter()
+                .map(|(page, orientation)| (page - 1, orientation)),
+        );
+
+        removed
+    }
+
+    /// The size of the page with the given index. The format size, swapped when the page orientation differs from the format orientation
+    pub fn page_size(&self, page: usize) -> na::Vector2<f64> {
+        if self.page_orientation(page) == self.format.orientation {
+            na::vector![self.format.width, self.format.height]
+        } else {
+            na::vector![self.format.height, self.format.width]
+        }
+    }
+
+    /// A standalone document with the pages in the given range, with the pages stacked vertically from the origin.
+    /// `pages_offsets` holds the bounds of each extracted page and the offset it is moved by.
+    /// The sections, outline entries, comments and per page settings of the extracted pages are kept.
+    pub(crate) fn extract_pages(
+        &self,
+        range: Range<usize>,
+        pages_offsets: &[(AABB, na::Vector2<f64>)],
+    ) -> Self {
+        let mut doc = self.clone();
+
+        if !doc.layout.pages_stacked_vertically() {
+            doc.layout = Layout::FixedSize;
+        }
+
+        // The section of the first page continues on it, even if it starts before
+        doc.sections = self
+            .sections
+            .iter()
+            .enumerate()
+            .filter_map(|(i, section)| {
+                if range.contains(&section.start_page) {
+                    Some(Section {
+                        start_page: section.start_page - range.start,
+                        ..section.clone()
+                    })
+                } else if Some(i) == self.section_index_for_page(range.start) {
+                    Some(Section {
+                        start_page: 0,
+                        ..section.clone()
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        doc.outline = self
+            .outline
+            .iter()
+            .filter_map(|entry| {
+                let (_, offset) = pages_offsets.iter().find(|(page_bounds, _)| {
+                    page_bounds.contains_local_point(&na::Point2::from(entry.pos))
+                })?;
+
+                Some(OutlineEntry::new(entry.name.clone(), entry.pos + offset))
+            })
+            .collect();
+        doc.view_bookmarks = self
+            .view_bookmarks
+            .iter()
+            .filter_map(|bookmark| {
+                let (_, offset) = pages_offsets.iter().find(|(page_bounds, _)| {
+                    page_bounds.contains_local_point(&na::Point2::from(bookmark.pos))
+                })?;
+
+                Some(ViewBookmark::new(
+                    bookmark.name.clone(),
+                    bookmark.pos + offset,
+                    bookmark.zoom,
+                ))
+            })
+            .collect();
+        // The strokes get new keys in the extracted document, so the comments only keep their position
+        doc.comments = self
+            .comments
+            .iter()
+            .filter_map(|comment| {
+                let (_, offset) = pages_offsets.iter().find(|(page_bounds, _)| {
+                    page_bounds.contains_local_point(&na::Point2::from(comment.pos))
+                })?;
+
+                Some(Comment {
+                    pos: comment.pos + offset,
+                    stroke: None,
+                    ..comment.clone()
+                })
+            })
+            .collect();
+
+        doc.page_orientations = range
+            .clone()
+            .filter_map(|page| Some((page - range.start, *self.page_orientations.get(&page)?)))
+            .collect();
+        doc.background.page_overrides = range
+            .clone()
+            .filter_map(|page| {
+                Some((
+                    page - range.start,
+                    self.background.page_overrides.get(&page)?.clone(),
+                ))
+            })
+            .collect();
+
+        let pages_extents =
+            pages_offsets
+                .iter()
+                .fold(na::Vector2::<f64>::zeros(), |acc, (page_bounds, _)| {
+                    na::vector![
+                        acc[0].max(page_bounds.extents()[0]),
+                        acc[1] + page_bounds.extents()[1]
+                    ]
+                });
+        doc.x = 0.0;
+        doc.y = 0.0;
+        doc.width = pages_extents[0];
+        doc.height = pages_extents[1];
+
+        doc
+    }
+
+    /// Fits the width of the document to the widest page, in the fixed size layout
+    pub(crate) fn fit_width_to_pages(&mut self) {
+        if self.layout != Layout::FixedSize {
+            return;
+        }
+
+        self.width = self
+            .stacked_pages_bounds(self.height)
+            .iter()
+            .map(|page_bounds| page_bounds.extents()[0])
+            .fold(0.0, f64::max);
+    }
+
+    /// Wether the pages have differing orientations, so they can't be laid out in a regular grid
+    fn has_mixed_page_orientations(&self) -> bool {
+        self.layout == Layout::FixedSize && !self.page_orientations.is_empty()
+    }
+
+    /// The bounds of the pages stacked vertically from the document origin, with their individual sizes.
+    /// Pages are added until they cover the given height, at least one page.
+    fn stacked_pages_bounds(&self, height: f64) -> Vec<AABB> {
+        let mut pages_bounds = vec![];
+        let mut page_top = self.y;
+
+        while pages_bounds.is_empty() || page_top < self.y + height - Self::PAGE_STACK_EPSILON {
+            let page_size = self.page_size(pages_bounds.len());
+            pages_bounds.push(AABB::new(
+                na::point![self.x, page_top],
+                na::point![self.x + page_size[0], page_top + page_size[1]],
+            ));
+            page_top += page_size[1];
+        }
+
+        pages_bounds
+    }
+
     pub fn bounds(&self) -> AABB {
         AABB::new(
             na::point![self.x, self.y],
@@ -96,6 +532,10 @@ impl Document {
         let doc_bounds = self.bounds();
 
         if self.format.height > 0.0 && self.format.width > 0.0 {
+            if self.has_mixed_page_orientations() {
+                return self.stacked_pages_bounds(self.height);
+            }
+
             doc_bounds
                 .split_extended_origin_aligned(na::vector![self.format.width, self.format.height])
         } else {
@@ -103,9 +543,20 @@ impl Document {
         }
     }
 
+    /// The index into `pages_bounds()` of the page containing the given position
+    pub fn page_index_at(&self, pos: na::Point2<f64>) -> Option<usize> {
+        self.pages_bounds()
+            .iter()
+            .position(|page_bounds| page_bounds.contains_local_point(&pos))
+    }
+
     pub fn calc_n_pages(&self) -> u32 {
         // Avoid div by 0
         if self.format.height > 0.0 && self.format.width > 0.0 {
+            if self.has_mixed_page_orientations() {
+                return self.pages_bounds().len() as u32;
+            }
+
             (self.width / self.format.width).round() as u32
                 * (self.height / self.format.height).round() as u32
         } else {
@@ -125,6 +576,9 @@ impl Document {
                 self.resize_doc_infinite_layout_to_fit_strokes(store);
                 self.expand_doc_infinite_layout(camera.viewport());
             }
+            Layout::ContinuousHorizontal => {
+                self.resize_doc_continuous_horizontal_layout(store);
+            }
         }
     }
 
@@ -140,10 +594,28 @@ impl Document {
                 self.resize_doc_infinite_layout_to_fit_strokes(store);
                 self.expand_doc_infinite_layout(camera.viewport());
             }
+            Layout::ContinuousHorizontal => {
+                self.resize_doc_continuous_horizontal_layout(store);
+            }
         }
     }
 
     pub(crate) fn resize_doc_fixed_size_layout(&mut self, store: &StrokeStore) {
+        if self.has_mixed_page_orientations() {
+            let pages_bounds = self.stacked_pages_bounds(store.calc_height().max(1.0));
+            let pages_extents = pages_bounds
+                .iter()
+                .fold(AABB::new_invalid(), |acc, page_bounds| {
+                    acc.merged(page_bounds)
+                })
+                .extents();
+
+            self.x = 0.0;
+            self.y = 0.0;
+            self.width = pages_extents[0];
+            self.height = pages_extents[1];
+            return;
+        }
         let format_height = self.format.height;
 
         let new_width = self.format.width;
@@ -167,6 +639,17 @@ impl Document {
         self.height = new_height;
     }
 
+    pub(crate) fn resize_doc_continuous_horizontal_layout(&mut self, store: &StrokeStore) {
+        let padding_right = self.format.width;
+        let new_width = store.calc_width() + padding_right;
+        let new_height = self.format.height;
+
+        self.x = 0.0;
+        self.y = 0.0;
+        self.width = new_width;
+        self.height = new_height;
+    }
+
     pub(crate) fn expand_doc_infinite_layout(&mut self, viewport: AABB) {
         let padding_horizontal = self.format.width * 2.0;
         let padding_vertical = self.format.height * 2.0;
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// An entry of the document outline, a named bookmark pointing to a position in the document
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename = "outline_entry")]
+pub struct OutlineEntry {
+    /// The name of the entry
+    #[serde(rename = "name")]
+    pub name: String,
+    /// The position in the document the entry points to
+    #[serde(rename = "pos")]
+    pub pos: na::Vector2<f64>,
+}
+
+impl OutlineEntry {
+    pub fn new(name: String, pos: na::Vector2<f64>) -> Self {
+        Self { name, pos }
+    }
+}
@@ -0,0 +1,165 @@
+use p2d::bounding_volume::AABB;
+use piet::{RenderContext, Text, TextLayout, TextLayoutBuilder};
+use rnote_compose::helpers::Vector2Helpers;
+use rnote_compose::Color;
+use serde::{Deserialize, Serialize};
+
+/// The position of the page numbers on the pages
+#[derive(
+    Debug,
+    Eq,
+    PartialEq,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    num_derive::FromPrimitive,
+    num_derive::ToPrimitive,
+)]
+#[serde(rename = "page_number_position")]
+pub enum PageNumberPosition {
+    #[serde(rename = "top_left")]
+    TopLeft = 0,
+    #[serde(rename = "top_center")]
+    TopCenter,
+    #[serde(rename = "top_right")]
+    TopRight,
+    #[serde(rename = "bottom_left")]
+    BottomLeft,
+    #[serde(rename = "bottom_center")]
+    BottomCenter,
+    #[serde(rename = "bottom_right")]
+    BottomRight,
+}
+
+impl Default for PageNumberPosition {
+    fn default() -> Self {
+        Self::BottomCenter
+    }
+}
+
+impl TryFrom<u32> for PageNumberPosition {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        num_traits::FromPrimitive::from_u32(value).ok_or_else(|| {
+            anyhow::anyhow!(
+                "PageNumberPosition try_from::<u32>() for value {} failed",
+                value
+            )
+        })
+    }
+}
+
+/// The page numbers, rendered on the pages with content
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename = "page_numbers")]
+pub struct PageNumbers {
+    /// Wether page numbers are shown
+    #[serde(rename = "show")]
+    pub show: bool,
+    /// The position on the pages
+    #[serde(rename = "position")]
+    pub position: PageNumberPosition,
+    /// The format string. `{n}` is replaced with the page number, `{total}` with the number of pages
+    #[serde(rename = "format_string")]
+    pub format_string: String,
+    /// The number of the first page
+    #[serde(rename = "start_number")]
+    pub start_number: u32,
+    /// The text color
+    #[serde(rename = "color")]
+    pub color: Color,
+}
+
+impl Default for PageNumbers {
+    fn default() -> Self {
+        Self {
+            show: false,
+            position: PageNumberPosition::default(),
+            format_string: String::from(Self::FORMAT_STRING_DEFAULT),
+            start_number: 1,
+            color: Self::COLOR_DEFAULT,
+        }
+    }
+}
+
+impl PageNumbers {
+    pub const FORMAT_STRING_DEFAULT: &'static str = "{n}";
+    pub const COLOR_DEFAULT: Color = Color {
+        r: 0.4,
+        g: 0.4,
+        b: 0.4,
+        a: 1.0,
+    };
+    const FONT_SIZE: f64 = 14.0;
+    /// The distance of the page numbers from the page edges
+    const EDGE_DISTANCE: f64 = 32.0;
+
+    /// The text for the page with the given index
+    pub fn page_text(&self, index: usize, n_pages: usize) -> String {
+        let number = self.start_number as usize + index;
+        let total = self.start_number as usize + n_pages.saturating_sub(1);
+
+        self.format_string
+            .replace("{n}", &number.to_string())
+            .replace("{total}", &total.to_string())
+    }
+
+    /// Draws the page number for the page with the given index and bounds
+    pub fn draw_page_number(
+        &self,
+        cx: &mut impl RenderContext,
+        page_bounds: AABB,
+        index: usize,
+        n_pages: usize,
+    ) -> anyhow::Result<()> {
+        let text_layout = cx
+            .text()
+            .new_text_layout(self.page_text(index, n_pages))
+            .text_color(self.color.into())
+            .font(piet::FontFamily::SANS_SERIF, Self::FONT_SIZE)
+            .build()
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let text_size = na::vector![text_layout.size().width, text_layout.size().height];
+
+        let x = match self.position {
+            PageNumberPosition::TopLeft | PageNumberPosition::BottomLeft => {
+                page_bounds.mins[0] + Self::EDGE_DISTANCE
+            }
+            PageNumberPosition::TopCenter | PageNumberPosition::BottomCenter => {
+                page_bounds.center()[0] - text_size[0] * 0.5
+            }
+            PageNumberPosition::TopRight | PageNumberPosition::BottomRight => {
+                page_bounds.maxs[0] - Self::EDGE_DISTANCE - text_size[0]
+            }
+        };
+        let y = match self.position {
+            PageNumberPosition::TopLeft
+            | PageNumberPosition::TopCenter
+            | PageNumberPosition::TopRight => page_bounds.mins[1] + Self::EDGE_DISTANCE,
+            PageNumberPosition::BottomLeft
+            | PageNumberPosition::BottomCenter
+            | PageNumberPosition::BottomRight => {
+                page_bounds.maxs[1] - Self::EDGE_DISTANCE - text_size[1]
+            }
+        };
+
+        cx.draw_text(&text_layout, na::vector![x, y].to_kurbo_point());
+
+        Ok(())
+    }
+
+    /// Draws the page numbers on the given pages, if they are shown. The pages are numbered in the given order
+    pub fn draw(&self, cx: &mut impl RenderContext, pages_bounds: &[AABB]) -> anyhow::Result<()> {
+        if !self.show {
+            return Ok(());
+        }
+
+        for (index, page_bounds) in pages_bounds.iter().enumerate() {
+            self.draw_page_number(cx, *page_bounds, index, pages_bounds.len())?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+use rnote_compose::Color;
+use serde::{Deserialize, Serialize};
+
+/// A section marker, dividing the document into named sections. A section spans from its starting page up to the starting page of the next section.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename = "section")]
+pub struct Section {
+    /// The name of the section
+    #[serde(rename = "name")]
+    pub name: String,
+    /// The color of the section, e.g. for its tab
+    #[serde(rename = "color")]
+    pub color: Color,
+    /// The starting page of the section, as index into the document pages
+    #[serde(rename = "start_page")]
+    pub start_page: usize,
+}
+
+impl Default for Section {
+    fn default() -> Self {
+        Self {
+            name: String::from(""),
+            color: Self::COLOR_DEFAULT,
+            start_page: 0,
+        }
+    }
+}
+
+impl Section {
+    pub const COLOR_DEFAULT: Color = Color {
+        r: 0.21,
+        g: 0.52,
+        b: 0.89,
+        a: 1.0,
+    };
+
+    pub fn new(name: String, color: Color, start_page: usize) -> Self {
+        Self {
+            name,
+            color,
+            start_page,
+        }
+    }
+}
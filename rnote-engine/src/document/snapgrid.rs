@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+use super::Background;
+
+/// Where the spacing of the snap grid is taken from
+#[derive(
+    Debug,
+    Eq,
+    PartialEq,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    num_derive::FromPrimitive,
+    num_derive::ToPrimitive,
+)]
+#[serde(rename = "snap_grid_source")]
+pub enum SnapGridSource {
+    /// The spacing of the background pattern
+    #[serde(rename = "background_pattern")]
+    BackgroundPattern = 0,
+    /// The custom step
+    #[serde(rename = "custom_step")]
+    CustomStep,
+}
+
+impl Default for SnapGridSource {
+    fn default() -> Self {
+        Self::BackgroundPattern
+    }
+}
+
+impl TryFrom<u32> for SnapGridSource {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        num_traits::FromPrimitive::from_u32(value).ok_or_else(|| {
+            anyhow::anyhow!(
+                "SnapGridSource try_from::<u32>() for value {} failed",
+                value
+            )
+        })
+    }
+}
+
+/// The grid the selection is snapped to when moving and resizing it, so that diagrams line up cleanly.
+/// The grid starts at the document origin, so it lines up with the background pattern
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename = "snap_grid")]
+pub struct SnapGrid {
+    /// Wether snapping is enabled
+    #[serde(rename = "enabled")]
+    pub enabled: bool,
+    /// Where the spacing of the grid is taken from
+    #[serde(rename = "source")]
+    pub source: SnapGridSource,
+    /// The spacing of the grid when the source is the custom step
+    #[serde(rename = "custom_step")]
+    pub custom_step: f64,
+}
+
+impl Default for SnapGrid {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            source: SnapGridSource::default(),
+            custom_step: Self::CUSTOM_STEP_DEFAULT,
+        }
+    }
+}
+
+impl SnapGrid {
+    pub const CUSTOM_STEP_DEFAULT: f64 = 20.0;
+    /// The smallest spacing of the grid, smaller spacings are not useful for snapping
+    pub const SPACING_MIN: f64 = 1.0;
+
+    /// The spacing of the grid
+    pub fn spacing(&self, background: &Background) -> na::Vector2<f64> {
+        match self.source {
+            SnapGridSource::BackgroundPattern => background.pattern_size,
+            SnapGridSource::CustomStep => na::Vector2::from_element(self.custom_step),
+        }
+        .map(|spacing| spacing.max(Self::SPACING_MIN))
+    }
+
+    /// Snaps the point to the nearest grid point. The point is returned unchanged when snapping is disabled
+    pub fn snap_point(&self, point: na::Vector2<f64>, background: &Background) -> na::Vector2<f64> {
+        if !self.enabled {
+            return point;
+        }
+        let spacing = self.spacing(background);
+
+        point
+            .component_div(&spacing)
+            .map(|coord| coord.round())
+            .component_mul(&spacing)
+    }
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+/// A named bookmark of a camera view, to jump back to it later
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename = "view_bookmark")]
+pub struct ViewBookmark {
+    /// The name of the bookmark
+    #[serde(rename = "name")]
+    pub name: String,
+    /// The position of the top left of the view, in document coords
+    #[serde(rename = "pos")]
+    pub pos: na::Vector2<f64>,
+    /// The zoom of the view
+    #[serde(rename = "zoom")]
+    pub zoom: f64,
+}
+
+impl Default for ViewBookmark {
+    fn default() -> Self {
+        Self {
+            name: String::default(),
+            pos: na::Vector2::zeros(),
+            zoom: 1.0,
+        }
+    }
+}
+
+impl ViewBookmark {
+    pub fn new(name: String, pos: na::Vector2<f64>, zoom: f64) -> Self {
+        Self { name, pos, zoom }
+    }
+}
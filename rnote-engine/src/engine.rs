@@ -1,26 +1,51 @@
+use std::cell::RefCell;
 use std::path::PathBuf;
 use std::sync::Arc;
-
-use crate::document::Layout;
+use std::time::Duration;
+
+#[cfg(feature = "broadcast")]
+use crate::broadcast::{BroadcastPage, BroadcastServer};
+use crate::cameraanimation::{CameraAnimation, CameraAnimationStep};
+use crate::checkpoint::Checkpoint;
+use crate::configautosave::{ConfigAutosave, ConfigSink};
+use crate::document::{Comment, Layout, Metadata, ViewBookmark};
+use crate::fileerror::FileError;
+use crate::flipbook::Flipbook;
 use crate::import::PdfImportPrefs;
+use crate::pages::PageTemplate;
 use crate::pens::penholder::PenStyle;
 use crate::pens::PenMode;
-use crate::store::StrokeKey;
+use crate::presentation::Presentation;
+use crate::spellcheck::SpellChecker;
+use crate::store::chrono_comp::StrokeLayer;
+use crate::store::{
+    crdt, Author, RenderFocus, SelectionCriteria, StoreOp, StrokeKey, ZOrderChange,
+};
 use crate::strokes::strokebehaviour::GeneratedStrokeImages;
+use crate::strokes::textstroke::LinkTarget;
+use crate::strokes::{Stroke, StrokeRestyle, StrokeStyle, VectorImage};
+#[cfg(feature = "sync")]
+use crate::sync::{Presence, SyncClient, SyncMessage};
+use crate::textsearch::TextSearch;
+use crate::utils::GrapheneRectHelpers;
+use crate::viewport::{AdditionalViewport, ViewportKey};
 use crate::{render, AudioPlayer, DrawBehaviour, DrawOnDocBehaviour, WidgetFlags};
 use crate::{Camera, Document, PenHolder, StrokeStore};
-use gtk4::Snapshot;
+use gtk4::{graphene, gsk, Snapshot};
 use piet::RenderContext;
-use rnote_compose::helpers::{AABBHelpers, Vector2Helpers};
+use rnote_compose::helpers::{AABBHelpers, Affine2Helpers, Vector2Helpers};
 use rnote_compose::penhelpers::{PenEvent, ShortcutKey};
+use rnote_compose::shapes::ShapeBehaviour;
 use rnote_compose::transform::TransformBehaviour;
-use rnote_fileformats::rnoteformat::RnotefileMaj0Min5;
+use rnote_compose::Color;
+use rnote_fileformats::rnoteformat::{self, RnotefileMaj0Min6};
 use rnote_fileformats::{xoppformat, FileFormatSaver};
 
 use anyhow::Context;
 use futures::channel::{mpsc, oneshot};
 use p2d::bounding_volume::{BoundingVolume, AABB};
 use serde::{Deserialize, Serialize};
+use slotmap::HopSlotMap;
 
 /// A view into the rest of the engine, excluding the penholder
 #[allow(missing_debug_implementations)]
@@ -61,9 +86,14 @@ pub enum EngineTask {
     /// Replace the images of the render_comp.
     /// Note that usually the state of the render component should be set **before** spawning a thread, generating images and sending this task,
     /// to avoid spawning large amounts of already outdated rendering tasks when checking the render component state on resize / zooming, etc.
+    /// Results of outdated tasks, where the generation does not match the current render generation of the stroke, are dropped.
     UpdateStrokeWithImages {
         key: StrokeKey,
+        /// the additional viewport the images were generated for. None for the main viewport
+        viewport_key: Option<ViewportKey>,
         images: GeneratedStrokeImages,
+        /// the render generation of the stroke at the time the task was spawned
+        generation: u64,
     },
     /// Appends the images to the rendering of the stroke
     /// Note that usually the state of the render component should be set **before** spawning a thread, generating images and sending this task,
@@ -72,10 +102,41 @@ pub enum EngineTask {
         key: StrokeKey,
         images: GeneratedStrokeImages,
     },
+    /// A long running import / export task exceeded the timeout of the watchdog
+    TaskTimedOut {
+        name: String,
+        elapsed: Duration,
+        /// wether the task is aborted
+        aborted: bool,
+    },
+    /// Requests a redraw while the laser strokes are fading out
+    LaserFadeTick,
+    /// Requests checking the engine config for changes, to autosave it. See `ConfigAutosave`
+    CheckConfigChanged,
+    /// A message was received from the sync server. See `SyncClient`
+    #[cfg(feature = "sync")]
+    SyncMessageReceived(SyncMessage),
+    /// Requests sending the local store operations and the presence to the sync server
+    #[cfg(feature = "sync")]
+    SyncTick,
+    /// The connection to the sync server was closed
+    #[cfg(feature = "sync")]
+    SyncDisconnected,
+    /// Requests updating the pages broadcasted to the subscribers. See `BroadcastServer`
+    #[cfg(feature = "broadcast")]
+    BroadcastTick,
     /// indicates that the application is quitting. Usually handled to quit the async loop which receives the tasks
     Quit,
 }
 
+/// The strokes of the selection in the native clipboard format, together with their layers
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename = "clipboard_strokes")]
+struct ClipboardStrokes {
+    #[serde(rename = "strokes")]
+    strokes: Vec<(Stroke, StrokeLayer)>,
+}
+
 #[allow(missing_debug_implementations)]
 #[derive(Serialize, Deserialize)]
 #[serde(default, rename = "engine_config")]
@@ -88,6 +149,18 @@ struct EngineConfig {
     pdf_import_prefs: serde_json::Value,
     #[serde(rename = "pen_sounds")]
     pen_sounds: serde_json::Value,
+    #[serde(rename = "page_templates")]
+    page_templates: serde_json::Value,
+    #[serde(rename = "remove_empty_pages_on_save")]
+    remove_empty_pages_on_save: serde_json::Value,
+    #[serde(rename = "export_fit_to_margins")]
+    export_fit_to_margins: serde_json::Value,
+    #[serde(rename = "dark_mode")]
+    dark_mode: serde_json::Value,
+    #[serde(rename = "zoom_config")]
+    zoom_config: serde_json::Value,
+    #[serde(rename = "autoscroll")]
+    autoscroll: serde_json::Value,
 }
 
 impl Default for EngineConfig {
@@ -100,10 +173,70 @@ impl Default for EngineConfig {
 
             pdf_import_prefs: serde_json::to_value(&engine.pdf_import_prefs).unwrap(),
             pen_sounds: serde_json::to_value(&engine.pen_sounds).unwrap(),
+            page_templates: serde_json::to_value(&engine.page_templates).unwrap(),
+            remove_empty_pages_on_save: serde_json::to_value(&engine.remove_empty_pages_on_save)
+                .unwrap(),
+            export_fit_to_margins: serde_json::to_value(&engine.export_fit_to_margins).unwrap(),
+            dark_mode: serde_json::to_value(&engine.dark_mode).unwrap(),
+            zoom_config: serde_json::to_value(engine.camera.zoom_config()).unwrap(),
+            autoscroll: serde_json::to_value(&engine.autoscroll).unwrap(),
         }
     }
 }
 
+/// Which page is drawn ghosted beneath the current page, for consistent multi-page sketches and animation roughs.
+/// The current page is the page in the center of the viewport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename = "onion_skin")]
+pub enum OnionSkin {
+    #[serde(rename = "off")]
+    Off,
+    #[serde(rename = "previous_page")]
+    PreviousPage,
+    #[serde(rename = "next_page")]
+    NextPage,
+    /// a chosen page, as index into the document pages
+    #[serde(rename = "page")]
+    Page(usize),
+}
+
+impl Default for OnionSkin {
+    fn default() -> Self {
+        Self::Off
+    }
+}
+
+/// The cached rendering of the onion skin page. See `RnoteEngine::draw_onion_skin()`
+#[derive(Debug, Clone)]
+struct OnionSkinCache {
+    page: usize,
+    page_bounds: AABB,
+    image_scale: f64,
+    /// The changes count of the store when the rendering was generated
+    changes_count: u64,
+    rendernode: gsk::RenderNode,
+}
+
+/// The format of the exported text content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextExportFormat {
+    /// The formatting of the text strokes is kept as markdown
+    Markdown,
+    PlainText,
+}
+
+/// Exact values the selection is transformed to, e.g. from an object properties dialog. Values that are None are left unchanged.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TransformSpec {
+    /// The position of the top left corner of the selection bounds, in document coords
+    pub pos: Option<na::Vector2<f64>>,
+    /// The size of the selection bounds
+    pub size: Option<na::Vector2<f64>>,
+    /// The angle (rad) the selection is rotated by around its center.
+    /// Strokes don't keep track of their rotation, so it is relative to the current orientation
+    pub rotation: Option<f64>,
+}
+
 pub type EngineTaskSender = mpsc::UnboundedSender<EngineTask>;
 pub type EngineTaskReceiver = mpsc::UnboundedReceiver<EngineTask>;
 
@@ -125,11 +258,69 @@ pub struct RnoteEngine {
     pub pdf_import_prefs: PdfImportPrefs,
     #[serde(rename = "pen_sounds")]
     pub pen_sounds: bool,
+    /// Wether reference images are included in exports
+    #[serde(rename = "export_reference_images")]
+    pub export_reference_images: bool,
+    /// The page that is drawn ghosted beneath the current page
+    #[serde(rename = "onion_skin")]
+    pub onion_skin: OnionSkin,
+    #[serde(rename = "flipbook")]
+    pub flipbook: Flipbook,
+    /// The saved page templates, for inserting new pages
+    #[serde(rename = "page_templates")]
+    pub page_templates: Vec<PageTemplate>,
+    /// Wether empty pages are removed before saving, in the fixed size layout
+    #[serde(rename = "remove_empty_pages_on_save")]
+    pub remove_empty_pages_on_save: bool,
+    /// Wether the pages are scaled into the printable area inside the format margins when exporting as PDF
+    #[serde(rename = "export_fit_to_margins")]
+    pub export_fit_to_margins: bool,
+    /// Wether the document is displayed in a dark color scheme. Applied when drawing, so the strokes and exports are unaffected
+    #[serde(rename = "dark_mode")]
+    pub dark_mode: bool,
+    /// Wether the focus mode is enabled. See `render_focus()`
+    #[serde(rename = "focus_mode")]
+    pub focus_mode: bool,
+    /// Wether the camera auto-scrolls while drawing near the viewport border
+    #[serde(rename = "autoscroll")]
+    pub autoscroll: bool,
 
     #[serde(skip)]
     pub audioplayer: Option<AudioPlayer>,
     #[serde(skip)]
+    config_autosave: ConfigAutosave,
+    #[serde(skip)]
     pub visual_debug: bool,
+    /// Tints the strokes by the author who modified them last
+    #[serde(skip)]
+    pub author_tint: bool,
+    #[serde(skip)]
+    camera_animation: Option<CameraAnimation>,
+    #[serde(skip)]
+    presentation: Presentation,
+    /// The style copied from a stroke, to paste it onto other strokes
+    #[serde(skip)]
+    copied_style: Option<StrokeStyle>,
+    /// The search across the text strokes, its matches are highlighted
+    #[serde(skip)]
+    text_search: TextSearch,
+    /// The connection to the sync server, while collaborating
+    #[cfg(feature = "sync")]
+    #[serde(skip)]
+    sync: Option<SyncClient>,
+    /// The server broadcasting the document read-only, while broadcasting
+    #[cfg(feature = "broadcast")]
+    #[serde(skip)]
+    broadcast: Option<BroadcastServer>,
+    /// The checkpoints of the store, saved in the .rnote file
+    #[serde(skip)]
+    checkpoints: Vec<Checkpoint>,
+    /// Additional viewports observing the document, e.g. for a split view
+    #[serde(skip)]
+    additional_viewports: HopSlotMap<ViewportKey, AdditionalViewport>,
+    /// Regenerated only when the strokes of the onion skin page change, or the zoom changes significantly
+    #[serde(skip)]
+    onion_skin_cache: RefCell<Option<OnionSkinCache>>,
     #[serde(skip)]
     pub tasks_tx: EngineTaskSender,
     /// To be taken out into a loop which processes the receiver stream. The received tasks should be processed with process_received_task()
@@ -146,6 +337,35 @@ impl Default for RnoteEngine {
 impl RnoteEngine {
     /// The used image scale factor on export
     pub const EXPORT_IMAGE_SCALE: f64 = 1.5;
+    /// The id of the root of the pdf outline (cairo's `CAIRO_PDF_OUTLINE_ROOT`)
+    const PDF_OUTLINE_ROOT: i32 = 0;
+    /// The name of cairo's link tag (`CAIRO_TAG_LINK`)
+    const PDF_TAG_LINK: &'static str = "Link";
+    /// The opacity of the ghosted page when the onion skin is enabled
+    pub const ONION_SKIN_OPACITY: f64 = 0.25;
+    /// The ratio between the current image scale and the one of the cached onion skin rendering, above which it is regenerated
+    const ONION_SKIN_RERENDER_RATIO: f64 = 1.5;
+    /// The alpha of the author tint
+    pub const AUTHOR_TINT_ALPHA: f64 = 0.25;
+    /// The luminance weights (Rec. 709) used for the dark mode color remapping
+    const DARK_MODE_LUMINANCE_WEIGHTS: [f32; 3] = [0.2126, 0.7152, 0.0722];
+    /// The factor the image scale is reduced by on each step when exporting with a size target
+    pub const EXPORT_SIZE_TARGET_IMAGE_SCALE_STEP: f64 = 0.75;
+    /// The minimum image scale when exporting with a size target
+    pub const EXPORT_SIZE_TARGET_MIN_IMAGE_SCALE: f64 = 0.1;
+    /// The color of the viewport outline in the overview image
+    pub const OVERVIEW_VIEWPORT_COLOR: Color = Color {
+        r: 0.2,
+        g: 0.5,
+        b: 0.9,
+        a: 1.0,
+    };
+    /// The width of the viewport outline in the overview image, in pixels
+    pub const OVERVIEW_VIEWPORT_WIDTH: f64 = 2.0;
+    /// The maximum number of copies of an array duplicate
+    pub const ARRAY_DUPLICATE_COPIES_MAX: usize = 100;
+    /// The mime type of the native clipboard format, which holds the serialized strokes so they stay editable when pasted
+    pub const STROKES_CLIPBOARD_MIME_TYPE: &'static str = "application/x-rnote-strokes";
 
     #[allow(clippy::new_without_default)]
     pub fn new(data_dir: Option<PathBuf>) -> Self {
@@ -176,9 +396,31 @@ impl RnoteEngine {
 
             pdf_import_prefs: PdfImportPrefs::default(),
             pen_sounds,
+            export_reference_images: false,
+            onion_skin: OnionSkin::default(),
+            flipbook: Flipbook::default(),
+            page_templates: vec![],
+            remove_empty_pages_on_save: false,
+            export_fit_to_margins: false,
+            dark_mode: false,
+            focus_mode: false,
+            autoscroll: true,
 
             audioplayer,
+            config_autosave: ConfigAutosave::default(),
             visual_debug: false,
+            author_tint: false,
+            camera_animation: None,
+            presentation: Presentation::default(),
+            copied_style: None,
+            text_search: TextSearch::default(),
+            #[cfg(feature = "sync")]
+            sync: None,
+            #[cfg(feature = "broadcast")]
+            broadcast: None,
+            checkpoints: vec![],
+            additional_viewports: HopSlotMap::with_key(),
+            onion_skin_cache: RefCell::new(None),
             tasks_tx,
             tasks_rx: Some(tasks_rx),
         }
@@ -188,6 +430,33 @@ impl RnoteEngine {
         self.tasks_tx.clone()
     }
 
+    /// The strokes in focus when drawing. When the focus mode is enabled, the current selection is in focus,
+    /// or the layer of the current pen if nothing is selected. Everything else is faded.
+    pub fn render_focus(&self) -> RenderFocus {
+        if !self.focus_mode {
+            return RenderFocus::All;
+        }
+
+        if !self.store.selection_keys_unordered().is_empty() {
+            RenderFocus::Selection
+        } else if let Some(layer) = self.penholder.current_layer() {
+            RenderFocus::Layer(layer)
+        } else {
+            RenderFocus::All
+        }
+    }
+
+    /// The timeout after which long running import / export tasks are reported by the watchdog. None if it is disabled.
+    pub fn task_timeout(&self) -> Option<Duration> {
+        self.store.watchdog().timeout()
+    }
+
+    /// Sets the timeout after which long running import / export tasks are reported with `EngineTask::TaskTimedOut`,
+    /// optionally also aborting them. None disables the watchdog.
+    pub fn set_task_timeout(&mut self, timeout: Option<Duration>, abort_on_timeout: bool) {
+        self.store.watchdog().set_timeout(timeout, abort_on_timeout);
+    }
+
     /// Gets the EngineView
     pub fn view(&self) -> EngineView {
         EngineView {
@@ -229,16 +498,264 @@ impl RnoteEngine {
         self.store.record()
     }
 
+    /// Replaces the selection with all strokes matching the criteria and switches to the selector.
+    /// The attributes are compared to the ones of the topmost selected stroke. Does nothing if no strokes match.
+    pub fn select_strokes_matching(&mut self, criteria: SelectionCriteria) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        let selection_keys = self.store.selection_keys_as_rendered();
+        let matching_keys = self
+            .store
+            .keys_matching_criteria(criteria, selection_keys.last().copied());
+        if matching_keys.is_empty() {
+            return widget_flags;
+        }
+
+        widget_flags.merge_with_other(self.record());
+
+        self.store.set_selected_keys(&selection_keys, false);
+        self.store.set_selected_keys(&matching_keys, true);
+
+        widget_flags.merge_with_other(self.change_pen_style(PenStyle::Selector));
+        self.update_pens_states();
+
+        widget_flags.redraw = true;
+        widget_flags.indicate_changed_store = true;
+
+        widget_flags
+    }
+
+    /// Changes the color, stroke width and opacity of the selected strokes in one step
+    pub fn restyle_selection(&mut self, restyle: StrokeRestyle) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        let selection_keys = self.store.selection_keys_as_rendered();
+        if selection_keys.is_empty() || restyle == StrokeRestyle::default() {
+            return widget_flags;
+        }
+
+        widget_flags.merge_with_other(self.record());
+
+        self.store.restyle_strokes(&selection_keys, &restyle);
+
+        // The opacity is applied while composing, so only color and width changes need to be rerendered
+        if restyle.needs_rerender() {
+            self.store.update_geometry_for_strokes(&selection_keys);
+            self.resize_autoexpand();
+            self.update_pens_states();
+            self.update_rendering_current_viewport();
+
+            widget_flags.resize = true;
+        }
+
+        widget_flags.redraw = true;
+        widget_flags.indicate_changed_store = true;
+
+        widget_flags
+    }
+
+    /// Locks the selected strokes, which deselects them. Locked strokes can't be selected or erased until they are unlocked
+    pub fn lock_selection(&mut self) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        let selection_keys = self.store.selection_keys_as_rendered();
+        if selection_keys.is_empty() {
+            return widget_flags;
+        }
+
+        widget_flags.merge_with_other(self.record());
+
+        self.store.set_locked_keys(&selection_keys, true);
+
+        self.update_pens_states();
+        self.update_rendering_current_viewport();
+
+        widget_flags.redraw = true;
+        widget_flags.indicate_changed_store = true;
+
+        widget_flags
+    }
+
+    /// Unlocks all locked strokes. Returns the number of unlocked strokes
+    pub fn unlock_all_strokes(&mut self) -> (usize, WidgetFlags) {
+        let mut widget_flags = WidgetFlags::default();
+
+        let locked_keys = self.store.locked_keys_unordered();
+        if locked_keys.is_empty() {
+            return (0, widget_flags);
+        }
+
+        widget_flags.merge_with_other(self.record());
+
+        self.store.set_locked_keys(&locked_keys, false);
+
+        widget_flags.indicate_changed_store = true;
+
+        (locked_keys.len(), widget_flags)
+    }
+
+    /// Converts the selected brush strokes to shape strokes. See `StrokeStore::convert_brushstrokes_to_shapes()`.
+    /// Returns the number of converted strokes
+    pub fn convert_selection_to_shapes(&mut self) -> (usize, WidgetFlags) {
+        let mut widget_flags = WidgetFlags::default();
+
+        let brushstroke_keys = self
+            .store
+            .selection_keys_as_rendered()
+            .into_iter()
+            .filter(|&key| matches!(self.store.get_stroke_ref(key), Some(Stroke::BrushStroke(_))))
+            .collect::<Vec<StrokeKey>>();
+        if brushstroke_keys.is_empty() {
+            return (0, widget_flags);
+        }
+
+        widget_flags.merge_with_other(self.record());
+
+        let converted_keys = self.store.convert_brushstrokes_to_shapes(&brushstroke_keys);
+
+        self.store.update_geometry_for_strokes(&converted_keys);
+        self.resize_autoexpand();
+        self.update_pens_states();
+        self.update_rendering_current_viewport();
+
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.indicate_changed_store = true;
+
+        (converted_keys.len(), widget_flags)
+    }
+
+    /// The style copied with `copy_style_of_selection()`
+    pub fn copied_style(&self) -> Option<&StrokeStyle> {
+        self.copied_style.as_ref()
+    }
+
+    /// Copies the style of the last selected stroke. Returns false if there is no selected stroke with a style
+    pub fn copy_style_of_selection(&mut self) -> bool {
+        let stroke_style = self
+            .store
+            .selection_keys_as_rendered()
+            .last()
+            .and_then(|&key| self.store.get_stroke_ref(key))
+            .and_then(|stroke| stroke.stroke_style());
+
+        match stroke_style {
+            Some(stroke_style) => {
+                self.copied_style = Some(stroke_style);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pastes the copied style onto the selected strokes it applies to
+    pub fn paste_style_to_selection(&mut self) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        let selection_keys = self.store.selection_keys_as_rendered();
+        let stroke_style = match self.copied_style.clone() {
+            Some(stroke_style) if !selection_keys.is_empty() => stroke_style,
+            _ => return widget_flags,
+        };
+
+        widget_flags.merge_with_other(self.record());
+
+        let changed_keys = self
+            .store
+            .apply_stroke_style_to_strokes(&selection_keys, &stroke_style);
+
+        self.store.update_geometry_for_strokes(&changed_keys);
+        self.resize_autoexpand();
+        self.update_pens_states();
+        self.update_rendering_current_viewport();
+
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.indicate_changed_store = true;
+
+        widget_flags
+    }
+
+    /// Sets the copied style as the config of the matching pen and switches to it
+    pub fn apply_copied_style_to_pen_config(&mut self) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        let pen_style = match &self.copied_style {
+            Some(StrokeStyle::Brush { style, .. }) => {
+                self.penholder.brush.load_style(style);
+                PenStyle::Brush
+            }
+            Some(StrokeStyle::Shape { style, .. }) => {
+                self.penholder.shaper.load_style(style);
+                PenStyle::Shaper
+            }
+            Some(StrokeStyle::Text { text_style, .. }) => {
+                StrokeStyle::apply_to_text_style(
+                    text_style,
+                    &mut self.penholder.typewriter.text_style,
+                );
+                PenStyle::Typewriter
+            }
+            None => return widget_flags,
+        };
+
+        widget_flags.merge_with_other(self.change_pen_style(pen_style));
+        widget_flags.redraw = true;
+        widget_flags.refresh_ui = true;
+
+        widget_flags
+    }
+
+    /// Changes the draw order of the selected strokes within their layers
+    pub fn change_selection_z_order(&mut self, change: ZOrderChange) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        let selection_keys = self.store.selection_keys_as_rendered();
+        if selection_keys.is_empty() {
+            return widget_flags;
+        }
+
+        widget_flags.merge_with_other(self.record());
+
+        self.store.change_z_order(&selection_keys, change);
+
+        widget_flags.redraw = true;
+        widget_flags.indicate_changed_store = true;
+
+        widget_flags
+    }
+
+    /// Wether undo and redo are available. They are not while syncing with other replicas,
+    /// as importing a previous state would revert their changes as well
+    pub fn history_available(&self) -> bool {
+        #[cfg(feature = "sync")]
+        if self.sync.is_some() {
+            return false;
+        }
+
+        true
+    }
+
     /// Undo the latest changes
     pub fn undo(&mut self) -> WidgetFlags {
         let mut widget_flags = WidgetFlags::default();
+        if self.presentation.is_active() || !self.history_available() {
+            return widget_flags;
+        }
         let current_pen_style = self.penholder.current_style_w_override();
 
         if current_pen_style != PenStyle::Selector {
             widget_flags.merge_with_other(self.handle_pen_event(PenEvent::Cancel, None));
         }
 
+        self.store.refresh_pages_state(self.document.pages_state());
+        let pages_state = Arc::clone(self.store.pages_state());
         widget_flags.merge_with_other(self.store.undo());
+        if !Arc::ptr_eq(&pages_state, self.store.pages_state()) {
+            self.document.import_pages_state(self.store.pages_state());
+            widget_flags.resize = true;
+            widget_flags.refresh_ui = true;
+        }
 
         if !self.store.selection_keys_unordered().is_empty() {
             widget_flags.merge_with_other(
@@ -263,13 +780,23 @@ impl RnoteEngine {
     /// redo the latest changes
     pub fn redo(&mut self) -> WidgetFlags {
         let mut widget_flags = WidgetFlags::default();
+        if self.presentation.is_active() || !self.history_available() {
+            return widget_flags;
+        }
         let current_pen_style = self.penholder.current_style_w_override();
 
         if current_pen_style != PenStyle::Selector {
             widget_flags.merge_with_other(self.handle_pen_event(PenEvent::Cancel, None));
         }
 
+        self.store.refresh_pages_state(self.document.pages_state());
+        let pages_state = Arc::clone(self.store.pages_state());
         widget_flags.merge_with_other(self.store.redo());
+        if !Arc::ptr_eq(&pages_state, self.store.pages_state()) {
+            self.document.import_pages_state(self.store.pages_state());
+            widget_flags.resize = true;
+            widget_flags.refresh_ui = true;
+        }
 
         if !self.store.selection_keys_unordered().is_empty() {
             widget_flags.merge_with_other(
@@ -291,9 +818,242 @@ impl RnoteEngine {
         widget_flags
     }
 
-    // Clears the store
+    /// Applies the store operations received from the other collaborating replicas. See `StrokeStore::apply_ops()`
+    pub fn apply_store_ops(&mut self, ops: Vec<StoreOp>) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        self.store.apply_ops(ops);
+
+        self.resize_autoexpand();
+        self.update_pens_states();
+        self.update_rendering_current_viewport();
+
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.indicate_changed_store = true;
+
+        widget_flags
+    }
+
+    /// Starts collaborating by connecting to the sync server at the url, with a random replica id.
+    /// The current strokes are sent to the other replicas. Connecting happens in the background, see `SyncClient`
+    #[cfg(feature = "sync")]
+    pub fn start_sync(&mut self, url: &str, name: String) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+        let replica = rand::random::<u32>();
+
+        self.sync = Some(SyncClient::connect(
+            url,
+            Presence {
+                replica,
+                name: name.clone(),
+                cursor: None,
+            },
+            self.tasks_tx(),
+        )?);
+        self.store.start_collaborating(replica);
+        self.store.set_local_author(Some(Author {
+            name,
+            device: Some(gtk4::glib::host_name().to_string()),
+        }));
+
+        widget_flags.redraw = true;
+        widget_flags.hide_undo = Some(true);
+        widget_flags.hide_redo = Some(true);
+
+        Ok(widget_flags)
+    }
+
+    /// Stops collaborating and disconnects from the sync server
+    #[cfg(feature = "sync")]
+    pub fn stop_sync(&mut self) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        self.sync = None;
+        self.store.stop_collaborating();
+
+        widget_flags.redraw = true;
+        widget_flags.hide_undo = Some(!self.store.can_undo());
+        widget_flags.hide_redo = Some(!self.store.can_redo());
+        widget_flags.refresh_ui = true;
+
+        widget_flags
+    }
+
+    /// Wether the engine is collaborating with a sync server
+    #[cfg(feature = "sync")]
+    pub fn syncing(&self) -> bool {
+        self.sync.is_some()
+    }
+
+    /// The presences of the other collaborating replicas
+    #[cfg(feature = "sync")]
+    pub fn remote_presences(&self) -> Vec<Presence> {
+        self.sync
+            .as_ref()
+            .map(|sync| sync.remote_presences().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Starts broadcasting the document read-only on the address, e.g. `0.0.0.0:8080`.
+    /// Subscribers follow it by opening the address in their browser. Returns the address the server is bound to. See `BroadcastServer`
+    #[cfg(feature = "broadcast")]
+    pub fn start_broadcast(&mut self, address: &str) -> anyhow::Result<std::net::SocketAddr> {
+        let broadcast = BroadcastServer::start(address, self.tasks_tx())?;
+        let address = broadcast.address();
+        self.broadcast = Some(broadcast);
+
+        Ok(address)
+    }
+
+    /// Stops broadcasting the document
+    #[cfg(feature = "broadcast")]
+    pub fn stop_broadcast(&mut self) {
+        self.broadcast = None;
+    }
+
+    /// The address the document is broadcasted on, if it is broadcasted
+    #[cfg(feature = "broadcast")]
+    pub fn broadcast_address(&self) -> Option<std::net::SocketAddr> {
+        self.broadcast.as_ref().map(|broadcast| broadcast.address())
+    }
+
+    /// Renders the changed pages anew and sends them to the broadcast subscribers. The pages are rendered on a worker thread
+    #[cfg(feature = "broadcast")]
+    fn update_broadcast(&mut self) -> anyhow::Result<()> {
+        if !matches!(&self.broadcast, Some(broadcast) if !broadcast.is_rendering()) {
+            return Ok(());
+        }
+        let store_snapshot = self.store.take_store_snapshot();
+        let pages_bounds = self.document.pages_bounds();
+        let pages_bounds_w_content = self.pages_bounds_w_content();
+
+        let broadcast = match self.broadcast.as_mut() {
+            Some(broadcast) => broadcast,
+            None => return Ok(()),
+        };
+        let changed_pages = match broadcast.changed_pages(
+            &store_snapshot,
+            &pages_bounds,
+            &self.document.background,
+            &self.document.page_numbers,
+        ) {
+            Some(changed_pages) => changed_pages,
+            None => return Ok(()),
+        };
+
+        let pages = changed_pages
+            .into_iter()
+            .map(|index| {
+                let page_bounds = pages_bounds[index];
+                let mut background_svg = self
+                    .document
+                    .background
+                    .gen_svg(page_bounds, &pages_bounds)?;
+                background_svg.wrap_svg_root(
+                    Some(AABB::new(
+                        na::point![0.0, 0.0],
+                        na::Point2::from(page_bounds.extents()),
+                    )),
+                    Some(page_bounds),
+                    true,
+                );
+
+                Ok(BroadcastPage {
+                    index,
+                    bounds: page_bounds,
+                    background_svg: Some(background_svg),
+                    strokes: self.store.stroke_keys_as_exported_intersecting_bounds(
+                        page_bounds,
+                        self.export_reference_images,
+                    ),
+                })
+            })
+            .collect::<anyhow::Result<Vec<BroadcastPage>>>()?;
+
+        broadcast.render_pages(
+            pages,
+            pages_bounds,
+            pages_bounds_w_content,
+            store_snapshot,
+            &self.document.background,
+            &self.document.page_numbers,
+        );
+
+        Ok(())
+    }
+
+    /// Draws the cursors of the other collaborating replicas. Expects the snapshot in document coords
+    #[cfg(feature = "sync")]
+    fn draw_remote_cursors(&self, snapshot: &Snapshot) {
+        use crate::utils::GdkRGBAHelpers;
+        const CURSOR_COLORS: [piet::Color; 5] = [
+            rnote_compose::color::GNOME_BLUES[3],
+            rnote_compose::color::GNOME_REDS[3],
+            rnote_compose::color::GNOME_GREENS[3],
+            rnote_compose::color::GNOME_ORANGES[3],
+            rnote_compose::color::GNOME_PURPLES[3],
+        ];
+        let cursor_size = 8.0 / self.camera.total_zoom();
+
+        for presence in self.remote_presences() {
+            if let Some(cursor) = presence.cursor {
+                let color = CURSOR_COLORS[presence.replica as usize % CURSOR_COLORS.len()];
+
+                snapshot.append_color(
+                    &gtk4::gdk::RGBA::from_piet_color(color),
+                    &graphene::Rect::from_p2d_aabb(AABB::from_half_extents(
+                        na::Point2::from(cursor),
+                        na::Vector2::repeat(cursor_size * 0.5),
+                    )),
+                );
+            }
+        }
+    }
+
+    /// The color strokes are tinted with for the author, when `author_tint` is enabled
+    pub fn author_tint_color(author: &Author) -> piet::Color {
+        use std::hash::{Hash, Hasher};
+        const AUTHOR_COLORS: [piet::Color; 6] = [
+            rnote_compose::color::GNOME_BLUES[3],
+            rnote_compose::color::GNOME_REDS[3],
+            rnote_compose::color::GNOME_GREENS[3],
+            rnote_compose::color::GNOME_ORANGES[3],
+            rnote_compose::color::GNOME_PURPLES[3],
+            rnote_compose::color::GNOME_BROWNS[3],
+        ];
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        author.name.hash(&mut hasher);
+
+        AUTHOR_COLORS[(hasher.finish() % AUTHOR_COLORS.len() as u64) as usize]
+            .with_alpha(Self::AUTHOR_TINT_ALPHA)
+    }
+
+    /// Tints the strokes in the viewport by their author. Strokes without an author are not tinted
+    fn draw_author_tint(&self, snapshot: &Snapshot) {
+        use crate::utils::GdkRGBAHelpers;
+
+        for key in self
+            .store
+            .stroke_keys_as_rendered_intersecting_bounds(self.camera.viewport())
+        {
+            if let (Some(author), Some(stroke)) = (
+                self.store.stroke_author(key),
+                self.store.get_stroke_ref(key),
+            ) {
+                snapshot.append_color(
+                    &gtk4::gdk::RGBA::from_piet_color(Self::author_tint_color(author)),
+                    &graphene::Rect::from_p2d_aabb(stroke.bounds()),
+                );
+            }
+        }
+    }
+
+    // Clears the store and the data of the current file
     pub fn clear(&mut self) {
         self.store.clear();
+        self.document.clear_file_data();
+        self.checkpoints.clear();
         self.update_pens_states();
     }
 
@@ -321,8 +1081,24 @@ impl RnoteEngine {
         let mut widget_flags = WidgetFlags::default();
 
         match task {
-            EngineTask::UpdateStrokeWithImages { key, images } => {
-                if let Err(e) = self.store.replace_rendering_with_images(key, images) {
+            EngineTask::UpdateStrokeWithImages {
+                key,
+                viewport_key,
+                images,
+                generation,
+            } => {
+                // The stroke changed or a newer task was spawned in the meantime, so the images are outdated
+                if !self
+                    .store
+                    .is_current_render_generation(key, viewport_key, generation)
+                {
+                    return widget_flags;
+                }
+
+                if let Err(e) = self
+                    .store
+                    .replace_rendering_with_images(key, viewport_key, images)
+                {
                     log::error!("replace_rendering_with_images() in process_received_task() failed with Err {}", e);
                 }
 
@@ -340,16 +1116,95 @@ impl RnoteEngine {
                 widget_flags.redraw = true;
                 widget_flags.indicate_changed_store = true;
             }
-            EngineTask::Quit => {
-                widget_flags.quit = true;
+            EngineTask::TaskTimedOut {
+                name,
+                elapsed,
+                aborted,
+            } => {
+                log::warn!(
+                    "task `{}` timed out, running for {:.2}s, aborted: {}",
+                    name,
+                    elapsed.as_secs_f64(),
+                    aborted
+                );
             }
-        }
-
-        widget_flags
-    }
+            EngineTask::LaserFadeTick => {
+                self.penholder.laser.remove_faded_strokes();
 
-    /// handle an pen event
+                widget_flags.redraw = true;
+            }
+            EngineTask::CheckConfigChanged => match self.save_engine_config() {
+                Ok(config) => self.config_autosave.check(&config),
+                Err(e) => log::error!(
+                    "save_engine_config() for the config autosave failed in process_received_task() with Err {}",
+                    e
+                ),
+            },
+            #[cfg(feature = "sync")]
+            EngineTask::SyncMessageReceived(message) => {
+                if let Some(sync) = self.sync.as_mut() {
+                    let ops = sync.handle_message(message);
+                    if !ops.is_empty() {
+                        widget_flags.merge_with_other(self.apply_store_ops(ops));
+                    }
+                    widget_flags.redraw = true;
+                }
+            }
+            #[cfg(feature = "sync")]
+            EngineTask::SyncTick => {
+                if let Some(sync) = self.sync.as_mut() {
+                    let mut ops = if sync.take_catch_up_request() {
+                        self.store.state_ops()
+                    } else {
+                        vec![]
+                    };
+                    ops.append(&mut self.store.take_pending_ops());
+
+                    if let Err(e) = sync.flush(ops) {
+                        log::error!("flushing sync client in process_received_task() failed with Err {}", e);
+                    }
+                }
+            }
+            #[cfg(feature = "sync")]
+            EngineTask::SyncDisconnected => {
+                if self.sync.is_some() {
+                    log::warn!("the connection to the sync server was closed");
+                    widget_flags.merge_with_other(self.stop_sync());
+                }
+            }
+            #[cfg(feature = "broadcast")]
+            EngineTask::BroadcastTick => {
+                if let Err(e) = self.update_broadcast() {
+                    log::error!(
+                        "update_broadcast() in process_received_task() failed with Err {}",
+                        e
+                    );
+                }
+            }
+            EngineTask::Quit => {
+                widget_flags.quit = true;
+            }
+        }
+
+        widget_flags
+    }
+
+    /// handle an pen event. Ignored while presenting
     pub fn handle_pen_event(&mut self, event: PenEvent, pen_mode: Option<PenMode>) -> WidgetFlags {
+        if self.presentation.is_active() {
+            return WidgetFlags::default();
+        }
+
+        #[cfg(feature = "sync")]
+        if let Some(sync) = self.sync.as_mut() {
+            match &event {
+                PenEvent::Down { element, .. }
+                | PenEvent::Up { element, .. }
+                | PenEvent::Proximity { element, .. } => sync.set_cursor(Some(element.pos)),
+                _ => {}
+            }
+        }
+
         self.penholder.handle_pen_event(
             event,
             pen_mode,
@@ -363,8 +1218,20 @@ impl RnoteEngine {
         )
     }
 
-    /// Handle a pressed shortcut key
+    /// Wether the camera auto-scrolls while the current pen is drawing near the viewport border.
+    /// Not for the tools pen, as its drag tool moves the view itself
+    pub fn autoscroll_active(&self) -> bool {
+        self.autoscroll
+            && !self.presentation.is_active()
+            && self.penholder.current_style_w_override() != PenStyle::Tools
+    }
+
+    /// Handle a pressed shortcut key. Ignored while presenting
     pub fn handle_pen_pressed_shortcut_key(&mut self, shortcut_key: ShortcutKey) -> WidgetFlags {
+        if self.presentation.is_active() {
+            return WidgetFlags::default();
+        }
+
         self.penholder.handle_pressed_shortcut_key(
             shortcut_key,
             &mut EngineViewMut {
@@ -427,97 +1294,762 @@ impl RnoteEngine {
     pub fn update_background_rendering_current_viewport(&mut self) {
         let viewport = self.camera.viewport();
 
-        // Update background and strokes for the new viewport
-        if let Err(e) = self.document.background.update_rendernodes(viewport) {
-            log::error!(
-                "failed to update background rendernodes on canvas resize with Err {}",
-                e
-            );
+        // Update background and strokes for the new viewport
+        if let Err(e) = self.document.background.update_rendernodes(viewport) {
+            log::error!(
+                "failed to update background rendernodes on canvas resize with Err {}",
+                e
+            );
+        }
+    }
+
+    /// updates the content rendering for the current viewport. including the background rendering.
+    pub fn update_rendering_current_viewport(&mut self) {
+        let viewport = self.camera.viewport();
+        let image_scale = self.camera.image_scale();
+
+        self.update_background_rendering_current_viewport();
+
+        self.store.regenerate_rendering_in_viewport_threaded(
+            self.tasks_tx(),
+            false,
+            viewport,
+            image_scale,
+        );
+
+        let viewport_keys = self
+            .additional_viewports
+            .keys()
+            .collect::<Vec<ViewportKey>>();
+        for viewport_key in viewport_keys {
+            self.update_rendering_additional_viewport(viewport_key);
+        }
+    }
+
+    /// Adds an additional viewport with the given camera, observing the same document and store. Returns its key.
+    /// The viewport has its own render caches, its rendering is generated right away.
+    pub fn add_additional_viewport(&mut self, camera: Camera) -> ViewportKey {
+        let viewport_key = self
+            .additional_viewports
+            .insert(AdditionalViewport::new(camera));
+        self.store.add_viewport_render_components(viewport_key);
+        self.update_rendering_additional_viewport(viewport_key);
+
+        viewport_key
+    }
+
+    /// Removes the additional viewport and its render caches
+    pub fn remove_additional_viewport(&mut self, viewport_key: ViewportKey) {
+        self.additional_viewports.remove(viewport_key);
+        self.store.remove_viewport_render_components(viewport_key);
+    }
+
+    /// The keys of the additional viewports
+    pub fn additional_viewport_keys(&self) -> Vec<ViewportKey> {
+        self.additional_viewports.keys().collect()
+    }
+
+    /// The camera of the additional viewport
+    pub fn additional_viewport_camera(&self, viewport_key: ViewportKey) -> Option<&Camera> {
+        self.additional_viewports
+            .get(viewport_key)
+            .map(|additional_viewport| &additional_viewport.camera)
+    }
+
+    /// The camera of the additional viewport, mutable.
+    /// The rendering of the viewport then needs to be updated with `update_rendering_additional_viewport()`.
+    pub fn additional_viewport_camera_mut(
+        &mut self,
+        viewport_key: ViewportKey,
+    ) -> Option<&mut Camera> {
+        self.additional_viewports
+            .get_mut(viewport_key)
+            .map(|additional_viewport| &mut additional_viewport.camera)
+    }
+
+    /// Updates the rendering of the additional viewport for the viewport of its camera. Only the additional viewport is affected.
+    /// The background pattern is shared with the main viewport, so it is not regenerated for the image scale of the viewport.
+    pub fn update_rendering_additional_viewport(&mut self, viewport_key: ViewportKey) {
+        let additional_viewport = match self.additional_viewports.get_mut(viewport_key) {
+            Some(additional_viewport) => additional_viewport,
+            None => return,
+        };
+        let viewport = additional_viewport.camera.viewport();
+        let image_scale = additional_viewport.camera.image_scale();
+
+        match self.document.background.gen_rendernodes(viewport) {
+            Ok(rendernodes) => additional_viewport.background_rendernodes = rendernodes,
+            Err(e) => log::error!(
+                "generating background rendernodes failed in update_rendering_additional_viewport() with Err {}",
+                e
+            ),
+        }
+
+        self.store
+            .regenerate_rendering_in_additional_viewport_threaded(
+                self.tasks_tx(),
+                viewport_key,
+                viewport,
+                image_scale,
+            );
+    }
+
+    // Generates bounds for each page on the document which contains content
+    pub fn pages_bounds_w_content(&self) -> Vec<AABB> {
+        let keys = self.store.stroke_keys_as_rendered();
+
+        let strokes_bounds = self.store.strokes_bounds(&keys);
+
+        let pages_bounds = self
+            .document
+            .pages_bounds()
+            .into_iter()
+            .filter(|page_bounds| {
+                // Filter the pages out that doesn't intersect with any stroke
+                strokes_bounds
+                    .iter()
+                    .any(|stroke_bounds| stroke_bounds.intersects(page_bounds))
+            })
+            .collect::<Vec<AABB>>();
+
+        if pages_bounds.is_empty() {
+            // If no page has content, return the origin page
+            vec![AABB::new(
+                na::point![0.0, 0.0],
+                na::Point2::from(self.document.page_size(0)),
+            )]
+        } else {
+            pages_bounds
+        }
+    }
+
+    /// Generates bounds which contain all pages on the doc with content extended to fit the format.
+    pub fn bounds_w_content_extended(&self) -> Option<AABB> {
+        let pages_bounds = self.pages_bounds_w_content();
+
+        if pages_bounds.is_empty() {
+            return None;
+        }
+
+        Some(
+            pages_bounds
+                .into_iter()
+                .fold(AABB::new_invalid(), |prev, next| prev.merged(&next)),
+        )
+    }
+
+    /// The bounds of the current selection
+    pub fn selection_bounds(&self) -> Option<AABB> {
+        self.store
+            .bounds_for_strokes(&self.store.selection_keys_as_rendered())
+    }
+
+    /// Transforms the selection to the exact values of the spec. The size is applied first, then the rotation and then the position.
+    pub fn transform_selection(&mut self, spec: TransformSpec) -> anyhow::Result<WidgetFlags> {
+        let selection_keys = self.store.selection_keys_as_rendered();
+        let selection_bounds = self
+            .store
+            .bounds_for_strokes(&selection_keys)
+            .ok_or_else(|| anyhow::anyhow!("transform_selection() failed, nothing is selected"))?;
+        if let Some(size) = spec.size {
+            if size.iter().any(|&size| !size.is_finite() || size <= 0.0) {
+                return Err(anyhow::anyhow!(
+                    "transform_selection() failed, invalid size {:?}",
+                    size
+                ));
+            }
+        }
+
+        let mut widget_flags = self.store.record();
+
+        if let Some(size) = spec.size {
+            let extents = selection_bounds.extents();
+            // Selections without extent in one dimension ( e.g. straight lines ) can't be scaled in that dimension
+            let scale = na::Vector2::from_fn(|i, _| {
+                if extents[i] > 0.0 {
+                    size[i] / extents[i]
+                } else {
+                    1.0
+                }
+            });
+            let pivot = selection_bounds.mins.coords;
+
+            self.store.scale_strokes_with_pivot(
+                &selection_keys,
+                scale,
+                pivot,
+                self.penholder.selector.resize_scale_stroke_widths,
+            );
+            self.store
+                .scale_strokes_images_with_pivot(&selection_keys, scale, pivot);
+        }
+        if let Some(rotation) = spec.rotation {
+            if let Some(bounds) = self.store.bounds_for_strokes(&selection_keys) {
+                self.store
+                    .rotate_strokes(&selection_keys, rotation, bounds.center());
+                self.store
+                    .rotate_strokes_images(&selection_keys, rotation, bounds.center());
+            }
+        }
+        if let Some(pos) = spec.pos {
+            if let Some(bounds) = self.store.bounds_for_strokes(&selection_keys) {
+                let offset = pos - bounds.mins.coords;
+
+                self.store.translate_strokes(&selection_keys, offset);
+                self.store.translate_strokes_images(&selection_keys, offset);
+            }
+        }
+
+        self.store.update_geometry_for_strokes(&selection_keys);
+        self.resize_autoexpand();
+        self.update_pens_states();
+        self.update_rendering_current_viewport();
+
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Duplicates the selection, the duplicate is translated by the given offset and selected
+    pub fn duplicate_selection_with_offset(&mut self, offset: na::Vector2<f64>) -> WidgetFlags {
+        self.array_duplicate_selection(1, offset)
+    }
+
+    /// Duplicates the selection n times with a fixed spacing between the copies, useful for repeating elements
+    /// like table rows. The copies are selected afterwards.
+    pub fn array_duplicate_selection(
+        &mut self,
+        n_copies: usize,
+        spacing: na::Vector2<f64>,
+    ) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        let n_copies = n_copies.min(Self::ARRAY_DUPLICATE_COPIES_MAX);
+
+        if n_copies == 0 || self.store.selection_keys_as_rendered().is_empty() {
+            return widget_flags;
+        }
+
+        widget_flags.merge_with_other(self.store.record());
+
+        let new_selected = self.store.array_duplicate_selection(n_copies, spacing);
+
+        self.store.update_geometry_for_strokes(&new_selected);
+        self.resize_autoexpand();
+        self.update_pens_states();
+        self.update_rendering_current_viewport();
+
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.indicate_changed_store = true;
+
+        widget_flags
+    }
+
+    /// Zooms and moves the camera so that the given bounds fit into the viewport. A running camera animation is stopped.
+    pub fn zoom_fit_to_bounds(&mut self, bounds: AABB) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        self.stop_camera_animation();
+        self.camera.zoom_fit_to_bounds(bounds);
+        // Expands the document in autoexpanding layouts
+        self.update_camera_offset(self.camera.offset);
+        self.update_rendering_after_zoom();
+
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.update_view = true;
+
+        widget_flags
+    }
+
+    /// Regenerates the background pattern and the rendering of all strokes for the new zoom of the camera
+    fn update_rendering_after_zoom(&mut self) {
+        self.store.set_rendering_dirty_all_keys();
+        if let Err(e) = self
+            .document
+            .background
+            .regenerate_pattern(self.camera.viewport(), self.camera.image_scale())
+        {
+            log::error!(
+                "regenerating background pattern in update_rendering_after_zoom() failed with Err {}",
+                e
+            );
+        }
+        self.update_rendering_current_viewport();
+    }
+
+    /// Zooms to fit the current selection. Does nothing if nothing is selected
+    pub fn zoom_fit_selection(&mut self) -> WidgetFlags {
+        match self.selection_bounds() {
+            Some(bounds) => self.zoom_fit_to_bounds(bounds),
+            None => WidgetFlags::default(),
+        }
+    }
+
+    /// Zooms to fit the current page. Does nothing if the layout has no pages
+    pub fn zoom_fit_current_page(&mut self) -> WidgetFlags {
+        match self.current_page_bounds() {
+            Some(bounds) => self.zoom_fit_to_bounds(bounds),
+            None => WidgetFlags::default(),
+        }
+    }
+
+    /// Zooms to fit all pages with content
+    pub fn zoom_fit_document(&mut self) -> WidgetFlags {
+        match self.bounds_w_content_extended() {
+            Some(bounds) => self.zoom_fit_to_bounds(bounds),
+            None => WidgetFlags::default(),
+        }
+    }
+
+    /// The view bookmarks of the document
+    pub fn view_bookmarks(&self) -> &[ViewBookmark] {
+        self.document.view_bookmarks()
+    }
+
+    /// Saves the current camera view as a bookmark with the given name. Returns the index of the added bookmark.
+    pub fn save_view_bookmark(&mut self, name: String) -> (usize, WidgetFlags) {
+        let mut widget_flags = WidgetFlags::default();
+        let zoom = self.camera.total_zoom();
+
+        self.document.view_bookmarks_mut().push(ViewBookmark::new(
+            name,
+            self.camera.offset / zoom,
+            zoom,
+        ));
+
+        widget_flags.indicate_changed_store = true;
+
+        (self.document.view_bookmarks().len() - 1, widget_flags)
+    }
+
+    /// Renames the view bookmark with the given index
+    pub fn rename_view_bookmark(
+        &mut self,
+        index: usize,
+        name: String,
+    ) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+
+        let bookmark = self
+            .document
+            .view_bookmarks_mut()
+            .get_mut(index)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "rename_view_bookmark() failed, bookmark {} does not exist",
+                    index
+                )
+            })?;
+        bookmark.name = name;
+
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Removes the view bookmark with the given index
+    pub fn remove_view_bookmark(&mut self, index: usize) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+
+        if index >= self.document.view_bookmarks().len() {
+            return Err(anyhow::anyhow!(
+                "remove_view_bookmark() failed, bookmark {} does not exist",
+                index
+            ));
+        }
+        self.document.view_bookmarks_mut().remove(index);
+
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Moves the camera back to the view of the bookmark with the given index. A running camera animation is stopped.
+    pub fn go_to_view_bookmark(&mut self, index: usize) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+
+        let bookmark = self
+            .document
+            .view_bookmarks()
+            .get(index)
+            .cloned()
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "go_to_view_bookmark() failed, bookmark {} does not exist",
+                    index
+                )
+            })?;
+
+        self.stop_camera_animation();
+        self.camera.set_temporary_zoom(1.0);
+        self.camera.set_zoom(bookmark.zoom);
+        self.update_camera_offset(bookmark.pos * self.camera.zoom());
+        self.update_rendering_after_zoom();
+
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.update_view = true;
+
+        Ok(widget_flags)
+    }
+
+    /// The review comments of the document
+    pub fn comments(&self) -> &[Comment] {
+        self.document.comments()
+    }
+
+    /// Adds a comment at the position, or anchored to the stroke if it is given. The local author of the store is recorded as its author.
+    /// Returns the index of the added comment.
+    pub fn add_comment(
+        &mut self,
+        text: String,
+        pos: na::Vector2<f64>,
+        stroke: Option<StrokeKey>,
+    ) -> (usize, WidgetFlags) {
+        let mut widget_flags = WidgetFlags::default();
+
+        let mut comment = Comment::new(text, self.store.local_author().cloned(), pos);
+        comment.stroke = stroke;
+        comment.pos = comment.marker_pos(&self.store);
+        comment.expanded = true;
+        self.document.comments_mut().push(comment);
+
+        widget_flags.redraw = true;
+        widget_flags.indicate_changed_store = true;
+
+        (self.document.comments().len() - 1, widget_flags)
+    }
+
+    /// Marks the comment with the given index as resolved, or reopens it
+    pub fn resolve_comment(&mut self, index: usize, resolved: bool) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+
+        let comment = self.document.comments_mut().get_mut(index).ok_or_else(|| {
+            anyhow::anyhow!("resolve_comment() failed, comment {} does not exist", index)
+        })?;
+        comment.resolved = resolved;
+
+        widget_flags.redraw = true;
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Removes the comment with the given index
+    pub fn remove_comment(&mut self, index: usize) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+
+        if index >= self.document.comments().len() {
+            return Err(anyhow::anyhow!(
+                "remove_comment() failed, comment {} does not exist",
+                index
+            ));
+        }
+        self.document.comments_mut().remove(index);
+
+        widget_flags.redraw = true;
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// The index of the unresolved comment whose marker is at the position, the topmost first
+    pub fn comment_marker_at(&self, pos: na::Vector2<f64>) -> Option<usize> {
+        let total_zoom = self.camera.total_zoom();
+
+        self.document
+            .comments()
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, comment)| {
+                !comment.resolved
+                    && comment
+                        .marker_bounds(&self.store, total_zoom)
+                        .contains_local_point(&na::Point2::from(pos))
+            })
+            .map(|(index, _)| index)
+    }
+
+    /// Expands or collapses the marker of the comment with the given index
+    pub fn set_comment_expanded(
+        &mut self,
+        index: usize,
+        expanded: bool,
+    ) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+
+        let comment = self.document.comments_mut().get_mut(index).ok_or_else(|| {
+            anyhow::anyhow!(
+                "set_comment_expanded() failed, comment {} does not exist",
+                index
+            )
+        })?;
+        comment.expanded = expanded;
+
+        widget_flags.redraw = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Draws the markers of the unresolved comments
+    fn draw_comments(&self, snapshot: &Snapshot, surface_bounds: AABB) -> anyhow::Result<()> {
+        let total_zoom = self.camera.total_zoom();
+        let viewport = self.camera.viewport();
+        let comments = self
+            .document
+            .comments()
+            .iter()
+            .filter(|comment| !comment.resolved)
+            .collect::<Vec<&Comment>>();
+        if comments.is_empty() {
+            return Ok(());
+        }
+
+        {
+            let cairo_cx = snapshot.append_cairo(&graphene::Rect::from_p2d_aabb(surface_bounds));
+            let mut piet_cx = piet_cairo::CairoRenderContext::new(&cairo_cx);
+
+            piet_cx.transform(self.camera.transform().to_kurbo());
+            for comment in comments {
+                // Expanded text boxes extend beyond the marker, so they are always drawn
+                if comment.expanded
+                    || comment
+                        .marker_bounds(&self.store, total_zoom)
+                        .intersects(&viewport)
+                {
+                    comment.draw(&mut piet_cx, &self.store, total_zoom)?;
+                }
+            }
+
+            piet_cx.finish().map_err(|e| anyhow::anyhow!("{}", e))?;
+        }
+
+        Ok(())
+    }
+
+    /// the current document layout
+    pub fn doc_layout(&self) -> Layout {
+        self.document.layout()
+    }
+
+    pub fn set_doc_layout(&mut self, layout: Layout) {
+        self.document.set_layout(layout, &self.store, &self.camera);
+    }
+
+    /// The document metadata
+    pub fn metadata(&self) -> &Metadata {
+        &self.document.metadata
+    }
+
+    /// Sets the document metadata
+    pub fn set_metadata(&mut self, metadata: Metadata) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        if self.document.metadata != metadata {
+            self.document.metadata = metadata;
+            widget_flags.indicate_changed_store = true;
+        }
+
+        widget_flags
+    }
+
+    /// Updates the metadata timestamps and app version. Needs to be called before saving the document
+    pub fn touch_metadata(&mut self, app_version: &str) {
+        self.document.metadata.touch(app_version);
+    }
+
+    /// resizes the doc to the format and to fit all strokes
+    /// Document background rendering then needs to be updated.
+    pub fn resize_to_fit_strokes(&mut self) {
+        self.document
+            .resize_to_fit_strokes(&self.store, &self.camera);
+    }
+
+    /// resize the doc when in autoexpanding layouts. called e.g. when finishing a new stroke
+    /// Document background rendering then needs to be updated.
+    pub fn resize_autoexpand(&mut self) {
+        self.document.resize_autoexpand(&self.store, &self.camera);
+    }
+
+    /// Translates all strokes so that the top left of the content becomes the new origin, in the infinite layout.
+    /// The camera is moved along, so the viewport keeps showing the same content.
+    pub fn recenter_origin(&mut self) -> anyhow::Result<WidgetFlags> {
+        if self.document.layout() != Layout::Infinite {
+            return Err(anyhow::anyhow!(
+                "recenter_origin() failed, the origin can only be recentered in the infinite layout"
+            ));
+        }
+        let content_bounds = match self
+            .store
+            .bounds_for_strokes(&self.store.stroke_keys_as_rendered())
+        {
+            Some(content_bounds) => content_bounds,
+            None => return Ok(WidgetFlags::default()),
+        };
+        let offset = -content_bounds.mins.coords;
+        if offset == na::Vector2::zeros() {
+            return Ok(WidgetFlags::default());
+        }
+
+        let mut widget_flags = self.store.record();
+
+        // Trashed strokes are translated as well, so they stay in place relative to the content when they are restored
+        let keys = self.store.keys_sorted_chrono();
+        self.store.translate_strokes(&keys, offset);
+        self.store.translate_strokes_images(&keys, offset);
+        for entry in self.document.outline_mut() {
+            entry.pos += offset;
+        }
+        for bookmark in self.document.view_bookmarks_mut() {
+            bookmark.pos += offset;
+        }
+        for comment in self.document.comments_mut() {
+            comment.pos += offset;
+        }
+
+        let camera_offset = self.camera.offset + offset * self.camera.total_zoom();
+        self.update_camera_offset(camera_offset);
+        self.resize_autoexpand();
+        self.update_rendering_current_viewport();
+
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.update_view = true;
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Starts gliding the camera from the current view to the target center in document coords and the target zoom.
+    /// The animation is then stepped with `tick_camera_animation()` on each frame. A running animation is replaced.
+    pub fn animate_camera_to(&mut self, target_center: na::Vector2<f64>, target_zoom: f64) {
+        let current_center =
+            (self.camera.transform().inverse() * na::Point2::from(self.camera.size * 0.5)).coords;
+        let target_zoom = self.camera.zoom_config().clamp_zoom(target_zoom);
+
+        self.camera_animation = Some(CameraAnimation::new(
+            current_center,
+            self.camera.total_zoom(),
+            target_center,
+            target_zoom,
+        ));
+    }
+
+    /// Sets the spellchecker for the typewriter, which underlines the misspelled words of the text being modified.
+    /// None disables spellchecking
+    pub fn set_spellchecker(
+        &mut self,
+        spellchecker: Option<std::rc::Rc<dyn SpellChecker>>,
+    ) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        self.penholder.typewriter.set_spellchecker(spellchecker);
+
+        widget_flags.redraw = true;
+
+        widget_flags
+    }
+
+    /// The text search. See `search_text()`
+    pub fn text_search(&self) -> &TextSearch {
+        &self.text_search
+    }
+
+    /// Searches the text strokes for the query and highlights the matches. An empty query clears the search.
+    /// Returns the number of matches
+    pub fn search_text(&mut self, query: String) -> (usize, WidgetFlags) {
+        let mut widget_flags = WidgetFlags::default();
+
+        if query.is_empty() {
+            self.text_search.clear();
+        } else {
+            self.text_search.update(query, &self.store);
         }
+
+        widget_flags.redraw = true;
+
+        (self.text_search.matches().len(), widget_flags)
     }
 
-    /// updates the content rendering for the current viewport. including the background rendering.
-    pub fn update_rendering_current_viewport(&mut self) {
-        let viewport = self.camera.viewport();
-        let image_scale = self.camera.image_scale();
+    /// Clears the text search and its highlights
+    pub fn clear_text_search(&mut self) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
 
-        self.update_background_rendering_current_viewport();
+        self.text_search.clear();
 
-        self.store.regenerate_rendering_in_viewport_threaded(
-            self.tasks_tx(),
-            false,
-            viewport,
-            image_scale,
-        );
+        widget_flags.redraw = true;
+
+        widget_flags
     }
 
-    // Generates bounds for each page on the document which contains content
-    pub fn pages_bounds_w_content(&self) -> Vec<AABB> {
-        let doc_bounds = self.document.bounds();
-        let keys = self.store.stroke_keys_as_rendered();
+    /// Moves the camera to the next match of the text search, wrapping around after the last
+    pub fn text_search_next(&mut self) -> WidgetFlags {
+        self.step_text_search(true)
+    }
 
-        let strokes_bounds = self.store.strokes_bounds(&keys);
+    /// Moves the camera to the previous match of the text search, wrapping around before the first
+    pub fn text_search_prev(&mut self) -> WidgetFlags {
+        self.step_text_search(false)
+    }
 
-        let pages_bounds = doc_bounds
-            .split_extended_origin_aligned(na::vector![
-                self.document.format.width,
-                self.document.format.height
-            ])
-            .into_iter()
-            .filter(|page_bounds| {
-                // Filter the pages out that doesn't intersect with any stroke
-                strokes_bounds
-                    .iter()
-                    .any(|stroke_bounds| stroke_bounds.intersects(page_bounds))
-            })
-            .collect::<Vec<AABB>>();
+    fn step_text_search(&mut self, forward: bool) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
 
-        if pages_bounds.is_empty() {
-            // If no page has content, return the origin page
-            vec![AABB::new(
-                na::point![0.0, 0.0],
-                na::point![self.document.format.width, self.document.format.height],
-            )]
-        } else {
-            pages_bounds
+        // The strokes might have changed since searching
+        let query = self.text_search.query().to_string();
+        if query.is_empty() {
+            return widget_flags;
         }
-    }
+        self.text_search.update(query, &self.store);
 
-    /// Generates bounds which contain all pages on the doc with content extended to fit the format.
-    pub fn bounds_w_content_extended(&self) -> Option<AABB> {
-        let pages_bounds = self.pages_bounds_w_content();
+        let match_center = match self.text_search.step(forward) {
+            Some(search_match) => search_match.bounds.center().coords,
+            None => return widget_flags,
+        };
 
-        if pages_bounds.is_empty() {
-            return None;
-        }
+        self.stop_camera_animation();
+        self.update_camera_offset(match_center * self.camera.zoom() - self.camera.size * 0.5);
+        self.update_rendering_current_viewport();
 
-        Some(
-            pages_bounds
-                .into_iter()
-                .fold(AABB::new_invalid(), |prev, next| prev.merged(&next)),
-        )
-    }
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.update_view = true;
 
-    /// the current document layout
-    pub fn doc_layout(&self) -> Layout {
-        self.document.layout()
+        widget_flags
     }
 
-    pub fn set_doc_layout(&mut self, layout: Layout) {
-        self.document.set_layout(layout, &self.store, &self.camera);
+    /// Wether a camera animation is running
+    pub fn camera_animation_running(&self) -> bool {
+        self.camera_animation.is_some()
     }
 
-    /// resizes the doc to the format and to fit all strokes
-    /// Document background rendering then needs to be updated.
-    pub fn resize_to_fit_strokes(&mut self) {
-        self.document
-            .resize_to_fit_strokes(&self.store, &self.camera);
+    /// Stops the running camera animation, leaving the camera where it currently is
+    pub fn stop_camera_animation(&mut self) {
+        self.camera_animation = None;
     }
 
-    /// resize the doc when in autoexpanding layouts. called e.g. when finishing a new stroke
-    /// Document background rendering then needs to be updated.
-    pub fn resize_autoexpand(&mut self) {
-        self.document.resize_autoexpand(&self.store, &self.camera);
+    /// Steps the running camera animation to the given frame time of the frame clock, in microseconds.
+    /// The zoom is applied as temporary zoom, so it needs to be made permanent when the returned step is finished.
+    /// Returns None if no animation is running.
+    pub fn tick_camera_animation(&mut self, frame_time: i64) -> Option<CameraAnimationStep> {
+        let step = self
+            .camera_animation
+            .as_mut()?
+            .step(frame_time, self.camera.size);
+
+        self.camera
+            .set_temporary_zoom(step.zoom / self.camera.zoom());
+        self.update_camera_offset(step.offset);
+
+        if step.finished {
+            self.camera_animation = None;
+        }
+
+        Some(step)
     }
 
     /// Updates the camera and expands doc dimensions with offset
@@ -538,6 +2070,10 @@ impl RnoteEngine {
                 self.document
                     .expand_doc_infinite_layout(self.camera.viewport());
             }
+            Layout::ContinuousHorizontal => {
+                self.document
+                    .resize_doc_continuous_horizontal_layout(&self.store);
+            }
         }
     }
 
@@ -554,32 +2090,63 @@ impl RnoteEngine {
     }
 
     /// Fetches clipboard content from current state.
-    /// Returns (the content, mime_type)
-    pub fn fetch_clipboard_content(&self) -> anyhow::Result<Option<(Vec<u8>, String)>> {
-        // First try exporting the selection as svg
+    /// Returns the content in all offered formats as (the content, mime_type), ordered from the most specific to the most generic
+    pub fn fetch_clipboard_content(&self) -> anyhow::Result<Vec<(Vec<u8>, String)>> {
+        // First try exporting the selection, in the native format and as svg
         if let Some(selection_svg) = self.export_selection_as_svg_string(false)? {
-            return Ok(Some((
-                selection_svg.into_bytes(),
-                String::from("image/svg+xml"),
-            )));
+            let mut content = vec![];
+
+            match self.selection_as_clipboard_strokes() {
+                Ok(strokes_data) => content.push((
+                    strokes_data,
+                    String::from(Self::STROKES_CLIPBOARD_MIME_TYPE),
+                )),
+                Err(e) => log::error!(
+                    "selection_as_clipboard_strokes() failed in fetch_clipboard_content(), Err {}",
+                    e
+                ),
+            }
+            content.push((selection_svg.into_bytes(), String::from("image/svg+xml")));
+
+            return Ok(content);
         }
 
         // else fetch from pen
-        self.penholder.fetch_clipboard_content(&EngineView {
-            tasks_tx: self.tasks_tx(),
-            doc: &self.document,
-            store: &self.store,
-            camera: &self.camera,
-            audioplayer: &self.audioplayer,
-        })
+        Ok(self
+            .penholder
+            .fetch_clipboard_content(&EngineView {
+                tasks_tx: self.tasks_tx(),
+                doc: &self.document,
+                store: &self.store,
+                camera: &self.camera,
+                audioplayer: &self.audioplayer,
+            })?
+            .into_iter()
+            .collect())
     }
 
-    // pastes clipboard content
+    /// Pastes clipboard content. Content in the native strokes format is inserted as new selection, everything else is pasted into the current pen
     pub fn paste_clipboard_content(
         &mut self,
         clipboard_content: &[u8],
         mime_types: Vec<String>,
     ) -> WidgetFlags {
+        if mime_types
+            .iter()
+            .any(|mime_type| mime_type == Self::STROKES_CLIPBOARD_MIME_TYPE)
+        {
+            return match self.paste_clipboard_strokes(clipboard_content) {
+                Ok(widget_flags) => widget_flags,
+                Err(e) => {
+                    log::error!(
+                        "paste_clipboard_strokes() failed in paste_clipboard_content(), Err {}",
+                        e
+                    );
+                    WidgetFlags::default()
+                }
+            };
+        }
+
         self.penholder.paste_clipboard_content(
             clipboard_content,
             mime_types,
@@ -593,14 +2160,75 @@ impl RnoteEngine {
         )
     }
 
+    /// Serializes the selected strokes into the native clipboard format
+    fn selection_as_clipboard_strokes(&self) -> anyhow::Result<Vec<u8>> {
+        let selection_keys = self.store.selection_keys_as_rendered();
+
+        let clipboard_strokes = ClipboardStrokes {
+            strokes: selection_keys
+                .into_iter()
+                .filter_map(|key| {
+                    Some((
+                        self.store.get_stroke_ref(key)?.clone(),
+                        self.store.stroke_layer(key).unwrap_or_default(),
+                    ))
+                })
+                .collect(),
+        };
+
+        Ok(serde_json::to_vec(&clipboard_strokes)?)
+    }
+
+    /// Inserts the strokes from the native clipboard format as new selection, moved into the current viewport
+    fn paste_clipboard_strokes(&mut self, clipboard_content: &[u8]) -> anyhow::Result<WidgetFlags> {
+        let clipboard_strokes = serde_json::from_slice::<ClipboardStrokes>(clipboard_content)
+            .context("deserializing the clipboard strokes failed")?;
+        let mut strokes = clipboard_strokes
+            .strokes
+            .into_iter()
+            .map(|(stroke, layer)| (stroke, Some(layer)))
+            .collect::<Vec<(Stroke, Option<StrokeLayer>)>>();
+
+        let bounds = strokes
+            .iter()
+            .map(|(stroke, _)| stroke.bounds())
+            .reduce(|acc, bounds| acc.merged(&bounds));
+        let bounds = match bounds {
+            Some(bounds) => bounds,
+            None => return Ok(WidgetFlags::default()),
+        };
+
+        // Paste at the same place as imported vector images, so pasting from other documents ends up in view
+        let insert_pos = (self.camera.transform().inverse()
+            * na::Point2::from(VectorImage::IMPORT_OFFSET_DEFAULT))
+        .coords;
+        let offset = insert_pos - bounds.mins.coords;
+        for (stroke, _) in strokes.iter_mut() {
+            stroke.translate(offset);
+        }
+
+        Ok(self.import_generated_strokes(strokes))
+    }
+
     /// Imports and replace the engine config. NOT for opening files
     pub fn load_engine_config(&mut self, serialized_config: &str) -> anyhow::Result<()> {
         let engine_config = serde_json::from_str::<EngineConfig>(serialized_config)?;
 
-        self.document = serde_json::from_value(engine_config.document)?;
+        // The file data belongs to the current document and is not part of the config
+        let mut document = serde_json::from_value::<Document>(engine_config.document)?;
+        document.swap_file_data(&mut self.document);
+        self.document = document;
         self.penholder = serde_json::from_value(engine_config.penholder)?;
         self.pdf_import_prefs = serde_json::from_value(engine_config.pdf_import_prefs)?;
         self.pen_sounds = serde_json::from_value(engine_config.pen_sounds)?;
+        self.page_templates = serde_json::from_value(engine_config.page_templates)?;
+        self.remove_empty_pages_on_save =
+            serde_json::from_value(engine_config.remove_empty_pages_on_save)?;
+        self.export_fit_to_margins = serde_json::from_value(engine_config.export_fit_to_margins)?;
+        self.dark_mode = serde_json::from_value(engine_config.dark_mode)?;
+        self.camera
+            .set_zoom_config(serde_json::from_value(engine_config.zoom_config)?);
+        self.autoscroll = serde_json::from_value(engine_config.autoscroll)?;
 
         // Set the pen sounds to update the audioplayer
         self.set_pen_sounds(self.pen_sounds);
@@ -608,39 +2236,66 @@ impl RnoteEngine {
         Ok(())
     }
 
+    /// Sets a sink which the engine config is passed to whenever it changes, debounced. None disables the autosave.
+    pub fn set_config_autosave_sink(&mut self, sink: Option<ConfigSink>) -> anyhow::Result<()> {
+        let current_config = self.save_engine_config()?;
+        self.config_autosave
+            .set_sink(sink, &current_config, self.tasks_tx.clone());
+
+        Ok(())
+    }
+
     /// Exports the current engine config as JSON string
     pub fn save_engine_config(&self) -> anyhow::Result<String> {
+        let mut document = self.document.clone();
+        document.clear_file_data();
+
         let engine_config = EngineConfig {
-            document: serde_json::to_value(&self.document)?,
+            document: serde_json::to_value(&document)?,
             penholder: serde_json::to_value(&self.penholder)?,
             pdf_import_prefs: serde_json::to_value(&self.pdf_import_prefs)?,
             pen_sounds: serde_json::to_value(&self.pen_sounds)?,
+            page_templates: serde_json::to_value(&self.page_templates)?,
+            remove_empty_pages_on_save: serde_json::to_value(&self.remove_empty_pages_on_save)?,
+            export_fit_to_margins: serde_json::to_value(&self.export_fit_to_margins)?,
+            dark_mode: serde_json::to_value(&self.dark_mode)?,
+            zoom_config: serde_json::to_value(self.camera.zoom_config())?,
+            autoscroll: serde_json::to_value(&self.autoscroll)?,
         };
 
         Ok(serde_json::to_string(&engine_config)?)
     }
 
-    /// Saves the current state as a .rnote file.
+    /// Saves the current state as a .rnote file. The file is encrypted when a passphrase is given.
     pub fn save_as_rnote_bytes(
         &self,
         file_name: String,
-    ) -> anyhow::Result<oneshot::Receiver<anyhow::Result<Vec<u8>>>> {
-        let (oneshot_sender, oneshot_receiver) = oneshot::channel::<anyhow::Result<Vec<u8>>>();
+        passphrase: Option<String>,
+    ) -> Result<oneshot::Receiver<Result<Vec<u8>, FileError>>, FileError> {
+        let (oneshot_sender, oneshot_receiver) = oneshot::channel::<Result<Vec<u8>, FileError>>();
 
         let mut store_snapshot = self.store.take_store_snapshot();
         Arc::make_mut(&mut store_snapshot).process_before_saving();
 
         // the doc is currently not thread safe, so we have to serialize it in the same thread that holds the engine
         let doc = serde_json::to_value(&self.document)?;
+        let checkpoints = self.encode_checkpoints()?;
+        let watchdog_guard = self
+            .store
+            .watchdog()
+            .watch("save_as_rnote_bytes", self.tasks_tx());
 
         rayon::spawn(move || {
-            let result = || -> anyhow::Result<Vec<u8>> {
-                let rnote_file = RnotefileMaj0Min5 {
+            let _watchdog_guard = watchdog_guard;
+
+            let result = || -> Result<Vec<u8>, FileError> {
+                let rnote_file = RnotefileMaj0Min6 {
                     document: doc,
-                    store_snapshot: serde_json::to_value(&*store_snapshot)?,
+                    store_snapshot: rnoteformat::encode_cbor(&*store_snapshot)?,
+                    checkpoints,
                 };
 
-                rnote_file.save_as_bytes(&file_name)
+                Ok(rnote_file.save_as_bytes_w_passphrase(&file_name, passphrase.as_deref())?)
             };
 
             if let Err(_data) = oneshot_sender.send(result()) {
@@ -657,16 +2312,60 @@ impl RnoteEngine {
         Ok(serde_json::to_string_pretty(self)?)
     }
 
+    /// Saves the state as .rnote file and opens it again in a new engine, through the same path as saving and opening files,
+    /// and checks that the saved state ( the document, the store and the checkpoints ) is semantically equal after the round-trip.
+    /// Catches state which gets lost in the serialization chain. The error lists the paths of the differing values.
+    pub fn check_state_roundtrip(&self) -> anyhow::Result<()> {
+        let bytes = futures::executor::block_on(
+            self.save_as_rnote_bytes(String::from("roundtrip.rnote"), None)?,
+        )??;
+
+        let mut reopened = RnoteEngine::default();
+        let store_snapshot =
+            futures::executor::block_on(reopened.open_from_rnote_bytes_p1(bytes, None)?)??;
+        reopened.open_from_store_snapshot_p2(&store_snapshot)?;
+
+        let saved = self.saved_state_as_value()?;
+        let reopened = reopened.saved_state_as_value()?;
+
+        let diffs = crate::utils::json_value_diffs(&saved, &reopened, "");
+        if !diffs.is_empty() {
+            return Err(anyhow::anyhow!(
+                "state differs after a save and open round-trip at: {}",
+                diffs.join(", ")
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The state which is saved in .rnote files, serialized as JSON value. Deferred strokes are serialized as the strokes they hold.
+    fn saved_state_as_value(&self) -> anyhow::Result<serde_json::Value> {
+        let mut store_snapshot = self.store.take_store_snapshot();
+        Arc::make_mut(&mut store_snapshot).process_before_saving();
+
+        Ok(serde_json::json!({
+            "document": serde_json::to_value(&self.document)?,
+            "store_snapshot": serde_json::to_value(&*store_snapshot)?,
+            "checkpoints": serde_json::to_value(&self.checkpoints)?,
+        }))
+    }
+
     /// generates the doc svg.
     /// The coordinates are translated so that the svg has origin 0.0, 0.0
     /// without root or xml header.
     pub fn gen_doc_svg(&self, with_background: bool) -> Result<render::Svg, anyhow::Error> {
         let doc_bounds = self.document.bounds();
 
-        let strokes = self.store.stroke_keys_as_rendered();
+        let strokes = self
+            .store
+            .stroke_keys_as_exported(self.export_reference_images);
 
         let mut doc_svg = if with_background {
-            let mut background_svg = self.document.background.gen_svg(doc_bounds)?;
+            let mut background_svg = self
+                .document
+                .background
+                .gen_svg(doc_bounds, &self.document.pages_bounds())?;
 
             background_svg.wrap_svg_root(
                 Some(AABB::new(
@@ -686,6 +2385,8 @@ impl RnoteEngine {
             }
         };
 
+        let pages_bounds = self.pages_bounds_w_content();
+
         doc_svg.merge([render::Svg::gen_with_piet_cairo_backend(
             |piet_cx| {
                 piet_cx.transform(kurbo::Affine::translate(
@@ -696,7 +2397,9 @@ impl RnoteEngine {
                     &strokes,
                     piet_cx,
                     RnoteEngine::EXPORT_IMAGE_SCALE,
-                )
+                )?;
+
+                self.document.page_numbers.draw(piet_cx, &pages_bounds)
             },
             AABB::new(na::point![0.0, 0.0], na::Point2::from(doc_bounds.extents())),
         )?]);
@@ -714,7 +2417,10 @@ impl RnoteEngine {
     ) -> Result<render::Svg, anyhow::Error> {
         // Background bounds are still doc bounds, for correct alignment of the background pattern
         let mut doc_svg = if with_background {
-            let mut background_svg = self.document.background.gen_svg(viewport)?;
+            let mut background_svg = self
+                .document
+                .background
+                .gen_svg(viewport, &self.document.pages_bounds())?;
 
             background_svg.wrap_svg_root(
                 Some(AABB::new(
@@ -736,7 +2442,9 @@ impl RnoteEngine {
 
         let strokes_in_viewport = self
             .store
-            .stroke_keys_as_rendered_intersecting_bounds(viewport);
+            .stroke_keys_as_exported_intersecting_bounds(viewport, self.export_reference_images);
+
+        let pages_bounds = self.pages_bounds_w_content();
 
         doc_svg.merge([render::Svg::gen_with_piet_cairo_backend(
             |piet_cx| {
@@ -748,7 +2456,9 @@ impl RnoteEngine {
                     &strokes_in_viewport,
                     piet_cx,
                     RnoteEngine::EXPORT_IMAGE_SCALE,
-                )
+                )?;
+
+                self.document.page_numbers.draw(piet_cx, &pages_bounds)
             },
             AABB::new(na::point![0.0, 0.0], na::Point2::from(viewport.extents())),
         )?]);
@@ -777,7 +2487,10 @@ impl RnoteEngine {
             };
 
         let mut selection_svg = if with_background {
-            let mut background_svg = self.document.background.gen_svg(selection_bounds)?;
+            let mut background_svg = self
+                .document
+                .background
+                .gen_svg(selection_bounds, &self.document.pages_bounds())?;
 
             background_svg.wrap_svg_root(
                 Some(AABB::new(
@@ -821,7 +2534,7 @@ impl RnoteEngine {
     }
 
     /// Exports the doc with the strokes as a SVG string.
-    pub fn export_doc_as_svg_string(&self, with_background: bool) -> Result<String, anyhow::Error> {
+    pub fn export_doc_as_svg_string(&self, with_background: bool) -> Result<String, FileError> {
         let doc_svg = self.gen_doc_svg(with_background)?;
 
         Ok(rnote_compose::utils::add_xml_header(
@@ -839,7 +2552,7 @@ impl RnoteEngine {
     pub fn export_selection_as_svg_string(
         &self,
         with_background: bool,
-    ) -> anyhow::Result<Option<String>> {
+    ) -> Result<Option<String>, FileError> {
         let selection_svg = match self.gen_selection_svg(with_background)? {
             Some(selection_svg) => selection_svg,
             None => return Ok(None),
@@ -856,19 +2569,69 @@ impl RnoteEngine {
         )))
     }
 
+    /// Generates a low resolution overview image of the entire document with the current viewport outlined,
+    /// fitting into the given maximum size in pixels. Meant for displaying a navigable minimap.
+    /// The image rect is in document coords, so positions on the image map back to the document.
+    pub fn gen_overview_image(&self, max_size: na::Vector2<f64>) -> anyhow::Result<render::Image> {
+        let doc_bounds = self.document.bounds();
+        let doc_extents = doc_bounds.extents();
+
+        if doc_extents.min() <= 0.0 || max_size.min() <= 0.0 {
+            return Err(anyhow::anyhow!(
+                "gen_overview_image() failed, the document or the maximum size is empty"
+            ));
+        }
+
+        let image_scale = (max_size[0] / doc_extents[0]).min(max_size[1] / doc_extents[1]);
+        let strokes = self.store.stroke_keys_as_rendered();
+        let pages_bounds = self.document.pages_bounds();
+        let viewport = self.camera.viewport();
+
+        render::Image::gen_with_piet(
+            |piet_cx| {
+                piet_cx.fill(
+                    doc_bounds.to_kurbo_rect(),
+                    &piet::Color::from(self.document.background.color),
+                );
+                for (page, page_bounds) in pages_bounds.iter().enumerate() {
+                    piet_cx.fill(
+                        page_bounds.to_kurbo_rect(),
+                        &piet::Color::from(self.document.background.page_color(page)),
+                    );
+                }
+
+                // Drawing the strokes with the low image scale keeps the image strokes cheap
+                self.store
+                    .draw_stroke_keys_to_piet(&strokes, piet_cx, image_scale)?;
+
+                piet_cx.stroke(
+                    viewport.to_kurbo_rect(),
+                    &piet::Color::from(Self::OVERVIEW_VIEWPORT_COLOR),
+                    Self::OVERVIEW_VIEWPORT_WIDTH / image_scale,
+                );
+
+                Ok(())
+            },
+            doc_bounds,
+            image_scale,
+        )
+    }
+
     /// Exporting doc as encoded image bytes (Png / Jpg, etc.)
     pub fn export_doc_as_bitmapimage_bytes(
         &self,
         format: image::ImageOutputFormat,
         with_background: bool,
-    ) -> Result<Vec<u8>, anyhow::Error> {
+    ) -> Result<Vec<u8>, FileError> {
         let image_scale = 1.0;
 
         let doc_svg = self.gen_doc_svg(with_background)?;
         let doc_svg_bounds = doc_svg.bounds;
 
-        render::Image::gen_image_from_svg(doc_svg, doc_svg_bounds, image_scale)?
-            .into_encoded_bytes(format)
+        Ok(
+            render::Image::gen_image_from_svg(doc_svg, doc_svg_bounds, image_scale)?
+                .into_encoded_bytes(format)?,
+        )
     }
 
     /// Exporting selection as encoded image bytes (Png / Jpg, etc.)
@@ -876,7 +2639,7 @@ impl RnoteEngine {
         &self,
         format: image::ImageOutputFormat,
         with_background: bool,
-    ) -> Result<Option<Vec<u8>>, anyhow::Error> {
+    ) -> Result<Option<Vec<u8>>, FileError> {
         let image_scale = 1.0;
 
         let selection_svg = match self.gen_selection_svg(with_background)? {
@@ -891,27 +2654,114 @@ impl RnoteEngine {
         ))
     }
 
-    /// Exports the doc with the strokes as a Xournal++ .xopp file. Excluding the current selection.
-    pub fn export_doc_as_xopp_bytes(&self, filename: &str) -> Result<Vec<u8>, anyhow::Error> {
-        let current_dpi = self.document.format.dpi;
+    /// Exporting doc as JPEG encoded image bytes that fit into the given size budget ( in bytes ).
+    /// The JPEG quality and, if that is not sufficient, the image scale are lowered until the budget is met.
+    pub fn export_doc_as_bitmapimage_bytes_w_size_target(
+        &self,
+        max_bytes: usize,
+        with_background: bool,
+    ) -> Result<Vec<u8>, FileError> {
+        let doc_svg = self.gen_doc_svg(with_background)?;
 
-        // Only one background for all pages
-        let background = xoppformat::XoppBackground {
-            name: None,
-            bg_type: xoppformat::XoppBackgroundType::Solid {
-                color: self.document.background.color.into(),
-                style: xoppformat::XoppBackgroundSolidStyle::Plain,
-            },
+        Self::encode_svg_as_jpeg_w_size_target(doc_svg, max_bytes)
+    }
+
+    /// Exporting selection as JPEG encoded image bytes that fit into the given size budget ( in bytes ).
+    pub fn export_selection_as_bitmapimage_bytes_w_size_target(
+        &self,
+        max_bytes: usize,
+        with_background: bool,
+    ) -> Result<Option<Vec<u8>>, FileError> {
+        let selection_svg = match self.gen_selection_svg(with_background)? {
+            Some(selection_svg) => selection_svg,
+            None => return Ok(None),
         };
 
+        Ok(Some(Self::encode_svg_as_jpeg_w_size_target(
+            selection_svg,
+            max_bytes,
+        )?))
+    }
+
+    fn encode_svg_as_jpeg_w_size_target(
+        svg: render::Svg,
+        max_bytes: usize,
+    ) -> Result<Vec<u8>, FileError> {
+        let svg_bounds = svg.bounds;
+        let mut image_scale = 1.0;
+
+        while image_scale >= Self::EXPORT_SIZE_TARGET_MIN_IMAGE_SCALE {
+            let image = render::Image::gen_image_from_svg(svg.clone(), svg_bounds, image_scale)?;
+
+            if let Some(bytes) = image.encode_jpeg_w_size_target(max_bytes)? {
+                return Ok(bytes);
+            }
+
+            image_scale *= Self::EXPORT_SIZE_TARGET_IMAGE_SCALE_STEP;
+        }
+
+        Err(FileError::TooLarge(format!(
+            "could not meet the size target of {} bytes in encode_svg_as_jpeg_w_size_target()",
+            max_bytes
+        )))
+    }
+
+    /// Exports the edit operations since the last export as an op log, for storing and replaying deltas instead of whole files.
+    /// Starts recording the operations when not yet collaborating, the first log then inserts all current strokes.
+    /// Collaborating with a sync server takes the operations as well, so only one of them should be used.
+    pub fn export_op_log_bytes(&mut self) -> anyhow::Result<Vec<u8>> {
+        if !self.store.collaborating() {
+            self.store.start_collaborating(rand::random::<u32>());
+        }
+
+        crdt::encode_op_log(&self.store.take_pending_ops())
+    }
+
+    /// Imports an op log, replaying its operations. Already applied operations are skipped, so overlapping logs can be imported.
+    /// A document replayed from logs should start out empty, as the strokes that were not inserted through operations are not known to the other replicas.
+    pub fn import_op_log_bytes(&mut self, bytes: &[u8]) -> anyhow::Result<WidgetFlags> {
+        let ops = crdt::decode_op_log(bytes)?;
+        if !self.store.collaborating() {
+            self.store.start_collaborating(rand::random::<u32>());
+        }
+
+        Ok(self.apply_store_ops(ops))
+    }
+
+    /// Exports the content of the text strokes in reading order, page by page and from top to bottom.
+    /// The text strokes are separated by empty lines
+    pub fn export_doc_as_text(&self, format: TextExportFormat) -> String {
+        let mut text = self
+            .store
+            .textstrokes_in_reading_order(&self.document.pages_bounds())
+            .into_iter()
+            .map(|textstroke| match format {
+                TextExportFormat::Markdown => textstroke.to_markdown(),
+                TextExportFormat::PlainText => textstroke.text.clone(),
+            })
+            .filter(|text| !text.trim().is_empty())
+            .collect::<Vec<String>>()
+            .join("\n\n");
+
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Exports the doc with the strokes as a Xournal++ .xopp file. Excluding the current selection.
+    pub fn export_doc_as_xopp_bytes(&self, filename: &str) -> Result<Vec<u8>, FileError> {
+        let current_dpi = self.document.format.dpi;
+
         // xopp spec needs at least one page in vec, but its fine because pages_bounds_w_content() always produces at least one
         let pages = self
             .pages_bounds_w_content()
             .iter()
             .map(|&page_bounds| {
-                let page_keys = self
-                    .store
-                    .stroke_keys_as_rendered_intersecting_bounds(page_bounds);
+                let page_keys = self.store.stroke_keys_as_exported_intersecting_bounds(
+                    page_bounds,
+                    self.export_reference_images,
+                );
 
                 let strokes = self.store.clone_strokes(&page_keys);
 
@@ -974,10 +2824,24 @@ impl RnoteEngine {
                     xoppformat::XoppFile::DPI,
                 );
 
+                // The page color respects the per-page overrides
+                let page_color = self
+                    .document
+                    .page_index_at(page_bounds.center())
+                    .map(|page| self.document.background.page_color(page))
+                    .unwrap_or(self.document.background.color);
+                let background = xoppformat::XoppBackground {
+                    name: None,
+                    bg_type: xoppformat::XoppBackgroundType::Solid {
+                        color: page_color.into(),
+                        style: xoppformat::XoppBackgroundSolidStyle::Plain,
+                    },
+                };
+
                 xoppformat::XoppPage {
                     width: page_dimensions[0],
                     height: page_dimensions[1],
-                    background: background.clone(),
+                    background,
                     layers: vec![layer],
                 }
             })
@@ -999,20 +2863,27 @@ impl RnoteEngine {
     }
 
     /// Exports the doc with the strokes as a PDF file.
+    /// The recognized text of the strokes is written as invisible text layer, so that the exported notes are searchable.
+    /// Links in text strokes become clickable link annotations.
     pub fn export_doc_as_pdf_bytes(
         &self,
         title: String,
         with_background: bool,
-    ) -> oneshot::Receiver<anyhow::Result<Vec<u8>>> {
-        let (oneshot_sender, oneshot_receiver) = oneshot::channel::<anyhow::Result<Vec<u8>>>();
+    ) -> oneshot::Receiver<Result<Vec<u8>, FileError>> {
+        let (oneshot_sender, oneshot_receiver) = oneshot::channel::<Result<Vec<u8>, FileError>>();
         let doc_bounds = self.document.bounds();
-        let format_size = na::vector![self.document.format.width, self.document.format.height];
+        let format_size = self.document.page_size(0);
         let store_snapshot = self.store.take_store_snapshot();
+        let fit_to_margins = self
+            .export_fit_to_margins
+            .then(|| self.document.format.clone());
+        let metadata = self.document.metadata.clone();
+        let page_numbers = self.document.page_numbers.clone();
 
         let background_svg = if with_background {
             self.document
                 .background
-                .gen_svg(doc_bounds)
+                .gen_svg(doc_bounds, &self.document.pages_bounds())
                 .map_err(|e| {
                     log::error!(
                         "background.gen_svg() failed in export_doc_as_pdf_bytes() with Err {}",
@@ -1028,13 +2899,41 @@ impl RnoteEngine {
             .pages_bounds_w_content()
             .into_iter()
             .map(|page_bounds| {
-                let strokes_in_viewport = self
-                    .store
-                    .stroke_keys_as_rendered_intersecting_bounds(page_bounds);
+                let strokes_in_viewport = self.store.stroke_keys_as_exported_intersecting_bounds(
+                    page_bounds,
+                    self.export_reference_images,
+                );
 
                 (page_bounds, strokes_in_viewport)
             })
             .collect::<Vec<(AABB, Vec<StrokeKey>)>>();
+        let exported_doc_pages = pages_strokes
+            .iter()
+            .map(|(page_bounds, _)| self.document.page_index_at(page_bounds.center()))
+            .collect::<Vec<Option<usize>>>();
+        let sections_outlines = self.sections_outlines(&exported_doc_pages);
+        // The link areas with the attributes of their link tags, for every exported page
+        let pages_links = pages_strokes
+            .iter()
+            .map(|(_, page_strokes)| {
+                page_strokes
+                    .iter()
+                    .flat_map(|&key| self.store.link_areas(key))
+                    .filter_map(|(target, area)| {
+                        Some((
+                            area,
+                            self.pdf_link_attributes(&target, &exported_doc_pages)?,
+                        ))
+                    })
+                    .collect::<Vec<(AABB, String)>>()
+            })
+            .collect::<Vec<Vec<(AABB, String)>>>();
+        let watchdog_guard = self
+            .store
+            .watchdog()
+            .watch("export_doc_as_pdf_bytes", self.tasks_tx());
+
+        let n_pages = pages_strokes.len();
 
         // Fill the pdf surface on a new thread to avoid blocking
         rayon::spawn(move || {
@@ -1043,25 +2942,110 @@ impl RnoteEngine {
                     cairo::PdfSurface::for_stream(format_size[0], format_size[1], Vec::<u8>::new())
                         .context("pdfsurface creation failed")?;
 
+                let title = if metadata.title.is_empty() {
+                    title
+                } else {
+                    metadata.title.clone()
+                };
                 surface
                     .set_metadata(cairo::PdfMetadata::Title, title.as_str())
                     .context("set pdf surface title metadata failed")?;
                 surface
                     .set_metadata(
                         cairo::PdfMetadata::CreateDate,
-                        crate::utils::now_formatted_string().as_str(),
+                        metadata
+                            .created
+                            .as_ref()
+                            .map(Metadata::format_pdf_date)
+                            .unwrap_or_else(crate::utils::now_formatted_string)
+                            .as_str(),
                     )
                     .context("set pdf surface date metadata failed")?;
+                if let Some(modified) = metadata.modified.as_ref() {
+                    surface
+                        .set_metadata(
+                            cairo::PdfMetadata::ModDate,
+                            Metadata::format_pdf_date(modified).as_str(),
+                        )
+                        .context("set pdf surface modification date metadata failed")?;
+                }
+                if !metadata.author.is_empty() {
+                    surface
+                        .set_metadata(cairo::PdfMetadata::Author, metadata.author.as_str())
+                        .context("set pdf surface author metadata failed")?;
+                }
+                if !metadata.tags.is_empty() {
+                    surface
+                        .set_metadata(
+                            cairo::PdfMetadata::Keywords,
+                            metadata.tags_joined().as_str(),
+                        )
+                        .context("set pdf surface keywords metadata failed")?;
+                }
+                if !metadata.app_version.is_empty() {
+                    surface
+                        .set_metadata(
+                            cairo::PdfMetadata::Creator,
+                            format!("Rnote {}", metadata.app_version).as_str(),
+                        )
+                        .context("set pdf surface creator metadata failed")?;
+                }
 
                 // New scope to avoid errors when flushing
                 {
                     let cairo_cx =
                         cairo::Context::new(&surface).context("cario cx new() failed")?;
 
-                    for (i, (page_bounds, page_strokes)) in pages_strokes.into_iter().enumerate() {
+                    for (i, ((page_bounds, page_strokes), page_links)) in
+                        pages_strokes.into_iter().zip(pages_links).enumerate()
+                    {
+                        if watchdog_guard.is_timed_out() {
+                            return Err(FileError::TimedOut(format!(
+                                "export_doc_as_pdf_bytes() was aborted at page {}",
+                                i
+                            ))
+                            .into());
+                        }
+
+                        // Pages can differ in size when they have differing orientations
+                        let page_size = page_bounds.extents();
+                        surface
+                            .set_size(page_size[0], page_size[1])
+                            .context("set pdf surface page size failed")?;
+
+                        // Scales the page into the printable area, keeping the aspect ratio
+                        let (page_offset, page_scale) = match &fit_to_margins {
+                            Some(format) => {
+                                let printable_bounds = format.printable_bounds(AABB::new(
+                                    na::point![0.0, 0.0],
+                                    na::Point2::from(page_size),
+                                ));
+                                let scale = printable_bounds
+                                    .extents()
+                                    .component_div(&page_bounds.extents())
+                                    .min();
+
+                                (
+                                    printable_bounds.center().coords
+                                        - page_bounds.extents() * scale * 0.5,
+                                    scale,
+                                )
+                            }
+                            None => (na::Vector2::zeros(), 1.0),
+                        };
+
                         // We can't render the background svg with piet, so we have to do it with cairo.
                         cairo_cx.save()?;
+                        cairo_cx.translate(page_offset[0], page_offset[1]);
+                        cairo_cx.scale(page_scale, page_scale);
                         cairo_cx.translate(-page_bounds.mins[0], -page_bounds.mins[1]);
+                        cairo_cx.rectangle(
+                            page_bounds.mins[0],
+                            page_bounds.mins[1],
+                            page_bounds.extents()[0],
+                            page_bounds.extents()[1],
+                        );
+                        cairo_cx.clip();
 
                         if let Some(background_svg) = background_svg.clone() {
                             render::Svg::draw_svgs_to_cairo_context(&[background_svg], &cairo_cx)?;
@@ -1071,16 +3055,53 @@ impl RnoteEngine {
                         // Draw the strokes with piet
                         let mut piet_cx = piet_cairo::CairoRenderContext::new(&cairo_cx);
                         piet_cx.save().map_err(|e| anyhow::anyhow!("{}", e))?;
-                        piet_cx.transform(kurbo::Affine::translate(
-                            -page_bounds.mins.coords.to_kurbo_vec(),
-                        ));
-
-                        for stroke in page_strokes.into_iter() {
+                        piet_cx.transform(
+                            kurbo::Affine::translate(page_offset.to_kurbo_vec())
+                                * kurbo::Affine::scale(page_scale)
+                                * kurbo::Affine::translate(-page_bounds.mins.coords.to_kurbo_vec()),
+                        );
+                        piet_cx.clip(page_bounds.to_kurbo_rect());
+
+                        for &stroke in page_strokes.iter() {
                             if let Some(stroke) = store_snapshot.stroke_components.get(stroke) {
                                 stroke.draw(&mut piet_cx, RnoteEngine::EXPORT_IMAGE_SCALE)?;
                             }
                         }
 
+                        // The invisible text layer above the strokes makes the handwriting searchable
+                        for stroke in page_strokes.into_iter() {
+                            if let (Some(recognition_comp), Some(stroke)) = (
+                                store_snapshot.recognition_components.get(stroke),
+                                store_snapshot.stroke_components.get(stroke),
+                            ) {
+                                recognition_comp.draw_text_layer(&mut piet_cx, stroke.bounds())?;
+                            }
+                        }
+
+                        if page_numbers.show {
+                            page_numbers.draw_page_number(&mut piet_cx, page_bounds, i, n_pages)?;
+                        }
+
+                        // Link annotations, the link areas are in page coords
+                        cairo_cx.save()?;
+                        cairo_cx.identity_matrix();
+                        for (area, link_attributes) in page_links {
+                            let mins = page_offset
+                                + (area.mins.coords - page_bounds.mins.coords) * page_scale;
+                            let extents = area.extents() * page_scale;
+
+                            cairo_cx.tag_begin(
+                                Self::PDF_TAG_LINK,
+                                format!(
+                                    "rect=[{} {} {} {}] {}",
+                                    mins[0], mins[1], extents[0], extents[1], link_attributes
+                                )
+                                .as_str(),
+                            );
+                            cairo_cx.tag_end(Self::PDF_TAG_LINK);
+                        }
+                        cairo_cx.restore()?;
+
                         cairo_cx.show_page().map_err(|e| {
                             anyhow::anyhow!(
                                 "show_page() failed when exporting page {} as pdf, Err {}",
@@ -1092,6 +3113,19 @@ impl RnoteEngine {
                         piet_cx.restore().map_err(|e| anyhow::anyhow!("{}", e))?;
                     }
                 }
+
+                // The sections are emitted as top-level bookmarks
+                for (name, page_number) in sections_outlines {
+                    surface
+                        .add_outline(
+                            Self::PDF_OUTLINE_ROOT,
+                            name.as_str(),
+                            format!("page={}", page_number).as_str(),
+                            cairo::PdfOutline::empty(),
+                        )
+                        .context("add pdf outline for section failed")?;
+                }
+
                 let data = *surface
                     .finish_output_stream()
                     .map_err(|e| {
@@ -1111,7 +3145,7 @@ impl RnoteEngine {
                 Ok(data)
             };
 
-            if let Err(_data) = oneshot_sender.send(result()) {
+            if let Err(_data) = oneshot_sender.send(result().map_err(FileError::from)) {
                 log::error!("sending result to receiver in export_doc_as_pdf_bytes() failed. Receiver already dropped.");
             }
         });
@@ -1119,6 +3153,481 @@ impl RnoteEngine {
         oneshot_receiver
     }
 
+    /// The names of the sections and the (one-based) numbers of the exported pages they start on.
+    /// Because empty pages are not exported, a section starts on the first exported page at or after its starting page.
+    /// Sections without any exported pages are skipped.
+    /// `exported_doc_pages` are the document page indices of the exported pages
+    fn sections_outlines(&self, exported_doc_pages: &[Option<usize>]) -> Vec<(String, usize)> {
+        let sections = self.document.sections();
+
+        sections
+            .iter()
+            .enumerate()
+            .filter_map(|(i, section)| {
+                let section_end = sections
+                    .get(i + 1)
+                    .map(|next| next.start_page)
+                    .unwrap_or(usize::MAX);
+
+                exported_doc_pages
+                    .iter()
+                    .position(|doc_page| {
+                        doc_page.map_or(false, |doc_page| {
+                            doc_page >= section.start_page && doc_page < section_end
+                        })
+                    })
+                    .map(|exported_index| (section.name.clone(), exported_index + 1))
+            })
+            .collect()
+    }
+
+    /// Exports the pages of the document as frames of an animated GIF, with the frame rate of the flipbook.
+    pub fn export_flipbook_as_gif_bytes(
+        &self,
+        with_background: bool,
+    ) -> Result<oneshot::Receiver<Result<Vec<u8>, FileError>>, FileError> {
+        let (oneshot_sender, oneshot_receiver) = oneshot::channel::<Result<Vec<u8>, FileError>>();
+        let frame_delay = image::Delay::from_saturating_duration(self.flipbook.frame_duration());
+
+        // Generating the svgs needs the store, so it is done before spawning the encoding thread
+        let frames_svgs = self
+            .document
+            .pages_bounds()
+            .into_iter()
+            .map(|page_bounds| self.gen_doc_svg_with_viewport(page_bounds, with_background))
+            .collect::<anyhow::Result<Vec<render::Svg>>>()?;
+        let watchdog_guard = self
+            .store
+            .watchdog()
+            .watch("export_flipbook_as_gif_bytes", self.tasks_tx());
+
+        // Rasterize and encode the frames on a new thread to avoid blocking
+        rayon::spawn(move || {
+            let result = || -> anyhow::Result<Vec<u8>> {
+                let mut bytes_buf: Vec<u8> = vec![];
+
+                // New scope, because the encoder borrows the buffer
+                {
+                    let mut encoder = image::codecs::gif::GifEncoder::new(&mut bytes_buf);
+
+                    for (i, frame_svg) in frames_svgs.into_iter().enumerate() {
+                        if watchdog_guard.is_timed_out() {
+                            return Err(FileError::TimedOut(format!(
+                                "export_flipbook_as_gif_bytes() was aborted at frame {}",
+                                i
+                            ))
+                            .into());
+                        }
+
+                        let frame_svg_bounds = frame_svg.bounds;
+                        let frame_imgbuf =
+                            render::Image::gen_image_from_svg(frame_svg, frame_svg_bounds, 1.0)?
+                                .to_imgbuf()?;
+
+                        encoder
+                            .encode_frame(image::Frame::from_parts(
+                                frame_imgbuf,
+                                0,
+                                0,
+                                frame_delay,
+                            ))
+                            .with_context(|| {
+                                format!(
+                                    "encode_frame() failed in export_flipbook_as_gif_bytes() for frame {}",
+                                    i
+                                )
+                            })?;
+                    }
+                }
+
+                Ok(bytes_buf)
+            };
+
+            if let Err(_data) = oneshot_sender.send(result().map_err(FileError::from)) {
+                log::error!("sending result to receiver in export_flipbook_as_gif_bytes() failed. Receiver already dropped.");
+            }
+        });
+
+        Ok(oneshot_receiver)
+    }
+
+    /// Starts the flipbook playback at the current page.
+    /// The frames then need to be advanced with `flipbook_advance_frame()` in the interval of `flipbook.frame_duration()`.
+    pub fn flipbook_start_playback(&mut self) {
+        let current_page = self.current_page().unwrap_or(0);
+
+        self.flipbook.start_playback(current_page);
+    }
+
+    pub fn flipbook_stop_playback(&mut self) {
+        self.flipbook.stop_playback();
+    }
+
+    /// Advances the flipbook playback to the next frame.
+    /// Returns the bounds of the page that should be shown, or None if the playback has ended.
+    pub fn flipbook_advance_frame(&mut self) -> Option<AABB> {
+        let pages_bounds = self.document.pages_bounds();
+
+        self.flipbook
+            .advance(pages_bounds.len())
+            .map(|frame| pages_bounds[frame])
+    }
+
+    /// Wether the presentation mode is active
+    pub fn presentation_active(&self) -> bool {
+        self.presentation.is_active()
+    }
+
+    /// Starts the presentation mode at the current page. Editing is locked and the page is fit into the viewport.
+    pub fn start_presentation(&mut self) -> anyhow::Result<WidgetFlags> {
+        if self.document.pages_bounds().is_empty() {
+            return Err(anyhow::anyhow!(
+                "start_presentation() failed, the document has no pages"
+            ));
+        }
+        // Finish what the current pen is doing before locking
+        let mut widget_flags = self.handle_pen_event(PenEvent::Cancel, None);
+
+        let page = self.current_page().unwrap_or(0);
+        self.presentation.start(page);
+        widget_flags.merge_with_other(self.zoom_fit_page(page));
+
+        widget_flags.hide_scrollbars = Some(true);
+        widget_flags.refresh_ui = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Stops the presentation mode, unlocking editing
+    pub fn stop_presentation(&mut self) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        self.presentation.stop();
+
+        widget_flags.hide_scrollbars = Some(false);
+        widget_flags.refresh_ui = true;
+
+        widget_flags
+    }
+
+    /// Jumps the camera to the page after the current one, fitting it into the viewport. Advances the presentation when presenting.
+    /// Does nothing on the last page
+    pub fn next_page(&mut self) -> WidgetFlags {
+        let current_page = if self.presentation.is_active() {
+            Some(self.presentation.current_page())
+        } else {
+            self.current_page()
+        };
+
+        match current_page {
+            Some(current_page) => self.jump_to_page(current_page + 1),
+            None => WidgetFlags::default(),
+        }
+    }
+
+    /// Jumps the camera to the page before the current one, fitting it into the viewport. Goes back in the presentation when presenting.
+    /// Does nothing on the first page
+    pub fn prev_page(&mut self) -> WidgetFlags {
+        let current_page = if self.presentation.is_active() {
+            Some(self.presentation.current_page())
+        } else {
+            self.current_page()
+        };
+
+        match current_page.and_then(|current_page| current_page.checked_sub(1)) {
+            Some(page) => self.jump_to_page(page),
+            None => WidgetFlags::default(),
+        }
+    }
+
+    fn jump_to_page(&mut self, page: usize) -> WidgetFlags {
+        let n_pages = self.document.pages_bounds().len();
+
+        if self.presentation.is_active() && self.presentation.go_to_page(page, n_pages).is_none() {
+            return WidgetFlags::default();
+        }
+
+        self.zoom_fit_page(page)
+    }
+
+    /// Activates the link at the given position, in document coords. Links are only activated while presenting, as they are edited otherwise.
+    /// Page and view bookmark targets are jumped to, the activated target is returned so that the frontend can open urls.
+    pub fn activate_link_at(&mut self, pos: na::Vector2<f64>) -> (Option<LinkTarget>, WidgetFlags) {
+        if !self.presentation.is_active() {
+            return (None, WidgetFlags::default());
+        }
+        let target = match self.store.link_at_coord(pos) {
+            Some(target) => target,
+            None => return (None, WidgetFlags::default()),
+        };
+
+        let widget_flags = match target {
+            LinkTarget::Url(_) => WidgetFlags::default(),
+            LinkTarget::Page(_) | LinkTarget::ViewBookmark(_) => {
+                match self.link_target_page(&target) {
+                    Some(page) => self.jump_to_page(page),
+                    None => {
+                        log::warn!(
+                            "activating link in activate_link_at() failed, target {:?} does not exist",
+                            target
+                        );
+                        WidgetFlags::default()
+                    }
+                }
+            }
+        };
+
+        (Some(target), widget_flags)
+    }
+
+    /// The index of the page the link target points to. None for urls and for targets that don't exist.
+    /// View bookmarks point to the page at the top left of their view
+    fn link_target_page(&self, target: &LinkTarget) -> Option<usize> {
+        match target {
+            LinkTarget::Url(_) => None,
+            LinkTarget::Page(page) => {
+                if *page < self.document.pages_bounds().len() {
+                    Some(*page)
+                } else {
+                    None
+                }
+            }
+            LinkTarget::ViewBookmark(name) => self
+                .document
+                .view_bookmarks()
+                .iter()
+                .find(|bookmark| &bookmark.name == name)
+                .and_then(|bookmark| self.document.page_index_at(bookmark.pos)),
+        }
+    }
+
+    /// The attributes of a cairo link tag for the target, without the link area.
+    /// Links to pages that are not exported are skipped, because empty pages are not exported.
+    fn pdf_link_attributes(
+        &self,
+        target: &LinkTarget,
+        exported_doc_pages: &[Option<usize>],
+    ) -> Option<String> {
+        match target {
+            LinkTarget::Url(url) => Some(format!(
+                "uri='{}'",
+                url.replace('\\', "\\\\").replace('\'', "\\'")
+            )),
+            LinkTarget::Page(_) | LinkTarget::ViewBookmark(_) => {
+                let page = self.link_target_page(target)?;
+
+                exported_doc_pages
+                    .iter()
+                    .position(|&doc_page| doc_page == Some(page))
+                    .map(|exported_index| format!("page={}", exported_index + 1))
+            }
+        }
+    }
+
+    /// Zooms to fit the page with the given index. Does nothing if it doesn't exist
+    fn zoom_fit_page(&mut self, page: usize) -> WidgetFlags {
+        match self.document.pages_bounds().get(page).copied() {
+            Some(page_bounds) => self.zoom_fit_to_bounds(page_bounds),
+            None => WidgetFlags::default(),
+        }
+    }
+
+    /// The index of the current page, which is the page in the center of the viewport
+    pub fn current_page(&self) -> Option<usize> {
+        self.document.page_index_at(self.camera.viewport().center())
+    }
+
+    /// The bounds of the current page
+    pub fn current_page_bounds(&self) -> Option<AABB> {
+        self.current_page()
+            .and_then(|page| self.document.pages_bounds().get(page).copied())
+    }
+
+    /// The paper color of the current page
+    pub fn current_page_color(&self) -> Option<Color> {
+        self.current_page()
+            .map(|page| self.document.background.page_color(page))
+    }
+
+    /// Sets the paper color of the current page. None resets it to the background color.
+    /// The background rendering then needs to be updated.
+    pub fn set_current_page_color(&mut self, color: Option<Color>) {
+        if let Some(page) = self.current_page() {
+            self.document.background.set_page_color(page, color);
+        }
+    }
+
+    /// The index of the current page and of the page that is ghosted beneath it, if the onion skin is enabled.
+    fn onion_skin_pages(&self) -> Option<(usize, usize)> {
+        let current_page = self.current_page()?;
+
+        let onion_skin_page = match self.onion_skin {
+            OnionSkin::Off => return None,
+            OnionSkin::PreviousPage => current_page.checked_sub(1)?,
+            OnionSkin::NextPage => current_page + 1,
+            OnionSkin::Page(page) => page,
+        };
+
+        if onion_skin_page == current_page {
+            return None;
+        }
+
+        Some((current_page, onion_skin_page))
+    }
+
+    /// The bounds of the current page and the page that is ghosted beneath it, if the onion skin is enabled.
+    pub fn onion_skin_pages_bounds(&self) -> Option<(AABB, AABB)> {
+        let pages_bounds = self.document.pages_bounds();
+        let (current_page, onion_skin_page) = self.onion_skin_pages()?;
+
+        Some((
+            pages_bounds[current_page],
+            *pages_bounds.get(onion_skin_page)?,
+        ))
+    }
+
+    /// The rendering of the strokes of the onion skin page, in doc coordinates.
+    /// It is cached, and regenerated only when the strokes of the page changed or the zoom changed significantly.
+    fn onion_skin_rendernode(
+        &self,
+        page: usize,
+        page_bounds: AABB,
+    ) -> anyhow::Result<gsk::RenderNode> {
+        let image_scale = self.camera.image_scale();
+        let mut onion_skin_cache = self.onion_skin_cache.borrow_mut();
+
+        if let Some(cache) = onion_skin_cache.as_mut() {
+            let scale_ratio = image_scale / cache.image_scale;
+
+            if cache.page == page
+                && cache.page_bounds == page_bounds
+                && scale_ratio <= Self::ONION_SKIN_RERENDER_RATIO
+                && scale_ratio >= 1.0 / Self::ONION_SKIN_RERENDER_RATIO
+                && !self
+                    .store
+                    .strokes_changed_since(cache.changes_count, page_bounds)
+            {
+                // Keeps the range of changes that are checked on the next draw small
+                cache.changes_count = self.store.changes_count();
+                return Ok(cache.rendernode.clone());
+            }
+        }
+
+        let changes_count = self.store.changes_count();
+        let rendernode = render::Image::gen_with_piet(
+            |piet_cx| {
+                self.store.draw_strokes_immediate_w_piet(
+                    piet_cx,
+                    self.document.bounds(),
+                    page_bounds,
+                    image_scale,
+                )
+            },
+            page_bounds,
+            image_scale,
+        )?
+        .to_rendernode(None)?;
+
+        *onion_skin_cache = Some(OnionSkinCache {
+            page,
+            page_bounds,
+            image_scale,
+            changes_count,
+            rendernode: rendernode.clone(),
+        });
+
+        Ok(rendernode)
+    }
+
+    /// Draws the strokes of the onion skin page ghosted beneath the current page.
+    fn draw_onion_skin(&self, snapshot: &Snapshot) -> anyhow::Result<()> {
+        let (current_page, onion_skin_page) = match self.onion_skin_pages() {
+            Some(pages) => pages,
+            None => {
+                self.onion_skin_cache.borrow_mut().take();
+                return Ok(());
+            }
+        };
+        let pages_bounds = self.document.pages_bounds();
+        let (current_page_bounds, onion_skin_page_bounds) = match (
+            pages_bounds.get(current_page),
+            pages_bounds.get(onion_skin_page),
+        ) {
+            (Some(current_page_bounds), Some(onion_skin_page_bounds)) => {
+                (*current_page_bounds, *onion_skin_page_bounds)
+            }
+            _ => return Ok(()),
+        };
+        let rendernode = self.onion_skin_rendernode(onion_skin_page, onion_skin_page_bounds)?;
+        let offset = current_page_bounds.mins - onion_skin_page_bounds.mins;
+
+        // Transform to doc coordinate space, and move the onion skin page onto the current page
+        snapshot.save();
+        snapshot.transform(Some(&self.camera.transform_for_gtk_snapshot()));
+        snapshot.push_clip(&graphene::Rect::from_p2d_aabb(current_page_bounds));
+        snapshot.translate(&graphene::Point::new(offset[0] as f32, offset[1] as f32));
+        snapshot.push_opacity(Self::ONION_SKIN_OPACITY);
+
+        snapshot.append_node(&rendernode);
+
+        snapshot.pop();
+        snapshot.pop();
+        snapshot.restore();
+
+        Ok(())
+    }
+
+    /// Draws the page numbers on the pages with content, if they are shown
+    fn draw_page_numbers(&self, snapshot: &Snapshot, surface_bounds: AABB) -> anyhow::Result<()> {
+        if !self.document.page_numbers.show {
+            return Ok(());
+        }
+        let pages_bounds = self.pages_bounds_w_content();
+
+        {
+            let cairo_cx = snapshot.append_cairo(&graphene::Rect::from_p2d_aabb(surface_bounds));
+            let mut piet_cx = piet_cairo::CairoRenderContext::new(&cairo_cx);
+
+            piet_cx.transform(self.camera.transform().to_kurbo());
+            self.document
+                .page_numbers
+                .draw(&mut piet_cx, &pages_bounds)?;
+
+            piet_cx.finish().map_err(|e| anyhow::anyhow!("{}", e))?;
+        }
+
+        Ok(())
+    }
+
+    /// The color matrix and offset for the dark mode. Inverts the luminance of the colors while preserving their hue,
+    /// so white backgrounds become black and near-black strokes become light.
+    fn dark_mode_color_matrix() -> (graphene::Matrix, graphene::Vec4) {
+        let [wr, wg, wb] = Self::DARK_MODE_LUMINANCE_WEIGHTS;
+
+        // GTK multiplies the colors as row vectors, so each row holds the contributions of one input channel
+        let matrix = graphene::Matrix::from_float([
+            1.0 - 2.0 * wr,
+            -2.0 * wr,
+            -2.0 * wr,
+            0.0,
+            -2.0 * wg,
+            1.0 - 2.0 * wg,
+            -2.0 * wg,
+            0.0,
+            -2.0 * wb,
+            -2.0 * wb,
+            1.0 - 2.0 * wb,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            1.0,
+        ]);
+        let offset = graphene::Vec4::new(1.0, 1.0, 1.0, 0.0);
+
+        (matrix, offset)
+    }
+
     /// Draws the entire engine (doc, pens, strokes, selection, ..) on a GTK snapshot.
     pub fn draw_on_snapshot(
         &self,
@@ -1128,24 +3637,53 @@ impl RnoteEngine {
         let doc_bounds = self.document.bounds();
         let viewport = self.camera.viewport();
 
+        if self.dark_mode {
+            let (matrix, offset) = Self::dark_mode_color_matrix();
+            snapshot.push_color_matrix(&matrix, &offset);
+        }
+
         snapshot.save();
         snapshot.transform(Some(&self.camera.transform_for_gtk_snapshot()));
 
         self.document.draw_shadow(snapshot);
 
+        let pages_bounds = self.document.pages_bounds();
+
         self.document
             .background
-            .draw(snapshot, doc_bounds, &self.camera)?;
+            .draw(snapshot, doc_bounds, &pages_bounds, &self.camera)?;
 
         self.document
             .format
-            .draw(snapshot, doc_bounds, &self.camera)?;
+            .draw(snapshot, doc_bounds, &pages_bounds, &self.camera)?;
 
-        self.store
-            .draw_strokes_to_snapshot(snapshot, doc_bounds, viewport);
+        snapshot.restore();
+
+        self.draw_onion_skin(snapshot)?;
+        self.draw_page_numbers(snapshot, surface_bounds)?;
+
+        snapshot.save();
+        snapshot.transform(Some(&self.camera.transform_for_gtk_snapshot()));
+
+        self.store.draw_strokes_to_snapshot(
+            snapshot,
+            doc_bounds,
+            viewport,
+            None,
+            self.render_focus(),
+        );
+
+        if self.author_tint {
+            self.draw_author_tint(snapshot);
+        }
+        self.text_search.draw(snapshot);
+        #[cfg(feature = "sync")]
+        self.draw_remote_cursors(snapshot);
 
         snapshot.restore();
 
+        self.draw_comments(snapshot, surface_bounds)?;
+
         self.penholder.draw_on_doc_snapshot(
             snapshot,
             &EngineView {
@@ -1156,6 +3694,10 @@ impl RnoteEngine {
                 audioplayer: &self.audioplayer,
             },
         )?;
+
+        if self.dark_mode {
+            snapshot.pop();
+        }
         /*
                {
                    use crate::utils::GrapheneRectHelpers;
@@ -1201,6 +3743,60 @@ impl RnoteEngine {
 
         Ok(())
     }
+
+    /// Draws the document and the strokes as seen by the camera of the additional viewport on a GTK snapshot.
+    /// The pens and overlays are only drawn on the main viewport.
+    pub fn draw_additional_viewport_on_snapshot(
+        &self,
+        viewport_key: ViewportKey,
+        snapshot: &Snapshot,
+    ) -> anyhow::Result<()> {
+        let additional_viewport = self.additional_viewports.get(viewport_key).ok_or_else(|| {
+            anyhow::anyhow!(
+                "draw_additional_viewport_on_snapshot() failed, no viewport for key {:?}",
+                viewport_key
+            )
+        })?;
+        let camera = &additional_viewport.camera;
+        let doc_bounds = self.document.bounds();
+        let viewport = camera.viewport();
+        let pages_bounds = self.document.pages_bounds();
+
+        if self.dark_mode {
+            let (matrix, offset) = Self::dark_mode_color_matrix();
+            snapshot.push_color_matrix(&matrix, &offset);
+        }
+
+        snapshot.save();
+        snapshot.transform(Some(&camera.transform_for_gtk_snapshot()));
+
+        self.document.draw_shadow(snapshot);
+        self.document.background.draw_w_rendernodes(
+            snapshot,
+            doc_bounds,
+            &pages_bounds,
+            viewport,
+            &additional_viewport.background_rendernodes,
+        )?;
+        self.document
+            .format
+            .draw(snapshot, doc_bounds, &pages_bounds, camera)?;
+        self.store.draw_strokes_to_snapshot(
+            snapshot,
+            doc_bounds,
+            viewport,
+            Some(viewport_key),
+            self.render_focus(),
+        );
+
+        snapshot.restore();
+
+        if self.dark_mode {
+            snapshot.pop();
+        }
+
+        Ok(())
+    }
 }
 
 /// module for visual debugging
@@ -1430,9 +4026,177 @@ pub mod visual_debug {
                     draw_bounds(bounds, COLOR_SELECTOR_BOUNDS, snapshot, border_widths);
                 }
             }
-            PenStyle::Brush | PenStyle::Shaper | PenStyle::Typewriter | PenStyle::Tools => {}
+            PenStyle::Brush
+            | PenStyle::Highlighter
+            | PenStyle::Laser
+            | PenStyle::Shaper
+            | PenStyle::Spline
+            | PenStyle::Typewriter
+            | PenStyle::Tools => {}
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strokes::Stroke;
+    use approx::assert_relative_eq;
+    use rnote_compose::Style;
+
+    #[test]
+    fn state_roundtrip() {
+        let mut engine = RnoteEngine::default();
+        engine.store.insert_stroke(
+            Stroke::line_fixture(na::vector![0.0, 0.0], na::vector![10.0, 5.0]),
+            None,
+        );
+
+        engine.check_state_roundtrip().unwrap();
+    }
+
+    #[test]
+    fn transform_selection() {
+        let mut engine = RnoteEngine::default();
+        let key = engine.store.insert_stroke(
+            Stroke::line_fixture(na::vector![0.0, 0.0], na::vector![100.0, 50.0]),
+            None,
+        );
+        engine.store.set_selected(key, true);
+
+        let pos = na::vector![40.0, 60.0];
+        engine
+            .transform_selection(TransformSpec {
+                pos: Some(pos),
+                ..TransformSpec::default()
+            })
+            .unwrap();
+
+        assert_relative_eq!(engine.selection_bounds().unwrap().mins.coords, pos);
+    }
+
+    #[test]
+    fn transform_selection_scale_stroke_widths() {
+        let mut engine = RnoteEngine::default();
+        let key = engine.store.insert_stroke(
+            Stroke::line_fixture(na::vector![0.0, 0.0], na::vector![100.0, 100.0]),
+            None,
+        );
+        engine.store.set_selected(key, true);
+        engine.penholder.selector.resize_scale_stroke_widths = true;
+        let stroke_width = Style::default().stroke_width();
+
+        let size = engine.selection_bounds().unwrap().extents() * 2.0;
+        engine
+            .transform_selection(TransformSpec {
+                size: Some(size),
+                ..TransformSpec::default()
+            })
+            .unwrap();
+
+        match engine.store.get_stroke_ref(key) {
+            Some(Stroke::BrushStroke(brushstroke)) => {
+                assert_relative_eq!(brushstroke.style.stroke_width(), stroke_width * 2.0)
+            }
+            _ => panic!("stroke is not a brush stroke"),
+        }
+    }
+
+    #[test]
+    fn clipboard_strokes_roundtrip() {
+        let mut engine = RnoteEngine::default();
+        let key = engine.store.insert_stroke(
+            Stroke::line_fixture(na::vector![0.0, 0.0], na::vector![100.0, 50.0]),
+            Some(StrokeLayer::Highlighter),
+        );
+        engine.store.set_selected(key, true);
+
+        let content = engine.fetch_clipboard_content().unwrap();
+        let (data, mime_type) = content.first().unwrap();
+        assert_eq!(mime_type, RnoteEngine::STROKES_CLIPBOARD_MIME_TYPE);
+
+        let mut other = RnoteEngine::default();
+        other.paste_clipboard_content(data, vec![mime_type.clone()]);
+
+        let pasted_keys = other.store.selection_keys_as_rendered();
+        assert_eq!(pasted_keys.len(), 1);
+        assert_eq!(
+            other.store.stroke_layer(pasted_keys[0]),
+            Some(StrokeLayer::Highlighter)
+        );
+        assert_relative_eq!(
+            other
+                .store
+                .get_stroke_ref(pasted_keys[0])
+                .unwrap()
+                .bounds()
+                .extents(),
+            engine.store.get_stroke_ref(key).unwrap().bounds().extents()
+        );
+    }
+
+    #[test]
+    fn activate_links() {
+        use crate::strokes::textstroke::{LinkTarget, TextAttribute};
+
+        let mut textstroke = crate::strokes::TextStroke::new(
+            String::from("see the docs"),
+            na::vector![10.0, 10.0],
+            crate::strokes::textstroke::TextStyle::default(),
+        );
+        let target = LinkTarget::Url(String::from("https://rnote.flxzt.net"));
+        textstroke.set_attr_for_range(TextAttribute::Link(target.clone()), 8..12);
+        let link_center = textstroke.link_areas()[0].1.center().coords;
+
+        let mut engine = RnoteEngine::default();
+        engine
+            .store
+            .insert_stroke(Stroke::TextStroke(textstroke), None);
+        assert_eq!(engine.store.link_at_coord(link_center), Some(target));
+        // Links are not activated while editing
+        assert!(engine.activate_link_at(link_center).0.is_none());
+    }
+
+    #[test]
+    fn op_log_roundtrip() {
+        let mut engine = RnoteEngine::default();
+        engine.store.insert_stroke(
+            Stroke::line_fixture(na::vector![0.0, 0.0], na::vector![100.0, 50.0]),
+            None,
+        );
+        let first_log = engine.export_op_log_bytes().unwrap();
+        let key = engine.store.stroke_keys_unordered()[0];
+        engine
+            .store
+            .translate_strokes(&[key], na::vector![10.0, 10.0]);
+        let second_log = engine.export_op_log_bytes().unwrap();
+
+        let mut replayed = RnoteEngine::default();
+        let _ = replayed.import_op_log_bytes(&first_log).unwrap();
+        // Overlapping logs are skipped
+        let _ = replayed
+            .import_op_log_bytes(&[first_log, second_log].concat())
+            .unwrap();
+
+        let replayed_keys = replayed.store.stroke_keys_unordered();
+        assert_eq!(replayed_keys.len(), 1);
+        assert_relative_eq!(
+            replayed
+                .store
+                .get_stroke_ref(replayed_keys[0])
+                .unwrap()
+                .bounds()
+                .mins
+                .coords,
+            engine
+                .store
+                .get_stroke_ref(key)
+                .unwrap()
+                .bounds()
+                .mins
+                .coords
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use crate::strokes::vectorimage::SvgImportError;
+use rnote_fileformats::rnoteformat;
+use std::fmt;
+use std::io;
+
+/// The error type of the import / export surface of the engine.
+/// Typed so that frontends are able to show actionable messages and offer retries.
+#[derive(Debug)]
+pub enum FileError {
+    /// The format of the data is not supported
+    UnsupportedFormat(String),
+    /// The data is protected and a password is needed to open it
+    PasswordRequired,
+    /// The given password is wrong, or the protected data was tampered with
+    WrongPassword,
+    /// The data is corrupted and could not be read
+    Corrupted(anyhow::Error),
+    /// The data exceeds a size limit
+    TooLarge(String),
+    /// Reading or writing the data failed
+    IoError(io::Error),
+    /// The task exceeded the watchdog timeout and was aborted
+    TimedOut(String),
+    /// Any other failure, e.g. when rendering for exports
+    Other(anyhow::Error),
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedFormat(msg) => write!(f, "unsupported format, {}", msg),
+            Self::PasswordRequired => write!(f, "a password is required"),
+            Self::WrongPassword => write!(f, "the password is wrong"),
+            Self::Corrupted(e) => write!(f, "corrupted data, {}", e),
+            Self::TooLarge(msg) => write!(f, "too large, {}", msg),
+            Self::IoError(e) => write!(f, "io error, {}", e),
+            Self::TimedOut(msg) => write!(f, "timed out, {}", msg),
+            Self::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Corrupted(e) | Self::Other(e) => Some(e.as_ref()),
+            Self::IoError(e) => Some(e),
+            Self::UnsupportedFormat(_)
+            | Self::PasswordRequired
+            | Self::WrongPassword
+            | Self::TooLarge(_)
+            | Self::TimedOut(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for FileError {
+    fn from(e: io::Error) -> Self {
+        Self::IoError(e)
+    }
+}
+
+impl From<serde_json::Error> for FileError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Other(e.into())
+    }
+}
+
+impl From<image::ImageError> for FileError {
+    fn from(e: image::ImageError) -> Self {
+        match e {
+            image::ImageError::Unsupported(e) => Self::UnsupportedFormat(e.to_string()),
+            image::ImageError::Limits(e) => Self::TooLarge(e.to_string()),
+            image::ImageError::IoError(e) => Self::IoError(e),
+            e @ image::ImageError::Decoding(_) => Self::Corrupted(e.into()),
+            e => Self::Other(e.into()),
+        }
+    }
+}
+
+impl From<SvgImportError> for FileError {
+    fn from(e: SvgImportError) -> Self {
+        match e {
+            e @ SvgImportError::TooLarge { .. } => Self::TooLarge(e.to_string()),
+            e @ SvgImportError::Parse(_) => Self::Corrupted(e.into()),
+        }
+    }
+}
+
+/// Recovers the typed errors that were wrapped in anyhow errors. Errors which can't be classified end up in `Other`.
+impl From<anyhow::Error> for FileError {
+    fn from(e: anyhow::Error) -> Self {
+        let e = match e.downcast::<FileError>() {
+            Ok(e) => return e,
+            Err(e) => e,
+        };
+        let e = match e.downcast::<rnoteformat::PassphraseError>() {
+            Ok(rnoteformat::PassphraseError::Required) => return Self::PasswordRequired,
+            Ok(rnoteformat::PassphraseError::Wrong) => return Self::WrongPassword,
+            Err(e) => e,
+        };
+        let e = match e.downcast::<io::Error>() {
+            Ok(e) => return Self::IoError(e),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<SvgImportError>() {
+            Ok(e) => return Self::from(e),
+            Err(e) => e,
+        };
+        match e.downcast::<image::ImageError>() {
+            Ok(e) => Self::from(e),
+            Err(e) => Self::Other(e),
+        }
+    }
+}
+
+impl FileError {
+    /// Wraps the error as corrupted data, if it can't be classified more precisely.
+    pub fn corrupted(e: impl Into<anyhow::Error>) -> Self {
+        match Self::from(e.into()) {
+            Self::Other(e) => Self::Corrupted(e),
+            e => e,
+        }
+    }
+
+    /// Classifies errors from opening pdf documents
+    pub fn from_pdf_error(e: gtk4::glib::Error) -> Self {
+        match e.kind::<poppler::Error>() {
+            Some(poppler::Error::Encrypted) => Self::PasswordRequired,
+            Some(poppler::Error::Invalid) => Self::UnsupportedFormat(e.to_string()),
+            Some(poppler::Error::OpenFile) => {
+                Self::IoError(io::Error::new(io::ErrorKind::Other, e.to_string()))
+            }
+            _ => Self::Corrupted(e.into()),
+        }
+    }
+}
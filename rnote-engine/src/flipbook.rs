@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Treats each page of the document as a frame of an animation, for playback previews and animated exports.
+/// The animation is only exported as GIF. WebM would need a VP8 / VP9 encoder, which is not available in the dependencies.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename = "flipbook")]
+pub struct Flipbook {
+    /// The frames per second
+    #[serde(rename = "fps")]
+    pub fps: f64,
+    /// Wether the playback restarts at the first frame after the last one
+    #[serde(rename = "loop_playback")]
+    pub loop_playback: bool,
+
+    #[serde(skip)]
+    playing: bool,
+    #[serde(skip)]
+    current_frame: usize,
+}
+
+impl Default for Flipbook {
+    fn default() -> Self {
+        Self {
+            fps: 12.0,
+            loop_playback: true,
+
+            playing: false,
+            current_frame: 0,
+        }
+    }
+}
+
+impl Flipbook {
+    /// The minimum frames per second
+    pub const FPS_MIN: f64 = 1.0;
+    /// The maximum frames per second
+    pub const FPS_MAX: f64 = 60.0;
+
+    /// The duration a single frame is shown
+    pub fn frame_duration(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.fps.clamp(Self::FPS_MIN, Self::FPS_MAX))
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// The index of the current frame, which is the index into the document pages
+    pub fn current_frame(&self) -> usize {
+        self.current_frame
+    }
+
+    /// Starts the playback at the given frame
+    pub fn start_playback(&mut self, frame: usize) {
+        self.playing = true;
+        self.current_frame = frame;
+    }
+
+    pub fn stop_playback(&mut self) {
+        self.playing = false;
+    }
+
+    /// Advances the playback to the next frame. Returns the new current frame, or None if the playback has ended.
+    pub fn advance(&mut self, n_frames: usize) -> Option<usize> {
+        if !self.playing || n_frames == 0 {
+            self.playing = false;
+            return None;
+        }
+
+        if self.current_frame + 1 < n_frames {
+            self.current_frame += 1;
+        } else if self.loop_playback {
+            self.current_frame = 0;
+        } else {
+            self.playing = false;
+            return None;
+        }
+
+        Some(self.current_frame)
+    }
+}
@@ -5,10 +5,11 @@ use rnote_fileformats::{rnoteformat, xoppformat, FileFormatLoader};
 use serde::{Deserialize, Serialize};
 
 use crate::document::{background, Background, Format};
+use crate::fileerror::FileError;
 use crate::pens::penholder::PenStyle;
 use crate::store::chrono_comp::StrokeLayer;
 use crate::store::{StoreSnapshot, StrokeKey};
-use crate::strokes::{BitmapImage, Stroke, VectorImage};
+use crate::strokes::{BitmapImage, Stroke, TextStroke, VectorImage};
 use crate::{Document, RnoteEngine, StrokeStore, WidgetFlags};
 
 #[derive(
@@ -98,20 +99,34 @@ impl Default for PdfImportPrefs {
 impl RnoteEngine {
     /// opens a .rnote file. We need to split this into two methods,
     /// because we can't have it as a async function and await when the engine is wrapped in a refcell without causing panics :/
+    /// Encrypted files need the passphrase, else `FileError::PasswordRequired` or `FileError::WrongPassword` is returned.
     pub fn open_from_rnote_bytes_p1(
         &mut self,
         bytes: Vec<u8>,
-    ) -> anyhow::Result<oneshot::Receiver<anyhow::Result<StoreSnapshot>>> {
-        let rnote_file = rnoteformat::RnotefileMaj0Min5::load_from_bytes(&bytes)?;
+        passphrase: Option<&str>,
+    ) -> Result<oneshot::Receiver<Result<StoreSnapshot, FileError>>, FileError> {
+        let rnote_file =
+            rnoteformat::RnotefileMaj0Min6::load_from_bytes_w_passphrase(&bytes, passphrase)
+                .map_err(FileError::corrupted)?;
 
-        self.document = serde_json::from_value(rnote_file.document)?;
+        self.document =
+            serde_json::from_value(rnote_file.document).map_err(FileError::corrupted)?;
+        self.load_checkpoints(&rnote_file.checkpoints)
+            .map_err(FileError::corrupted)?;
 
         let (store_snapshot_sender, store_snapshot_receiver) =
-            oneshot::channel::<anyhow::Result<StoreSnapshot>>();
+            oneshot::channel::<Result<StoreSnapshot, FileError>>();
+        let watchdog_guard = self
+            .store
+            .watchdog()
+            .watch("open_from_rnote_bytes", self.tasks_tx());
 
         rayon::spawn(move || {
-            let result = || -> anyhow::Result<StoreSnapshot> {
-                Ok(serde_json::from_value(rnote_file.store_snapshot)?)
+            let _watchdog_guard = watchdog_guard;
+
+            let result = || -> Result<StoreSnapshot, FileError> {
+                StoreSnapshot::from_cbor_w_deferred_strokes(&rnote_file.store_snapshot)
+                    .map_err(FileError::corrupted)
             };
 
             if let Err(_data) = store_snapshot_sender.send(result()) {
@@ -135,8 +150,9 @@ impl RnoteEngine {
     }
 
     /// Opens a  Xournal++ .xopp file, and replaces the current state with it.
-    pub fn open_from_xopp_bytes(&mut self, bytes: Vec<u8>) -> anyhow::Result<()> {
-        let xopp_file = xoppformat::XoppFile::load_from_bytes(&bytes)?;
+    pub fn open_from_xopp_bytes(&mut self, bytes: Vec<u8>) -> Result<(), FileError> {
+        let xopp_file =
+            xoppformat::XoppFile::load_from_bytes(&bytes).map_err(FileError::corrupted)?;
 
         // Extract the largest width of all pages, add together all heights
         let (doc_width, doc_height) = xopp_file
@@ -233,14 +249,17 @@ impl RnoteEngine {
         &self,
         pos: na::Vector2<f64>,
         bytes: Vec<u8>,
-    ) -> oneshot::Receiver<anyhow::Result<VectorImage>> {
-        let (oneshot_sender, oneshot_receiver) = oneshot::channel::<anyhow::Result<VectorImage>>();
+    ) -> oneshot::Receiver<Result<VectorImage, FileError>> {
+        let (oneshot_sender, oneshot_receiver) =
+            oneshot::channel::<Result<VectorImage, FileError>>();
 
         rayon::spawn(move || {
-            let result = || -> anyhow::Result<VectorImage> {
-                let svg_str = String::from_utf8(bytes)?;
+            let result = || -> Result<VectorImage, FileError> {
+                let svg_str = String::from_utf8(bytes).map_err(|e| {
+                    FileError::UnsupportedFormat(format!("svg data is not valid utf-8, {}", e))
+                })?;
 
-                VectorImage::import_from_svg_data(&svg_str, pos, None)
+                Ok(VectorImage::import_from_svg_data(&svg_str, pos, None)?)
             };
 
             if let Err(_data) = oneshot_sender.send(result()) {
@@ -256,12 +275,13 @@ impl RnoteEngine {
         &self,
         pos: na::Vector2<f64>,
         bytes: Vec<u8>,
-    ) -> oneshot::Receiver<anyhow::Result<BitmapImage>> {
-        let (oneshot_sender, oneshot_receiver) = oneshot::channel::<anyhow::Result<BitmapImage>>();
+    ) -> oneshot::Receiver<Result<BitmapImage, FileError>> {
+        let (oneshot_sender, oneshot_receiver) =
+            oneshot::channel::<Result<BitmapImage, FileError>>();
 
         rayon::spawn(move || {
-            let result = || -> anyhow::Result<BitmapImage> {
-                BitmapImage::import_from_image_bytes(&bytes, pos)
+            let result = || -> Result<BitmapImage, FileError> {
+                Ok(BitmapImage::import_from_image_bytes(&bytes, pos)?)
             };
 
             if let Err(_data) = oneshot_sender.send(result()) {
@@ -272,21 +292,84 @@ impl RnoteEngine {
         oneshot_receiver
     }
 
+    //// generates a bitmapimage from the frame with the given index of a animated image ( e.g. GIF or WebP )
+    pub fn generate_bitmapimage_from_bytes_w_frame(
+        &self,
+        pos: na::Vector2<f64>,
+        bytes: Vec<u8>,
+        frame_index: usize,
+    ) -> oneshot::Receiver<Result<BitmapImage, FileError>> {
+        let (oneshot_sender, oneshot_receiver) =
+            oneshot::channel::<Result<BitmapImage, FileError>>();
+
+        rayon::spawn(move || {
+            let result = || -> Result<BitmapImage, FileError> {
+                Ok(BitmapImage::import_from_image_bytes_w_frame(
+                    &bytes,
+                    pos,
+                    frame_index,
+                )?)
+            };
+
+            if let Err(_data) = oneshot_sender.send(result()) {
+                log::error!("sending result to receiver in generate_bitmapimage_from_bytes_w_frame() failed. Receiver already dropped.");
+            }
+        });
+
+        oneshot_receiver
+    }
+
+    //// generates a textstroke for the bytes ( from a markdown file ), styled with the typewriter text style
+    pub fn generate_textstroke_from_markdown_bytes(
+        &self,
+        pos: na::Vector2<f64>,
+        bytes: Vec<u8>,
+    ) -> oneshot::Receiver<Result<TextStroke, FileError>> {
+        let (oneshot_sender, oneshot_receiver) =
+            oneshot::channel::<Result<TextStroke, FileError>>();
+        let mut text_style = self.penholder.typewriter.text_style.clone();
+        if self.penholder.typewriter.max_width_enabled {
+            text_style.max_width = Some(self.penholder.typewriter.text_width);
+        }
+
+        rayon::spawn(move || {
+            let result = || -> Result<TextStroke, FileError> {
+                let markdown = String::from_utf8(bytes).map_err(|e| {
+                    FileError::UnsupportedFormat(format!("markdown is not valid utf-8, {}", e))
+                })?;
+
+                Ok(TextStroke::from_markdown(&markdown, pos, text_style))
+            };
+
+            if let Err(_data) = oneshot_sender.send(result()) {
+                log::error!("sending result to receiver in generate_textstroke_from_markdown_bytes() failed. Receiver already dropped.");
+            }
+        });
+
+        oneshot_receiver
+    }
+
     //// generates strokes for each page for the bytes ( from a PDF file )
     pub fn generate_strokes_from_pdf_bytes(
         &self,
         bytes: Vec<u8>,
         insert_pos: na::Vector2<f64>,
         page_range: Option<Range<u32>>,
-    ) -> oneshot::Receiver<anyhow::Result<Vec<(Stroke, Option<StrokeLayer>)>>> {
+    ) -> oneshot::Receiver<Result<Vec<(Stroke, Option<StrokeLayer>)>, FileError>> {
         let (oneshot_sender, oneshot_receiver) =
-            oneshot::channel::<anyhow::Result<Vec<(Stroke, Option<StrokeLayer>)>>>();
+            oneshot::channel::<Result<Vec<(Stroke, Option<StrokeLayer>)>, FileError>>();
         let pdf_import_prefs = self.pdf_import_prefs;
 
         let format = self.document.format.clone();
+        let watchdog_guard = self
+            .store
+            .watchdog()
+            .watch("generate_strokes_from_pdf_bytes", self.tasks_tx());
 
         rayon::spawn(move || {
-            let result = || -> anyhow::Result<Vec<(Stroke, Option<StrokeLayer>)>> {
+            let _watchdog_guard = watchdog_guard;
+
+            let result = || -> Result<Vec<(Stroke, Option<StrokeLayer>)>, FileError> {
                 match pdf_import_prefs.pages_type {
                     PdfImportPagesType::Bitmap => {
                         let bitmapimages = BitmapImage::import_from_pdf_bytes(
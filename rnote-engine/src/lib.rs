@@ -7,26 +7,46 @@
 //! The main entry point is the RnoteEngine struct.
 
 pub mod audioplayer;
+#[cfg(feature = "broadcast")]
+pub mod broadcast;
 pub mod camera;
+pub mod cameraanimation;
+/// module concerned with the checkpoints of the store saved in the .rnote file
+pub mod checkpoint;
+pub mod configautosave;
 pub mod document;
 mod drawbehaviour;
 pub mod engine;
+pub mod fileerror;
+pub mod flipbook;
 /// module concerned with importing data into the engine
 pub mod import;
+/// module concerned with merging .rnote files
+pub mod merge;
+/// module concerned with operations on the pages of the document
+pub mod pages;
 pub mod pens;
+pub mod presentation;
 pub mod render;
+pub mod spellcheck;
 pub mod store;
 pub mod strokes;
+#[cfg(feature = "sync")]
+pub mod sync;
+pub mod textsearch;
 pub mod utils;
+pub mod viewport;
+pub mod watchdog;
 pub mod widgetflags;
 
 // Re-exports
 pub use audioplayer::AudioPlayer;
-pub use camera::Camera;
+pub use camera::{Camera, ZoomConfig};
 pub use document::Document;
 pub use drawbehaviour::DrawBehaviour;
 pub use drawbehaviour::DrawOnDocBehaviour;
 pub use engine::RnoteEngine;
+pub use fileerror::FileError;
 pub use pens::PenHolder;
 pub use store::StrokeStore;
 pub use widgetflags::WidgetFlags;
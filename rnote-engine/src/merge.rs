@@ -0,0 +1,207 @@
+use rnote_fileformats::rnoteformat::{self, RnotefileMaj0Min6};
+use rnote_fileformats::{FileFormatLoader, FileFormatSaver};
+
+use crate::store::{StoreSnapshot, StrokeKey};
+use crate::strokes::Stroke;
+use crate::StrokeStore;
+
+/// A conflict that was encountered while merging. The keys are the keys of the strokes in the merged file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeConflict {
+    /// The stroke was modified differently on both sides. Our version is kept
+    StrokeModifiedInBoth(StrokeKey),
+    /// We modified the stroke, while they removed it. Our version is kept
+    StrokeModifiedAndRemoved(StrokeKey),
+    /// We removed the stroke, while they modified it. Their version is kept
+    StrokeRemovedAndModified(StrokeKey),
+    /// The document (format, background, ..) was modified differently on both sides. Our version is kept
+    DocumentModifiedInBoth,
+}
+
+/// The loaded content of a .rnote file
+struct MergeSide {
+    document: serde_json::Value,
+    snapshot: StoreSnapshot,
+    checkpoints: Vec<u8>,
+}
+
+impl MergeSide {
+    fn load_from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let rnote_file = RnotefileMaj0Min6::load_from_bytes(bytes)?;
+
+        Ok(Self {
+            document: rnote_file.document,
+            snapshot: StoreSnapshot::from_cbor_w_deferred_strokes(&rnote_file.store_snapshot)?,
+            checkpoints: rnote_file.checkpoints,
+        })
+    }
+
+    fn stroke(&self, key: StrokeKey) -> Option<&Stroke> {
+        self.snapshot
+            .stroke_components
+            .get(key)
+            .map(|stroke| &**stroke)
+    }
+}
+
+/// Merges two .rnote files that were modified independently from the common ancestor `base` (e.g. on two devices).
+///
+/// Strokes are matched by their keys, which are kept stable through saving and loading.
+/// Stroke additions, modifications and removals of both sides are combined. When both sides changed the same stroke or the document,
+/// our version is kept (or the modified version, when the other side removed the stroke) and the conflict is reported.
+///
+/// Returns the bytes of the merged file and the conflicts.
+pub fn merge_rnote_files(
+    base: &[u8],
+    ours: &[u8],
+    theirs: &[u8],
+    file_name: &str,
+) -> anyhow::Result<(Vec<u8>, Vec<MergeConflict>)> {
+    let base = MergeSide::load_from_bytes(base)
+        .map_err(|e| anyhow::anyhow!("loading base file in merge_rnote_files() failed, {}", e))?;
+    let ours = MergeSide::load_from_bytes(ours)
+        .map_err(|e| anyhow::anyhow!("loading our file in merge_rnote_files() failed, {}", e))?;
+    let theirs = MergeSide::load_from_bytes(theirs)
+        .map_err(|e| anyhow::anyhow!("loading their file in merge_rnote_files() failed, {}", e))?;
+
+    let mut conflicts = vec![];
+
+    let document = if theirs.document == base.document || theirs.document == ours.document {
+        ours.document.clone()
+    } else if ours.document == base.document {
+        theirs.document.clone()
+    } else {
+        conflicts.push(MergeConflict::DocumentModifiedInBoth);
+        ours.document.clone()
+    };
+
+    let mut merged = StrokeStore::new();
+    merged.import_snapshot(&ours.snapshot);
+
+    let their_layer = |key: StrokeKey| {
+        theirs
+            .snapshot
+            .chrono_components
+            .get(key)
+            .map(|chrono_comp| chrono_comp.layer)
+    };
+
+    for (key, base_stroke) in base.snapshot.stroke_components.iter() {
+        let base_value = serde_json::to_value(&**base_stroke)?;
+
+        match (ours.stroke(key), theirs.stroke(key)) {
+            (Some(our_stroke), Some(their_stroke)) => {
+                let our_value = serde_json::to_value(our_stroke)?;
+                let their_value = serde_json::to_value(their_stroke)?;
+
+                if their_value == base_value || their_value == our_value {
+                    continue;
+                }
+                if our_value == base_value {
+                    if let Some(stroke) = merged.get_stroke_mut(key) {
+                        *stroke = their_stroke.clone();
+                    }
+                    merged.update_geometry_for_stroke(key);
+                } else {
+                    conflicts.push(MergeConflict::StrokeModifiedInBoth(key));
+                }
+            }
+            (Some(our_stroke), None) => {
+                if serde_json::to_value(our_stroke)? == base_value {
+                    merged.remove_stroke(key);
+                } else {
+                    conflicts.push(MergeConflict::StrokeModifiedAndRemoved(key));
+                }
+            }
+            (None, Some(their_stroke)) => {
+                if serde_json::to_value(their_stroke)? != base_value {
+                    let new_key = merged.insert_stroke(their_stroke.clone(), their_layer(key));
+                    conflicts.push(MergeConflict::StrokeRemovedAndModified(new_key));
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    // Keys of strokes added on both sides might collide, so their added strokes are inserted with new keys
+    for (key, their_stroke) in theirs.snapshot.stroke_components.iter() {
+        if !base.snapshot.stroke_components.contains_key(key) {
+            merged.insert_stroke((**their_stroke).clone(), their_layer(key));
+        }
+    }
+
+    let mut store_snapshot = merged.take_store_snapshot();
+    std::sync::Arc::make_mut(&mut store_snapshot).process_before_saving();
+
+    let rnote_file = RnotefileMaj0Min6 {
+        document,
+        store_snapshot: rnoteformat::encode_cbor(&*store_snapshot)?,
+        checkpoints: ours.checkpoints,
+    };
+
+    Ok((rnote_file.save_as_bytes(file_name)?, conflicts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RnoteEngine;
+    use approx::assert_relative_eq;
+    use rnote_compose::shapes::ShapeBehaviour;
+
+    #[test]
+    fn merge_strokes() {
+        let line = |x: f64| Stroke::line_fixture(na::vector![x, 0.0], na::vector![x + 10.0, 10.0]);
+        let save = |engine: &RnoteEngine| {
+            RnotefileMaj0Min6 {
+                document: serde_json::to_value(&engine.document).unwrap(),
+                store_snapshot: rnoteformat::encode_cbor(&*engine.store.take_store_snapshot())
+                    .unwrap(),
+                checkpoints: vec![],
+            }
+            .save_as_bytes("merge.rnote")
+            .unwrap()
+        };
+
+        let mut base = RnoteEngine::default();
+        let a = base.store.insert_stroke(line(0.0), None);
+        let b = base.store.insert_stroke(line(100.0), None);
+        let c = base.store.insert_stroke(line(200.0), None);
+
+        let mut ours = RnoteEngine::default();
+        ours.store
+            .import_snapshot(&base.store.take_store_snapshot());
+        ours.store.translate_strokes(&[a], na::vector![10.0, 0.0]);
+        ours.store.remove_stroke(b);
+        ours.store.insert_stroke(line(300.0), None);
+
+        let mut theirs = RnoteEngine::default();
+        theirs
+            .store
+            .import_snapshot(&base.store.take_store_snapshot());
+        theirs.store.translate_strokes(&[a], na::vector![0.0, 10.0]);
+        theirs.store.translate_strokes(&[c], na::vector![0.0, 50.0]);
+        theirs.store.insert_stroke(line(400.0), None);
+
+        let (merged_bytes, conflicts) =
+            merge_rnote_files(&save(&base), &save(&ours), &save(&theirs), "merge.rnote").unwrap();
+        assert_eq!(conflicts, vec![MergeConflict::StrokeModifiedInBoth(a)]);
+
+        let merged = StoreSnapshot::from_cbor_w_deferred_strokes(
+            &RnotefileMaj0Min6::load_from_bytes(&merged_bytes)
+                .unwrap()
+                .store_snapshot,
+        )
+        .unwrap();
+        assert_eq!(merged.stroke_components.len(), 4);
+        assert!(!merged.stroke_components.contains_key(b));
+        assert_relative_eq!(
+            merged.stroke_components[a].bounds().mins.coords,
+            ours.store.get_stroke_ref(a).unwrap().bounds().mins.coords
+        );
+        assert_relative_eq!(
+            merged.stroke_components[c].bounds().mins.coords,
+            theirs.store.get_stroke_ref(c).unwrap().bounds().mins.coords
+        );
+    }
+}
@@ -0,0 +1,945 @@
+use std::collections::HashSet;
+use std::ops::Range;
+use std::sync::Arc;
+
+use anyhow::Context;
+use p2d::bounding_volume::{BoundingVolume, AABB};
+use rnote_compose::shapes::ShapeBehaviour;
+use rnote_compose::transform::TransformBehaviour;
+use serde::{Deserialize, Serialize};
+
+use crate::document::background::PageBackgroundOverride;
+use crate::document::format::Orientation;
+use crate::document::{Layout, OutlineEntry, Section};
+use crate::store::StrokeKey;
+use crate::strokes::Stroke;
+use crate::{RnoteEngine, StrokeStore, WidgetFlags};
+use rnote_compose::Color;
+use rnote_fileformats::rnoteformat::{self, RnotefileMaj0Min6};
+
+/// A template for new pages, with a background and pre-placed strokes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename = "page_template")]
+pub struct PageTemplate {
+    /// The name of the template
+    #[serde(rename = "name")]
+    pub name: String,
+    /// The background overrides of the page
+    #[serde(rename = "background")]
+    pub background: PageBackgroundOverride,
+    /// The strokes, with coordinates relative to the page origin
+    #[serde(rename = "strokes")]
+    pub strokes: Vec<Stroke>,
+}
+
+impl RnoteEngine {
+    /// The vertical spacing between the existing content and the content pulled up when merging pages
+    pub const PAGE_MERGE_SPACING: f64 = 20.0;
+    /// The min factor the content is scaled down with to fit when merging pages
+    pub const PAGE_MERGE_MIN_SCALE: f64 = 0.25;
+
+    /// The bounds of the page with the given index
+    pub fn page_bounds(&self, page: usize) -> Option<AABB> {
+        self.document.pages_bounds().get(page).copied()
+    }
+
+    /// Creates a template from the page with the given index, with its background overrides and the strokes contained in it.
+    pub fn page_as_template(&self, page: usize, name: String) -> anyhow::Result<PageTemplate> {
+        let page_bounds = self.page_bounds(page).ok_or_else(|| {
+            anyhow::anyhow!("page_as_template() failed, page {} does not exist", page)
+        })?;
+
+        let keys = self
+            .store
+            .stroke_keys_as_rendered_intersecting_bounds(page_bounds)
+            .into_iter()
+            .filter(|&key| {
+                self.store
+                    .get_stroke_ref(key)
+                    .map(|stroke| page_bounds.contains(&stroke.bounds()))
+                    .unwrap_or(false)
+            })
+            .collect::<Vec<StrokeKey>>();
+
+        let strokes = self
+            .store
+            .clone_strokes(&keys)
+            .into_iter()
+            .map(|mut stroke| {
+                stroke.translate(-page_bounds.mins.coords);
+                stroke
+            })
+            .collect();
+
+        Ok(PageTemplate {
+            name,
+            background: self
+                .document
+                .background
+                .page_overrides
+                .get(&page)
+                .cloned()
+                .unwrap_or_default(),
+            strokes,
+        })
+    }
+
+    /// Inserts a new page from the template before the page with the given index, shifting the subsequent content down.
+    /// Pages can only be inserted in the fixed size and continuous vertical layouts.
+    pub fn insert_page_from_template(
+        &mut self,
+        index: usize,
+        template: &PageTemplate,
+    ) -> anyhow::Result<WidgetFlags> {
+        self.check_page_insertable(index)
+            .context("insert_page_from_template() failed")?;
+
+        let mut widget_flags = self.store.record_pages_state(self.document.pages_state());
+
+        let page_origin = self.insert_empty_page(
+            index,
+            template.background.clone(),
+            self.document.format.orientation,
+        );
+
+        let inserted = template
+            .strokes
+            .iter()
+            .cloned()
+            .map(|mut stroke| {
+                stroke.translate(page_origin);
+                self.store.insert_stroke(stroke, None)
+            })
+            .collect::<Vec<StrokeKey>>();
+        self.store.update_geometry_for_strokes(&inserted);
+
+        self.store.set_pages_state(self.document.pages_state());
+        self.resize_autoexpand();
+        self.update_rendering_current_viewport();
+
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Splits the page at the given position into two at a horizontal cut.
+    /// The strokes below the cut are moved to a newly inserted page after it, the strokes crossing the cut stay.
+    /// Pages can only be split in the fixed size and continuous vertical layouts.
+    pub fn split_page_at(&mut self, pos: na::Point2<f64>) -> anyhow::Result<WidgetFlags> {
+        let page = self.document.page_index_at(pos).ok_or_else(|| {
+            anyhow::anyhow!(
+                "split_page_at() failed, no page at position {:?}",
+                pos.coords
+            )
+        })?;
+        self.check_page_insertable(page + 1)
+            .context("split_page_at() failed")?;
+        let page_bounds = self.document.pages_bounds()[page];
+
+        let mut widget_flags = self.store.record_pages_state(self.document.pages_state());
+
+        // Collect the strokes below the cut, before the subsequent content is shifted
+        let below_page = self
+            .store
+            .keys_below_y_pos(page_bounds.maxs[1])
+            .into_iter()
+            .collect::<HashSet<StrokeKey>>();
+        let below_cut = self
+            .store
+            .keys_below_y_pos(pos[1])
+            .into_iter()
+            .filter(|key| !below_page.contains(key))
+            .collect::<Vec<StrokeKey>>();
+
+        let page_override = self
+            .document
+            .background
+            .page_overrides
+            .get(&page)
+            .cloned()
+            .unwrap_or_default();
+        let new_page_origin = self.insert_empty_page(
+            page + 1,
+            page_override,
+            self.document.page_orientation(page),
+        );
+
+        // Move the strokes below the cut to the top of the new page
+        let offset = na::vector![0.0, new_page_origin[1] - pos[1]];
+        self.store.translate_strokes(&below_cut, offset);
+        self.store.translate_strokes_images(&below_cut, offset);
+        self.document.map_page_anchors_y(|y| {
+            if y >= pos[1] && y < page_bounds.maxs[1] {
+                y + offset[1]
+            } else {
+                y
+            }
+        });
+
+        self.store.set_pages_state(self.document.pages_state());
+        self.resize_autoexpand();
+        self.update_rendering_current_viewport();
+
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Pulls the content of the page after the given one up onto it, below the existing content, and removes the emptied page.
+    /// The content is scaled down if it does not fit.
+    /// Pages can only be merged in the fixed size and continuous vertical layouts.
+    pub fn merge_page_with_next(&mut self, page: usize) -> anyhow::Result<WidgetFlags> {
+        if !self.document.layout().pages_stacked_vertically() {
+            return Err(anyhow::anyhow!(
+                "merge_page_with_next() failed, pages can only be merged in the fixed size and continuous vertical layouts"
+            ));
+        }
+        let pages_bounds = self.document.pages_bounds();
+        let (page_bounds, next_page_bounds) =
+            match (pages_bounds.get(page), pages_bounds.get(page + 1)) {
+                (Some(&page_bounds), Some(&next_page_bounds)) => (page_bounds, next_page_bounds),
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "merge_page_with_next() failed, page {} has no next page",
+                        page
+                    ))
+                }
+            };
+
+        let free_top = self
+            .store
+            .bounds_for_strokes(&self.keys_starting_on_page(page_bounds))
+            .map(|content_bounds| content_bounds.maxs[1] + Self::PAGE_MERGE_SPACING)
+            .unwrap_or(page_bounds.mins[1]);
+        let free_height = page_bounds.maxs[1] - free_top;
+
+        let next_keys = self.keys_starting_on_page(next_page_bounds);
+        let next_content_bounds = self.store.bounds_for_strokes(&next_keys);
+
+        let scale = match next_content_bounds {
+            Some(next_content_bounds) if next_content_bounds.extents()[1] > free_height => {
+                let scale = free_height / next_content_bounds.extents()[1];
+
+                if scale < Self::PAGE_MERGE_MIN_SCALE {
+                    return Err(anyhow::anyhow!(
+                        "merge_page_with_next() failed, not enough space on page {} for the content of the next page",
+                        page
+                    ));
+                }
+                scale
+            }
+            _ => 1.0,
+        };
+
+        let mut widget_flags = self.store.record_pages_state(self.document.pages_state());
+
+        if let Some(next_content_bounds) = next_content_bounds {
+            let pivot = next_content_bounds.mins.coords;
+            let offset = na::vector![0.0, free_top - next_content_bounds.mins[1]];
+
+            if scale < 1.0 {
+                self.store.scale_strokes_with_pivot(
+                    &next_keys,
+                    na::Vector2::from_element(scale),
+                    pivot,
+                    false,
+                );
+                self.store.scale_strokes_images_with_pivot(
+                    &next_keys,
+                    na::Vector2::from_element(scale),
+                    pivot,
+                );
+            }
+            self.store.translate_strokes(&next_keys, offset);
+            self.store.translate_strokes_images(&next_keys, offset);
+            self.store.update_geometry_for_strokes(&next_keys);
+            self.document.map_page_anchors_y(|y| {
+                if y >= next_page_bounds.mins[1] && y < next_page_bounds.maxs[1] {
+                    pivot[1] + (y - pivot[1]).max(0.0) * scale + offset[1]
+                } else {
+                    y
+                }
+            });
+        }
+
+        self.remove_empty_page(page + 1);
+
+        self.store.set_pages_state(self.document.pages_state());
+        self.resize_autoexpand();
+        self.update_rendering_current_viewport();
+
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Inserts a blank page before the page with the given index, shifting the subsequent content down.
+    /// Pages can only be inserted in the fixed size and continuous vertical layouts.
+    pub fn insert_page(&mut self, index: usize) -> anyhow::Result<WidgetFlags> {
+        self.check_page_insertable(index)
+            .context("insert_page() failed")?;
+
+        let mut widget_flags = self.store.record_pages_state(self.document.pages_state());
+
+        self.insert_empty_page(
+            index,
+            PageBackgroundOverride::default(),
+            self.document.format.orientation,
+        );
+
+        self.store.set_pages_state(self.document.pages_state());
+        self.resize_autoexpand();
+        self.update_rendering_current_viewport();
+
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Deletes the page with the given index. The strokes starting on it are trashed and the subsequent content moves up.
+    /// At least one page is kept. Pages can only be deleted in the fixed size and continuous vertical layouts.
+    pub fn delete_page(&mut self, index: usize) -> anyhow::Result<WidgetFlags> {
+        if !self.document.layout().pages_stacked_vertically() {
+            return Err(anyhow::anyhow!(
+                "delete_page() failed, pages can only be deleted in the fixed size and continuous vertical layouts"
+            ));
+        }
+        let pages_bounds = self.document.pages_bounds();
+        let page_bounds = *pages_bounds.get(index).ok_or_else(|| {
+            anyhow::anyhow!(
+                "delete_page() failed, index {} is out of bounds for {} pages",
+                index,
+                pages_bounds.len()
+            )
+        })?;
+        if pages_bounds.len() <= 1 {
+            return Err(anyhow::anyhow!(
+                "delete_page() failed, the last remaining page can't be deleted"
+            ));
+        }
+
+        let mut widget_flags = self.store.record_pages_state(self.document.pages_state());
+
+        let page_keys = self.keys_starting_on_page(page_bounds);
+        self.store.set_trashed_keys(&page_keys, true);
+
+        self.remove_empty_page(index);
+
+        self.store.set_pages_state(self.document.pages_state());
+        self.resize_autoexpand();
+        self.update_rendering_current_viewport();
+
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Moves the page with the given index to the new index, together with its strokes and background overrides.
+    /// The pages in between shift to fill the gap. The section markers stay at their page indices.
+    /// Pages can only be moved in the fixed size and continuous vertical layouts.
+    pub fn move_page(&mut self, from: usize, to: usize) -> anyhow::Result<WidgetFlags> {
+        if !self.document.layout().pages_stacked_vertically() {
+            return Err(anyhow::anyhow!(
+                "move_page() failed, pages can only be moved in the fixed size and continuous vertical layouts"
+            ));
+        }
+        let pages_bounds = self.document.pages_bounds();
+        if from >= pages_bounds.len() || to >= pages_bounds.len() {
+            return Err(anyhow::anyhow!(
+                "move_page() failed, indices {} and {} are out of bounds for {} pages",
+                from,
+                to,
+                pages_bounds.len()
+            ));
+        }
+        if from == to {
+            return Ok(WidgetFlags::default());
+        }
+
+        let mut widget_flags = self.store.record_pages_state(self.document.pages_state());
+
+        // The new tops of the pages, by their current index. Pages may differ in height
+        let mut new_order = (0..pages_bounds.len()).collect::<Vec<usize>>();
+        let moved = new_order.remove(from);
+        new_order.insert(to, moved);
+        let mut new_tops = vec![0.0; pages_bounds.len()];
+        let mut page_top = pages_bounds[0].mins[1];
+        for &page in new_order.iter() {
+            new_tops[page] = page_top;
+            page_top += pages_bounds[page].extents()[1];
+        }
+
+        // Collect the strokes of all affected pages before any of them are moved
+        let (first, last) = (from.min(to), from.max(to));
+        let pages_keys = (first..=last)
+            .map(|page| (page, self.keys_starting_on_page(pages_bounds[page])))
+            .collect::<Vec<(usize, Vec<StrokeKey>)>>();
+
+        for (page, keys) in pages_keys {
+            let offset = na::vector![0.0, new_tops[page] - pages_bounds[page].mins[1]];
+
+            self.store.translate_strokes(&keys, offset);
+            self.store.translate_strokes_images(&keys, offset);
+            self.store.update_geometry_for_strokes(&keys);
+        }
+        self.document.map_page_anchors_y(|y| {
+            match (first..=last)
+                .find(|&page| y >= pages_bounds[page].mins[1] && y < pages_bounds[page].maxs[1])
+            {
+                Some(page) => y + new_tops[page] - pages_bounds[page].mins[1],
+                None => y,
+            }
+        });
+
+        let page_override = self
+            .document
+            .background
+            .remove_page_override(from)
+            .unwrap_or_default();
+        self.document
+            .background
+            .insert_page_override(to, page_override);
+        let orientation = self.document.remove_page_orientation(from);
+        self.document.insert_page_orientation(to, orientation);
+
+        self.store.set_pages_state(self.document.pages_state());
+        self.resize_autoexpand();
+        self.update_rendering_current_viewport();
+
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Clones the strokes starting on the pages in the given range into a standalone document, e.g. for splitting a notebook into multiple files.
+    /// The pages are stacked vertically from the origin, keeping their sections, outline entries and backgrounds.
+    pub fn extract_pages(&self, range: Range<usize>) -> anyhow::Result<RnotefileMaj0Min6> {
+        let pages_bounds = self.document.pages_bounds();
+        if range.is_empty() || range.end > pages_bounds.len() {
+            return Err(anyhow::anyhow!(
+                "extract_pages() failed, range {:?} is invalid for {} pages",
+                range,
+                pages_bounds.len()
+            ));
+        }
+
+        let mut page_top = 0.0;
+        let pages_offsets = pages_bounds[range.clone()]
+            .iter()
+            .map(|&page_bounds| {
+                let offset = na::vector![0.0, page_top] - page_bounds.mins.coords;
+                page_top += page_bounds.extents()[1];
+
+                (page_bounds, offset)
+            })
+            .collect::<Vec<(AABB, na::Vector2<f64>)>>();
+
+        let mut store = StrokeStore::default();
+        for &(page_bounds, offset) in pages_offsets.iter() {
+            let keys = self.keys_starting_on_page(page_bounds);
+
+            for mut stroke in self.store.clone_strokes(&keys) {
+                stroke.translate(offset);
+                store.insert_stroke(stroke, None);
+            }
+        }
+        let mut store_snapshot = store.take_store_snapshot();
+        Arc::make_mut(&mut store_snapshot).process_before_saving();
+
+        let doc = self.document.extract_pages(range, &pages_offsets);
+
+        Ok(RnotefileMaj0Min6 {
+            document: serde_json::to_value(&doc)?,
+            store_snapshot: rnoteformat::encode_cbor(&*store_snapshot)?,
+            checkpoints: vec![],
+        })
+    }
+
+    /// The orientation of the page with the given index
+    pub fn page_orientation(&self, page: usize) -> Orientation {
+        self.document.page_orientation(page)
+    }
+
+    /// Sets the orientation of the page with the given index, independent of the format orientation.
+    /// The subsequent content is shifted by the change of the page height.
+    /// Page orientations can only be set in the fixed size layout.
+    pub fn set_page_orientation(
+        &mut self,
+        page: usize,
+        orientation: Orientation,
+    ) -> anyhow::Result<WidgetFlags> {
+        if self.document.layout() != Layout::FixedSize {
+            return Err(anyhow::anyhow!(
+                "set_page_orientation() failed, page orientations can only be set in the fixed size layout"
+            ));
+        }
+        let page_bounds = self.page_bounds(page).ok_or_else(|| {
+            anyhow::anyhow!(
+                "set_page_orientation() failed, page {} does not exist",
+                page
+            )
+        })?;
+        if self.document.page_orientation(page) == orientation {
+            return Ok(WidgetFlags::default());
+        }
+
+        let mut widget_flags = self.store.record_pages_state(self.document.pages_state());
+
+        let keys_below = self.store.keys_below_y_pos(page_bounds.maxs[1]);
+        self.document.set_page_orientation(page, orientation);
+        let height_delta = self.document.page_size(page)[1] - page_bounds.extents()[1];
+
+        self.store
+            .translate_strokes(&keys_below, na::vector![0.0, height_delta]);
+        self.store
+            .translate_strokes_images(&keys_below, na::vector![0.0, height_delta]);
+        self.document.height += height_delta;
+        self.document.fit_width_to_pages();
+        self.document.map_page_anchors_y(|y| {
+            if y >= page_bounds.maxs[1] {
+                y + height_delta
+            } else {
+                y
+            }
+        });
+
+        self.store.set_pages_state(self.document.pages_state());
+        self.update_rendering_current_viewport();
+
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// The section markers of the document, ordered by their starting page
+    pub fn sections(&self) -> &[Section] {
+        self.document.sections()
+    }
+
+    /// Adds a section marker starting on the page with the given index. An existing section starting on the same page is replaced.
+    /// Returns the index of the added section.
+    pub fn add_section(
+        &mut self,
+        name: String,
+        color: Color,
+        start_page: usize,
+    ) -> anyhow::Result<(usize, WidgetFlags)> {
+        let n_pages = self.document.pages_bounds().len();
+        if start_page >= n_pages {
+            return Err(anyhow::anyhow!(
+                "add_section() failed, page {} is out of bounds for {} pages",
+                start_page,
+                n_pages
+            ));
+        }
+        let mut widget_flags = WidgetFlags::default();
+
+        let index = self
+            .document
+            .add_section(Section::new(name, color, start_page));
+
+        widget_flags.redraw = true;
+        widget_flags.indicate_changed_store = true;
+
+        Ok((index, widget_flags))
+    }
+
+    /// Updates the name and color of the section marker with the given index
+    pub fn update_section(
+        &mut self,
+        index: usize,
+        name: String,
+        color: Color,
+    ) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+        let start_page = self
+            .document
+            .sections()
+            .get(index)
+            .map(|section| section.start_page)
+            .ok_or_else(|| {
+                anyhow::anyhow!("update_section() failed, section {} does not exist", index)
+            })?;
+
+        self.document
+            .add_section(Section::new(name, color, start_page));
+
+        widget_flags.redraw = true;
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Removes the section marker with the given index
+    pub fn remove_section(&mut self, index: usize) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+
+        self.document.remove_section(index).ok_or_else(|| {
+            anyhow::anyhow!("remove_section() failed, section {} does not exist", index)
+        })?;
+
+        widget_flags.redraw = true;
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// The section the page with the given index belongs to
+    pub fn section_for_page(&self, page: usize) -> Option<&Section> {
+        self.document
+            .section_index_for_page(page)
+            .and_then(|index| self.document.sections().get(index))
+    }
+
+    /// The entries of the document outline
+    pub fn outline(&self) -> &[OutlineEntry] {
+        self.document.outline()
+    }
+
+    /// Appends an entry pointing to the given position to the document outline. Returns the index of the added entry.
+    pub fn add_outline_entry(
+        &mut self,
+        name: String,
+        pos: na::Vector2<f64>,
+    ) -> (usize, WidgetFlags) {
+        let mut widget_flags = WidgetFlags::default();
+
+        self.document
+            .outline_mut()
+            .push(OutlineEntry::new(name, pos));
+
+        widget_flags.indicate_changed_store = true;
+
+        (self.document.outline().len() - 1, widget_flags)
+    }
+
+    /// Renames the outline entry with the given index
+    pub fn rename_outline_entry(
+        &mut self,
+        index: usize,
+        name: String,
+    ) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+
+        let entry = self.document.outline_mut().get_mut(index).ok_or_else(|| {
+            anyhow::anyhow!(
+                "rename_outline_entry() failed, entry {} does not exist",
+                index
+            )
+        })?;
+        entry.name = name;
+
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Removes the outline entry with the given index
+    pub fn remove_outline_entry(&mut self, index: usize) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+
+        if index >= self.document.outline().len() {
+            return Err(anyhow::anyhow!(
+                "remove_outline_entry() failed, entry {} does not exist",
+                index
+            ));
+        }
+        self.document.outline_mut().remove(index);
+
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Moves the outline entry with the given index to the new index, shifting the entries in between
+    pub fn move_outline_entry(&mut self, from: usize, to: usize) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+        let n_entries = self.document.outline().len();
+
+        if from >= n_entries || to >= n_entries {
+            return Err(anyhow::anyhow!(
+                "move_outline_entry() failed, indices {} and {} are out of bounds for {} entries",
+                from,
+                to,
+                n_entries
+            ));
+        }
+        let entry = self.document.outline_mut().remove(from);
+        self.document.outline_mut().insert(to, entry);
+
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// The index of the page the outline entry with the given index points to
+    pub fn outline_entry_page(&self, index: usize) -> Option<usize> {
+        let entry = self.document.outline().get(index)?;
+
+        self.document.page_index_at(na::Point2::from(entry.pos))
+    }
+
+    /// The camera offset for navigating to the outline entry with the given index. The entry position is placed at the top left of the viewport.
+    pub fn outline_entry_camera_offset(&self, index: usize) -> Option<na::Vector2<f64>> {
+        let entry = self.document.outline().get(index)?;
+
+        Some(entry.pos * self.camera.total_zoom())
+    }
+
+    /// Removes all pages without any strokes on them in the fixed size layout, at least one page is kept.
+    /// The subsequent pages move up, and the camera is adjusted so that the content in the viewport stays in place.
+    pub fn remove_empty_pages(&mut self) -> anyhow::Result<WidgetFlags> {
+        if self.document.layout() != Layout::FixedSize {
+            return Err(anyhow::anyhow!(
+                "remove_empty_pages() failed, empty pages can only be removed in the fixed size layout"
+            ));
+        }
+        let pages_bounds = self.document.pages_bounds();
+
+        let mut empty_pages = pages_bounds
+            .iter()
+            .enumerate()
+            .filter_map(|(page, &page_bounds)| {
+                if self
+                    .store
+                    .stroke_keys_as_rendered_intersecting_bounds(page_bounds)
+                    .is_empty()
+                {
+                    Some(page)
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<usize>>();
+        // Keep at least one page
+        if empty_pages.len() == pages_bounds.len() {
+            empty_pages.remove(0);
+        }
+        if empty_pages.is_empty() {
+            return Ok(WidgetFlags::default());
+        }
+
+        let mut widget_flags = self.store.record_pages_state(self.document.pages_state());
+
+        // Removed pages above the viewport shift the content in it up
+        let viewport_top = self.camera.viewport().mins[1];
+        let removed_height_above = empty_pages
+            .iter()
+            .filter(|&&page| pages_bounds[page].maxs[1] <= viewport_top)
+            .map(|&page| pages_bounds[page].extents()[1])
+            .sum::<f64>();
+
+        for &page in empty_pages.iter().rev() {
+            self.remove_empty_page(page);
+        }
+
+        let camera_offset =
+            self.camera.offset - na::vector![0.0, removed_height_above * self.camera.total_zoom()];
+        self.update_camera_offset(camera_offset);
+        self.store.set_pages_state(self.document.pages_state());
+        self.update_rendering_current_viewport();
+
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.update_view = true;
+        widget_flags.indicate_changed_store = true;
+
+        Ok(widget_flags)
+    }
+
+    /// The keys of the (not trashed) strokes which start on the page with the given bounds
+    fn keys_starting_on_page(&self, page_bounds: AABB) -> Vec<StrokeKey> {
+        self.store
+            .stroke_keys_as_rendered_intersecting_bounds(page_bounds)
+            .into_iter()
+            .filter(|&key| {
+                self.store
+                    .get_stroke_ref(key)
+                    .map(|stroke| {
+                        let top = stroke.bounds().mins[1];
+                        top >= page_bounds.mins[1] && top < page_bounds.maxs[1]
+                    })
+                    .unwrap_or(false)
+            })
+            .collect()
+    }
+
+    /// Checks if a page can be inserted at the given index
+    fn check_page_insertable(&self, index: usize) -> anyhow::Result<()> {
+        if !self.document.layout().pages_stacked_vertically() {
+            return Err(anyhow::anyhow!(
+                "pages can only be inserted in the fixed size and continuous vertical layouts"
+            ));
+        }
+        let n_pages = self.document.pages_bounds().len();
+        if index > n_pages {
+            return Err(anyhow::anyhow!(
+                "index {} is out of bounds for {} pages",
+                index,
+                n_pages
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Inserts an empty page with the given orientation at the given index, shifting the subsequent content and the background overrides down.
+    /// Returns the origin of the new page. The strokes then need to update their rendering.
+    fn insert_empty_page(
+        &mut self,
+        index: usize,
+        page_override: PageBackgroundOverride,
+        orientation: Orientation,
+    ) -> na::Vector2<f64> {
+        let pages_bounds = self.document.pages_bounds();
+        let page_top = pages_bounds
+            .get(index)
+            .map(|page_bounds| page_bounds.mins[1])
+            .unwrap_or_else(|| {
+                pages_bounds
+                    .last()
+                    .map(|page_bounds| page_bounds.maxs[1])
+                    .unwrap_or(self.document.y)
+            });
+        let page_origin = na::vector![self.document.x, page_top];
+
+        self.document.insert_page_orientation(index, orientation);
+        let page_height = self.document.page_size(index)[1];
+
+        let keys_below = self.store.keys_below_y_pos(page_origin[1]);
+        self.store
+            .translate_strokes(&keys_below, na::vector![0.0, page_height]);
+        self.store
+            .translate_strokes_images(&keys_below, na::vector![0.0, page_height]);
+        self.document.map_page_anchors_y(|y| {
+            if y >= page_origin[1] {
+                y + page_height
+            } else {
+                y
+            }
+        });
+        self.document
+            .background
+            .insert_page_override(index, page_override);
+        self.document.insert_page_in_sections(index);
+
+        self.document.height += page_height;
+        self.document.fit_width_to_pages();
+
+        page_origin
+    }
+
+    /// Removes the empty page at the given index, shifting the subsequent content and the background overrides up.
+    /// The strokes then need to update their rendering.
+    fn remove_empty_page(&mut self, index: usize) {
+        let page_bounds = match self.document.pages_bounds().get(index) {
+            Some(&page_bounds) => page_bounds,
+            None => return,
+        };
+        let page_height = page_bounds.extents()[1];
+
+        let keys_below = self.store.keys_below_y_pos(page_bounds.maxs[1]);
+        self.store
+            .translate_strokes(&keys_below, na::vector![0.0, -page_height]);
+        self.store
+            .translate_strokes_images(&keys_below, na::vector![0.0, -page_height]);
+        // The anchors on the removed page move to the top of the subsequent page
+        self.document.map_page_anchors_y(|y| {
+            if y >= page_bounds.maxs[1] {
+                y - page_height
+            } else if y > page_bounds.mins[1] {
+                page_bounds.mins[1]
+            } else {
+                y
+            }
+        });
+        self.document.background.remove_page_override(index);
+        self.document.remove_page_in_sections(index);
+        self.document.remove_page_orientation(index);
+
+        self.document.height =
+            (self.document.height - page_height).max(self.document.page_size(0)[1]);
+        self.document.fit_width_to_pages();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn undo_page_operations() {
+        let mut engine = RnoteEngine::default();
+        engine.set_doc_layout(Layout::FixedSize);
+        let page_height = engine.document.format.height;
+
+        let _ = engine.insert_page(1).unwrap();
+        let _ = engine
+            .add_section(String::from("Second"), Color::BLACK, 1)
+            .unwrap();
+        let _ =
+            engine.add_outline_entry(String::from("Second"), na::vector![0.0, page_height + 10.0]);
+
+        let _ = engine.insert_page(0).unwrap();
+        assert_eq!(engine.document.pages_bounds().len(), 3);
+        assert_eq!(engine.sections()[0].start_page, 2);
+        assert_relative_eq!(engine.outline()[0].pos[1], 2.0 * page_height + 10.0);
+
+        let _ = engine.undo();
+        assert_eq!(engine.document.pages_bounds().len(), 2);
+        assert_eq!(engine.sections()[0].start_page, 1);
+        assert_relative_eq!(engine.outline()[0].pos[1], page_height + 10.0);
+
+        let _ = engine.redo();
+        assert_eq!(engine.document.pages_bounds().len(), 3);
+        assert_eq!(engine.sections()[0].start_page, 2);
+        assert_relative_eq!(engine.outline()[0].pos[1], 2.0 * page_height + 10.0);
+    }
+
+    #[test]
+    fn undo_page_operation_keeps_later_comments() {
+        let mut engine = RnoteEngine::default();
+        engine.set_doc_layout(Layout::FixedSize);
+        let page_height = engine.document.format.height;
+
+        let _ = engine.insert_page(1).unwrap();
+        let _ = engine.add_comment(
+            String::from("Comment"),
+            na::vector![10.0, page_height + 10.0],
+            None,
+        );
+
+        let _ = engine.undo();
+        assert_eq!(engine.document.pages_bounds().len(), 1);
+
+        let _ = engine.redo();
+        assert_eq!(engine.document.pages_bounds().len(), 2);
+        assert_eq!(engine.comments().len(), 1);
+        assert_eq!(engine.comments()[0].text, "Comment");
+
+        // Added while the page operation is undone
+        let _ = engine.undo();
+        let _ = engine.add_comment(String::from("Undone"), na::vector![10.0, 10.0], None);
+        let _ = engine.redo();
+        let _ = engine.undo();
+        assert!(engine
+            .comments()
+            .iter()
+            .any(|comment| comment.text == "Undone"));
+    }
+}
@@ -2,8 +2,8 @@ use super::penbehaviour::{PenBehaviour, PenProgress};
 use crate::engine::{EngineView, EngineViewMut};
 use crate::store::chrono_comp::StrokeLayer;
 use crate::store::StrokeKey;
-use crate::strokes::BrushStroke;
-use crate::strokes::Stroke;
+use crate::strokes::{BrushStroke, ShapeStroke};
+use crate::strokes::{Stroke, StrokeStyle};
 use crate::AudioPlayer;
 use crate::{DrawOnDocBehaviour, WidgetFlags};
 use rnote_compose::builders::shapebuilderbehaviour::{BuilderProgress, ShapeBuilderCreator};
@@ -11,8 +11,11 @@ use rnote_compose::builders::Constraints;
 use rnote_compose::builders::{PenPathBuilder, ShapeBuilderBehaviour};
 use rnote_compose::penhelpers::PenEvent;
 use rnote_compose::penpath::Segment;
+use rnote_compose::stabilizer::{Stabilizer, StabilizerState};
+use rnote_compose::style::calligraphy::CalligraphyOptions;
+use rnote_compose::style::stamped::StampedOptions;
 use rnote_compose::style::textured::TexturedOptions;
-use rnote_compose::style::PressureCurve;
+use rnote_compose::style::{PressureCurve, PressureResponse};
 use rnote_compose::{Shape, Style};
 
 use p2d::bounding_volume::{BoundingVolume, AABB};
@@ -40,6 +43,10 @@ pub enum BrushStyle {
     Solid,
     #[serde(rename = "textured")]
     Textured,
+    #[serde(rename = "calligraphy")]
+    Calligraphy,
+    #[serde(rename = "stamped")]
+    Stamped,
 }
 
 impl Default for BrushStyle {
@@ -114,6 +121,7 @@ enum BrushState {
     Idle,
     Drawing {
         path_builder: PenPathBuilder,
+        stabilizer_state: StabilizerState,
         current_stroke_key: StrokeKey,
     },
 }
@@ -129,6 +137,22 @@ pub struct Brush {
     pub solid_options: SolidOptions,
     #[serde(rename = "textured_options")]
     pub textured_options: TexturedOptions,
+    #[serde(rename = "calligraphy_options")]
+    pub calligraphy_options: CalligraphyOptions,
+    #[serde(rename = "stamped_options")]
+    pub stamped_options: StampedOptions,
+    /// When set, finished strokes are compacted with this epsilon, merging nearly collinear segments. See `PenPath::compact()`
+    #[serde(rename = "compaction_epsilon")]
+    pub compaction_epsilon: Option<f64>,
+    /// When set, finished strokes are simplified with this epsilon before they are compacted. See `PenPath::simplify()`
+    #[serde(rename = "simplification_epsilon")]
+    pub simplification_epsilon: Option<f64>,
+    /// The stabilizer, applied to the input before it is committed to the stroke
+    #[serde(rename = "stabilizer")]
+    pub stabilizer: Stabilizer,
+    /// Wether finished strokes closely matching a line, circle, ellipse, rectangle or triangle are replaced with the clean shape
+    #[serde(rename = "shape_recognition")]
+    pub shape_recognition: bool,
 
     #[serde(skip)]
     state: BrushState,
@@ -148,6 +172,12 @@ impl Default for Brush {
             marker_options,
             solid_options,
             textured_options,
+            calligraphy_options: CalligraphyOptions::default(),
+            stamped_options: StampedOptions::default(),
+            compaction_epsilon: None,
+            simplification_epsilon: None,
+            stabilizer: Stabilizer::default(),
+            shape_recognition: false,
             state: BrushState::Idle,
         }
     }
@@ -190,6 +220,7 @@ impl PenBehaviour for Brush {
                         .insert_stroke(brushstroke, Some(self.layer_for_current_options()));
 
                     let path_builder = PenPathBuilder::start(element);
+                    let stabilizer_state = self.stabilizer.start(element);
 
                     if let Err(e) = engine_view.store.regenerate_rendering_for_stroke(
                         current_stroke_key,
@@ -201,6 +232,7 @@ impl PenBehaviour for Brush {
 
                     self.state = BrushState::Drawing {
                         path_builder,
+                        stabilizer_state,
                         current_stroke_key,
                     };
 
@@ -212,6 +244,25 @@ impl PenBehaviour for Brush {
                     PenProgress::Idle
                 }
             }
+            (BrushState::Idle, PenEvent::Scroll { delta, .. }) => {
+                let options_width = match self.style {
+                    BrushStyle::Marker => &mut self.marker_options.stroke_width,
+                    BrushStyle::Solid => &mut self.solid_options.stroke_width,
+                    BrushStyle::Textured => &mut self.textured_options.stroke_width,
+                    BrushStyle::Calligraphy => &mut self.calligraphy_options.stroke_width,
+                    BrushStyle::Stamped => &mut self.stamped_options.stroke_width,
+                };
+                *options_width = super::scroll_adjusted_size(
+                    *options_width,
+                    delta,
+                    Self::STROKE_WIDTH_MIN,
+                    Self::STROKE_WIDTH_MAX,
+                );
+
+                widget_flags.refresh_ui = true;
+
+                PenProgress::Idle
+            }
             (BrushState::Idle, _) => PenProgress::Idle,
             (
                 BrushState::Drawing {
@@ -222,6 +273,23 @@ impl PenBehaviour for Brush {
                 Self::stop_audio(style, engine_view.audioplayer);
 
                 // Finish up the last stroke
+                if let Some(epsilon) = self.simplification_epsilon {
+                    engine_view
+                        .store
+                        .simplify_brushstroke(*current_stroke_key, epsilon);
+                }
+                if let Some(epsilon) = self.compaction_epsilon {
+                    engine_view
+                        .store
+                        .compact_brushstroke(*current_stroke_key, epsilon);
+                }
+                if self.shape_recognition {
+                    *current_stroke_key = Self::replace_w_recognized_shape(
+                        *current_stroke_key,
+                        engine_view,
+                        &mut widget_flags,
+                    );
+                }
                 engine_view
                     .store
                     .update_geometry_for_stroke(*current_stroke_key);
@@ -248,10 +316,17 @@ impl PenBehaviour for Brush {
             (
                 BrushState::Drawing {
                     path_builder,
+                    stabilizer_state,
                     current_stroke_key,
                 },
                 pen_event,
             ) => {
+                let pen_event = match stabilizer_state.stabilize_event(pen_event) {
+                    Some(pen_event) => pen_event,
+                    // The stabilized position did not move
+                    None => return (PenProgress::InProgress, widget_flags),
+                };
+
                 match path_builder.handle_event(pen_event, Constraints::default()) {
                     BuilderProgress::InProgress => {
                         widget_flags.redraw = true;
@@ -307,6 +382,23 @@ impl PenBehaviour for Brush {
                         }
 
                         // Finish up the last stroke
+                        if let Some(epsilon) = self.simplification_epsilon {
+                            engine_view
+                                .store
+                                .simplify_brushstroke(*current_stroke_key, epsilon);
+                        }
+                        if let Some(epsilon) = self.compaction_epsilon {
+                            engine_view
+                                .store
+                                .compact_brushstroke(*current_stroke_key, epsilon);
+                        }
+                        if self.shape_recognition {
+                            *current_stroke_key = Self::replace_w_recognized_shape(
+                                *current_stroke_key,
+                                engine_view,
+                                &mut widget_flags,
+                            );
+                        }
                         engine_view
                             .store
                             .update_geometry_for_stroke(*current_stroke_key);
@@ -366,7 +458,10 @@ impl DrawOnDocBehaviour for Brush {
                     BrushStyle::Marker => {
                         // Don't draw the marker, as the pen would render on top of other strokes, while the stroke itself would render underneath them.
                     }
-                    BrushStyle::Solid | BrushStyle::Textured => {
+                    BrushStyle::Solid
+                    | BrushStyle::Textured
+                    | BrushStyle::Calligraphy
+                    | BrushStyle::Stamped => {
                         let style = self.style_for_current_options();
                         path_builder.draw_styled(cx, &style, engine_view.camera.total_zoom());
                     }
@@ -385,6 +480,10 @@ impl Brush {
     pub const STROKE_WIDTH_MIN: f64 = 1.0;
     pub const STROKE_WIDTH_MAX: f64 = 500.0;
     pub const STROKE_WIDTH_DEFAULT: f64 = 2.0;
+    /// The max epsilon for compacting and simplifying finished strokes
+    pub const PATH_EPSILON_MAX: f64 = 10.0;
+    /// The max deviation of a stroke from a recognized shape, as ratio of the stroke bounds diagonal
+    const SHAPE_RECOGNITION_MAX_DEVIATION_RATIO: f64 = 0.08;
 
     fn start_audio(style: BrushStyle, audioplayer: &mut Option<AudioPlayer>) {
         if let Some(audioplayer) = audioplayer {
@@ -392,7 +491,10 @@ impl Brush {
                 BrushStyle::Marker => {
                     audioplayer.play_random_marker_sound();
                 }
-                BrushStyle::Solid | BrushStyle::Textured => {
+                BrushStyle::Solid
+                | BrushStyle::Textured
+                | BrushStyle::Calligraphy
+                | BrushStyle::Stamped => {
                     audioplayer.start_random_brush_sound();
                 }
             }
@@ -405,10 +507,67 @@ impl Brush {
         }
     }
 
+    /// Replaces the brush stroke with a shape stroke in the same layer, if its path is recognized as a shape. See `PenPath::recognize_shape()`.
+    /// The replacement is recorded separately, so undoing it restores the drawn stroke. Returns the key of the resulting stroke.
+    fn replace_w_recognized_shape(
+        key: StrokeKey,
+        engine_view: &mut EngineViewMut,
+        widget_flags: &mut WidgetFlags,
+    ) -> StrokeKey {
+        let (shape, style) = match engine_view.store.get_stroke_ref(key) {
+            Some(Stroke::BrushStroke(brushstroke)) => {
+                match brushstroke
+                    .path
+                    .recognize_shape(Self::SHAPE_RECOGNITION_MAX_DEVIATION_RATIO)
+                {
+                    // Textured and stamped styles can't be drawn on shapes
+                    Some(shape) => (shape, StrokeStyle::shape_style(&brushstroke.style)),
+                    None => return key,
+                }
+            }
+            _ => return key,
+        };
+        let layer = engine_view.store.stroke_layer(key);
+
+        // Record the finished drawn stroke first
+        engine_view.store.update_geometry_for_stroke(key);
+        widget_flags.merge_with_other(engine_view.store.record());
+
+        engine_view.store.remove_stroke(key);
+        engine_view
+            .store
+            .insert_stroke(Stroke::ShapeStroke(ShapeStroke::new(shape, style)), layer)
+    }
+
+    /// The pressure response of the options for the current style
+    pub fn pressure_response(&self) -> PressureResponse {
+        match self.style {
+            BrushStyle::Marker => self.marker_options.pressure_response,
+            BrushStyle::Solid => self.solid_options.pressure_response,
+            BrushStyle::Textured => self.textured_options.pressure_response,
+            BrushStyle::Calligraphy => self.calligraphy_options.pressure_response,
+            BrushStyle::Stamped => self.stamped_options.pressure_response,
+        }
+    }
+
+    /// The mutable pressure response of the options for the current style
+    pub fn pressure_response_mut(&mut self) -> &mut PressureResponse {
+        match self.style {
+            BrushStyle::Marker => &mut self.marker_options.pressure_response,
+            BrushStyle::Solid => &mut self.solid_options.pressure_response,
+            BrushStyle::Textured => &mut self.textured_options.pressure_response,
+            BrushStyle::Calligraphy => &mut self.calligraphy_options.pressure_response,
+            BrushStyle::Stamped => &mut self.stamped_options.pressure_response,
+        }
+    }
+
     pub fn layer_for_current_options(&self) -> StrokeLayer {
         match &self.style {
             BrushStyle::Marker => StrokeLayer::Highlighter,
-            BrushStyle::Solid | BrushStyle::Textured => StrokeLayer::UserLayer(0),
+            BrushStyle::Solid
+            | BrushStyle::Textured
+            | BrushStyle::Calligraphy
+            | BrushStyle::Stamped => StrokeLayer::UserLayer(0),
         }
     }
 
@@ -429,6 +588,97 @@ impl Brush {
 
                 Style::Textured(options)
             }
+            BrushStyle::Calligraphy => {
+                let options = self.calligraphy_options.clone();
+
+                Style::Calligraphy(options)
+            }
+            BrushStyle::Stamped => {
+                let options = self.stamped_options.clone();
+
+                Style::Stamped(options)
+            }
+        }
+    }
+    /// Loads the given style into the options of the matching brush style and selects it.
+    /// The brush has no rough style, for rough styles only the stroke width and color are taken over into the solid style.
+    pub fn load_style(&mut self, style: &Style) {
+        match style {
+            // smooth styles are used by both the marker and the solid style
+            Style::Smooth(options) => match self.style {
+                BrushStyle::Marker => *self.marker_options = options.clone(),
+                _ => {
+                    self.style = BrushStyle::Solid;
+                    *self.solid_options = options.clone();
+                }
+            },
+            Style::Rough(options) => {
+                self.style = BrushStyle::Solid;
+                self.solid_options.stroke_width = options.stroke_width;
+                self.solid_options.stroke_color = options.stroke_color;
+            }
+            Style::Textured(options) => {
+                self.style = BrushStyle::Textured;
+                self.textured_options = options.clone();
+            }
+            Style::Calligraphy(options) => {
+                self.style = BrushStyle::Calligraphy;
+                self.calligraphy_options = options.clone();
+            }
+            Style::Stamped(options) => {
+                self.style = BrushStyle::Stamped;
+                self.stamped_options = options.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RnoteEngine;
+    use rnote_compose::penpath::{Element, PenPath};
+
+    #[test]
+    fn recognize_textured_shape() {
+        let mut engine = RnoteEngine::default();
+        let corners = [
+            na::vector![0.0, 0.0],
+            na::vector![100.0, 0.0],
+            na::vector![100.0, 60.0],
+            na::vector![0.0, 60.0],
+            na::vector![0.0, 0.0],
+        ];
+        let mut path = PenPath::new_w_dot(Element::new(corners[0], 0.5));
+        for corner in corners.windows(2) {
+            // A rough rectangle, with elements along its sides
+            for i in 0..10 {
+                let start = corner[0] + (corner[1] - corner[0]) * (i as f64 / 10.0);
+                let end = corner[0] + (corner[1] - corner[0]) * ((i + 1) as f64 / 10.0);
+                path.push_back(Segment::Line {
+                    start: Element::new(start, 0.5),
+                    end: Element::new(end, 0.5),
+                });
+            }
+        }
+        let brushstroke =
+            BrushStroke::from_penpath(path, Style::Textured(TexturedOptions::default())).unwrap();
+        let key = engine
+            .store
+            .insert_stroke(Stroke::BrushStroke(brushstroke), None);
+
+        let key = Brush::replace_w_recognized_shape(
+            key,
+            &mut engine.view_mut(),
+            &mut WidgetFlags::default(),
+        );
+
+        match engine.store.get_stroke_ref(key) {
+            Some(Stroke::ShapeStroke(shapestroke)) => {
+                assert!(matches!(shapestroke.shape, Shape::Rectangle(_)));
+                assert!(matches!(shapestroke.style, Style::Smooth(_)));
+            }
+            _ => panic!("stroke is not a shape stroke"),
         }
     }
 }
@@ -187,6 +187,20 @@ impl PenBehaviour for Eraser {
                 PenProgress::Finished
             }
             (EraserState::Down(_), PenEvent::KeyPressed { .. }) => PenProgress::InProgress,
+            (EraserState::Up | EraserState::Proximity(_), PenEvent::Scroll { delta, .. }) => {
+                self.width = super::scroll_adjusted_size(
+                    self.width,
+                    delta,
+                    Self::WIDTH_MIN,
+                    Self::WIDTH_MAX,
+                );
+
+                widget_flags.redraw = true;
+                widget_flags.refresh_ui = true;
+
+                PenProgress::Idle
+            }
+            (EraserState::Down(_), PenEvent::Scroll { .. }) => PenProgress::InProgress,
             (EraserState::Proximity(_), PenEvent::Up { .. }) => {
                 self.state = EraserState::Up;
                 widget_flags.redraw = true;
@@ -0,0 +1,270 @@
+use super::penbehaviour::{PenBehaviour, PenProgress};
+use crate::engine::{EngineView, EngineViewMut};
+use crate::store::chrono_comp::StrokeLayer;
+use crate::store::StrokeKey;
+use crate::strokes::BrushStroke;
+use crate::strokes::Stroke;
+use crate::{DrawOnDocBehaviour, WidgetFlags};
+use rnote_compose::builders::shapebuilderbehaviour::{BuilderProgress, ShapeBuilderCreator};
+use rnote_compose::builders::Constraints;
+use rnote_compose::builders::{PenPathBuilder, ShapeBuilderBehaviour};
+use rnote_compose::penhelpers::PenEvent;
+use rnote_compose::penpath::Segment;
+use rnote_compose::style::smooth::{LineCap, SmoothOptions};
+use rnote_compose::style::PressureCurve;
+use rnote_compose::{Color, Shape, Style};
+
+use p2d::bounding_volume::{BoundingVolume, AABB};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone)]
+enum HighlighterState {
+    Idle,
+    Drawing {
+        path_builder: PenPathBuilder,
+        current_stroke_key: StrokeKey,
+    },
+}
+
+/// A highlighter, drawing strokes with flat caps and constant width into the highlighter layer.
+/// The strokes are blended with the strokes and images underneath them with multiply blending.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename = "highlighter")]
+pub struct Highlighter {
+    #[serde(rename = "options")]
+    pub options: SmoothOptions,
+    /// Wether nearly straight strokes are snapped to a straight line when they are finished
+    #[serde(rename = "straight_line_snapping")]
+    pub straight_line_snapping: bool,
+
+    #[serde(skip)]
+    state: HighlighterState,
+}
+
+impl Default for Highlighter {
+    fn default() -> Self {
+        let mut options = SmoothOptions::default();
+        options.stroke_width = Self::STROKE_WIDTH_DEFAULT;
+        options.stroke_color = Some(Self::COLOR_DEFAULT);
+        options.pressure_curve = PressureCurve::Const;
+        options.line_cap = LineCap::Flat;
+
+        Self {
+            options,
+            straight_line_snapping: true,
+            state: HighlighterState::Idle,
+        }
+    }
+}
+
+impl PenBehaviour for Highlighter {
+    fn handle_event(
+        &mut self,
+        event: PenEvent,
+        engine_view: &mut EngineViewMut,
+    ) -> (PenProgress, WidgetFlags) {
+        let mut widget_flags = WidgetFlags::default();
+
+        let pen_progress = match (&mut self.state, event) {
+            (
+                HighlighterState::Idle,
+                PenEvent::Down {
+                    element,
+                    shortcut_keys: _,
+                },
+            ) => {
+                if !element
+                    .filter_by_bounds(engine_view.doc.bounds().loosened(Self::INPUT_OVERSHOOT))
+                {
+                    widget_flags.merge_with_other(engine_view.store.record());
+
+                    if let Some(audioplayer) = engine_view.audioplayer.as_mut() {
+                        audioplayer.play_random_marker_sound();
+                    }
+
+                    let brushstroke = Stroke::BrushStroke(BrushStroke::new(
+                        Segment::Dot { element },
+                        self.style_for_current_options(),
+                    ));
+                    let current_stroke_key = engine_view
+                        .store
+                        .insert_stroke(brushstroke, Some(StrokeLayer::Highlighter));
+
+                    let path_builder = PenPathBuilder::start(element);
+
+                    if let Err(e) = engine_view.store.regenerate_rendering_for_stroke(
+                        current_stroke_key,
+                        engine_view.camera.viewport(),
+                        engine_view.camera.image_scale(),
+                    ) {
+                        log::error!("regenerate_rendering_for_stroke() failed after inserting highlighter stroke, Err {}", e);
+                    }
+
+                    self.state = HighlighterState::Drawing {
+                        path_builder,
+                        current_stroke_key,
+                    };
+
+                    widget_flags.redraw = true;
+                    widget_flags.hide_scrollbars = Some(true);
+
+                    PenProgress::InProgress
+                } else {
+                    PenProgress::Idle
+                }
+            }
+            (HighlighterState::Idle, PenEvent::Scroll { delta, .. }) => {
+                self.options.stroke_width = super::scroll_adjusted_size(
+                    self.options.stroke_width,
+                    delta,
+                    Self::STROKE_WIDTH_MIN,
+                    Self::STROKE_WIDTH_MAX,
+                );
+
+                widget_flags.refresh_ui = true;
+
+                PenProgress::Idle
+            }
+            (HighlighterState::Idle, _) => PenProgress::Idle,
+            (
+                HighlighterState::Drawing {
+                    current_stroke_key, ..
+                },
+                PenEvent::Cancel,
+            ) => {
+                let current_stroke_key = *current_stroke_key;
+
+                self.finish_stroke(current_stroke_key, engine_view);
+                self.state = HighlighterState::Idle;
+
+                widget_flags.redraw = true;
+                widget_flags.resize = true;
+                widget_flags.indicate_changed_store = true;
+                widget_flags.hide_scrollbars = Some(false);
+
+                PenProgress::Finished
+            }
+            (
+                HighlighterState::Drawing {
+                    path_builder,
+                    current_stroke_key,
+                },
+                pen_event,
+            ) => {
+                let current_stroke_key = *current_stroke_key;
+
+                match path_builder.handle_event(pen_event, Constraints::default()) {
+                    BuilderProgress::InProgress => {
+                        widget_flags.redraw = true;
+
+                        PenProgress::InProgress
+                    }
+                    BuilderProgress::EmitContinue(shapes) => {
+                        for shape in shapes {
+                            if let Shape::Segment(new_segment) = shape {
+                                engine_view
+                                    .store
+                                    .add_segment_to_brushstroke(current_stroke_key, new_segment);
+                                widget_flags.indicate_changed_store = true;
+                            }
+                        }
+
+                        // The path is drawn with flat caps as a whole, so appending images for the last segments is not possible
+                        engine_view
+                            .store
+                            .update_geometry_for_stroke(current_stroke_key);
+                        if let Err(e) = engine_view.store.regenerate_rendering_for_stroke(
+                            current_stroke_key,
+                            engine_view.camera.viewport(),
+                            engine_view.camera.image_scale(),
+                        ) {
+                            log::error!("regenerate_rendering_for_stroke() failed while drawing highlighter stroke, Err {}", e);
+                        }
+                        widget_flags.redraw = true;
+
+                        PenProgress::InProgress
+                    }
+                    BuilderProgress::Finished(shapes) => {
+                        for shape in shapes {
+                            if let Shape::Segment(new_segment) = shape {
+                                engine_view
+                                    .store
+                                    .add_segment_to_brushstroke(current_stroke_key, new_segment);
+                                widget_flags.indicate_changed_store = true;
+                            }
+                        }
+
+                        self.finish_stroke(current_stroke_key, engine_view);
+                        self.state = HighlighterState::Idle;
+
+                        widget_flags.redraw = true;
+                        widget_flags.resize = true;
+                        widget_flags.indicate_changed_store = true;
+                        widget_flags.hide_scrollbars = Some(false);
+
+                        PenProgress::Finished
+                    }
+                }
+            }
+        };
+
+        (pen_progress, widget_flags)
+    }
+}
+
+impl DrawOnDocBehaviour for Highlighter {
+    fn bounds_on_doc(&self, _engine_view: &EngineView) -> Option<AABB> {
+        None
+    }
+
+    fn draw_on_doc(
+        &self,
+        _cx: &mut piet_cairo::CairoRenderContext,
+        _engine_view: &EngineView,
+    ) -> anyhow::Result<()> {
+        // The stroke is rendered by the store while drawing, so that it is blended and layered correctly
+        Ok(())
+    }
+}
+
+impl Highlighter {
+    const INPUT_OVERSHOOT: f64 = 30.0;
+    /// The max deviation from a straight line, as ratio of its length, for strokes to be snapped to it
+    const STRAIGHT_LINE_SNAP_RATIO: f64 = 0.05;
+
+    pub const STROKE_WIDTH_MIN: f64 = 1.0;
+    pub const STROKE_WIDTH_MAX: f64 = 100.0;
+    pub const STROKE_WIDTH_DEFAULT: f64 = 16.0;
+    pub const COLOR_DEFAULT: Color = Color {
+        r: 1.0,
+        g: 0.9,
+        b: 0.0,
+        a: 0.6,
+    };
+
+    pub fn style_for_current_options(&self) -> Style {
+        let mut options = self.options.clone();
+        options.line_cap = LineCap::Flat;
+
+        Style::Smooth(options)
+    }
+
+    fn finish_stroke(&self, key: StrokeKey, engine_view: &mut EngineViewMut) {
+        if self.straight_line_snapping {
+            engine_view
+                .store
+                .straighten_brushstroke(key, Self::STRAIGHT_LINE_SNAP_RATIO);
+        }
+        engine_view.store.update_geometry_for_stroke(key);
+        engine_view.store.regenerate_rendering_for_stroke_threaded(
+            engine_view.tasks_tx.clone(),
+            key,
+            engine_view.camera.viewport(),
+            engine_view.camera.image_scale(),
+        );
+
+        engine_view
+            .doc
+            .resize_autoexpand(engine_view.store, engine_view.camera);
+    }
+}
@@ -0,0 +1,275 @@
+use super::penbehaviour::{PenBehaviour, PenProgress};
+use crate::engine::{EngineTask, EngineTaskSender, EngineView, EngineViewMut};
+use crate::{DrawOnDocBehaviour, WidgetFlags};
+use rnote_compose::builders::shapebuilderbehaviour::{BuilderProgress, ShapeBuilderCreator};
+use rnote_compose::builders::Constraints;
+use rnote_compose::builders::{PenPathBuilder, ShapeBuilderBehaviour};
+use rnote_compose::penhelpers::PenEvent;
+use rnote_compose::style::smooth::SmoothOptions;
+use rnote_compose::style::{Composer, PressureCurve};
+use rnote_compose::{Color, PenPath, Shape};
+
+use p2d::bounding_volume::{BoundingVolume, AABB};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A temporary laser stroke. It starts fading out when it is finished.
+#[derive(Debug, Clone)]
+struct LaserStroke {
+    path: PenPath,
+    finished: Option<Instant>,
+}
+
+/// Sends `EngineTask::LaserFadeTick` through the engine task channel until the deadline is reached,
+/// so that the fading laser strokes get redrawn.
+#[derive(Debug, Clone, Default)]
+struct LaserFadeTicker {
+    /// The deadline, and wether the ticker thread is currently running
+    state: Arc<Mutex<(Option<Instant>, bool)>>,
+}
+
+impl LaserFadeTicker {
+    const TICK_INTERVAL: Duration = Duration::from_millis(16);
+
+    /// Ticks at least until the deadline. Spawns the ticker thread if it is not running.
+    fn tick_until(&self, deadline: Instant, tasks_tx: EngineTaskSender) {
+        let mut state = self.state.lock().unwrap();
+        state.0 = Some(state.0.map_or(deadline, |current| current.max(deadline)));
+
+        if state.1 {
+            return;
+        }
+        state.1 = true;
+        drop(state);
+
+        let ticker_state = Arc::clone(&self.state);
+        let spawn_result = std::thread::Builder::new()
+            .name(String::from("rnote-laser-ticker"))
+            .spawn(move || loop {
+                std::thread::sleep(Self::TICK_INTERVAL);
+
+                if let Err(e) = tasks_tx.unbounded_send(EngineTask::LaserFadeTick) {
+                    log::error!(
+                        "tasks_tx.send() LaserFadeTick failed in the laser fade ticker, Err {}",
+                        e
+                    );
+                }
+
+                let mut state = ticker_state.lock().unwrap();
+                if state.0.map_or(true, |deadline| Instant::now() >= deadline) {
+                    *state = (None, false);
+                    return;
+                }
+            });
+
+        if let Err(e) = spawn_result {
+            log::error!(
+                "spawning the ticker thread failed in LaserFadeTicker::tick_until(), Err {}",
+                e
+            );
+            self.state.lock().unwrap().1 = false;
+        }
+    }
+}
+
+/// A laser pointer. It draws temporary strokes which fade out after a duration, and are never inserted into the store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename = "laser")]
+pub struct Laser {
+    #[serde(rename = "options")]
+    pub options: SmoothOptions,
+    /// The duration in seconds the strokes take to fade out after they are finished
+    #[serde(rename = "fade_duration")]
+    pub fade_duration: f64,
+
+    #[serde(skip)]
+    strokes: Vec<LaserStroke>,
+    #[serde(skip)]
+    path_builder: Option<PenPathBuilder>,
+    #[serde(skip)]
+    ticker: LaserFadeTicker,
+}
+
+impl Default for Laser {
+    fn default() -> Self {
+        let mut options = SmoothOptions::default();
+        options.stroke_width = Self::STROKE_WIDTH_DEFAULT;
+        options.stroke_color = Some(Self::COLOR_DEFAULT);
+        options.pressure_curve = PressureCurve::Const;
+
+        Self {
+            options,
+            fade_duration: Self::FADE_DURATION_DEFAULT,
+            strokes: vec![],
+            path_builder: None,
+            ticker: LaserFadeTicker::default(),
+        }
+    }
+}
+
+impl PenBehaviour for Laser {
+    fn handle_event(
+        &mut self,
+        event: PenEvent,
+        engine_view: &mut EngineViewMut,
+    ) -> (PenProgress, WidgetFlags) {
+        let mut widget_flags = WidgetFlags::default();
+        self.remove_faded_strokes();
+
+        let pen_progress = match (self.path_builder.as_mut(), event) {
+            (
+                None,
+                PenEvent::Down {
+                    element,
+                    shortcut_keys: _,
+                },
+            ) => {
+                self.path_builder = Some(PenPathBuilder::start(element));
+                self.strokes.push(LaserStroke {
+                    path: PenPath::new_w_dot(element),
+                    finished: None,
+                });
+
+                widget_flags.redraw = true;
+
+                PenProgress::InProgress
+            }
+            (None, PenEvent::Scroll { delta, .. }) => {
+                self.options.stroke_width = super::scroll_adjusted_size(
+                    self.options.stroke_width,
+                    delta,
+                    Self::STROKE_WIDTH_MIN,
+                    Self::STROKE_WIDTH_MAX,
+                );
+
+                widget_flags.refresh_ui = true;
+
+                PenProgress::Idle
+            }
+            (None, _) => PenProgress::Idle,
+            (Some(_), PenEvent::Cancel) => {
+                self.finish_current_stroke(engine_view.tasks_tx.clone());
+
+                widget_flags.redraw = true;
+
+                PenProgress::Finished
+            }
+            (Some(path_builder), pen_event) => {
+                match path_builder.handle_event(pen_event, Constraints::default()) {
+                    BuilderProgress::InProgress => {
+                        widget_flags.redraw = true;
+
+                        PenProgress::InProgress
+                    }
+                    BuilderProgress::EmitContinue(shapes) => {
+                        self.append_shapes(shapes);
+                        widget_flags.redraw = true;
+
+                        PenProgress::InProgress
+                    }
+                    BuilderProgress::Finished(shapes) => {
+                        self.append_shapes(shapes);
+                        self.finish_current_stroke(engine_view.tasks_tx.clone());
+                        widget_flags.redraw = true;
+
+                        PenProgress::Finished
+                    }
+                }
+            }
+        };
+
+        (pen_progress, widget_flags)
+    }
+}
+
+impl DrawOnDocBehaviour for Laser {
+    fn bounds_on_doc(&self, _engine_view: &EngineView) -> Option<AABB> {
+        self.strokes
+            .iter()
+            .map(|stroke| stroke.path.composed_bounds(&self.options))
+            .reduce(|acc, bounds| acc.merged(&bounds))
+    }
+
+    fn draw_on_doc(
+        &self,
+        cx: &mut piet_cairo::CairoRenderContext,
+        _engine_view: &EngineView,
+    ) -> anyhow::Result<()> {
+        let now = Instant::now();
+
+        for stroke in self.strokes.iter() {
+            let fade = stroke.finished.map_or(1.0, |finished| {
+                1.0 - (now.duration_since(finished).as_secs_f64() / self.fade_duration)
+            });
+            if fade <= 0.0 {
+                continue;
+            }
+
+            let mut options = self.options.clone();
+            options.stroke_color = options.stroke_color.map(|color| Color {
+                a: color.a * fade,
+                ..color
+            });
+
+            stroke.path.draw_composed(cx, &options);
+        }
+
+        Ok(())
+    }
+}
+
+impl Laser {
+    pub const STROKE_WIDTH_MIN: f64 = 1.0;
+    pub const STROKE_WIDTH_MAX: f64 = 50.0;
+    pub const STROKE_WIDTH_DEFAULT: f64 = 4.0;
+    pub const FADE_DURATION_MIN: f64 = 0.2;
+    pub const FADE_DURATION_MAX: f64 = 10.0;
+    pub const FADE_DURATION_DEFAULT: f64 = 1.5;
+    pub const COLOR_DEFAULT: Color = Color {
+        r: 0.88,
+        g: 0.11,
+        b: 0.14,
+        a: 1.0,
+    };
+
+    /// Removes the strokes which have faded out completely
+    pub fn remove_faded_strokes(&mut self) {
+        let fade_duration = Duration::from_secs_f64(self.fade_duration.max(0.0));
+
+        self.strokes.retain(|stroke| {
+            stroke
+                .finished
+                .map_or(true, |finished| finished.elapsed() < fade_duration)
+        });
+    }
+
+    /// Wether there are any laser strokes visible
+    pub fn has_strokes(&self) -> bool {
+        !self.strokes.is_empty()
+    }
+
+    fn append_shapes(&mut self, shapes: Vec<Shape>) {
+        if let Some(current_stroke) = self.strokes.last_mut() {
+            for shape in shapes {
+                if let Shape::Segment(new_segment) = shape {
+                    current_stroke.path.push_back(new_segment);
+                }
+            }
+        }
+    }
+
+    fn finish_current_stroke(&mut self, tasks_tx: EngineTaskSender) {
+        let now = Instant::now();
+
+        self.path_builder = None;
+        if let Some(current_stroke) = self.strokes.last_mut() {
+            current_stroke.finished = Some(now);
+        }
+
+        self.ticker.tick_until(
+            now + Duration::from_secs_f64(self.fade_duration.max(0.0)),
+            tasks_tx,
+        );
+    }
+}
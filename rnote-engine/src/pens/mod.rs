@@ -1,22 +1,36 @@
 pub mod brush;
 pub mod eraser;
+pub mod highlighter;
+pub mod laser;
 pub mod penbehaviour;
 pub mod penholder;
 pub mod penmode;
 pub mod selector;
 pub mod shaper;
 pub mod shortcuts;
+pub mod spline;
 pub mod tools;
 pub mod typewriter;
 
 // Re-exports
 pub use brush::Brush;
 pub use eraser::Eraser;
+pub use highlighter::Highlighter;
+pub use laser::Laser;
 pub use penbehaviour::PenBehaviour;
 pub use penholder::PenHolder;
 pub use penmode::PenMode;
 pub use selector::Selector;
 pub use shaper::Shaper;
 pub use shortcuts::Shortcuts;
+pub use spline::Spline;
 pub use tools::Tools;
 pub use typewriter::Typewriter;
+
+/// The ratio a pen size changes per scroll step, when it is adjusted with scrolling
+const SCROLL_ADJUST_RATIO: f64 = 0.1;
+
+/// Adjusts a pen size (width, font size, ..) with the scroll delta. Scrolling up increases it, scrolling down decreases it.
+pub(crate) fn scroll_adjusted_size(size: f64, delta: na::Vector2<f64>, min: f64, max: f64) -> f64 {
+    (size * (1.0 - delta[1] * SCROLL_ADJUST_RATIO).max(SCROLL_ADJUST_RATIO)).clamp(min, max)
+}
@@ -1,6 +1,7 @@
 use crate::engine::{EngineView, EngineViewMut};
 use crate::pens::shortcuts::ShortcutAction;
 use crate::pens::Tools;
+use crate::store::chrono_comp::StrokeLayer;
 
 use crate::widgetflags::WidgetFlags;
 use crate::DrawOnDocBehaviour;
@@ -13,7 +14,10 @@ use serde::{Deserialize, Serialize};
 
 use super::penbehaviour::PenProgress;
 use super::penmode::PenModeState;
-use super::{Brush, Eraser, PenBehaviour, PenMode, Selector, Shaper, Shortcuts, Typewriter};
+use super::{
+    Brush, Eraser, Highlighter, Laser, PenBehaviour, PenMode, Selector, Shaper, Shortcuts, Spline,
+    Typewriter,
+};
 
 #[derive(
     Eq,
@@ -51,6 +55,15 @@ pub enum PenStyle {
     #[enum_value(name = "Tools", nick = "tools")]
     #[serde(rename = "tools")]
     Tools,
+    #[enum_value(name = "Highlighter", nick = "highlighter")]
+    #[serde(rename = "highlighter")]
+    Highlighter,
+    #[enum_value(name = "Laser", nick = "laser")]
+    #[serde(rename = "laser")]
+    Laser,
+    #[enum_value(name = "Spline", nick = "spline")]
+    #[serde(rename = "spline")]
+    Spline,
 }
 
 impl Default for PenStyle {
@@ -93,6 +106,9 @@ impl PenStyle {
             Self::Eraser => String::from("pen-eraser-symbolic"),
             Self::Selector => String::from("pen-selector-symbolic"),
             Self::Tools => String::from("pen-tools-symbolic"),
+            Self::Highlighter => String::from("pen-highlighter-symbolic"),
+            Self::Laser => String::from("pen-laser-symbolic"),
+            Self::Spline => String::from("pen-spline-symbolic"),
         }
     }
 }
@@ -104,8 +120,14 @@ impl PenStyle {
 pub struct PenHolder {
     #[serde(rename = "brush")]
     pub brush: Brush,
+    #[serde(rename = "highlighter")]
+    pub highlighter: Highlighter,
+    #[serde(rename = "laser")]
+    pub laser: Laser,
     #[serde(rename = "shaper")]
     pub shaper: Shaper,
+    #[serde(rename = "spline")]
+    pub spline: Spline,
     #[serde(rename = "typewriter")]
     pub typewriter: Typewriter,
     #[serde(rename = "eraser")]
@@ -127,7 +149,10 @@ impl Default for PenHolder {
     fn default() -> Self {
         Self {
             brush: Brush::default(),
+            highlighter: Highlighter::default(),
+            laser: Laser::default(),
             shaper: Shaper::default(),
+            spline: Spline::default(),
             eraser: Eraser::default(),
             selector: Selector::default(),
             typewriter: Typewriter::default(),
@@ -169,6 +194,18 @@ impl PenHolder {
         self.pen_mode_state.current_style_w_override()
     }
 
+    /// The layer the current pen inserts its strokes into. None if the pen does not create strokes.
+    pub fn current_layer(&self) -> Option<StrokeLayer> {
+        match self.current_style_w_override() {
+            PenStyle::Brush => Some(self.brush.layer_for_current_options()),
+            PenStyle::Highlighter => Some(StrokeLayer::Highlighter),
+            PenStyle::Shaper | PenStyle::Spline | PenStyle::Typewriter => {
+                Some(StrokeLayer::UserLayer(0))
+            }
+            PenStyle::Eraser | PenStyle::Selector | PenStyle::Tools | PenStyle::Laser => None,
+        }
+    }
+
     /// forces a new style without triggering any side effects
     pub fn force_style_without_sideeffects(&mut self, style: PenStyle) -> WidgetFlags {
         let mut widget_flags = WidgetFlags::default();
@@ -319,6 +356,12 @@ impl PenHolder {
                     ));
                 }
             }
+            PenEvent::Scroll { shortcut_keys, .. } => {
+                // Scroll events are only handled by the pens while a shortcut key for it is held
+                if !self.scroll_adjusts_pen(shortcut_keys) {
+                    return widget_flags;
+                }
+            }
             PenEvent::KeyPressed { .. } => {}
             PenEvent::Cancel => {}
         }
@@ -326,7 +369,10 @@ impl PenHolder {
         // Handle the events with the current pen
         let (pen_progress, other_widget_flags) = match self.current_style_w_override() {
             PenStyle::Brush => self.brush.handle_event(event, engine_view),
+            PenStyle::Highlighter => self.highlighter.handle_event(event, engine_view),
+            PenStyle::Laser => self.laser.handle_event(event, engine_view),
             PenStyle::Shaper => self.shaper.handle_event(event, engine_view),
+            PenStyle::Spline => self.spline.handle_event(event, engine_view),
             PenStyle::Typewriter => self.typewriter.handle_event(event, engine_view),
             PenStyle::Eraser => self.eraser.handle_event(event, engine_view),
             PenStyle::Selector => self.selector.handle_event(event, engine_view),
@@ -381,12 +427,24 @@ impl PenHolder {
                         );
                     }
                 }
+                // Only applies to scroll events
+                ShortcutAction::AdjustWithScroll => {}
             }
         }
 
         widget_flags
     }
 
+    /// Wether scrolling adjusts the current pen with the given pressed shortcut keys, instead of scrolling the canvas
+    pub fn scroll_adjusts_pen(&self, shortcut_keys: &[ShortcutKey]) -> bool {
+        shortcut_keys.iter().any(|&key| {
+            matches!(
+                self.get_shortcut_action(key),
+                Some(ShortcutAction::AdjustWithScroll)
+            )
+        })
+    }
+
     /// fetches clipboard content from the current pen
     pub fn fetch_clipboard_content(
         &self,
@@ -394,7 +452,10 @@ impl PenHolder {
     ) -> anyhow::Result<Option<(Vec<u8>, String)>> {
         match self.current_style_w_override() {
             PenStyle::Brush => self.brush.fetch_clipboard_content(engine_view),
+            PenStyle::Highlighter => self.highlighter.fetch_clipboard_content(engine_view),
+            PenStyle::Laser => self.laser.fetch_clipboard_content(engine_view),
             PenStyle::Shaper => self.shaper.fetch_clipboard_content(engine_view),
+            PenStyle::Spline => self.spline.fetch_clipboard_content(engine_view),
             PenStyle::Typewriter => self.typewriter.fetch_clipboard_content(engine_view),
             PenStyle::Eraser => self.eraser.fetch_clipboard_content(engine_view),
             PenStyle::Selector => self.selector.fetch_clipboard_content(engine_view),
@@ -414,10 +475,22 @@ impl PenHolder {
                 self.brush
                     .paste_clipboard_content(clipboard_content, mime_types, engine_view)
             }
+            PenStyle::Highlighter => {
+                self.highlighter
+                    .paste_clipboard_content(clipboard_content, mime_types, engine_view)
+            }
+            PenStyle::Laser => {
+                self.laser
+                    .paste_clipboard_content(clipboard_content, mime_types, engine_view)
+            }
             PenStyle::Shaper => {
                 self.shaper
                     .paste_clipboard_content(clipboard_content, mime_types, engine_view)
             }
+            PenStyle::Spline => {
+                self.spline
+                    .paste_clipboard_content(clipboard_content, mime_types, engine_view)
+            }
             PenStyle::Typewriter => {
                 self.typewriter
                     .paste_clipboard_content(clipboard_content, mime_types, engine_view)
@@ -444,7 +517,10 @@ impl PenHolder {
     // Updates the penholder and pens internal state
     pub fn update_internal_state(&mut self, engine_view: &EngineView) {
         self.brush.update_internal_state(engine_view);
+        self.highlighter.update_internal_state(engine_view);
+        self.laser.update_internal_state(engine_view);
         self.shaper.update_internal_state(engine_view);
+        self.spline.update_internal_state(engine_view);
         self.typewriter.update_internal_state(engine_view);
         self.eraser.update_internal_state(engine_view);
         self.selector.update_internal_state(engine_view);
@@ -456,7 +532,10 @@ impl DrawOnDocBehaviour for PenHolder {
     fn bounds_on_doc(&self, engine_view: &EngineView) -> Option<AABB> {
         match self.current_style_w_override() {
             PenStyle::Brush => self.brush.bounds_on_doc(engine_view),
+            PenStyle::Highlighter => self.highlighter.bounds_on_doc(engine_view),
+            PenStyle::Laser => self.laser.bounds_on_doc(engine_view),
             PenStyle::Shaper => self.shaper.bounds_on_doc(engine_view),
+            PenStyle::Spline => self.spline.bounds_on_doc(engine_view),
             PenStyle::Typewriter => self.typewriter.bounds_on_doc(engine_view),
             PenStyle::Eraser => self.eraser.bounds_on_doc(engine_view),
             PenStyle::Selector => self.selector.bounds_on_doc(engine_view),
@@ -472,7 +551,10 @@ impl DrawOnDocBehaviour for PenHolder {
 
         match self.current_style_w_override() {
             PenStyle::Brush => self.brush.draw_on_doc(cx, engine_view),
+            PenStyle::Highlighter => self.highlighter.draw_on_doc(cx, engine_view),
+            PenStyle::Laser => self.laser.draw_on_doc(cx, engine_view),
             PenStyle::Shaper => self.shaper.draw_on_doc(cx, engine_view),
+            PenStyle::Spline => self.spline.draw_on_doc(cx, engine_view),
             PenStyle::Typewriter => self.typewriter.draw_on_doc(cx, engine_view),
             PenStyle::Eraser => self.eraser.draw_on_doc(cx, engine_view),
             PenStyle::Selector => self.selector.draw_on_doc(cx, engine_view),
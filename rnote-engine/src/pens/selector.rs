@@ -24,12 +24,24 @@ pub(super) enum ResizeCorner {
     BottomRight,
 }
 
+impl ResizeCorner {
+    /// The position of the corner of the bounds
+    fn corner_of(&self, bounds: AABB) -> na::Vector2<f64> {
+        match self {
+            ResizeCorner::TopLeft => bounds.mins.coords,
+            ResizeCorner::TopRight => na::vector![bounds.maxs[0], bounds.mins[1]],
+            ResizeCorner::BottomLeft => na::vector![bounds.mins[0], bounds.maxs[1]],
+            ResizeCorner::BottomRight => bounds.maxs.coords,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub(super) enum ModifyState {
     Up,
     Translate {
         start_pos: na::Vector2<f64>,
-        current_pos: na::Vector2<f64>,
+        start_bounds: AABB,
     },
     Rotate {
         rotation_center: na::Point2<f64>,
@@ -49,11 +61,37 @@ impl Default for ModifyState {
     }
 }
 
+/// How the newly selected strokes are combined with the current selection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum SelectionMode {
+    /// Replace the current selection
+    Replace,
+    /// Add to the current selection. Selecting while holding Shift
+    Add,
+    /// Subtract from the current selection. Selecting while holding Alt
+    Subtract,
+}
+
+impl SelectionMode {
+    fn from_shortcut_keys(shortcut_keys: &[ShortcutKey]) -> Self {
+        if shortcut_keys.contains(&ShortcutKey::KeyboardShift) {
+            Self::Add
+        } else if shortcut_keys.contains(&ShortcutKey::KeyboardAlt) {
+            Self::Subtract
+        } else {
+            Self::Replace
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub(super) enum SelectorState {
     Idle,
     Selecting {
         path: Vec<Element>,
+        mode: SelectionMode,
+        /// The selection before selecting started, which is extended or reduced depending on the mode
+        previous_selection: Vec<StrokeKey>,
     },
     ModifySelection {
         modify_state: ModifyState,
@@ -116,6 +154,9 @@ pub struct Selector {
     pub style: SelectorStyle,
     #[serde(rename = "resize_lock_aspectratio")]
     pub resize_lock_aspectratio: bool,
+    /// Wether the stroke widths are scaled proportionally when resizing the selection, else they are kept constant
+    #[serde(rename = "resize_scale_stroke_widths")]
+    pub resize_scale_stroke_widths: bool,
     #[serde(skip)]
     pub(super) state: SelectorState,
 }
@@ -125,6 +166,7 @@ impl Default for Selector {
         Self {
             style: SelectorStyle::default(),
             resize_lock_aspectratio: false,
+            resize_scale_stroke_widths: false,
             state: SelectorState::default(),
         }
     }
@@ -141,7 +183,13 @@ impl PenBehaviour for Selector {
         //log::debug!("selector state: {:?}, event: {:?}", &self.state, &event);
 
         let pen_progress = match (&mut self.state, event) {
-            (SelectorState::Idle, PenEvent::Down { element, .. }) => {
+            (
+                SelectorState::Idle,
+                PenEvent::Down {
+                    element,
+                    shortcut_keys,
+                },
+            ) => {
                 widget_flags.merge_with_other(engine_view.store.record());
 
                 // Deselect on start
@@ -150,6 +198,8 @@ impl PenBehaviour for Selector {
 
                 self.state = SelectorState::Selecting {
                     path: vec![element],
+                    mode: SelectionMode::from_shortcut_keys(&shortcut_keys),
+                    previous_selection: vec![],
                 };
 
                 widget_flags.redraw = true;
@@ -168,7 +218,11 @@ impl PenBehaviour for Selector {
                 KeyboardKey::Unicode('a') => {
                     // Select all keys
                     if shortcut_keys.contains(&ShortcutKey::KeyboardCtrl) {
-                        let all_keys = engine_view.store.keys_sorted_chrono();
+                        let all_keys = engine_view
+                            .store
+                            .keys_sorted_chrono_iter()
+                            .filter(|&key| engine_view.store.can_select(key))
+                            .collect::<Vec<StrokeKey>>();
 
                         if let Some(new_selection_bounds) =
                             engine_view.store.bounds_for_strokes(&all_keys)
@@ -198,106 +252,122 @@ impl PenBehaviour for Selector {
                 _ => PenProgress::InProgress,
             },
             (SelectorState::Idle, _) => PenProgress::Idle,
-            (SelectorState::Selecting { path }, PenEvent::Down { element, .. }) => {
+            (SelectorState::Selecting { path, .. }, PenEvent::Down { element, .. }) => {
                 Self::add_to_select_path(self.style, path, element);
 
                 widget_flags.redraw = true;
 
                 PenProgress::InProgress
             }
-            (SelectorState::Selecting { path }, PenEvent::Up { .. }) => {
+            (
+                SelectorState::Selecting {
+                    path,
+                    mode,
+                    previous_selection,
+                },
+                PenEvent::Up { .. },
+            ) => {
                 let mut state = SelectorState::Idle;
                 let mut pen_progress = PenProgress::Finished;
 
-                if let Some(selection) = match self.style {
-                    SelectorStyle::Polygon => {
-                        if path.len() < 3 {
-                            None
-                        } else {
-                            let new_keys = engine_view
-                                .store
-                                .strokes_hitboxes_contained_in_path_polygon(
-                                    path,
-                                    engine_view.camera.viewport(),
-                                );
-                            if !new_keys.is_empty() {
-                                engine_view.store.set_selected_keys(&new_keys, true);
-                                Some(new_keys)
-                            } else {
-                                None
-                            }
-                        }
-                    }
-                    SelectorStyle::Rectangle => {
-                        if let (Some(first), Some(last)) = (path.first(), path.last()) {
-                            let aabb = AABB::new_positive(
-                                na::Point2::from(first.pos),
-                                na::Point2::from(last.pos),
-                            );
-                            let new_keys = engine_view.store.strokes_hitboxes_contained_in_aabb(
-                                aabb,
-                                engine_view.camera.viewport(),
-                            );
-                            if !new_keys.is_empty() {
-                                engine_view.store.set_selected_keys(&new_keys, true);
-                                Some(new_keys)
-                            } else {
-                                None
-                            }
-                        } else {
-                            None
-                        }
-                    }
-                    SelectorStyle::Apiece => {
-                        if let Some(last) = path.last() {
-                            if let Some(&new_key) = engine_view
+                let new_keys = if Self::path_is_click(path, engine_view.camera) {
+                    // A click selects the topmost stroke under the cursor, regardless of the style
+                    path.last()
+                        .and_then(|last| {
+                            engine_view
                                 .store
                                 .stroke_hitboxes_contain_coord(
                                     engine_view.camera.viewport(),
                                     last.pos,
                                 )
                                 .last()
-                            {
-                                engine_view.store.set_selected(new_key, true);
-
-                                Some(vec![new_key])
+                                .copied()
+                        })
+                        .into_iter()
+                        .collect::<Vec<StrokeKey>>()
+                } else {
+                    match self.style {
+                        SelectorStyle::Polygon => {
+                            if path.len() < 3 {
+                                vec![]
                             } else {
-                                None
+                                engine_view
+                                    .store
+                                    .strokes_hitboxes_contained_in_path_polygon(
+                                        path,
+                                        engine_view.camera.viewport(),
+                                    )
                             }
-                        } else {
-                            None
                         }
-                    }
-                    SelectorStyle::IntersectingPath => {
-                        if path.len() < 3 {
-                            None
-                        } else {
-                            let intersecting_keys =
-                                engine_view.store.strokes_hitboxes_intersect_path(
-                                    path,
-                                    engine_view.camera.viewport(),
+                        SelectorStyle::Rectangle => {
+                            if let (Some(first), Some(last)) = (path.first(), path.last()) {
+                                let aabb = AABB::new_positive(
+                                    na::Point2::from(first.pos),
+                                    na::Point2::from(last.pos),
                                 );
-                            if !intersecting_keys.is_empty() {
+                                engine_view.store.strokes_hitboxes_contained_in_aabb(
+                                    aabb,
+                                    engine_view.camera.viewport(),
+                                )
+                            } else {
+                                vec![]
+                            }
+                        }
+                        SelectorStyle::Apiece => path
+                            .last()
+                            .and_then(|last| {
                                 engine_view
                                     .store
-                                    .set_selected_keys(&intersecting_keys, true);
-                                Some(intersecting_keys)
+                                    .stroke_hitboxes_contain_coord(
+                                        engine_view.camera.viewport(),
+                                        last.pos,
+                                    )
+                                    .last()
+                                    .copied()
+                            })
+                            .into_iter()
+                            .collect(),
+                        SelectorStyle::IntersectingPath => {
+                            if path.len() < 3 {
+                                vec![]
                             } else {
-                                None
+                                engine_view.store.strokes_hitboxes_intersect_path(
+                                    path,
+                                    engine_view.camera.viewport(),
+                                )
                             }
                         }
                     }
-                } {
-                    if let Some(selection_bounds) = engine_view.store.bounds_for_strokes(&selection)
-                    {
-                        // Change to the modifiy state
-                        state = SelectorState::ModifySelection {
-                            modify_state: ModifyState::default(),
-                            selection,
-                            selection_bounds,
-                        };
-                        pen_progress = PenProgress::InProgress;
+                };
+
+                let selection = match mode {
+                    SelectionMode::Replace => new_keys,
+                    SelectionMode::Add => {
+                        let new_keys = new_keys
+                            .into_iter()
+                            .filter(|key| !previous_selection.contains(key))
+                            .collect::<Vec<StrokeKey>>();
+                        let mut selection = std::mem::take(previous_selection);
+                        selection.extend(new_keys);
+                        selection
+                    }
+                    SelectionMode::Subtract => {
+                        let mut selection = std::mem::take(previous_selection);
+                        engine_view.store.set_selected_keys(&new_keys, false);
+                        selection.retain(|key| !new_keys.contains(key));
+                        selection
                     }
+                };
+                engine_view.store.set_selected_keys(&selection, true);
+
+                if let Some(selection_bounds) = engine_view.store.bounds_for_strokes(&selection) {
+                    // Change to the modifiy state
+                    state = SelectorState::ModifySelection {
+                        modify_state: ModifyState::default(),
+                        selection,
+                        selection_bounds,
+                    };
+                    pen_progress = PenProgress::InProgress;
                 }
 
                 self.state = state;
@@ -311,6 +381,7 @@ impl PenBehaviour for Selector {
             (SelectorState::Selecting { .. }, PenEvent::Proximity { .. }) => {
                 PenProgress::InProgress
             }
+            (SelectorState::Selecting { .. }, PenEvent::Scroll { .. }) => PenProgress::InProgress,
             (
                 SelectorState::Selecting { .. },
                 PenEvent::KeyPressed {
@@ -321,7 +392,11 @@ impl PenBehaviour for Selector {
                 KeyboardKey::Unicode('a') => {
                     // Select all keys
                     if shortcut_keys.contains(&ShortcutKey::KeyboardCtrl) {
-                        let all_keys = engine_view.store.keys_sorted_chrono();
+                        let all_keys = engine_view
+                            .store
+                            .keys_sorted_chrono_iter()
+                            .filter(|&key| engine_view.store.can_select(key))
+                            .collect::<Vec<StrokeKey>>();
 
                         if let Some(new_selection_bounds) =
                             engine_view.store.bounds_for_strokes(&all_keys)
@@ -380,20 +455,22 @@ impl PenBehaviour for Selector {
                     ModifyState::Up => {
                         widget_flags.merge_with_other(engine_view.store.record());
 
+                        let mode = SelectionMode::from_shortcut_keys(&shortcut_keys);
                         // If we click on another, not-already selected stroke while in apiece style or while pressing Shift, we add it to the selection
                         let keys = engine_view.store.stroke_hitboxes_contain_coord(
                             engine_view.camera.viewport(),
                             element.pos,
                         );
-                        let key_to_add = keys.last();
-
-                        if (self.style == SelectorStyle::Apiece
-                            || shortcut_keys.contains(&ShortcutKey::KeyboardShift))
-                            && key_to_add
-                                .and_then(|&key| engine_view.store.selected(key).map(|s| !s))
-                                .unwrap_or(false)
+                        let key_under_cursor = keys.last().copied();
+                        let key_under_cursor_selected = key_under_cursor
+                            .and_then(|key| engine_view.store.selected(key))
+                            .unwrap_or(false);
+
+                        if (self.style == SelectorStyle::Apiece || mode == SelectionMode::Add)
+                            && key_under_cursor.is_some()
+                            && !key_under_cursor_selected
                         {
-                            let key_to_add = *key_to_add.unwrap();
+                            let key_to_add = key_under_cursor.unwrap();
                             engine_view.store.set_selected(key_to_add, true);
 
                             selection.push(key_to_add);
@@ -402,6 +479,28 @@ impl PenBehaviour for Selector {
                                 .store
                                 .bounds_for_strokes(selection)
                                 .map(|new_bounds| *selection_bounds = new_bounds);
+                        } else if mode == SelectionMode::Subtract && key_under_cursor_selected {
+                            // Clicking on a selected stroke while pressing Alt removes it from the selection
+                            let key_to_remove = key_under_cursor.unwrap();
+                            engine_view.store.set_selected(key_to_remove, false);
+
+                            selection.retain(|&key| key != key_to_remove);
+
+                            match engine_view.store.bounds_for_strokes(selection) {
+                                Some(new_bounds) => *selection_bounds = new_bounds,
+                                None => {
+                                    self.state = SelectorState::Idle;
+
+                                    pen_progress = PenProgress::Finished;
+                                }
+                            }
+                        } else if mode != SelectionMode::Replace {
+                            // Start selecting with the modifiers, extending or reducing the current selection
+                            self.state = SelectorState::Selecting {
+                                path: vec![element],
+                                mode,
+                                previous_selection: selection.clone(),
+                            };
                         } else if Self::rotate_node_sphere(*selection_bounds, engine_view.camera)
                             .contains_local_point(&na::Point2::from(element.pos))
                         {
@@ -471,7 +570,7 @@ impl PenBehaviour for Selector {
                             // clicking inside the selection bounds, triggering translation
                             *modify_state = ModifyState::Translate {
                                 start_pos: element.pos,
-                                current_pos: element.pos,
+                                start_bounds: *selection_bounds,
                             };
                         } else {
                             // If clicking outside the selection bounds, reset
@@ -482,10 +581,14 @@ impl PenBehaviour for Selector {
                         }
                     }
                     ModifyState::Translate {
-                        start_pos: _,
-                        current_pos,
+                        start_pos,
+                        start_bounds,
                     } => {
-                        let offset = element.pos - *current_pos;
+                        // the top left corner of the selection is snapped to the grid
+                        let new_mins = engine_view
+                            .doc
+                            .snap_point(start_bounds.mins.coords + element.pos - *start_pos);
+                        let offset = new_mins - selection_bounds.mins.coords;
 
                         if offset.magnitude()
                             > Self::TRANSLATE_MAGNITUDE_THRESHOLD / engine_view.camera.total_zoom()
@@ -503,20 +606,29 @@ impl PenBehaviour for Selector {
                                 engine_view.camera.viewport(),
                                 engine_view.camera.image_scale(),
                             );
-
-                            *current_pos = element.pos;
                         }
                     }
                     ModifyState::Rotate {
                         rotation_center,
-                        start_rotation_angle: _,
+                        start_rotation_angle,
                         current_rotation_angle,
                     } => {
-                        let new_rotation_angle = {
+                        let mut new_rotation_angle = {
                             let vec = element.pos - rotation_center.coords;
                             na::Vector2::x().angle_ahead(&vec)
                         };
-                        let angle_delta = new_rotation_angle - *current_rotation_angle;
+                        // Snap the rotation since starting to rotate to steps while pressing Ctrl
+                        if shortcut_keys.contains(&ShortcutKey::KeyboardCtrl) {
+                            let rotation = new_rotation_angle - *start_rotation_angle;
+                            new_rotation_angle = *start_rotation_angle
+                                + (rotation / Self::ROTATE_SNAP_STEP).round()
+                                    * Self::ROTATE_SNAP_STEP;
+                        }
+                        // wrapped into [-PI, PI), so that crossing the x axis does not rotate by a full turn
+                        let angle_delta = (new_rotation_angle - *current_rotation_angle
+                            + std::f64::consts::PI)
+                            .rem_euclid(std::f64::consts::TAU)
+                            - std::f64::consts::PI;
 
                         if angle_delta.abs() > Self::ROTATE_ANGLE_THRESHOLD {
                             engine_view.store.rotate_strokes(
@@ -543,7 +655,12 @@ impl PenBehaviour for Selector {
                         start_pos,
                     } => {
                         let (pos_offset, pivot) = {
-                            let pos_offset = element.pos - *start_pos;
+                            // the dragged corner is snapped to the grid
+                            let start_corner = from_corner.corner_of(*start_bounds);
+                            let pos_offset = engine_view
+                                .doc
+                                .snap_point(start_corner + element.pos - *start_pos)
+                                - start_corner;
 
                             match from_corner {
                                 ResizeCorner::TopLeft => (-pos_offset, start_bounds.maxs.coords),
@@ -580,9 +697,12 @@ impl PenBehaviour for Selector {
 
                         let scale = new_extents.component_div(&selection_bounds.extents());
 
-                        engine_view
-                            .store
-                            .scale_strokes_with_pivot(selection, scale, pivot);
+                        engine_view.store.scale_strokes_with_pivot(
+                            selection,
+                            scale,
+                            pivot,
+                            self.resize_scale_stroke_widths,
+                        );
                         engine_view
                             .store
                             .scale_strokes_images_with_pivot(selection, scale, pivot);
@@ -635,6 +755,9 @@ impl PenBehaviour for Selector {
             (SelectorState::ModifySelection { .. }, PenEvent::Proximity { .. }) => {
                 PenProgress::InProgress
             }
+            (SelectorState::ModifySelection { .. }, PenEvent::Scroll { .. }) => {
+                PenProgress::InProgress
+            }
             (
                 SelectorState::ModifySelection { selection, .. },
                 PenEvent::KeyPressed {
@@ -645,7 +768,11 @@ impl PenBehaviour for Selector {
                 KeyboardKey::Unicode('a') => {
                     // Select all keys
                     if shortcut_keys.contains(&ShortcutKey::KeyboardCtrl) {
-                        let all_keys = engine_view.store.keys_sorted_chrono();
+                        let all_keys = engine_view
+                            .store
+                            .keys_sorted_chrono_iter()
+                            .filter(|&key| engine_view.store.can_select(key))
+                            .collect::<Vec<StrokeKey>>();
 
                         if let Some(new_selection_bounds) =
                             engine_view.store.bounds_for_strokes(&all_keys)
@@ -749,7 +876,7 @@ impl DrawOnDocBehaviour for Selector {
 
         match &self.state {
             SelectorState::Idle => None,
-            SelectorState::Selecting { path } => {
+            SelectorState::Selecting { path, .. } => {
                 // Making sure bounds are always outside of coord + width
                 let mut path_iter = path.iter();
                 if let Some(first) = path_iter.next() {
@@ -787,7 +914,7 @@ impl DrawOnDocBehaviour for Selector {
 
         match &self.state {
             SelectorState::Idle => {}
-            SelectorState::Selecting { path } => match self.style {
+            SelectorState::Selecting { path, .. } => match self.style {
                 SelectorStyle::Polygon => {
                     let mut bez_path = kurbo::BezPath::new();
                     let mut path_iter = path.iter();
@@ -933,8 +1060,12 @@ impl DrawOnDocBehaviour for Selector {
 impl Selector {
     /// The threshold where a translation is applied ( in offset magnitude, surface coords )
     const TRANSLATE_MAGNITUDE_THRESHOLD: f64 = 1.0;
+    /// The max distance the pen may move while selecting for it to be considered a click
+    const CLICK_MAGNITUDE_THRESHOLD: f64 = 4.0;
     /// The threshold angle (rad) where a rotation is applied
     const ROTATE_ANGLE_THRESHOLD: f64 = ((2.0 * std::f64::consts::PI) / 360.0) * 0.2;
+    /// The step angle (rad) the rotation is snapped to while pressing Ctrl
+    const ROTATE_SNAP_STEP: f64 = std::f64::consts::PI / 12.0;
 
     const SELECTION_OUTLINE_WIDTH: f64 = 1.5;
     const OUTLINE_COLOR: piet::Color = color::GNOME_BRIGHTS[4].with_a8(0xf0);
@@ -948,6 +1079,16 @@ impl Selector {
     /// rotate node size, in surface coords
     const ROTATE_NODE_SIZE: f64 = 18.0;
 
+    /// Wether the selecting path stayed within the click threshold, so that it is considered a click
+    fn path_is_click(path: &[Element], camera: &Camera) -> bool {
+        let threshold = Self::CLICK_MAGNITUDE_THRESHOLD / camera.total_zoom();
+
+        path.first().map_or(false, |first| {
+            path.iter()
+                .all(|element| (element.pos - first.pos).magnitude() <= threshold)
+        })
+    }
+
     fn add_to_select_path(style: SelectorStyle, path: &mut Vec<Element>, element: Element) {
         match style {
             SelectorStyle::Polygon | SelectorStyle::Apiece | SelectorStyle::IntersectingPath => {
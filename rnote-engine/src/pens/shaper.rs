@@ -8,13 +8,16 @@ use p2d::bounding_volume::AABB;
 use piet::RenderContext;
 use rand::{Rng, SeedableRng};
 use rnote_compose::builders::shapebuilderbehaviour::{BuilderProgress, ShapeBuilderCreator};
-use rnote_compose::builders::{Constraints, CubBezBuilder, QuadBezBuilder, ShapeBuilderType};
 use rnote_compose::builders::{
-    EllipseBuilder, FociEllipseBuilder, LineBuilder, RectangleBuilder, ShapeBuilderBehaviour,
+    ArrowBuilder, EllipseBuilder, FociEllipseBuilder, LineBuilder, RectangleBuilder,
+    RegularPolygonBuilder, ShapeBuilderBehaviour, StarBuilder,
 };
+use rnote_compose::builders::{Constraints, CubBezBuilder, QuadBezBuilder, ShapeBuilderType};
 use rnote_compose::penhelpers::{PenEvent, ShortcutKey};
+use rnote_compose::shapes::ArrowHead;
 use rnote_compose::style::rough::RoughOptions;
 use rnote_compose::style::smooth::SmoothOptions;
+use rnote_compose::style::LineStyle;
 use rnote_compose::Style;
 use serde::{Deserialize, Serialize};
 
@@ -66,6 +69,15 @@ pub struct Shaper {
     pub rough_options: RoughOptions,
     #[serde(rename = "constraints")]
     pub constraints: Constraints,
+    /// The head of built arrows
+    #[serde(rename = "arrow_head")]
+    pub arrow_head: ArrowHead,
+    /// The number of sides of built regular polygons
+    #[serde(rename = "polygon_sides")]
+    pub polygon_sides: u32,
+    /// The number of points of built stars
+    #[serde(rename = "star_points")]
+    pub star_points: u32,
     #[serde(skip)]
     state: ShaperState,
 }
@@ -83,6 +95,9 @@ impl Default for Shaper {
             smooth_options,
             rough_options,
             constraints: Constraints::default(),
+            arrow_head: ArrowHead::default(),
+            polygon_sides: RegularPolygonBuilder::SIDES_DEFAULT,
+            star_points: StarBuilder::POINTS_DEFAULT,
             state: ShaperState::Idle,
         }
     }
@@ -133,12 +148,62 @@ impl PenBehaviour for Shaper {
                             builder: Box::new(CubBezBuilder::start(element)),
                         }
                     }
+                    ShapeBuilderType::Arrow => {
+                        self.state = ShaperState::BuildShape {
+                            builder: Box::new(ArrowBuilder::start_w_head(
+                                element,
+                                self.arrow_head,
+                                false,
+                            )),
+                        }
+                    }
+                    ShapeBuilderType::DoubleArrow => {
+                        self.state = ShaperState::BuildShape {
+                            builder: Box::new(ArrowBuilder::start_w_head(
+                                element,
+                                self.arrow_head,
+                                true,
+                            )),
+                        }
+                    }
+                    ShapeBuilderType::RegularPolygon => {
+                        self.state = ShaperState::BuildShape {
+                            builder: Box::new(RegularPolygonBuilder::start_w_sides(
+                                element,
+                                self.polygon_sides,
+                            )),
+                        }
+                    }
+                    ShapeBuilderType::Star => {
+                        self.state = ShaperState::BuildShape {
+                            builder: Box::new(StarBuilder::start_w_points(
+                                element,
+                                self.star_points,
+                            )),
+                        }
+                    }
                 }
 
                 widget_flags.redraw = true;
 
                 PenProgress::InProgress
             }
+            (ShaperState::Idle, PenEvent::Scroll { delta, .. }) => {
+                let options_width = match self.style {
+                    ShaperStyle::Smooth => &mut self.smooth_options.stroke_width,
+                    ShaperStyle::Rough => &mut self.rough_options.stroke_width,
+                };
+                *options_width = super::scroll_adjusted_size(
+                    *options_width,
+                    delta,
+                    Self::STROKE_WIDTH_MIN,
+                    Self::STROKE_WIDTH_MAX,
+                );
+
+                widget_flags.refresh_ui = true;
+
+                PenProgress::Idle
+            }
             (ShaperState::Idle, _) => PenProgress::Idle,
             (ShaperState::BuildShape { .. }, PenEvent::Cancel) => {
                 self.state = ShaperState::Idle;
@@ -161,6 +226,9 @@ impl PenBehaviour for Shaper {
                     }
                     | PenEvent::KeyPressed {
                         ref shortcut_keys, ..
+                    }
+                    | PenEvent::Scroll {
+                        ref shortcut_keys, ..
                     } => constraints.enabled ^ shortcut_keys.contains(&ShortcutKey::KeyboardCtrl),
                     PenEvent::Cancel => false,
                 };
@@ -282,6 +350,20 @@ impl Shaper {
     pub const STROKE_WIDTH_MAX: f64 = 500.0;
     pub const STROKE_WIDTH_DEFAULT: f64 = 2.0;
 
+    /// The line style of the current style
+    pub fn line_style(&self) -> LineStyle {
+        match &self.style {
+            ShaperStyle::Smooth => self.smooth_options.line_style,
+            ShaperStyle::Rough => self.rough_options.line_style,
+        }
+    }
+
+    /// Sets the line style for all styles
+    pub fn set_line_style(&mut self, line_style: LineStyle) {
+        self.smooth_options.line_style = line_style;
+        self.rough_options.line_style = line_style;
+    }
+
     pub fn gen_style_for_current_options(&self) -> Style {
         match &self.style {
             ShaperStyle::Smooth => {
@@ -296,4 +378,23 @@ impl Shaper {
             }
         }
     }
+    /// Loads the given style into the options of the matching shaper style and selects it.
+    /// For styles which are not available for shapes only the stroke width and color are taken over into the smooth style.
+    pub fn load_style(&mut self, style: &Style) {
+        match style {
+            Style::Smooth(options) => {
+                self.style = ShaperStyle::Smooth;
+                self.smooth_options = options.clone();
+            }
+            Style::Rough(options) => {
+                self.style = ShaperStyle::Rough;
+                self.rough_options = options.clone();
+            }
+            Style::Textured(_) | Style::Calligraphy(_) | Style::Stamped(_) => {
+                self.style = ShaperStyle::Smooth;
+                self.smooth_options.stroke_width = style.stroke_width();
+                self.smooth_options.stroke_color = style.stroke_color();
+            }
+        }
+    }
 }
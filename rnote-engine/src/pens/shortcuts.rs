@@ -16,6 +16,9 @@ pub enum ShortcutAction {
         #[serde(rename = "permanent")]
         permanent: bool,
     },
+    /// While the shortcut key is held, scrolling adjusts the current pen (e.g. its size) instead of scrolling the canvas
+    #[serde(rename = "adjust_with_scroll")]
+    AdjustWithScroll,
 }
 
 /// holds the registered shortcut actions for the given shortcut keys
@@ -47,6 +50,7 @@ impl Default for Shortcuts {
                 permanent: false,
             },
         );
+        map.insert(ShortcutKey::KeyboardAlt, ShortcutAction::AdjustWithScroll);
 
         Self(map)
     }
@@ -0,0 +1,407 @@
+use super::penbehaviour::{PenBehaviour, PenProgress};
+use crate::engine::{EngineView, EngineViewMut};
+use crate::strokes::{BrushStroke, Stroke};
+use crate::{DrawOnDocBehaviour, WidgetFlags};
+use rnote_compose::penhelpers::{KeyboardKey, PenEvent, PenState};
+use rnote_compose::penpath::{Element, Segment};
+use rnote_compose::style::smooth::SmoothOptions;
+use rnote_compose::style::{drawhelpers, Composer, PressureCurve};
+use rnote_compose::{PenPath, Style};
+
+use p2d::bounding_volume::{BoundingSphere, BoundingVolume, AABB};
+use serde::{Deserialize, Serialize};
+
+/// An anchor point of the spline
+#[derive(Debug, Clone, Copy)]
+struct SplineAnchor {
+    pos: na::Vector2<f64>,
+    /// The outgoing handle, relative to the anchor position. The incoming handle mirrors it.
+    /// When None, the handle is calculated from the neighbouring anchors so that the spline runs smoothly through them.
+    handle: Option<na::Vector2<f64>>,
+}
+
+impl SplineAnchor {
+    fn new(pos: na::Vector2<f64>) -> Self {
+        Self { pos, handle: None }
+    }
+}
+
+/// What is currently dragged while the pen is down
+#[derive(Debug, Clone, Copy)]
+enum SplineDrag {
+    Anchor(usize),
+    HandleOut(usize),
+    HandleIn(usize),
+    /// The handle of a just placed anchor, which is only set when the pen moved far enough away
+    NewAnchorHandle(usize),
+}
+
+#[derive(Debug, Clone)]
+enum SplineState {
+    Idle,
+    Building {
+        anchors: Vec<SplineAnchor>,
+        drag: Option<SplineDrag>,
+    },
+}
+
+impl Default for SplineState {
+    fn default() -> Self {
+        Self::Idle
+    }
+}
+
+/// A pen placing anchor points on click, building a smooth cubic spline through them.
+/// The handles of the anchors can be edited before the spline is committed as a stroke.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename = "spline")]
+pub struct Spline {
+    #[serde(rename = "options")]
+    pub options: SmoothOptions,
+
+    #[serde(skip)]
+    state: SplineState,
+}
+
+impl Default for Spline {
+    fn default() -> Self {
+        let mut options = SmoothOptions::default();
+        options.stroke_width = Self::STROKE_WIDTH_DEFAULT;
+        options.pressure_curve = PressureCurve::Const;
+
+        Self {
+            options,
+            state: SplineState::Idle,
+        }
+    }
+}
+
+impl PenBehaviour for Spline {
+    fn handle_event(
+        &mut self,
+        event: PenEvent,
+        engine_view: &mut EngineViewMut,
+    ) -> (PenProgress, WidgetFlags) {
+        let mut widget_flags = WidgetFlags::default();
+        let zoom = engine_view.camera.total_zoom();
+
+        let pen_progress = match (&mut self.state, event) {
+            (SplineState::Idle, PenEvent::Down { element, .. }) => {
+                self.state = SplineState::Building {
+                    anchors: vec![SplineAnchor::new(element.pos)],
+                    drag: Some(SplineDrag::NewAnchorHandle(0)),
+                };
+
+                widget_flags.redraw = true;
+
+                PenProgress::InProgress
+            }
+            (SplineState::Idle, PenEvent::Scroll { delta, .. }) => {
+                self.options.stroke_width = super::scroll_adjusted_size(
+                    self.options.stroke_width,
+                    delta,
+                    Self::STROKE_WIDTH_MIN,
+                    Self::STROKE_WIDTH_MAX,
+                );
+
+                widget_flags.refresh_ui = true;
+
+                PenProgress::Idle
+            }
+            (SplineState::Idle, _) => PenProgress::Idle,
+            (SplineState::Building { anchors, drag }, PenEvent::Down { element, .. }) => {
+                let pos = element.pos;
+
+                match drag {
+                    Some(SplineDrag::Anchor(i)) => {
+                        anchors[*i].pos = pos;
+                    }
+                    Some(SplineDrag::HandleOut(i)) => {
+                        anchors[*i].handle = Some(pos - anchors[*i].pos);
+                    }
+                    Some(SplineDrag::HandleIn(i)) => {
+                        anchors[*i].handle = Some(anchors[*i].pos - pos);
+                    }
+                    Some(SplineDrag::NewAnchorHandle(i)) => {
+                        let handle = pos - anchors[*i].pos;
+
+                        if handle.magnitude() > Self::HIT_RADIUS / zoom {
+                            anchors[*i].handle = Some(handle);
+                        }
+                    }
+                    None => {
+                        *drag = Some(Self::hit_test(anchors, pos, zoom).unwrap_or_else(|| {
+                            anchors.push(SplineAnchor::new(pos));
+                            SplineDrag::NewAnchorHandle(anchors.len() - 1)
+                        }));
+                    }
+                }
+
+                // Pressing on the last anchor again finishes the spline
+                if let Some(SplineDrag::Anchor(i)) = drag {
+                    if *i == anchors.len() - 1 && anchors.len() > 1 {
+                        widget_flags.merge_with_other(self.finish(engine_view));
+
+                        return (PenProgress::Finished, widget_flags);
+                    }
+                }
+
+                widget_flags.redraw = true;
+
+                PenProgress::InProgress
+            }
+            (SplineState::Building { drag, .. }, PenEvent::Up { .. }) => {
+                *drag = None;
+
+                widget_flags.redraw = true;
+
+                PenProgress::InProgress
+            }
+            (
+                SplineState::Building { anchors, drag },
+                PenEvent::KeyPressed { keyboard_key, .. },
+            ) => match keyboard_key {
+                KeyboardKey::CarriageReturn | KeyboardKey::Linefeed => {
+                    widget_flags.merge_with_other(self.finish(engine_view));
+
+                    PenProgress::Finished
+                }
+                KeyboardKey::Escape => {
+                    self.state = SplineState::Idle;
+
+                    widget_flags.redraw = true;
+
+                    PenProgress::Finished
+                }
+                KeyboardKey::BackSpace | KeyboardKey::Delete => {
+                    anchors.pop();
+                    *drag = None;
+
+                    widget_flags.redraw = true;
+
+                    if anchors.is_empty() {
+                        self.state = SplineState::Idle;
+
+                        PenProgress::Finished
+                    } else {
+                        PenProgress::InProgress
+                    }
+                }
+                _ => PenProgress::InProgress,
+            },
+            (SplineState::Building { .. }, PenEvent::Cancel) => {
+                widget_flags.merge_with_other(self.finish(engine_view));
+
+                PenProgress::Finished
+            }
+            (SplineState::Building { .. }, _) => PenProgress::InProgress,
+        };
+
+        (pen_progress, widget_flags)
+    }
+}
+
+impl DrawOnDocBehaviour for Spline {
+    fn bounds_on_doc(&self, engine_view: &EngineView) -> Option<AABB> {
+        match &self.state {
+            SplineState::Idle => None,
+            SplineState::Building { anchors, .. } => {
+                let mut bounds = Self::anchors_to_penpath(anchors)
+                    .map(|path| path.composed_bounds(&self.options))
+                    .unwrap_or_else(AABB::new_invalid);
+
+                for (anchor_pos, handle_in, handle_out) in Self::handle_positions(anchors) {
+                    bounds.take_point(na::Point2::from(anchor_pos));
+                    bounds.take_point(na::Point2::from(handle_in));
+                    bounds.take_point(na::Point2::from(handle_out));
+                }
+
+                Some(bounds.loosened(Self::HIT_RADIUS / engine_view.camera.total_zoom()))
+            }
+        }
+    }
+
+    fn draw_on_doc(
+        &self,
+        cx: &mut piet_cairo::CairoRenderContext,
+        engine_view: &EngineView,
+    ) -> anyhow::Result<()> {
+        let zoom = engine_view.camera.total_zoom();
+
+        if let SplineState::Building { anchors, drag } = &self.state {
+            if let Some(path) = Self::anchors_to_penpath(anchors) {
+                path.draw_composed(cx, &self.options);
+            }
+
+            for (i, (anchor_pos, handle_in, handle_out)) in
+                Self::handle_positions(anchors).into_iter().enumerate()
+            {
+                let handle_state = |dragged: bool| {
+                    if dragged {
+                        PenState::Down
+                    } else {
+                        PenState::Up
+                    }
+                };
+                let anchor_dragged = matches!(drag, Some(SplineDrag::Anchor(j)) if *j == i);
+                let handle_dragged = matches!(drag,
+                    Some(SplineDrag::HandleIn(j) | SplineDrag::HandleOut(j) | SplineDrag::NewAnchorHandle(j)) if *j == i);
+
+                drawhelpers::draw_vec_indicator(
+                    cx,
+                    handle_state(handle_dragged),
+                    handle_in,
+                    handle_out,
+                    zoom,
+                );
+                for handle in [handle_in, handle_out] {
+                    drawhelpers::draw_circular_node(
+                        cx,
+                        handle_state(handle_dragged),
+                        BoundingSphere::new(
+                            na::Point2::from(handle),
+                            Self::HANDLE_NODE_RADIUS / zoom,
+                        ),
+                        zoom,
+                    );
+                }
+                drawhelpers::draw_pos_indicator(cx, handle_state(anchor_dragged), anchor_pos, zoom);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Spline {
+    pub const STROKE_WIDTH_MIN: f64 = 0.1;
+    pub const STROKE_WIDTH_MAX: f64 = 500.0;
+    pub const STROKE_WIDTH_DEFAULT: f64 = 2.0;
+    /// The radius in which anchors and handles are hit when pressing on them
+    const HIT_RADIUS: f64 = 8.0;
+    const HANDLE_NODE_RADIUS: f64 = 4.0;
+
+    /// Wether a spline is currently being built
+    pub fn is_building(&self) -> bool {
+        matches!(self.state, SplineState::Building { .. })
+    }
+
+    /// The handle of the anchor at the given index, relative to the anchor position
+    fn anchor_handle(anchors: &[SplineAnchor], i: usize) -> na::Vector2<f64> {
+        anchors[i].handle.unwrap_or_else(|| {
+            // Catmull-Rom tangent, one sided at the ends
+            let prev = anchors[i.saturating_sub(1)].pos;
+            let next = anchors[(i + 1).min(anchors.len() - 1)].pos;
+
+            (next - prev) / 6.0
+        })
+    }
+
+    /// The positions of the anchors and their incoming and outgoing handles
+    fn handle_positions(
+        anchors: &[SplineAnchor],
+    ) -> Vec<(na::Vector2<f64>, na::Vector2<f64>, na::Vector2<f64>)> {
+        (0..anchors.len())
+            .map(|i| {
+                let handle = Self::anchor_handle(anchors, i);
+
+                (
+                    anchors[i].pos,
+                    anchors[i].pos - handle,
+                    anchors[i].pos + handle,
+                )
+            })
+            .collect()
+    }
+
+    /// Hit tests the handles first, then the anchors.
+    /// Handles which are too close to their anchor to be distinguishable from it are skipped.
+    fn hit_test(anchors: &[SplineAnchor], pos: na::Vector2<f64>, zoom: f64) -> Option<SplineDrag> {
+        let hit = |target: na::Vector2<f64>| (target - pos).magnitude() < Self::HIT_RADIUS / zoom;
+        let handle_positions = Self::handle_positions(anchors);
+
+        handle_positions
+            .iter()
+            .enumerate()
+            .filter(|(_, (anchor_pos, _, handle_out))| {
+                (handle_out - anchor_pos).magnitude() >= Self::HIT_RADIUS / zoom
+            })
+            .find_map(|(i, (_, handle_in, handle_out))| {
+                if hit(*handle_out) {
+                    Some(SplineDrag::HandleOut(i))
+                } else if hit(*handle_in) {
+                    Some(SplineDrag::HandleIn(i))
+                } else {
+                    None
+                }
+            })
+            .or_else(|| {
+                // Prefer the last anchor, so that pressing it again finishes the spline
+                handle_positions
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .find(|(_, (anchor_pos, _, _))| hit(*anchor_pos))
+                    .map(|(i, _)| SplineDrag::Anchor(i))
+            })
+    }
+
+    /// The spline as pen path of cubic bezier segments. None if there are less than two anchors
+    fn anchors_to_penpath(anchors: &[SplineAnchor]) -> Option<PenPath> {
+        if anchors.len() < 2 {
+            return None;
+        }
+
+        let mut path = PenPath::default();
+        for i in 0..anchors.len() - 1 {
+            path.push_back(Segment::CubBez {
+                start: Element::new(anchors[i].pos, 1.0),
+                cp1: anchors[i].pos + Self::anchor_handle(anchors, i),
+                cp2: anchors[i + 1].pos - Self::anchor_handle(anchors, i + 1),
+                end: Element::new(anchors[i + 1].pos, 1.0),
+            });
+        }
+
+        Some(path)
+    }
+
+    /// Commits the current spline as stroke and resets the state
+    fn finish(&mut self, engine_view: &mut EngineViewMut) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        let state = std::mem::take(&mut self.state);
+
+        if let SplineState::Building { anchors, .. } = state {
+            if let Some(brushstroke) = Self::anchors_to_penpath(&anchors).and_then(|path| {
+                BrushStroke::from_penpath(path, Style::Smooth(self.options.clone()))
+            }) {
+                widget_flags.merge_with_other(engine_view.store.record());
+
+                let key = engine_view
+                    .store
+                    .insert_stroke(Stroke::BrushStroke(brushstroke), None);
+                if let Err(e) = engine_view.store.regenerate_rendering_for_stroke(
+                    key,
+                    engine_view.camera.viewport(),
+                    engine_view.camera.image_scale(),
+                ) {
+                    log::error!(
+                        "regenerate_rendering_for_stroke() failed after inserting spline, Err {}",
+                        e
+                    );
+                }
+
+                engine_view
+                    .doc
+                    .resize_autoexpand(engine_view.store, engine_view.camera);
+
+                widget_flags.resize = true;
+                widget_flags.indicate_changed_store = true;
+            }
+        }
+
+        widget_flags.redraw = true;
+
+        widget_flags
+    }
+}
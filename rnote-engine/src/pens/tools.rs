@@ -1,12 +1,15 @@
 use crate::engine::{EngineView, EngineViewMut};
 use crate::store::StrokeKey;
+use crate::strokes::{ShapeStroke, Stroke};
 use crate::{DrawOnDocBehaviour, WidgetFlags};
 use piet::RenderContext;
 use rnote_compose::color;
 use rnote_compose::helpers::{AABBHelpers, Vector2Helpers};
-use rnote_compose::penhelpers::PenEvent;
+use rnote_compose::penhelpers::{KeyboardKey, PenEvent, ShortcutKey};
+use rnote_compose::style::smooth::SmoothOptions;
+use rnote_compose::{Color, Shape, Style};
 
-use p2d::bounding_volume::AABB;
+use p2d::bounding_volume::{BoundingVolume, AABB};
 use serde::{Deserialize, Serialize};
 
 use super::penbehaviour::{PenBehaviour, PenProgress};
@@ -101,6 +104,194 @@ impl DrawOnDocBehaviour for VerticalSpaceTool {
     }
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, rename = "horizontalspace_tool")]
+pub struct HorizontalSpaceTool {
+    #[serde(skip)]
+    start_pos_x: f64,
+    #[serde(skip)]
+    current_pos_x: f64,
+    #[serde(skip)]
+    strokes_right: Vec<StrokeKey>,
+}
+
+impl Default for HorizontalSpaceTool {
+    fn default() -> Self {
+        Self {
+            start_pos_x: 0.0,
+            current_pos_x: 0.0,
+            strokes_right: vec![],
+        }
+    }
+}
+
+impl HorizontalSpaceTool {
+    const X_OFFSET_THRESHOLD: f64 = 0.1;
+
+    const FILL_COLOR: piet::Color = color::GNOME_BRIGHTS[2].with_a8(0x17);
+    const THRESHOLD_LINE_COLOR: piet::Color = color::GNOME_GREENS[4].with_a8(0xf0);
+    const OFFSET_LINE_COLOR: piet::Color = color::GNOME_BLUES[3];
+
+    const THRESHOLD_LINE_WIDTH: f64 = 4.0;
+    const OFFSET_LINE_WIDTH: f64 = 2.0;
+}
+
+impl DrawOnDocBehaviour for HorizontalSpaceTool {
+    fn bounds_on_doc(&self, engine_view: &EngineView) -> Option<AABB> {
+        let viewport = engine_view.camera.viewport();
+
+        let x = self.start_pos_x;
+        let y = viewport.mins[1];
+        let width = self.current_pos_x - self.start_pos_x;
+        let height = viewport.extents()[1];
+        let tool_bounds = AABB::new_positive(na::point![x, y], na::point![x + width, y + height]);
+
+        Some(tool_bounds)
+    }
+
+    fn draw_on_doc(
+        &self,
+        cx: &mut piet_cairo::CairoRenderContext,
+        engine_view: &EngineView,
+    ) -> anyhow::Result<()> {
+        cx.save().map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        let viewport = engine_view.camera.viewport();
+        let x = self.start_pos_x;
+        let y = viewport.mins[1];
+        let width = self.current_pos_x - self.start_pos_x;
+        let height = viewport.extents()[1];
+        let tool_bounds = AABB::new_positive(na::point![x, y], na::point![x + width, y + height]);
+
+        let tool_bounds_rect = kurbo::Rect::from_points(
+            tool_bounds.mins.coords.to_kurbo_point(),
+            tool_bounds.maxs.coords.to_kurbo_point(),
+        );
+        cx.fill(tool_bounds_rect, &Self::FILL_COLOR);
+
+        let threshold_line =
+            kurbo::Line::new(kurbo::Point::new(x, y), kurbo::Point::new(x, y + height));
+
+        cx.stroke_styled(
+            threshold_line,
+            &Self::THRESHOLD_LINE_COLOR,
+            Self::THRESHOLD_LINE_WIDTH,
+            &piet::StrokeStyle::new().dash_pattern(&[12.0, 6.0]),
+        );
+
+        let offset_line = kurbo::Line::new(
+            kurbo::Point::new(x + width, y),
+            kurbo::Point::new(x + width, y + height),
+        );
+        cx.stroke(
+            offset_line,
+            &Self::OFFSET_LINE_COLOR,
+            Self::OFFSET_LINE_WIDTH,
+        );
+
+        cx.restore().map_err(|e| anyhow::anyhow!("{}", e))?;
+        Ok(())
+    }
+}
+
+/// Moves the strokes of a whole page. When released, the offset is snapped to whole pages.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, rename = "movepage_tool")]
+pub struct MovePageTool {
+    #[serde(skip)]
+    start_pos: na::Vector2<f64>,
+    #[serde(skip)]
+    current_pos: na::Vector2<f64>,
+    #[serde(skip)]
+    page_bounds: Option<AABB>,
+    #[serde(skip)]
+    strokes_on_page: Vec<StrokeKey>,
+}
+
+impl Default for MovePageTool {
+    fn default() -> Self {
+        Self {
+            start_pos: na::Vector2::zeros(),
+            current_pos: na::Vector2::zeros(),
+            page_bounds: None,
+            strokes_on_page: vec![],
+        }
+    }
+}
+
+impl MovePageTool {
+    const OFFSET_THRESHOLD: f64 = 0.1;
+
+    const FILL_COLOR: piet::Color = color::GNOME_BRIGHTS[2].with_a8(0x17);
+    const START_OUTLINE_COLOR: piet::Color = color::GNOME_GREENS[4].with_a8(0xf0);
+    const TARGET_OUTLINE_COLOR: piet::Color = color::GNOME_BLUES[3];
+
+    const START_OUTLINE_WIDTH: f64 = 4.0;
+    const TARGET_OUTLINE_WIDTH: f64 = 2.0;
+
+    /// The offset the strokes are moved by when released, snapped to whole pages
+    fn snapped_offset(&self, page_size: na::Vector2<f64>) -> na::Vector2<f64> {
+        let offset = self.current_pos - self.start_pos;
+
+        if page_size[0] > 0.0 && page_size[1] > 0.0 {
+            offset
+                .component_div(&page_size)
+                .map(|pages| pages.round())
+                .component_mul(&page_size)
+        } else {
+            offset
+        }
+    }
+}
+
+impl DrawOnDocBehaviour for MovePageTool {
+    fn bounds_on_doc(&self, engine_view: &EngineView) -> Option<AABB> {
+        let page_bounds = self.page_bounds?;
+        let page_size = na::vector![engine_view.doc.format.width, engine_view.doc.format.height];
+
+        Some(
+            page_bounds
+                .merged(&page_bounds.translate(self.current_pos - self.start_pos))
+                .merged(&page_bounds.translate(self.snapped_offset(page_size)))
+                .loosened(Self::START_OUTLINE_WIDTH),
+        )
+    }
+
+    fn draw_on_doc(
+        &self,
+        cx: &mut piet_cairo::CairoRenderContext,
+        engine_view: &EngineView,
+    ) -> anyhow::Result<()> {
+        let page_bounds = match self.page_bounds {
+            Some(page_bounds) => page_bounds,
+            None => return Ok(()),
+        };
+        let page_size = na::vector![engine_view.doc.format.width, engine_view.doc.format.height];
+
+        cx.save().map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        cx.stroke_styled(
+            page_bounds.to_kurbo_rect(),
+            &Self::START_OUTLINE_COLOR,
+            Self::START_OUTLINE_WIDTH,
+            &piet::StrokeStyle::new().dash_pattern(&[12.0, 6.0]),
+        );
+
+        let target_rect = page_bounds
+            .translate(self.snapped_offset(page_size))
+            .to_kurbo_rect();
+        cx.fill(target_rect, &Self::FILL_COLOR);
+        cx.stroke(
+            target_rect,
+            &Self::TARGET_OUTLINE_COLOR,
+            Self::TARGET_OUTLINE_WIDTH,
+        );
+
+        cx.restore().map_err(|e| anyhow::anyhow!("{}", e))?;
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default, rename = "dragproximity_tool")]
 pub struct DragProximityTool {
@@ -218,6 +409,314 @@ impl DrawOnDocBehaviour for OffsetCameraTool {
     }
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, rename = "fill_tool")]
+pub struct FillTool {
+    /// The color the enclosed regions are filled with
+    #[serde(rename = "color")]
+    pub color: Color,
+}
+
+impl Default for FillTool {
+    fn default() -> Self {
+        Self {
+            color: Self::COLOR_DEFAULT,
+        }
+    }
+}
+
+impl FillTool {
+    pub const COLOR_DEFAULT: Color = Color {
+        r: 0.21,
+        g: 0.52,
+        b: 0.89,
+        a: 1.0,
+    };
+
+    /// Fills the region enclosed by strokes around the position with a new shape stroke, inserted below the enclosing strokes.
+    /// Returns the key of the inserted stroke, or None if the position is not enclosed by strokes in the viewport.
+    fn fill_at_pos(
+        &self,
+        pos: na::Vector2<f64>,
+        engine_view: &mut EngineViewMut,
+    ) -> Option<StrokeKey> {
+        let (polygon, enclosing_keys) = engine_view
+            .store
+            .enclosed_region_at_pos(pos, engine_view.camera.viewport())?;
+
+        let mut options = SmoothOptions::default();
+        options.stroke_color = None;
+        options.fill_color = Some(self.color);
+
+        let shapestroke = Stroke::ShapeStroke(ShapeStroke::new(
+            Shape::Polygon(polygon),
+            Style::Smooth(options),
+        ));
+        let key = engine_view.store.insert_stroke(shapestroke, None);
+
+        if let Some(&lowest_key) = enclosing_keys.first() {
+            if let Err(e) = engine_view.store.move_stroke_before(key, lowest_key) {
+                log::error!(
+                    "move_stroke_before() failed in FillTool fill_at_pos(), Err {}",
+                    e
+                );
+            }
+        }
+
+        if let Err(e) = engine_view.store.regenerate_rendering_for_stroke(
+            key,
+            engine_view.camera.viewport(),
+            engine_view.camera.image_scale(),
+        ) {
+            log::error!(
+                "regenerate_rendering_for_stroke() failed after inserting fill stroke, Err {}",
+                e
+            );
+        }
+
+        Some(key)
+    }
+}
+
+/// Edits the nodes of brush and shape strokes. Tapping a stroke starts editing it, its nodes can then be dragged.
+/// Tapping the edited stroke between nodes inserts a node, Ctrl + tapping a node or pressing Delete removes the selected node.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename = "editnodes_tool")]
+pub struct EditNodesTool {
+    /// The stroke whose nodes are edited
+    #[serde(skip)]
+    pub stroke: Option<StrokeKey>,
+    /// The selected node, which is dragged while the pen is down
+    #[serde(skip)]
+    pub selected_node: Option<usize>,
+    #[serde(skip)]
+    dragging: bool,
+}
+
+impl EditNodesTool {
+    /// The radius of the drawn nodes, in surface coords
+    const NODE_RADIUS: f64 = 5.0;
+    /// The distance a node or the stroke can be hit from, in surface coords
+    const HIT_TOLERANCE: f64 = 8.0;
+    const NODE_OUTLINE_WIDTH: f64 = 1.5;
+    const NODE_OUTLINE_COLOR: piet::Color = color::GNOME_BLUES[4];
+    const NODE_FILL_COLOR: piet::Color = color::GNOME_BRIGHTS[1];
+    const SELECTED_NODE_FILL_COLOR: piet::Color = color::GNOME_BLUES[2];
+
+    fn nodes(&self, engine_view: &EngineView) -> Vec<na::Vector2<f64>> {
+        self.stroke
+            .and_then(|key| engine_view.store.get_stroke_ref(key))
+            .map(|stroke| stroke.nodes())
+            .unwrap_or_default()
+    }
+
+    /// The index of the node of the edited stroke at the position
+    fn node_at_pos(&self, pos: na::Vector2<f64>, engine_view: &EngineViewMut) -> Option<usize> {
+        let tolerance = Self::HIT_TOLERANCE / engine_view.camera.total_zoom();
+
+        self.stroke
+            .and_then(|key| engine_view.store.get_stroke_ref(key))
+            .map(|stroke| stroke.nodes())
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            .map(|(index, node)| (index, (node - pos).magnitude()))
+            .filter(|(_, distance)| *distance <= tolerance)
+            .min_by(|first, second| first.1.total_cmp(&second.1))
+            .map(|(index, _)| index)
+    }
+
+    /// Handles the pen going down. Selects and starts dragging a node of the edited stroke, removes or inserts a node,
+    /// or else picks the stroke under the position for editing.
+    fn pen_down(
+        &mut self,
+        pos: na::Vector2<f64>,
+        shortcut_keys: &[ShortcutKey],
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        let tolerance = Self::HIT_TOLERANCE / engine_view.camera.total_zoom();
+
+        if let Some(key) = self.stroke {
+            if let Some(index) = self.node_at_pos(pos, engine_view) {
+                widget_flags.merge_with_other(engine_view.store.record());
+
+                if shortcut_keys.contains(&ShortcutKey::KeyboardCtrl) {
+                    self.remove_node(key, index, engine_view);
+                } else {
+                    self.selected_node = Some(index);
+                    self.dragging = true;
+                }
+                return widget_flags;
+            }
+
+            let hits_stroke = engine_view
+                .store
+                .stroke_hitboxes_contain_coord(engine_view.camera.viewport(), pos)
+                .contains(&key);
+            if hits_stroke {
+                widget_flags.merge_with_other(engine_view.store.record());
+
+                let inserted = engine_view
+                    .store
+                    .get_stroke_mut(key)
+                    .and_then(|stroke| stroke.insert_node_at(pos, tolerance));
+                if let Some(index) = inserted {
+                    self.update_stroke(key, engine_view);
+                    self.selected_node = Some(index);
+                    self.dragging = true;
+                }
+                return widget_flags;
+            }
+        }
+
+        // Pick the topmost stroke with nodes
+        self.stroke = engine_view
+            .store
+            .stroke_hitboxes_contain_coord(engine_view.camera.viewport(), pos)
+            .into_iter()
+            .rev()
+            .find(|&key| {
+                engine_view
+                    .store
+                    .get_stroke_ref(key)
+                    .map(|stroke| !stroke.nodes().is_empty())
+                    .unwrap_or(false)
+            });
+        self.selected_node = None;
+        self.dragging = false;
+
+        widget_flags
+    }
+
+    /// Drags the selected node to the position
+    fn pen_drag(&mut self, pos: na::Vector2<f64>, engine_view: &mut EngineViewMut) {
+        if let (Some(key), Some(index), true) = (self.stroke, self.selected_node, self.dragging) {
+            let pos = engine_view.doc.snap_point(pos);
+
+            if let Some(stroke) = engine_view.store.get_stroke_mut(key) {
+                stroke.move_node(index, pos);
+            }
+            self.update_stroke(key, engine_view);
+        }
+    }
+
+    /// Removes the selected node of the edited stroke
+    fn remove_selected_node(&mut self, engine_view: &mut EngineViewMut) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        if let (Some(key), Some(index)) = (self.stroke, self.selected_node) {
+            widget_flags.merge_with_other(engine_view.store.record());
+            self.remove_node(key, index, engine_view);
+
+            widget_flags.redraw = true;
+            widget_flags.indicate_changed_store = true;
+        }
+
+        widget_flags
+    }
+
+    fn remove_node(&mut self, key: StrokeKey, index: usize, engine_view: &mut EngineViewMut) {
+        let removed = engine_view
+            .store
+            .get_stroke_mut(key)
+            .map(|stroke| stroke.remove_node(index))
+            .unwrap_or(false);
+        if removed {
+            self.update_stroke(key, engine_view);
+        }
+        self.selected_node = None;
+        self.dragging = false;
+    }
+
+    fn update_stroke(&self, key: StrokeKey, engine_view: &mut EngineViewMut) {
+        engine_view.store.record_replace_op(&[key]);
+        engine_view.store.update_geometry_for_stroke(key);
+
+        if let Err(e) = engine_view.store.regenerate_rendering_for_stroke(
+            key,
+            engine_view.camera.viewport(),
+            engine_view.camera.image_scale(),
+        ) {
+            log::error!(
+                "regenerate_rendering_for_stroke() failed while editing nodes, Err {}",
+                e
+            );
+        }
+    }
+
+    fn update_internal_state(&mut self, engine_view: &EngineView) {
+        let stroke_exists = self
+            .stroke
+            .map(|key| !engine_view.store.trashed(key).unwrap_or(true))
+            .unwrap_or(false);
+        if !stroke_exists {
+            self.stroke = None;
+            self.selected_node = None;
+        }
+        let n_nodes = self.nodes(engine_view).len();
+        if self
+            .selected_node
+            .map(|index| index >= n_nodes)
+            .unwrap_or(false)
+        {
+            self.selected_node = None;
+        }
+    }
+}
+
+impl DrawOnDocBehaviour for EditNodesTool {
+    fn bounds_on_doc(&self, engine_view: &EngineView) -> Option<AABB> {
+        let nodes = self.nodes(engine_view);
+        if nodes.is_empty() {
+            return None;
+        }
+
+        Some(
+            nodes
+                .into_iter()
+                .fold(AABB::new_invalid(), |bounds, node| {
+                    bounds.merged(&AABB::from_half_extents(
+                        na::Point2::from(node),
+                        na::Vector2::zeros(),
+                    ))
+                })
+                .loosened(
+                    (Self::NODE_RADIUS + Self::NODE_OUTLINE_WIDTH)
+                        / engine_view.camera.total_zoom(),
+                ),
+        )
+    }
+
+    fn draw_on_doc(
+        &self,
+        cx: &mut piet_cairo::CairoRenderContext,
+        engine_view: &EngineView,
+    ) -> anyhow::Result<()> {
+        cx.save().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let total_zoom = engine_view.camera.total_zoom();
+
+        for (index, node) in self.nodes(engine_view).into_iter().enumerate() {
+            let circle = kurbo::Circle::new(node.to_kurbo_point(), Self::NODE_RADIUS / total_zoom);
+            let fill_color = if self.selected_node == Some(index) {
+                &Self::SELECTED_NODE_FILL_COLOR
+            } else {
+                &Self::NODE_FILL_COLOR
+            };
+
+            cx.fill(circle, fill_color);
+            cx.stroke(
+                circle,
+                &Self::NODE_OUTLINE_COLOR,
+                Self::NODE_OUTLINE_WIDTH / total_zoom,
+            );
+        }
+
+        cx.restore().map_err(|e| anyhow::anyhow!("{}", e))?;
+        Ok(())
+    }
+}
+
 #[derive(
     Debug,
     Clone,
@@ -235,10 +734,18 @@ impl DrawOnDocBehaviour for OffsetCameraTool {
 pub enum ToolsStyle {
     #[serde(rename = "verticalspace")]
     VerticalSpace,
+    #[serde(rename = "horizontalspace")]
+    HorizontalSpace,
+    #[serde(rename = "movepage")]
+    MovePage,
     #[serde(rename = "dragproximity")]
     DragProximity,
     #[serde(rename = "offsetcamera")]
     OffsetCamera,
+    #[serde(rename = "fill")]
+    Fill,
+    #[serde(rename = "editnodes")]
+    EditNodes,
 }
 
 impl Default for ToolsStyle {
@@ -276,10 +783,18 @@ pub struct Tools {
     pub style: ToolsStyle,
     #[serde(rename = "verticalspace_tool")]
     pub verticalspace_tool: VerticalSpaceTool,
+    #[serde(rename = "horizontalspace_tool")]
+    pub horizontalspace_tool: HorizontalSpaceTool,
+    #[serde(rename = "movepage_tool")]
+    pub movepage_tool: MovePageTool,
     #[serde(rename = "dragproximity_tool")]
     pub dragproximity_tool: DragProximityTool,
     #[serde(rename = "offsetcamera_tool")]
     pub offsetcamera_tool: OffsetCameraTool,
+    #[serde(rename = "fill_tool")]
+    pub fill_tool: FillTool,
+    #[serde(rename = "editnodes_tool")]
+    pub editnodes_tool: EditNodesTool,
 
     #[serde(skip)]
     state: ToolsState,
@@ -298,10 +813,13 @@ impl PenBehaviour for Tools {
                 ToolsState::Idle,
                 PenEvent::Down {
                     element,
-                    shortcut_keys: _,
+                    shortcut_keys,
                 },
             ) => {
-                widget_flags.merge_with_other(engine_view.store.record());
+                // The node editor only records when it modifies the stroke
+                if self.style != ToolsStyle::EditNodes {
+                    widget_flags.merge_with_other(engine_view.store.record());
+                }
 
                 match self.style {
                     ToolsStyle::VerticalSpace => {
@@ -312,6 +830,32 @@ impl PenBehaviour for Tools {
                             .store
                             .keys_below_y_pos(self.verticalspace_tool.current_pos_y);
                     }
+                    ToolsStyle::HorizontalSpace => {
+                        self.horizontalspace_tool.start_pos_x = element.pos[0];
+                        self.horizontalspace_tool.current_pos_x = element.pos[0];
+
+                        self.horizontalspace_tool.strokes_right = engine_view
+                            .store
+                            .keys_right_of_x_pos(self.horizontalspace_tool.current_pos_x);
+                    }
+                    ToolsStyle::MovePage => {
+                        self.movepage_tool.start_pos = element.pos;
+                        self.movepage_tool.current_pos = element.pos;
+
+                        self.movepage_tool.page_bounds = engine_view
+                            .doc
+                            .page_index_at(na::Point2::from(element.pos))
+                            .map(|page| engine_view.doc.pages_bounds()[page]);
+                        self.movepage_tool.strokes_on_page = self
+                            .movepage_tool
+                            .page_bounds
+                            .map(|page_bounds| {
+                                engine_view
+                                    .store
+                                    .stroke_keys_as_rendered_intersecting_bounds(page_bounds)
+                            })
+                            .unwrap_or_default();
+                    }
                     ToolsStyle::DragProximity => {
                         self.dragproximity_tool.pos = element.pos;
                         self.dragproximity_tool.offset = na::Vector2::zeros();
@@ -319,6 +863,16 @@ impl PenBehaviour for Tools {
                     ToolsStyle::OffsetCamera => {
                         self.offsetcamera_tool.start = element.pos;
                     }
+                    ToolsStyle::Fill => {
+                        self.fill_tool.fill_at_pos(element.pos, engine_view);
+                    }
+                    ToolsStyle::EditNodes => {
+                        widget_flags.merge_with_other(self.editnodes_tool.pen_down(
+                            element.pos,
+                            &shortcut_keys,
+                            engine_view,
+                        ));
+                    }
                 }
 
                 self.state = ToolsState::Active;
@@ -334,6 +888,18 @@ impl PenBehaviour for Tools {
 
                 PenProgress::InProgress
             }
+            (
+                ToolsState::Idle,
+                PenEvent::KeyPressed {
+                    keyboard_key: KeyboardKey::Delete | KeyboardKey::BackSpace,
+                    ..
+                },
+            ) if self.style == ToolsStyle::EditNodes => {
+                widget_flags
+                    .merge_with_other(self.editnodes_tool.remove_selected_node(engine_view));
+
+                PenProgress::Idle
+            }
             (ToolsState::Idle, _) => PenProgress::Idle,
             (
                 ToolsState::Active,
@@ -361,6 +927,41 @@ impl PenBehaviour for Tools {
 
                         PenProgress::InProgress
                     }
+                    ToolsStyle::HorizontalSpace => {
+                        let x_offset = element.pos[0] - self.horizontalspace_tool.current_pos_x;
+
+                        if x_offset.abs() > HorizontalSpaceTool::X_OFFSET_THRESHOLD {
+                            engine_view.store.translate_strokes(
+                                &self.horizontalspace_tool.strokes_right,
+                                na::vector![x_offset, 0.0],
+                            );
+                            engine_view.store.translate_strokes_images(
+                                &self.horizontalspace_tool.strokes_right,
+                                na::vector![x_offset, 0.0],
+                            );
+
+                            self.horizontalspace_tool.current_pos_x = element.pos[0];
+                        }
+
+                        PenProgress::InProgress
+                    }
+                    ToolsStyle::MovePage => {
+                        let offset = element.pos - self.movepage_tool.current_pos;
+
+                        if offset.magnitude() > MovePageTool::OFFSET_THRESHOLD {
+                            engine_view
+                                .store
+                                .translate_strokes(&self.movepage_tool.strokes_on_page, offset);
+                            engine_view.store.translate_strokes_images(
+                                &self.movepage_tool.strokes_on_page,
+                                offset,
+                            );
+
+                            self.movepage_tool.current_pos = element.pos;
+                        }
+
+                        PenProgress::InProgress
+                    }
                     ToolsStyle::DragProximity => {
                         let offset = element.pos - self.dragproximity_tool.pos;
                         self.dragproximity_tool.offset = offset;
@@ -407,6 +1008,12 @@ impl PenBehaviour for Tools {
                             widget_flags.update_view = true;
                         }
 
+                        PenProgress::InProgress
+                    }
+                    ToolsStyle::Fill => PenProgress::InProgress,
+                    ToolsStyle::EditNodes => {
+                        self.editnodes_tool.pen_drag(element.pos, engine_view);
+
                         PenProgress::InProgress
                     }
                 };
@@ -423,8 +1030,31 @@ impl PenBehaviour for Tools {
                             .store
                             .update_geometry_for_strokes(&self.verticalspace_tool.strokes_below);
                     }
+                    ToolsStyle::HorizontalSpace => {
+                        engine_view
+                            .store
+                            .update_geometry_for_strokes(&self.horizontalspace_tool.strokes_right);
+                    }
+                    ToolsStyle::MovePage => {
+                        let page_size = na::vector![
+                            engine_view.doc.format.width,
+                            engine_view.doc.format.height
+                        ];
+                        // Snap the strokes to the page they were moved to
+                        let snap_offset = self.movepage_tool.snapped_offset(page_size)
+                            - (self.movepage_tool.current_pos - self.movepage_tool.start_pos);
+
+                        engine_view
+                            .store
+                            .translate_strokes(&self.movepage_tool.strokes_on_page, snap_offset);
+                        engine_view
+                            .store
+                            .update_geometry_for_strokes(&self.movepage_tool.strokes_on_page);
+                    }
                     ToolsStyle::DragProximity => {}
                     ToolsStyle::OffsetCamera => {}
+                    ToolsStyle::Fill => {}
+                    ToolsStyle::EditNodes => {}
                 }
                 engine_view.store.regenerate_rendering_in_viewport_threaded(
                     engine_view.tasks_tx.clone(),
@@ -449,6 +1079,7 @@ impl PenBehaviour for Tools {
             }
             (ToolsState::Active, PenEvent::Proximity { .. }) => PenProgress::InProgress,
             (ToolsState::Active, PenEvent::KeyPressed { .. }) => PenProgress::InProgress,
+            (ToolsState::Active, PenEvent::Scroll { .. }) => PenProgress::InProgress,
             (ToolsState::Active, PenEvent::Cancel) => {
                 self.reset();
                 self.state = ToolsState::Idle;
@@ -468,15 +1099,27 @@ impl PenBehaviour for Tools {
 
         (pen_progress, widget_flags)
     }
+
+    fn update_internal_state(&mut self, engine_view: &EngineView) {
+        self.editnodes_tool.update_internal_state(engine_view);
+    }
 }
 
 impl DrawOnDocBehaviour for Tools {
     fn bounds_on_doc(&self, engine_view: &EngineView) -> Option<AABB> {
+        // The nodes are also shown while idle
+        if self.style == ToolsStyle::EditNodes {
+            return self.editnodes_tool.bounds_on_doc(engine_view);
+        }
+
         match self.state {
             ToolsState::Active => match self.style {
                 ToolsStyle::VerticalSpace => self.verticalspace_tool.bounds_on_doc(engine_view),
+                ToolsStyle::HorizontalSpace => self.horizontalspace_tool.bounds_on_doc(engine_view),
+                ToolsStyle::MovePage => self.movepage_tool.bounds_on_doc(engine_view),
                 ToolsStyle::DragProximity => self.dragproximity_tool.bounds_on_doc(engine_view),
                 ToolsStyle::OffsetCamera => self.offsetcamera_tool.bounds_on_doc(engine_view),
+                ToolsStyle::Fill | ToolsStyle::EditNodes => None,
             },
             ToolsState::Idle => None,
         }
@@ -493,12 +1136,22 @@ impl DrawOnDocBehaviour for Tools {
             ToolsStyle::VerticalSpace => {
                 self.verticalspace_tool.draw_on_doc(cx, engine_view)?;
             }
+            ToolsStyle::HorizontalSpace => {
+                self.horizontalspace_tool.draw_on_doc(cx, engine_view)?;
+            }
+            ToolsStyle::MovePage => {
+                self.movepage_tool.draw_on_doc(cx, engine_view)?;
+            }
             ToolsStyle::DragProximity => {
                 self.dragproximity_tool.draw_on_doc(cx, engine_view)?;
             }
             ToolsStyle::OffsetCamera => {
                 self.offsetcamera_tool.draw_on_doc(cx, engine_view)?;
             }
+            ToolsStyle::Fill => {}
+            ToolsStyle::EditNodes => {
+                self.editnodes_tool.draw_on_doc(cx, engine_view)?;
+            }
         }
 
         cx.restore().map_err(|e| anyhow::anyhow!("{}", e))?;
@@ -515,6 +1168,16 @@ impl Tools {
                 self.verticalspace_tool.start_pos_y = 0.0;
                 self.verticalspace_tool.current_pos_y = 0.0;
             }
+            ToolsStyle::HorizontalSpace => {
+                self.horizontalspace_tool.start_pos_x = 0.0;
+                self.horizontalspace_tool.current_pos_x = 0.0;
+            }
+            ToolsStyle::MovePage => {
+                self.movepage_tool.start_pos = na::Vector2::zeros();
+                self.movepage_tool.current_pos = na::Vector2::zeros();
+                self.movepage_tool.page_bounds = None;
+                self.movepage_tool.strokes_on_page = vec![];
+            }
             ToolsStyle::DragProximity => {
                 self.dragproximity_tool.pos = na::Vector2::zeros();
                 self.dragproximity_tool.offset = na::Vector2::zeros();
@@ -522,6 +1185,10 @@ impl Tools {
             ToolsStyle::OffsetCamera => {
                 self.offsetcamera_tool.start = na::Vector2::zeros();
             }
+            ToolsStyle::Fill => {}
+            ToolsStyle::EditNodes => {
+                self.editnodes_tool.dragging = false;
+            }
         }
     }
 }
@@ -1,4 +1,6 @@
+use std::cell::RefCell;
 use std::ops::Range;
+use std::rc::Rc;
 
 use p2d::bounding_volume::{BoundingVolume, AABB};
 use piet::RenderContext;
@@ -10,8 +12,9 @@ use rnote_compose::{color, Transform};
 use serde::{Deserialize, Serialize};
 
 use crate::engine::{EngineView, EngineViewMut};
+use crate::spellcheck::{self, SpellChecker};
 use crate::store::StrokeKey;
-use crate::strokes::textstroke::{RangedTextAttribute, TextAttribute, TextStyle};
+use crate::strokes::textstroke::{TextAttribute, TextStyle};
 use crate::strokes::{Stroke, TextStroke};
 use crate::{AudioPlayer, Camera, DrawOnDocBehaviour, StrokeStore, WidgetFlags};
 
@@ -67,6 +70,11 @@ pub struct Typewriter {
 
     #[serde(skip)]
     state: TypewriterState,
+    #[serde(skip)]
+    spellchecker: Option<Rc<dyn SpellChecker>>,
+    /// The last checked text and its misspelled ranges, so the spellchecker is only queried when the text changes
+    #[serde(skip)]
+    misspelled_cache: RefCell<Option<(String, Vec<Range<usize>>)>>,
 }
 
 impl Default for Typewriter {
@@ -77,6 +85,8 @@ impl Default for Typewriter {
             text_width: 600.0,
 
             state: TypewriterState::default(),
+            spellchecker: None,
+            misspelled_cache: RefCell::new(None),
         }
     }
 }
@@ -165,6 +175,8 @@ impl DrawOnDocBehaviour for Typewriter {
 
                     cx.stroke(text_drawrect, &OUTLINE_COLOR, outline_width);
 
+                    self.draw_misspelled_underlines(cx, textstroke, engine_view.camera)?;
+
                     // Draw the cursor
                     textstroke.text_style.draw_cursor(
                         cx,
@@ -216,6 +228,8 @@ impl DrawOnDocBehaviour for Typewriter {
 
                     cx.stroke(text_drawrect, &OUTLINE_COLOR, outline_width);
 
+                    self.draw_misspelled_underlines(cx, textstroke, engine_view.camera)?;
+
                     // Draw the text selection
                     textstroke.text_style.draw_text_selection(
                         cx,
@@ -404,7 +418,34 @@ impl PenBehaviour for Typewriter {
 
                 PenProgress::InProgress
             }
+            (TypewriterState::Idle, PenEvent::Scroll { delta, .. }) => {
+                self.text_style.font_size = super::scroll_adjusted_size(
+                    self.text_style.font_size,
+                    delta,
+                    TextStyle::FONT_SIZE_MIN,
+                    TextStyle::FONT_SIZE_MAX,
+                );
+
+                widget_flags.refresh_ui = true;
+
+                PenProgress::Idle
+            }
             (TypewriterState::Idle, _) => PenProgress::Idle,
+            (TypewriterState::Start(_), PenEvent::Scroll { delta, .. }) => {
+                self.text_style.font_size = super::scroll_adjusted_size(
+                    self.text_style.font_size,
+                    delta,
+                    TextStyle::FONT_SIZE_MIN,
+                    TextStyle::FONT_SIZE_MAX,
+                );
+
+                widget_flags.redraw = true;
+                widget_flags.refresh_ui = true;
+
+                PenProgress::InProgress
+            }
+            // The font size of texts which are already being modified is changed through the text attributes instead
+            (_, PenEvent::Scroll { .. }) => PenProgress::InProgress,
             (TypewriterState::Start(_), PenEvent::Proximity { .. } | PenEvent::Up { .. }) => {
                 PenProgress::InProgress
             }
@@ -569,6 +610,7 @@ impl PenBehaviour for Typewriter {
                         widget_flags.merge_with_other(store.record());
 
                         store.update_geometry_for_stroke(*stroke_key);
+                        store.touch_strokes(&[*stroke_key]);
                         store.regenerate_rendering_for_stroke_threaded(
                             engine_view.tasks_tx.clone(),
                             *stroke_key,
@@ -636,7 +678,7 @@ impl PenBehaviour for Typewriter {
                         KeyboardKey::NavLeft => {
                             if shortcut_keys.contains(&ShortcutKey::KeyboardShift) {
                                 let mut new_cursor = cursor.clone();
-                                textstroke.move_cursor_back(&mut new_cursor);
+                                textstroke.move_cursor_left(&mut new_cursor);
 
                                 Some(TypewriterState::Selecting {
                                     stroke_key: *stroke_key,
@@ -645,7 +687,7 @@ impl PenBehaviour for Typewriter {
                                     finished: false,
                                 })
                             } else {
-                                textstroke.move_cursor_back(cursor);
+                                textstroke.move_cursor_left(cursor);
 
                                 None
                             }
@@ -653,7 +695,7 @@ impl PenBehaviour for Typewriter {
                         KeyboardKey::NavRight => {
                             if shortcut_keys.contains(&ShortcutKey::KeyboardShift) {
                                 let mut new_cursor = cursor.clone();
-                                textstroke.move_cursor_forward(&mut new_cursor);
+                                textstroke.move_cursor_right(&mut new_cursor);
 
                                 Some(TypewriterState::Selecting {
                                     stroke_key: *stroke_key,
@@ -662,7 +704,7 @@ impl PenBehaviour for Typewriter {
                                     finished: false,
                                 })
                             } else {
-                                textstroke.move_cursor_forward(cursor);
+                                textstroke.move_cursor_right(cursor);
 
                                 None
                             }
@@ -814,6 +856,7 @@ impl PenBehaviour for Typewriter {
                         widget_flags.merge_with_other(store.record());
 
                         store.update_geometry_for_stroke(*stroke_key);
+                        store.touch_strokes(&[*stroke_key]);
                         store.regenerate_rendering_for_stroke_threaded(
                             engine_view.tasks_tx.clone(),
                             *stroke_key,
@@ -850,7 +893,7 @@ impl PenBehaviour for Typewriter {
                         }
                         KeyboardKey::NavLeft => {
                             if shortcut_keys.contains(&ShortcutKey::KeyboardShift) {
-                                textstroke.move_cursor_back(cursor);
+                                textstroke.move_cursor_left(cursor);
                                 false
                             } else {
                                 true
@@ -858,7 +901,7 @@ impl PenBehaviour for Typewriter {
                         }
                         KeyboardKey::NavRight => {
                             if shortcut_keys.contains(&ShortcutKey::KeyboardShift) {
-                                textstroke.move_cursor_forward(cursor);
+                                textstroke.move_cursor_right(cursor);
                                 false
                             } else {
                                 true
@@ -1249,6 +1292,53 @@ impl Typewriter {
     // The size of the translate node, located in the upper left corner
     const ADJUST_TEXT_WIDTH_NODE_SIZE: na::Vector2<f64> = na::vector![18.0, 18.0];
 
+    /// Sets the spellchecker that is queried for the text being modified. None disables spellchecking
+    pub fn set_spellchecker(&mut self, spellchecker: Option<Rc<dyn SpellChecker>>) {
+        self.spellchecker = spellchecker;
+        *self.misspelled_cache.get_mut() = None;
+    }
+
+    pub fn spellchecker(&self) -> Option<&Rc<dyn SpellChecker>> {
+        self.spellchecker.as_ref()
+    }
+
+    /// The misspelled ranges of the text. The spellchecker is only queried again if the text has changed
+    pub fn misspelled_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        let spellchecker = match &self.spellchecker {
+            Some(spellchecker) => spellchecker,
+            None => return vec![],
+        };
+        let mut misspelled_cache = self.misspelled_cache.borrow_mut();
+
+        match &*misspelled_cache {
+            Some((checked_text, misspelled_ranges)) if checked_text == text => {
+                misspelled_ranges.clone()
+            }
+            _ => {
+                let misspelled_ranges = spellcheck::misspelled_ranges(spellchecker.as_ref(), text);
+                *misspelled_cache = Some((text.to_string(), misspelled_ranges.clone()));
+                misspelled_ranges
+            }
+        }
+    }
+
+    fn draw_misspelled_underlines(
+        &self,
+        cx: &mut impl RenderContext,
+        textstroke: &TextStroke,
+        camera: &Camera,
+    ) -> anyhow::Result<()> {
+        let misspelled_ranges = self.misspelled_ranges(&textstroke.text);
+
+        textstroke.text_style.draw_misspelled_underlines(
+            cx,
+            textstroke.text.clone(),
+            &misspelled_ranges,
+            &textstroke.transform,
+            camera,
+        )
+    }
+
     fn start_audio(keyboard_key: KeyboardKey, audioplayer: &mut Option<AudioPlayer>) {
         if let Some(audioplayer) = audioplayer {
             audioplayer.play_typewriter_key_sound(keyboard_key);
@@ -1387,6 +1477,7 @@ impl Typewriter {
                     textstroke.insert_text_after_cursor(text.as_str(), cursor);
 
                     engine_view.store.update_geometry_for_stroke(*stroke_key);
+                    engine_view.store.touch_strokes(&[*stroke_key]);
                     engine_view.store.regenerate_rendering_for_stroke_threaded(
                         engine_view.tasks_tx.clone(),
                         *stroke_key,
@@ -1421,6 +1512,7 @@ impl Typewriter {
                     );
 
                     engine_view.store.update_geometry_for_stroke(*stroke_key);
+                    engine_view.store.touch_strokes(&[*stroke_key]);
                     engine_view.store.regenerate_rendering_for_stroke_threaded(
                         engine_view.tasks_tx.clone(),
                         *stroke_key,
@@ -1532,13 +1624,7 @@ impl Typewriter {
             if let Some(Stroke::TextStroke(textstroke)) =
                 engine_view.store.get_stroke_mut(stroke_key)
             {
-                textstroke
-                    .text_style
-                    .ranged_text_attributes
-                    .push(RangedTextAttribute {
-                        attribute: text_attribute,
-                        range: selection_range,
-                    });
+                textstroke.set_attr_for_range(text_attribute, selection_range);
 
                 engine_view.store.update_geometry_for_stroke(stroke_key);
                 if let Err(e) = engine_view.store.regenerate_rendering_for_stroke(
@@ -1557,3 +1643,35 @@ impl Typewriter {
         widget_flags
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn misspelled_ranges_cached() {
+        #[derive(Debug)]
+        struct TestSpellChecker;
+
+        impl SpellChecker for TestSpellChecker {
+            fn check_word(&self, word: &str) -> bool {
+                ["this", "is", "spelled"].contains(&word.to_lowercase().as_str())
+            }
+        }
+
+        let mut typewriter = Typewriter::default();
+        assert!(typewriter.misspelled_ranges("wrnog").is_empty());
+
+        typewriter.set_spellchecker(Some(Rc::new(TestSpellChecker)));
+        assert_eq!(
+            typewriter.misspelled_ranges("This is mispelled, 2nd"),
+            vec![8..17]
+        );
+        // Served from the cache
+        assert_eq!(
+            typewriter.misspelled_ranges("This is mispelled, 2nd"),
+            vec![8..17]
+        );
+        assert!(typewriter.misspelled_ranges("This is spelled").is_empty());
+    }
+}
@@ -0,0 +1,38 @@
+/// Presents the document one page at a time. Editing is locked while presenting.
+#[derive(Debug, Clone, Default)]
+pub struct Presentation {
+    active: bool,
+    current_page: usize,
+}
+
+impl Presentation {
+    /// Wether the presentation is running
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// The index of the presented page
+    pub fn current_page(&self) -> usize {
+        self.current_page
+    }
+
+    /// Starts the presentation at the given page
+    pub fn start(&mut self, page: usize) {
+        self.active = true;
+        self.current_page = page;
+    }
+
+    pub fn stop(&mut self) {
+        self.active = false;
+    }
+
+    /// Moves to the given page. Returns the new current page, or None if the page is out of bounds or the presentation is not running.
+    pub fn go_to_page(&mut self, page: usize, n_pages: usize) -> Option<usize> {
+        if !self.active || page >= n_pages {
+            return None;
+        }
+        self.current_page = page;
+
+        Some(self.current_page)
+    }
+}
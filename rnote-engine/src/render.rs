@@ -4,13 +4,14 @@ use std::ops::Deref;
 use anyhow::Context;
 use gtk4::{gdk, gio, glib, graphene, gsk, prelude::*, Snapshot};
 use image::io::Reader;
-use image::GenericImageView;
+use image::AnimationDecoder;
 use p2d::bounding_volume::{BoundingVolume, AABB};
 use piet::RenderContext;
 use rnote_compose::shapes::{Rectangle, ShapeBehaviour};
 use rnote_compose::transform::TransformBehaviour;
 use serde::{Deserialize, Serialize};
 
+use crate::fileerror::FileError;
 use crate::utils::{base64, GrapheneRectHelpers};
 use crate::DrawBehaviour;
 use rnote_compose::helpers::{AABBHelpers, Vector2Helpers};
@@ -188,6 +189,9 @@ impl TransformBehaviour for Image {
 }
 
 impl Image {
+    /// The JPEG qualities which are tried in order when encoding with a size target
+    pub const JPEG_SIZE_TARGET_QUALITIES: [u8; 5] = [90, 75, 60, 45, 30];
+
     pub fn assert_valid(&self) -> anyhow::Result<()> {
         self.rect.bounds().assert_valid()?;
 
@@ -204,10 +208,222 @@ impl Image {
     }
 
     pub fn try_from_encoded_bytes(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        if Self::is_heif(bytes) {
+            return Self::try_from_heif_bytes(bytes);
+        }
+
         let reader = Reader::new(io::Cursor::new(bytes)).with_guessed_format()?;
         Ok(Image::from(reader.decode()?))
     }
 
+    /// Decodes the frame with the given index from a ( possibly animated ) encoded image.
+    /// Still images only have a single frame with index 0.
+    pub fn try_from_encoded_bytes_w_frame(
+        bytes: &[u8],
+        frame_index: usize,
+    ) -> Result<Self, anyhow::Error> {
+        if Self::is_animated_webp(bytes) {
+            let decoder = image::codecs::webp::WebPDecoder::new(io::Cursor::new(bytes))?;
+
+            let frame = decoder.into_frames().nth(frame_index).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "frame with index {} does not exist in try_from_encoded_bytes_w_frame()",
+                    frame_index
+                )
+            })??;
+
+            return Ok(Image::from(image::DynamicImage::ImageRgba8(
+                frame.into_buffer(),
+            )));
+        }
+
+        match image::guess_format(bytes)? {
+            image::ImageFormat::Gif => {
+                let decoder = image::codecs::gif::GifDecoder::new(io::Cursor::new(bytes))?;
+
+                let frame = decoder.into_frames().nth(frame_index).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "frame with index {} does not exist in try_from_encoded_bytes_w_frame()",
+                        frame_index
+                    )
+                })??;
+
+                Ok(Image::from(image::DynamicImage::ImageRgba8(
+                    frame.into_buffer(),
+                )))
+            }
+            _ if frame_index == 0 => Self::try_from_encoded_bytes(bytes),
+            _ => Err(anyhow::anyhow!(
+                "frame with index {} requested for a still image in try_from_encoded_bytes_w_frame()",
+                frame_index
+            )),
+        }
+    }
+
+    /// The number of animation frames of the encoded image. Still images have one frame.
+    /// Only the container structure is walked without decoding the frames, so it is cheap even for large animations.
+    pub fn count_animation_frames(bytes: &[u8]) -> Result<usize, anyhow::Error> {
+        if Self::is_animated_webp(bytes) {
+            return Self::count_webp_animation_frames(bytes);
+        }
+
+        match image::guess_format(bytes) {
+            Ok(image::ImageFormat::Gif) => Self::count_gif_frames(bytes),
+            _ => Ok(1),
+        }
+    }
+
+    /// Counts the `ANMF` chunks of an animated WebP image
+    fn count_webp_animation_frames(bytes: &[u8]) -> Result<usize, anyhow::Error> {
+        // Skips the RIFF header
+        let mut pos = 12;
+        let mut n_frames = 0;
+
+        while pos + 8 <= bytes.len() {
+            let chunk_size = u32::from_le_bytes([
+                bytes[pos + 4],
+                bytes[pos + 5],
+                bytes[pos + 6],
+                bytes[pos + 7],
+            ]) as usize;
+
+            if &bytes[pos..pos + 4] == b"ANMF" {
+                n_frames += 1;
+            }
+
+            // Chunks are padded to an even size
+            pos += 8 + chunk_size + chunk_size % 2;
+        }
+
+        if n_frames == 0 {
+            return Err(anyhow::anyhow!(
+                "animated WebP image has no frames in count_webp_animation_frames()"
+            ));
+        }
+
+        Ok(n_frames)
+    }
+
+    /// Counts the image descriptor blocks of a GIF image
+    fn count_gif_frames(bytes: &[u8]) -> Result<usize, anyhow::Error> {
+        const GIF_EXTENSION_INTRODUCER: u8 = 0x21;
+        const GIF_IMAGE_SEPARATOR: u8 = 0x2C;
+        const GIF_TRAILER: u8 = 0x3B;
+
+        let truncated_err =
+            || anyhow::anyhow!("GIF image is truncated or corrupted in count_gif_frames()");
+
+        // The size of the color table that follows, if its flag is set in the packed fields
+        let color_table_size = |packed_fields: u8| {
+            if packed_fields & 0x80 != 0 {
+                3 * (1 << ((packed_fields & 0x07) + 1))
+            } else {
+                0
+            }
+        };
+
+        // Skips data sub-blocks, returning the position after the terminating zero size block
+        let skip_sub_blocks = |mut pos: usize| -> anyhow::Result<usize> {
+            loop {
+                let block_size = *bytes.get(pos).ok_or_else(truncated_err)? as usize;
+                pos += 1;
+                if block_size == 0 {
+                    return Ok(pos);
+                }
+                pos += block_size;
+            }
+        };
+
+        // The header and the logical screen descriptor
+        let mut pos = 13 + color_table_size(*bytes.get(10).ok_or_else(truncated_err)?);
+        let mut n_frames = 0;
+
+        loop {
+            match *bytes.get(pos).ok_or_else(truncated_err)? {
+                GIF_EXTENSION_INTRODUCER => {
+                    // Skips the introducer and the label
+                    pos = skip_sub_blocks(pos + 2)?;
+                }
+                GIF_IMAGE_SEPARATOR => {
+                    n_frames += 1;
+                    let packed_fields = *bytes.get(pos + 9).ok_or_else(truncated_err)?;
+                    // Skips the image descriptor, the local color table and the LZW minimum code size
+                    pos = skip_sub_blocks(pos + 10 + color_table_size(packed_fields) + 1)?;
+                }
+                GIF_TRAILER => break,
+                _ => return Err(truncated_err()),
+            }
+        }
+
+        Ok(n_frames.max(1))
+    }
+
+    /// Checks the WebP extended header for the animation flag
+    fn is_animated_webp(bytes: &[u8]) -> bool {
+        bytes.len() >= 21
+            && &bytes[0..4] == b"RIFF"
+            && &bytes[8..16] == b"WEBPVP8X"
+            && bytes[20] & 0x02 != 0
+    }
+
+    /// Checks the ISO base media file header for a HEIF brand ( HEIC / HEIF photos, e.g. from iPhones )
+    pub fn is_heif(bytes: &[u8]) -> bool {
+        const HEIF_BRANDS: [&[u8]; 8] = [
+            b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"mif1", b"msf1",
+        ];
+
+        bytes.len() >= 12 && &bytes[4..8] == b"ftyp" && HEIF_BRANDS.contains(&&bytes[8..12])
+    }
+
+    #[cfg(feature = "heif")]
+    fn try_from_heif_bytes(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        use libheif_rs::{ColorSpace, HeifContext, RgbChroma};
+
+        let heif_cx = HeifContext::read_from_bytes(bytes)?;
+        let handle = heif_cx.primary_image_handle()?;
+        let heif_image = handle.decode(ColorSpace::Rgb(RgbChroma::Rgba), false)?;
+
+        let pixel_width = heif_image.width(libheif_rs::Channel::Interleaved)?;
+        let pixel_height = heif_image.height(libheif_rs::Channel::Interleaved)?;
+        let planes = heif_image.planes();
+        let interleaved = planes.interleaved.ok_or_else(|| {
+            anyhow::anyhow!("decoded heif image has no interleaved plane in try_from_heif_bytes()")
+        })?;
+
+        // Remove the stride padding at the end of each row
+        let data = interleaved
+            .data
+            .chunks(interleaved.stride)
+            .take(pixel_height as usize)
+            .flat_map(|row| &row[..pixel_width as usize * 4])
+            .copied()
+            .collect::<Vec<u8>>();
+
+        let imgbuf =
+            image::RgbaImage::from_vec(pixel_width, pixel_height, data).ok_or_else(|| {
+                anyhow::anyhow!("RgbaImage::from_vec() failed in try_from_heif_bytes()")
+            })?;
+
+        Ok(Image::from(image::DynamicImage::ImageRgba8(imgbuf)))
+    }
+
+    #[cfg(not(feature = "heif"))]
+    fn try_from_heif_bytes(_bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        Err(FileError::UnsupportedFormat(String::from(
+            "importing HEIC / HEIF images is not supported, rnote-engine was built without the `heif` feature",
+        ))
+        .into())
+    }
+
+    /// Multiplies the image with the given opacity. All supported memory formats are premultiplied, so every channel is scaled.
+    pub fn apply_opacity(&mut self, opacity: f64) {
+        let opacity = opacity.clamp(0.0, 1.0);
+
+        for byte in self.data.iter_mut() {
+            *byte = (f64::from(*byte) * opacity).round() as u8;
+        }
+    }
+
     pub fn convert_to_rgba8pre(&mut self) -> anyhow::Result<()> {
         self.assert_valid()?;
 
@@ -217,33 +433,21 @@ impl Image {
                 return Ok(());
             }
             ImageMemoryFormat::B8g8r8a8Premultiplied => {
-                let imgbuf_bgra8 = image::ImageBuffer::<image::Bgra<u8>, Vec<u8>>::from_vec(
-                    self.pixel_width,
-                    self.pixel_height,
-                    self.data.clone(),
-                )
-                .ok_or_else(|| {
-                    anyhow::anyhow!(
-                    "RgbaImage::from_vec() failed in Image to_imgbuf() for image with Format {:?}",
-                    self.memory_format
-                )
-                })?;
-
-                let dynamic_image = image::DynamicImage::ImageBgra8(imgbuf_bgra8).into_rgba8();
-
-                *self = Self {
-                    pixel_width: self.pixel_width,
-                    pixel_height: self.pixel_height,
-                    data: dynamic_image.into_vec(),
-                    rect: self.rect,
-                    memory_format: ImageMemoryFormat::R8g8b8a8Premultiplied,
-                };
+                Self::swap_red_blue_channels(&mut self.data);
+                self.memory_format = ImageMemoryFormat::R8g8b8a8Premultiplied;
             }
         }
 
         Ok(())
     }
 
+    /// Swaps the red and blue channels of 4 byte pixels, converting between RGBA and BGRA
+    fn swap_red_blue_channels(data: &mut [u8]) {
+        for pixel in data.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+
     pub fn to_imgbuf(self) -> Result<image::ImageBuffer<image::Rgba<u8>, Vec<u8>>, anyhow::Error> {
         self.assert_valid()?;
 
@@ -258,19 +462,17 @@ impl Image {
                     })
             }
             ImageMemoryFormat::B8g8r8a8Premultiplied => {
-                let imgbuf_bgra8 = image::ImageBuffer::<image::Bgra<u8>, Vec<u8>>::from_vec(
-                    self.pixel_width,
-                    self.pixel_height,
-                    self.data,
-                )
-                .ok_or_else(|| {
-                    anyhow::anyhow!(
+                let mut data = self.data;
+                Self::swap_red_blue_channels(&mut data);
+
+                image::RgbaImage::from_vec(self.pixel_width, self.pixel_height, data).ok_or_else(
+                    || {
+                        anyhow::anyhow!(
                     "RgbaImage::from_vec() failed in Image to_imgbuf() for image with Format {:?}",
                     self.memory_format
                 )
-                })?;
-
-                Ok(image::DynamicImage::ImageBgra8(imgbuf_bgra8).into_rgba8())
+                    },
+                )
             }
         }
     }
@@ -287,12 +489,28 @@ impl Image {
                 .context("image.to_imgbuf() failed in image_to_bytes()")?,
         );
         dynamic_image
-            .write_to(&mut bytes_buf, format)
+            .write_to(&mut io::Cursor::new(&mut bytes_buf), format)
             .context("dynamic_image.write_to() failed in image_to_bytes()")?;
 
         Ok(bytes_buf)
     }
 
+    /// Encodes the image as JPEG, lowering the quality step by step until the encoded size is below the given byte budget.
+    /// Returns None if even the lowest quality exceeds the budget.
+    pub fn encode_jpeg_w_size_target(&self, max_bytes: usize) -> anyhow::Result<Option<Vec<u8>>> {
+        for quality in Self::JPEG_SIZE_TARGET_QUALITIES {
+            let bytes = self
+                .clone()
+                .into_encoded_bytes(image::ImageOutputFormat::Jpeg(quality))?;
+
+            if bytes.len() <= max_bytes {
+                return Ok(Some(bytes));
+            }
+        }
+
+        Ok(None)
+    }
+
     pub fn to_memtexture(&self) -> Result<gdk::MemoryTexture, anyhow::Error> {
         self.assert_valid()?;
 
@@ -798,3 +1016,39 @@ impl Svg {
         Ok(new_caironode)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_gif_frames() {
+        let mut bytes = vec![];
+        {
+            let mut encoder = image::codecs::gif::GifEncoder::new(&mut bytes);
+            for i in 0..3_u8 {
+                encoder
+                    .encode_frame(image::Frame::new(image::RgbaImage::from_pixel(
+                        4,
+                        4,
+                        image::Rgba([i * 80, 0, 0, 255]),
+                    )))
+                    .unwrap();
+            }
+        }
+
+        assert_eq!(Image::count_animation_frames(&bytes).unwrap(), 3);
+        assert!(Image::count_animation_frames(&bytes[..bytes.len() - 4]).is_err());
+    }
+
+    #[test]
+    fn count_still_image_frames() {
+        let bytes = Image::from(image::DynamicImage::ImageRgba8(
+            image::RgbaImage::from_pixel(4, 4, image::Rgba([0, 0, 0, 255])),
+        ))
+        .into_encoded_bytes(image::ImageOutputFormat::Png)
+        .unwrap();
+
+        assert_eq!(Image::count_animation_frames(&bytes).unwrap(), 1);
+    }
+}
@@ -0,0 +1,17 @@
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A spell checker the typewriter queries for the text it is modifying. Implemented by the frontend, e.g. backed by enchant.
+pub trait SpellChecker: std::fmt::Debug {
+    /// Wether the word is spelled correctly
+    fn check_word(&self, word: &str) -> bool;
+}
+
+/// Finds the misspelled words in the text and returns their byte ranges. Words containing digits are not checked.
+pub fn misspelled_ranges(spellchecker: &dyn SpellChecker, text: &str) -> Vec<Range<usize>> {
+    text.unicode_word_indices()
+        .filter(|(_, word)| !word.chars().any(|c| c.is_numeric()) && !spellchecker.check_word(word))
+        .map(|(start, word)| start..start + word.len())
+        .collect()
+}
@@ -0,0 +1,142 @@
+use super::{StrokeKey, StrokeStore};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// The author of strokes, e.g. a participant of a co-authored whiteboard
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default, rename = "author")]
+pub struct Author {
+    /// The name of the author
+    #[serde(rename = "name")]
+    pub name: String,
+    /// The device the author used
+    #[serde(rename = "device")]
+    pub device: Option<String>,
+}
+
+/// Tracks who modified the stroke last, and when.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename = "author_component")]
+pub struct AuthorComponent {
+    /// None when no author was set while modifying the stroke
+    #[serde(rename = "author")]
+    pub author: Option<Author>,
+    #[serde(rename = "modified")]
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// Systems that are related to the authorship of strokes
+impl StrokeStore {
+    /// The author that is recorded for strokes modified locally
+    pub fn local_author(&self) -> Option<&Author> {
+        self.local_author.as_ref()
+    }
+
+    /// Sets the author that is recorded for strokes modified locally. `None` stops recording authors, only the modification time is recorded then
+    pub fn set_local_author(&mut self, author: Option<Author>) {
+        self.local_author = author;
+    }
+
+    /// The author who modified the stroke last. Strokes loaded from older files have no author
+    pub fn stroke_author(&self, key: StrokeKey) -> Option<&Author> {
+        self.author_components
+            .get(key)
+            .and_then(|author_comp| author_comp.author.as_ref())
+    }
+
+    /// When the stroke was modified last
+    pub fn stroke_modified(&self, key: StrokeKey) -> Option<DateTime<Utc>> {
+        self.author_components
+            .get(key)
+            .and_then(|author_comp| author_comp.modified)
+    }
+
+    /// Records the strokes as modified now by the local author
+    pub fn touch_strokes(&mut self, keys: &[StrokeKey]) {
+        let author_comp = Arc::new(AuthorComponent {
+            author: self.local_author.clone(),
+            modified: Some(Utc::now()),
+        });
+
+        for &key in keys {
+            if self.stroke_components.contains_key(key) {
+                Arc::make_mut(&mut self.author_components).insert(key, Arc::clone(&author_comp));
+            }
+        }
+    }
+
+    /// The distinct authors of the strokes, excluding trashed strokes
+    pub fn stroke_authors(&self) -> Vec<Author> {
+        let mut authors = vec![];
+
+        for key in self.stroke_keys_unordered() {
+            if let Some(author) = self.stroke_author(key) {
+                if !authors.contains(author) {
+                    authors.push(author.clone());
+                }
+            }
+        }
+
+        authors
+    }
+
+    /// The keys of the strokes last modified by the author, excluding trashed strokes. Unordered
+    pub fn keys_by_author(&self, author: &Author) -> Vec<StrokeKey> {
+        self.stroke_keys_unordered()
+            .into_iter()
+            .filter(|&key| self.stroke_author(key) == Some(author))
+            .collect()
+    }
+
+    /// The keys of the strokes modified after the given time, excluding trashed strokes. Unordered
+    pub fn keys_modified_since(&self, since: DateTime<Utc>) -> Vec<StrokeKey> {
+        self.stroke_keys_unordered()
+            .into_iter()
+            .filter(|&key| matches!(self.stroke_modified(key), Some(modified) if modified > since))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strokes::Stroke;
+
+    #[test]
+    fn authors_and_modification_times() {
+        let mut store = StrokeStore::default();
+        let line = || Stroke::line_fixture(na::vector![0.0, 0.0], na::vector![100.0, 50.0]);
+        let alice = Author {
+            name: String::from("Alice"),
+            device: None,
+        };
+        let bob = Author {
+            name: String::from("Bob"),
+            device: Some(String::from("tablet")),
+        };
+
+        let anonymous = store.insert_stroke(line(), None);
+        store.set_local_author(Some(alice.clone()));
+        let first = store.insert_stroke(line(), None);
+        let second = store.insert_stroke(line(), None);
+        assert_eq!(store.stroke_author(anonymous), None);
+        assert!(store.stroke_modified(anonymous).is_some());
+
+        let since = store.stroke_modified(second).unwrap();
+        store.set_local_author(Some(bob.clone()));
+        store.translate_strokes(&[second], na::vector![10.0, 0.0]);
+
+        assert_eq!(store.keys_by_author(&alice), vec![first]);
+        assert_eq!(store.keys_by_author(&bob), vec![second]);
+        assert_eq!(store.stroke_authors().len(), 2);
+        assert!(!store.keys_modified_since(since).contains(&first));
+        assert_eq!(
+            store
+                .keys_modified_since(since - chrono::Duration::seconds(1))
+                .len(),
+            3
+        );
+    }
+}
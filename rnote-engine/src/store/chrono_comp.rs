@@ -4,6 +4,7 @@ use std::sync::Arc;
 use p2d::bounding_volume::AABB;
 use rayon::slice::ParallelSliceMut;
 use serde::{Deserialize, Serialize};
+use slotmap::SecondaryMap;
 
 use super::{StrokeKey, StrokeStore};
 
@@ -83,12 +84,37 @@ impl ChronoComponent {
     }
 }
 
+/// A change of the draw order of strokes within their layers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZOrderChange {
+    /// Draw the strokes on top of all other strokes in their layer
+    BringToFront,
+    /// Draw the strokes underneath all other strokes in their layer
+    SendToBack,
+    /// Draw the strokes one step higher
+    Raise,
+    /// Draw the strokes one step lower
+    Lower,
+}
+
+/// The cached chronological order of the strokes
+#[derive(Debug, Clone, Default)]
+pub(super) struct ChronoOrder {
+    /// the keys in chronological order
+    keys: Vec<StrokeKey>,
+    /// the position of the keys in the chronological order
+    ranks: SecondaryMap<StrokeKey, usize>,
+}
+
 /// Systems that are related to their chronological ordering.
 impl StrokeStore {
     pub fn update_chrono_to_last(&mut self, key: StrokeKey) {
         if let Some(chrono_comp) = Arc::make_mut(&mut self.chrono_components).get_mut(key) {
             self.chrono_counter += 1;
             Arc::make_mut(chrono_comp).t = self.chrono_counter;
+            self.invalidate_chrono_order();
+            // The stroke is now drawn above the others
+            self.add_change_for_stroke(key);
         } else {
             log::debug!(
                 "get chrono_comp in set_chrono_to_last() returned None for stroke with key {:?}",
@@ -97,35 +123,182 @@ impl StrokeStore {
         }
     }
 
-    /// Returns the keys in chronological order, as in first: gets drawn first, last: gets drawn last
-    pub fn keys_sorted_chrono(&self) -> Vec<StrokeKey> {
-        let chrono_components = &self.chrono_components;
+    /// The layer of the stroke
+    pub fn stroke_layer(&self, key: StrokeKey) -> Option<StrokeLayer> {
+        self.chrono_components
+            .get(key)
+            .map(|chrono_comp| chrono_comp.layer)
+    }
 
-        let mut keys = self.stroke_components.keys().collect::<Vec<StrokeKey>>();
+    /// Returns the (not trashed) stroke keys in draw order, grouped by their layers.
+    /// The groups are ordered from the bottom-most to the top-most layer, and the keys within a group in the order they are drawn.
+    pub fn draw_order(&self) -> Vec<(StrokeLayer, Vec<StrokeKey>)> {
+        let mut groups: Vec<(StrokeLayer, Vec<StrokeKey>)> = vec![];
 
-        keys.par_sort_unstable_by(|&first, &second| {
-            if let (Some(first_chrono), Some(second_chrono)) =
-                (chrono_components.get(first), chrono_components.get(second))
-            {
-                let layer_order = first_chrono.layer.cmp(&second_chrono.layer);
+        for key in self.stroke_keys_as_rendered() {
+            let layer = self.stroke_layer(key).unwrap_or_default();
 
-                if layer_order != std::cmp::Ordering::Equal {
-                    layer_order
-                } else {
-                    first_chrono.t.cmp(&second_chrono.t)
+            match groups.last_mut() {
+                Some((last_layer, keys)) if *last_layer == layer => keys.push(key),
+                _ => groups.push((layer, vec![key])),
+            }
+        }
+
+        groups
+    }
+
+    /// Moves the stroke in the draw order directly before the other stroke, so that it gets drawn underneath it.
+    /// The stroke is moved into the layer of the other stroke.
+    pub fn move_stroke_before(&mut self, key: StrokeKey, other: StrokeKey) -> anyhow::Result<()> {
+        self.move_stroke_relative_to(key, other, false)
+    }
+
+    /// Moves the stroke in the draw order directly after the other stroke, so that it gets drawn on top of it.
+    /// The stroke is moved into the layer of the other stroke.
+    pub fn move_stroke_after(&mut self, key: StrokeKey, other: StrokeKey) -> anyhow::Result<()> {
+        self.move_stroke_relative_to(key, other, true)
+    }
+
+    fn move_stroke_relative_to(
+        &mut self,
+        key: StrokeKey,
+        other: StrokeKey,
+        after: bool,
+    ) -> anyhow::Result<()> {
+        if key == other {
+            return Err(anyhow::anyhow!(
+                "can't move stroke with key {:?} relative to itself",
+                key
+            ));
+        }
+        if self.stroke_layer(key).is_none() {
+            return Err(anyhow::anyhow!(
+                "no chrono component for stroke with key {:?}",
+                key
+            ));
+        }
+        let layer = self.stroke_layer(other).ok_or_else(|| {
+            anyhow::anyhow!("no chrono component for other stroke with key {:?}", other)
+        })?;
+
+        // Reorder the strokes of the target layer, then assign new times in that order.
+        // Only the order within a layer is relevant, so the other layers are not affected.
+        let mut layer_keys = self
+            .keys_sorted_chrono_iter()
+            .filter(|&k| k != key && self.stroke_layer(k) == Some(layer))
+            .collect::<Vec<StrokeKey>>();
+        let other_pos = layer_keys.iter().position(|&k| k == other).ok_or_else(|| {
+            anyhow::anyhow!("other stroke with key {:?} not found in its layer", other)
+        })?;
+        layer_keys.insert(if after { other_pos + 1 } else { other_pos }, key);
+
+        let chrono_components = Arc::make_mut(&mut self.chrono_components);
+        for layer_key in layer_keys {
+            if let Some(chrono_comp) = chrono_components.get_mut(layer_key) {
+                self.chrono_counter += 1;
+                let chrono_comp = Arc::make_mut(chrono_comp);
+                chrono_comp.t = self.chrono_counter;
+                chrono_comp.layer = layer;
+            }
+        }
+        self.invalidate_chrono_order();
+
+        Ok(())
+    }
+
+    /// Changes the draw order of the given strokes. The strokes stay in their layers, only the order within a layer is changed.
+    pub fn change_z_order(&mut self, keys: &[StrokeKey], change: ZOrderChange) {
+        let mut layers = keys
+            .iter()
+            .filter_map(|&key| self.stroke_layer(key))
+            .collect::<Vec<StrokeLayer>>();
+        layers.sort_unstable();
+        layers.dedup();
+
+        for layer in layers {
+            // Trashed strokes are not drawn, so they are not considered when moving strokes past others
+            let mut layer_keys = self
+                .stroke_keys_as_rendered()
+                .into_iter()
+                .filter(|&k| self.stroke_layer(k) == Some(layer))
+                .collect::<Vec<StrokeKey>>();
+            let is_moved = |key: &StrokeKey| keys.contains(key);
+
+            match change {
+                ZOrderChange::BringToFront => {
+                    // stable, so the moved strokes keep their order among each other
+                    layer_keys.sort_by_key(|key| is_moved(key));
+                }
+                ZOrderChange::SendToBack => {
+                    layer_keys.sort_by_key(|key| !is_moved(key));
+                }
+                ZOrderChange::Raise => {
+                    // Iterating from the top, so that adjacent moved strokes move as a block
+                    for i in (0..layer_keys.len().saturating_sub(1)).rev() {
+                        if is_moved(&layer_keys[i]) && !is_moved(&layer_keys[i + 1]) {
+                            layer_keys.swap(i, i + 1);
+                        }
+                    }
+                }
+                ZOrderChange::Lower => {
+                    for i in 1..layer_keys.len() {
+                        if is_moved(&layer_keys[i]) && !is_moved(&layer_keys[i - 1]) {
+                            layer_keys.swap(i, i - 1);
+                        }
+                    }
+                }
+            }
+
+            let chrono_components = Arc::make_mut(&mut self.chrono_components);
+            for layer_key in layer_keys {
+                if let Some(chrono_comp) = chrono_components.get_mut(layer_key) {
+                    self.chrono_counter += 1;
+                    Arc::make_mut(chrono_comp).t = self.chrono_counter;
                 }
-            } else {
-                std::cmp::Ordering::Equal
             }
+        }
+
+        self.invalidate_chrono_order();
+    }
+
+    /// Returns the keys in chronological order, as in first: gets drawn first, last: gets drawn last
+    pub fn keys_sorted_chrono(&self) -> Vec<StrokeKey> {
+        self.chrono_order().keys.clone()
+    }
+
+    /// Returns an iterator over the keys in chronological order, without allocating when the cached order is still valid.
+    pub fn keys_sorted_chrono_iter(&self) -> impl Iterator<Item = StrokeKey> {
+        let chrono_order = self.chrono_order();
+
+        (0..chrono_order.keys.len()).map(move |i| chrono_order.keys[i])
+    }
+
+    pub fn keys_sorted_chrono_intersecting_bounds(&self, bounds: AABB) -> Vec<StrokeKey> {
+        let chrono_order = self.chrono_order();
+
+        let mut keys = self.key_tree.keys_intersecting_bounds(bounds);
+
+        keys.par_sort_unstable_by_key(|&key| {
+            chrono_order.ranks.get(key).copied().unwrap_or(usize::MAX)
         });
 
         keys
     }
 
-    pub fn keys_sorted_chrono_intersecting_bounds(&self, bounds: AABB) -> Vec<StrokeKey> {
+    /// Invalidates the cached chronological order. Must be called when strokes are inserted, removed or their chrono components are modified.
+    pub(super) fn invalidate_chrono_order(&self) {
+        self.chrono_order.borrow_mut().take();
+    }
+
+    /// The cached chronological order, which gets regenerated if it was invalidated
+    fn chrono_order(&self) -> Arc<ChronoOrder> {
+        if let Some(chrono_order) = self.chrono_order.borrow().as_ref() {
+            return Arc::clone(chrono_order);
+        }
+
         let chrono_components = &self.chrono_components;
 
-        let mut keys = self.key_tree.keys_intersecting_bounds(bounds);
+        let mut keys = self.stroke_components.keys().collect::<Vec<StrokeKey>>();
 
         keys.par_sort_unstable_by(|&first, &second| {
             if let (Some(first_chrono), Some(second_chrono)) =
@@ -143,6 +316,15 @@ impl StrokeStore {
             }
         });
 
-        keys
+        let ranks = keys
+            .iter()
+            .enumerate()
+            .map(|(rank, &key)| (key, rank))
+            .collect::<SecondaryMap<StrokeKey, usize>>();
+
+        let chrono_order = Arc::new(ChronoOrder { keys, ranks });
+        *self.chrono_order.borrow_mut() = Some(Arc::clone(&chrono_order));
+
+        chrono_order
     }
 }
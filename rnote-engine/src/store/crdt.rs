@@ -0,0 +1,733 @@
+use super::chrono_comp::StrokeLayer;
+use super::{StrokeKey, StrokeStore};
+use crate::strokes::Stroke;
+
+use rnote_compose::transform::TransformBehaviour;
+use serde::{Deserialize, Serialize};
+use slotmap::{HopSlotMap, SecondaryMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+/// The id of an operation. Ordered by the lamport timestamp, ties are broken by the replica id.
+/// The id of an insert operation is also the id of the inserted stroke across all replicas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename = "op_id")]
+pub struct OpId {
+    #[serde(rename = "lamport")]
+    pub lamport: u64,
+    #[serde(rename = "replica")]
+    pub replica: u32,
+}
+
+/// A transformation of the stroke geometry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "stroke_transform")]
+pub enum StrokeTransform {
+    #[serde(rename = "translate")]
+    Translate(na::Vector2<f64>),
+    #[serde(rename = "rotate")]
+    Rotate {
+        #[serde(rename = "angle")]
+        angle: f64,
+        #[serde(rename = "center")]
+        center: na::Point2<f64>,
+    },
+    #[serde(rename = "scale")]
+    Scale(na::Vector2<f64>),
+    #[serde(rename = "scale_stroke_width")]
+    ScaleStrokeWidth(f64),
+}
+
+impl StrokeTransform {
+    pub fn apply(&self, stroke: &mut Stroke) {
+        match self {
+            StrokeTransform::Translate(offset) => stroke.translate(*offset),
+            StrokeTransform::Rotate { angle, center } => stroke.rotate(*angle, *center),
+            StrokeTransform::Scale(scale) => stroke.scale(*scale),
+            StrokeTransform::ScaleStrokeWidth(factor) => stroke.scale_stroke_width(*factor),
+        }
+    }
+}
+
+/// An operation on the store, exchanged between replicas.
+/// Applying the same set of operations in any order results in the same strokes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "store_op")]
+pub enum StoreOp {
+    /// Inserts a stroke, with the id of the op as the id of the stroke
+    #[serde(rename = "insert")]
+    Insert {
+        #[serde(rename = "id")]
+        id: OpId,
+        #[serde(rename = "stroke")]
+        stroke: Stroke,
+        #[serde(rename = "layer")]
+        layer: StrokeLayer,
+    },
+    /// Deletes the target stroke. Deletions win over concurrent inserts and transforms
+    #[serde(rename = "delete")]
+    Delete {
+        #[serde(rename = "id")]
+        id: OpId,
+        #[serde(rename = "target")]
+        target: OpId,
+    },
+    /// Transforms the target stroke. Concurrent transforms are applied in the order of their ids
+    #[serde(rename = "transform")]
+    Transform {
+        #[serde(rename = "id")]
+        id: OpId,
+        #[serde(rename = "target")]
+        target: OpId,
+        #[serde(rename = "transforms")]
+        transforms: Vec<StrokeTransform>,
+    },
+    /// Replaces the target stroke with a new state, for edits that can't be expressed as transforms.
+    /// The newest replacement wins, transforms with older ids are discarded
+    #[serde(rename = "replace")]
+    Replace {
+        #[serde(rename = "id")]
+        id: OpId,
+        #[serde(rename = "target")]
+        target: OpId,
+        #[serde(rename = "stroke")]
+        stroke: Stroke,
+        #[serde(rename = "layer")]
+        layer: StrokeLayer,
+    },
+}
+
+impl StoreOp {
+    pub fn id(&self) -> OpId {
+        match self {
+            StoreOp::Insert { id, .. }
+            | StoreOp::Delete { id, .. }
+            | StoreOp::Transform { id, .. }
+            | StoreOp::Replace { id, .. } => *id,
+        }
+    }
+}
+
+/// Encodes the ops as an op log of newline delimited JSON. Logs can be concatenated and are replayed with `decode_op_log()`
+pub fn encode_op_log(ops: &[StoreOp]) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for op in ops {
+        serde_json::to_writer(&mut bytes, op)?;
+        bytes.push(b'\n');
+    }
+
+    Ok(bytes)
+}
+
+/// Decodes the ops of an op log. See `encode_op_log()`
+pub fn decode_op_log(bytes: &[u8]) -> anyhow::Result<Vec<StoreOp>> {
+    bytes
+        .split(|&byte| byte == b'\n')
+        .filter(|line| !line.iter().all(|byte| byte.is_ascii_whitespace()))
+        .enumerate()
+        .map(|(i, line)| {
+            serde_json::from_slice::<StoreOp>(line).map_err(|e| {
+                anyhow::anyhow!("decode_op_log() failed, op {} is invalid, Err {}", i, e)
+            })
+        })
+        .collect()
+}
+
+/// The state of the CRDT the store mutations are applied through while collaborating.
+#[derive(Debug, Clone, Default)]
+pub struct StoreCrdt {
+    /// Is None when not collaborating
+    replica: Option<u32>,
+    /// The lamport clock, the highest timestamp that was seen
+    clock: u64,
+    stroke_ids: SecondaryMap<StrokeKey, OpId>,
+    stroke_keys: HashMap<OpId, StrokeKey>,
+    /// The strokes as they were inserted, to replay their transforms in order
+    origins: HashMap<OpId, (Arc<Stroke>, StrokeLayer)>,
+    /// The transforms for each stroke, ordered by their ids
+    transforms: HashMap<OpId, BTreeMap<OpId, Vec<StrokeTransform>>>,
+    /// The id of the newest replacement of each stroke
+    replaced: HashMap<OpId, OpId>,
+    /// The deleted strokes. Kept, so that inserts and transforms arriving after the deletion are ignored
+    tombstones: HashSet<OpId>,
+    applied: HashSet<OpId>,
+    /// Locally inserted strokes, which are sent with their state at the time the pending ops are taken
+    pending_inserts: Vec<StrokeKey>,
+    /// Locally edited strokes, which are sent with their state at the time the pending ops are taken
+    pending_replaces: Vec<StrokeKey>,
+    pending_ops: Vec<StoreOp>,
+}
+
+impl StoreCrdt {
+    fn next_id(&mut self, replica: u32) -> OpId {
+        self.clock += 1;
+
+        OpId {
+            lamport: self.clock,
+            replica,
+        }
+    }
+
+    fn register_stroke(&mut self, key: StrokeKey, id: OpId) {
+        if let Some(old_id) = self.stroke_ids.insert(key, id) {
+            self.stroke_keys.remove(&old_id);
+        }
+        self.stroke_keys.insert(id, key);
+    }
+
+    fn unregister_stroke(&mut self, key: StrokeKey) -> Option<OpId> {
+        let id = self.stroke_ids.remove(key)?;
+        self.stroke_keys.remove(&id);
+
+        Some(id)
+    }
+
+    /// Sets the new state of the stroke, discarding the transforms which are older than the replacement
+    fn replace_origin(&mut self, target: OpId, id: OpId, stroke: Arc<Stroke>, layer: StrokeLayer) {
+        self.origins.insert(target, (stroke, layer));
+        self.replaced.insert(target, id);
+        if let Some(transforms) = self.transforms.get_mut(&target) {
+            *transforms = transforms.split_off(&id);
+        }
+    }
+
+    /// Wether a newer replacement of the target supersedes the op with the given id
+    fn superseded(&self, target: OpId, id: OpId) -> bool {
+        matches!(self.replaced.get(&target), Some(&replace_id) if replace_id > id)
+    }
+}
+
+/// Systems that are related to collaborative editing.
+/// While collaborating, inserting, removing, trashing, transforming and editing strokes is recorded as operations,
+/// which are taken with `take_pending_ops()` and applied on the other replicas with `apply_ops()`.
+/// Undo and redo are recorded as the changes between the states, see `record_history_ops()`.
+impl StrokeStore {
+    /// Wether collaborative editing is active
+    pub fn collaborating(&self) -> bool {
+        self.crdt.replica.is_some()
+    }
+
+    /// Starts collaborating as the replica with the given id, which must be unique among the collaborating replicas.
+    /// The current strokes are inserted into the pending operations
+    pub fn start_collaborating(&mut self, replica: u32) {
+        self.crdt = StoreCrdt {
+            replica: Some(replica),
+            ..StoreCrdt::default()
+        };
+
+        for key in self.stroke_keys_as_rendered() {
+            self.record_insert_op(key);
+        }
+    }
+
+    pub fn stop_collaborating(&mut self) {
+        self.crdt = StoreCrdt::default();
+    }
+
+    /// The id of the stroke, shared across the replicas
+    pub fn stroke_op_id(&self, key: StrokeKey) -> Option<OpId> {
+        self.crdt.stroke_ids.get(key).copied()
+    }
+
+    /// The key of the stroke with the given id
+    pub fn stroke_key_for_op_id(&self, id: OpId) -> Option<StrokeKey> {
+        self.crdt.stroke_keys.get(&id).copied()
+    }
+
+    /// Takes the operations of the local mutations since the last call, to be sent to the other replicas
+    pub fn take_pending_ops(&mut self) -> Vec<StoreOp> {
+        let mut ops = std::mem::take(&mut self.crdt.pending_inserts)
+            .into_iter()
+            .filter_map(|key| {
+                let id = self.crdt.stroke_ids.get(key).copied()?;
+                let stroke = Arc::clone(self.stroke_components.get(key)?);
+                let layer = self.chrono_components.get(key)?.layer;
+                self.crdt.origins.insert(id, (Arc::clone(&stroke), layer));
+
+                Some(StoreOp::Insert {
+                    id,
+                    stroke: (*stroke).clone(),
+                    layer,
+                })
+            })
+            .collect::<Vec<StoreOp>>();
+        ops.append(&mut self.crdt.pending_ops);
+
+        let replica = match self.crdt.replica {
+            Some(replica) => replica,
+            None => return ops,
+        };
+        for key in std::mem::take(&mut self.crdt.pending_replaces) {
+            let (target, stroke, layer) = match (
+                self.crdt.stroke_ids.get(key).copied(),
+                self.stroke_components.get(key),
+                self.chrono_components.get(key),
+            ) {
+                (Some(target), Some(stroke), Some(chrono_comp)) => {
+                    (target, Arc::clone(stroke), chrono_comp.layer)
+                }
+                _ => continue,
+            };
+
+            let id = self.crdt.next_id(replica);
+            self.crdt.applied.insert(id);
+            self.crdt
+                .replace_origin(target, id, Arc::clone(&stroke), layer);
+            ops.push(StoreOp::Replace {
+                id,
+                target,
+                stroke: (*stroke).clone(),
+                layer,
+            });
+        }
+
+        ops
+    }
+
+    /// The operations which rebuild the current strokes, to catch up replicas that joined later.
+    /// Only strokes that were already sent are included, the pending ops are taken with `take_pending_ops()`
+    pub fn state_ops(&self) -> Vec<StoreOp> {
+        self.crdt
+            .origins
+            .iter()
+            .filter(|(id, _)| self.crdt.stroke_keys.contains_key(id))
+            .flat_map(|(&target, (stroke, layer))| {
+                let origin = match self.crdt.replaced.get(&target) {
+                    Some(&id) => StoreOp::Replace {
+                        id,
+                        target,
+                        stroke: (**stroke).clone(),
+                        layer: *layer,
+                    },
+                    None => StoreOp::Insert {
+                        id: target,
+                        stroke: (**stroke).clone(),
+                        layer: *layer,
+                    },
+                };
+                let transforms = self.crdt.transforms.get(&target).into_iter().flatten().map(
+                    move |(&id, transforms)| StoreOp::Transform {
+                        id,
+                        target,
+                        transforms: transforms.clone(),
+                    },
+                );
+
+                std::iter::once(origin).chain(transforms)
+            })
+            .collect()
+    }
+
+    /// Applies the operations received from other replicas. Operations that were already applied are skipped.
+    /// Returns the keys of the inserted and transformed strokes, which then need to update their rendering
+    pub fn apply_ops(&mut self, ops: Vec<StoreOp>) -> Vec<StrokeKey> {
+        let mut changed_keys = vec![];
+
+        for op in ops {
+            let id = op.id();
+            if !self.crdt.applied.insert(id) {
+                continue;
+            }
+            self.crdt.clock = self.crdt.clock.max(id.lamport);
+
+            match op {
+                StoreOp::Insert { id, stroke, layer } => {
+                    if self.crdt.tombstones.contains(&id) {
+                        continue;
+                    }
+                    // A replacement may have arrived before the insert
+                    self.crdt
+                        .origins
+                        .entry(id)
+                        .or_insert_with(|| (Arc::new(stroke), layer));
+                    changed_keys.extend(self.replay_stroke(id));
+                }
+                StoreOp::Delete { target, .. } => {
+                    self.crdt.tombstones.insert(target);
+                    self.crdt.origins.remove(&target);
+                    self.crdt.transforms.remove(&target);
+                    self.crdt.replaced.remove(&target);
+
+                    if let Some(&key) = self.crdt.stroke_keys.get(&target) {
+                        self.crdt.unregister_stroke(key);
+                        self.remove_stroke(key);
+                    }
+                }
+                StoreOp::Transform {
+                    id,
+                    target,
+                    transforms,
+                } => {
+                    if self.crdt.tombstones.contains(&target) || self.crdt.superseded(target, id) {
+                        continue;
+                    }
+                    let stroke_transforms = self.crdt.transforms.entry(target).or_default();
+                    let in_order =
+                        !matches!(stroke_transforms.keys().next_back(), Some(&last) if last > id);
+                    stroke_transforms.insert(id, transforms.clone());
+
+                    match self.crdt.stroke_keys.get(&target).copied() {
+                        // The transform is the newest, so it can be applied on top
+                        Some(key) if in_order => {
+                            if let Some(stroke) = Arc::make_mut(&mut self.stroke_components)
+                                .get_mut(key)
+                                .map(Arc::make_mut)
+                            {
+                                for transform in transforms.iter() {
+                                    transform.apply(stroke);
+                                }
+                                changed_keys.push(key);
+                            }
+                        }
+                        // An older transform arrived late, so all transforms are replayed in order
+                        Some(_) => changed_keys.extend(self.replay_stroke(target)),
+                        // The insert has not arrived yet
+                        None => {}
+                    }
+                }
+                StoreOp::Replace {
+                    id,
+                    target,
+                    stroke,
+                    layer,
+                } => {
+                    if self.crdt.tombstones.contains(&target) || self.crdt.superseded(target, id) {
+                        continue;
+                    }
+                    self.crdt
+                        .replace_origin(target, id, Arc::new(stroke), layer);
+                    changed_keys.extend(self.replay_stroke(target));
+                }
+            }
+        }
+
+        changed_keys.sort_unstable();
+        changed_keys.dedup();
+        changed_keys.retain(|&key| self.stroke_components.contains_key(key));
+        self.update_geometry_for_strokes(&changed_keys);
+
+        changed_keys
+    }
+
+    /// Rebuilds the stroke from its inserted state and its transforms, in the order of their ids.
+    fn replay_stroke(&mut self, id: OpId) -> Option<StrokeKey> {
+        let (origin, layer) = self.crdt.origins.get(&id).cloned()?;
+        let mut stroke = (*origin).clone();
+        if let Some(transforms) = self.crdt.transforms.get(&id) {
+            for transform in transforms.values().flatten() {
+                transform.apply(&mut stroke);
+            }
+        }
+
+        match self.crdt.stroke_keys.get(&id).copied() {
+            Some(key) => {
+                if let Some(old_stroke) = Arc::make_mut(&mut self.stroke_components).get_mut(key) {
+                    *old_stroke = Arc::new(stroke);
+                }
+                Some(key)
+            }
+            None => {
+                let key = self.insert_stroke_unrecorded(stroke, Some(layer));
+                self.crdt.register_stroke(key, id);
+                Some(key)
+            }
+        }
+    }
+
+    /// Records the local insertion of the stroke
+    pub(super) fn record_insert_op(&mut self, key: StrokeKey) {
+        let replica = match self.crdt.replica {
+            Some(replica) => replica,
+            None => return,
+        };
+
+        let id = self.crdt.next_id(replica);
+        self.crdt.applied.insert(id);
+        self.crdt.register_stroke(key, id);
+        self.crdt.pending_inserts.push(key);
+    }
+
+    /// Records the local deletion of the stroke
+    pub(super) fn record_delete_op(&mut self, key: StrokeKey) {
+        let replica = match self.crdt.replica {
+            Some(replica) => replica,
+            None => return,
+        };
+        let target = match self.crdt.unregister_stroke(key) {
+            Some(target) => target,
+            None => return,
+        };
+        self.crdt.tombstones.insert(target);
+        self.crdt.origins.remove(&target);
+        self.crdt.transforms.remove(&target);
+        self.crdt.replaced.remove(&target);
+        self.crdt
+            .pending_replaces
+            .retain(|&pending_key| pending_key != key);
+
+        // Strokes that were not sent yet don't need to be deleted on the other replicas
+        if let Some(pos) = self
+            .crdt
+            .pending_inserts
+            .iter()
+            .position(|&pending_key| pending_key == key)
+        {
+            self.crdt.pending_inserts.remove(pos);
+            return;
+        }
+
+        let id = self.crdt.next_id(replica);
+        self.crdt.applied.insert(id);
+        self.crdt.pending_ops.push(StoreOp::Delete { id, target });
+    }
+
+    /// Records the local transformation of the strokes
+    pub(super) fn record_transform_op(
+        &mut self,
+        keys: &[StrokeKey],
+        transforms: Vec<StrokeTransform>,
+    ) {
+        let replica = match self.crdt.replica {
+            Some(replica) => replica,
+            None => return,
+        };
+
+        for &key in keys {
+            let target = match self.crdt.stroke_ids.get(key) {
+                Some(&target) => target,
+                None => continue,
+            };
+            // The strokes that were not sent yet are sent in their transformed state
+            if self.crdt.pending_inserts.contains(&key) {
+                continue;
+            }
+
+            let id = self.crdt.next_id(replica);
+            self.crdt.applied.insert(id);
+            self.crdt
+                .transforms
+                .entry(target)
+                .or_default()
+                .insert(id, transforms.clone());
+            self.crdt.pending_ops.push(StoreOp::Transform {
+                id,
+                target,
+                transforms: transforms.clone(),
+            });
+        }
+    }
+
+    /// Records the local edit of the strokes, which can't be expressed as transforms.
+    /// The strokes are sent in their state at the time the pending ops are taken, so repeated edits are coalesced
+    pub(crate) fn record_replace_op(&mut self, keys: &[StrokeKey]) {
+        if !self.collaborating() {
+            return;
+        }
+
+        for &key in keys {
+            // The strokes that were not sent yet are sent in their edited state
+            if !self.crdt.stroke_ids.contains_key(key)
+                || self.crdt.pending_inserts.contains(&key)
+                || self.crdt.pending_replaces.contains(&key)
+            {
+                continue;
+            }
+            self.crdt.pending_replaces.push(key);
+        }
+    }
+
+    /// Records the changes of undo and redo, which import a previous state of the strokes.
+    /// Strokes that are gone are deleted, strokes that reappeared are inserted and changed strokes are replaced
+    pub(super) fn record_history_ops(&mut self, prev_strokes: &HopSlotMap<StrokeKey, Arc<Stroke>>) {
+        if !self.collaborating() {
+            return;
+        }
+
+        let removed_keys = self
+            .crdt
+            .stroke_ids
+            .keys()
+            .filter(|&key| {
+                !self.stroke_components.contains_key(key) || self.trashed(key).unwrap_or(true)
+            })
+            .collect::<Vec<StrokeKey>>();
+        for key in removed_keys {
+            self.record_delete_op(key);
+        }
+
+        let present_keys = self
+            .stroke_components
+            .keys()
+            .filter(|&key| !self.trashed(key).unwrap_or(true))
+            .collect::<Vec<StrokeKey>>();
+        for key in present_keys {
+            if !self.crdt.stroke_ids.contains_key(key) {
+                self.record_insert_op(key);
+            } else if prev_strokes
+                .get(key)
+                .zip(self.stroke_components.get(key))
+                .map(|(prev, current)| !Arc::ptr_eq(prev, current))
+                .unwrap_or(true)
+            {
+                self.record_replace_op(&[key]);
+            }
+        }
+    }
+
+    /// Trashing deletes the strokes on the other replicas, untrashing inserts them again as new strokes
+    pub(super) fn record_trash_op(&mut self, key: StrokeKey, trash: bool) {
+        if !self.collaborating() {
+            return;
+        }
+
+        match (trash, self.crdt.stroke_ids.contains_key(key)) {
+            (true, true) => self.record_delete_op(key),
+            (false, false) => {
+                if self.stroke_components.contains_key(key) {
+                    self.record_insert_op(key);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::strokes::Stroke;
+    use crate::StrokeStore;
+    use approx::assert_relative_eq;
+    use rnote_compose::shapes::ShapeBehaviour;
+
+    #[test]
+    fn ops_converge() {
+        let mut first = StrokeStore::default();
+        let mut second = StrokeStore::default();
+        first.start_collaborating(1);
+        second.start_collaborating(2);
+
+        let first_key = first.insert_stroke(
+            Stroke::line_fixture(na::vector![0.0, 0.0], na::vector![100.0, 50.0]),
+            None,
+        );
+        let second_key = second.insert_stroke(
+            Stroke::line_fixture(na::vector![0.0, 0.0], na::vector![20.0, 80.0]),
+            None,
+        );
+        let first_ops = first.take_pending_ops();
+        let second_ops = second.take_pending_ops();
+        first.apply_ops(second_ops);
+        second.apply_ops(first_ops);
+
+        // Concurrent transforms of the same stroke, and a deletion
+        let first_id = first.stroke_op_id(first_key).unwrap();
+        let second_id = second.stroke_op_id(second_key).unwrap();
+        first.translate_strokes(&[first_key], na::vector![10.0, 0.0]);
+        second.rotate_strokes(
+            &[second.stroke_key_for_op_id(first_id).unwrap()],
+            1.0,
+            na::point![0.0, 0.0],
+        );
+        second.remove_stroke(second_key);
+        let first_ops = first.take_pending_ops();
+        let second_ops = second.take_pending_ops();
+        first.apply_ops(second_ops);
+        second.apply_ops(first_ops);
+
+        let first_bounds = first.get_stroke_ref(first_key).unwrap().bounds();
+        let second_bounds = second
+            .get_stroke_ref(second.stroke_key_for_op_id(first_id).unwrap())
+            .unwrap()
+            .bounds();
+        assert_relative_eq!(first_bounds.mins.coords, second_bounds.mins.coords);
+        assert_relative_eq!(first_bounds.maxs.coords, second_bounds.maxs.coords);
+        assert!(first.stroke_key_for_op_id(second_id).is_none());
+        assert_eq!(first.stroke_keys_unordered().len(), 1);
+        assert_eq!(second.stroke_keys_unordered().len(), 1);
+    }
+
+    #[test]
+    fn replace_ops_converge() {
+        let mut first = StrokeStore::default();
+        let mut second = StrokeStore::default();
+        first.start_collaborating(1);
+        second.start_collaborating(2);
+
+        let key = first.insert_stroke(
+            Stroke::line_fixture(na::vector![0.0, 0.0], na::vector![100.0, 50.0]),
+            None,
+        );
+        second.apply_ops(first.take_pending_ops());
+        let id = first.stroke_op_id(key).unwrap();
+        let second_key = second.stroke_key_for_op_id(id).unwrap();
+
+        // An edit that is not a transform, concurrent to an older transform
+        second.translate_strokes(&[second_key], na::vector![10.0, 0.0]);
+        let _ = first.record();
+        first.set_opacity_for_strokes(&[key], 0.5);
+        let first_ops = first.take_pending_ops();
+        let second_ops = second.take_pending_ops();
+        first.apply_ops(second_ops);
+        second.apply_ops(first_ops);
+        assert_relative_eq!(second.get_stroke_ref(second_key).unwrap().opacity(), 0.5);
+        assert_relative_eq!(
+            first.get_stroke_ref(key).unwrap().bounds().mins.coords,
+            second
+                .get_stroke_ref(second_key)
+                .unwrap()
+                .bounds()
+                .mins
+                .coords
+        );
+
+        // Undo is sent as the changes between the states
+        let _ = first.undo();
+        second.apply_ops(first.take_pending_ops());
+        assert_relative_eq!(
+            first.get_stroke_ref(key).unwrap().opacity(),
+            second.get_stroke_ref(second_key).unwrap().opacity()
+        );
+        assert_relative_eq!(
+            first.get_stroke_ref(key).unwrap().bounds().mins.coords,
+            second
+                .get_stroke_ref(second_key)
+                .unwrap()
+                .bounds()
+                .mins
+                .coords
+        );
+    }
+
+    #[test]
+    fn state_ops_catch_up() {
+        let mut first = StrokeStore::default();
+        first.start_collaborating(1);
+        let key = first.insert_stroke(
+            Stroke::line_fixture(na::vector![0.0, 0.0], na::vector![100.0, 50.0]),
+            None,
+        );
+        let _ = first.take_pending_ops();
+        first.translate_strokes(&[key], na::vector![10.0, 0.0]);
+        first.set_opacity_for_strokes(&[key], 0.5);
+        let _ = first.take_pending_ops();
+
+        // A replica joining later
+        let mut second = StrokeStore::default();
+        second.start_collaborating(2);
+        second.apply_ops(first.state_ops());
+
+        let second_key = second
+            .stroke_key_for_op_id(first.stroke_op_id(key).unwrap())
+            .unwrap();
+        assert_relative_eq!(second.get_stroke_ref(second_key).unwrap().opacity(), 0.5);
+        assert_relative_eq!(
+            first.get_stroke_ref(key).unwrap().bounds().mins.coords,
+            second
+                .get_stroke_ref(second_key)
+                .unwrap()
+                .bounds()
+                .mins
+                .coords
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use super::{StrokeKey, StrokeStore};
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Locked strokes can't be selected or erased until they are unlocked, e.g. to pin down a traced reference or a form template.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, rename = "lock_component")]
+pub struct LockComponent {
+    #[serde(rename = "locked")]
+    pub locked: bool,
+}
+
+impl Default for LockComponent {
+    fn default() -> Self {
+        Self { locked: false }
+    }
+}
+
+/// Systems that are related to locking
+impl StrokeStore {
+    /// Wether the stroke is locked. Strokes loaded from older files have no lock component and are unlocked.
+    pub fn locked(&self, key: StrokeKey) -> bool {
+        self.lock_components
+            .get(key)
+            .map(|lock_comp| lock_comp.locked)
+            .unwrap_or(false)
+    }
+
+    /// Locks or unlocks the stroke. Locked strokes get deselected
+    pub fn set_locked(&mut self, key: StrokeKey, locked: bool) {
+        if !self.stroke_components.contains_key(key) {
+            log::debug!(
+                "get stroke in set_locked() returned None for stroke with key {:?}",
+                key
+            );
+            return;
+        }
+
+        if locked {
+            self.set_selected(key, false);
+        }
+        Arc::make_mut(&mut self.lock_components).insert(key, Arc::new(LockComponent { locked }));
+    }
+
+    pub fn set_locked_keys(&mut self, keys: &[StrokeKey], locked: bool) {
+        keys.iter().for_each(|&key| {
+            self.set_locked(key, locked);
+        });
+    }
+
+    /// The keys of the locked strokes, excluding trashed strokes. Unordered
+    pub fn locked_keys_unordered(&self) -> Vec<StrokeKey> {
+        self.stroke_components
+            .keys()
+            .filter(|&key| !(self.trashed(key).unwrap_or(false)) && self.locked(key))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::strokes::Stroke;
+    use crate::StrokeStore;
+
+    #[test]
+    fn lock_strokes() {
+        let mut store = StrokeStore::default();
+        let key = store.insert_stroke(
+            Stroke::line_fixture(na::vector![0.0, 0.0], na::vector![100.0, 50.0]),
+            None,
+        );
+        store.set_selected(key, true);
+
+        store.set_locked_keys(&[key], true);
+        assert!(store.locked(key));
+        assert!(!store.can_select(key));
+        assert!(store.selection_keys_unordered().is_empty());
+        assert_eq!(store.locked_keys_unordered(), vec![key]);
+
+        store.set_locked_keys(&[key], false);
+        assert!(store.can_select(key));
+        assert!(store.locked_keys_unordered().is_empty());
+    }
+}
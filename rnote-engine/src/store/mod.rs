@@ -1,27 +1,46 @@
+pub mod author_comp;
 pub mod chrono_comp;
+pub mod crdt;
 pub mod keytree;
+pub mod lock_comp;
+pub mod recognition_comp;
 pub mod render_comp;
 pub mod selection_comp;
 pub mod stroke_comp;
 pub mod trash_comp;
 
 // Re-exports
-pub use chrono_comp::ChronoComponent;
+pub use author_comp::{Author, AuthorComponent};
+pub use chrono_comp::{ChronoComponent, ZOrderChange};
+use crdt::StoreCrdt;
+pub use crdt::{OpId, StoreOp, StrokeTransform};
 use keytree::KeyTree;
+pub use lock_comp::LockComponent;
+pub use recognition_comp::RecognitionComponent;
 pub use render_comp::RenderComponent;
-pub use selection_comp::SelectionComponent;
+pub use render_comp::RenderFocus;
+use render_comp::{RenderDamage, StrokeChanges};
+pub use selection_comp::{SelectionComponent, SelectionCriteria};
+pub use stroke_comp::TextSearchMatch;
 pub use trash_comp::TrashComponent;
 
-use std::collections::VecDeque;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::sync::Arc;
 
-use crate::strokes::Stroke;
+use crate::document::PagesState;
+use crate::strokes::{DeferredStroke, Stroke};
+use crate::viewport::ViewportKey;
+use crate::watchdog::TaskWatchdog;
 use crate::WidgetFlags;
+use p2d::bounding_volume::AABB;
 use rnote_compose::shapes::ShapeBehaviour;
+use rnote_fileformats::rnoteformat;
 use serde::{Deserialize, Serialize};
 use slotmap::{HopSlotMap, SecondaryMap};
 
-use self::chrono_comp::StrokeLayer;
+use self::chrono_comp::{ChronoOrder, StrokeLayer};
 
 slotmap::new_key_type! {
     pub struct StrokeKey;
@@ -38,9 +57,21 @@ pub struct HistoryEntry {
     pub selection_components: Arc<SecondaryMap<StrokeKey, Arc<SelectionComponent>>>,
     #[serde(rename = "chrono_components")]
     pub chrono_components: Arc<SecondaryMap<StrokeKey, Arc<ChronoComponent>>>,
+    #[serde(rename = "lock_components")]
+    pub lock_components: Arc<SecondaryMap<StrokeKey, Arc<LockComponent>>>,
+    #[serde(rename = "recognition_components")]
+    pub recognition_components: Arc<SecondaryMap<StrokeKey, Arc<RecognitionComponent>>>,
+    #[serde(rename = "author_components")]
+    pub author_components: Arc<SecondaryMap<StrokeKey, Arc<AuthorComponent>>>,
 
     #[serde(rename = "chrono_counter")]
     chrono_counter: u32,
+    /// The bounds of the strokes, saved alongside to be able to defer loading the strokes. Only filled when saving.
+    #[serde(rename = "stroke_bounds")]
+    stroke_bounds: SecondaryMap<StrokeKey, AABB>,
+    /// The state of the document pages, which changes with inserting, deleting and moving pages. Not saved in files
+    #[serde(skip)]
+    pub pages_state: Arc<PagesState>,
 }
 
 impl Default for HistoryEntry {
@@ -50,8 +81,13 @@ impl Default for HistoryEntry {
             trash_components: Arc::new(SecondaryMap::new()),
             selection_components: Arc::new(SecondaryMap::new()),
             chrono_components: Arc::new(SecondaryMap::new()),
+            lock_components: Arc::new(SecondaryMap::new()),
+            recognition_components: Arc::new(SecondaryMap::new()),
+            author_components: Arc::new(SecondaryMap::new()),
 
             chrono_counter: 0,
+            stroke_bounds: SecondaryMap::new(),
+            pages_state: Arc::new(PagesState::default()),
         }
     }
 }
@@ -74,7 +110,71 @@ impl StoreSnapshot {
             Arc::make_mut(&mut self.trash_components).remove(key);
             Arc::make_mut(&mut self.selection_components).remove(key);
             Arc::make_mut(&mut self.chrono_components).remove(key);
+            Arc::make_mut(&mut self.lock_components).remove(key);
+            Arc::make_mut(&mut self.recognition_components).remove(key);
+            Arc::make_mut(&mut self.author_components).remove(key);
         }
+
+        self.stroke_bounds = self
+            .stroke_components
+            .iter()
+            .map(|(key, stroke)| (key, stroke.bounds()))
+            .collect();
+    }
+
+    /// Decodes a CBOR encoded snapshot, deferring deserializing the strokes whose bounds were saved alongside.
+    /// They are loaded on demand, when entering the viewport or when they are needed otherwise.
+    pub fn from_cbor_w_deferred_strokes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut snapshot: Self =
+            DeferredStroke::with_deferred_deserialization(|| rnoteformat::decode_cbor(bytes))?;
+        let stroke_bounds = std::mem::take(&mut snapshot.stroke_bounds);
+
+        for (key, stroke) in Arc::make_mut(&mut snapshot.stroke_components).iter_mut() {
+            if let Stroke::Deferred(deferred) = Arc::make_mut(stroke) {
+                match stroke_bounds.get(key) {
+                    Some(&bounds) => deferred.bounds = bounds,
+                    // Older files don't have the bounds saved, so the stroke must be loaded
+                    None => {
+                        let loaded = deferred.load()?;
+                        *stroke = Arc::new(loaded);
+                    }
+                }
+            }
+        }
+
+        Ok(snapshot)
+    }
+}
+
+/// An estimation of the memory used by the store, in bytes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryUsage {
+    /// The geometry of the strokes in the current state
+    pub strokes_geometry: usize,
+    /// The rendered images of the strokes
+    pub render_images: usize,
+    /// The strokes only referenced by history entries, which are not shared with the current state
+    pub history: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.strokes_geometry + self.render_images + self.history
+    }
+}
+
+impl fmt::Display for MemoryUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mib = |bytes: usize| bytes as f64 / (1024.0 * 1024.0);
+
+        write!(
+            f,
+            "strokes geometry: {:.1} MiB, render images: {:.1} MiB, history: {:.1} MiB, total: {:.1} MiB",
+            mib(self.strokes_geometry),
+            mib(self.render_images),
+            mib(self.history),
+            mib(self.total())
+        )
     }
 }
 
@@ -84,7 +184,11 @@ impl StoreSnapshot {
 ///     * 'trash_components': Hold state whether the strokes are trashed
 ///     * 'selection_components': Hold state whether the strokes are selected
 ///     * 'chrono_components': Hold state about the chronological ordering
+///     * 'lock_components': Hold state whether the strokes are locked
+///     * 'recognition_components': Hold the text recognized from the strokes
+///     * 'author_components': Hold who modified the strokes last, and when
 ///     * 'render_components': Hold state about the current rendering of the strokes.
+///     * 'viewport_render_components': Hold state about the rendering of the strokes for each additional viewport.
 ///
 /// The systems are implemented as methods on StrokesStore, loosely categorized to the different components (but often modify others as well).
 /// Most systems take a key or a slice of keys, and iterate with them over the different components.
@@ -103,18 +207,51 @@ pub struct StrokeStore {
     selection_components: Arc<SecondaryMap<StrokeKey, Arc<SelectionComponent>>>,
     #[serde(rename = "chrono_components")]
     chrono_components: Arc<SecondaryMap<StrokeKey, Arc<ChronoComponent>>>,
+    #[serde(rename = "lock_components")]
+    lock_components: Arc<SecondaryMap<StrokeKey, Arc<LockComponent>>>,
+    #[serde(rename = "recognition_components")]
+    recognition_components: Arc<SecondaryMap<StrokeKey, Arc<RecognitionComponent>>>,
+    #[serde(rename = "author_components")]
+    author_components: Arc<SecondaryMap<StrokeKey, Arc<AuthorComponent>>>,
     #[serde(skip)]
     render_components: SecondaryMap<StrokeKey, RenderComponent>,
+    /// The render components for the additional viewports. The main viewport uses `render_components`
+    #[serde(skip)]
+    viewport_render_components: HashMap<ViewportKey, SecondaryMap<StrokeKey, RenderComponent>>,
+    /// The regions damaged by edits since the last rendering pass
+    #[serde(skip)]
+    damage: RenderDamage,
+    /// The changes of the strokes, for caches of the rendering of regions
+    #[serde(skip)]
+    changes: StrokeChanges,
 
     // The history
     #[serde(skip)]
     history: VecDeque<Arc<HistoryEntry>>,
     #[serde(skip)]
     history_pos: Option<usize>,
+    /// The state of the document pages as of the latest page operation, or as imported from the history. See `record_pages_state()`
+    #[serde(skip)]
+    pages_state: Arc<PagesState>,
 
     // A rtree backed by the slotmap, for faster spatial queries. Needs to be updated with update_with_key() when strokes changed their geometry or position!
     #[serde(skip)]
     key_tree: KeyTree,
+    /// A dedicated thread pool for rendering tasks. When None, the global rayon pool is used.
+    #[serde(skip)]
+    render_pool: Option<Arc<rayon::ThreadPool>>,
+    /// The cached chronological order of the strokes. Invalidated on mutations
+    #[serde(skip)]
+    chrono_order: RefCell<Option<Arc<ChronoOrder>>>,
+    /// Watches the background tasks and reports them when they exceed a timeout
+    #[serde(skip)]
+    watchdog: TaskWatchdog,
+    /// The CRDT the mutations are recorded in while collaborating
+    #[serde(skip)]
+    crdt: StoreCrdt,
+    /// The author recorded for local modifications
+    #[serde(skip)]
+    local_author: Option<Author>,
 
     // Other state
     /// incrementing counter for chrono_components. value is equal chrono_component of the newest inserted or modified stroke.
@@ -129,12 +266,24 @@ impl Default for StrokeStore {
             trash_components: Arc::new(SecondaryMap::new()),
             selection_components: Arc::new(SecondaryMap::new()),
             chrono_components: Arc::new(SecondaryMap::new()),
+            lock_components: Arc::new(SecondaryMap::new()),
+            recognition_components: Arc::new(SecondaryMap::new()),
+            author_components: Arc::new(SecondaryMap::new()),
             render_components: SecondaryMap::new(),
+            viewport_render_components: HashMap::new(),
+            damage: RenderDamage::default(),
+            changes: StrokeChanges::default(),
 
             history: VecDeque::new(),
             history_pos: None,
+            pages_state: Arc::new(PagesState::default()),
 
             key_tree: KeyTree::default(),
+            render_pool: None,
+            chrono_order: RefCell::new(None),
+            watchdog: TaskWatchdog::default(),
+            crdt: StoreCrdt::default(),
+            local_author: None,
 
             chrono_counter: 0,
         }
@@ -157,8 +306,12 @@ impl StrokeStore {
         self.trash_components = Arc::clone(&store_snapshot.trash_components);
         self.selection_components = Arc::clone(&store_snapshot.selection_components);
         self.chrono_components = Arc::clone(&store_snapshot.chrono_components);
+        self.lock_components = Arc::clone(&store_snapshot.lock_components);
+        self.recognition_components = Arc::clone(&store_snapshot.recognition_components);
+        self.author_components = Arc::clone(&store_snapshot.author_components);
 
         self.chrono_counter = store_snapshot.chrono_counter;
+        self.invalidate_chrono_order();
 
         self.update_geometry_for_strokes(&self.keys_unordered());
 
@@ -185,6 +338,13 @@ impl StrokeStore {
                 &history_entry.selection_components,
             )
             && Arc::ptr_eq(&self.chrono_components, &history_entry.chrono_components)
+            && Arc::ptr_eq(&self.pages_state, &history_entry.pages_state)
+            && Arc::ptr_eq(&self.lock_components, &history_entry.lock_components)
+            && Arc::ptr_eq(
+                &self.recognition_components,
+                &history_entry.recognition_components,
+            )
+            && Arc::ptr_eq(&self.author_components, &history_entry.author_components)
     }
 
     /// Returns a history entry created from the current state
@@ -194,7 +354,12 @@ impl StrokeStore {
             trash_components: Arc::clone(&self.trash_components),
             selection_components: Arc::clone(&self.selection_components),
             chrono_components: Arc::clone(&self.chrono_components),
+            lock_components: Arc::clone(&self.lock_components),
+            recognition_components: Arc::clone(&self.recognition_components),
+            author_components: Arc::clone(&self.author_components),
             chrono_counter: self.chrono_counter,
+            stroke_bounds: SecondaryMap::new(),
+            pages_state: Arc::clone(&self.pages_state),
         })
     }
 
@@ -203,14 +368,58 @@ impl StrokeStore {
         self.history_entry_from_current_state()
     }
 
+    /// Estimates the memory used by the stroke geometry, the render images and the history.
+    /// Strokes are shared between the current state and history entries, so shared strokes are only counted once.
+    pub fn estimate_memory_usage(&self) -> MemoryUsage {
+        let mut counted_strokes = HashSet::new();
+
+        let strokes_geometry = self
+            .stroke_components
+            .values()
+            .filter(|stroke| counted_strokes.insert(Arc::as_ptr(stroke)))
+            .map(|stroke| stroke.estimate_memory_usage())
+            .sum();
+
+        let render_images = self
+            .render_components
+            .values()
+            .chain(
+                self.viewport_render_components
+                    .values()
+                    .flat_map(|render_components| render_components.values()),
+            )
+            .flat_map(|render_comp| render_comp.images.iter())
+            .map(|image| image.data.len())
+            .sum();
+
+        let history = self
+            .history
+            .iter()
+            .flat_map(|history_entry| history_entry.stroke_components.values())
+            .filter(|stroke| counted_strokes.insert(Arc::as_ptr(stroke)))
+            .map(|stroke| stroke.estimate_memory_usage())
+            .sum();
+
+        MemoryUsage {
+            strokes_geometry,
+            render_images,
+            history,
+        }
+    }
+
     /// Imports a given history entry and replaces the current state with it.
     fn import_history_entry(&mut self, history_entry: &Arc<HistoryEntry>) {
         self.stroke_components = Arc::clone(&history_entry.stroke_components);
         self.trash_components = Arc::clone(&history_entry.trash_components);
         self.selection_components = Arc::clone(&history_entry.selection_components);
         self.chrono_components = Arc::clone(&history_entry.chrono_components);
+        self.lock_components = Arc::clone(&history_entry.lock_components);
+        self.recognition_components = Arc::clone(&history_entry.recognition_components);
+        self.author_components = Arc::clone(&history_entry.author_components);
 
         self.chrono_counter = history_entry.chrono_counter;
+        self.invalidate_chrono_order();
+        self.pages_state = Arc::clone(&history_entry.pages_state);
 
         // Since we don't store the tree in the history, we need to reload it.
         self.reload_tree();
@@ -251,7 +460,11 @@ impl StrokeStore {
                    self.history_pos
                );
         */
-        self.simple_style_undo()
+        let prev_strokes = Arc::clone(&self.stroke_components);
+        let widget_flags = self.simple_style_undo();
+        self.record_history_ops(&prev_strokes);
+
+        widget_flags
         /*
                log::debug!(
                    "after undo - history len: {}, pos: {:?}",
@@ -271,7 +484,11 @@ impl StrokeStore {
                    self.history_pos
                );
         */
-        self.simple_style_redo()
+        let prev_strokes = Arc::clone(&self.stroke_components);
+        let widget_flags = self.simple_style_redo();
+        self.record_history_ops(&prev_strokes);
+
+        widget_flags
         /*
                log::debug!(
                    "after redo - history len: {}, pos: {:?}",
@@ -422,6 +639,51 @@ impl StrokeStore {
         self.history_pos = None;
     }
 
+    /// The state of the document pages, as of the latest page operation or as imported by undo and redo.
+    /// Needs to be applied to the document after undo and redo when it changed.
+    pub fn pages_state(&self) -> &Arc<PagesState> {
+        &self.pages_state
+    }
+
+    /// Records the current state together with the state of the document pages, before the pages are changed.
+    /// After the pages are changed, their new state is set with `set_pages_state()`
+    pub fn record_pages_state(&mut self, pages_state: PagesState) -> WidgetFlags {
+        self.refresh_pages_state(pages_state);
+
+        self.record()
+    }
+
+    /// Changes of the pages state outside of page operations ( e.g. added comments ) are not part of the history,
+    /// so the current pages state and the history entries since the latest page operation are updated to the given state.
+    /// Needs to be called before the pages state is recorded or replaced by undo and redo, so these changes are not lost.
+    pub fn refresh_pages_state(&mut self, pages_state: PagesState) {
+        let prev = std::mem::replace(&mut self.pages_state, Arc::new(pages_state));
+        for entry in self.history.iter_mut() {
+            if Arc::ptr_eq(&entry.pages_state, &prev) {
+                Arc::make_mut(entry).pages_state = Arc::clone(&self.pages_state);
+            }
+        }
+    }
+
+    /// Sets the state of the document pages after they were changed. See `record_pages_state()`
+    pub fn set_pages_state(&mut self, pages_state: PagesState) {
+        self.pages_state = Arc::new(pages_state);
+    }
+
+    /// Wether there is a previous state to undo to
+    pub fn can_undo(&self) -> bool {
+        self.history_pos
+            .map(|pos| pos > 0)
+            .unwrap_or(!self.history.is_empty())
+    }
+
+    /// Wether there is a next state to redo to
+    pub fn can_redo(&self) -> bool {
+        self.history_pos
+            .map(|pos| pos + 1 < self.history.len())
+            .unwrap_or(false)
+    }
+
     pub fn clear_history(&mut self) {
         self.history.clear();
         self.history_pos = None;
@@ -430,6 +692,19 @@ impl StrokeStore {
     /// inserts a new stroke into the store. Optionally a desired layer can be specified, or the default stroke layer is used.
     /// stroke then needs to update its rendering
     pub fn insert_stroke(&mut self, stroke: Stroke, layer: Option<StrokeLayer>) -> StrokeKey {
+        let key = self.insert_stroke_unrecorded(stroke, layer);
+        self.record_insert_op(key);
+        self.touch_strokes(&[key]);
+
+        key
+    }
+
+    /// inserts a new stroke without recording it in the CRDT
+    fn insert_stroke_unrecorded(
+        &mut self,
+        stroke: Stroke,
+        layer: Option<StrokeLayer>,
+    ) -> StrokeKey {
         let bounds = stroke.bounds();
         let layer = layer.unwrap_or(stroke.extract_default_layer());
 
@@ -444,18 +719,34 @@ impl StrokeStore {
             key,
             Arc::new(ChronoComponent::new(self.chrono_counter, layer)),
         );
+        Arc::make_mut(&mut self.lock_components).insert(key, Arc::new(LockComponent::default()));
         self.render_components
             .insert(key, RenderComponent::default());
+        for render_components in self.viewport_render_components.values_mut() {
+            render_components.insert(key, RenderComponent::default());
+        }
+        self.add_render_damage_for_strokes(&[key]);
+        self.invalidate_chrono_order();
 
         key
     }
 
     /// permanently removes a stroke with the given key from the store
     pub fn remove_stroke(&mut self, key: StrokeKey) -> Option<Stroke> {
+        self.record_delete_op(key);
+        self.add_change_for_stroke(key);
+
         Arc::make_mut(&mut self.trash_components).remove(key);
         Arc::make_mut(&mut self.selection_components).remove(key);
         Arc::make_mut(&mut self.chrono_components).remove(key);
+        Arc::make_mut(&mut self.lock_components).remove(key);
+        Arc::make_mut(&mut self.recognition_components).remove(key);
+        Arc::make_mut(&mut self.author_components).remove(key);
         self.render_components.remove(key);
+        for render_components in self.viewport_render_components.values_mut() {
+            render_components.remove(key);
+        }
+        self.invalidate_chrono_order();
 
         self.key_tree.remove_with_key(key);
         Arc::make_mut(&mut self.stroke_components)
@@ -469,11 +760,19 @@ impl StrokeStore {
         Arc::make_mut(&mut self.trash_components).clear();
         Arc::make_mut(&mut self.selection_components).clear();
         Arc::make_mut(&mut self.chrono_components).clear();
+        Arc::make_mut(&mut self.lock_components).clear();
+        Arc::make_mut(&mut self.recognition_components).clear();
+        Arc::make_mut(&mut self.author_components).clear();
+        self.invalidate_chrono_order();
 
         self.chrono_counter = 0;
         self.clear_history();
 
         self.render_components.clear();
+        for render_components in self.viewport_render_components.values_mut() {
+            render_components.clear();
+        }
         self.key_tree.clear();
+        self.changes.set_all_changed();
     }
 }
@@ -0,0 +1,122 @@
+use super::{StrokeKey, StrokeStore};
+
+use p2d::bounding_volume::AABB;
+use piet::{RenderContext, Text, TextLayout, TextLayoutBuilder};
+use rnote_compose::helpers::Vector2Helpers;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Holds the text that was recognized from handwritten strokes.
+/// It is written as invisible text layer into exported PDFs, so that the notes are searchable and copyable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename = "recognition_component")]
+pub struct RecognitionComponent {
+    #[serde(rename = "text")]
+    pub text: String,
+}
+
+impl Default for RecognitionComponent {
+    fn default() -> Self {
+        Self {
+            text: String::default(),
+        }
+    }
+}
+
+impl RecognitionComponent {
+    /// The text layer alpha. Cairo drops drawing operations with a fully transparent source, so it needs to be slightly above zero
+    pub const TEXT_LAYER_ALPHA: f64 = 0.01;
+
+    /// Draws the recognized text, stretched to the given bounds and (almost) invisible
+    pub fn draw_text_layer(&self, cx: &mut impl RenderContext, bounds: AABB) -> anyhow::Result<()> {
+        let text = self.text.trim();
+        if text.is_empty() {
+            return Ok(());
+        }
+        let extents = bounds.extents();
+
+        let text_layout = cx
+            .text()
+            .new_text_layout(text.to_string())
+            .text_color(piet::Color::rgba(0.0, 0.0, 0.0, Self::TEXT_LAYER_ALPHA))
+            .font(piet::FontFamily::SANS_SERIF, extents[1].max(1.0))
+            .build()
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let text_size = na::vector![text_layout.size().width, text_layout.size().height];
+        if text_size[0] <= 0.0 || text_size[1] <= 0.0 {
+            return Ok(());
+        }
+
+        // Stretch the text so that selecting it in a pdf reader matches the handwriting
+        cx.save().map_err(|e| anyhow::anyhow!("{}", e))?;
+        cx.transform(
+            kurbo::Affine::translate(bounds.mins.coords.to_kurbo_vec())
+                * kurbo::Affine::scale_non_uniform(
+                    extents[0] / text_size[0],
+                    extents[1] / text_size[1],
+                ),
+        );
+        cx.draw_text(&text_layout, kurbo::Point::ZERO);
+        cx.restore().map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        Ok(())
+    }
+}
+
+/// Systems that are related to the recognized text of strokes
+impl StrokeStore {
+    /// The recognized text of the stroke, if there is any.
+    pub fn recognized_text(&self, key: StrokeKey) -> Option<&str> {
+        self.recognition_components
+            .get(key)
+            .map(|recognition_comp| recognition_comp.text.as_str())
+            .filter(|text| !text.is_empty())
+    }
+
+    /// Sets the recognized text of the stroke. `None` removes it
+    pub fn set_recognized_text(&mut self, key: StrokeKey, text: Option<String>) {
+        if !self.stroke_components.contains_key(key) {
+            log::debug!(
+                "get stroke in set_recognized_text() returned None for stroke with key {:?}",
+                key
+            );
+            return;
+        }
+
+        match text {
+            Some(text) => {
+                Arc::make_mut(&mut self.recognition_components)
+                    .insert(key, Arc::new(RecognitionComponent { text }));
+            }
+            None => {
+                Arc::make_mut(&mut self.recognition_components).remove(key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::strokes::Stroke;
+    use crate::StrokeStore;
+
+    #[test]
+    fn recognized_text_roundtrip() {
+        let mut store = StrokeStore::default();
+        let key = store.insert_stroke(
+            Stroke::line_fixture(na::vector![0.0, 0.0], na::vector![100.0, 50.0]),
+            None,
+        );
+        assert_eq!(store.recognized_text(key), None);
+
+        store.set_recognized_text(key, Some(String::from("hello")));
+        assert_eq!(store.recognized_text(key), Some("hello"));
+
+        let store_snapshot = store.take_store_snapshot();
+        store.set_recognized_text(key, None);
+        assert_eq!(store.recognized_text(key), None);
+
+        store.import_snapshot(&store_snapshot);
+        assert_eq!(store.recognized_text(key), Some("hello"));
+    }
+}
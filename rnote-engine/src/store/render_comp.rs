@@ -1,17 +1,51 @@
+use super::chrono_comp::StrokeLayer;
 use super::{Stroke, StrokeKey, StrokeStore};
 use crate::engine::visual_debug;
 use crate::engine::{EngineTask, EngineTaskSender};
 use crate::strokes::strokebehaviour::GeneratedStrokeImages;
 use crate::strokes::StrokeBehaviour;
 use crate::utils::{GdkRGBAHelpers, GrapheneRectHelpers};
+use crate::viewport::ViewportKey;
+use crate::watchdog::TaskWatchdog;
 use crate::{render, DrawBehaviour, RnoteEngine};
 
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
 use anyhow::Context;
 use gtk4::{gdk, graphene, gsk, Snapshot};
 use p2d::bounding_volume::{BoundingVolume, AABB};
 use rnote_compose::color;
 use rnote_compose::helpers::AABBHelpers;
 use rnote_compose::shapes::ShapeBehaviour;
+
+/// The counter for the render generations. Global so that generations are unique even across reloaded render components.
+static RENDER_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Which strokes are in focus when drawing. Strokes out of focus are drawn faded.
+/// This only affects drawing, the strokes themselves are not modified.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderFocus {
+    /// All strokes are in focus
+    All,
+    /// Only the selected strokes are in focus
+    Selection,
+    /// Only the strokes in the layer are in focus
+    Layer(StrokeLayer),
+}
+
+impl Default for RenderFocus {
+    fn default() -> Self {
+        Self::All
+    }
+}
+
+impl RenderFocus {
+    /// The opacity strokes out of focus are drawn with
+    pub const FADED_OPACITY: f64 = 0.3;
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum RenderCompState {
     Complete,
@@ -31,6 +65,11 @@ pub struct RenderComponent {
     pub images: Vec<render::Image>,
     pub rendernodes: Vec<gsk::RenderNode>,
     pub(super) state: RenderCompState,
+    /// The generation of the current rendering. Bumped when spawning render tasks or when the rendering gets outdated,
+    /// so that results of outdated tasks are dropped cheaply and queued outdated tasks don't generate images at all.
+    pub(super) generation: Arc<AtomicU64>,
+    /// The image scale the current images were generated with
+    pub(super) image_scale: f64,
 }
 
 impl Default for RenderComponent {
@@ -39,11 +78,184 @@ impl Default for RenderComponent {
             state: RenderCompState::default(),
             images: vec![],
             rendernodes: vec![],
+            generation: Arc::new(AtomicU64::new(0)),
+            image_scale: 1.0,
+        }
+    }
+}
+
+impl RenderComponent {
+    /// Starts a new generation, outdating all previously spawned render tasks.
+    pub(super) fn next_generation(&self) -> u64 {
+        let generation = RENDER_GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+        self.generation.store(generation, Ordering::Relaxed);
+        generation
+    }
+
+    /// Wether the generation is the current one
+    pub(super) fn is_current_generation(&self, generation: u64) -> bool {
+        self.generation.load(Ordering::Relaxed) == generation
+    }
+
+    /// The image scale from which on images generated with a lower image scale are regenerated, so that zoomed in strokes stay sharp
+    pub const SHARP_RERENDER_IMAGE_SCALE_THRESHOLD: f64 = 2.0;
+    /// The ratio between the current image scale and the one of the images, above which they are regenerated.
+    /// Acts as hysteresis, so that the images are not regenerated on every small zoom change.
+    pub const SHARP_RERENDER_RATIO: f64 = 1.5;
+
+    /// Wether the images are upscaled so much at the given image scale that they get blurry and need to be regenerated
+    pub(super) fn needs_sharp_rerender(&self, image_scale: f64) -> bool {
+        image_scale >= Self::SHARP_RERENDER_IMAGE_SCALE_THRESHOLD
+            && image_scale > self.image_scale * Self::SHARP_RERENDER_RATIO
+    }
+}
+
+/// Tracks the regions which were damaged by edits since the last pass that regenerated the rendering in the viewport.
+/// When the viewport and the image scale did not change since then, only the strokes intersecting the damaged regions need to be checked.
+#[derive(Debug, Clone, Default)]
+pub(super) struct RenderDamage {
+    regions: Vec<AABB>,
+    /// everything is damaged, e.g. after importing a snapshot or undo / redo
+    full: bool,
+    /// the viewport and image scale of the last pass
+    last_pass: Option<(AABB, f64)>,
+}
+
+impl RenderDamage {
+    /// The max number of tracked regions. When exceeding it, everything is considered damaged
+    const MAX_REGIONS: usize = 64;
+
+    pub(super) fn add_region(&mut self, bounds: AABB) {
+        if self.full || bounds.assert_valid().is_err() {
+            return;
+        }
+
+        if let Some(region) = self
+            .regions
+            .iter_mut()
+            .find(|region| region.intersects(&bounds))
+        {
+            *region = region.merged(&bounds);
+            return;
+        }
+        self.regions.push(bounds);
+
+        if self.regions.len() > Self::MAX_REGIONS {
+            self.set_full();
+        }
+    }
+
+    pub(super) fn set_full(&mut self) {
+        self.regions.clear();
+        self.full = true;
+    }
+
+    /// Takes the damaged regions for a new pass. Returns None if the entire viewport needs to be checked.
+    fn take_for_pass(&mut self, viewport: AABB, image_scale: f64) -> Option<Vec<AABB>> {
+        let unchanged = self.last_pass == Some((viewport, image_scale));
+        let full = std::mem::take(&mut self.full);
+        let regions = std::mem::take(&mut self.regions);
+        self.last_pass = Some((viewport, image_scale));
+
+        if unchanged && !full {
+            Some(regions)
+        } else {
+            None
         }
     }
 }
 
+/// Counts the changes of the strokes and keeps the regions of the latest ones,
+/// so that caches of the rendering of a region ( e.g. the onion skin page ) can check wether they are outdated.
+/// Unlike the render damage it is not taken by the rendering passes.
+#[derive(Debug, Clone, Default)]
+pub(super) struct StrokeChanges {
+    count: u64,
+    /// Changes up to this count are not tracked anymore, so everything is considered changed
+    untracked_until: u64,
+    /// The regions of the latest changes, with their count
+    regions: VecDeque<(u64, AABB)>,
+}
+
+impl StrokeChanges {
+    /// The max number of tracked regions. The oldest regions are dropped when exceeding it
+    const MAX_REGIONS: usize = 256;
+
+    pub(super) fn add_region(&mut self, bounds: AABB) {
+        if bounds.assert_valid().is_err() {
+            return;
+        }
+        self.count += 1;
+        self.regions.push_back((self.count, bounds));
+
+        if self.regions.len() > Self::MAX_REGIONS {
+            if let Some((count, _)) = self.regions.pop_front() {
+                self.untracked_until = count;
+            }
+        }
+    }
+
+    pub(super) fn set_all_changed(&mut self) {
+        self.count += 1;
+        self.untracked_until = self.count;
+        self.regions.clear();
+    }
+
+    fn changed_since(&self, count: u64, bounds: AABB) -> bool {
+        count < self.untracked_until
+            || self
+                .regions
+                .iter()
+                .rev()
+                .take_while(|(region_count, _)| *region_count > count)
+                .any(|(_, region)| region.intersects(&bounds))
+    }
+}
+
 impl StrokeStore {
+    /// The number of threads of the dedicated render pool. None if the global rayon pool is used.
+    pub fn render_pool_n_threads(&self) -> Option<usize> {
+        self.render_pool
+            .as_ref()
+            .map(|render_pool| render_pool.current_num_threads())
+    }
+
+    /// Sets up a dedicated thread pool with the given number of threads for rendering, so that rendering does not compete with other tasks ( saving, exporting ) on the global pool.
+    /// None switches back to the global rayon pool.
+    pub fn set_render_pool_n_threads(&mut self, n_threads: Option<usize>) -> anyhow::Result<()> {
+        self.render_pool = match n_threads {
+            Some(n_threads) => Some(Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(n_threads)
+                    .thread_name(|i| format!("rnote-render-{}", i))
+                    .build()
+                    .context(
+                        "building the render thread pool failed in set_render_pool_n_threads()",
+                    )?,
+            )),
+            None => None,
+        };
+
+        Ok(())
+    }
+
+    /// The watchdog for the background tasks
+    pub fn watchdog(&self) -> &TaskWatchdog {
+        &self.watchdog
+    }
+
+    /// Spawns a render task, either on the dedicated render pool or the global rayon pool.
+    /// Render tasks are short and quickly outdated, so they are not watched by the watchdog.
+    fn spawn_render_task<F>(&self, func: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        match self.render_pool.as_ref() {
+            Some(render_pool) => render_pool.spawn(func),
+            None => rayon::spawn(func),
+        }
+    }
+
     /// Reloads the slotmap with empty render components from the keys returned from the primary map, stroke_components.
     pub fn reload_render_components_slotmap(&mut self) {
         self.render_components = slotmap::SecondaryMap::new();
@@ -51,6 +263,53 @@ impl StrokeStore {
             self.render_components
                 .insert(key, RenderComponent::default());
         });
+        for render_components in self.viewport_render_components.values_mut() {
+            *render_components = self
+                .stroke_components
+                .keys()
+                .map(|key| (key, RenderComponent::default()))
+                .collect();
+        }
+        self.damage.set_full();
+    }
+
+    /// Adds empty render components for a new additional viewport. Its rendering then needs to be regenerated
+    pub fn add_viewport_render_components(&mut self, viewport_key: ViewportKey) {
+        self.viewport_render_components.insert(
+            viewport_key,
+            self.stroke_components
+                .keys()
+                .map(|key| (key, RenderComponent::default()))
+                .collect(),
+        );
+    }
+
+    /// Removes the render components of the additional viewport
+    pub fn remove_viewport_render_components(&mut self, viewport_key: ViewportKey) {
+        self.viewport_render_components.remove(&viewport_key);
+    }
+
+    /// The render components of the main viewport when the viewport key is None, else of the additional viewport
+    fn render_components_for_viewport(
+        &self,
+        viewport_key: Option<ViewportKey>,
+    ) -> Option<&slotmap::SecondaryMap<StrokeKey, RenderComponent>> {
+        match viewport_key {
+            None => Some(&self.render_components),
+            Some(viewport_key) => self.viewport_render_components.get(&viewport_key),
+        }
+    }
+
+    /// The render components of the stroke for the main and all additional viewports
+    pub(super) fn render_comps_for_key_mut(
+        &mut self,
+        key: StrokeKey,
+    ) -> impl Iterator<Item = &mut RenderComponent> {
+        self.render_components.get_mut(key).into_iter().chain(
+            self.viewport_render_components
+                .values_mut()
+                .filter_map(move |render_components| render_components.get_mut(key)),
+        )
     }
 
     /// Returns false if rendering is not supported
@@ -64,9 +323,31 @@ impl StrokeStore {
             .map(|render_comp| render_comp.state)
     }
 
+    /// The count of the changes of the strokes so far. See `strokes_changed_since()`
+    pub fn changes_count(&self) -> u64 {
+        self.changes.count
+    }
+
+    /// Wether strokes in the bounds changed after the given changes count, so that a cached rendering of them is outdated
+    pub fn strokes_changed_since(&self, changes_count: u64, bounds: AABB) -> bool {
+        self.changes.changed_since(changes_count, bounds)
+    }
+
+    /// Records that the stroke changed in its current bounds, e.g. when it is trashed or removed
+    pub(super) fn add_change_for_stroke(&mut self, key: StrokeKey) {
+        if let Some(stroke) = self.stroke_components.get(key) {
+            self.changes.add_region(stroke.bounds());
+        }
+    }
+
     pub fn set_rendering_dirty(&mut self, key: StrokeKey) {
-        if let Some(render_comp) = self.render_components.get_mut(key) {
+        for render_comp in self.render_comps_for_key_mut(key) {
             render_comp.state = RenderCompState::Dirty;
+            render_comp.next_generation();
+        }
+        if let Some(stroke) = self.stroke_components.get(key) {
+            self.damage.add_region(stroke.bounds());
+            self.changes.add_region(stroke.bounds());
         }
     }
 
@@ -76,6 +357,27 @@ impl StrokeStore {
 
     pub fn set_rendering_dirty_all_keys(&mut self) {
         self.set_rendering_dirty_for_strokes(&self.keys_unordered());
+        self.damage.set_full();
+        self.changes.set_all_changed();
+    }
+
+    /// Marks the bounds of the strokes and their current images as damaged, so that they are checked on the next rendering pass.
+    pub(super) fn add_render_damage_for_strokes(&mut self, keys: &[StrokeKey]) {
+        for &key in keys {
+            let stroke_bounds = self
+                .stroke_components
+                .get(key)
+                .map(|stroke| stroke.bounds());
+
+            if let Some(bounds) = stroke_bounds
+                .into_iter()
+                .chain(self.gen_bounds_for_stroke_images(key))
+                .reduce(|acc, x| acc.merged(&x))
+            {
+                self.damage.add_region(bounds);
+                self.changes.add_region(bounds);
+            }
+        }
     }
 
     pub fn gen_bounds_for_stroke_images(&self, key: StrokeKey) -> Option<AABB> {
@@ -132,6 +434,9 @@ impl StrokeStore {
             let images = stroke
                 .gen_images(viewport, image_scale)
                 .context("gen_images() failed  in regenerate_rendering_for_stroke()")?;
+            // outdates the running render tasks
+            render_comp.next_generation();
+            render_comp.image_scale = image_scale;
 
             match images {
                 GeneratedStrokeImages::Partial { images, viewport } => {
@@ -193,19 +498,31 @@ impl StrokeStore {
 
             // indicates that a task is now started rendering the stroke
             render_comp.state = RenderCompState::BusyRenderingInTask;
+            render_comp.image_scale = image_scale;
+            let generation = render_comp.next_generation();
+            let current_generation = Arc::clone(&render_comp.generation);
 
             // Spawn a new thread for image rendering
-            rayon::spawn(move || match stroke.gen_images(viewport, image_scale) {
-                Ok(images) => {
-                    tasks_tx.unbounded_send(EngineTask::UpdateStrokeWithImages {
+            self.spawn_render_task(move || {
+                // Skip generating images when the task was already outdated while waiting in the queue
+                if current_generation.load(Ordering::Relaxed) != generation {
+                    return;
+                }
+
+                match stroke.gen_images(viewport, image_scale) {
+                    Ok(images) => {
+                        tasks_tx.unbounded_send(EngineTask::UpdateStrokeWithImages {
                             key,
+                            viewport_key: None,
                             images,
+                            generation,
                         }).unwrap_or_else(|e| {
                             log::error!("tasks_tx.send() UpdateStrokeWithImages failed in regenerate_rendering_for_stroke_threaded() for stroke with key {:?}, with Err, {}",key, e);
                         });
-                }
-                Err(e) => {
-                    log::debug!("stroke.gen_image() failed in regenerate_rendering_for_stroke_threaded() for stroke with key {:?}, with Err {}", key, e);
+                    }
+                    Err(e) => {
+                        log::debug!("stroke.gen_image() failed in regenerate_rendering_for_stroke_threaded() for stroke with key {:?}, with Err {}", key, e);
+                    }
                 }
             });
         }
@@ -219,11 +536,81 @@ impl StrokeStore {
         viewport: AABB,
         image_scale: f64,
     ) {
-        let keys = self.render_components.keys().collect::<Vec<StrokeKey>>();
+        // Strokes in the viewport need to be loaded to be rendered
+        self.load_deferred_strokes_intersecting_bounds(
+            viewport.extend_by(viewport.extents() * render::VIEWPORT_EXTENTS_MARGIN_FACTOR),
+        );
+
+        // When the viewport did not change since the last pass, only the strokes in the damaged regions can need rerendering
+        let keys = match self.damage.take_for_pass(viewport, image_scale) {
+            Some(regions) if !force_regenerate => regions
+                .into_iter()
+                .flat_map(|region| self.key_tree.keys_intersecting_bounds(region))
+                .collect::<HashSet<StrokeKey>>()
+                .into_iter()
+                .collect::<Vec<StrokeKey>>(),
+            _ => self.render_components.keys().collect::<Vec<StrokeKey>>(),
+        };
+
+        self.regenerate_rendering_for_keys_in_viewport_threaded(
+            tasks_tx,
+            &keys,
+            None,
+            force_regenerate,
+            viewport,
+            image_scale,
+        );
+    }
+
+    /// Regenerates the rendering of all keys for the additional viewport that need rerendering.
+    /// The damage tracking is only done for the main viewport, so all keys are checked.
+    pub fn regenerate_rendering_in_additional_viewport_threaded(
+        &mut self,
+        tasks_tx: EngineTaskSender,
+        viewport_key: ViewportKey,
+        viewport: AABB,
+        image_scale: f64,
+    ) {
+        self.load_deferred_strokes_intersecting_bounds(
+            viewport.extend_by(viewport.extents() * render::VIEWPORT_EXTENTS_MARGIN_FACTOR),
+        );
+
+        let keys = match self.viewport_render_components.get(&viewport_key) {
+            Some(render_components) => render_components.keys().collect::<Vec<StrokeKey>>(),
+            None => return,
+        };
+
+        self.regenerate_rendering_for_keys_in_viewport_threaded(
+            tasks_tx,
+            &keys,
+            Some(viewport_key),
+            false,
+            viewport,
+            image_scale,
+        );
+    }
+
+    /// Regenerates the rendering of the given keys that need rerendering, for the main viewport when the viewport key is None, else for the additional viewport.
+    fn regenerate_rendering_for_keys_in_viewport_threaded(
+        &mut self,
+        tasks_tx: EngineTaskSender,
+        keys: &[StrokeKey],
+        viewport_key: Option<ViewportKey>,
+        force_regenerate: bool,
+        viewport: AABB,
+        image_scale: f64,
+    ) {
+        keys.iter().for_each(|&key| {
+            let render_comp = match viewport_key {
+                None => self.render_components.get_mut(key),
+                Some(viewport_key) => self
+                    .viewport_render_components
+                    .get_mut(&viewport_key)
+                    .and_then(|render_components| render_components.get_mut(key)),
+            };
 
-        keys.into_iter().for_each(|key| {
             if let (Some(stroke), Some(render_comp)) =
-                (self.stroke_components.get(key), self.render_components.get_mut(key))
+                (self.stroke_components.get(key), render_comp)
             {
                 let tasks_tx = tasks_tx.clone();
                 let stroke_bounds = stroke.bounds();
@@ -241,8 +628,12 @@ impl StrokeStore {
                     return;
                 }
 
+                // images that became blurry at high zoom are regenerated, e.g. while zooming temporarily
+                let sharp_rerender = render_comp.state != RenderCompState::BusyRenderingInTask
+                    && render_comp.needs_sharp_rerender(image_scale);
+
                 // only check if rerendering is not forced
-                if !force_regenerate {
+                if !force_regenerate && !sharp_rerender {
                     match render_comp.state {
                         RenderCompState::Complete | RenderCompState::BusyRenderingInTask => {
                             return;
@@ -264,24 +655,34 @@ impl StrokeStore {
 
                 // indicates that a task is now started rendering the stroke
                 render_comp.state = RenderCompState::BusyRenderingInTask;
+                render_comp.image_scale = image_scale;
+                let generation = render_comp.next_generation();
+                let current_generation = Arc::clone(&render_comp.generation);
 
                 let stroke = stroke.clone();
 
                 //log::debug!("updating stroke with viewport: {:#?}", viewport);
 
                 // Spawn a new thread for image rendering
-                rayon::spawn(move || {
+                self.spawn_render_task(move || {
+                    // Skip generating images when the task was already outdated while waiting in the queue, e.g. when zooming fast
+                    if current_generation.load(Ordering::Relaxed) != generation {
+                        return;
+                    }
+
                     match stroke.gen_images(viewport, image_scale) {
                         Ok(images) => {
                             tasks_tx.unbounded_send(EngineTask::UpdateStrokeWithImages {
                                 key,
+                                viewport_key,
                                 images,
+                                generation,
                             }).unwrap_or_else(|e| {
-                                log::error!("tasks_tx.send() UpdateStrokeWithImages failed in regenerate_rendering_in_viewport_threaded() for stroke with key {:?}, with Err, {}",key, e);
+                                log::error!("tasks_tx.send() UpdateStrokeWithImages failed in regenerate_rendering_for_keys_in_viewport_threaded() for stroke with key {:?}, with Err, {}",key, e);
                             });
                         }
                         Err(e) => {
-                            log::debug!("stroke.gen_image() failed in regenerate_rendering_in_viewport_threaded() for stroke with key {:?}, with Err {}", key, e);
+                            log::debug!("stroke.gen_image() failed in regenerate_rendering_for_keys_in_viewport_threaded() for stroke with key {:?}, with Err {}", key, e);
                         }
                     }
                 });
@@ -316,7 +717,8 @@ impl StrokeStore {
                 Stroke::ShapeStroke(_)
                 | Stroke::TextStroke(_)
                 | Stroke::VectorImage(_)
-                | Stroke::BitmapImage(_) => {
+                | Stroke::BitmapImage(_)
+                | Stroke::Deferred(_) => {
                     self.regenerate_rendering_for_stroke_threaded(
                         tasks_tx,
                         key,
@@ -330,12 +732,36 @@ impl StrokeStore {
     }
 
     /// Replaces the entire current rendering with the given new images. Alos updates the renderstate
+    /// Wether the render generation is the current one of the stroke. Results of tasks with outdated generations should be dropped.
+    /// The viewport key is None for the main viewport.
+    pub fn is_current_render_generation(
+        &self,
+        key: StrokeKey,
+        viewport_key: Option<ViewportKey>,
+        generation: u64,
+    ) -> bool {
+        self.render_components_for_viewport(viewport_key)
+            .and_then(|render_components| render_components.get(key))
+            .map(|render_comp| render_comp.is_current_generation(generation))
+            .unwrap_or(false)
+    }
+
+    /// The viewport key is None for the main viewport.
     pub fn replace_rendering_with_images(
         &mut self,
         key: StrokeKey,
+        viewport_key: Option<ViewportKey>,
         images: GeneratedStrokeImages,
     ) -> anyhow::Result<()> {
-        if let Some(render_comp) = self.render_components.get_mut(key) {
+        let render_comp = match viewport_key {
+            None => self.render_components.get_mut(key),
+            Some(viewport_key) => self
+                .viewport_render_components
+                .get_mut(&viewport_key)
+                .and_then(|render_components| render_components.get_mut(key)),
+        };
+
+        if let Some(render_comp) = render_comp {
             match images {
                 GeneratedStrokeImages::Partial { images, viewport } => {
                     let rendernodes = render::Image::images_to_rendernodes(&images)?;
@@ -381,30 +807,116 @@ impl StrokeStore {
         Ok(())
     }
 
-    /// Draws all strokes on the snapshot
-    pub fn draw_strokes_to_snapshot(&self, snapshot: &Snapshot, doc_bounds: AABB, viewport: AABB) {
+    /// Draws all strokes on the snapshot. Strokes out of focus are drawn faded.
+    /// The strokes in the highlighter layer are blended with the strokes and images underneath them with multiply blending.
+    /// The rendering of the main viewport is drawn when the viewport key is None, else the rendering of the additional viewport.
+    pub fn draw_strokes_to_snapshot(
+        &self,
+        snapshot: &Snapshot,
+        doc_bounds: AABB,
+        viewport: AABB,
+        viewport_key: Option<ViewportKey>,
+        focus: RenderFocus,
+    ) {
+        let render_components = match self.render_components_for_viewport(viewport_key) {
+            Some(render_components) => render_components,
+            None => return,
+        };
+
         snapshot.push_clip(&graphene::Rect::from_p2d_aabb(doc_bounds));
 
-        self.stroke_keys_as_rendered_intersecting_bounds(viewport)
-            .iter()
-            .for_each(|&key| {
-                if let (Some(stroke), Some(render_comp)) = (
-                    self.stroke_components.get(key),
-                    self.render_components.get(key),
-                ) {
-                    if render_comp.rendernodes.is_empty() {
-                        Self::draw_stroke_placeholder(snapshot, stroke.bounds())
-                    }
+        let layer = |key: StrokeKey| {
+            self.chrono_components
+                .get(key)
+                .map(|chrono_comp| chrono_comp.layer)
+                .unwrap_or_default()
+        };
+        let mut keys = self
+            .stroke_keys_as_rendered_intersecting_bounds(viewport)
+            .into_iter()
+            .peekable();
 
-                    for rendernode in render_comp.rendernodes.iter() {
-                        snapshot.append_node(rendernode);
-                    }
+        // The keys are in rendering order, so the layers underneath the highlighters come first
+        let below_snapshot = Snapshot::new();
+        while let Some(key) = keys.next_if(|&key| layer(key) < StrokeLayer::Highlighter) {
+            self.draw_stroke_to_snapshot(&below_snapshot, render_components, key, focus);
+        }
+        let highlighter_snapshot = Snapshot::new();
+        while let Some(key) = keys.next_if(|&key| layer(key) == StrokeLayer::Highlighter) {
+            self.draw_stroke_to_snapshot(&highlighter_snapshot, render_components, key, focus);
+        }
+
+        match (below_snapshot.to_node(), highlighter_snapshot.to_node()) {
+            (Some(below_node), Some(highlighter_node)) => {
+                snapshot.push_blend(gsk::BlendMode::Multiply);
+                snapshot.append_node(&below_node);
+                snapshot.pop();
+                snapshot.append_node(&highlighter_node);
+                snapshot.pop();
+            }
+            (below_node, highlighter_node) => {
+                for node in below_node.iter().chain(highlighter_node.iter()) {
+                    snapshot.append_node(node);
                 }
-            });
+            }
+        }
+
+        for key in keys {
+            self.draw_stroke_to_snapshot(snapshot, render_components, key, focus);
+        }
 
         snapshot.pop();
     }
 
+    /// Wether the stroke is in focus. See `RenderFocus`
+    fn in_focus(&self, key: StrokeKey, focus: RenderFocus) -> bool {
+        match focus {
+            RenderFocus::All => true,
+            RenderFocus::Selection => self.selected(key).unwrap_or(false),
+            RenderFocus::Layer(layer) => self
+                .chrono_components
+                .get(key)
+                .map(|chrono_comp| chrono_comp.layer == layer)
+                .unwrap_or(false),
+        }
+    }
+
+    /// Draws the rendering of a single stroke on the snapshot, or a placeholder if it is not rendered yet
+    fn draw_stroke_to_snapshot(
+        &self,
+        snapshot: &Snapshot,
+        render_components: &slotmap::SecondaryMap<StrokeKey, RenderComponent>,
+        key: StrokeKey,
+        focus: RenderFocus,
+    ) {
+        if let (Some(stroke), Some(render_comp)) =
+            (self.stroke_components.get(key), render_components.get(key))
+        {
+            if render_comp.rendernodes.is_empty() {
+                Self::draw_stroke_placeholder(snapshot, stroke.bounds())
+            }
+
+            let mut opacity = stroke.opacity();
+            if stroke.is_reference() {
+                opacity *= Stroke::REFERENCE_OPACITY;
+            }
+            if !self.in_focus(key, focus) {
+                opacity *= RenderFocus::FADED_OPACITY;
+            }
+            if opacity < 1.0 {
+                snapshot.push_opacity(opacity);
+            }
+
+            for rendernode in render_comp.rendernodes.iter() {
+                snapshot.append_node(rendernode);
+            }
+
+            if opacity < 1.0 {
+                snapshot.pop();
+            }
+        }
+    }
+
     // Draws the given strokes on a piet render context. Note that every given stroke get drawn, even the trashed ones.
     pub fn draw_stroke_keys_to_piet(
         &self,
@@ -499,7 +1011,7 @@ impl StrokeStore {
     ) -> anyhow::Result<()> {
         let border_widths = 1.0 / engine.camera.total_zoom();
 
-        self.keys_sorted_chrono().into_iter().for_each(|key| {
+        self.keys_sorted_chrono_iter().for_each(|key| {
             if let Some(stroke) = self.stroke_components.get(key) {
                 // Push opacity for strokes which are normally hidden
                 if let Some(trash_comp) = self.trash_components.get(key) {
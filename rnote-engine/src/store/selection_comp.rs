@@ -1,6 +1,7 @@
 use super::{StrokeKey, StrokeStore};
+use rnote_compose::shapes::ShapeBehaviour;
 
-use p2d::bounding_volume::AABB;
+use p2d::bounding_volume::{BoundingVolume, AABB};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
@@ -17,8 +18,21 @@ impl Default for SelectionComponent {
     }
 }
 
+/// Criteria to select strokes by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectionCriteria {
+    /// Strokes with the same color as the reference stroke
+    SameColor,
+    /// Strokes of the same type as the reference stroke
+    SameType,
+    /// Strokes in the same layer as the reference stroke
+    SameLayer,
+    /// Strokes which lie within the given bounds, e.g. the current page
+    WithinBounds(AABB),
+}
+
 impl SelectionComponent {
-    const SELECTION_DUPLICATION_OFFSET: na::Vector2<f64> = na::vector![20.0, 20.0];
+    pub const SELECTION_DUPLICATION_OFFSET: na::Vector2<f64> = na::vector![20.0, 20.0];
 
     pub fn new(selected: bool) -> Self {
         Self { selected }
@@ -26,9 +40,9 @@ impl SelectionComponent {
 }
 
 impl StrokeStore {
-    /// Returns false if selecting is unsupported
+    /// Returns false if selecting is unsupported. Reference images and locked strokes can't be selected.
     pub fn can_select(&self, key: StrokeKey) -> bool {
-        self.selection_components.get(key).is_some()
+        self.selection_components.get(key).is_some() && !self.is_reference(key) && !self.locked(key)
     }
 
     pub fn selected(&self, key: StrokeKey) -> Option<bool> {
@@ -67,10 +81,7 @@ impl StrokeStore {
     /// Returns the selection keys in the order that they should be rendered.
     /// Does not return the not-selected stroke keys.
     pub fn selection_keys_as_rendered(&self) -> Vec<StrokeKey> {
-        let keys_sorted_chrono = self.keys_sorted_chrono();
-
-        keys_sorted_chrono
-            .into_iter()
+        self.keys_sorted_chrono_iter()
             .filter(|&key| {
                 !(self.trashed(key).unwrap_or(false)) && (self.selected(key).unwrap_or(false))
             })
@@ -94,27 +105,87 @@ impl StrokeStore {
         self.bounds_for_strokes(&self.selection_keys_unordered())
     }
 
+    /// Returns the selectable keys in the order that they should be rendered which match the criteria.
+    /// The attributes are compared to the ones of the reference stroke, which is not needed for `SelectionCriteria::WithinBounds`.
+    pub fn keys_matching_criteria(
+        &self,
+        criteria: SelectionCriteria,
+        reference: Option<StrokeKey>,
+    ) -> Vec<StrokeKey> {
+        let reference = reference.and_then(|key| Some((key, self.stroke_components.get(key)?)));
+
+        self.keys_sorted_chrono_iter()
+            .filter(|&key| !(self.trashed(key).unwrap_or(false)) && self.can_select(key))
+            .filter(|&key| {
+                let stroke = match self.stroke_components.get(key) {
+                    Some(stroke) => stroke,
+                    None => return false,
+                };
+
+                match (criteria, reference) {
+                    (SelectionCriteria::SameColor, Some((_, reference_stroke))) => {
+                        reference_stroke.color().is_some()
+                            && stroke.color() == reference_stroke.color()
+                    }
+                    (SelectionCriteria::SameType, Some((_, reference_stroke))) => {
+                        stroke.type_name() == reference_stroke.type_name()
+                    }
+                    (SelectionCriteria::SameLayer, Some((reference_key, _))) => {
+                        self.stroke_layer(key) == self.stroke_layer(reference_key)
+                    }
+                    (SelectionCriteria::WithinBounds(bounds), _) => {
+                        bounds.contains(&stroke.bounds())
+                    }
+                    (_, None) => false,
+                }
+            })
+            .collect::<Vec<StrokeKey>>()
+    }
+
     /// Duplicates the selected keys
     /// the returned, duplicated strokes then need to update their geometry and rendering
     pub fn duplicate_selection(&mut self) -> Vec<StrokeKey> {
+        self.duplicate_selection_with_offset(SelectionComponent::SELECTION_DUPLICATION_OFFSET)
+    }
+
+    /// Duplicates the selected keys, the duplicates are translated by the given offset
+    /// the returned, duplicated strokes then need to update their geometry and rendering
+    pub fn duplicate_selection_with_offset(&mut self, offset: na::Vector2<f64>) -> Vec<StrokeKey> {
+        self.array_duplicate_selection(1, offset)
+    }
+
+    /// Duplicates the selected keys n times, the copy with index i ( starting at 1 ) is translated by `spacing * i`.
+    /// All copies are selected afterwards.
+    /// the returned, duplicated strokes then need to update their geometry and rendering
+    pub fn array_duplicate_selection(
+        &mut self,
+        n_copies: usize,
+        spacing: na::Vector2<f64>,
+    ) -> Vec<StrokeKey> {
         let old_selected = self.selection_keys_as_rendered();
+        if n_copies == 0 || old_selected.is_empty() {
+            return vec![];
+        }
         self.set_selected_keys(&old_selected, false);
 
-        let new_selected = old_selected
-            .iter()
-            .filter_map(|&key| {
-                let new_key =
-                    self.insert_stroke((**self.stroke_components.get(key)?).clone(), None);
-                self.set_selected(new_key, true);
-                Some(new_key)
-            })
-            .collect::<Vec<StrokeKey>>();
+        let mut new_selected = Vec::with_capacity(old_selected.len() * n_copies);
+
+        for i in 1..=n_copies {
+            let copy_keys = old_selected
+                .iter()
+                .filter_map(|&key| {
+                    let new_key =
+                        self.insert_stroke((**self.stroke_components.get(key)?).clone(), None);
+                    self.set_selected(new_key, true);
+                    Some(new_key)
+                })
+                .collect::<Vec<StrokeKey>>();
 
-        // Offsetting the new selected stroke to make the duplication apparent
-        self.translate_strokes(
-            &new_selected,
-            SelectionComponent::SELECTION_DUPLICATION_OFFSET,
-        );
+            // Offsetting the copies to make the duplication apparent
+            self.translate_strokes(&copy_keys, spacing * i as f64);
+
+            new_selected.extend(copy_keys);
+        }
 
         new_selected
     }
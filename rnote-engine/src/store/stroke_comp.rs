@@ -1,20 +1,42 @@
+use super::crdt::StrokeTransform;
 use super::render_comp::RenderCompState;
 use super::StrokeKey;
 use crate::pens::tools::DragProximityTool;
-use crate::strokes::Stroke;
+use crate::strokes::textstroke::LinkTarget;
+use crate::strokes::{ShapeStroke, Stroke, StrokeRestyle, StrokeStyle, TextStroke};
 use crate::{render, StrokeStore};
 use geo::intersects::Intersects;
 use geo::prelude::Contains;
 use rnote_compose::helpers;
 use rnote_compose::penpath::{Element, Segment};
-use rnote_compose::shapes::ShapeBehaviour;
+use rnote_compose::shapes::{Line, Polygon, ShapeBehaviour};
 use rnote_compose::transform::TransformBehaviour;
+use rnote_compose::Shape;
 
 use p2d::bounding_volume::{BoundingSphere, BoundingVolume, AABB};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::ops::Range;
 use std::sync::Arc;
 
+/// A match of a text search in a text stroke. See `StrokeStore::search_text()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextSearchMatch {
+    /// The key of the text stroke
+    pub key: StrokeKey,
+    /// The byte range of the match in the text
+    pub range: Range<usize>,
+    /// The bounds of the matched text, in document coords
+    pub bounds: AABB,
+}
+
 /// Systems that are related to the stroke components.
 impl StrokeStore {
+    /// The max deviation ratio of brush strokes from recognized shapes when converting them. See `PenPath::recognize_shape()`
+    pub const SHAPE_CONVERSION_MAX_DEVIATION_RATIO: f64 = 0.08;
+    /// The max deviation ratio of brush strokes from the fitted bezier curve when converting them. See `PenPath::fit_cubic_bezier()`
+    pub const BEZIER_CONVERSION_MAX_DEVIATION_RATIO: f64 = 0.06;
+
     /// Gets a reference to a stroke
     pub fn get_stroke_ref(&self, key: StrokeKey) -> Option<&Stroke> {
         self.stroke_components.get(key).map(|stroke| &**stroke)
@@ -47,6 +69,205 @@ impl StrokeStore {
         }
     }
 
+    /// Compacts the path of the brush stroke. If the stroke is not a brushstroke this does nothing.
+    /// stroke then needs to update its geometry and its rendering
+    pub fn compact_brushstroke(&mut self, key: StrokeKey, epsilon: f64) {
+        if let Some(Stroke::BrushStroke(brushstroke)) = Arc::make_mut(&mut self.stroke_components)
+            .get_mut(key)
+            .map(Arc::make_mut)
+        {
+            brushstroke.compact(epsilon);
+
+            self.set_rendering_dirty(key);
+        }
+    }
+
+    /// Simplifies the path of the brush stroke. The stroke then needs to update its geometry and rendering
+    pub fn simplify_brushstroke(&mut self, key: StrokeKey, epsilon: f64) {
+        if let Some(Stroke::BrushStroke(brushstroke)) = Arc::make_mut(&mut self.stroke_components)
+            .get_mut(key)
+            .map(Arc::make_mut)
+        {
+            brushstroke.simplify(epsilon);
+
+            self.set_rendering_dirty(key);
+        }
+    }
+
+    /// Straightens the path of the brush stroke into a single line, if it is nearly straight. If the stroke is not a brushstroke this does nothing.
+    /// The stroke then needs to update its geometry and rendering
+    pub fn straighten_brushstroke(&mut self, key: StrokeKey, max_deviation_ratio: f64) -> bool {
+        if let Some(Stroke::BrushStroke(brushstroke)) = Arc::make_mut(&mut self.stroke_components)
+            .get_mut(key)
+            .map(Arc::make_mut)
+        {
+            if brushstroke.straighten(max_deviation_ratio) {
+                self.set_rendering_dirty(key);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Replaces the brush strokes with shape strokes, fitted to recognized shapes or else to a single cubic bezier curve.
+    /// The shape strokes take the place of the brush strokes in the draw order. Brush strokes that can't be fitted and other strokes are left untouched.
+    /// Returns the keys of the new shape strokes, which then need to update their geometry and rendering
+    pub fn convert_brushstrokes_to_shapes(&mut self, keys: &[StrokeKey]) -> Vec<StrokeKey> {
+        keys.iter()
+            .filter_map(|&key| {
+                let (shape, style, opacity) = match self.get_stroke_ref(key) {
+                    Some(Stroke::BrushStroke(brushstroke)) => {
+                        let shape = brushstroke
+                            .path
+                            .recognize_shape(Self::SHAPE_CONVERSION_MAX_DEVIATION_RATIO)
+                            .or_else(|| {
+                                brushstroke
+                                    .path
+                                    .fit_cubic_bezier(Self::BEZIER_CONVERSION_MAX_DEVIATION_RATIO)
+                                    .map(Shape::CubicBezier)
+                            })?;
+                        // Textured and stamped styles can't be drawn on shapes
+                        (
+                            shape,
+                            StrokeStyle::shape_style(&brushstroke.style),
+                            brushstroke.opacity,
+                        )
+                    }
+                    _ => return None,
+                };
+                let layer = self.stroke_layer(key);
+                let selected = self.selected(key).unwrap_or(false);
+
+                let mut shapestroke = ShapeStroke::new(shape, style);
+                shapestroke.opacity = opacity;
+                let new_key = self.insert_stroke(Stroke::ShapeStroke(shapestroke), layer);
+                if let Err(e) = self.move_stroke_before(new_key, key) {
+                    log::error!(
+                        "move_stroke_before() failed in convert_brushstrokes_to_shapes(), Err {}",
+                        e
+                    );
+                }
+                self.set_selected(new_key, selected);
+                self.remove_stroke(key);
+
+                Some(new_key)
+            })
+            .collect()
+    }
+
+    /// Searches the text strokes for the query, ignoring case. Returns the matches in reading order, from top to bottom and left to right.
+    pub fn search_text(&self, query: &str) -> Vec<TextSearchMatch> {
+        let mut matches = self
+            .stroke_keys_unordered()
+            .into_iter()
+            .flat_map(|key| {
+                let textstroke = match self.textstroke_loaded(key) {
+                    Some(textstroke) => textstroke,
+                    None => return vec![],
+                };
+
+                textstroke
+                    .find_text(query)
+                    .into_iter()
+                    .filter_map(|range| {
+                        Some(TextSearchMatch {
+                            key,
+                            bounds: textstroke.bounds_for_range(range.clone())?,
+                            range,
+                        })
+                    })
+                    .collect::<Vec<TextSearchMatch>>()
+            })
+            .collect::<Vec<TextSearchMatch>>();
+
+        matches.sort_by(|first, second| {
+            first.bounds.mins[1]
+                .total_cmp(&second.bounds.mins[1])
+                .then(first.bounds.mins[0].total_cmp(&second.bounds.mins[0]))
+        });
+
+        matches
+    }
+
+    /// The text strokes in reading order: page by page, and on each page from top to bottom and left to right.
+    /// Trashed strokes are excluded, deferred text strokes are loaded.
+    pub fn textstrokes_in_reading_order(&self, pages_bounds: &[AABB]) -> Vec<Cow<TextStroke>> {
+        let mut textstrokes = self
+            .stroke_keys_as_rendered()
+            .into_iter()
+            .filter_map(|key| {
+                let textstroke = self.textstroke_loaded(key)?;
+                let bounds = textstroke.bounds();
+                // Strokes outside of the pages come last
+                let page_index = pages_bounds
+                    .iter()
+                    .position(|page_bounds| page_bounds.contains_local_point(&bounds.mins))
+                    .unwrap_or(usize::MAX);
+
+                Some((page_index, bounds, textstroke))
+            })
+            .collect::<Vec<(usize, AABB, Cow<TextStroke>)>>();
+
+        textstrokes.sort_by(
+            |(first_page, first_bounds, _), (second_page, second_bounds, _)| {
+                first_page
+                    .cmp(second_page)
+                    .then(first_bounds.mins[1].total_cmp(&second_bounds.mins[1]))
+                    .then(first_bounds.mins[0].total_cmp(&second_bounds.mins[0]))
+            },
+        );
+
+        textstrokes
+            .into_iter()
+            .map(|(_, _, textstroke)| textstroke)
+            .collect()
+    }
+
+    /// The links of the text stroke with the areas of their text, in document coords. Empty for other stroke types
+    pub fn link_areas(&self, key: StrokeKey) -> Vec<(LinkTarget, AABB)> {
+        self.textstroke_loaded(key)
+            .map(|textstroke| textstroke.link_areas())
+            .unwrap_or_default()
+    }
+
+    /// The target of the topmost link at the given coord, in document coords
+    pub fn link_at_coord(&self, coord: na::Vector2<f64>) -> Option<LinkTarget> {
+        self.stroke_keys_as_rendered()
+            .into_iter()
+            .rev()
+            .filter(|&key| {
+                matches!(self.stroke_components.get(key), Some(stroke)
+                    if stroke.bounds().contains_local_point(&na::Point2::from(coord)))
+            })
+            .find_map(|key| self.textstroke_loaded(key)?.link_at_coord(coord))
+    }
+
+    /// The text stroke for the key. Deferred text strokes are loaded, None for other stroke types
+    fn textstroke_loaded(&self, key: StrokeKey) -> Option<Cow<TextStroke>> {
+        match self
+            .stroke_components
+            .get(key)
+            .map(|stroke| stroke.as_ref())
+        {
+            Some(Stroke::TextStroke(textstroke)) => Some(Cow::Borrowed(textstroke)),
+            // only deferred text strokes are loaded
+            Some(Stroke::Deferred(deferred)) if deferred.type_name() == "textstroke" => {
+                match deferred.loaded() {
+                    Ok(Stroke::TextStroke(textstroke)) => Some(Cow::Borrowed(textstroke)),
+                    Ok(_) => None,
+                    Err(e) => {
+                        log::error!(
+                            "loading deferred stroke failed in textstroke_loaded(), Err {}",
+                            e
+                        );
+                        None
+                    }
+                }
+            }
+            _ => None,
+        }
+    }
+
     /// All stroke keys unordered
     pub fn keys_unordered(&self) -> Vec<StrokeKey> {
         self.stroke_components.keys().collect()
@@ -66,12 +287,106 @@ impl StrokeStore {
 
     /// Returns the stroke keys in the order that they should be rendered.
     pub fn stroke_keys_as_rendered(&self) -> Vec<StrokeKey> {
-        self.keys_sorted_chrono()
-            .into_iter()
+        self.keys_sorted_chrono_iter()
             .filter(|&key| !(self.trashed(key).unwrap_or(false)))
             .collect::<Vec<StrokeKey>>()
     }
 
+    /// Returns the stroke keys in the order that they should be exported. Reference images are only included if `with_reference_images` is true.
+    pub fn stroke_keys_as_exported(&self, with_reference_images: bool) -> Vec<StrokeKey> {
+        self.keys_sorted_chrono_iter()
+            .filter(|&key| {
+                !(self.trashed(key).unwrap_or(false))
+                    && (with_reference_images || !self.is_reference(key))
+            })
+            .collect::<Vec<StrokeKey>>()
+    }
+
+    /// Returns the stroke keys in the order that they should be exported, intersecting the given bounds. Reference images are only included if `with_reference_images` is true.
+    pub fn stroke_keys_as_exported_intersecting_bounds(
+        &self,
+        bounds: AABB,
+        with_reference_images: bool,
+    ) -> Vec<StrokeKey> {
+        self.stroke_keys_as_rendered_intersecting_bounds(bounds)
+            .into_iter()
+            .filter(|&key| with_reference_images || !self.is_reference(key))
+            .collect::<Vec<StrokeKey>>()
+    }
+
+    /// Wether the stroke is a reference image. See `Stroke::is_reference()`
+    pub fn is_reference(&self, key: StrokeKey) -> bool {
+        self.stroke_components
+            .get(key)
+            .map(|stroke| stroke.is_reference())
+            .unwrap_or(false)
+    }
+
+    /// Marks image strokes as reference images, or back as regular images. Reference images get deselected.
+    /// Does nothing for other strokes.
+    pub fn set_reference(&mut self, key: StrokeKey, reference: bool) {
+        match self.get_stroke_mut(key) {
+            Some(Stroke::VectorImage(vectorimage)) => vectorimage.reference = reference,
+            Some(Stroke::BitmapImage(bitmapimage)) => bitmapimage.reference = reference,
+            _ => return,
+        }
+
+        if reference {
+            self.set_selected(key, false);
+        }
+    }
+
+    /// The opacity of the stroke. See `Stroke::opacity()`
+    pub fn opacity(&self, key: StrokeKey) -> Option<f64> {
+        self.stroke_components
+            .get(key)
+            .map(|stroke| stroke.opacity())
+    }
+
+    /// Sets the opacity of the strokes. The opacity is applied when drawing the stroke renderings, so they don't need to be regenerated.
+    pub fn set_opacity_for_strokes(&mut self, keys: &[StrokeKey], opacity: f64) {
+        keys.iter().for_each(|&key| {
+            if let Some(stroke) = self.get_stroke_mut(key) {
+                stroke.set_opacity(opacity);
+            }
+        });
+        self.record_replace_op(keys);
+        self.touch_strokes(keys);
+    }
+
+    /// Restyles the strokes. The strokes then need to update their geometry and rendering if `StrokeRestyle::needs_rerender()`
+    pub fn restyle_strokes(&mut self, keys: &[StrokeKey], restyle: &StrokeRestyle) {
+        keys.iter().for_each(|&key| {
+            if let Some(stroke) = self.get_stroke_mut(key) {
+                stroke.restyle(restyle);
+            }
+        });
+        self.record_replace_op(keys);
+        self.touch_strokes(keys);
+    }
+
+    /// Applies the captured style to the strokes and returns the keys of the strokes it applied to.
+    /// The returned strokes then need to update their geometry and rendering
+    pub fn apply_stroke_style_to_strokes(
+        &mut self,
+        keys: &[StrokeKey],
+        stroke_style: &StrokeStyle,
+    ) -> Vec<StrokeKey> {
+        let applied_keys = keys
+            .iter()
+            .copied()
+            .filter(|&key| {
+                self.get_stroke_mut(key)
+                    .map(|stroke| stroke.apply_stroke_style(stroke_style))
+                    .unwrap_or(false)
+            })
+            .collect::<Vec<StrokeKey>>();
+        self.record_replace_op(&applied_keys);
+        self.touch_strokes(&applied_keys);
+
+        applied_keys
+    }
+
     /// Returns the stroke keys in the order that they should be rendered, intersecting the given bounds.
     pub fn stroke_keys_as_rendered_intersecting_bounds(&self, bounds: AABB) -> Vec<StrokeKey> {
         self.keys_sorted_chrono_intersecting_bounds(bounds)
@@ -101,7 +416,10 @@ impl StrokeStore {
                 Stroke::ShapeStroke(shapestroke) => {
                     shapestroke.update_geometry();
                 }
-                Stroke::TextStroke(_) | Stroke::VectorImage(_) | Stroke::BitmapImage(_) => {}
+                Stroke::TextStroke(_)
+                | Stroke::VectorImage(_)
+                | Stroke::BitmapImage(_)
+                | Stroke::Deferred(_) => {}
             }
 
             self.key_tree.update_with_key(key, stroke.bounds());
@@ -117,6 +435,33 @@ impl StrokeStore {
         });
     }
 
+    /// Loads the deferred strokes that intersect the given bounds.
+    /// strokes then need to update their rendering
+    pub fn load_deferred_strokes_intersecting_bounds(&mut self, bounds: AABB) {
+        let deferred_keys = self
+            .key_tree
+            .keys_intersecting_bounds(bounds)
+            .into_iter()
+            .filter(|&key| matches!(self.get_stroke_ref(key), Some(Stroke::Deferred(_))))
+            .collect::<Vec<StrokeKey>>();
+
+        for key in deferred_keys {
+            if let Some(stroke) = self.get_stroke_mut(key) {
+                if let Stroke::Deferred(deferred) = stroke {
+                    match deferred.load() {
+                        Ok(loaded) => *stroke = loaded,
+                        Err(e) => {
+                            log::error!("loading deferred stroke failed in load_deferred_strokes_intersecting_bounds() for stroke with key {:?}, Err {}", key, e);
+                            continue;
+                        }
+                    }
+                }
+            }
+
+            self.update_geometry_for_stroke(key);
+        }
+    }
+
     /// Calculates the height needed to fit all strokes
     pub fn calc_height(&self) -> f64 {
         let strokes_iter = self
@@ -132,6 +477,20 @@ impl StrokeStore {
         strokes_max_y - strokes_min_y
     }
 
+    pub fn calc_width(&self) -> f64 {
+        let strokes_iter = self
+            .stroke_keys_unordered()
+            .into_iter()
+            .filter_map(|key| self.stroke_components.get(key));
+
+        let strokes_min_x = strokes_iter
+            .clone()
+            .fold(0.0, |acc, stroke| stroke.bounds().mins[0].min(acc));
+        let strokes_max_x = strokes_iter.fold(0.0, |acc, stroke| stroke.bounds().maxs[0].max(acc));
+
+        strokes_max_x - strokes_min_x
+    }
+
     /// Generates the enclosing bounds for the given stroke keys
     pub fn bounds_for_strokes(&self, keys: &[StrokeKey]) -> Option<AABB> {
         let mut keys_iter = keys.iter();
@@ -170,11 +529,14 @@ impl StrokeStore {
                 }
             }
         });
+
+        self.record_transform_op(keys, vec![StrokeTransform::Translate(offset)]);
+        self.touch_strokes(keys);
     }
 
     pub fn translate_strokes_images(&mut self, keys: &[StrokeKey], offset: na::Vector2<f64>) {
         keys.iter().for_each(|&key| {
-            if let Some(render_comp) = self.render_components.get_mut(key) {
+            for render_comp in self.render_comps_for_key_mut(key) {
                 for image in render_comp.images.iter_mut() {
                     image.translate(offset);
                 }
@@ -190,6 +552,8 @@ impl StrokeStore {
                 }
             }
         });
+
+        self.add_render_damage_for_strokes(keys);
     }
 
     /// Rotates the stroke with angle (rad) around the center.
@@ -207,6 +571,9 @@ impl StrokeStore {
                 }
             }
         });
+
+        self.record_transform_op(keys, vec![StrokeTransform::Rotate { angle, center }]);
+        self.touch_strokes(keys);
     }
 
     pub fn rotate_strokes_images(
@@ -216,7 +583,7 @@ impl StrokeStore {
         center: na::Point2<f64>,
     ) {
         keys.iter().for_each(|&key| {
-            if let Some(render_comp) = self.render_components.get_mut(key) {
+            for render_comp in self.render_comps_for_key_mut(key) {
                 render_comp.state = RenderCompState::Dirty;
 
                 for image in render_comp.images.iter_mut() {
@@ -234,28 +601,47 @@ impl StrokeStore {
                 }
             }
         });
+
+        self.add_render_damage_for_strokes(keys);
     }
 
-    /// Scales the strokes with the factor.
+    /// Scales the strokes with the factor. When `scale_stroke_widths` is true the stroke widths are scaled proportionally, else they are kept constant.
     /// strokes then need to update their rendering
-    pub fn scale_strokes(&mut self, keys: &[StrokeKey], scale: na::Vector2<f64>) {
+    pub fn scale_strokes(
+        &mut self,
+        keys: &[StrokeKey],
+        scale: na::Vector2<f64>,
+        scale_stroke_widths: bool,
+    ) {
         keys.iter().for_each(|&key| {
             if let Some(stroke) = Arc::make_mut(&mut self.stroke_components)
                 .get_mut(key)
                 .map(Arc::make_mut)
             {
                 {
-                    // rotate the stroke geometry
+                    // scale the stroke geometry
                     stroke.scale(scale);
+                    if scale_stroke_widths {
+                        stroke.scale_stroke_width(Self::stroke_width_factor(scale));
+                    }
                     self.key_tree.update_with_key(key, stroke.bounds());
                 }
             }
         });
+
+        let mut transforms = vec![StrokeTransform::Scale(scale)];
+        if scale_stroke_widths {
+            transforms.push(StrokeTransform::ScaleStrokeWidth(
+                Self::stroke_width_factor(scale),
+            ));
+        }
+        self.record_transform_op(keys, transforms);
+        self.touch_strokes(keys);
     }
 
     pub fn scale_strokes_images(&mut self, keys: &[StrokeKey], scale: na::Vector2<f64>) {
         keys.iter().for_each(|&key| {
-            if let Some(render_comp) = self.render_components.get_mut(key) {
+            for render_comp in self.render_comps_for_key_mut(key) {
                 render_comp.state = RenderCompState::Dirty;
 
                 for image in render_comp.images.iter_mut() {
@@ -273,18 +659,21 @@ impl StrokeStore {
                 }
             }
         });
+
+        self.add_render_damage_for_strokes(keys);
     }
 
-    /// Scales the strokes with a pivot as the scaling origin
+    /// Scales the strokes with a pivot as the scaling origin. See `scale_strokes()`
     /// strokes then need to update their rendering
     pub fn scale_strokes_with_pivot(
         &mut self,
         keys: &[StrokeKey],
         scale: na::Vector2<f64>,
         pivot: na::Vector2<f64>,
+        scale_stroke_widths: bool,
     ) {
         self.translate_strokes(keys, -pivot);
-        self.scale_strokes(keys, scale);
+        self.scale_strokes(keys, scale, scale_stroke_widths);
         self.translate_strokes(keys, pivot);
     }
 
@@ -299,9 +688,14 @@ impl StrokeStore {
         self.translate_strokes_images(strokes, pivot);
     }
 
-    /// Resizes the strokes to new bounds.
+    /// Resizes the strokes to new bounds. When `scale_stroke_widths` is true the stroke widths are scaled proportionally, else they are kept constant.
     /// strokes then need to update their rendering
-    pub fn resize_strokes(&mut self, keys: &[StrokeKey], new_bounds: AABB) {
+    pub fn resize_strokes(
+        &mut self,
+        keys: &[StrokeKey],
+        new_bounds: AABB,
+        scale_stroke_widths: bool,
+    ) {
         let old_bounds = match self.bounds_for_strokes(keys) {
             Some(old_bounds) => old_bounds,
             None => return,
@@ -326,16 +720,39 @@ impl StrokeStore {
                     let rel_offset = new_stroke_bounds.center() - old_stroke_bounds.center();
 
                     // Translate in relation to the outer bounds
-                    stroke.translate(rel_offset - old_stroke_bounds.center().coords);
-                    stroke.scale(scale);
-                    stroke.translate(old_stroke_bounds.center().coords);
+                    let mut transforms = vec![
+                        StrokeTransform::Translate(rel_offset - old_stroke_bounds.center().coords),
+                        StrokeTransform::Scale(scale),
+                        StrokeTransform::Translate(old_stroke_bounds.center().coords),
+                    ];
+                    if scale_stroke_widths {
+                        transforms.push(StrokeTransform::ScaleStrokeWidth(
+                            Self::stroke_width_factor(scale),
+                        ));
+                    }
+                    for transform in transforms.iter() {
+                        transform.apply(stroke);
+                    }
 
                     self.key_tree.update_with_key(key, stroke.bounds());
+                    self.record_transform_op(&[key], transforms);
+                    self.touch_strokes(&[key]);
                 }
             }
         });
     }
 
+    /// The factor stroke widths are scaled with for the given scale. For non-uniform scales it is the geometric mean of the components
+    fn stroke_width_factor(scale: na::Vector2<f64>) -> f64 {
+        let factor = (scale[0] * scale[1]).abs().sqrt();
+
+        if factor.is_finite() && factor > 0.0 {
+            factor
+        } else {
+            1.0
+        }
+    }
+
     pub fn resize_strokes_images(&mut self, keys: &[StrokeKey], new_bounds: AABB) {
         let old_bounds = match self.bounds_for_strokes(keys) {
             Some(old_bounds) => old_bounds,
@@ -343,7 +760,7 @@ impl StrokeStore {
         };
 
         keys.iter().for_each(|&key| {
-            if let Some(render_comp) = self.render_components.get_mut(key) {
+            for render_comp in self.render_comps_for_key_mut(key) {
                 render_comp.state = RenderCompState::Dirty;
 
                 for image in render_comp.images.iter_mut() {
@@ -376,6 +793,8 @@ impl StrokeStore {
                 }
             }
         });
+
+        self.add_render_damage_for_strokes(keys);
     }
 
     /// returns the strokes whose hitboxes are contained in the given polygon path.
@@ -399,8 +818,8 @@ impl StrokeStore {
         self.keys_sorted_chrono_intersecting_bounds(viewport)
             .into_iter()
             .filter_map(|key| {
-                // skip if stroke is trashed
-                if self.trashed(key)? {
+                // skip if stroke is trashed or can't be selected
+                if self.trashed(key)? || !self.can_select(key) {
                     return None;
                 }
 
@@ -450,8 +869,8 @@ impl StrokeStore {
         self.keys_sorted_chrono_intersecting_bounds(viewport)
             .into_iter()
             .filter_map(|key| {
-                // skip if stroke is trashed
-                if self.trashed(key)? {
+                // skip if stroke is trashed or can't be selected
+                if self.trashed(key)? || !self.can_select(key) {
                     return None;
                 }
 
@@ -483,8 +902,8 @@ impl StrokeStore {
         self.keys_sorted_chrono_intersecting_bounds(viewport)
             .into_iter()
             .filter_map(|key| {
-                // skip if stroke is trashed
-                if self.trashed(key)? {
+                // skip if stroke is trashed or can't be selected
+                if self.trashed(key)? || !self.can_select(key) {
                     return None;
                 }
 
@@ -517,6 +936,10 @@ impl StrokeStore {
         self.stroke_keys_as_rendered_intersecting_bounds(viewport)
             .into_iter()
             .filter(|&key| {
+                if !self.can_select(key) {
+                    return false;
+                }
+
                 if let Some(stroke) = self.stroke_components.get(key) {
                     stroke
                         .hitboxes()
@@ -529,6 +952,56 @@ impl StrokeStore {
             .collect()
     }
 
+    /// Finds the region enclosed by the stroke outlines around the given position, by casting rays from it in all directions.
+    /// Returns the region as polygon and the keys of the strokes enclosing it in the order they are rendered,
+    /// or None if the position is not enclosed by the strokes intersecting the given bounds.
+    ///
+    /// Only the part of the region that is visible from the position is found, so deeply concave regions are not filled completely.
+    pub fn enclosed_region_at_pos(
+        &self,
+        pos: na::Vector2<f64>,
+        bounds: AABB,
+    ) -> Option<(Polygon, Vec<StrokeKey>)> {
+        const N_RAYS: u32 = 360;
+
+        let keys = self.stroke_keys_as_rendered_intersecting_bounds(bounds);
+        let outline_lines = keys
+            .iter()
+            .filter_map(|&key| Some((key, self.stroke_components.get(key)?.outline_lines())))
+            .flat_map(|(key, lines)| lines.into_iter().map(move |line| (key, line)))
+            .collect::<Vec<(StrokeKey, Line)>>();
+
+        let mut vertices = Vec::with_capacity(N_RAYS as usize);
+        let mut hit_keys = HashSet::new();
+
+        for i in 0..N_RAYS {
+            let angle = 2.0 * std::f64::consts::PI * f64::from(i) / f64::from(N_RAYS);
+            let direction = na::vector![angle.cos(), angle.sin()];
+
+            // The region is not enclosed if any ray escapes
+            let (key, dist) = outline_lines
+                .iter()
+                .filter_map(|(key, line)| {
+                    Some((*key, ray_line_intersection(pos, direction, line)?))
+                })
+                .min_by(|(_, first), (_, second)| {
+                    first
+                        .partial_cmp(second)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })?;
+
+            vertices.push(pos + direction * dist);
+            hit_keys.insert(key);
+        }
+
+        let enclosing_keys = keys
+            .into_iter()
+            .filter(|key| hit_keys.contains(key))
+            .collect::<Vec<StrokeKey>>();
+
+        Some((Polygon::new(vertices), enclosing_keys))
+    }
+
     /// Returns all keys below the y_pos
     pub fn keys_below_y_pos(&self, y_pos: f64) -> Vec<StrokeKey> {
         self.stroke_components
@@ -543,6 +1016,20 @@ impl StrokeStore {
             .collect::<Vec<StrokeKey>>()
     }
 
+    /// Returns all keys right of the x_pos
+    pub fn keys_right_of_x_pos(&self, x_pos: f64) -> Vec<StrokeKey> {
+        self.stroke_components
+            .iter()
+            .filter_map(|(key, stroke)| {
+                if stroke.bounds().mins[0] > x_pos {
+                    Some(key)
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<StrokeKey>>()
+    }
+
     /// Unimplemented!
     /// strokes then need to update their rendering
     pub fn drag_strokes_proximity(&mut self, drag_proximity_tool: &DragProximityTool) {
@@ -564,3 +1051,26 @@ impl StrokeStore {
         todo!()
     }
 }
+
+/// The distance along the ray from the origin in the given direction to the line, or None if the ray misses it
+fn ray_line_intersection(
+    origin: na::Vector2<f64>,
+    direction: na::Vector2<f64>,
+    line: &Line,
+) -> Option<f64> {
+    let line_vec = line.end - line.start;
+    let denom = direction.perp(&line_vec);
+    if denom.abs() < f64::EPSILON {
+        return None;
+    }
+
+    let to_start = line.start - origin;
+    let dist = to_start.perp(&line_vec) / denom;
+    let line_t = to_start.perp(&direction) / denom;
+
+    if dist >= 0.0 && (0.0..=1.0).contains(&line_t) {
+        Some(dist)
+    } else {
+        None
+    }
+}
@@ -3,9 +3,7 @@ use crate::strokes::{BrushStroke, Stroke};
 use crate::WidgetFlags;
 
 use p2d::bounding_volume::{BoundingVolume, AABB};
-use rnote_compose::penpath::Segment;
 use rnote_compose::shapes::ShapeBehaviour;
-use rnote_compose::PenPath;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
@@ -47,7 +45,9 @@ impl StrokeStore {
         {
             trash_comp.trashed = trash;
 
+            self.add_change_for_stroke(key);
             self.update_chrono_to_last(key);
+            self.record_trash_op(key, trash);
         } else {
             log::debug!(
                 "get trash_comp in set_trashed() returned None for stroke with key {:?}",
@@ -80,10 +80,16 @@ impl StrokeStore {
     /// trash strokes that collide with the given bounds
     pub fn trash_colliding_strokes(&mut self, eraser_bounds: AABB, viewport: AABB) -> WidgetFlags {
         let mut widget_flags = WidgetFlags::default();
+        // The hitboxes of deferred strokes are only known once they are loaded
+        self.load_deferred_strokes_intersecting_bounds(eraser_bounds);
 
         self.stroke_keys_as_rendered_intersecting_bounds(viewport)
             .into_iter()
             .for_each(|key| {
+                // Locked strokes can't be erased
+                if self.locked(key) {
+                    return;
+                }
                 let mut trash_current_stroke = false;
 
                 if let Some(stroke) = self.stroke_components.get(key) {
@@ -109,6 +115,9 @@ impl StrokeStore {
                         Stroke::BitmapImage(_bitmapimage) => {
                             // Ignore bitmap images when trashing with the Eraser
                         }
+                        Stroke::Deferred(_) => {
+                            // Deferred strokes colliding with the eraser were loaded, the others are out of reach
+                        }
                     }
                 }
 
@@ -121,7 +130,7 @@ impl StrokeStore {
         widget_flags
     }
 
-    /// remove colliding stroke segments with the given bounds. The stroke is then split. For strokes that don't have segments, trash the entire stroke.
+    /// cuts away the parts of brush strokes inside the given bounds, splitting them into new strokes with the same style. Other colliding strokes are trashed entirely.
     /// Returns the keys of all created or modified strokes.
     /// returned strokes need to update their rendering.
     pub fn split_colliding_strokes(
@@ -130,106 +139,101 @@ impl StrokeStore {
         viewport: AABB,
     ) -> Vec<StrokeKey> {
         let mut modified_keys = vec![];
+        self.load_deferred_strokes_intersecting_bounds(eraser_bounds);
 
-        let new_strokes = self
-            .stroke_keys_as_rendered_intersecting_bounds(viewport)
-            .into_iter()
-            .flat_map(|key| {
-                let stroke = match Arc::make_mut(&mut self.stroke_components)
-                    .get_mut(key)
-                    .map(Arc::make_mut)
-                {
-                    Some(stroke) => stroke,
-                    None => return vec![],
-                };
-
-                let mut new_strokes = vec![];
-                let mut trash_current_stroke = false;
-                let stroke_bounds = stroke.bounds();
-
-                match stroke {
-                    Stroke::BrushStroke(brushstroke) => {
-                        if eraser_bounds.intersects(&stroke_bounds) {
-                            let stroke_width = brushstroke.style.stroke_width();
-                            brushstroke.path.make_contiguous();
-
-                            let split_segments = brushstroke
-                                .path
-                                .as_slices()
-                                .0
-                                .split(|segment| {
-                                    segment.hitboxes().iter().any(|hitbox| {
-                                        // The hitboxes of the individual segments need to be loosened with the style stroke width
-                                        hitbox
-                                            .loosened(stroke_width * 0.5)
-                                            .intersects(&eraser_bounds)
-                                    })
-                                })
-                                .collect::<Vec<&[Segment]>>();
-
-                            // If this is met, we intersect with the stroke but we cant form any new split strokes, so we trash it ( e.g. strokes that only have a single element )
-                            if split_segments.iter().all(|segments| segments.is_empty()) {
-                                trash_current_stroke = true;
-                            } else {
-                                // Filter out all empty paths
-                                let mut split_penpaths = split_segments
-                                    .into_iter()
-                                    .filter_map(|segments| {
-                                        let split_penpath =
-                                            PenPath::from_iter(segments.iter().cloned());
-
-                                        if split_penpath.is_empty() {
-                                            None
-                                        } else {
-                                            Some(split_penpath)
-                                        }
-                                    })
-                                    .collect::<Vec<PenPath>>();
-
-                                if let Some(last_penpath) = split_penpaths.pop() {
-                                    for split_penpath in split_penpaths {
-                                        if let Some(new_brushstroke) = BrushStroke::from_penpath(
-                                            split_penpath,
-                                            brushstroke.style.clone(),
-                                        ) {
-                                            new_strokes.push(Stroke::BrushStroke(new_brushstroke));
+        let new_strokes =
+            self.stroke_keys_as_rendered_intersecting_bounds(viewport)
+                .into_iter()
+                .flat_map(|key| {
+                    // Locked strokes can't be erased
+                    if self.locked(key) {
+                        return vec![];
+                    }
+                    let stroke = match Arc::make_mut(&mut self.stroke_components)
+                        .get_mut(key)
+                        .map(Arc::make_mut)
+                    {
+                        Some(stroke) => stroke,
+                        None => return vec![],
+                    };
+
+                    let mut new_strokes = vec![];
+                    let mut trash_current_stroke = false;
+                    let stroke_bounds = stroke.bounds();
+
+                    match stroke {
+                        Stroke::BrushStroke(brushstroke) => {
+                            if eraser_bounds.intersects(&stroke_bounds) {
+                                // The path is cut where its center line enters the eraser bounds, loosened with half the stroke width,
+                                // so that the remaining parts end where they are touched by the eraser
+                                let cut_bounds =
+                                    eraser_bounds.loosened(brushstroke.style.stroke_width() * 0.5);
+
+                                let intersects = brushstroke.path.iter().any(|segment| {
+                                    segment
+                                        .hitboxes()
+                                        .iter()
+                                        .any(|hitbox| hitbox.intersects(&cut_bounds))
+                                });
+
+                                if intersects {
+                                    let mut split_penpaths =
+                                        brushstroke.path.split_outside_bounds(cut_bounds);
+
+                                    match split_penpaths.pop() {
+                                        Some(last_penpath) => {
+                                            for split_penpath in split_penpaths {
+                                                if let Some(new_brushstroke) =
+                                                    BrushStroke::from_penpath(
+                                                        split_penpath,
+                                                        brushstroke.style.clone(),
+                                                    )
+                                                {
+                                                    new_strokes
+                                                        .push(Stroke::BrushStroke(new_brushstroke));
+                                                }
+                                            }
+
+                                            // reusing the current brushstroke by replacing its path with the last new path
+                                            brushstroke.replace_path(last_penpath);
+                                            modified_keys.push(key);
                                         }
+                                        // The entire stroke is inside the eraser
+                                        None => trash_current_stroke = true,
                                     }
-
-                                    // reusing the current brushstroke by replacing its path with the last new path
-                                    brushstroke.replace_path(last_penpath);
-                                    modified_keys.push(key);
                                 }
                             }
                         }
-                    }
-                    Stroke::ShapeStroke(_) => {
-                        if eraser_bounds.intersects(&stroke_bounds) {
-                            for hitbox_elem in stroke.hitboxes().iter() {
-                                if eraser_bounds.intersects(hitbox_elem) {
-                                    trash_current_stroke = true;
+                        Stroke::ShapeStroke(_) => {
+                            if eraser_bounds.intersects(&stroke_bounds) {
+                                for hitbox_elem in stroke.hitboxes().iter() {
+                                    if eraser_bounds.intersects(hitbox_elem) {
+                                        trash_current_stroke = true;
+                                    }
                                 }
                             }
                         }
+                        Stroke::TextStroke(_textstroke) => {
+                            // Ignore text strokes when trashing with the Eraser
+                        }
+                        Stroke::VectorImage(_vectorimage) => {
+                            // Ignore vector images when trashing with the Eraser
+                        }
+                        Stroke::BitmapImage(_bitmapimage) => {
+                            // Ignore bitmap images when trashing with the Eraser
+                        }
+                        Stroke::Deferred(_) => {
+                            // Deferred strokes colliding with the eraser were loaded, the others are out of reach
+                        }
                     }
-                    Stroke::TextStroke(_textstroke) => {
-                        // Ignore text strokes when trashing with the Eraser
-                    }
-                    Stroke::VectorImage(_vectorimage) => {
-                        // Ignore vector images when trashing with the Eraser
-                    }
-                    Stroke::BitmapImage(_bitmapimage) => {
-                        // Ignore bitmap images when trashing with the Eraser
-                    }
-                }
 
-                if trash_current_stroke {
-                    self.set_trashed(key, true);
-                }
+                    if trash_current_stroke {
+                        self.set_trashed(key, true);
+                    }
 
-                new_strokes
-            })
-            .collect::<Vec<Stroke>>();
+                    new_strokes
+                })
+                .collect::<Vec<Stroke>>();
 
         modified_keys.append(
             &mut new_strokes
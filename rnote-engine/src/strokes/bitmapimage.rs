@@ -3,6 +3,7 @@ use std::ops::Range;
 use super::strokebehaviour::GeneratedStrokeImages;
 use super::StrokeBehaviour;
 use crate::document::Format;
+use crate::fileerror::FileError;
 use crate::import::{PdfImportPageSpacing, PdfImportPrefs};
 use crate::render;
 use crate::DrawBehaviour;
@@ -28,6 +29,12 @@ pub struct BitmapImage {
     pub image: render::Image,
     #[serde(rename = "rectangle")]
     pub rectangle: Rectangle,
+    /// Wether the image is a reference underlay. See `Stroke::is_reference()`
+    #[serde(rename = "reference")]
+    pub reference: bool,
+    /// The opacity the stroke is drawn with. See `Stroke::opacity()`
+    #[serde(rename = "opacity")]
+    pub opacity: f64,
 }
 
 impl Default for BitmapImage {
@@ -35,6 +42,8 @@ impl Default for BitmapImage {
         Self {
             image: render::Image::default(),
             rectangle: Rectangle::default(),
+            reference: false,
+            opacity: 1.0,
         }
     }
 }
@@ -142,7 +151,22 @@ impl BitmapImage {
         bytes: &[u8],
         pos: na::Vector2<f64>,
     ) -> Result<Self, anyhow::Error> {
-        let mut image = render::Image::try_from_encoded_bytes(bytes)?;
+        Self::from_image(render::Image::try_from_encoded_bytes(bytes)?, pos)
+    }
+
+    /// Imports the frame with the given index of a animated image ( e.g. GIF or WebP ). Still images only have the frame with index 0.
+    pub fn import_from_image_bytes_w_frame(
+        bytes: &[u8],
+        pos: na::Vector2<f64>,
+        frame_index: usize,
+    ) -> Result<Self, anyhow::Error> {
+        Self::from_image(
+            render::Image::try_from_encoded_bytes_w_frame(bytes, frame_index)?,
+            pos,
+        )
+    }
+
+    fn from_image(mut image: render::Image, pos: na::Vector2<f64>) -> Result<Self, anyhow::Error> {
         // Ensure we are in rgba8-remultiplied format, to be able to draw to piet
         image.convert_to_rgba8pre()?;
 
@@ -153,7 +177,12 @@ impl BitmapImage {
             transform: Transform::new_w_isometry(na::Isometry2::new(pos + size * 0.5, 0.0)),
         };
 
-        Ok(Self { image, rectangle })
+        Ok(Self {
+            image,
+            rectangle,
+            reference: false,
+            opacity: 1.0,
+        })
     }
 
     pub fn import_from_pdf_bytes(
@@ -163,7 +192,8 @@ impl BitmapImage {
         page_range: Option<Range<u32>>,
         format: &Format,
     ) -> Result<Vec<Self>, anyhow::Error> {
-        let doc = poppler::Document::from_bytes(&glib::Bytes::from(to_be_read), None)?;
+        let doc = poppler::Document::from_bytes(&glib::Bytes::from(to_be_read), None)
+            .map_err(FileError::from_pdf_error)?;
         let page_range = page_range.unwrap_or(0..doc.n_pages() as u32);
 
         let page_width = format.width * (pdf_import_prefs.page_width_perc / 100.0);
@@ -6,6 +6,7 @@ use piet::RenderContext;
 use rnote_compose::helpers::Vector2Helpers;
 use rnote_compose::penpath::{Element, Segment};
 use rnote_compose::shapes::ShapeBehaviour;
+use rnote_compose::style::smooth::LineCap;
 use rnote_compose::style::Composer;
 use rnote_compose::transform::TransformBehaviour;
 use rnote_compose::{PenPath, Style};
@@ -20,6 +21,9 @@ pub struct BrushStroke {
     pub path: PenPath,
     #[serde(rename = "style")]
     pub style: Style,
+    /// The opacity the stroke is drawn with. See `Stroke::opacity()`
+    #[serde(rename = "opacity")]
+    pub opacity: f64,
     #[serde(skip)]
     // since the path can have many hitboxes, we store them for faster queries and update them when the stroke geometry changes
     hitboxes: Vec<AABB>,
@@ -61,8 +65,13 @@ impl StrokeBehaviour for BrushStroke {
             (viewport, true)
         };
 
-        let images = if bounds.extents()[0] < Self::IMAGES_SEGMENTS_THRESHOLD / image_scale
-            && bounds.extents()[1] < Self::IMAGES_SEGMENTS_THRESHOLD / image_scale
+        // Paths with flat caps can't be split into segments, as the segments would be drawn with caps in between
+        let flat_caps =
+            matches!(&self.style, Style::Smooth(options) if options.line_cap == LineCap::Flat);
+
+        let images = if flat_caps
+            || bounds.extents()[0] < Self::IMAGES_SEGMENTS_THRESHOLD / image_scale
+                && bounds.extents()[1] < Self::IMAGES_SEGMENTS_THRESHOLD / image_scale
         {
             // generate a single image when bounds are below threshold
             match &self.style {
@@ -84,6 +93,42 @@ impl StrokeBehaviour for BrushStroke {
                         }
                     }
                 }
+                Style::Calligraphy(options) => {
+                    let image = render::Image::gen_with_piet(
+                        |piet_cx| {
+                            self.path.draw_composed(piet_cx, options);
+                            Ok(())
+                        },
+                        bounds,
+                        image_scale,
+                    );
+
+                    match image {
+                        Ok(image) => vec![image],
+                        Err(e) => {
+                            log::error!("gen_images() in brushstroke failed with Err {}", e);
+                            vec![]
+                        }
+                    }
+                }
+                Style::Stamped(options) => {
+                    let image = render::Image::gen_with_piet(
+                        |piet_cx| {
+                            self.path.draw_composed(piet_cx, options);
+                            Ok(())
+                        },
+                        bounds,
+                        image_scale,
+                    );
+
+                    match image {
+                        Ok(image) => vec![image],
+                        Err(e) => {
+                            log::error!("gen_images() in brushstroke failed with Err {}", e);
+                            vec![]
+                        }
+                    }
+                }
                 Style::Rough(_options) => {
                     // Unsupported
                     vec![]
@@ -131,6 +176,50 @@ impl StrokeBehaviour for BrushStroke {
                         }
                     })
                     .collect::<Vec<render::Image>>(),
+                Style::Calligraphy(options) => self
+                    .path
+                    .iter()
+                    .filter_map(|segment| {
+                        let image = render::Image::gen_with_piet(
+                            |piet_cx| {
+                                segment.draw_composed(piet_cx, options);
+                                Ok(())
+                            },
+                            segment.composed_bounds(options),
+                            image_scale,
+                        );
+
+                        match image {
+                            Ok(image) => Some(image),
+                            Err(e) => {
+                                log::error!("gen_images() in brushstroke failed with Err {}", e);
+                                None
+                            }
+                        }
+                    })
+                    .collect::<Vec<render::Image>>(),
+                Style::Stamped(options) => self
+                    .path
+                    .iter()
+                    .filter_map(|segment| {
+                        let image = render::Image::gen_with_piet(
+                            |piet_cx| {
+                                segment.draw_composed(piet_cx, options);
+                                Ok(())
+                            },
+                            segment.composed_bounds(options),
+                            image_scale,
+                        );
+
+                        match image {
+                            Ok(image) => Some(image),
+                            Err(e) => {
+                                log::error!("gen_images() in brushstroke failed with Err {}", e);
+                                None
+                            }
+                        }
+                    })
+                    .collect::<Vec<render::Image>>(),
                 Style::Rough(_) => {
                     // Unsupported
                     vec![]
@@ -182,6 +271,8 @@ impl DrawBehaviour for BrushStroke {
 
         match &self.style {
             Style::Smooth(options) => self.path.draw_composed(cx, options),
+            Style::Calligraphy(options) => self.path.draw_composed(cx, options),
+            Style::Stamped(options) => self.path.draw_composed(cx, options),
             Style::Rough(_) => {
                 // Rough style currently unsupported for pen paths
             }
@@ -197,6 +288,8 @@ impl ShapeBehaviour for BrushStroke {
     fn bounds(&self) -> AABB {
         match &self.style {
             Style::Smooth(options) => self.path.composed_bounds(options),
+            Style::Calligraphy(options) => self.path.composed_bounds(options),
+            Style::Stamped(options) => self.path.composed_bounds(options),
             // TODO: Needs fixing
             Style::Rough(_options) => self.path.bounds(),
             Style::Textured(options) => self.path.composed_bounds(options),
@@ -237,6 +330,7 @@ impl BrushStroke {
         let mut new_brushstroke = Self {
             path,
             style,
+            opacity: 1.0,
             hitboxes: vec![],
         };
         new_brushstroke.update_geometry();
@@ -244,6 +338,13 @@ impl BrushStroke {
         Some(new_brushstroke)
     }
 
+    /// An estimation of the bytes used by the stroke, including the path and the cached hitboxes
+    pub fn estimate_memory_usage(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.path.len() * std::mem::size_of::<Segment>()
+            + self.hitboxes.len() * std::mem::size_of::<AABB>()
+    }
+
     pub fn push_segment(&mut self, segment: Segment) {
         self.path.push_back(segment);
     }
@@ -252,12 +353,105 @@ impl BrushStroke {
         self.hitboxes = self.gen_hitboxes();
     }
 
+    /// Compacts the path, merging nearly collinear segments. See `PenPath::compact()`
+    pub fn compact(&mut self, epsilon: f64) {
+        self.path.compact(epsilon);
+        self.update_geometry();
+    }
+
+    /// Simplifies the path with the Ramer-Douglas-Peucker algorithm. See `PenPath::simplify()`
+    pub fn simplify(&mut self, epsilon: f64) {
+        self.path.simplify(epsilon);
+        self.update_geometry();
+    }
+
+    /// Straightens the path into a single line, if it is nearly straight. See `PenPath::straighten()`
+    pub fn straighten(&mut self, max_deviation_ratio: f64) -> bool {
+        let straightened = self.path.straighten(max_deviation_ratio);
+        self.update_geometry();
+        straightened
+    }
+
     /// Replacing the current path with a new one. the new path must not be empty.
     pub fn replace_path(&mut self, path: PenPath) {
         self.path = path;
         self.update_geometry();
     }
 
+    /// The nodes of the path, which are the joints between its segments.
+    /// Node i is the start of segment i, the last node is the end of the last segment.
+    pub fn nodes(&self) -> Vec<na::Vector2<f64>> {
+        self.path
+            .front()
+            .map(|first| first.start().pos)
+            .into_iter()
+            .chain(self.path.iter().map(|segment| segment.end().pos))
+            .collect()
+    }
+
+    /// Moves the node with the given index to the position, together with the adjacent segment ends.
+    pub fn move_node(&mut self, index: usize, pos: na::Vector2<f64>) {
+        if let Some(segment) = self.path.get_mut(index) {
+            segment.set_start_pos(pos);
+        }
+        if index > 0 {
+            if let Some(segment) = self.path.get_mut(index - 1) {
+                segment.set_end_pos(pos);
+            }
+        }
+        self.update_geometry();
+    }
+
+    /// Inserts a node by splitting the segment nearest to the position, if it is within the max distance.
+    /// Returns the index of the inserted node.
+    pub fn insert_node_at(&mut self, pos: na::Vector2<f64>, max_distance: f64) -> Option<usize> {
+        let (index, t, distance) = self
+            .path
+            .iter()
+            .enumerate()
+            .map(|(index, segment)| {
+                let (t, distance) = segment.nearest_t(pos);
+                (index, t, distance)
+            })
+            .min_by(|first, second| first.2.total_cmp(&second.2))?;
+
+        // Positions at the segment ends are already nodes
+        if distance > max_distance || t <= 0.0 || t >= 1.0 {
+            return None;
+        }
+
+        let segment = self.path[index].clone();
+        self.path[index] = segment.sub_segment(0.0, t);
+        self.path.insert(index + 1, segment.sub_segment(t, 1.0));
+        self.update_geometry();
+
+        Some(index + 1)
+    }
+
+    /// Removes the node with the given index. Inner nodes are removed by joining the adjacent segments with a line.
+    /// The path keeps at least one segment. Returns false if the node could not be removed.
+    pub fn remove_node(&mut self, index: usize) -> bool {
+        let n_segments = self.path.len();
+        if n_segments <= 1 || index > n_segments {
+            return false;
+        }
+
+        if index == 0 {
+            self.path.pop_front();
+        } else if index == n_segments {
+            self.path.pop_back();
+        } else {
+            let start = self.path[index - 1].start();
+            let end = self.path[index].end();
+
+            self.path[index - 1] = Segment::Line { start, end };
+            self.path.remove(index);
+        }
+        self.update_geometry();
+
+        true
+    }
+
     // internal method generating the current hitboxes.
     fn gen_hitboxes(&self) -> Vec<AABB> {
         let stroke_width = self.style.stroke_width();
@@ -304,6 +498,56 @@ impl BrushStroke {
                     }
                 })
                 .collect::<Vec<render::Image>>(),
+            Style::Calligraphy(options) => self
+                .path
+                .iter()
+                .rev()
+                .take(no_last_segments)
+                .rev()
+                .filter_map(|segment| {
+                    let image = render::Image::gen_with_piet(
+                        |piet_cx| {
+                            segment.draw_composed(piet_cx, options);
+                            Ok(())
+                        },
+                        segment.composed_bounds(options),
+                        image_scale,
+                    );
+
+                    match image {
+                        Ok(image) => Some(image),
+                        Err(e) => {
+                            log::error!("gen_images_for_last_segments() failed with Err {}", e);
+                            None
+                        }
+                    }
+                })
+                .collect::<Vec<render::Image>>(),
+            Style::Stamped(options) => self
+                .path
+                .iter()
+                .rev()
+                .take(no_last_segments)
+                .rev()
+                .filter_map(|segment| {
+                    let image = render::Image::gen_with_piet(
+                        |piet_cx| {
+                            segment.draw_composed(piet_cx, options);
+                            Ok(())
+                        },
+                        segment.composed_bounds(options),
+                        image_scale,
+                    );
+
+                    match image {
+                        Ok(image) => Some(image),
+                        Err(e) => {
+                            log::error!("gen_images_for_last_segments() failed with Err {}", e);
+                            None
+                        }
+                    }
+                })
+                .collect::<Vec<render::Image>>(),
             Style::Rough(_) => vec![],
             Style::Textured(options) => self
                 .path
@@ -0,0 +1,184 @@
+use std::cell::Cell;
+use std::sync::Arc;
+
+use once_cell::sync::OnceCell;
+use p2d::bounding_volume::AABB;
+use rnote_compose::helpers::AABBHelpers;
+use rnote_fileformats::rnoteformat;
+
+use super::Stroke;
+
+thread_local! {
+    /// When set, strokes are deserialized as deferred strokes.
+    static DEFER_DESERIALIZATION: Cell<bool> = Cell::new(false);
+}
+
+/// A stroke of which the deserialization is deferred until it is needed, e.g. when it enters the viewport.
+/// Only the bounds and the peeked properties are known, the data is kept CBOR encoded.
+#[derive(Debug, Clone)]
+pub struct DeferredStroke {
+    pub bounds: AABB,
+    /// The CBOR encoded stroke
+    data: Arc<[u8]>,
+    peeked: Arc<PeekedProperties>,
+    /// The stroke, cached after it was loaded the first time
+    loaded: Arc<OnceCell<Stroke>>,
+}
+
+/// The properties that are peeked from the serialized stroke, so that they are available without loading it.
+#[derive(Debug, Clone)]
+struct PeekedProperties {
+    type_name: String,
+    opacity: f64,
+    reference: bool,
+}
+
+impl PeekedProperties {
+    /// Peeks into the serialized representation of the stroke, which is `{ "<type name>": { <fields> } }`
+    fn from_value(value: &serde_json::Value) -> Self {
+        let (type_name, stroke) = value
+            .as_object()
+            .and_then(|variant| variant.iter().next())
+            .map(|(type_name, stroke)| (type_name.clone(), Some(stroke)))
+            .unwrap_or_default();
+        let field = |name: &str| stroke.and_then(|stroke| stroke.get(name));
+
+        Self {
+            type_name,
+            opacity: field("opacity")
+                .and_then(|opacity| opacity.as_f64())
+                .unwrap_or(1.0),
+            reference: field("reference")
+                .and_then(|reference| reference.as_bool())
+                .unwrap_or(false),
+        }
+    }
+}
+
+impl DeferredStroke {
+    /// A new deferred stroke from its serialized representation. The bounds are unknown and need to be set before it is used.
+    pub fn new(value: serde_json::Value) -> anyhow::Result<Self> {
+        Ok(Self {
+            bounds: AABB::new_zero(),
+            data: Arc::from(rnoteformat::encode_cbor(&value)?),
+            peeked: Arc::new(PeekedProperties::from_value(&value)),
+            loaded: Arc::new(OnceCell::new()),
+        })
+    }
+
+    /// The name of the stroke type, as it is serialized
+    pub fn type_name(&self) -> &str {
+        &self.peeked.type_name
+    }
+
+    /// The opacity of the stroke
+    pub fn opacity(&self) -> f64 {
+        self.peeked.opacity
+    }
+
+    /// Wether the stroke is a reference image
+    pub fn is_reference(&self) -> bool {
+        self.peeked.reference
+    }
+
+    /// The size of the encoded stroke data in bytes
+    pub fn data_len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Decodes the serialized representation of the stroke.
+    pub fn decode_value(&self) -> anyhow::Result<serde_json::Value> {
+        rnoteformat::decode_cbor(&self.data)
+    }
+
+    /// The loaded stroke. It is deserialized on the first call, and cached for the following calls.
+    pub fn loaded(&self) -> anyhow::Result<&Stroke> {
+        self.loaded
+            .get_or_try_init(|| rnoteformat::decode_cbor(&self.data))
+    }
+
+    /// Loads the stroke.
+    pub fn load(&self) -> anyhow::Result<Stroke> {
+        self.loaded().cloned()
+    }
+
+    /// Loads the stroke, avoiding cloning the cached stroke if it is not shared.
+    pub fn into_loaded(self) -> anyhow::Result<Stroke> {
+        let cached = match Arc::try_unwrap(self.loaded) {
+            Ok(loaded) => loaded.into_inner(),
+            Err(loaded) => loaded.get().cloned(),
+        };
+
+        match cached {
+            Some(stroke) => Ok(stroke),
+            None => rnoteformat::decode_cbor(&self.data),
+        }
+    }
+
+    /// Wether strokes are currently deserialized as deferred strokes on this thread
+    pub(crate) fn deferring_deserialization() -> bool {
+        DEFER_DESERIALIZATION.with(|defer| defer.get())
+    }
+
+    /// Executes the function while deserializing strokes as deferred strokes on this thread
+    pub(crate) fn with_deferred_deserialization<T>(func: impl FnOnce() -> T) -> T {
+        /// Resets the flag when dropped, so it is also reset when the function panics
+        struct ResetGuard(bool);
+
+        impl Drop for ResetGuard {
+            fn drop(&mut self) {
+                DEFER_DESERIALIZATION.with(|defer| defer.set(self.0));
+            }
+        }
+
+        let _reset_guard = ResetGuard(DEFER_DESERIALIZATION.with(|defer| defer.replace(true)));
+
+        func()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deferred_stroke_roundtrip() {
+        let mut stroke = Stroke::line_fixture(na::vector![0.0, 0.0], na::vector![100.0, 50.0]);
+        stroke.set_opacity(0.5);
+        let value = serde_json::to_value(&stroke).unwrap();
+
+        let deferred = match DeferredStroke::with_deferred_deserialization(|| {
+            serde_json::from_value::<Stroke>(value.clone())
+        })
+        .unwrap()
+        {
+            Stroke::Deferred(deferred) => deferred,
+            _ => panic!("stroke is not deferred"),
+        };
+        assert_eq!(deferred.type_name(), "brushstroke");
+        assert_eq!(deferred.opacity(), 0.5);
+        assert!(!deferred.is_reference());
+        assert_eq!(deferred.decode_value().unwrap(), value);
+
+        // The loaded stroke is cached
+        let loaded = deferred.loaded().unwrap() as *const Stroke;
+        assert_eq!(deferred.loaded().unwrap() as *const Stroke, loaded);
+        assert_eq!(
+            serde_json::to_value(deferred.into_loaded().unwrap()).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn deferred_deserialization_reset_on_panic() {
+        let res = std::panic::catch_unwind(|| {
+            DeferredStroke::with_deferred_deserialization(|| {
+                assert!(DeferredStroke::deferring_deserialization());
+                panic!("deserializing failed");
+            })
+        });
+
+        assert!(res.is_err());
+        assert!(!DeferredStroke::deferring_deserialization());
+    }
+}
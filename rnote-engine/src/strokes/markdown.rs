@@ -0,0 +1,487 @@
+use super::textstroke::{FontStyle, RangedTextAttribute, TextAttribute, TextStyle};
+
+/// Converts a subset of markdown into plain text and ranged text attributes for text strokes.
+///
+/// Supported are ATX headings, unordered and ordered lists, fenced code blocks and the inline
+/// emphasis (`*`, `_`), strong emphasis (`**`, `__`), strikethrough (`~~`) and code spans (`` ` ``).
+/// Everything else is kept as text.
+#[derive(Debug, Clone)]
+pub struct MarkdownConverter<'a> {
+    text_style: &'a TextStyle,
+    text: String,
+    attributes: Vec<RangedTextAttribute>,
+}
+
+impl<'a> MarkdownConverter<'a> {
+    /// The font family for code spans and blocks
+    pub const CODE_FONT_FAMILY: &'static str = "monospace";
+    /// The font weight for headings and strong emphasis
+    pub const BOLD_FONT_WEIGHT: u16 = 700;
+    /// The font size factors for the heading levels 1 to 6
+    pub const HEADING_FONT_SIZE_FACTORS: [f64; 6] = [2.0, 1.6, 1.3, 1.15, 1.0, 1.0];
+    /// The indentation of nested list items
+    pub const LIST_INDENT: &'static str = "    ";
+    /// The prefix of unordered list items
+    pub const BULLET: &'static str = "• ";
+
+    pub fn new(text_style: &'a TextStyle) -> Self {
+        Self {
+            text_style,
+            text: String::new(),
+            attributes: vec![],
+        }
+    }
+
+    /// Converts the markdown, returning the text and the attributes for it
+    pub fn convert(mut self, markdown: &str) -> (String, Vec<RangedTextAttribute>) {
+        let mut in_code_block = false;
+
+        for line in markdown.lines() {
+            let trimmed = line.trim_start();
+
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_code_block = !in_code_block;
+                continue;
+            }
+            if !self.text.is_empty() {
+                self.text.push('\n');
+            }
+
+            if in_code_block {
+                let start = self.text.len();
+                self.text.push_str(line);
+                self.push_attr(
+                    start..self.text.len(),
+                    TextAttribute::FontFamily(String::from(Self::CODE_FONT_FAMILY)),
+                );
+            } else if let Some((level, content)) = Self::heading(trimmed) {
+                let start = self.text.len();
+                self.convert_inline(content);
+                let range = start..self.text.len();
+
+                self.push_attr(
+                    range.clone(),
+                    TextAttribute::FontSize(
+                        self.text_style.font_size * Self::HEADING_FONT_SIZE_FACTORS[level - 1],
+                    ),
+                );
+                self.push_attr(range, TextAttribute::FontWeight(Self::BOLD_FONT_WEIGHT));
+            } else if let Some((depth, marker, content)) = Self::list_item(line) {
+                for _ in 0..depth {
+                    self.text.push_str(Self::LIST_INDENT);
+                }
+                self.text.push_str(&marker);
+                self.convert_inline(content);
+            } else {
+                self.convert_inline(line);
+            }
+        }
+
+        let text_len = self.text.trim_end().len();
+        self.text.truncate(text_len);
+        self.attributes.retain(|attr| attr.range.start < text_len);
+        for attr in self.attributes.iter_mut() {
+            attr.range.end = attr.range.end.min(text_len);
+        }
+
+        (self.text, self.attributes)
+    }
+
+    fn push_attr(&mut self, range: std::ops::Range<usize>, attribute: TextAttribute) {
+        if !range.is_empty() {
+            self.attributes
+                .push(RangedTextAttribute { range, attribute });
+        }
+    }
+
+    /// Returns the level and the content, if the line is a heading
+    fn heading(line: &str) -> Option<(usize, &str)> {
+        let level = line.chars().take_while(|&c| c == '#').count();
+        if level == 0 || level > 6 {
+            return None;
+        }
+        let rest = &line[level..];
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+
+        Some((level, rest.trim().trim_end_matches('#').trim_end()))
+    }
+
+    /// Returns the nesting depth, the marker as it should be displayed and the content, if the line is a list item
+    fn list_item(line: &str) -> Option<(usize, String, &str)> {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        let depth = line[..indent]
+            .chars()
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum::<usize>()
+            / 2;
+
+        let mut chars = trimmed.chars();
+        match chars.next() {
+            Some('-' | '*' | '+') if chars.next() == Some(' ') => {
+                Some((depth, String::from(Self::BULLET), trimmed[2..].trim_start()))
+            }
+            _ => {
+                let n_digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+                if n_digits == 0 || n_digits > 9 {
+                    return None;
+                }
+                let rest = &trimmed[n_digits..];
+                if rest.starts_with(". ") || rest.starts_with(") ") {
+                    Some((
+                        depth,
+                        format!("{}. ", &trimmed[..n_digits]),
+                        rest[2..].trim_start(),
+                    ))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Converts the inline formatting
+    fn convert_inline(&mut self, src: &str) {
+        let mut pos = 0;
+
+        while pos < src.len() {
+            let rest = &src[pos..];
+
+            // Escaped characters are kept as they are
+            if let Some(escaped) = rest.strip_prefix('\\') {
+                if let Some(c) = escaped.chars().next().filter(|c| c.is_ascii_punctuation()) {
+                    self.text.push(c);
+                    pos += 1 + c.len_utf8();
+                    continue;
+                }
+            }
+
+            if let Some((delimiter, inner)) = Self::delimited(src, pos) {
+                let start = self.text.len();
+
+                if delimiter == "`" {
+                    self.text.push_str(inner);
+                    self.push_attr(
+                        start..self.text.len(),
+                        TextAttribute::FontFamily(String::from(Self::CODE_FONT_FAMILY)),
+                    );
+                } else {
+                    self.convert_inline(inner);
+                    let attribute = match delimiter {
+                        "**" | "__" => TextAttribute::FontWeight(Self::BOLD_FONT_WEIGHT),
+                        "~~" => TextAttribute::Strikethrough(true),
+                        _ => TextAttribute::Style(FontStyle::Italic),
+                    };
+                    self.push_attr(start..self.text.len(), attribute);
+                }
+
+                pos += inner.len() + delimiter.len() * 2;
+                continue;
+            }
+
+            // Safe to unwrap, pos is below the length
+            let c = rest.chars().next().unwrap();
+            self.text.push(c);
+            pos += c.len_utf8();
+        }
+    }
+
+    /// Returns the delimiter and the enclosed content, if a delimited inline span starts at the position
+    fn delimited(src: &str, pos: usize) -> Option<(&'static str, &str)> {
+        const DELIMITERS: [&str; 6] = ["**", "__", "~~", "`", "*", "_"];
+
+        let rest = &src[pos..];
+        let delimiter = DELIMITERS
+            .into_iter()
+            .find(|delimiter| rest.starts_with(delimiter))?;
+
+        // Underscores inside words, like in snake_case, are no emphasis
+        if delimiter.starts_with('_')
+            && matches!(src[..pos].chars().next_back(), Some(c) if c.is_alphanumeric())
+        {
+            return None;
+        }
+
+        let content = &rest[delimiter.len()..];
+        if content.starts_with(char::is_whitespace) {
+            return None;
+        }
+        // Single emphasis delimiters must not be part of a double delimiter
+        let end = if delimiter.len() == 1 && delimiter != "`" {
+            content
+                .match_indices(delimiter)
+                .map(|(i, _)| i)
+                .find(|&i| {
+                    !content[i + 1..].starts_with(delimiter) && !content[..i].ends_with(delimiter)
+                })?
+        } else {
+            content.find(delimiter)?
+        };
+        if end == 0 {
+            return None;
+        }
+
+        Some((delimiter, &content[..end]))
+    }
+}
+
+/// The inline formatting that is written as markdown delimiters, ordered from outermost to innermost
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InlineMarker {
+    Bold,
+    Italic,
+    Strikethrough,
+    Code,
+}
+
+impl InlineMarker {
+    fn delimiter(self) -> &'static str {
+        match self {
+            Self::Bold => "**",
+            // Using underscores, so that combined with bold it doesn't end up as ambiguous `***`
+            Self::Italic => "_",
+            Self::Strikethrough => "~~",
+            Self::Code => "`",
+        }
+    }
+
+    fn for_attribute(attribute: &TextAttribute) -> Option<Self> {
+        match attribute {
+            TextAttribute::FontWeight(weight)
+                if *weight >= MarkdownWriter::BOLD_FONT_WEIGHT_MIN =>
+            {
+                Some(Self::Bold)
+            }
+            TextAttribute::Style(FontStyle::Italic) => Some(Self::Italic),
+            TextAttribute::Strikethrough(true) => Some(Self::Strikethrough),
+            TextAttribute::FontFamily(font_family)
+                if font_family == MarkdownConverter::CODE_FONT_FAMILY =>
+            {
+                Some(Self::Code)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Writes text with ranged text attributes as markdown. The inverse of `MarkdownConverter`
+#[derive(Debug, Clone)]
+pub struct MarkdownWriter<'a> {
+    text_style: &'a TextStyle,
+    markdown: String,
+}
+
+impl<'a> MarkdownWriter<'a> {
+    /// The min font weight that is written as strong emphasis
+    pub const BOLD_FONT_WEIGHT_MIN: u16 = 600;
+
+    pub fn new(text_style: &'a TextStyle) -> Self {
+        Self {
+            text_style,
+            markdown: String::new(),
+        }
+    }
+
+    /// Writes the text as markdown. The attributes are taken from the text style
+    pub fn write(mut self, text: &str) -> String {
+        let mut line_start = 0;
+        let mut in_code_block = false;
+
+        for (i, line) in text.split('\n').enumerate() {
+            let line_range = line_start..line_start + line.len();
+            line_start = line_range.end + 1;
+
+            let code_line = !line.is_empty()
+                && self.covered_by(line_range.clone(), |attr| {
+                    InlineMarker::for_attribute(attr) == Some(InlineMarker::Code)
+                });
+            if code_line != in_code_block {
+                if i > 0 {
+                    self.markdown.push('\n');
+                }
+                self.markdown.push_str("```");
+                in_code_block = code_line;
+            }
+            if i > 0 || in_code_block {
+                self.markdown.push('\n');
+            }
+
+            if in_code_block {
+                self.markdown.push_str(line);
+            } else if let Some(level) = self.heading_level(line_range.clone()) {
+                for _ in 0..level {
+                    self.markdown.push('#');
+                }
+                self.markdown.push(' ');
+                self.write_inline(text, line_range, true);
+            } else {
+                let mut content_start = line_range.start;
+                let mut content = line;
+
+                while let Some(rest) = content.strip_prefix(MarkdownConverter::LIST_INDENT) {
+                    self.markdown.push_str("  ");
+                    content_start += MarkdownConverter::LIST_INDENT.len();
+                    content = rest;
+                }
+                if let Some(rest) = content.strip_prefix(MarkdownConverter::BULLET) {
+                    self.markdown.push_str("- ");
+                    content_start += MarkdownConverter::BULLET.len();
+                    content = rest;
+                } else if content_start > line_range.start {
+                    // The indentation was not part of a list item
+                    let indent_len = content_start - line_range.start;
+                    self.markdown.truncate(self.markdown.len() - indent_len / 2);
+                    content_start = line_range.start;
+                    content = line;
+                }
+
+                self.write_inline(text, content_start..content_start + content.len(), false);
+            }
+        }
+        if in_code_block {
+            self.markdown.push_str("\n```");
+        }
+
+        self.markdown
+    }
+
+    /// Wether the range is covered by an attribute for which the predicate returns true
+    fn covered_by<P>(&self, range: std::ops::Range<usize>, predicate: P) -> bool
+    where
+        P: Fn(&TextAttribute) -> bool,
+    {
+        self.text_style.ranged_text_attributes.iter().any(|attr| {
+            attr.range.start <= range.start
+                && attr.range.end >= range.end
+                && predicate(&attr.attribute)
+        })
+    }
+
+    /// The heading level of the line, if it is entirely set in an enlarged font size
+    fn heading_level(&self, line_range: std::ops::Range<usize>) -> Option<usize> {
+        if line_range.is_empty() {
+            return None;
+        }
+
+        self.text_style
+            .ranged_text_attributes
+            .iter()
+            .filter_map(|attr| match attr.attribute {
+                TextAttribute::FontSize(font_size)
+                    if attr.range.start <= line_range.start && attr.range.end >= line_range.end =>
+                {
+                    Some(font_size / self.text_style.font_size)
+                }
+                _ => None,
+            })
+            .filter(|&ratio| ratio > 1.0 + f64::EPSILON)
+            .find_map(|ratio| {
+                MarkdownConverter::HEADING_FONT_SIZE_FACTORS
+                    .iter()
+                    .position(|&factor| ratio >= factor - 0.01)
+                    .map(|index| index + 1)
+            })
+    }
+
+    /// Writes the inline formatting of the text in the range
+    fn write_inline(&mut self, text: &str, range: std::ops::Range<usize>, in_heading: bool) {
+        let attrs = self
+            .text_style
+            .ranged_text_attributes
+            .iter()
+            .filter_map(|attr| {
+                let marker = InlineMarker::for_attribute(&attr.attribute)?;
+                if in_heading && marker == InlineMarker::Bold {
+                    return None;
+                }
+                let start = attr.range.start.max(range.start);
+                let end = attr.range.end.min(range.end);
+                if start < end {
+                    Some((start..end, marker))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<(std::ops::Range<usize>, InlineMarker)>>();
+
+        let mut boundaries = attrs
+            .iter()
+            .flat_map(|(attr_range, _)| [attr_range.start, attr_range.end])
+            .chain([range.start, range.end])
+            .filter(|&boundary| text.is_char_boundary(boundary))
+            .collect::<Vec<usize>>();
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        let mut open_markers: Vec<InlineMarker> = vec![];
+        for segment in boundaries.windows(2) {
+            let segment = segment[0]..segment[1];
+            let mut active_markers = [
+                InlineMarker::Bold,
+                InlineMarker::Italic,
+                InlineMarker::Strikethrough,
+                InlineMarker::Code,
+            ]
+            .into_iter()
+            .filter(|&marker| {
+                attrs.iter().any(|(attr_range, attr_marker)| {
+                    *attr_marker == marker
+                        && attr_range.start <= segment.start
+                        && attr_range.end >= segment.end
+                })
+            })
+            .collect::<Vec<InlineMarker>>();
+            // Nothing can be nested inside code spans
+            if active_markers.contains(&InlineMarker::Code) {
+                active_markers.retain(|&marker| {
+                    marker == InlineMarker::Code || open_markers.contains(&marker)
+                });
+            }
+
+            // Closing in reverse order, reopening the ones above which are still active
+            if let Some(index) = open_markers
+                .iter()
+                .position(|marker| !active_markers.contains(marker))
+            {
+                for marker in open_markers.drain(index..).rev() {
+                    self.markdown.push_str(marker.delimiter());
+                }
+            }
+            for marker in active_markers {
+                if !open_markers.contains(&marker) {
+                    self.markdown.push_str(marker.delimiter());
+                    open_markers.push(marker);
+                }
+            }
+
+            if open_markers.contains(&InlineMarker::Code) {
+                self.markdown.push_str(&text[segment]);
+            } else {
+                self.write_escaped(&text[segment]);
+            }
+        }
+        for marker in open_markers.into_iter().rev() {
+            self.markdown.push_str(marker.delimiter());
+        }
+    }
+
+    /// Escapes the characters that would otherwise be read as inline formatting
+    fn write_escaped(&mut self, text: &str) {
+        for c in text.chars() {
+            let escape = match c {
+                '\\' | '*' | '`' | '~' => true,
+                // Underscores inside words, like in snake_case, are no emphasis
+                '_' => {
+                    !matches!(self.markdown.chars().next_back(), Some(prev) if prev.is_alphanumeric())
+                }
+                _ => false,
+            };
+            if escape {
+                self.markdown.push('\\');
+            }
+            self.markdown.push(c);
+        }
+    }
+}
@@ -1,16 +1,21 @@
 pub mod bitmapimage;
 pub mod brushstroke;
+pub mod deferredstroke;
+pub mod markdown;
 pub mod shapestroke;
 pub mod stroke;
 pub mod strokebehaviour;
+pub mod strokestyle;
 pub mod textstroke;
 pub mod vectorimage;
 
 // Re-exports
 pub use bitmapimage::BitmapImage;
 pub use brushstroke::BrushStroke;
+pub use deferredstroke::DeferredStroke;
 pub use shapestroke::ShapeStroke;
 pub use stroke::Stroke;
 pub use strokebehaviour::StrokeBehaviour;
+pub use strokestyle::{StrokeRestyle, StrokeStyle};
 pub use textstroke::TextStroke;
 pub use vectorimage::VectorImage;
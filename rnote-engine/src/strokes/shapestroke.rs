@@ -12,18 +12,27 @@ use rnote_compose::Style;
 use p2d::bounding_volume::{BoundingVolume, AABB};
 use serde::{Deserialize, Serialize};
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default, rename = "shapestroke")]
 pub struct ShapeStroke {
     #[serde(rename = "shape")]
     pub shape: Shape,
     #[serde(rename = "style")]
     pub style: Style,
+    /// The opacity the stroke is drawn with. See `Stroke::opacity()`
+    #[serde(rename = "opacity")]
+    pub opacity: f64,
     #[serde(skip)]
     // since the shape can have many hitboxes, we store them for faster queries and update them when the stroke geometry changes
     hitboxes: Vec<AABB>,
 }
 
+impl Default for ShapeStroke {
+    fn default() -> Self {
+        Self::new(Shape::default(), Style::default())
+    }
+}
+
 impl StrokeBehaviour for ShapeStroke {
     fn gen_svg(&self) -> Result<crate::render::Svg, anyhow::Error> {
         let bounds = self.bounds();
@@ -87,6 +96,8 @@ impl ShapeBehaviour for ShapeStroke {
             Style::Smooth(options) => self.shape.composed_bounds(options),
             Style::Rough(options) => self.shape.composed_bounds(options),
             Style::Textured(_) => self.shape.bounds(),
+            Style::Calligraphy(options) => self.shape.composed_bounds(options),
+            Style::Stamped(_) => self.shape.bounds(),
         }
     }
 
@@ -112,6 +123,7 @@ impl ShapeStroke {
         let mut shapestroke = Self {
             shape,
             style,
+            opacity: 1.0,
             hitboxes: vec![],
         };
         shapestroke.update_geometry();
@@ -123,6 +135,109 @@ impl ShapeStroke {
         self.hitboxes = self.gen_hitboxes();
     }
 
+    /// The nodes of the shape: the end points, control points and vertices.
+    /// Rectangles and ellipses are defined by their transform and have no nodes.
+    pub fn nodes(&self) -> Vec<na::Vector2<f64>> {
+        match &self.shape {
+            Shape::Line(line) => vec![line.start, line.end],
+            Shape::Arrow(arrow) => vec![arrow.start, arrow.end],
+            Shape::QuadraticBezier(quadbez) => vec![quadbez.start, quadbez.cp, quadbez.end],
+            Shape::CubicBezier(cubbez) => {
+                vec![cubbez.start, cubbez.cp1, cubbez.cp2, cubbez.end]
+            }
+            Shape::Segment(segment) => vec![segment.start().pos, segment.end().pos],
+            Shape::Polygon(polygon) => polygon.vertices.clone(),
+            Shape::Rectangle(_) | Shape::Ellipse(_) => vec![],
+        }
+    }
+
+    /// Moves the node with the given index to the position. See `nodes()`
+    pub fn move_node(&mut self, index: usize, pos: na::Vector2<f64>) {
+        let node = match &mut self.shape {
+            Shape::Line(line) => [&mut line.start, &mut line.end].into_iter().nth(index),
+            Shape::Arrow(arrow) => [&mut arrow.start, &mut arrow.end].into_iter().nth(index),
+            Shape::QuadraticBezier(quadbez) => {
+                [&mut quadbez.start, &mut quadbez.cp, &mut quadbez.end]
+                    .into_iter()
+                    .nth(index)
+            }
+            Shape::CubicBezier(cubbez) => [
+                &mut cubbez.start,
+                &mut cubbez.cp1,
+                &mut cubbez.cp2,
+                &mut cubbez.end,
+            ]
+            .into_iter()
+            .nth(index),
+            Shape::Segment(segment) => {
+                match index {
+                    0 => segment.set_start_pos(pos),
+                    1 => segment.set_end_pos(pos),
+                    _ => {}
+                }
+                None
+            }
+            Shape::Polygon(polygon) => polygon.vertices.get_mut(index),
+            Shape::Rectangle(_) | Shape::Ellipse(_) => None,
+        };
+        if let Some(node) = node {
+            *node = pos;
+        }
+        self.update_geometry();
+    }
+
+    /// Inserts a vertex into the polygon edge nearest to the position, if it is within the max distance.
+    /// Only polygons can have nodes inserted. Returns the index of the inserted node.
+    pub fn insert_node_at(&mut self, pos: na::Vector2<f64>, max_distance: f64) -> Option<usize> {
+        let polygon = match &mut self.shape {
+            Shape::Polygon(polygon) => polygon,
+            _ => return None,
+        };
+        let n_vertices = polygon.vertices.len();
+        if n_vertices < 2 {
+            return None;
+        }
+
+        let (index, projected, distance) = (0..n_vertices)
+            .map(|i| {
+                let start = polygon.vertices[i];
+                let edge = polygon.vertices[(i + 1) % n_vertices] - start;
+                let t = if edge.magnitude_squared() > 0.0 {
+                    ((pos - start).dot(&edge) / edge.magnitude_squared()).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let projected = start + edge * t;
+
+                (i, projected, (pos - projected).magnitude())
+            })
+            .min_by(|first, second| first.2.total_cmp(&second.2))?;
+
+        if distance > max_distance {
+            return None;
+        }
+
+        polygon.vertices.insert(index + 1, projected);
+        self.update_geometry();
+
+        Some(index + 1)
+    }
+
+    /// Removes the node with the given index. Only polygons with more than three vertices can have nodes removed.
+    /// Returns false if the node could not be removed.
+    pub fn remove_node(&mut self, index: usize) -> bool {
+        match &mut self.shape {
+            Shape::Polygon(polygon)
+                if polygon.vertices.len() > 3 && index < polygon.vertices.len() =>
+            {
+                polygon.vertices.remove(index);
+                self.update_geometry();
+                true
+            }
+            _ => false,
+        }
+    }
+
     fn gen_hitboxes(&self) -> Vec<AABB> {
         let width = self.style.stroke_width();
 
@@ -1,7 +1,10 @@
 use super::bitmapimage::BitmapImage;
 use super::brushstroke::BrushStroke;
+use super::deferredstroke::DeferredStroke;
 use super::shapestroke::ShapeStroke;
 use super::strokebehaviour::GeneratedStrokeImages;
+use super::strokestyle::{StrokeRestyle, StrokeStyle};
+use super::textstroke::TextStyle;
 use super::vectorimage::VectorImage;
 use super::{StrokeBehaviour, TextStroke};
 use crate::store::chrono_comp::StrokeLayer;
@@ -9,7 +12,7 @@ use crate::{render, RnoteEngine};
 use crate::{utils, DrawBehaviour};
 use rnote_compose::helpers::AABBHelpers;
 use rnote_compose::penpath::{Element, Segment};
-use rnote_compose::shapes::{Rectangle, ShapeBehaviour};
+use rnote_compose::shapes::{Line, Rectangle, ShapeBehaviour};
 use rnote_compose::style::smooth::SmoothOptions;
 use rnote_compose::transform::Transform;
 use rnote_compose::transform::TransformBehaviour;
@@ -19,9 +22,36 @@ use p2d::bounding_volume::AABB;
 use rnote_fileformats::xoppformat::{self, XoppColor};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename = "stroke")]
+#[derive(Debug, Clone)]
 pub enum Stroke {
+    BrushStroke(BrushStroke),
+    ShapeStroke(ShapeStroke),
+    TextStroke(TextStroke),
+    VectorImage(VectorImage),
+    BitmapImage(BitmapImage),
+    /// A stroke which is not yet deserialized. Gets loaded on demand.
+    Deferred(DeferredStroke),
+}
+
+// The serialized representation of strokes
+#[derive(Serialize)]
+#[serde(rename = "stroke")]
+enum StrokeSerRef<'a> {
+    #[serde(rename = "brushstroke")]
+    BrushStroke(&'a BrushStroke),
+    #[serde(rename = "shapestroke")]
+    ShapeStroke(&'a ShapeStroke),
+    #[serde(rename = "textstroke")]
+    TextStroke(&'a TextStroke),
+    #[serde(rename = "vectorimage")]
+    VectorImage(&'a VectorImage),
+    #[serde(rename = "bitmapimage")]
+    BitmapImage(&'a BitmapImage),
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "stroke")]
+enum StrokeDe {
     #[serde(rename = "brushstroke")]
     BrushStroke(BrushStroke),
     #[serde(rename = "shapestroke")]
@@ -34,6 +64,57 @@ pub enum Stroke {
     BitmapImage(BitmapImage),
 }
 
+impl Serialize for Stroke {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Self::BrushStroke(brushstroke) => {
+                StrokeSerRef::BrushStroke(brushstroke).serialize(serializer)
+            }
+            Self::ShapeStroke(shapestroke) => {
+                StrokeSerRef::ShapeStroke(shapestroke).serialize(serializer)
+            }
+            Self::TextStroke(textstroke) => {
+                StrokeSerRef::TextStroke(textstroke).serialize(serializer)
+            }
+            Self::VectorImage(vectorimage) => {
+                StrokeSerRef::VectorImage(vectorimage).serialize(serializer)
+            }
+            Self::BitmapImage(bitmapimage) => {
+                StrokeSerRef::BitmapImage(bitmapimage).serialize(serializer)
+            }
+            // the data is already in the serialized representation
+            Self::Deferred(deferred) => deferred
+                .decode_value()
+                .map_err(serde::ser::Error::custom)?
+                .serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Stroke {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        if DeferredStroke::deferring_deserialization() {
+            return DeferredStroke::new(serde_json::Value::deserialize(deserializer)?)
+                .map(Self::Deferred)
+                .map_err(serde::de::Error::custom);
+        }
+
+        Ok(match StrokeDe::deserialize(deserializer)? {
+            StrokeDe::BrushStroke(brushstroke) => Self::BrushStroke(brushstroke),
+            StrokeDe::ShapeStroke(shapestroke) => Self::ShapeStroke(shapestroke),
+            StrokeDe::TextStroke(textstroke) => Self::TextStroke(textstroke),
+            StrokeDe::VectorImage(vectorimage) => Self::VectorImage(vectorimage),
+            StrokeDe::BitmapImage(bitmapimage) => Self::BitmapImage(bitmapimage),
+        })
+    }
+}
+
 impl Default for Stroke {
     fn default() -> Self {
         Self::BrushStroke(BrushStroke::default())
@@ -42,13 +123,21 @@ impl Default for Stroke {
 
 impl StrokeBehaviour for Stroke {
     fn gen_svg(&self) -> Result<render::Svg, anyhow::Error> {
-        match self {
+        let mut svg = match self {
             Stroke::BrushStroke(brushstroke) => brushstroke.gen_svg(),
             Stroke::ShapeStroke(shapestroke) => shapestroke.gen_svg(),
             Stroke::TextStroke(textstroke) => textstroke.gen_svg(),
             Stroke::VectorImage(vectorimage) => vectorimage.gen_svg(),
             Stroke::BitmapImage(bitmapimage) => bitmapimage.gen_svg(),
+            Stroke::Deferred(deferred) => return deferred.loaded()?.gen_svg(),
+        }?;
+
+        let opacity = self.opacity();
+        if opacity < 1.0 {
+            svg.svg_data = format!("<g opacity=\"{}\">\n{}\n</g>", opacity, svg.svg_data);
         }
+
+        Ok(svg)
     }
 
     fn gen_images(
@@ -62,18 +151,46 @@ impl StrokeBehaviour for Stroke {
             Stroke::TextStroke(textstroke) => textstroke.gen_images(viewport, image_scale),
             Stroke::VectorImage(vectorimage) => vectorimage.gen_images(viewport, image_scale),
             Stroke::BitmapImage(bitmapimage) => bitmapimage.gen_images(viewport, image_scale),
+            Stroke::Deferred(deferred) => deferred.loaded()?.gen_images(viewport, image_scale),
         }
     }
 }
 
 impl DrawBehaviour for Stroke {
     fn draw(&self, cx: &mut impl piet::RenderContext, image_scale: f64) -> anyhow::Result<()> {
+        let opacity = self.opacity();
+
+        if opacity < 1.0 {
+            // piet has no support for group opacity, so the stroke is rasterized first
+            let mut image = render::Image::gen_with_piet(
+                |piet_cx| self.draw_opaque(piet_cx, image_scale),
+                self.bounds(),
+                image_scale,
+            )?;
+            image.convert_to_rgba8pre()?;
+            image.apply_opacity(opacity);
+
+            image.draw(cx, image_scale)
+        } else {
+            self.draw_opaque(cx, image_scale)
+        }
+    }
+}
+
+impl Stroke {
+    /// Draws the stroke without applying its opacity
+    fn draw_opaque(
+        &self,
+        cx: &mut impl piet::RenderContext,
+        image_scale: f64,
+    ) -> anyhow::Result<()> {
         match self {
             Stroke::BrushStroke(brushstroke) => brushstroke.draw(cx, image_scale),
             Stroke::ShapeStroke(shapestroke) => shapestroke.draw(cx, image_scale),
             Stroke::TextStroke(textstroke) => textstroke.draw(cx, image_scale),
             Stroke::VectorImage(vectorimage) => vectorimage.draw(cx, image_scale),
             Stroke::BitmapImage(bitmapimage) => bitmapimage.draw(cx, image_scale),
+            Stroke::Deferred(deferred) => deferred.loaded()?.draw_opaque(cx, image_scale),
         }
     }
 }
@@ -86,6 +203,7 @@ impl ShapeBehaviour for Stroke {
             Self::TextStroke(textstroke) => textstroke.bounds(),
             Self::VectorImage(vectorimage) => vectorimage.bounds(),
             Self::BitmapImage(bitmapimage) => bitmapimage.bounds(),
+            Self::Deferred(deferred) => deferred.bounds,
         }
     }
 
@@ -96,6 +214,7 @@ impl ShapeBehaviour for Stroke {
             Self::TextStroke(textstroke) => textstroke.hitboxes(),
             Self::VectorImage(vectorimage) => vectorimage.hitboxes(),
             Self::BitmapImage(bitmapimage) => bitmapimage.hitboxes(),
+            Self::Deferred(deferred) => vec![deferred.bounds],
         }
     }
 }
@@ -118,6 +237,15 @@ impl TransformBehaviour for Stroke {
             Self::BitmapImage(bitmapimage) => {
                 bitmapimage.translate(offset);
             }
+            Self::Deferred(deferred) => match deferred.load() {
+                Ok(mut stroke) => {
+                    stroke.translate(offset);
+                    *self = stroke;
+                }
+                Err(e) => {
+                    log::error!("loading deferred stroke failed in translate(), Err {}", e);
+                }
+            },
         }
     }
 
@@ -138,6 +266,15 @@ impl TransformBehaviour for Stroke {
             Self::BitmapImage(bitmapimage) => {
                 bitmapimage.rotate(angle, center);
             }
+            Self::Deferred(deferred) => match deferred.load() {
+                Ok(mut stroke) => {
+                    stroke.rotate(angle, center);
+                    *self = stroke;
+                }
+                Err(e) => {
+                    log::error!("loading deferred stroke failed in rotate(), Err {}", e);
+                }
+            },
         }
     }
 
@@ -158,17 +295,366 @@ impl TransformBehaviour for Stroke {
             Self::BitmapImage(bitmapimage) => {
                 bitmapimage.scale(scale);
             }
+            Self::Deferred(deferred) => match deferred.load() {
+                Ok(mut stroke) => {
+                    stroke.scale(scale);
+                    *self = stroke;
+                }
+                Err(e) => {
+                    log::error!("loading deferred stroke failed in scale(), Err {}", e);
+                }
+            },
         }
     }
 }
 
 impl Stroke {
+    /// The opacity reference images are drawn with
+    pub const REFERENCE_OPACITY: f64 = 0.35;
+    /// The minimum opacity of strokes, so that they don't become invisible
+    pub const OPACITY_MIN: f64 = 0.05;
+
+    /// Wether the stroke is a reference image. Reference images are underlays for tracing:
+    /// they are drawn dimmed, excluded from exports by default and can't be selected.
+    pub fn is_reference(&self) -> bool {
+        match self {
+            Stroke::VectorImage(vectorimage) => vectorimage.reference,
+            Stroke::BitmapImage(bitmapimage) => bitmapimage.reference,
+            Stroke::Deferred(deferred) => deferred.is_reference(),
+            Stroke::BrushStroke(_) | Stroke::ShapeStroke(_) | Stroke::TextStroke(_) => false,
+        }
+    }
+
+    /// The opacity the stroke is drawn with, on top of the colors of the stroke.
+    /// When rendering in the canvas it is applied while composing, so changing it does not need a rerender.
+    pub fn opacity(&self) -> f64 {
+        match self {
+            Stroke::BrushStroke(brushstroke) => brushstroke.opacity,
+            Stroke::ShapeStroke(shapestroke) => shapestroke.opacity,
+            Stroke::TextStroke(textstroke) => textstroke.opacity,
+            Stroke::VectorImage(vectorimage) => vectorimage.opacity,
+            Stroke::BitmapImage(bitmapimage) => bitmapimage.opacity,
+            Stroke::Deferred(deferred) => deferred.opacity(),
+        }
+    }
+
+    /// Sets the opacity of the stroke, clamped between `OPACITY_MIN` and 1.0
+    pub fn set_opacity(&mut self, opacity: f64) {
+        let opacity = opacity.clamp(Self::OPACITY_MIN, 1.0);
+
+        match self {
+            Stroke::BrushStroke(brushstroke) => brushstroke.opacity = opacity,
+            Stroke::ShapeStroke(shapestroke) => shapestroke.opacity = opacity,
+            Stroke::TextStroke(textstroke) => textstroke.opacity = opacity,
+            Stroke::VectorImage(vectorimage) => vectorimage.opacity = opacity,
+            Stroke::BitmapImage(bitmapimage) => bitmapimage.opacity = opacity,
+            Stroke::Deferred(deferred) => match deferred.load() {
+                Ok(mut stroke) => {
+                    stroke.set_opacity(opacity);
+                    *self = stroke;
+                }
+                Err(e) => {
+                    log::error!("loading deferred stroke failed in set_opacity(), Err {}", e);
+                }
+            },
+        }
+    }
+
+    /// The name of the stroke type, as it is serialized. Deferred strokes return the name of the stroke they hold.
+    pub fn type_name(&self) -> &str {
+        match self {
+            Stroke::BrushStroke(_) => "brushstroke",
+            Stroke::ShapeStroke(_) => "shapestroke",
+            Stroke::TextStroke(_) => "textstroke",
+            Stroke::VectorImage(_) => "vectorimage",
+            Stroke::BitmapImage(_) => "bitmapimage",
+            Stroke::Deferred(deferred) => deferred.type_name(),
+        }
+    }
+
+    /// The main color of the stroke. The stroke color for brush and shape strokes, the text color for text strokes.
+    /// None for images and strokes without a stroke color.
+    pub fn color(&self) -> Option<Color> {
+        match self {
+            Stroke::BrushStroke(brushstroke) => brushstroke.style.stroke_color(),
+            Stroke::ShapeStroke(shapestroke) => shapestroke.style.stroke_color(),
+            Stroke::TextStroke(textstroke) => Some(textstroke.text_style.color),
+            Stroke::VectorImage(_) | Stroke::BitmapImage(_) => None,
+            Stroke::Deferred(deferred) => match deferred.load() {
+                Ok(stroke) => stroke.color(),
+                Err(e) => {
+                    log::error!("loading deferred stroke failed in color(), Err {}", e);
+                    None
+                }
+            },
+        }
+    }
+
+    /// Applies the restyle to the stroke. Attributes which don't apply to the stroke are skipped,
+    /// e.g. images only have their opacity changed.
+    /// The geometry and rendering of the stroke then need to be updated if `StrokeRestyle::needs_rerender()`
+    pub fn restyle(&mut self, restyle: &StrokeRestyle) {
+        if let Some(opacity) = restyle.opacity {
+            self.set_opacity(opacity);
+        }
+
+        match self {
+            Stroke::BrushStroke(brushstroke) => {
+                if let Some(color) = restyle.color {
+                    brushstroke.style.set_stroke_color(Some(color));
+                }
+                if let Some(stroke_width) = restyle.stroke_width {
+                    brushstroke.style.set_stroke_width(stroke_width);
+                }
+            }
+            Stroke::ShapeStroke(shapestroke) => {
+                if let Some(color) = restyle.color {
+                    match &mut shapestroke.style {
+                        // Recolor the fill of shapes without outline, e.g. created by the fill tool
+                        Style::Smooth(options) if options.stroke_color.is_none() => {
+                            options.fill_color = Some(color);
+                        }
+                        style => style.set_stroke_color(Some(color)),
+                    }
+                }
+                if let Some(stroke_width) = restyle.stroke_width {
+                    shapestroke.style.set_stroke_width(stroke_width);
+                }
+            }
+            Stroke::TextStroke(textstroke) => {
+                if let Some(color) = restyle.color {
+                    textstroke.text_style.color = color;
+                }
+            }
+            Stroke::VectorImage(_) | Stroke::BitmapImage(_) => {}
+            Stroke::Deferred(deferred) => {
+                if !restyle.needs_rerender() {
+                    return;
+                }
+                match deferred.load() {
+                    Ok(mut stroke) => {
+                        stroke.restyle(restyle);
+                        *self = stroke;
+                    }
+                    Err(e) => {
+                        log::error!("loading deferred stroke failed in restyle(), Err {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Scales the stroke width of brush and shape strokes by the factor. Text strokes and images are scaled entirely by their transform.
+    /// The geometry and rendering of the stroke then need to be updated
+    pub fn scale_stroke_width(&mut self, factor: f64) {
+        match self {
+            Stroke::BrushStroke(brushstroke) => {
+                let stroke_width = brushstroke.style.stroke_width() * factor;
+                brushstroke.style.set_stroke_width(stroke_width);
+            }
+            Stroke::ShapeStroke(shapestroke) => {
+                let stroke_width = shapestroke.style.stroke_width() * factor;
+                shapestroke.style.set_stroke_width(stroke_width);
+            }
+            Stroke::TextStroke(_) | Stroke::VectorImage(_) | Stroke::BitmapImage(_) => {}
+            Stroke::Deferred(deferred) => match deferred.load() {
+                Ok(mut stroke) => {
+                    stroke.scale_stroke_width(factor);
+                    *self = stroke;
+                }
+                Err(e) => {
+                    log::error!(
+                        "loading deferred stroke failed in scale_stroke_width(), Err {}",
+                        e
+                    );
+                }
+            },
+        }
+    }
+
+    /// Captures the style of the stroke. None for images
+    pub fn stroke_style(&self) -> Option<StrokeStyle> {
+        match self {
+            Stroke::BrushStroke(brushstroke) => Some(StrokeStyle::Brush {
+                style: brushstroke.style.clone(),
+                opacity: brushstroke.opacity,
+            }),
+            Stroke::ShapeStroke(shapestroke) => Some(StrokeStyle::Shape {
+                style: shapestroke.style.clone(),
+                opacity: shapestroke.opacity,
+            }),
+            Stroke::TextStroke(textstroke) => {
+                let mut text_style = TextStyle::default();
+                StrokeStyle::apply_to_text_style(&textstroke.text_style, &mut text_style);
+
+                Some(StrokeStyle::Text {
+                    text_style,
+                    opacity: textstroke.opacity,
+                })
+            }
+            Stroke::VectorImage(_) | Stroke::BitmapImage(_) => None,
+            Stroke::Deferred(deferred) => match deferred.loaded() {
+                Ok(stroke) => stroke.stroke_style(),
+                Err(e) => {
+                    log::error!(
+                        "loading deferred stroke failed in stroke_style(), Err {}",
+                        e
+                    );
+                    None
+                }
+            },
+        }
+    }
+
+    /// Applies the captured style to the stroke. The style of brush and shape strokes is interchangeable,
+    /// text styles only apply to text strokes. Returns false if the style does not apply to the stroke.
+    /// The geometry and rendering of the stroke then need to be updated
+    pub fn apply_stroke_style(&mut self, stroke_style: &StrokeStyle) -> bool {
+        if let Stroke::Deferred(deferred) = self {
+            match deferred.load() {
+                Ok(mut stroke) => {
+                    let applied = stroke.apply_stroke_style(stroke_style);
+                    *self = stroke;
+                    return applied;
+                }
+                Err(e) => {
+                    log::error!(
+                        "loading deferred stroke failed in apply_stroke_style(), Err {}",
+                        e
+                    );
+                    return false;
+                }
+            }
+        }
+
+        match (self, stroke_style) {
+            (
+                Stroke::BrushStroke(brushstroke),
+                StrokeStyle::Brush { style, opacity } | StrokeStyle::Shape { style, opacity },
+            ) => {
+                brushstroke.style = style.clone();
+                brushstroke.opacity = *opacity;
+                true
+            }
+            (
+                Stroke::ShapeStroke(shapestroke),
+                StrokeStyle::Brush { style, opacity } | StrokeStyle::Shape { style, opacity },
+            ) => {
+                shapestroke.style = StrokeStyle::shape_style(style);
+                shapestroke.opacity = *opacity;
+                true
+            }
+            (
+                Stroke::TextStroke(textstroke),
+                StrokeStyle::Text {
+                    text_style,
+                    opacity,
+                },
+            ) => {
+                StrokeStyle::apply_to_text_style(text_style, &mut textstroke.text_style);
+                textstroke.opacity = *opacity;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The editable nodes of the stroke. Only brush and shape strokes have nodes
+    pub fn nodes(&self) -> Vec<na::Vector2<f64>> {
+        match self {
+            Stroke::BrushStroke(brushstroke) => brushstroke.nodes(),
+            Stroke::ShapeStroke(shapestroke) => shapestroke.nodes(),
+            Stroke::TextStroke(_)
+            | Stroke::VectorImage(_)
+            | Stroke::BitmapImage(_)
+            | Stroke::Deferred(_) => vec![],
+        }
+    }
+
+    /// Moves the node with the given index. See `nodes()`
+    pub fn move_node(&mut self, index: usize, pos: na::Vector2<f64>) {
+        match self {
+            Stroke::BrushStroke(brushstroke) => brushstroke.move_node(index, pos),
+            Stroke::ShapeStroke(shapestroke) => shapestroke.move_node(index, pos),
+            Stroke::TextStroke(_)
+            | Stroke::VectorImage(_)
+            | Stroke::BitmapImage(_)
+            | Stroke::Deferred(_) => {}
+        }
+    }
+
+    /// Inserts a node on the stroke near the position, if possible. Returns the index of the inserted node
+    pub fn insert_node_at(&mut self, pos: na::Vector2<f64>, max_distance: f64) -> Option<usize> {
+        match self {
+            Stroke::BrushStroke(brushstroke) => brushstroke.insert_node_at(pos, max_distance),
+            Stroke::ShapeStroke(shapestroke) => shapestroke.insert_node_at(pos, max_distance),
+            Stroke::TextStroke(_)
+            | Stroke::VectorImage(_)
+            | Stroke::BitmapImage(_)
+            | Stroke::Deferred(_) => None,
+        }
+    }
+
+    /// Removes the node with the given index, if possible. Returns false if it could not be removed
+    pub fn remove_node(&mut self, index: usize) -> bool {
+        match self {
+            Stroke::BrushStroke(brushstroke) => brushstroke.remove_node(index),
+            Stroke::ShapeStroke(shapestroke) => shapestroke.remove_node(index),
+            Stroke::TextStroke(_)
+            | Stroke::VectorImage(_)
+            | Stroke::BitmapImage(_)
+            | Stroke::Deferred(_) => false,
+        }
+    }
+
+    /// An estimation of the bytes used by the stroke geometry.
+    /// For deferred strokes the size of the encoded data is used as approximation.
+    pub fn estimate_memory_usage(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + match self {
+                Stroke::BrushStroke(brushstroke) => brushstroke.estimate_memory_usage(),
+                Stroke::ShapeStroke(_) => 0,
+                Stroke::TextStroke(textstroke) => {
+                    textstroke.text.len()
+                        + textstroke.text_style.font_family.len()
+                        + textstroke.text_style.ranged_text_attributes.len()
+                            * std::mem::size_of::<super::textstroke::RangedTextAttribute>()
+                }
+                Stroke::VectorImage(vectorimage) => vectorimage.svg_data.len(),
+                Stroke::BitmapImage(bitmapimage) => bitmapimage.image.data.len(),
+                Stroke::Deferred(deferred) => deferred.data_len(),
+            }
+    }
+
+    /// The outline of the stroke geometry approximated with lines. Used to find regions enclosed by strokes.
+    /// Text, images and deferred strokes have no outline.
+    pub fn outline_lines(&self) -> Vec<Line> {
+        const N_SPLITS: i32 = 8;
+
+        match self {
+            Stroke::BrushStroke(brushstroke) => brushstroke
+                .path
+                .iter()
+                .flat_map(|segment| segment.approx_with_lines(N_SPLITS))
+                .collect(),
+            Stroke::ShapeStroke(shapestroke) => shapestroke.shape.approx_with_lines(N_SPLITS),
+            Stroke::TextStroke(_)
+            | Stroke::VectorImage(_)
+            | Stroke::BitmapImage(_)
+            | Stroke::Deferred(_) => vec![],
+        }
+    }
+
     pub fn extract_default_layer(&self) -> StrokeLayer {
         match self {
             Stroke::BrushStroke(_) => StrokeLayer::UserLayer(0),
             Stroke::ShapeStroke(_) => StrokeLayer::UserLayer(0),
             Stroke::TextStroke(_) => StrokeLayer::UserLayer(0),
             Stroke::VectorImage(_) | Stroke::BitmapImage(_) => StrokeLayer::Image,
+            Stroke::Deferred(deferred) => deferred
+                .loaded()
+                .map(|stroke| stroke.extract_default_layer())
+                .unwrap_or(StrokeLayer::UserLayer(0)),
         }
     }
     pub fn from_xoppstroke(
@@ -264,7 +750,12 @@ impl Stroke {
         };
         let image = render::Image::try_from_encoded_bytes(&bytes)?;
 
-        Ok(Stroke::BitmapImage(BitmapImage { image, rectangle }))
+        Ok(Stroke::BitmapImage(BitmapImage {
+            image,
+            rectangle,
+            reference: false,
+            opacity: 1.0,
+        }))
     }
 
     pub fn into_xopp(self, current_dpi: f64) -> Option<xoppformat::XoppStrokeType> {
@@ -277,6 +768,10 @@ impl Stroke {
                     Style::Textured(options) => {
                         (options.stroke_width, options.stroke_color?.into())
                     }
+                    Style::Calligraphy(options) => {
+                        (options.stroke_width, options.stroke_color?.into())
+                    }
+                    Style::Stamped(options) => (options.stroke_width, options.stroke_color?.into()),
                 };
 
                 let tool = xoppformat::XoppTool::Pen;
@@ -506,6 +1001,68 @@ impl Stroke {
                     },
                 ))
             }
+            Stroke::Deferred(deferred) => match deferred.into_loaded() {
+                Ok(stroke) => stroke.into_xopp(current_dpi),
+                Err(e) => {
+                    log::error!(
+                        "loading deferred stroke failed in stroke to_xopp() with Err `{}`",
+                        e
+                    );
+                    None
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+impl Stroke {
+    /// A brush stroke line with the default style, shared by the tests
+    pub(crate) fn line_fixture(start: na::Vector2<f64>, end: na::Vector2<f64>) -> Self {
+        Stroke::BrushStroke(BrushStroke::new(
+            Segment::Line {
+                start: Element::new(start, 0.5),
+                end: Element::new(end, 0.5),
+            },
+            Style::default(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rnote_compose::style::stamped::StampedOptions;
+    use rnote_compose::Shape;
+
+    #[test]
+    fn apply_stamped_style_to_shape() {
+        let mut stroke = Stroke::ShapeStroke(ShapeStroke::new(
+            Shape::Rectangle(Rectangle::from_p2d_aabb(AABB::new(
+                na::point![0.0, 0.0],
+                na::point![10.0, 10.0],
+            ))),
+            Style::default(),
+        ));
+        let stamped = StampedOptions {
+            stroke_width: 4.0,
+            stroke_color: Some(Color::RED),
+            ..StampedOptions::default()
+        };
+
+        assert!(stroke.apply_stroke_style(&StrokeStyle::Brush {
+            style: Style::Stamped(stamped),
+            opacity: 0.5,
+        }));
+
+        match stroke {
+            Stroke::ShapeStroke(shapestroke) => {
+                assert!(matches!(shapestroke.style, Style::Smooth(_)));
+                assert_eq!(shapestroke.style.stroke_width(), 4.0);
+                assert_eq!(shapestroke.style.stroke_color(), Some(Color::RED));
+                assert_eq!(shapestroke.opacity, 0.5);
+            }
+            _ => panic!("stroke is not a shape stroke"),
         }
     }
 }
@@ -0,0 +1,67 @@
+use rnote_compose::style::smooth::SmoothOptions;
+use rnote_compose::{Color, Style};
+
+use super::textstroke::TextStyle;
+
+/// The style captured from a stroke, to apply it to other strokes or to the pen config ( "format painter" )
+#[derive(Debug, Clone)]
+pub enum StrokeStyle {
+    /// The style of a brush stroke
+    Brush { style: Style, opacity: f64 },
+    /// The style of a shape stroke
+    Shape { style: Style, opacity: f64 },
+    /// The text attributes of a text stroke. Ranged text attributes and the max width are not part of the style
+    Text { text_style: TextStyle, opacity: f64 },
+}
+
+impl StrokeStyle {
+    /// The opacity of the captured stroke
+    pub fn opacity(&self) -> f64 {
+        match self {
+            StrokeStyle::Brush { opacity, .. }
+            | StrokeStyle::Shape { opacity, .. }
+            | StrokeStyle::Text { opacity, .. } => *opacity,
+        }
+    }
+
+    /// The style as it can be applied to shapes. Textured and stamped styles can't be drawn on shapes,
+    /// so they are mapped to a smooth style with the same width and color
+    pub(crate) fn shape_style(style: &Style) -> Style {
+        match style {
+            Style::Textured(_) | Style::Stamped(_) => Style::Smooth(SmoothOptions {
+                stroke_width: style.stroke_width(),
+                stroke_color: style.stroke_color(),
+                ..SmoothOptions::default()
+            }),
+            style => style.clone(),
+        }
+    }
+
+    /// Applies the text attributes of the style onto the given text style, keeping its max width and ranged attributes
+    pub(crate) fn apply_to_text_style(style: &TextStyle, target: &mut TextStyle) {
+        target.font_family = style.font_family.clone();
+        target.font_size = style.font_size;
+        target.font_weight = style.font_weight;
+        target.font_style = style.font_style;
+        target.color = style.color;
+        target.alignment = style.alignment;
+    }
+}
+
+/// A bulk change of the style of strokes. Only the attributes which are Some are changed
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StrokeRestyle {
+    /// The new main color. See `Stroke::color()`
+    pub color: Option<Color>,
+    /// The new stroke width. Only applies to brush and shape strokes
+    pub stroke_width: Option<f64>,
+    /// The new opacity
+    pub opacity: Option<f64>,
+}
+
+impl StrokeRestyle {
+    /// Wether the restyle changes the geometry or rendering of strokes, which opacity changes don't
+    pub fn needs_rerender(&self) -> bool {
+        self.color.is_some() || self.stroke_width.is_some()
+    }
+}
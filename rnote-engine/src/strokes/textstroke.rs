@@ -1,4 +1,6 @@
+use std::collections::HashSet;
 use std::ops::Range;
+use std::sync::Mutex;
 
 use gtk4::pango;
 use kurbo::Shape;
@@ -9,12 +11,32 @@ use rnote_compose::shapes::ShapeBehaviour;
 use rnote_compose::transform::TransformBehaviour;
 use rnote_compose::{color, Color, Transform};
 use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{render, Camera, DrawBehaviour};
 
+use super::markdown::{MarkdownConverter, MarkdownWriter};
 use super::strokebehaviour::GeneratedStrokeImages;
 use super::StrokeBehaviour;
 
+lazy_static! {
+    /// The font families which were found missing, so that the fallback is only warned about once per family
+    static ref MISSING_FONT_FAMILIES: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+/// Logs a warning that the font family is not available, once per family
+fn warn_missing_font_family(font_family: &str, fallback: &str) {
+    if let Ok(mut missing_font_families) = MISSING_FONT_FAMILIES.lock() {
+        if missing_font_families.insert(font_family.to_string()) {
+            log::warn!(
+                "font family `{}` is not available, falling back to `{}`",
+                font_family,
+                fallback
+            );
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(rename = "font_style")]
 pub enum FontStyle {
@@ -119,6 +141,65 @@ impl TextAlignment {
     }
 }
 
+/// The direction in which the lines of the text are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename = "writing_mode")]
+pub enum WritingMode {
+    /// Horizontal lines, stacked from top to bottom
+    #[serde(rename = "horizontal_tb")]
+    HorizontalTb,
+    /// Vertical lines, stacked from right to left, as used for CJK text.
+    /// CJK characters stay upright, other characters are rotated clockwise
+    #[serde(rename = "vertical_rl")]
+    VerticalRl,
+}
+
+impl Default for WritingMode {
+    fn default() -> Self {
+        Self::HorizontalTb
+    }
+}
+
+/// Wether the character stays upright in vertical text. Approximates the `Vertical_Orientation` unicode property,
+/// brackets and dashes are rotated as we can't use the vertical alternates of the font
+fn is_upright_in_vertical_text(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x11FF // Hangul Jamo
+        | 0x2E80..=0x3007 // CJK radicals, ideographic description, CJK symbols
+        | 0x3012..=0x3013
+        | 0x3020..=0x30FB // Kana
+        | 0x30FD..=0x4DBF // Bopomofo, Hangul compatibility Jamo, CJK strokes, CJK extension A
+        | 0x4E00..=0x9FFF // CJK unified ideographs
+        | 0xA960..=0xA97F
+        | 0xAC00..=0xD7FF // Hangul syllables
+        | 0xF900..=0xFAFF // CJK compatibility ideographs
+        | 0xFE30..=0xFE4F // CJK compatibility forms
+        | 0xFF01..=0xFF07 // Fullwidth forms, without brackets and dashes
+        | 0xFF0A..=0xFF0C
+        | 0xFF0E..=0xFF1B
+        | 0xFF1F..=0xFF3A
+        | 0xFF3C
+        | 0xFF3E
+        | 0xFF40..=0xFF5A
+        | 0xFFE0..=0xFFE6
+        | 0x1F000..=0x1FAFF // Emoji and symbols
+        | 0x20000..=0x3FFFD // CJK extensions B and later
+    )
+}
+
+/// The byte ranges of the graphemes of the text that stay upright in vertical text
+fn upright_grapheme_ranges(text: &str) -> Vec<Range<usize>> {
+    text.grapheme_indices(true)
+        .filter(|(_, grapheme)| {
+            grapheme
+                .chars()
+                .next()
+                .map_or(false, is_upright_in_vertical_text)
+        })
+        .map(|(start, grapheme)| start..start + grapheme.len())
+        .collect()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename = "text_attribute")]
 pub enum TextAttribute {
@@ -143,6 +224,9 @@ pub enum TextAttribute {
     /// Strikethrough.
     #[serde(rename = "strikethrough")]
     Strikethrough(bool),
+    /// A link, drawn underlined and in the link color.
+    #[serde(rename = "link")]
+    Link(LinkTarget),
 }
 
 impl From<piet::TextAttribute> for TextAttribute {
@@ -162,6 +246,11 @@ impl From<piet::TextAttribute> for TextAttribute {
 }
 
 impl TextAttribute {
+    /// Wether the attributes are of the same kind, e.g. both are text colors. Their values may differ.
+    pub fn same_kind(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
     pub fn try_into_piet<T>(self, piet_text: &mut T) -> anyhow::Result<piet::TextAttribute>
     where
         T: piet::Text,
@@ -169,17 +258,36 @@ impl TextAttribute {
         match self {
             TextAttribute::FontFamily(font_family) => piet_text.font_family(font_family.as_str()).map(
                 piet::TextAttribute::FontFamily)
-                    .ok_or_else(|| anyhow::anyhow!("piet font_family() failed in textattribute try_into_piet() with font family name: {}", font_family)),
+                    .ok_or_else(|| {
+                        warn_missing_font_family(&font_family, "the stroke font family");
+                        anyhow::anyhow!("piet font_family() failed in textattribute try_into_piet() with font family name: {}", font_family)
+                    }),
             TextAttribute::FontSize(font_size) => Ok(piet::TextAttribute::FontSize(font_size)),
             TextAttribute::FontWeight(font_weight) => Ok(piet::TextAttribute::Weight(piet::FontWeight::new(font_weight))),
             TextAttribute::TextColor(color) => Ok(piet::TextAttribute::TextColor(piet::Color::from(color))),
             TextAttribute::Style(style) => Ok(piet::TextAttribute::Style(piet::FontStyle::from(style))),
             TextAttribute::Underline(underline) => Ok(piet::TextAttribute::Underline(underline)),
             TextAttribute::Strikethrough(strikethrough) => Ok(piet::TextAttribute::Strikethrough(strikethrough)),
+            TextAttribute::Link(_) => Ok(piet::TextAttribute::Underline(true)),
         }
     }
 }
 
+/// The target of a link in a text stroke
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "link_target")]
+pub enum LinkTarget {
+    /// An url, opened by the frontend
+    #[serde(rename = "url")]
+    Url(String),
+    /// The index of a page of the document
+    #[serde(rename = "page")]
+    Page(usize),
+    /// The name of a view bookmark of the document
+    #[serde(rename = "view_bookmark")]
+    ViewBookmark(String),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename = "ranged_text_attribute")]
 pub struct RangedTextAttribute {
@@ -206,6 +314,9 @@ pub struct TextStyle {
     pub max_width: Option<f64>,
     #[serde(rename = "alignment")]
     pub alignment: TextAlignment,
+    /// In vertical writing mode the max width limits the height of the lines
+    #[serde(rename = "writing_mode")]
+    pub writing_mode: WritingMode,
 
     #[serde(rename = "ranged_text_attributes")]
     pub ranged_text_attributes: Vec<RangedTextAttribute>,
@@ -221,6 +332,7 @@ impl Default for TextStyle {
             color: Self::FONT_COLOR_DEFAULT,
             max_width: None,
             alignment: TextAlignment::Start,
+            writing_mode: WritingMode::default(),
             ranged_text_attributes: vec![],
         }
     }
@@ -233,6 +345,13 @@ impl TextStyle {
     pub const FONT_SIZE_MAX: f64 = 512.0;
     pub const FONT_WEIGHT_DEFAULT: u16 = 500;
     pub const FONT_COLOR_DEFAULT: Color = Color::BLACK;
+    /// The color links are drawn with
+    pub const LINK_COLOR: Color = Color {
+        r: 0.11,
+        g: 0.44,
+        b: 0.85,
+        a: 1.0,
+    };
 
     pub fn load_pango_font_desc(&mut self, pango_font_desc: pango::FontDescription) {
         if let Some(font_family) = pango_font_desc.family() {
@@ -269,6 +388,7 @@ impl TextStyle {
         pango_font_desc
     }
 
+    /// Builds the text layout. Vertical text is laid out horizontally, it needs to be transformed with `layout_affine()`.
     pub fn build_text_layout<T>(
         &self,
         piet_text: &mut T,
@@ -277,9 +397,18 @@ impl TextStyle {
     where
         T: piet::Text,
     {
-        let font_family = piet_text
-            .font_family(&self.font_family)
-            .unwrap_or(piet::FontFamily::SERIF);
+        // Upright graphemes of vertical text are hidden in the layout, and drawn separately. See `TextStroke::draw()`
+        let hidden_ranges = if self.writing_mode == WritingMode::VerticalRl {
+            upright_grapheme_ranges(&text)
+        } else {
+            vec![]
+        };
+
+        // The font family name is kept as it is, so that the font is used again when the file is opened on a machine where it is available
+        let font_family = piet_text.font_family(&self.font_family).unwrap_or_else(|| {
+            warn_missing_font_family(&self.font_family, Self::FONT_FAMILY_DEFAULT);
+            piet::FontFamily::SERIF
+        });
 
         let mut text_layout_builder = piet_text
             .new_text_layout(text)
@@ -298,8 +427,23 @@ impl TextStyle {
         // We need to sort the ranges before adding them to the text layout, else attributes might be skipped. (the cairo backend asserts for it in debug builds)
         // see https://docs.rs/piet/latest/piet/trait.TextLayoutBuilder.html#tymethod.range_attribute
         let mut ranged_text_attributes = self.ranged_text_attributes.clone();
-        ranged_text_attributes
-            .sort_unstable_by(|first, second| first.range.start.cmp(&second.range.start));
+        // Links are additionally colored
+        ranged_text_attributes.extend(
+            self.ranged_text_attributes
+                .iter()
+                .filter(|ranged_attr| matches!(ranged_attr.attribute, TextAttribute::Link(_)))
+                .map(|ranged_attr| RangedTextAttribute {
+                    range: ranged_attr.range.clone(),
+                    attribute: TextAttribute::TextColor(Self::LINK_COLOR),
+                })
+                .collect::<Vec<RangedTextAttribute>>(),
+        );
+        // Appended last, so that they take precedence over the other colors after the stable sort
+        ranged_text_attributes.extend(hidden_ranges.into_iter().map(|range| RangedTextAttribute {
+            range,
+            attribute: TextAttribute::TextColor(Color::TRANSPARENT),
+        }));
+        ranged_text_attributes.sort_by(|first, second| first.range.start.cmp(&second.range.start));
 
         // Apply ranged attributes
         for (range, piet_attr) in ranged_text_attributes
@@ -319,6 +463,42 @@ impl TextStyle {
             .map_err(|e| anyhow::anyhow!("{}", e))
     }
 
+    /// The transform from the text layout to the untransformed text.
+    /// Vertical text is laid out horizontally and then rotated clockwise, so that the lines are stacked from right to left.
+    pub fn layout_affine(&self, text_layout_size: kurbo::Size) -> kurbo::Affine {
+        match self.writing_mode {
+            WritingMode::HorizontalTb => kurbo::Affine::IDENTITY,
+            WritingMode::VerticalRl => {
+                kurbo::Affine::translate((text_layout_size.height, 0.0))
+                    * kurbo::Affine::rotate(std::f64::consts::FRAC_PI_2)
+            }
+        }
+    }
+
+    /// The style for a part of the text, with the ranged attributes clipped and shifted to the range
+    fn style_for_range(&self, range: Range<usize>) -> Self {
+        let ranged_text_attributes = self
+            .ranged_text_attributes
+            .iter()
+            .filter_map(|ranged_attr| {
+                let start = ranged_attr.range.start.max(range.start);
+                let end = ranged_attr.range.end.min(range.end);
+
+                (start < end).then(|| RangedTextAttribute {
+                    range: start - range.start..end - range.start,
+                    attribute: ranged_attr.attribute.clone(),
+                })
+            })
+            .collect();
+
+        Self {
+            max_width: None,
+            writing_mode: WritingMode::HorizontalTb,
+            ranged_text_attributes,
+            ..self.clone()
+        }
+    }
+
     pub fn untransformed_size<T>(&self, piet_text: &mut T, text: String) -> Option<na::Vector2<f64>>
     where
         T: piet::Text,
@@ -326,7 +506,10 @@ impl TextStyle {
         let text_layout = self.build_text_layout(piet_text, text).ok()?;
 
         let size = text_layout.size();
-        Some(na::vector![size.width, size.height])
+        match self.writing_mode {
+            WritingMode::HorizontalTb => Some(na::vector![size.width, size.height]),
+            WritingMode::VerticalRl => Some(na::vector![size.height, size.width]),
+        }
     }
 
     /// the cursors line metric relative to the textstroke bounds.
@@ -382,6 +565,7 @@ impl TextStyle {
         let text_layout = self
             .build_text_layout(&mut piet_cairo::CairoText::new(), text)
             .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let layout_affine = self.layout_affine(text_layout.size());
 
         let range = if selection_cursor.cur_cursor() >= cursor.cur_cursor() {
             cursor.cur_cursor()..selection_cursor.cur_cursor()
@@ -389,7 +573,11 @@ impl TextStyle {
             selection_cursor.cur_cursor()..cursor.cur_cursor()
         };
 
-        Ok(text_layout.rects_for_range(range))
+        Ok(text_layout
+            .rects_for_range(range)
+            .into_iter()
+            .map(|rect| layout_affine.transform_rect_bbox(rect))
+            .collect())
     }
 
     /// The line metric is relative to the transform
@@ -408,12 +596,15 @@ impl TextStyle {
         if let Ok(cursor_line_metric) =
             self.cursor_line_metric(cx.text(), text.clone(), cursor.cur_cursor())
         {
+            let layout_affine =
+                self.layout_affine(self.build_text_layout(cx.text(), text.clone())?.size());
             let x_pos = self
                 .cursor_hittest_position(cx.text(), text, cursor)?
                 .point
                 .x;
 
             let text_cursor = transform.to_kurbo()
+                * layout_affine
                 * kurbo::Line::new(
                     kurbo::Point::new(x_pos, cursor_line_metric.y_offset),
                     kurbo::Point::new(
@@ -453,6 +644,54 @@ impl TextStyle {
             }
         }
     }
+
+    /// Draws squiggly underlines beneath the misspelled ranges of the text
+    pub fn draw_misspelled_underlines(
+        &self,
+        cx: &mut impl piet::RenderContext,
+        text: String,
+        misspelled_ranges: &[Range<usize>],
+        transform: &Transform,
+        camera: &Camera,
+    ) -> anyhow::Result<()> {
+        const UNDERLINE_COLOR: piet::Color = color::GNOME_REDS[3];
+        let total_zoom = camera.total_zoom();
+        let underline_width = 1.2 / total_zoom;
+        let wave_half_len = 2.0 / total_zoom;
+        let wave_height = 1.5 / total_zoom;
+
+        if misspelled_ranges.is_empty() {
+            return Ok(());
+        }
+        let text_layout = self.build_text_layout(cx.text(), text)?;
+        let layout_affine = self.layout_affine(text_layout.size());
+
+        for range in misspelled_ranges {
+            for rect in text_layout.rects_for_range(range.clone()) {
+                let n_half_waves = ((rect.width() / wave_half_len).ceil() as usize).max(1);
+
+                let mut squiggle = kurbo::BezPath::new();
+                squiggle.move_to(kurbo::Point::new(rect.x0, rect.y1));
+                for i in 1..=n_half_waves {
+                    let x = (rect.x0 + i as f64 * wave_half_len).min(rect.x1);
+                    let y = if i % 2 == 1 {
+                        rect.y1 + wave_height
+                    } else {
+                        rect.y1
+                    };
+                    squiggle.line_to(kurbo::Point::new(x, y));
+                }
+
+                cx.stroke(
+                    transform.to_kurbo() * layout_affine * squiggle,
+                    &UNDERLINE_COLOR,
+                    underline_width,
+                );
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -465,6 +704,9 @@ pub struct TextStroke {
     pub transform: Transform,
     #[serde(rename = "text_style")]
     pub text_style: TextStyle,
+    /// The opacity the stroke is drawn with. See `Stroke::opacity()`
+    #[serde(rename = "opacity")]
+    pub opacity: f64,
 }
 
 impl Default for TextStroke {
@@ -473,6 +715,7 @@ impl Default for TextStroke {
             text: String::default(),
             transform: Transform::default(),
             text_style: TextStyle::default(),
+            opacity: 1.0,
         }
     }
 }
@@ -518,11 +761,16 @@ impl ShapeBehaviour for TextStroke {
                 return vec![self.bounds()];
             }
         };
+        let layout_affine = self.text_style.layout_affine(text_layout.size());
 
         text_layout
             .rects_for_range(0..self.text.len())
             .into_iter()
-            .map(|rect| self.transform.transform_aabb(AABB::from_kurbo_rect(rect)))
+            .map(|rect| {
+                self.transform.transform_aabb(AABB::from_kurbo_rect(
+                    layout_affine.transform_rect_bbox(rect),
+                ))
+            })
             .collect()
     }
 }
@@ -582,8 +830,19 @@ impl DrawBehaviour for TextStroke {
             .text_style
             .build_text_layout(cx.text(), self.text.clone())
         {
+            let layout_affine = self.text_style.layout_affine(text_layout.size());
+
             cx.transform(self.transform.affine.to_kurbo());
-            cx.draw_text(&text_layout, kurbo::Point::new(0.0, 0.0))
+            cx.with_save(|cx| {
+                cx.transform(layout_affine);
+                cx.draw_text(&text_layout, kurbo::Point::new(0.0, 0.0));
+                Ok(())
+            })
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+            if self.text_style.writing_mode == WritingMode::VerticalRl {
+                self.draw_upright_graphemes(cx, &text_layout, layout_affine)?;
+            }
         }
 
         cx.restore().map_err(|e| anyhow::anyhow!("{}", e))?;
@@ -597,13 +856,180 @@ impl TextStroke {
             text,
             transform: Transform::new_w_isometry(na::Isometry2::new(upper_left_pos, 0.0)),
             text_style,
+            opacity: 1.0,
         }
     }
 
+    /// Creates a text stroke from markdown, converting its formatting into ranged text attributes. See `MarkdownConverter`
+    pub fn from_markdown(
+        markdown: &str,
+        upper_left_pos: na::Vector2<f64>,
+        mut text_style: TextStyle,
+    ) -> Self {
+        let (text, ranged_text_attributes) = MarkdownConverter::new(&text_style).convert(markdown);
+        text_style.ranged_text_attributes = ranged_text_attributes;
+
+        Self::new(text, upper_left_pos, text_style)
+    }
+
+    /// The text as markdown, with the formatting of the ranged text attributes. See `MarkdownWriter`
+    pub fn to_markdown(&self) -> String {
+        MarkdownWriter::new(&self.text_style).write(&self.text)
+    }
+
+    /// Draws the graphemes that stay upright in vertical text, centered in their place in the rotated text layout.
+    /// They are hidden in the text layout itself
+    fn draw_upright_graphemes<R: RenderContext>(
+        &self,
+        cx: &mut R,
+        text_layout: &R::TextLayout,
+        layout_affine: kurbo::Affine,
+    ) -> anyhow::Result<()> {
+        for range in upright_grapheme_ranges(&self.text) {
+            let grapheme_rect = match text_layout.rects_for_range(range.clone()).first() {
+                Some(grapheme_rect) => *grapheme_rect,
+                None => continue,
+            };
+            let grapheme_layout = self
+                .text_style
+                .style_for_range(range.clone())
+                .build_text_layout(cx.text(), self.text[range].to_string())?;
+
+            let center = layout_affine * grapheme_rect.center();
+            cx.draw_text(
+                &grapheme_layout,
+                center - grapheme_layout.size().to_vec2() * 0.5,
+            );
+        }
+
+        Ok(())
+    }
+
     pub fn get_text_slice_for_range(&self, range: Range<usize>) -> &str {
         &self.text[range]
     }
 
+    /// Finds the occurrences of the query in the text, ignoring case. Returns the byte ranges of the non-overlapping matches
+    pub fn find_text(&self, query: &str) -> Vec<Range<usize>> {
+        let query = query.to_lowercase();
+        if query.is_empty() {
+            return vec![];
+        }
+
+        // Returns the length of the match at the start of the text
+        let match_len = |text: &str| -> Option<usize> {
+            let mut query_chars = query.chars().peekable();
+
+            for (i, c) in text.char_indices() {
+                for lowercase_c in c.to_lowercase() {
+                    if query_chars.next() != Some(lowercase_c) {
+                        return None;
+                    }
+                }
+                if query_chars.peek().is_none() {
+                    return Some(i + c.len_utf8());
+                }
+            }
+            None
+        };
+
+        let mut matches = vec![];
+        let mut search_start = 0;
+        for (start, _) in self.text.char_indices() {
+            if start < search_start {
+                continue;
+            }
+            if let Some(len) = match_len(&self.text[start..]) {
+                matches.push(start..start + len);
+                search_start = start + len;
+            }
+        }
+
+        matches
+    }
+
+    /// The bounds of the text in the range, in document coords. None if the range is empty or the text layout could not be built
+    pub fn bounds_for_range(&self, range: Range<usize>) -> Option<AABB> {
+        let text_layout = match self
+            .text_style
+            .build_text_layout(&mut piet_cairo::CairoText::new(), self.text.clone())
+        {
+            Ok(text_layout) => text_layout,
+            Err(e) => {
+                log::error!(
+                    "build_text_layout() failed in bounds_for_range(), Err {}",
+                    e
+                );
+                return None;
+            }
+        };
+        let layout_affine = self.text_style.layout_affine(text_layout.size());
+
+        text_layout
+            .rects_for_range(range)
+            .into_iter()
+            .map(|rect| {
+                self.transform.transform_aabb(AABB::from_kurbo_rect(
+                    layout_affine.transform_rect_bbox(rect),
+                ))
+            })
+            .reduce(|acc, bounds| acc.merged(&bounds))
+    }
+
+    /// The links with the areas of their text, in document coords. Links that are wrapped over multiple lines have an area for each line
+    pub fn link_areas(&self) -> Vec<(LinkTarget, AABB)> {
+        let links = self
+            .text_style
+            .ranged_text_attributes
+            .iter()
+            .filter_map(|ranged_attr| match &ranged_attr.attribute {
+                TextAttribute::Link(target) => Some((target, ranged_attr.range.clone())),
+                _ => None,
+            })
+            .collect::<Vec<(&LinkTarget, Range<usize>)>>();
+        if links.is_empty() {
+            return vec![];
+        }
+
+        let text_layout = match self
+            .text_style
+            .build_text_layout(&mut piet_cairo::CairoText::new(), self.text.clone())
+        {
+            Ok(text_layout) => text_layout,
+            Err(e) => {
+                log::error!("build_text_layout() failed in link_areas(), Err {}", e);
+                return vec![];
+            }
+        };
+        let layout_affine = self.text_style.layout_affine(text_layout.size());
+
+        links
+            .into_iter()
+            .flat_map(|(target, range)| {
+                text_layout
+                    .rects_for_range(range)
+                    .into_iter()
+                    .map(|rect| {
+                        (
+                            target.clone(),
+                            self.transform.transform_aabb(AABB::from_kurbo_rect(
+                                layout_affine.transform_rect_bbox(rect),
+                            )),
+                        )
+                    })
+                    .collect::<Vec<(LinkTarget, AABB)>>()
+            })
+            .collect()
+    }
+
+    /// The target of the link at the given coord, in document coords
+    pub fn link_at_coord(&self, coord: na::Vector2<f64>) -> Option<LinkTarget> {
+        self.link_areas()
+            .into_iter()
+            .find(|(_, area)| area.contains_local_point(&na::Point2::from(coord)))
+            .map(|(target, _)| target)
+    }
+
     // Gets a cursor matching best for the given coord. The coord is in global coordinate space
     pub fn get_cursor_for_global_coord(
         &self,
@@ -613,10 +1039,12 @@ impl TextStroke {
             .text_style
             .build_text_layout(&mut piet_cairo::CairoText::new(), self.text.clone())
             .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let layout_affine = self.text_style.layout_affine(text_layout.size());
         let hit_test_point = text_layout.hit_test_point(
-            (self.transform.affine.inverse() * na::Point2::from(coord))
-                .coords
-                .to_kurbo_point(),
+            layout_affine.inverse()
+                * (self.transform.affine.inverse() * na::Point2::from(coord))
+                    .coords
+                    .to_kurbo_point(),
         );
 
         Ok(unicode_segmentation::GraphemeCursor::new(
@@ -706,6 +1134,8 @@ impl TextStroke {
             selection_cursor_pos..cursor_pos
         };
 
+        // The attributes of the replaced text are removed, the ones after it translated
+        self.remove_attrs_for_range(cursor_range.clone());
         self.text.replace_range(cursor_range.clone(), replace_text);
 
         *cursor = unicode_segmentation::GraphemeCursor::new(
@@ -720,7 +1150,7 @@ impl TextStroke {
         );
 
         self.translate_attrs_after_cursor(
-            cursor.cur_cursor(),
+            cursor_range.start,
             -(cursor_range.end as i32 - cursor_range.start as i32) + replace_text.len() as i32,
         );
     }
@@ -746,9 +1176,27 @@ impl TextStroke {
         }
     }
 
+    /// Adds the attribute for the given range, replacing the attributes of the same kind in it.
+    /// This keeps e.g. color runs from piling up on top of each other.
+    pub fn set_attr_for_range(&mut self, attribute: TextAttribute, range: Range<usize>) {
+        self.remove_attrs_for_range_matching(range.clone(), |attr| attr.same_kind(&attribute));
+
+        self.text_style
+            .ranged_text_attributes
+            .push(RangedTextAttribute { range, attribute });
+    }
+
     /// Removes all attr in the given range
     pub fn remove_attrs_for_range(&mut self, range: Range<usize>) {
-        // partition into attrs that intersect the range, and those who don't and will be retained
+        self.remove_attrs_for_range_matching(range, |_| true);
+    }
+
+    /// Removes the attrs in the given range for which the predicate returns true
+    fn remove_attrs_for_range_matching<P>(&mut self, range: Range<usize>, predicate: P)
+    where
+        P: Fn(&TextAttribute) -> bool,
+    {
+        // partition into attrs that intersect the range and match, and those who don't and will be retained
         let (intersecting_attrs, mut retained_attrs): (
             Vec<RangedTextAttribute>,
             Vec<RangedTextAttribute>,
@@ -757,7 +1205,11 @@ impl TextStroke {
             .ranged_text_attributes
             .clone()
             .into_iter()
-            .partition(|attr| attr.range.end > range.start && attr.range.start < range.end);
+            .partition(|attr| {
+                attr.range.end > range.start
+                    && attr.range.start < range.end
+                    && predicate(&attr.attribute)
+            });
 
         // Truncate and filter the ranges of intersecting attrs
         let truncated_attrs = intersecting_attrs
@@ -816,7 +1268,81 @@ impl TextStroke {
         cursor.next_boundary(&self.text, 0).unwrap();
     }
 
+    /// Moves the cursor one grapheme to the left on screen. Moves forward in right-to-left text runs,
+    /// and to the next line in vertical text
+    pub fn move_cursor_left(&self, cursor: &mut unicode_segmentation::GraphemeCursor) {
+        if self.text_style.writing_mode == WritingMode::VerticalRl {
+            self.move_cursor_next_line(cursor);
+        } else if self.is_rtl_at(cursor.cur_cursor()) {
+            self.move_cursor_forward(cursor);
+        } else {
+            self.move_cursor_back(cursor);
+        }
+    }
+
+    /// Moves the cursor one grapheme to the right on screen. Moves back in right-to-left text runs,
+    /// and to the previous line in vertical text
+    pub fn move_cursor_right(&self, cursor: &mut unicode_segmentation::GraphemeCursor) {
+        if self.text_style.writing_mode == WritingMode::VerticalRl {
+            self.move_cursor_prev_line(cursor);
+        } else if self.is_rtl_at(cursor.cur_cursor()) {
+            self.move_cursor_back(cursor);
+        } else {
+            self.move_cursor_forward(cursor);
+        }
+    }
+
+    /// Wether the paragraph ( the text between line breaks ) containing the index is right-to-left.
+    /// Determined by its first strong directional character, like pango does when laying out the text.
+    /// Paragraphs without any are left-to-right
+    pub fn paragraph_is_rtl(&self, index: usize) -> bool {
+        let start = self.text[..index].rfind('\n').map_or(0, |i| i + 1);
+        let end = self.text[index..]
+            .find('\n')
+            .map_or(self.text.len(), |i| index + i);
+
+        pango::find_base_dir(&self.text[start..end]) == pango::Direction::Rtl
+    }
+
+    /// Wether the text run at the index is right-to-left. The characters before and after the index are checked for a strong direction,
+    /// else the direction of the paragraph is used ( e.g. for spaces and digits )
+    pub fn is_rtl_at(&self, index: usize) -> bool {
+        let adjacent_chars = [
+            self.text[..index].chars().next_back(),
+            self.text[index..].chars().next(),
+        ];
+
+        for c in adjacent_chars.into_iter().flatten() {
+            if c == '\n' {
+                continue;
+            }
+            match pango::find_base_dir(c.encode_utf8(&mut [0; 4])) {
+                pango::Direction::Rtl => return true,
+                pango::Direction::Ltr => return false,
+                _ => {}
+            }
+        }
+
+        self.paragraph_is_rtl(index)
+    }
+
+    /// Moves the cursor one line down on screen. Moves forward in vertical text
     pub fn move_cursor_line_down(&self, cursor: &mut unicode_segmentation::GraphemeCursor) {
+        match self.text_style.writing_mode {
+            WritingMode::HorizontalTb => self.move_cursor_next_line(cursor),
+            WritingMode::VerticalRl => self.move_cursor_forward(cursor),
+        }
+    }
+
+    /// Moves the cursor one line up on screen. Moves back in vertical text
+    pub fn move_cursor_line_up(&self, cursor: &mut unicode_segmentation::GraphemeCursor) {
+        match self.text_style.writing_mode {
+            WritingMode::HorizontalTb => self.move_cursor_prev_line(cursor),
+            WritingMode::VerticalRl => self.move_cursor_back(cursor),
+        }
+    }
+
+    fn move_cursor_next_line(&self, cursor: &mut unicode_segmentation::GraphemeCursor) {
         if let (Ok(lines), Ok(hittest_position)) = (
             self.text_style
                 .lines(&mut piet_cairo::CairoText::new(), self.text.clone()),
@@ -845,7 +1371,7 @@ impl TextStroke {
         }
     }
 
-    pub fn move_cursor_line_up(&self, cursor: &mut unicode_segmentation::GraphemeCursor) {
+    fn move_cursor_prev_line(&self, cursor: &mut unicode_segmentation::GraphemeCursor) {
         if let (Ok(lines), Ok(hittest_position)) = (
             self.text_style
                 .lines(&mut piet_cairo::CairoText::new(), self.text.clone()),
@@ -874,3 +1400,115 @@ impl TextStroke {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_text() {
+        let textstroke = TextStroke::new(
+            String::from("Rnote notes, NOTES and nöte"),
+            na::vector![0.0, 0.0],
+            TextStyle::default(),
+        );
+
+        assert_eq!(textstroke.find_text("note"), vec![1..5, 6..10, 13..17]);
+        assert_eq!(textstroke.find_text("NÖTE"), vec![23..28]);
+        assert!(textstroke.find_text("").is_empty());
+    }
+
+    #[test]
+    fn from_markdown() {
+        let textstroke = TextStroke::from_markdown(
+            "# Title\n\n- some **bold** and `code`\n",
+            na::vector![0.0, 0.0],
+            TextStyle::default(),
+        );
+
+        assert_eq!(textstroke.text, "Title\n\n• some bold and code");
+        let attr_texts = textstroke
+            .text_style
+            .ranged_text_attributes
+            .iter()
+            .map(|attr| &textstroke.text[attr.range.clone()])
+            .collect::<Vec<&str>>();
+        assert_eq!(attr_texts, vec!["Title", "Title", "bold", "code"]);
+    }
+
+    #[test]
+    fn markdown_roundtrip() {
+        let markdown = "# Title\n\n- some **bold**, _italic_ and `code`\n  - nested ~~item~~\n```\nlet a = 1;\n```";
+        let textstroke =
+            TextStroke::from_markdown(markdown, na::vector![0.0, 0.0], TextStyle::default());
+
+        assert_eq!(textstroke.to_markdown(), markdown);
+    }
+
+    #[test]
+    fn rtl_cursor_movement() {
+        // "hello שלום"
+        let textstroke = TextStroke::new(
+            String::from("hello \u{5e9}\u{5dc}\u{5d5}\u{5dd}\nab"),
+            na::vector![0.0, 0.0],
+            TextStyle::default(),
+        );
+        let len = textstroke.text.len();
+        assert!(!textstroke.paragraph_is_rtl(0));
+        assert!(!textstroke.is_rtl_at(2));
+        assert!(textstroke.is_rtl_at(8));
+
+        let mut cursor = unicode_segmentation::GraphemeCursor::new(8, len, true);
+        textstroke.move_cursor_left(&mut cursor);
+        assert_eq!(cursor.cur_cursor(), 10);
+        textstroke.move_cursor_right(&mut cursor);
+        assert_eq!(cursor.cur_cursor(), 8);
+
+        let mut cursor = unicode_segmentation::GraphemeCursor::new(2, len, true);
+        textstroke.move_cursor_left(&mut cursor);
+        assert_eq!(cursor.cur_cursor(), 1);
+    }
+
+    #[test]
+    fn vertical_writing_mode() {
+        let textstroke = TextStroke::new(
+            String::from("日本語\nかな"),
+            na::vector![0.0, 0.0],
+            TextStyle {
+                writing_mode: WritingMode::VerticalRl,
+                ..TextStyle::default()
+            },
+        );
+        let len = textstroke.text.len();
+        assert_eq!(upright_grapheme_ranges("a日「"), vec![1..4]);
+
+        // Lines become columns
+        let bounds = textstroke.bounds();
+        assert!(bounds.extents()[1] > bounds.extents()[0]);
+
+        let mut cursor = unicode_segmentation::GraphemeCursor::new(0, len, true);
+        textstroke.move_cursor_line_down(&mut cursor);
+        assert_eq!(cursor.cur_cursor(), 3);
+        textstroke.move_cursor_left(&mut cursor);
+        assert_eq!(cursor.cur_cursor(), 13);
+        textstroke.move_cursor_right(&mut cursor);
+        assert_eq!(cursor.cur_cursor(), 3);
+    }
+
+    #[test]
+    fn link_at_coord() {
+        let mut textstroke = TextStroke::new(
+            String::from("see the docs"),
+            na::vector![10.0, 10.0],
+            TextStyle::default(),
+        );
+        let target = LinkTarget::Url(String::from("https://rnote.flxzt.net"));
+        textstroke.set_attr_for_range(TextAttribute::Link(target.clone()), 8..12);
+
+        let link_areas = textstroke.link_areas();
+        assert_eq!(link_areas.len(), 1);
+        let link_center = link_areas[0].1.center().coords;
+        assert_eq!(textstroke.link_at_coord(link_center), Some(target));
+        assert_eq!(textstroke.link_at_coord(na::vector![12.0, 12.0]), None);
+    }
+}
@@ -3,6 +3,7 @@ use std::ops::Range;
 use super::strokebehaviour::GeneratedStrokeImages;
 use super::StrokeBehaviour;
 use crate::document::Format;
+use crate::fileerror::FileError;
 use crate::import::{PdfImportPageSpacing, PdfImportPrefs};
 use crate::{render, DrawBehaviour};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
@@ -17,6 +18,34 @@ use gtk4::glib;
 use p2d::bounding_volume::AABB;
 use serde::{Deserialize, Serialize};
 
+const SVG_NS: &str = "http://www.w3.org/2000/svg";
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Errors when importing a vector image
+#[derive(Debug)]
+pub enum SvgImportError {
+    /// The svg data exceeds the maximum allowed size
+    TooLarge { size: usize, max_size: usize },
+    /// The svg data could not be parsed
+    Parse(usvg::Error),
+}
+
+impl std::fmt::Display for SvgImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooLarge { size, max_size } => write!(
+                f,
+                "svg data with size {} bytes exceeds the maximum size of {} bytes",
+                size, max_size
+            ),
+            Self::Parse(e) => write!(f, "parsing svg data failed with Err {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SvgImportError {}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default, rename = "vectorimage")]
 pub struct VectorImage {
@@ -26,6 +55,12 @@ pub struct VectorImage {
     pub intrinsic_size: na::Vector2<f64>,
     #[serde(rename = "rectangle")]
     pub rectangle: Rectangle,
+    /// Wether the image is a reference underlay. See `Stroke::is_reference()`
+    #[serde(rename = "reference")]
+    pub reference: bool,
+    /// The opacity the stroke is drawn with. See `Stroke::opacity()`
+    #[serde(rename = "opacity")]
+    pub opacity: f64,
 }
 
 impl Default for VectorImage {
@@ -34,6 +69,8 @@ impl Default for VectorImage {
             svg_data: String::default(),
             intrinsic_size: na::Vector2::zeros(),
             rectangle: Rectangle::default(),
+            reference: false,
+            opacity: 1.0,
         }
     }
 }
@@ -77,6 +114,8 @@ impl StrokeBehaviour for VectorImage {
         image_scale: f64,
     ) -> Result<GeneratedStrokeImages, anyhow::Error> {
         let bounds = self.bounds();
+        // Bound the rasterization size, massive vector images would otherwise allocate huge surfaces
+        let image_scale = image_scale.min(Self::MAX_RASTERIZATION_SIZE / bounds.extents().max());
 
         // Always generate full stroke images for vectorimages, as they are too expensive to be repeatedly rendered
         Ok(GeneratedStrokeImages::Full(vec![
@@ -135,6 +174,24 @@ impl TransformBehaviour for VectorImage {
 impl VectorImage {
     /// The default offset in surface coords when importing a vector image
     pub const IMPORT_OFFSET_DEFAULT: na::Vector2<f64> = na::vector![32.0, 32.0];
+    /// The maximum size of the svg data in bytes that is accepted on import
+    pub const IMPORT_MAX_DATA_SIZE: usize = 64 * 1024 * 1024;
+    /// The maximum width / height of the rasterized image in pixels.
+    /// The image scale is lowered when the vector image would exceed it.
+    pub const MAX_RASTERIZATION_SIZE: f64 = 8192.0;
+
+    /// Strips scripts, foreign objects, event handler attributes and references to external resources from svg data.
+    /// The data is parsed and written again, keeping only elements and attributes of the svg, xlink and xml namespaces.
+    /// Internal references ( starting with `#` ) and embedded data are kept.
+    pub fn sanitize_svg_data(svg_data: &str) -> Result<String, SvgImportError> {
+        let doc = roxmltree::Document::parse(svg_data)
+            .map_err(|e| SvgImportError::Parse(usvg::Error::ParsingFailed(e)))?;
+
+        let mut sanitized = String::with_capacity(svg_data.len());
+        write_sanitized_svg_element(doc.root_element(), true, &mut sanitized);
+
+        Ok(sanitized)
+    }
 
     pub fn import_from_svg_data(
         svg_data: &str,
@@ -150,7 +207,17 @@ impl VectorImage {
             },
         };
 
-        let rtree = usvg::Tree::from_str(svg_data, &render::USVG_OPTIONS.to_ref())?;
+        if svg_data.len() > Self::IMPORT_MAX_DATA_SIZE {
+            return Err(SvgImportError::TooLarge {
+                size: svg_data.len(),
+                max_size: Self::IMPORT_MAX_DATA_SIZE,
+            }
+            .into());
+        }
+        let svg_data = Self::sanitize_svg_data(svg_data)?;
+
+        let rtree = usvg::Tree::from_str(&svg_data, &render::USVG_OPTIONS.to_ref())
+            .map_err(SvgImportError::Parse)?;
         let svg_data = rtree.to_string(&xml_options);
 
         let svg_node = rtree.svg_node();
@@ -175,6 +242,8 @@ impl VectorImage {
             svg_data,
             intrinsic_size,
             rectangle,
+            reference: false,
+            opacity: 1.0,
         })
     }
 
@@ -185,7 +254,8 @@ impl VectorImage {
         page_range: Option<Range<u32>>,
         format: &Format,
     ) -> Result<Vec<Self>, anyhow::Error> {
-        let doc = poppler::Document::from_bytes(&glib::Bytes::from(to_be_read), None)?;
+        let doc = poppler::Document::from_bytes(&glib::Bytes::from(to_be_read), None)
+            .map_err(FileError::from_pdf_error)?;
         let page_range = page_range.unwrap_or(0..doc.n_pages() as u32);
 
         let page_width = format.width * (pdf_import_prefs.page_width_perc / 100.0);
@@ -318,3 +388,191 @@ impl VectorImage {
         Ok(export_svg_data)
     }
 }
+
+/// Writes the element with its attributes and children, if it is allowed. Elements which could execute code are dropped.
+fn write_sanitized_svg_element(node: roxmltree::Node, is_root: bool, out: &mut String) {
+    let tag_name = node.tag_name();
+    if tag_name.namespace() != Some(SVG_NS)
+        || matches!(
+            tag_name.name(),
+            "script" | "foreignObject" | "set" | "animate" | "animateMotion" | "animateTransform"
+        )
+    {
+        return;
+    }
+
+    out.push('<');
+    out.push_str(tag_name.name());
+    if is_root {
+        out.push_str(&format!(
+            " xmlns=\"{}\" xmlns:xlink=\"{}\"",
+            SVG_NS, XLINK_NS
+        ));
+    }
+
+    for attr in node.attributes() {
+        let prefix = match attr.namespace() {
+            None => "",
+            Some(XLINK_NS) => "xlink:",
+            Some(XML_NS) => "xml:",
+            Some(_) => continue,
+        };
+        // Event handlers
+        if attr.name().to_ascii_lowercase().starts_with("on") {
+            continue;
+        }
+        if attr.name() == "href" && !is_internal_svg_reference(attr.value()) {
+            continue;
+        }
+
+        out.push(' ');
+        out.push_str(prefix);
+        out.push_str(attr.name());
+        out.push_str("=\"");
+        push_xml_escaped(&sanitize_css(attr.value()), out);
+        out.push('"');
+    }
+
+    if !node.has_children() {
+        out.push_str("/>");
+        return;
+    }
+    out.push('>');
+
+    for child in node.children() {
+        if child.is_element() {
+            write_sanitized_svg_element(child, false, out);
+        } else if child.is_text() {
+            let text = child.text().unwrap_or_default();
+
+            if tag_name.name() == "style" {
+                push_xml_escaped(&sanitize_css(text), out);
+            } else {
+                push_xml_escaped(text, out);
+            }
+        }
+    }
+
+    out.push_str("</");
+    out.push_str(tag_name.name());
+    out.push('>');
+}
+
+/// Wether the reference points to an element in the same document or to embedded data
+fn is_internal_svg_reference(reference: &str) -> bool {
+    let reference = reference.trim_start();
+
+    reference.starts_with('#') || reference.to_ascii_lowercase().starts_with("data:")
+}
+
+/// Drops `@import` rules and replaces `url(..)` functions referencing external resources with `none`.
+fn sanitize_css(css: &str) -> String {
+    // ascii lowercasing preserves the byte offsets
+    let lowercase = css.to_ascii_lowercase();
+    let mut sanitized = String::with_capacity(css.len());
+    let mut pos = 0;
+
+    loop {
+        let (start, is_import) = match (
+            lowercase[pos..].find("@import"),
+            lowercase[pos..].find("url("),
+        ) {
+            (Some(import), Some(url)) if import < url => (pos + import, true),
+            (Some(import), None) => (pos + import, true),
+            (_, Some(url)) => (pos + url, false),
+            (None, None) => {
+                sanitized.push_str(&css[pos..]);
+                break;
+            }
+        };
+        sanitized.push_str(&css[pos..start]);
+
+        if is_import {
+            pos = css[start..]
+                .find(';')
+                .map(|end| start + end + 1)
+                .unwrap_or(css.len());
+        } else {
+            let (reference, len) = parse_css_url(&css[start..]);
+            if is_internal_svg_reference(reference) {
+                sanitized.push_str(&css[start..start + len]);
+            } else {
+                sanitized.push_str("none");
+            }
+            pos = start + len;
+        }
+    }
+
+    sanitized
+}
+
+/// Parses the css `url(..)` function at the start of the string. Returns the referenced url and the length of the function.
+fn parse_css_url(css: &str) -> (&str, usize) {
+    let arg = css["url(".len()..].trim_start();
+    let arg_offset = css.len() - arg.len();
+
+    match arg.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let url_end = arg[1..].find(quote).map(|end| end + 1).unwrap_or(arg.len());
+            let len = arg[url_end..]
+                .find(')')
+                .map(|end| arg_offset + url_end + end + 1)
+                .unwrap_or(css.len());
+
+            (&arg[1..url_end], len)
+        }
+        _ => {
+            let url_end = arg.find(')').unwrap_or(arg.len());
+
+            (
+                arg[..url_end].trim_end(),
+                (arg_offset + url_end + 1).min(css.len()),
+            )
+        }
+    }
+}
+
+/// Pushes the text escaped, so that it can be used as xml text or as a double quoted attribute value
+fn push_xml_escaped(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SvgImportError;
+    use crate::strokes::VectorImage;
+
+    #[test]
+    fn sanitize_svg_data() {
+        let svg_data = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" onload="alert(1)">
+<script type="text/javascript"><![CDATA[alert(2)]]></script><foreignObject><div xmlns="http://www.w3.org/1999/xhtml" onclick="alert(3)"/></foreignObject>
+<style>@import url(a.css); rect { fill: URL( 'https://example.org/b' ) }</style>
+<rect ONCLICK='alert(4)' fill="url(#a)" style="stroke: url(file:///etc/passwd)"/><text>a &amp; b</text>
+<use xlink:href="#a"/><image href="file:///etc/passwd"/><image xlink:href=" https://example.org/c.png"/><image xlink:href="data:image/png;base64,AA"/></svg>"##;
+
+        assert_eq!(
+            VectorImage::sanitize_svg_data(svg_data).unwrap(),
+            r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+
+<style> rect { fill: none }</style>
+<rect fill="url(#a)" style="stroke: none"/><text>a &amp; b</text>
+<use xlink:href="#a"/><image/><image/><image xlink:href="data:image/png;base64,AA"/></svg>"##
+        );
+
+        // Unquoted attributes are not valid xml
+        assert!(matches!(
+            VectorImage::sanitize_svg_data(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" onload=alert(1)/>"#
+            ),
+            Err(SvgImportError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,217 @@
+use crate::engine::EngineTask;
+use crate::store::StoreOp;
+
+use anyhow::Context;
+use futures::channel::mpsc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::TcpStream;
+use std::sync::mpsc as std_mpsc;
+use std::time::Duration;
+use tungstenite::client::IntoClientRequest;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+/// The presence of a collaborating replica, to show where its user is working
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename = "presence")]
+pub struct Presence {
+    #[serde(rename = "replica")]
+    pub replica: u32,
+    /// The name of the user
+    #[serde(rename = "name")]
+    pub name: String,
+    /// The position of the cursor, in document coords. None when it is not on the canvas
+    #[serde(rename = "cursor")]
+    pub cursor: Option<na::Vector2<f64>>,
+}
+
+/// The messages exchanged with the sync server, which relays them to the other replicas
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "sync_message")]
+pub enum SyncMessage {
+    /// Store operations. See `StrokeStore::apply_ops()`
+    #[serde(rename = "ops")]
+    Ops(Vec<StoreOp>),
+    #[serde(rename = "presence")]
+    Presence(Presence),
+    /// The replica left the session
+    #[serde(rename = "leave")]
+    Leave(u32),
+}
+
+/// Syncs the store with other replicas over a WebSocket connection.
+/// The connection runs on its own thread, received messages are sent to the engine as `EngineTask::SyncMessageReceived`.
+/// The thread also sends `EngineTask::SyncTick` regularly, on which the local ops and the presence are sent with `flush()`.
+/// When another replica joins, the current state is sent to it, see `take_catch_up_request()`.
+#[derive(Debug)]
+pub struct SyncClient {
+    outgoing_tx: std_mpsc::Sender<SyncMessage>,
+    presence: Presence,
+    presence_changed: bool,
+    remote_presences: HashMap<u32, Presence>,
+    catch_up_requested: bool,
+}
+
+impl SyncClient {
+    /// The interval in which the connection is polled for received messages and the engine is ticked
+    pub const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    /// Connects to the sync server at the url. Only unencrypted (`ws://`) connections are supported.
+    /// Connecting happens on the connection thread, messages are queued until it is established.
+    /// When it fails, `EngineTask::SyncDisconnected` is sent
+    pub fn connect(
+        url: &str,
+        presence: Presence,
+        tasks_tx: mpsc::UnboundedSender<EngineTask>,
+    ) -> anyhow::Result<Self> {
+        let request = url
+            .into_client_request()
+            .with_context(|| format!("`{}` is not a valid sync server url", url))?;
+        let (outgoing_tx, outgoing_rx) = std_mpsc::channel::<SyncMessage>();
+
+        std::thread::spawn(move || {
+            if let Err(e) = Self::open_socket(request)
+                .and_then(|socket| Self::run_connection(socket, outgoing_rx, &tasks_tx))
+            {
+                log::error!("sync connection failed with Err {}", e);
+            }
+            let _ = tasks_tx.unbounded_send(EngineTask::SyncDisconnected);
+        });
+
+        Ok(Self {
+            outgoing_tx,
+            presence,
+            presence_changed: true,
+            remote_presences: HashMap::new(),
+            catch_up_requested: false,
+        })
+    }
+
+    fn open_socket(
+        request: tungstenite::handshake::client::Request,
+    ) -> anyhow::Result<WebSocket<MaybeTlsStream<TcpStream>>> {
+        let uri = request.uri().to_string();
+        let (mut socket, _response) = tungstenite::connect(request)
+            .with_context(|| format!("connecting to sync server at `{}` failed", uri))?;
+        // Reading must not block forever, so that outgoing messages can be sent in between
+        if let MaybeTlsStream::Plain(stream) = socket.get_mut() {
+            stream
+                .set_read_timeout(Some(Self::POLL_INTERVAL))
+                .context("setting the read timeout of the sync connection failed")?;
+        }
+
+        Ok(socket)
+    }
+
+    /// Runs until the connection is closed, or the client is dropped.
+    fn run_connection(
+        mut socket: WebSocket<MaybeTlsStream<TcpStream>>,
+        outgoing_rx: std_mpsc::Receiver<SyncMessage>,
+        tasks_tx: &mpsc::UnboundedSender<EngineTask>,
+    ) -> anyhow::Result<()> {
+        loop {
+            loop {
+                match outgoing_rx.try_recv() {
+                    Ok(message) => {
+                        socket.write_message(Message::Text(serde_json::to_string(&message)?))?
+                    }
+                    Err(std_mpsc::TryRecvError::Empty) => break,
+                    Err(std_mpsc::TryRecvError::Disconnected) => {
+                        socket.close(None)?;
+                        return Ok(());
+                    }
+                }
+            }
+
+            match socket.read_message() {
+                Ok(Message::Text(text)) => match serde_json::from_str::<SyncMessage>(&text) {
+                    Ok(message) => tasks_tx
+                        .unbounded_send(EngineTask::SyncMessageReceived(message))
+                        .context("sending received sync message to the engine failed")?,
+                    Err(e) => log::error!("deserializing received sync message failed, Err {}", e),
+                },
+                Ok(Message::Close(_)) => return Ok(()),
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e))
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            tasks_tx
+                .unbounded_send(EngineTask::SyncTick)
+                .context("sending sync tick to the engine failed")?;
+        }
+    }
+
+    /// The presences of the other replicas
+    pub fn remote_presences(&self) -> impl Iterator<Item = &Presence> {
+        self.remote_presences.values()
+    }
+
+    /// Updates the local cursor position, which is sent on the next flush
+    pub fn set_cursor(&mut self, cursor: Option<na::Vector2<f64>>) {
+        if self.presence.cursor != cursor {
+            self.presence.cursor = cursor;
+            self.presence_changed = true;
+        }
+    }
+
+    /// Sends the local ops and the presence, if it has changed
+    pub fn flush(&mut self, ops: Vec<StoreOp>) -> anyhow::Result<()> {
+        if !ops.is_empty() {
+            self.send(SyncMessage::Ops(ops))?;
+        }
+        if self.presence_changed {
+            self.send(SyncMessage::Presence(self.presence.clone()))?;
+            self.presence_changed = false;
+        }
+
+        Ok(())
+    }
+
+    /// Handles a received message. Returns the ops that need to be applied to the store
+    pub fn handle_message(&mut self, message: SyncMessage) -> Vec<StoreOp> {
+        match message {
+            SyncMessage::Ops(ops) => return ops,
+            SyncMessage::Presence(presence) => {
+                // A replica joined. It needs the current state, and our presence
+                if self
+                    .remote_presences
+                    .insert(presence.replica, presence)
+                    .is_none()
+                {
+                    self.catch_up_requested = true;
+                    self.presence_changed = true;
+                }
+            }
+            SyncMessage::Leave(replica) => {
+                self.remote_presences.remove(&replica);
+            }
+        }
+
+        vec![]
+    }
+
+    /// Wether a replica joined since the last call, which then needs to be sent the state with `StrokeStore::state_ops()`
+    pub fn take_catch_up_request(&mut self) -> bool {
+        std::mem::take(&mut self.catch_up_requested)
+    }
+
+    fn send(&self, message: SyncMessage) -> anyhow::Result<()> {
+        self.outgoing_tx
+            .send(message)
+            .map_err(|_| anyhow::anyhow!("sending sync message failed, the connection is closed"))
+    }
+}
+
+impl Drop for SyncClient {
+    fn drop(&mut self) {
+        let _ = self
+            .outgoing_tx
+            .send(SyncMessage::Leave(self.presence.replica));
+    }
+}
@@ -0,0 +1,100 @@
+use gtk4::{gdk, graphene, Snapshot};
+use rnote_compose::Color;
+
+use crate::store::TextSearchMatch;
+use crate::utils::{GdkRGBAHelpers, GrapheneRectHelpers};
+use crate::StrokeStore;
+
+/// A text search across the text strokes. Holds the matches for highlighting them and for stepping through them
+#[derive(Debug, Clone, Default)]
+pub struct TextSearch {
+    query: String,
+    matches: Vec<TextSearchMatch>,
+    current: Option<usize>,
+}
+
+impl TextSearch {
+    /// The highlight color of the matches
+    pub const MATCH_COLOR: Color = Color {
+        r: 0.98,
+        g: 0.85,
+        b: 0.2,
+        a: 0.35,
+    };
+    /// The highlight color of the current match
+    pub const CURRENT_MATCH_COLOR: Color = Color {
+        r: 0.96,
+        g: 0.6,
+        b: 0.1,
+        a: 0.5,
+    };
+
+    /// The searched query. Empty when no search is active
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn matches(&self) -> &[TextSearchMatch] {
+        &self.matches
+    }
+
+    /// The index of the current match
+    pub fn current_index(&self) -> Option<usize> {
+        self.current
+    }
+
+    pub fn current_match(&self) -> Option<&TextSearchMatch> {
+        self.current.and_then(|current| self.matches.get(current))
+    }
+
+    /// Searches the store for the query. The current match is kept if it is still found
+    pub fn update(&mut self, query: String, store: &StrokeStore) {
+        let current_match = self.current_match().cloned();
+
+        self.matches = store.search_text(&query);
+        self.current = current_match.and_then(|current_match| {
+            self.matches.iter().position(|search_match| {
+                search_match.key == current_match.key && search_match.range == current_match.range
+            })
+        });
+        self.query = query;
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Steps forward or backward to the next match, wrapping around at the ends. Returns the new current match
+    pub fn step(&mut self, forward: bool) -> Option<&TextSearchMatch> {
+        let n_matches = self.matches.len();
+        if n_matches == 0 {
+            self.current = None;
+            return None;
+        }
+
+        self.current = Some(match (self.current, forward) {
+            (Some(current), true) => (current + 1) % n_matches,
+            (Some(current), false) => (current + n_matches - 1) % n_matches,
+            (None, true) => 0,
+            (None, false) => n_matches - 1,
+        });
+
+        self.current_match()
+    }
+
+    /// Highlights the matches. Expects the snapshot in document coords
+    pub fn draw(&self, snapshot: &Snapshot) {
+        for (i, search_match) in self.matches.iter().enumerate() {
+            let color = if Some(i) == self.current {
+                Self::CURRENT_MATCH_COLOR
+            } else {
+                Self::MATCH_COLOR
+            };
+
+            snapshot.append_color(
+                &gdk::RGBA::from_compose_color(color),
+                &graphene::Rect::from_p2d_aabb(search_match.bounds),
+            );
+        }
+    }
+}
@@ -184,3 +184,52 @@ pub mod base64 {
         base64::decode(base64.as_bytes()).map_err(serde::de::Error::custom)
     }
 }
+
+/// Compares two json values semantically and collects the paths where they differ.
+pub fn json_value_diffs(a: &serde_json::Value, b: &serde_json::Value, path: &str) -> Vec<String> {
+    use serde_json::Value;
+
+    match (a, b) {
+        (Value::Object(a_map), Value::Object(b_map)) => {
+            let mut keys = a_map.keys().chain(b_map.keys()).collect::<Vec<&String>>();
+            keys.sort();
+            keys.dedup();
+
+            keys.into_iter()
+                .flat_map(|key| {
+                    let key_path = format!("{}/{}", path, key);
+
+                    match (a_map.get(key), b_map.get(key)) {
+                        (Some(a_value), Some(b_value)) => {
+                            json_value_diffs(a_value, b_value, &key_path)
+                        }
+                        _ => vec![key_path],
+                    }
+                })
+                .collect()
+        }
+        (Value::Array(a_vec), Value::Array(b_vec)) if a_vec.len() == b_vec.len() => a_vec
+            .iter()
+            .zip(b_vec.iter())
+            .enumerate()
+            .flat_map(|(i, (a_value, b_value))| {
+                json_value_diffs(a_value, b_value, &format!("{}/{}", path, i))
+            })
+            .collect(),
+        (a, b) if a == b => vec![],
+        _ => vec![path.to_string()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_value_diffs_paths() {
+        let a = serde_json::json!({ "a": 1.0, "b": { "c": [1, 2], "d": "x" } });
+        let b = serde_json::json!({ "a": 1.0, "b": { "c": [1, 3] }, "e": null });
+
+        assert_eq!(json_value_diffs(&a, &b, ""), vec!["/b/c/1", "/b/d", "/e"]);
+    }
+}
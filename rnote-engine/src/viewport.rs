@@ -0,0 +1,26 @@
+use crate::Camera;
+use gtk4::gsk;
+
+slotmap::new_key_type! {
+    /// The key of an additional viewport
+    pub struct ViewportKey;
+}
+
+/// An additional viewport observing the same document and store as the main camera, e.g. for a split view.
+/// The render caches of the strokes for the viewport are held by the store, keyed by the viewport key.
+#[derive(Debug, Clone, Default)]
+pub struct AdditionalViewport {
+    /// The camera of the viewport
+    pub camera: Camera,
+    /// The rendernodes of the background tiles for the camera viewport
+    pub(crate) background_rendernodes: Vec<gsk::RenderNode>,
+}
+
+impl AdditionalViewport {
+    pub fn new(camera: Camera) -> Self {
+        Self {
+            camera,
+            background_rendernodes: vec![],
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::engine::{EngineTask, EngineTaskSender};
+
+#[derive(Debug)]
+struct WatchedTask {
+    name: String,
+    started: Instant,
+    timed_out: Arc<AtomicBool>,
+    abort_on_timeout: bool,
+    tasks_tx: EngineTaskSender,
+    reported: bool,
+}
+
+#[derive(Debug)]
+struct WatchdogState {
+    timeout: Option<Duration>,
+    abort_on_timeout: bool,
+    tasks: HashMap<u64, WatchedTask>,
+    next_id: u64,
+    monitor_running: bool,
+}
+
+impl Default for WatchdogState {
+    fn default() -> Self {
+        Self {
+            timeout: Some(TaskWatchdog::TIMEOUT_DEFAULT),
+            abort_on_timeout: false,
+            tasks: HashMap::new(),
+            next_id: 0,
+            monitor_running: false,
+        }
+    }
+}
+
+/// A watchdog for long running import and export tasks.
+/// Tasks that exceed the configured timeout are reported through the engine task channel with `EngineTask::TaskTimedOut`,
+/// and flagged as timed out. When aborting on timeout is enabled, tasks are expected to check their guard with `is_timed_out()`
+/// and bail out early with `FileError::TimedOut`.
+#[derive(Debug, Clone, Default)]
+pub struct TaskWatchdog {
+    state: Arc<Mutex<WatchdogState>>,
+}
+
+impl TaskWatchdog {
+    /// The interval in which the running tasks are checked
+    pub const CHECK_INTERVAL: Duration = Duration::from_millis(100);
+    /// The default timeout. Tasks are only reported and not aborted by default
+    pub const TIMEOUT_DEFAULT: Duration = Duration::from_secs(60);
+
+    /// The timeout after which tasks are reported. None if the watchdog is disabled.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.state.lock().unwrap().timeout
+    }
+
+    /// Wether tasks exceeding the timeout get aborted
+    pub fn abort_on_timeout(&self) -> bool {
+        self.state.lock().unwrap().abort_on_timeout
+    }
+
+    /// Configures the watchdog. A timeout of None disables it.
+    pub fn set_timeout(&self, timeout: Option<Duration>, abort_on_timeout: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.timeout = timeout;
+        state.abort_on_timeout = abort_on_timeout;
+    }
+
+    /// Starts watching a task. It is watched until the returned guard is dropped.
+    pub fn watch(&self, name: &str, tasks_tx: EngineTaskSender) -> WatchdogGuard {
+        let timed_out = Arc::new(AtomicBool::new(false));
+        let mut state = self.state.lock().unwrap();
+
+        if state.timeout.is_none() {
+            return WatchdogGuard {
+                id: None,
+                timed_out,
+                abort_on_timeout: false,
+                watchdog: self.clone(),
+            };
+        }
+
+        let abort_on_timeout = state.abort_on_timeout;
+        let id = state.next_id;
+        state.next_id = state.next_id.wrapping_add(1);
+        state.tasks.insert(
+            id,
+            WatchedTask {
+                name: name.to_string(),
+                started: Instant::now(),
+                timed_out: Arc::clone(&timed_out),
+                abort_on_timeout,
+                tasks_tx,
+                reported: false,
+            },
+        );
+
+        let spawn_monitor = !state.monitor_running;
+        state.monitor_running = true;
+        drop(state);
+
+        if spawn_monitor {
+            self.spawn_monitor();
+        }
+
+        WatchdogGuard {
+            id: Some(id),
+            timed_out,
+            abort_on_timeout,
+            watchdog: self.clone(),
+        }
+    }
+
+    /// The monitor thread quits when there are no more tasks to watch, and is spawned again on the next watch().
+    fn spawn_monitor(&self) {
+        let watchdog = self.clone();
+
+        let spawn_result = std::thread::Builder::new()
+            .name(String::from("rnote-watchdog"))
+            .spawn(move || loop {
+                std::thread::sleep(Self::CHECK_INTERVAL);
+
+                let mut state = watchdog.state.lock().unwrap();
+                if state.tasks.is_empty() {
+                    state.monitor_running = false;
+                    return;
+                }
+                let timeout = match state.timeout {
+                    Some(timeout) => timeout,
+                    None => continue,
+                };
+
+                for task in state.tasks.values_mut() {
+                    let elapsed = task.started.elapsed();
+
+                    if task.reported || elapsed < timeout {
+                        continue;
+                    }
+                    task.reported = true;
+                    task.timed_out.store(true, Ordering::Relaxed);
+
+                    task.tasks_tx
+                        .unbounded_send(EngineTask::TaskTimedOut {
+                            name: task.name.clone(),
+                            elapsed,
+                            aborted: task.abort_on_timeout,
+                        })
+                        .unwrap_or_else(|e| {
+                            log::error!(
+                                "tasks_tx.send() TaskTimedOut failed in the watchdog monitor for task `{}`, with Err, {}",
+                                task.name,
+                                e
+                            );
+                        });
+                }
+            });
+
+        if let Err(e) = spawn_result {
+            log::error!(
+                "spawning the monitor thread failed in TaskWatchdog::spawn_monitor(), Err {}",
+                e
+            );
+            self.state.lock().unwrap().monitor_running = false;
+        }
+    }
+}
+
+/// Guards a watched task. Dropping it marks the task as finished.
+#[derive(Debug)]
+pub struct WatchdogGuard {
+    id: Option<u64>,
+    timed_out: Arc<AtomicBool>,
+    abort_on_timeout: bool,
+    watchdog: TaskWatchdog,
+}
+
+impl WatchdogGuard {
+    /// Wether the task exceeded the timeout and should be aborted. Long running tasks should check this regularly.
+    pub fn is_timed_out(&self) -> bool {
+        self.abort_on_timeout && self.timed_out.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for WatchdogGuard {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            self.watchdog.state.lock().unwrap().tasks.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+
+    #[test]
+    fn task_timed_out() {
+        let watchdog = TaskWatchdog::default();
+        watchdog.set_timeout(Some(Duration::from_millis(50)), true);
+        let (tasks_tx, mut tasks_rx) = mpsc::unbounded();
+
+        let guard = watchdog.watch("test", tasks_tx.clone());
+        let finished_guard = watchdog.watch("finished", tasks_tx);
+        drop(finished_guard);
+        assert!(!guard.is_timed_out());
+
+        std::thread::sleep(Duration::from_millis(50) + TaskWatchdog::CHECK_INTERVAL * 3);
+        assert!(guard.is_timed_out());
+
+        match tasks_rx.try_next() {
+            Ok(Some(EngineTask::TaskTimedOut { name, aborted, .. })) => {
+                assert_eq!(name, "test");
+                assert!(aborted);
+            }
+            _ => panic!("the timed out task was not reported"),
+        }
+        // The finished task is not reported
+        assert!(tasks_rx.try_next().is_err());
+    }
+
+    #[test]
+    fn task_timed_out_without_abort() {
+        let watchdog = TaskWatchdog::default();
+        watchdog.set_timeout(Some(Duration::from_millis(50)), false);
+        let (tasks_tx, mut tasks_rx) = mpsc::unbounded();
+
+        let guard = watchdog.watch("test", tasks_tx);
+        std::thread::sleep(Duration::from_millis(50) + TaskWatchdog::CHECK_INTERVAL * 3);
+
+        // Only reported, the task keeps running
+        assert!(!guard.is_timed_out());
+        assert!(matches!(
+            tasks_rx.try_next(),
+            Ok(Some(EngineTask::TaskTimedOut { aborted: false, .. }))
+        ));
+    }
+}
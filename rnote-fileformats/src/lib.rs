@@ -7,12 +7,13 @@
 //! Crates used for loading and writing:  
 //! XML: loading: `roxmltree`, writing: `xmlwriter`  
 //! Json: loading and writing `serde`, `serde-json`  
+//! CBOR: loading and writing `serde`, `ciborium`  
 //!
 //! it includes the following formats:
 //!
 //! | Format | file ending | XML | JSON | info |
 //! | --- | --- | --- | --- | --- |
-//! | Rnote | .rnote | - | native | see <https://github.com/flxzt/rnote> |
+//! | Rnote | .rnote | - | legacy, the store snapshot is CBOR encoded since 0.6 | see <https://github.com/flxzt/rnote> |
 //! | Xournal++ | .xopp | native | x | see <https://github.com/xournalpp/xournalpp> |
 
 use roxmltree::Node;
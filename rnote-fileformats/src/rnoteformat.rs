@@ -1,3 +1,5 @@
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
 use serde::{Deserialize, Serialize};
 use std::io::{Read, Write};
 
@@ -25,6 +27,20 @@ fn decompress_from_gzip(compressed: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
     Ok(bytes)
 }
 
+/// Encodes a value as CBOR
+pub fn encode_cbor<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, anyhow::Error> {
+    let mut bytes = Vec::<u8>::new();
+    ciborium::ser::into_writer(value, &mut bytes)
+        .map_err(|e| anyhow::anyhow!("encoding as cbor failed, {}", e))?;
+
+    Ok(bytes)
+}
+
+/// Decodes a value from CBOR
+pub fn decode_cbor<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T, anyhow::Error> {
+    ciborium::de::from_reader(bytes).map_err(|e| anyhow::anyhow!("decoding cbor failed, {}", e))
+}
+
 /// The rnote file wrapper. used to extract and match to the version up front, before deserializing the actual data.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename = "rnotefile_wrapper")]
@@ -51,9 +67,14 @@ pub struct RnotefileMaj0Min5 {
 
 impl FileFormatLoader for RnotefileMaj0Min5 {
     fn load_from_bytes(bytes: &[u8]) -> anyhow::Result<RnotefileMaj0Min5> {
-        let decompressed = String::from_utf8(decompress_from_gzip(bytes)?)?;
+        Self::load_from_decompressed(&decompress_from_gzip(bytes)?)
+    }
+}
 
-        let wrapped_rnote_file = serde_json::from_str::<RnotefileWrapper>(decompressed.as_str())?;
+impl RnotefileMaj0Min5 {
+    /// Loads the file from the already decompressed file data
+    fn load_from_decompressed(decompressed: &[u8]) -> anyhow::Result<Self> {
+        let wrapped_rnote_file = serde_json::from_slice::<RnotefileWrapper>(decompressed)?;
 
         // Conversions for older file format versions happens here
         if semver::VersionReq::parse(">=0.5.0")
@@ -69,6 +90,15 @@ impl FileFormatLoader for RnotefileMaj0Min5 {
             ))
         }
     }
+
+    /// Wether the decompressed file data is a JSON encoded file (up to version 0.5.x), as opposed to CBOR encoded (since version 0.6.0)
+    fn is_json_encoded(decompressed: &[u8]) -> bool {
+        decompressed
+            .iter()
+            .find(|byte| !byte.is_ascii_whitespace())
+            .map(|&byte| byte == b'{')
+            .unwrap_or(false)
+    }
 }
 
 impl FileFormatSaver for RnotefileMaj0Min5 {
@@ -84,6 +114,264 @@ impl FileFormatSaver for RnotefileMaj0Min5 {
     }
 }
 
+/// The CBOR encoded rnote file wrapper. used to extract and match to the version up front, before deserializing the actual data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "rnotefile_wrapper")]
+struct RnotefileWrapperCbor {
+    #[serde(rename = "version")]
+    version: semver::Version,
+    #[serde(rename = "data")]
+    data: ciborium::value::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// the Rnote file in format version 0.6.x. The actual (de-) serialization into strong types is happening in `rnote-engine`.
+///
+/// The file is encoded as gzip compressed CBOR instead of JSON. The store snapshot dominates the time needed for saving and loading,
+/// so it is kept as CBOR encoded bytes and decoded in `rnote-engine` directly into the store snapshot, without an intermediate representation.
+/// Files in older format versions are converted transparently when loading.
+#[serde(rename = "rnotefile_maj0_min6")]
+pub struct RnotefileMaj0Min6 {
+    /// the document
+    #[serde(rename = "document")]
+    pub document: serde_json::Value,
+    /// A CBOR encoded snapshot of the store
+    #[serde(rename = "store_snapshot", with = "serde_bytes")]
+    pub store_snapshot: Vec<u8>,
+    /// CBOR encoded checkpoints of the store, to revert to them. Empty when there are none
+    #[serde(default, rename = "checkpoints", with = "serde_bytes")]
+    pub checkpoints: Vec<u8>,
+}
+
+impl FileFormatLoader for RnotefileMaj0Min6 {
+    fn load_from_bytes(bytes: &[u8]) -> anyhow::Result<RnotefileMaj0Min6> {
+        if is_encrypted(bytes) {
+            return Err(PassphraseError::Required.into());
+        }
+
+        let decompressed = decompress_from_gzip(bytes)?;
+
+        // Older files are JSON encoded
+        if RnotefileMaj0Min5::is_json_encoded(&decompressed) {
+            return RnotefileMaj0Min6::try_from(RnotefileMaj0Min5::load_from_decompressed(
+                &decompressed,
+            )?);
+        }
+
+        let wrapped_rnote_file = decode_cbor::<RnotefileWrapperCbor>(&decompressed)?;
+
+        if semver::VersionReq::parse(">=0.6.0")
+            .unwrap()
+            .matches(&wrapped_rnote_file.version)
+        {
+            Ok(wrapped_rnote_file
+                .data
+                .deserialized::<RnotefileMaj0Min6>()
+                .map_err(|e| anyhow::anyhow!("deserializing rnote file data failed, {}", e))?)
+        } else {
+            Err(anyhow::anyhow!(
+                "failed to load rnote file from bytes, invalid version",
+            ))
+        }
+    }
+}
+
+impl FileFormatSaver for RnotefileMaj0Min6 {
+    fn save_as_bytes(&self, file_name: &str) -> anyhow::Result<Vec<u8>> {
+        let output = RnotefileWrapperCbor {
+            version: semver::Version::parse("0.6.0").unwrap(),
+            data: ciborium::value::Value::serialized(self)
+                .map_err(|e| anyhow::anyhow!("serializing rnote file data failed, {}", e))?,
+        };
+
+        let compressed = compress_to_gzip(&encode_cbor(&output)?, file_name)?;
+
+        Ok(compressed)
+    }
+}
+
+impl RnotefileMaj0Min6 {
+    /// Loads the file, decrypting it with the passphrase if it is encrypted. Unencrypted files are loaded regardless of the passphrase.
+    ///
+    /// Fails with `PassphraseError` when the file is encrypted and the passphrase is missing or wrong.
+    pub fn load_from_bytes_w_passphrase(
+        bytes: &[u8],
+        passphrase: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        if !is_encrypted(bytes) {
+            return Self::load_from_bytes(bytes);
+        }
+        let passphrase = passphrase.ok_or(PassphraseError::Required)?;
+
+        Self::load_from_bytes(&EncryptedRnotefile::decrypt(bytes, passphrase)?)
+    }
+
+    /// Saves the file, encrypting it with the passphrase if there is one
+    pub fn save_as_bytes_w_passphrase(
+        &self,
+        file_name: &str,
+        passphrase: Option<&str>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let bytes = self.save_as_bytes(file_name)?;
+
+        match passphrase {
+            Some(passphrase) => EncryptedRnotefile::encrypt(&bytes, passphrase),
+            None => Ok(bytes),
+        }
+    }
+}
+
+impl TryFrom<RnotefileMaj0Min5> for RnotefileMaj0Min6 {
+    type Error = anyhow::Error;
+
+    fn try_from(value: RnotefileMaj0Min5) -> Result<Self, Self::Error> {
+        Ok(Self {
+            document: value.document,
+            store_snapshot: encode_cbor(&value.store_snapshot)?,
+            checkpoints: vec![],
+        })
+    }
+}
+
+/// Encrypted rnote files start with these bytes, followed by the CBOR encoded `EncryptedRnotefile`
+const ENCRYPTED_MAGIC: &[u8] = b"RNOTEENC";
+
+/// Wether the file bytes are an encrypted rnote file
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(ENCRYPTED_MAGIC)
+}
+
+/// The errors when loading encrypted rnote files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassphraseError {
+    /// The file is encrypted, but no passphrase was given
+    Required,
+    /// The file could not be decrypted with the passphrase. Either it is wrong, or the file was tampered with
+    Wrong,
+}
+
+impl std::fmt::Display for PassphraseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Required => write!(f, "the file is encrypted, a passphrase is required"),
+            Self::Wrong => write!(f, "decrypting the file failed, wrong passphrase"),
+        }
+    }
+}
+
+impl std::error::Error for PassphraseError {}
+
+/// A rnote file, encrypted with XChaCha20-Poly1305 and a key derived from a passphrase with Argon2id.
+/// The encrypted payload is the complete gzip compressed file, so the file format version is encrypted as well.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "encrypted_rnotefile")]
+struct EncryptedRnotefile {
+    /// The Argon2id memory cost in KiB
+    #[serde(rename = "kdf_m_cost")]
+    kdf_m_cost: u32,
+    /// The Argon2id number of iterations
+    #[serde(rename = "kdf_t_cost")]
+    kdf_t_cost: u32,
+    /// The Argon2id degree of parallelism
+    #[serde(rename = "kdf_p_cost")]
+    kdf_p_cost: u32,
+    #[serde(rename = "salt", with = "serde_bytes")]
+    salt: Vec<u8>,
+    #[serde(rename = "nonce", with = "serde_bytes")]
+    nonce: Vec<u8>,
+    /// The encrypted file, including the authentication tag
+    #[serde(rename = "ciphertext", with = "serde_bytes")]
+    ciphertext: Vec<u8>,
+}
+
+impl EncryptedRnotefile {
+    const KDF_M_COST: u32 = 19 * 1024;
+    const KDF_T_COST: u32 = 2;
+    const KDF_P_COST: u32 = 1;
+    /// The upper bounds of the key derivation parameters read from files.
+    /// Crafted files could otherwise exhaust the memory or hang the CPU when they are opened
+    const KDF_M_COST_MAX: u32 = 256 * 1024;
+    const KDF_T_COST_MAX: u32 = 16;
+    const KDF_P_COST_MAX: u32 = 8;
+    const SALT_LEN: usize = 16;
+    const NONCE_LEN: usize = 24;
+    const KEY_LEN: usize = 32;
+
+    fn derive_key(&self, passphrase: &str) -> anyhow::Result<[u8; Self::KEY_LEN]> {
+        if self.kdf_m_cost > Self::KDF_M_COST_MAX
+            || self.kdf_t_cost > Self::KDF_T_COST_MAX
+            || self.kdf_p_cost > Self::KDF_P_COST_MAX
+        {
+            return Err(anyhow::anyhow!(
+                "key derivation parameters exceed the upper bounds, m_cost: {}, t_cost: {}, p_cost: {}",
+                self.kdf_m_cost,
+                self.kdf_t_cost,
+                self.kdf_p_cost
+            ));
+        }
+        let params = argon2::Params::new(
+            self.kdf_m_cost,
+            self.kdf_t_cost,
+            self.kdf_p_cost,
+            Some(Self::KEY_LEN),
+        )
+        .map_err(|e| anyhow::anyhow!("invalid key derivation parameters, {}", e))?;
+        let mut key = [0; Self::KEY_LEN];
+
+        argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &self.salt, &mut key)
+            .map_err(|e| anyhow::anyhow!("deriving key from passphrase failed, {}", e))?;
+
+        Ok(key)
+    }
+
+    /// Encrypts the file bytes. Every call generates a new salt and nonce
+    fn encrypt(bytes: &[u8], passphrase: &str) -> anyhow::Result<Vec<u8>> {
+        let mut salt = vec![0; Self::SALT_LEN];
+        let mut nonce = vec![0; Self::NONCE_LEN];
+        getrandom::getrandom(&mut salt)
+            .and_then(|_| getrandom::getrandom(&mut nonce))
+            .map_err(|e| anyhow::anyhow!("generating salt and nonce failed, {}", e))?;
+
+        let mut encrypted = Self {
+            kdf_m_cost: Self::KDF_M_COST,
+            kdf_t_cost: Self::KDF_T_COST,
+            kdf_p_cost: Self::KDF_P_COST,
+            salt,
+            nonce,
+            ciphertext: vec![],
+        };
+        let key = encrypted.derive_key(passphrase)?;
+        encrypted.ciphertext = XChaCha20Poly1305::new(Key::from_slice(&key))
+            .encrypt(XNonce::from_slice(&encrypted.nonce), bytes)
+            .map_err(|e| anyhow::anyhow!("encrypting the file failed, {}", e))?;
+
+        let mut output = ENCRYPTED_MAGIC.to_vec();
+        output.extend(encode_cbor(&encrypted)?);
+
+        Ok(output)
+    }
+
+    /// Decrypts the encrypted file bytes, starting with the magic bytes
+    fn decrypt(bytes: &[u8], passphrase: &str) -> anyhow::Result<Vec<u8>> {
+        let encrypted =
+            decode_cbor::<Self>(bytes.get(ENCRYPTED_MAGIC.len()..).unwrap_or_default())?;
+        if encrypted.nonce.len() != Self::NONCE_LEN {
+            return Err(anyhow::anyhow!(
+                "decrypting the file failed, invalid nonce length"
+            ));
+        }
+        let key = encrypted.derive_key(passphrase)?;
+
+        XChaCha20Poly1305::new(Key::from_slice(&key))
+            .decrypt(
+                XNonce::from_slice(&encrypted.nonce),
+                encrypted.ciphertext.as_slice(),
+            )
+            .map_err(|_| PassphraseError::Wrong.into())
+    }
+}
+
 // The file format is expected only to break on minor versions in prelease (0.x.x) and on major versions after 1.0.0 release. (equivalent to API breaks according to the semver spec)
 // Older formats can be added here, with the naming scheme RnoteFileMaj<X>Min<Y>, where X: semver major, Y: semver minor version.
 // Then TryFrom is implemented to allow conversions and chaining from older to newer versions.
@@ -92,3 +380,93 @@ impl FileFormatSaver for RnotefileMaj0Min5 {
 pub struct RnoteFileMaj0Min4 {
     doc: serde_json::Value,
 } */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_maj0_min6() {
+        let store_snapshot = serde_json::json!({ "stroke_components": [], "chrono_counter": 3 });
+        let rnote_file = RnotefileMaj0Min6 {
+            document: serde_json::json!({ "x": 0.0 }),
+            store_snapshot: encode_cbor(&store_snapshot).unwrap(),
+            checkpoints: vec![1, 2, 3],
+        };
+
+        let loaded =
+            RnotefileMaj0Min6::load_from_bytes(&rnote_file.save_as_bytes("test.rnote").unwrap())
+                .unwrap();
+
+        assert_eq!(loaded.document, rnote_file.document);
+        assert_eq!(
+            decode_cbor::<serde_json::Value>(&loaded.store_snapshot).unwrap(),
+            store_snapshot
+        );
+        assert_eq!(loaded.checkpoints, rnote_file.checkpoints);
+    }
+
+    #[test]
+    fn load_maj0_min6_encrypted() {
+        let rnote_file = RnotefileMaj0Min6 {
+            document: serde_json::json!({ "x": 0.0 }),
+            store_snapshot: encode_cbor(&serde_json::json!({ "chrono_counter": 3 })).unwrap(),
+            checkpoints: vec![],
+        };
+        let bytes = rnote_file
+            .save_as_bytes_w_passphrase("test.rnote", Some("passphrase"))
+            .unwrap();
+        assert!(is_encrypted(&bytes));
+
+        let loaded =
+            RnotefileMaj0Min6::load_from_bytes_w_passphrase(&bytes, Some("passphrase")).unwrap();
+        assert_eq!(loaded.document, rnote_file.document);
+        assert_eq!(loaded.store_snapshot, rnote_file.store_snapshot);
+
+        let passphrase_error = |passphrase| {
+            RnotefileMaj0Min6::load_from_bytes_w_passphrase(&bytes, passphrase)
+                .unwrap_err()
+                .downcast::<PassphraseError>()
+                .unwrap()
+        };
+        assert_eq!(passphrase_error(None), PassphraseError::Required);
+        assert_eq!(passphrase_error(Some("wrong")), PassphraseError::Wrong);
+    }
+
+    #[test]
+    fn reject_excessive_kdf_params() {
+        let encrypted = EncryptedRnotefile {
+            kdf_m_cost: u32::MAX,
+            kdf_t_cost: EncryptedRnotefile::KDF_T_COST,
+            kdf_p_cost: EncryptedRnotefile::KDF_P_COST,
+            salt: vec![0; EncryptedRnotefile::SALT_LEN],
+            nonce: vec![0; EncryptedRnotefile::NONCE_LEN],
+            ciphertext: vec![],
+        };
+        let mut bytes = ENCRYPTED_MAGIC.to_vec();
+        bytes.extend(encode_cbor(&encrypted).unwrap());
+
+        assert!(
+            RnotefileMaj0Min6::load_from_bytes_w_passphrase(&bytes, Some("passphrase")).is_err()
+        );
+    }
+
+    #[test]
+    fn load_maj0_min5_as_maj0_min6() {
+        let store_snapshot = serde_json::json!({ "stroke_components": [], "chrono_counter": 3 });
+        let rnote_file = RnotefileMaj0Min5 {
+            document: serde_json::json!({ "x": 0.0 }),
+            store_snapshot: store_snapshot.clone(),
+        };
+
+        let loaded =
+            RnotefileMaj0Min6::load_from_bytes(&rnote_file.save_as_bytes("test.rnote").unwrap())
+                .unwrap();
+
+        assert_eq!(loaded.document, rnote_file.document);
+        assert_eq!(
+            decode_cbor::<serde_json::Value>(&loaded.store_snapshot).unwrap(),
+            store_snapshot
+        );
+    }
+}
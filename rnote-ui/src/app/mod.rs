@@ -9,7 +9,8 @@ use rnote_engine::pens::penholder::PenStyle;
 
 use crate::{
     colorpicker::ColorSetter, config, penssidebar::BrushPage, penssidebar::EraserPage,
-    penssidebar::SelectorPage, penssidebar::ShaperPage, penssidebar::ToolsPage,
+    penssidebar::HighlighterPage, penssidebar::LaserPage, penssidebar::SelectorPage,
+    penssidebar::ShaperPage, penssidebar::SplinePage, penssidebar::ToolsPage,
     penssidebar::TypewriterPage, settingspanel::PenShortcutRow, utils, workspacebrowser::FileRow,
     workspacebrowser::WorkspaceRow, AppMenu, CanvasMenu, ColorPicker, IconPicker, MainHeader,
     PensSideBar, RnoteAppWindow, RnoteCanvas, SettingsPanel, UnitEntry, WorkspaceBrowser,
@@ -54,6 +55,9 @@ mod imp {
             BrushPage::static_type();
             ShaperPage::static_type();
             EraserPage::static_type();
+            HighlighterPage::static_type();
+            LaserPage::static_type();
+            SplinePage::static_type();
             SelectorPage::static_type();
             TypewriterPage::static_type();
             ToolsPage::static_type();
@@ -46,6 +46,11 @@ impl RnoteAppWindow {
             .bind("autosave-interval-secs", self, "autosave-interval-secs")
             .build();
 
+        // render threads
+        self.app_settings()
+            .bind("render-threads", self, "render-threads")
+            .build();
+
         // righthanded
         self.app_settings()
             .bind("righthanded", self, "righthanded")
@@ -89,6 +94,42 @@ impl RnoteAppWindow {
                 "selected",
             )
             .build();
+
+        // Highlighter page
+        self.app_settings()
+            .bind(
+                "highlighterpage-selected-color",
+                &self.penssidebar().highlighter_page().colorpicker(),
+                "selected",
+            )
+            .build();
+
+        // Laser page
+        self.app_settings()
+            .bind(
+                "laserpage-selected-color",
+                &self.penssidebar().laser_page().colorpicker(),
+                "selected",
+            )
+            .build();
+
+        // Spline page
+        self.app_settings()
+            .bind(
+                "splinepage-selected-color",
+                &self.penssidebar().spline_page().colorpicker(),
+                "selected",
+            )
+            .build();
+
+        // Tools page
+        self.app_settings()
+            .bind(
+                "toolspage-fill-selected-color",
+                &self.penssidebar().tools_page().fill_colorpicker(),
+                "selected",
+            )
+            .build();
     }
 
     /// load settings at start that are not bound in setup_settings. Setting changes through gsettings / dconf might not be applied until app restarts
@@ -195,6 +236,62 @@ impl RnoteAppWindow {
                 .load_colors(&colors);
         }
 
+        {
+            // Highlighter page
+            let colors = self
+                .app_settings()
+                .get::<(u32, u32, u32, u32)>("highlighterpage-colors");
+            let colors = [colors.0, colors.1, colors.2, colors.3]
+                .into_iter()
+                .map(Color::from)
+                .collect::<Vec<Color>>();
+            self.penssidebar()
+                .highlighter_page()
+                .colorpicker()
+                .load_colors(&colors);
+        }
+
+        {
+            // Laser page
+            let colors = self.app_settings().get::<(u32, u32)>("laserpage-colors");
+            let colors = [colors.0, colors.1]
+                .into_iter()
+                .map(Color::from)
+                .collect::<Vec<Color>>();
+            self.penssidebar()
+                .laser_page()
+                .colorpicker()
+                .load_colors(&colors);
+        }
+
+        {
+            // Spline page
+            let colors = self.app_settings().get::<(u32, u32)>("splinepage-colors");
+            let colors = [colors.0, colors.1]
+                .into_iter()
+                .map(Color::from)
+                .collect::<Vec<Color>>();
+            self.penssidebar()
+                .spline_page()
+                .colorpicker()
+                .load_colors(&colors);
+        }
+
+        {
+            // Tools page
+            let colors = self
+                .app_settings()
+                .get::<(u32, u32)>("toolspage-fill-colors");
+            let colors = [colors.0, colors.1]
+                .into_iter()
+                .map(Color::from)
+                .collect::<Vec<Color>>();
+            self.penssidebar()
+                .tools_page()
+                .fill_colorpicker()
+                .load_colors(&colors);
+        }
+
         {
             // load engine config
             let engine_config = self.app_settings().string("engine-config");
@@ -296,6 +393,70 @@ impl RnoteAppWindow {
                 .set_value("typewriterpage-colors", &colors.to_variant())?;
         }
 
+        {
+            // Highlighter page colors
+
+            let colors = self
+                .penssidebar()
+                .highlighter_page()
+                .colorpicker()
+                .fetch_all_colors()
+                .into_iter()
+                .map(|color| color.into())
+                .collect::<Vec<u32>>();
+            let colors = (colors[0], colors[1], colors[2], colors[3]);
+            self.app_settings()
+                .set_value("highlighterpage-colors", &colors.to_variant())?;
+        }
+
+        {
+            // Laser page colors
+
+            let colors = self
+                .penssidebar()
+                .laser_page()
+                .colorpicker()
+                .fetch_all_colors()
+                .into_iter()
+                .map(|color| color.into())
+                .collect::<Vec<u32>>();
+            let colors = (colors[0], colors[1]);
+            self.app_settings()
+                .set_value("laserpage-colors", &colors.to_variant())?;
+        }
+
+        {
+            // Spline page colors
+
+            let colors = self
+                .penssidebar()
+                .spline_page()
+                .colorpicker()
+                .fetch_all_colors()
+                .into_iter()
+                .map(|color| color.into())
+                .collect::<Vec<u32>>();
+            let colors = (colors[0], colors[1]);
+            self.app_settings()
+                .set_value("splinepage-colors", &colors.to_variant())?;
+        }
+
+        {
+            // Tools page fill colors
+
+            let colors = self
+                .penssidebar()
+                .tools_page()
+                .fill_colorpicker()
+                .fetch_all_colors()
+                .into_iter()
+                .map(|color| color.into())
+                .collect::<Vec<u32>>();
+            let colors = (colors[0], colors[1]);
+            self.app_settings()
+                .set_value("toolspage-fill-colors", &colors.to_variant())?;
+        }
+
         {
             // Save engine config
             self.save_engine_config()?;
@@ -7,7 +7,10 @@ use crate::{config, globals};
 use piet::RenderContext;
 use rnote_compose::helpers::Vector2Helpers;
 use rnote_engine::document::Layout;
+use rnote_engine::engine::TextExportFormat;
 use rnote_engine::pens::penholder::PenStyle;
+use rnote_engine::store::{SelectionComponent, SelectionCriteria, ZOrderChange};
+use rnote_engine::strokes::{Stroke, StrokeRestyle};
 use rnote_engine::{render, Camera, Document, DrawBehaviour, RnoteEngine};
 
 use gettextrs::gettext;
@@ -48,12 +51,20 @@ impl RnoteAppWindow {
         let action_visual_debug =
             gio::SimpleAction::new_stateful("visual-debug", None, &false.to_variant());
         self.add_action(&action_visual_debug);
+        let action_author_tint =
+            gio::SimpleAction::new_stateful("author-tint", None, &false.to_variant());
+        self.add_action(&action_author_tint);
         let action_debug_export_engine_state =
             gio::SimpleAction::new("debug-export-engine-state", None);
         self.add_action(&action_debug_export_engine_state);
         let action_debug_export_engine_config =
             gio::SimpleAction::new("debug-export-engine-config", None);
         self.add_action(&action_debug_export_engine_config);
+        let action_debug_check_state_roundtrip =
+            gio::SimpleAction::new("debug-check-state-roundtrip", None);
+        self.add_action(&action_debug_check_state_roundtrip);
+        let action_debug_memory_usage = gio::SimpleAction::new("debug-memory-usage", None);
+        self.add_action(&action_debug_memory_usage);
         let action_righthanded = gio::PropertyAction::new("righthanded", self, "righthanded");
         self.add_action(&action_righthanded);
         let action_touch_drawing =
@@ -63,9 +74,25 @@ impl RnoteAppWindow {
         let action_pen_sounds =
             gio::SimpleAction::new_stateful("pen-sounds", None, &false.to_variant());
         self.add_action(&action_pen_sounds);
+        let action_autoscroll =
+            gio::SimpleAction::new_stateful("autoscroll", None, &true.to_variant());
+        self.add_action(&action_autoscroll);
         let action_format_borders =
             gio::SimpleAction::new_stateful("format-borders", None, &true.to_variant());
         self.add_action(&action_format_borders);
+        let action_focus_mode =
+            gio::SimpleAction::new_stateful("focus-mode", None, &false.to_variant());
+        self.add_action(&action_focus_mode);
+        let action_presentation_mode =
+            gio::SimpleAction::new_stateful("presentation-mode", None, &false.to_variant());
+        self.add_action(&action_presentation_mode);
+        let action_next_page = gio::SimpleAction::new("next-page", None);
+        self.add_action(&action_next_page);
+        let action_prev_page = gio::SimpleAction::new("prev-page", None);
+        self.add_action(&action_prev_page);
+        let action_flipbook_playback =
+            gio::SimpleAction::new_stateful("flipbook-playback", None, &false.to_variant());
+        self.add_action(&action_flipbook_playback);
         let action_doc_layout = gio::SimpleAction::new_stateful(
             "doc-layout",
             Some(&glib::VariantType::new("s").unwrap()),
@@ -80,10 +107,24 @@ impl RnoteAppWindow {
         self.add_action(&action_zoom_reset);
         let action_zoom_fit_width = gio::SimpleAction::new("zoom-fit-width", None);
         self.add_action(&action_zoom_fit_width);
+        let action_zoom_fit_selection = gio::SimpleAction::new("zoom-fit-selection", None);
+        self.add_action(&action_zoom_fit_selection);
+        let action_zoom_fit_page = gio::SimpleAction::new("zoom-fit-page", None);
+        self.add_action(&action_zoom_fit_page);
+        let action_zoom_fit_document = gio::SimpleAction::new("zoom-fit-document", None);
+        self.add_action(&action_zoom_fit_document);
         let action_zoomin = gio::SimpleAction::new("zoom-in", None);
         self.add_action(&action_zoomin);
         let action_zoomout = gio::SimpleAction::new("zoom-out", None);
         self.add_action(&action_zoomout);
+        let action_rotate_canvas_clockwise =
+            gio::SimpleAction::new("rotate-canvas-clockwise", None);
+        self.add_action(&action_rotate_canvas_clockwise);
+        let action_rotate_canvas_counterclockwise =
+            gio::SimpleAction::new("rotate-canvas-counterclockwise", None);
+        self.add_action(&action_rotate_canvas_counterclockwise);
+        let action_rotation_reset = gio::SimpleAction::new("rotation-reset", None);
+        self.add_action(&action_rotation_reset);
         let action_zoom_to_value =
             gio::SimpleAction::new("zoom-to-value", Some(&glib::VariantType::new("d").unwrap()));
         self.add_action(&action_zoom_to_value);
@@ -100,10 +141,56 @@ impl RnoteAppWindow {
         self.add_action(&action_selection_duplicate);
         let action_selection_select_all = gio::SimpleAction::new("selection-select-all", None);
         self.add_action(&action_selection_select_all);
+        let action_selection_select_matching = gio::SimpleAction::new(
+            "selection-select-matching",
+            Some(&glib::VariantType::new("s").unwrap()),
+        );
+        self.add_action(&action_selection_select_matching);
+        let action_selection_copy_style = gio::SimpleAction::new("selection-copy-style", None);
+        self.add_action(&action_selection_copy_style);
+        let action_selection_paste_style = gio::SimpleAction::new("selection-paste-style", None);
+        self.add_action(&action_selection_paste_style);
+        let action_copied_style_to_pen = gio::SimpleAction::new("copied-style-to-pen", None);
+        self.add_action(&action_copied_style_to_pen);
+        let action_selection_z_order = gio::SimpleAction::new(
+            "selection-z-order",
+            Some(&glib::VariantType::new("s").unwrap()),
+        );
+        self.add_action(&action_selection_z_order);
+        let action_selection_convert_to_shapes =
+            gio::SimpleAction::new("selection-convert-to-shapes", None);
+        self.add_action(&action_selection_convert_to_shapes);
+        let action_selection_lock = gio::SimpleAction::new("selection-lock", None);
+        self.add_action(&action_selection_lock);
+        let action_unlock_all_strokes = gio::SimpleAction::new("unlock-all-strokes", None);
+        self.add_action(&action_unlock_all_strokes);
         let action_selection_deselect_all = gio::SimpleAction::new("selection-deselect-all", None);
         self.add_action(&action_selection_deselect_all);
+        let action_selection_opacity = gio::SimpleAction::new(
+            "selection-opacity",
+            Some(&glib::VariantType::new("d").unwrap()),
+        );
+        self.add_action(&action_selection_opacity);
         let action_clear_doc = gio::SimpleAction::new("clear-doc", None);
         self.add_action(&action_clear_doc);
+        let action_encrypt_doc = gio::SimpleAction::new("encrypt-doc", None);
+        self.add_action(&action_encrypt_doc);
+        let action_remove_empty_pages = gio::SimpleAction::new("remove-empty-pages", None);
+        self.add_action(&action_remove_empty_pages);
+        let action_recenter_origin = gio::SimpleAction::new("recenter-origin", None);
+        self.add_action(&action_recenter_origin);
+        let action_remove_empty_pages_on_save = gio::SimpleAction::new_stateful(
+            "remove-empty-pages-on-save",
+            None,
+            &false.to_variant(),
+        );
+        self.add_action(&action_remove_empty_pages_on_save);
+        let action_export_fit_to_margins =
+            gio::SimpleAction::new_stateful("export-fit-to-margins", None, &false.to_variant());
+        self.add_action(&action_export_fit_to_margins);
+        let action_dark_mode =
+            gio::SimpleAction::new_stateful("dark-mode", None, &false.to_variant());
+        self.add_action(&action_dark_mode);
         let action_new_doc = gio::SimpleAction::new("new-doc", None);
         self.add_action(&action_new_doc);
         let action_save_doc = gio::SimpleAction::new("save-doc", None);
@@ -126,12 +213,24 @@ impl RnoteAppWindow {
         let action_export_selection_as_png =
             gio::SimpleAction::new("export-selection-as-png", None);
         self.add_action(&action_export_selection_as_png);
+        let action_export_selection_as_jpeg =
+            gio::SimpleAction::new("export-selection-as-jpeg", None);
+        self.add_action(&action_export_selection_as_jpeg);
         let action_export_doc_as_svg = gio::SimpleAction::new("export-doc-as-svg", None);
         self.add_action(&action_export_doc_as_svg);
+        let action_export_doc_as_jpeg = gio::SimpleAction::new("export-doc-as-jpeg", None);
+        self.add_action(&action_export_doc_as_jpeg);
         let action_export_doc_as_pdf = gio::SimpleAction::new("export-doc-as-pdf", None);
         self.add_action(&action_export_doc_as_pdf);
         let action_export_doc_as_xopp = gio::SimpleAction::new("export-doc-as-xopp", None);
         self.add_action(&action_export_doc_as_xopp);
+        let action_export_doc_as_markdown = gio::SimpleAction::new("export-doc-as-markdown", None);
+        self.add_action(&action_export_doc_as_markdown);
+        let action_export_doc_as_plain_text =
+            gio::SimpleAction::new("export-doc-as-plain-text", None);
+        self.add_action(&action_export_doc_as_plain_text);
+        let action_export_flipbook_as_gif = gio::SimpleAction::new("export-flipbook-as-gif", None);
+        self.add_action(&action_export_flipbook_as_gif);
         let action_clipboard_copy = gio::SimpleAction::new("clipboard-copy", None);
         self.add_action(&action_clipboard_copy);
         let action_clipboard_paste = gio::SimpleAction::new("clipboard-paste", None);
@@ -234,6 +333,74 @@ impl RnoteAppWindow {
             }),
         );
 
+        // Tint strokes by author
+        action_author_tint.connect_change_state(
+            clone!(@weak self as appwindow => move |action_author_tint, state_request| {
+                let requested_state = state_request.unwrap().get::<bool>().unwrap();
+
+                appwindow.canvas().engine().borrow_mut().author_tint = requested_state;
+                appwindow.canvas().queue_draw();
+                action_author_tint.set_state(&requested_state.to_variant());
+            }),
+        );
+
+        // Collaborate with others through a sync server. The state is set once connected, see `dialog_start_sync()`
+        #[cfg(feature = "sync")]
+        {
+            let action_collaborate =
+                gio::SimpleAction::new_stateful("collaborate", None, &false.to_variant());
+            self.add_action(&action_collaborate);
+
+            action_collaborate.connect_change_state(
+                clone!(@weak self as appwindow => move |action_collaborate, state_request| {
+                    let requested_state = state_request.unwrap().get::<bool>().unwrap();
+
+                    if requested_state {
+                        dialogs::dialog_start_sync(&appwindow);
+                    } else {
+                        let widget_flags = appwindow.canvas().engine().borrow_mut().stop_sync();
+                        appwindow.handle_widget_flags(widget_flags);
+                        action_collaborate.set_state(&false.to_variant());
+                    }
+                }),
+            );
+        }
+
+        // Broadcast the document read-only to browsers in the local network
+        #[cfg(feature = "broadcast")]
+        {
+            let action_broadcast =
+                gio::SimpleAction::new_stateful("broadcast", None, &false.to_variant());
+            self.add_action(&action_broadcast);
+
+            action_broadcast.connect_change_state(
+                clone!(@weak self as appwindow => move |action_broadcast, state_request| {
+                    let requested_state = state_request.unwrap().get::<bool>().unwrap();
+
+                    if requested_state {
+                        // Any free port on all interfaces
+                        let res = appwindow.canvas().engine().borrow_mut().start_broadcast("0.0.0.0:0");
+
+                        match res {
+                            Ok(address) => {
+                                let url = format!("http://{}:{}", glib::host_name(), address.port());
+                                adw::prelude::ActionGroupExt::activate_action(&appwindow, "text-toast", Some(&gettext("Broadcasting the document at {}. It is reachable by anyone on all network interfaces").replace("{}", &url).to_variant()));
+                            }
+                            Err(e) => {
+                                log::error!("start_broadcast() failed with Err {}", e);
+                                adw::prelude::ActionGroupExt::activate_action(&appwindow, "error-toast", Some(&gettext("Starting the broadcast failed.").to_variant()));
+                                return;
+                            }
+                        }
+                    } else {
+                        appwindow.canvas().engine().borrow_mut().stop_broadcast();
+                    }
+
+                    action_broadcast.set_state(&requested_state.to_variant());
+                }),
+            );
+        }
+
         // Export engine state
         action_debug_export_engine_state.connect_activate(
             clone!(@weak self as appwindow => move |_action_debug_export_engine_state, _target| {
@@ -248,6 +415,28 @@ impl RnoteAppWindow {
             }),
         );
 
+        // Check the engine state round-trip through saving and opening
+        action_debug_check_state_roundtrip.connect_activate(
+            clone!(@weak self as appwindow => move |_action_debug_check_state_roundtrip, _target| {
+                if let Err(e) = appwindow.canvas().engine().borrow().check_state_roundtrip() {
+                    log::error!("check_state_roundtrip() failed with Err {}", e);
+                    adw::prelude::ActionGroupExt::activate_action(&appwindow, "error-toast", Some(&gettext("Engine state differs after saving and opening it again").to_variant()));
+                } else {
+                    adw::prelude::ActionGroupExt::activate_action(&appwindow, "text-toast", Some(&gettext("Engine state is equal after saving and opening it again").to_variant()));
+                }
+            }),
+        );
+
+        // Report the memory usage of the store
+        action_debug_memory_usage.connect_activate(
+            clone!(@weak self as appwindow => move |_action_debug_memory_usage, _target| {
+                let memory_usage = appwindow.canvas().engine().borrow().store.estimate_memory_usage();
+                log::info!("estimated memory usage - {}", memory_usage);
+
+                adw::prelude::ActionGroupExt::activate_action(&appwindow, "text-toast", Some(&memory_usage.to_string().to_variant()));
+            }),
+        );
+
         // Doc layout
         action_doc_layout.connect_activate(
             clone!(@weak self as appwindow => move |action_doc_layout, target| {
@@ -265,6 +454,10 @@ impl RnoteAppWindow {
                     "infinite" => {
                         appwindow.canvas().engine().borrow_mut().set_doc_layout(Layout::Infinite);
                         appwindow.canvas_fixedsize_quickactions_revealer().set_reveal_child(false);
+                    },
+                    "continuous-horizontal" => {
+                        appwindow.canvas().engine().borrow_mut().set_doc_layout(Layout::ContinuousHorizontal);
+                        appwindow.canvas_fixedsize_quickactions_revealer().set_reveal_child(false);
                     }
                     invalid_str => {
                         log::error!("action doc-layout failed, invalid str: {}", invalid_str);
@@ -287,6 +480,17 @@ impl RnoteAppWindow {
             }),
         );
 
+        // Auto-scroll
+        action_autoscroll.connect_change_state(
+            clone!(@weak self as appwindow => move |action_autoscroll, state_request| {
+                let autoscroll = state_request.unwrap().get::<bool>().unwrap();
+
+                appwindow.canvas().engine().borrow_mut().autoscroll = autoscroll;
+
+                action_autoscroll.set_state(&autoscroll.to_variant());
+            }),
+        );
+
         // Format borders
         action_format_borders.connect_change_state(
             clone!(@weak self as appwindow => move |action_format_borders, state_request| {
@@ -299,6 +503,21 @@ impl RnoteAppWindow {
             }),
         );
 
+        // Flipbook playback
+        action_flipbook_playback.connect_change_state(
+            clone!(@weak self as appwindow => move |action_flipbook_playback, state_request| {
+                let flipbook_playback = state_request.unwrap().get::<bool>().unwrap();
+
+                if flipbook_playback {
+                    appwindow.start_flipbook_playback();
+                } else {
+                    appwindow.stop_flipbook_playback();
+                }
+
+                action_flipbook_playback.set_state(&flipbook_playback.to_variant());
+            }),
+        );
+
         // Pen style
         action_pen_style.connect_activate(
             clone!(@weak self as appwindow => move |_action_pen_style, target| {
@@ -323,6 +542,15 @@ impl RnoteAppWindow {
                     "tools" => {
                         Some(PenStyle::Tools)
                     }
+                    "highlighter" => {
+                        Some(PenStyle::Highlighter)
+                    }
+                    "laser" => {
+                        Some(PenStyle::Laser)
+                    }
+                    "spline" => {
+                        Some(PenStyle::Spline)
+                    }
                     _ => {
                         log::error!("invalid target for action_pen_style, `{}`", pen_style);
                         None
@@ -370,6 +598,15 @@ impl RnoteAppWindow {
                     "tools" => {
                         Some(Some(PenStyle::Tools))
                     }
+                    "highlighter" => {
+                        Some(Some(PenStyle::Highlighter))
+                    }
+                    "laser" => {
+                        Some(Some(PenStyle::Laser))
+                    }
+                    "spline" => {
+                        Some(Some(PenStyle::Spline))
+                    }
                     "none" => {
                         Some(None)
                     }
@@ -392,6 +629,12 @@ impl RnoteAppWindow {
         action_refresh_ui_for_engine.connect_activate(clone!(
             @weak self as appwindow,
             @strong action_pen_sounds,
+            @strong action_autoscroll,
+            @strong action_remove_empty_pages_on_save,
+            @strong action_export_fit_to_margins,
+            @strong action_dark_mode,
+            @strong action_focus_mode,
+            @strong action_presentation_mode,
             @strong action_doc_layout,
             @strong action_format_borders,
             => move |_action_refresh_ui_for_engine, _| {
@@ -399,6 +642,12 @@ impl RnoteAppWindow {
             let format = appwindow.canvas().engine().borrow().document.format.clone();
             let doc_layout = appwindow.canvas().engine().borrow().doc_layout();
             let pen_sounds = appwindow.canvas().engine().borrow().pen_sounds();
+            let autoscroll = appwindow.canvas().engine().borrow().autoscroll;
+            let remove_empty_pages_on_save = appwindow.canvas().engine().borrow().remove_empty_pages_on_save;
+            let export_fit_to_margins = appwindow.canvas().engine().borrow().export_fit_to_margins;
+            let dark_mode = appwindow.canvas().engine().borrow().dark_mode;
+            let focus_mode = appwindow.canvas().engine().borrow().focus_mode;
+            let presentation_active = appwindow.canvas().engine().borrow().presentation_active();
             let pen_style = appwindow.canvas().engine().borrow().penholder.current_style_w_override();
 
             {
@@ -407,13 +656,29 @@ impl RnoteAppWindow {
                     Layout::FixedSize => "fixed-size",
                     Layout::ContinuousVertical => "continuous-vertical",
                     Layout::Infinite => "infinite",
+                    Layout::ContinuousHorizontal => "continuous-horizontal",
                 };
                 // we change the state through the actions, because they themselves hold state. ( e.g. used to display tickboxes for boolean actions )
                 action_doc_layout.activate(Some(&doc_layout.to_variant()));
                 action_pen_sounds.change_state(&pen_sounds.to_variant());
+                action_autoscroll.change_state(&autoscroll.to_variant());
+                action_remove_empty_pages_on_save.change_state(&remove_empty_pages_on_save.to_variant());
+                action_export_fit_to_margins.change_state(&export_fit_to_margins.to_variant());
+                action_dark_mode.change_state(&dark_mode.to_variant());
+                action_focus_mode.change_state(&focus_mode.to_variant());
+                action_presentation_mode.change_state(&presentation_active.to_variant());
                 action_format_borders.change_state(&format.show_borders.to_variant());
             }
 
+            // The connection to the sync server might have been closed
+            #[cfg(feature = "sync")]
+            {
+                let syncing = appwindow.canvas().engine().borrow().syncing();
+                if let Some(action_collaborate) = appwindow.lookup_action("collaborate").and_then(|action| action.downcast::<gio::SimpleAction>().ok()) {
+                    action_collaborate.set_state(&syncing.to_variant());
+                }
+            }
+
 
             // Current pen
             match pen_style {
@@ -447,6 +712,21 @@ impl RnoteAppWindow {
                     appwindow.narrow_tools_toggle().set_active(true);
                     appwindow.penssidebar().sidebar_stack().set_visible_child_name("tools_page");
                 }
+                PenStyle::Highlighter => {
+                    appwindow.mainheader().highlighter_toggle().set_active(true);
+                    appwindow.narrow_highlighter_toggle().set_active(true);
+                    appwindow.penssidebar().sidebar_stack().set_visible_child_name("highlighter_page");
+                }
+                PenStyle::Laser => {
+                    appwindow.mainheader().laser_toggle().set_active(true);
+                    appwindow.narrow_laser_toggle().set_active(true);
+                    appwindow.penssidebar().sidebar_stack().set_visible_child_name("laser_page");
+                }
+                PenStyle::Spline => {
+                    appwindow.mainheader().spline_toggle().set_active(true);
+                    appwindow.narrow_spline_toggle().set_active(true);
+                    appwindow.penssidebar().sidebar_stack().set_visible_child_name("spline_page");
+                }
             }
 
             // Brush page
@@ -461,6 +741,15 @@ impl RnoteAppWindow {
             // Eraser page
             appwindow.penssidebar().eraser_page().refresh_ui(&appwindow);
 
+            // Highlighter page
+            appwindow.penssidebar().highlighter_page().refresh_ui(&appwindow);
+
+            // Laser page
+            appwindow.penssidebar().laser_page().refresh_ui(&appwindow);
+
+            // Spline page
+            appwindow.penssidebar().spline_page().refresh_ui(&appwindow);
+
             // Selector
             appwindow.penssidebar().selector_page().refresh_ui(&appwindow);
 
@@ -489,16 +778,8 @@ impl RnoteAppWindow {
         // Duplicate Selection
         action_selection_duplicate.connect_activate(
             clone!(@weak self as appwindow => move |_action_selection_duplicate, _| {
-                let widget_flags = appwindow.canvas().engine().borrow_mut().record();
+                let widget_flags = appwindow.canvas().engine().borrow_mut().duplicate_selection_with_offset(SelectionComponent::SELECTION_DUPLICATION_OFFSET);
                 appwindow.handle_widget_flags(widget_flags);
-
-                let new_selected = appwindow.canvas().engine().borrow_mut().store.duplicate_selection();
-                appwindow.canvas().engine().borrow_mut().store.update_geometry_for_strokes(&new_selected);
-
-
-                appwindow.canvas().engine().borrow_mut().resize_autoexpand();
-                appwindow.canvas().engine().borrow_mut().update_pens_states();
-                appwindow.canvas().update_engine_rendering();
             }),
         );
 
@@ -520,6 +801,114 @@ impl RnoteAppWindow {
             }),
         );
 
+        // select all strokes matching the selection, or on the current page
+        action_selection_select_matching.connect_activate(
+            clone!(@weak self as appwindow => move |_action_selection_select_matching, target| {
+                let criteria = target.unwrap().str().unwrap();
+
+                let criteria = match criteria {
+                    "same-color" => SelectionCriteria::SameColor,
+                    "same-type" => SelectionCriteria::SameType,
+                    "same-layer" => SelectionCriteria::SameLayer,
+                    "current-page" => {
+                        let current_page_bounds = appwindow.canvas().engine().borrow().current_page_bounds();
+
+                        match current_page_bounds {
+                            Some(bounds) => SelectionCriteria::WithinBounds(bounds),
+                            None => return,
+                        }
+                    }
+                    _ => {
+                        log::error!("invalid target for action_selection_select_matching, `{}`", criteria);
+                        return;
+                    }
+                };
+
+                let widget_flags = appwindow.canvas().engine().borrow_mut().select_strokes_matching(criteria);
+                appwindow.handle_widget_flags(widget_flags);
+
+                appwindow.canvas().update_engine_rendering();
+            }),
+        );
+
+        // Copy the style of the selection
+        action_selection_copy_style.connect_activate(
+            clone!(@weak self as appwindow => move |_action_selection_copy_style, _| {
+                if !appwindow.canvas().engine().borrow_mut().copy_style_of_selection() {
+                    adw::prelude::ActionGroupExt::activate_action(&appwindow, "text-toast", Some(&gettext("The selection has no style to copy.").to_variant()));
+                }
+            }),
+        );
+
+        // Paste the copied style onto the selection
+        action_selection_paste_style.connect_activate(
+            clone!(@weak self as appwindow => move |_action_selection_paste_style, _| {
+                let widget_flags = appwindow.canvas().engine().borrow_mut().paste_style_to_selection();
+                appwindow.handle_widget_flags(widget_flags);
+            }),
+        );
+
+        // Set the copied style as the pen config
+        action_copied_style_to_pen.connect_activate(
+            clone!(@weak self as appwindow => move |_action_copied_style_to_pen, _| {
+                let widget_flags = appwindow.canvas().engine().borrow_mut().apply_copied_style_to_pen_config();
+                appwindow.handle_widget_flags(widget_flags);
+            }),
+        );
+
+        // Lock the selected strokes
+        action_selection_lock.connect_activate(
+            clone!(@weak self as appwindow => move |_action_selection_lock, _| {
+                let widget_flags = appwindow.canvas().engine().borrow_mut().lock_selection();
+                appwindow.handle_widget_flags(widget_flags);
+            }),
+        );
+
+        // Unlock all locked strokes
+        action_unlock_all_strokes.connect_activate(
+            clone!(@weak self as appwindow => move |_action_unlock_all_strokes, _| {
+                let (n_unlocked, widget_flags) = appwindow.canvas().engine().borrow_mut().unlock_all_strokes();
+                appwindow.handle_widget_flags(widget_flags);
+
+                if n_unlocked == 0 {
+                    adw::prelude::ActionGroupExt::activate_action(&appwindow, "text-toast", Some(&gettext("There are no locked strokes.").to_variant()));
+                }
+            }),
+        );
+
+        // Convert the selected brush strokes to shapes
+        action_selection_convert_to_shapes.connect_activate(
+            clone!(@weak self as appwindow => move |_action_selection_convert_to_shapes, _| {
+                let (n_converted, widget_flags) = appwindow.canvas().engine().borrow_mut().convert_selection_to_shapes();
+                appwindow.handle_widget_flags(widget_flags);
+
+                if n_converted == 0 {
+                    adw::prelude::ActionGroupExt::activate_action(&appwindow, "text-toast", Some(&gettext("No freehand strokes in the selection could be converted to shapes.").to_variant()));
+                }
+            }),
+        );
+
+        // Change the draw order of the selection
+        action_selection_z_order.connect_activate(
+            clone!(@weak self as appwindow => move |_action_selection_z_order, target| {
+                let change = target.unwrap().str().unwrap();
+
+                let change = match change {
+                    "bring-to-front" => ZOrderChange::BringToFront,
+                    "send-to-back" => ZOrderChange::SendToBack,
+                    "raise" => ZOrderChange::Raise,
+                    "lower" => ZOrderChange::Lower,
+                    _ => {
+                        log::error!("invalid target for action_selection_z_order, `{}`", change);
+                        return;
+                    }
+                };
+
+                let widget_flags = appwindow.canvas().engine().borrow_mut().change_selection_z_order(change);
+                appwindow.handle_widget_flags(widget_flags);
+            }),
+        );
+
         // deselect all strokes
         action_selection_deselect_all.connect_activate(
             clone!(@weak self as appwindow => move |_action_selection_deselect_all, _| {
@@ -535,11 +924,164 @@ impl RnoteAppWindow {
             }),
         );
 
+        // change the opacity of the selected strokes
+        action_selection_opacity.connect_activate(
+            clone!(@weak self as appwindow => move |_action_selection_opacity, target| {
+                let opacity = target.unwrap().get::<f64>().unwrap().clamp(Stroke::OPACITY_MIN, 1.0);
+                let selection_keys = appwindow.canvas().engine().borrow().store.selection_keys_as_rendered();
+
+                // Avoids recording a history entry when nothing changes, e.g. when the ui is refreshed
+                let unchanged = selection_keys.iter().all(|&key| {
+                    appwindow.canvas().engine().borrow().store.opacity(key).map_or(true, |current| (current - opacity).abs() < 1e-3)
+                });
+                if unchanged {
+                    return;
+                }
+
+                let widget_flags = appwindow.canvas().engine().borrow_mut().restyle_selection(StrokeRestyle {
+                    opacity: Some(opacity),
+                    ..StrokeRestyle::default()
+                });
+                appwindow.handle_widget_flags(widget_flags);
+            }),
+        );
+
         // Clear doc
         action_clear_doc.connect_activate(clone!(@weak self as appwindow => move |_, _| {
             dialogs::dialog_clear_doc(&appwindow);
         }));
 
+        // Encrypt document
+        action_encrypt_doc.connect_activate(clone!(@weak self as appwindow => move |_, _| {
+            dialogs::dialog_encrypt_doc(&appwindow);
+        }));
+
+        // Recenter origin
+        action_recenter_origin.connect_activate(clone!(@weak self as appwindow => move |_, _| {
+            let res = appwindow.canvas().engine().borrow_mut().recenter_origin();
+
+            match res {
+                Ok(widget_flags) => {
+                    appwindow.handle_widget_flags(widget_flags);
+                    appwindow.canvas().update_engine_rendering();
+                }
+                Err(e) => {
+                    log::error!("recenter_origin() failed with Err {}", e);
+                    adw::prelude::ActionGroupExt::activate_action(&appwindow, "error-toast", Some(&gettext("The origin can only be recentered in the infinite layout").to_variant()));
+                }
+            }
+        }));
+
+        // Remove empty pages
+        action_remove_empty_pages.connect_activate(clone!(@weak self as appwindow => move |_, _| {
+            let res = appwindow.canvas().engine().borrow_mut().remove_empty_pages();
+
+            match res {
+                Ok(widget_flags) => {
+                    appwindow.handle_widget_flags(widget_flags);
+                    appwindow.canvas().update_engine_rendering();
+                }
+                Err(e) => {
+                    log::error!("remove_empty_pages() failed with Err {}", e);
+                    adw::prelude::ActionGroupExt::activate_action(&appwindow, "error-toast", Some(&gettext("Empty pages can only be removed in the fixed size layout").to_variant()));
+                }
+            }
+        }));
+
+        // Remove empty pages on save
+        action_remove_empty_pages_on_save.connect_change_state(
+            clone!(@weak self as appwindow => move |action_remove_empty_pages_on_save, state_request| {
+                let remove_empty_pages_on_save = state_request.unwrap().get::<bool>().unwrap();
+
+                appwindow.canvas().engine().borrow_mut().remove_empty_pages_on_save = remove_empty_pages_on_save;
+
+                action_remove_empty_pages_on_save.set_state(&remove_empty_pages_on_save.to_variant());
+            }),
+        );
+
+        // Export fit to margins
+        action_export_fit_to_margins.connect_change_state(
+            clone!(@weak self as appwindow => move |action_export_fit_to_margins, state_request| {
+                let export_fit_to_margins = state_request.unwrap().get::<bool>().unwrap();
+
+                appwindow.canvas().engine().borrow_mut().export_fit_to_margins = export_fit_to_margins;
+
+                action_export_fit_to_margins.set_state(&export_fit_to_margins.to_variant());
+            }),
+        );
+
+        // Dark mode
+        action_dark_mode.connect_change_state(
+            clone!(@weak self as appwindow => move |action_dark_mode, state_request| {
+                let dark_mode = state_request.unwrap().get::<bool>().unwrap();
+
+                appwindow.canvas().engine().borrow_mut().dark_mode = dark_mode;
+                appwindow.canvas().queue_draw();
+
+                action_dark_mode.set_state(&dark_mode.to_variant());
+            }),
+        );
+
+        // Focus mode
+        action_focus_mode.connect_change_state(
+            clone!(@weak self as appwindow => move |action_focus_mode, state_request| {
+                let focus_mode = state_request.unwrap().get::<bool>().unwrap();
+
+                appwindow.canvas().engine().borrow_mut().focus_mode = focus_mode;
+                appwindow.canvas().queue_draw();
+
+                action_focus_mode.set_state(&focus_mode.to_variant());
+            }),
+        );
+
+        // Presentation mode
+        action_presentation_mode.connect_change_state(
+            clone!(@weak self as appwindow => move |action_presentation_mode, state_request| {
+                let presentation_mode = state_request.unwrap().get::<bool>().unwrap();
+                let presentation_active = appwindow.canvas().engine().borrow().presentation_active();
+
+                if presentation_mode && !presentation_active {
+                    let res = appwindow.canvas().engine().borrow_mut().start_presentation();
+                    match res {
+                        Ok(widget_flags) => {
+                            appwindow.handle_widget_flags(widget_flags);
+                        }
+                        Err(e) => {
+                            log::error!("start_presentation() failed with Err {}", e);
+                            adw::prelude::ActionGroupExt::activate_action(&appwindow, "error-toast", Some(&gettext("Presenting requires a document with pages").to_variant()));
+                            return;
+                        }
+                    }
+
+                    appwindow.app().set_accels_for_action("win.next-page", &["Page_Down", "Right", "space"]);
+                    appwindow.app().set_accels_for_action("win.prev-page", &["Page_Up", "Left", "BackSpace"]);
+                } else if !presentation_mode && presentation_active {
+                    let widget_flags = appwindow.canvas().engine().borrow_mut().stop_presentation();
+                    appwindow.handle_widget_flags(widget_flags);
+
+                    appwindow.app().set_accels_for_action("win.next-page", &[]);
+                    appwindow.app().set_accels_for_action("win.prev-page", &[]);
+                }
+
+                appwindow.canvas().update_engine_rendering();
+                action_presentation_mode.set_state(&presentation_mode.to_variant());
+            }),
+        );
+
+        // Next page
+        action_next_page.connect_activate(clone!(@weak self as appwindow => move |_,_| {
+            let widget_flags = appwindow.canvas().engine().borrow_mut().next_page();
+            appwindow.handle_widget_flags(widget_flags);
+            appwindow.canvas().update_engine_rendering();
+        }));
+
+        // Previous page
+        action_prev_page.connect_activate(clone!(@weak self as appwindow => move |_,_| {
+            let widget_flags = appwindow.canvas().engine().borrow_mut().prev_page();
+            appwindow.handle_widget_flags(widget_flags);
+            appwindow.canvas().update_engine_rendering();
+        }));
+
         // Undo stroke
         action_undo_stroke.connect_activate(clone!(@weak self as appwindow => move |_,_| {
             let widget_flags =appwindow.canvas().engine().borrow_mut().undo();
@@ -567,16 +1109,46 @@ impl RnoteAppWindow {
 
         // Zoom fit to width
         action_zoom_fit_width.connect_activate(clone!(@weak self as appwindow => move |_,_| {
-            let new_zoom = f64::from(appwindow.canvas_scroller().width()) / (appwindow.canvas().engine().borrow().document.format.width as f64 + 2.0 * Document::SHADOW_WIDTH);
+            let new_zoom = appwindow.canvas().engine().borrow().camera.zoom_config().clamp_zoom(f64::from(appwindow.canvas_scroller().width()) / (appwindow.canvas().engine().borrow().document.format.width as f64 + 2.0 * Document::SHADOW_WIDTH));
 
             let current_doc_center = appwindow.canvas().current_center_on_doc();
-            adw::prelude::ActionGroupExt::activate_action(&appwindow, "zoom-to-value", Some(&new_zoom.to_variant()));
-            appwindow.canvas().center_around_coord_on_doc(current_doc_center);
+            appwindow.canvas().animate_camera_to(current_doc_center, new_zoom);
+            appwindow.mainheader().canvasmenu().zoomreset_button().set_label(format!("{:.0}%", (100.0 * new_zoom).round()).as_str());
+        }));
+
+        // Zoom fit to selection
+        action_zoom_fit_selection.connect_activate(clone!(@weak self as appwindow => move |_,_| {
+            let selection_bounds = appwindow.canvas().engine().borrow().selection_bounds();
+
+            if let Some(selection_bounds) = selection_bounds {
+                let new_zoom = appwindow.canvas().animate_zoom_fit_to_bounds(selection_bounds);
+                appwindow.mainheader().canvasmenu().zoomreset_button().set_label(format!("{:.0}%", (100.0 * new_zoom).round()).as_str());
+            }
+        }));
+
+        // Zoom fit to the current page
+        action_zoom_fit_page.connect_activate(clone!(@weak self as appwindow => move |_,_| {
+            let page_bounds = appwindow.canvas().engine().borrow().current_page_bounds();
+
+            if let Some(page_bounds) = page_bounds {
+                let new_zoom = appwindow.canvas().animate_zoom_fit_to_bounds(page_bounds);
+                appwindow.mainheader().canvasmenu().zoomreset_button().set_label(format!("{:.0}%", (100.0 * new_zoom).round()).as_str());
+            }
+        }));
+
+        // Zoom fit to the document
+        action_zoom_fit_document.connect_activate(clone!(@weak self as appwindow => move |_,_| {
+            let doc_bounds = appwindow.canvas().engine().borrow().bounds_w_content_extended();
+
+            if let Some(doc_bounds) = doc_bounds {
+                let new_zoom = appwindow.canvas().animate_zoom_fit_to_bounds(doc_bounds);
+                appwindow.mainheader().canvasmenu().zoomreset_button().set_label(format!("{:.0}%", (100.0 * new_zoom).round()).as_str());
+            }
         }));
 
         // Zoom in
         action_zoomin.connect_activate(clone!(@weak self as appwindow => move |_,_| {
-            let new_zoom = appwindow.canvas().engine().borrow().camera.total_zoom() * (1.0 + RnoteCanvas::ZOOM_STEP);
+            let new_zoom = appwindow.canvas().engine().borrow().camera.total_zoom() * (1.0 + appwindow.canvas().engine().borrow().camera.zoom_config().zoom_step);
 
             let current_doc_center = appwindow.canvas().current_center_on_doc();
             adw::prelude::ActionGroupExt::activate_action(&appwindow, "zoom-to-value", Some(&new_zoom.to_variant()));
@@ -585,17 +1157,40 @@ impl RnoteAppWindow {
 
         // Zoom out
         action_zoomout.connect_activate(clone!(@weak self as appwindow => move |_,_| {
-            let new_zoom = appwindow.canvas().engine().borrow().camera.total_zoom() * (1.0 - RnoteCanvas::ZOOM_STEP);
+            let new_zoom = appwindow.canvas().engine().borrow().camera.total_zoom() * (1.0 - appwindow.canvas().engine().borrow().camera.zoom_config().zoom_step);
 
             let current_doc_center = appwindow.canvas().current_center_on_doc();
             adw::prelude::ActionGroupExt::activate_action(&appwindow, "zoom-to-value", Some(&new_zoom.to_variant()));
             appwindow.canvas().center_around_coord_on_doc(current_doc_center);
         }));
 
+        // Rotate canvas clockwise
+        action_rotate_canvas_clockwise.connect_activate(clone!(@weak self as appwindow => move |_,_| {
+            let new_rotation = appwindow.canvas().engine().borrow().camera.rotation() + RnoteCanvas::ROTATION_STEP;
+            appwindow.canvas().engine().borrow_mut().camera.set_rotation(new_rotation);
+
+            appwindow.canvas().update_engine_rendering();
+        }));
+
+        // Rotate canvas counterclockwise
+        action_rotate_canvas_counterclockwise.connect_activate(clone!(@weak self as appwindow => move |_,_| {
+            let new_rotation = appwindow.canvas().engine().borrow().camera.rotation() - RnoteCanvas::ROTATION_STEP;
+            appwindow.canvas().engine().borrow_mut().camera.set_rotation(new_rotation);
+
+            appwindow.canvas().update_engine_rendering();
+        }));
+
+        // Rotation reset
+        action_rotation_reset.connect_activate(clone!(@weak self as appwindow => move |_,_| {
+            appwindow.canvas().engine().borrow_mut().camera.set_rotation(Camera::ROTATION_DEFAULT);
+
+            appwindow.canvas().update_engine_rendering();
+        }));
+
         // Zoom to value
         action_zoom_to_value.connect_activate(
             clone!(@weak self as appwindow => move |_action_zoom_to_value, target| {
-                let new_zoom = target.unwrap().get::<f64>().unwrap().clamp(Camera::ZOOM_MIN, Camera::ZOOM_MAX);
+                let new_zoom = appwindow.canvas().engine().borrow().camera.zoom_config().snap_zoom(target.unwrap().get::<f64>().unwrap());
 
                 appwindow.canvas().zoom_temporarily_then_scale_to_after_timeout(new_zoom, RnoteCanvas::ZOOM_TIMEOUT_TIME);
 
@@ -623,7 +1218,9 @@ impl RnoteAppWindow {
 
         // Return to the origin page
         action_return_origin_page.connect_activate(clone!(@weak self as appwindow => move |_,_| {
-            appwindow.canvas().return_to_origin_page();
+            let origin_page_center = appwindow.canvas().origin_page_center_on_doc();
+            let zoom = appwindow.canvas().engine().borrow().camera.zoom();
+            appwindow.canvas().animate_camera_to(origin_page_center, zoom);
 
             appwindow.canvas().engine().borrow_mut().resize_autoexpand();
             appwindow.canvas().update_engine_rendering();
@@ -689,7 +1286,7 @@ impl RnoteAppWindow {
             let background_svg = if with_background {
                 appwindow.canvas().engine().borrow().document
                     .background
-                    .gen_svg(doc_bounds)
+                    .gen_svg(doc_bounds, &appwindow.canvas().engine().borrow().document.pages_bounds())
                     .map_err(|e| {
                         log::error!(
                             "background.gen_svg() failed in in the print document action, with Err {}",
@@ -807,11 +1404,23 @@ impl RnoteAppWindow {
             }),
         );
 
+        // Export selection as JPEG
+        action_export_selection_as_jpeg.connect_activate(
+            clone!(@weak self as appwindow => move |_,_| {
+                dialogs::dialog_export_selection_as_jpeg(&appwindow);
+            }),
+        );
+
         // Export document as SVG
         action_export_doc_as_svg.connect_activate(clone!(@weak self as appwindow => move |_,_| {
             dialogs::dialog_export_doc_as_svg(&appwindow);
         }));
 
+        // Export document as JPEG
+        action_export_doc_as_jpeg.connect_activate(clone!(@weak self as appwindow => move |_,_| {
+            dialogs::dialog_export_doc_as_jpeg(&appwindow);
+        }));
+
         // Export document as PDF
         action_export_doc_as_pdf.connect_activate(clone!(@weak self as appwindow => move |_,_| {
             dialogs::dialog_export_doc_as_pdf(&appwindow);
@@ -822,19 +1431,42 @@ impl RnoteAppWindow {
             dialogs::dialog_export_doc_as_xopp(&appwindow);
         }));
 
+        // Export text as markdown
+        action_export_doc_as_markdown.connect_activate(
+            clone!(@weak self as appwindow => move |_,_| {
+                dialogs::dialog_export_doc_as_text(&appwindow, TextExportFormat::Markdown);
+            }),
+        );
+
+        // Export text as plain text
+        action_export_doc_as_plain_text.connect_activate(
+            clone!(@weak self as appwindow => move |_,_| {
+                dialogs::dialog_export_doc_as_text(&appwindow, TextExportFormat::PlainText);
+            }),
+        );
+
+        // Export flipbook as GIF
+        action_export_flipbook_as_gif.connect_activate(
+            clone!(@weak self as appwindow => move |_,_| {
+                dialogs::dialog_export_flipbook_as_gif(&appwindow);
+            }),
+        );
+
         // Clipboard copy
         action_clipboard_copy.connect_activate(clone!(@weak self as appwindow => move |_, _| {
         match appwindow.canvas().engine().borrow().fetch_clipboard_content() {
-            Ok(Some((data, mime_type))) => {
-                //log::debug!("set clipboard with data: {:02x?}, mime-type: {}", data, mime_type);
-
-                let content = gdk::ContentProvider::for_bytes(mime_type.as_str(), &glib::Bytes::from_owned(data));
+            Ok(content) if !content.is_empty() => {
+                // Offer all formats, so other applications can pick the one they understand
+                let providers = content.into_iter().map(|(data, mime_type)| {
+                    gdk::ContentProvider::for_bytes(mime_type.as_str(), &glib::Bytes::from_owned(data))
+                }).collect::<Vec<gdk::ContentProvider>>();
+                let content = gdk::ContentProvider::new_union(&providers);
 
                 if let Err(e) = appwindow.clipboard().set_content(Some(&content)) {
                     log::error!("clipboard set_content() failed in clipboard-copy action, Err {}", e);
                 }
             }
-            Ok(None) => {
+            Ok(_) => {
                 log::debug!("no data available to copy into clipboard.");
             }
             Err(e) => {
@@ -848,7 +1480,35 @@ impl RnoteAppWindow {
             let content_formats = appwindow.clipboard().formats();
 
             // Order matters here, we want to go from specific -> generic, mostly because `text/plain` is contained in many text based formats
-            if content_formats.contain_mime_type("image/svg+xml") {
+            if content_formats.contain_mime_type(RnoteEngine::STROKES_CLIPBOARD_MIME_TYPE) {
+                glib::MainContext::default().spawn_local(clone!(@strong appwindow => async move {
+                    match appwindow.clipboard().read_future(&[RnoteEngine::STROKES_CLIPBOARD_MIME_TYPE], glib::PRIORITY_DEFAULT).await {
+                        Ok((input_stream, _)) => {
+                            let output_stream = gio::MemoryOutputStream::new_resizable();
+
+                            match output_stream.splice_future(
+                                &input_stream,
+                                gio::OutputStreamSpliceFlags::CLOSE_SOURCE | gio::OutputStreamSpliceFlags::CLOSE_TARGET,
+                                glib::PRIORITY_DEFAULT
+                            ).await {
+                                Ok(_) => {
+                                    let widget_flags = appwindow.canvas().engine().borrow_mut().paste_clipboard_content(
+                                        &output_stream.steal_as_bytes(),
+                                        vec![String::from(RnoteEngine::STROKES_CLIPBOARD_MIME_TYPE)]
+                                    );
+                                    appwindow.handle_widget_flags(widget_flags);
+                                }
+                                Err(e) => {
+                                    log::error!("failed to paste clipboard as strokes, splice_future() failed with Err {}", e);
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            log::error!("failed to paste clipboard as strokes, read_future() failed with Err {}", e);
+                        }
+                    }
+                }));
+            } else if content_formats.contain_mime_type("image/svg+xml") {
                 glib::MainContext::default().spawn_local(clone!(@strong appwindow => async move {
                     match appwindow.clipboard().read_text_future().await {
                         Ok(Some(text)) => {
@@ -910,7 +1570,7 @@ impl RnoteAppWindow {
                     glib::MainContext::default().spawn_local(clone!(@strong appwindow => async move {
                         match appwindow.clipboard().read_texture_future().await {
                             Ok(Some(texture)) => {
-                                if let Err(e) = appwindow.load_in_bitmapimage_bytes(texture.save_to_png_bytes().to_vec(), None).await {
+                                if let Err(e) = appwindow.load_in_bitmapimage_bytes(texture.save_to_png_bytes().to_vec(), None, 0).await {
                                     log::error!("failed to paste clipboard as {}, load_in_bitmapimage_bytes() returned Err {}", mime_type, e);
                                 };
                             }
@@ -921,6 +1581,20 @@ impl RnoteAppWindow {
                         };
                     }));
                 }
+            } else if content_formats.contain_mime_type("text/markdown") {
+                glib::MainContext::default().spawn_local(clone!(@strong appwindow => async move {
+                    match appwindow.clipboard().read_text_future().await {
+                        Ok(Some(text)) => {
+                            if let Err(e) = appwindow.load_in_markdown_bytes(text.as_bytes().to_vec(), None).await {
+                                log::error!("failed to paste clipboard as markdown, load_in_markdown_bytes() returned Err, {}", e);
+                            };
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            log::error!("failed to paste clipboard as markdown, read_text() failed with Err {}", e);
+                        }
+                    }
+                }));
             } else if content_formats.contain_mime_type("text/plain") || content_formats.contain_mime_type("text/plain;charset=utf-8"){
                 glib::MainContext::default().spawn_local(clone!(@strong appwindow => async move {
                     match appwindow.clipboard().read_text_future().await {
@@ -949,6 +1623,7 @@ impl RnoteAppWindow {
 
         app.set_accels_for_action("win.close-active", &["<Ctrl>w"]);
         app.set_accels_for_action("win.fullscreen", &["F11"]);
+        app.set_accels_for_action("win.presentation-mode", &["F5"]);
         app.set_accels_for_action("win.keyboard-shortcuts", &["<Ctrl>question"]);
         app.set_accels_for_action("win.open-canvasmenu", &["F9"]);
         app.set_accels_for_action("win.open-appmenu", &["F10"]);
@@ -963,6 +1638,8 @@ impl RnoteAppWindow {
         app.set_accels_for_action("win.redo", &["<Ctrl><Shift>z"]);
         app.set_accels_for_action("win.clipboard-copy", &["<Ctrl>c"]);
         app.set_accels_for_action("win.clipboard-paste", &["<Ctrl>v"]);
+        app.set_accels_for_action("win.selection-copy-style", &["<Ctrl><Alt>c"]);
+        app.set_accels_for_action("win.selection-paste-style", &["<Ctrl><Alt>v"]);
 
         // shortcuts for devel builds
         if config::PROFILE.to_lowercase().as_str() == "devel" {
@@ -4,7 +4,7 @@ mod appwindowactions;
 use std::ops::Range;
 use std::{
     cell::{Cell, RefCell},
-    path::Path,
+    path::{Path, PathBuf},
     rc::Rc,
 };
 
@@ -32,10 +32,12 @@ use crate::{
     {dialogs, mainheader::MainHeader},
 };
 use rnote_engine::{
-    engine::EngineTask,
+    document::Layout,
+    engine::{EngineTask, TextExportFormat},
     pens::penholder::PenStyle,
+    render,
     strokes::{BitmapImage, VectorImage},
-    Camera, WidgetFlags,
+    FileError, WidgetFlags,
 };
 
 mod imp {
@@ -49,10 +51,12 @@ mod imp {
         pub filechoosernative: Rc<RefCell<Option<FileChooserNative>>>,
         pub autosave_source_id: Rc<RefCell<Option<glib::SourceId>>>,
         pub progresspulse_source_id: Rc<RefCell<Option<glib::SourceId>>>,
+        pub flipbook_playback_source_id: Rc<RefCell<Option<glib::SourceId>>>,
 
         pub unsaved_changes: Cell<bool>,
         pub autosave: Cell<bool>,
         pub autosave_interval_secs: Cell<u32>,
+        pub render_threads: Cell<u32>,
         pub righthanded: Cell<bool>,
 
         #[template_child]
@@ -108,10 +112,16 @@ mod imp {
         #[template_child]
         pub narrow_brush_toggle: TemplateChild<ToggleButton>,
         #[template_child]
+        pub narrow_highlighter_toggle: TemplateChild<ToggleButton>,
+        #[template_child]
         pub narrow_shaper_toggle: TemplateChild<ToggleButton>,
         #[template_child]
         pub narrow_eraser_toggle: TemplateChild<ToggleButton>,
         #[template_child]
+        pub narrow_laser_toggle: TemplateChild<ToggleButton>,
+        #[template_child]
+        pub narrow_spline_toggle: TemplateChild<ToggleButton>,
+        #[template_child]
         pub narrow_selector_toggle: TemplateChild<ToggleButton>,
         #[template_child]
         pub narrow_typewriter_toggle: TemplateChild<ToggleButton>,
@@ -128,10 +138,12 @@ mod imp {
                 filechoosernative: Rc::new(RefCell::new(None)),
                 autosave_source_id: Rc::new(RefCell::new(None)),
                 progresspulse_source_id: Rc::new(RefCell::new(None)),
+                flipbook_playback_source_id: Rc::new(RefCell::new(None)),
 
                 unsaved_changes: Cell::new(false),
                 autosave: Cell::new(true),
                 autosave_interval_secs: Cell::new(super::RnoteAppWindow::AUTOSAVE_INTERVAL_DEFAULT),
+                render_threads: Cell::new(0),
                 righthanded: Cell::new(true),
 
                 toast_overlay: TemplateChild::<adw::ToastOverlay>::default(),
@@ -160,9 +172,12 @@ mod imp {
                 mainheader: TemplateChild::<MainHeader>::default(),
                 narrow_pens_toggles_revealer: TemplateChild::<Revealer>::default(),
                 narrow_brush_toggle: TemplateChild::<ToggleButton>::default(),
+                narrow_highlighter_toggle: TemplateChild::<ToggleButton>::default(),
                 narrow_shaper_toggle: TemplateChild::<ToggleButton>::default(),
                 narrow_typewriter_toggle: TemplateChild::<ToggleButton>::default(),
                 narrow_eraser_toggle: TemplateChild::<ToggleButton>::default(),
+                narrow_laser_toggle: TemplateChild::<ToggleButton>::default(),
+                narrow_spline_toggle: TemplateChild::<ToggleButton>::default(),
                 narrow_selector_toggle: TemplateChild::<ToggleButton>::default(),
                 narrow_tools_toggle: TemplateChild::<ToggleButton>::default(),
                 penssidebar: TemplateChild::<PensSideBar>::default(),
@@ -215,6 +230,12 @@ mod imp {
                 }
             }));
 
+            self.narrow_highlighter_toggle.connect_toggled(clone!(@weak obj as appwindow => move |narrow_highlighter_toggle| {
+                if narrow_highlighter_toggle.is_active() {
+                    adw::prelude::ActionGroupExt::activate_action(&appwindow, "pen-style", Some(&PenStyle::Highlighter.nick().to_variant()));
+                }
+            }));
+
             self.narrow_shaper_toggle.connect_toggled(clone!(@weak obj as appwindow => move |narrow_shaper_toggle| {
                 if narrow_shaper_toggle.is_active() {
                     adw::prelude::ActionGroupExt::activate_action(&appwindow, "pen-style", Some(&PenStyle::Shaper.nick().to_variant()));
@@ -233,6 +254,18 @@ mod imp {
                 }
             }));
 
+            self.narrow_laser_toggle.connect_toggled(clone!(@weak obj as appwindow => move |narrow_laser_toggle| {
+                if narrow_laser_toggle.is_active() {
+                    adw::prelude::ActionGroupExt::activate_action(&appwindow, "pen-style", Some(&PenStyle::Laser.nick().to_variant()));
+                }
+            }));
+
+            self.narrow_spline_toggle.connect_toggled(clone!(@weak obj as appwindow => move |narrow_spline_toggle| {
+                if narrow_spline_toggle.is_active() {
+                    adw::prelude::ActionGroupExt::activate_action(&appwindow, "pen-style", Some(&PenStyle::Spline.nick().to_variant()));
+                }
+            }));
+
             self.narrow_selector_toggle.connect_toggled(clone!(@weak obj as appwindow => move |narrow_selector_toggle| {
                 if narrow_selector_toggle.is_active() {
                     adw::prelude::ActionGroupExt::activate_action(&appwindow, "pen-style", Some(&PenStyle::Selector.nick().to_variant()));
@@ -274,6 +307,16 @@ mod imp {
                         super::RnoteAppWindow::AUTOSAVE_INTERVAL_DEFAULT,
                         glib::ParamFlags::READWRITE,
                     ),
+                    // the number of render threads, 0 shares the global thread pool
+                    glib::ParamSpecUInt::new(
+                        "render-threads",
+                        "render-threads",
+                        "render-threads",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READWRITE,
+                    ),
                     // righthanded
                     glib::ParamSpecBoolean::new(
                         "righthanded",
@@ -292,6 +335,7 @@ mod imp {
                 "unsaved-changes" => self.unsaved_changes.get().to_value(),
                 "autosave" => self.autosave.get().to_value(),
                 "autosave-interval-secs" => self.autosave_interval_secs.get().to_value(),
+                "render-threads" => self.render_threads.get().to_value(),
                 "righthanded" => self.righthanded.get().to_value(),
                 _ => unimplemented!(),
             }
@@ -336,6 +380,27 @@ mod imp {
                         self.update_autosave_handler(obj);
                     }
                 }
+                "render-threads" => {
+                    let render_threads = value
+                        .get::<u32>()
+                        .expect("The value needs to be of type `u32`.");
+
+                    self.render_threads.replace(render_threads);
+
+                    let n_threads = (render_threads > 0).then(|| render_threads as usize);
+                    if let Err(e) = obj
+                        .canvas()
+                        .engine()
+                        .borrow_mut()
+                        .store
+                        .set_render_pool_n_threads(n_threads)
+                    {
+                        log::error!(
+                            "set_render_pool_n_threads() failed while setting the render-threads property, Err {}",
+                            e
+                        );
+                    }
+                }
                 "righthanded" => {
                     let righthanded = value
                         .get::<bool>()
@@ -988,6 +1053,10 @@ impl RnoteAppWindow {
         self.imp().narrow_brush_toggle.get()
     }
 
+    pub fn narrow_highlighter_toggle(&self) -> ToggleButton {
+        self.imp().narrow_highlighter_toggle.get()
+    }
+
     pub fn narrow_shaper_toggle(&self) -> ToggleButton {
         self.imp().narrow_shaper_toggle.get()
     }
@@ -1000,6 +1069,14 @@ impl RnoteAppWindow {
         self.imp().narrow_eraser_toggle.get()
     }
 
+    pub fn narrow_laser_toggle(&self) -> ToggleButton {
+        self.imp().narrow_laser_toggle.get()
+    }
+
+    pub fn narrow_spline_toggle(&self) -> ToggleButton {
+        self.imp().narrow_spline_toggle.get()
+    }
+
     pub fn narrow_selector_toggle(&self) -> ToggleButton {
         self.imp().narrow_selector_toggle.get()
     }
@@ -1024,6 +1101,9 @@ impl RnoteAppWindow {
         self.imp().penssidebar.get().shaper_page().init(self);
         self.imp().penssidebar.get().typewriter_page().init(self);
         self.imp().penssidebar.get().eraser_page().init(self);
+        self.imp().penssidebar.get().highlighter_page().init(self);
+        self.imp().penssidebar.get().laser_page().init(self);
+        self.imp().penssidebar.get().spline_page().init(self);
         self.imp().penssidebar.get().selector_page().init(self);
         self.imp().penssidebar.get().tools_page().init(self);
         self.imp().canvas.get().init(self);
@@ -1105,7 +1185,7 @@ impl RnoteAppWindow {
         {
             canvas_zoom_scroll_controller.connect_scroll(clone!(@weak self as appwindow => @default-return Inhibit(false), move |zoom_scroll_controller, _dx, dy| {
                 if zoom_scroll_controller.current_event_state() == gdk::ModifierType::CONTROL_MASK {
-                    let new_zoom = appwindow.canvas().engine().borrow().camera.total_zoom() * (1.0 - dy * RnoteCanvas::ZOOM_STEP);
+                    let new_zoom = appwindow.canvas().engine().borrow().camera.total_zoom() * (1.0 - dy * appwindow.canvas().engine().borrow().camera.zoom_config().zoom_step);
 
                     let current_doc_center = appwindow.canvas().current_center_on_doc();
                     adw::prelude::ActionGroupExt::activate_action(&appwindow, "zoom-to-value", Some(&new_zoom.to_variant()));
@@ -1225,7 +1305,9 @@ impl RnoteAppWindow {
                 @strong bbcenter_begin,
                 @strong adjs_begin,
                 @weak self as appwindow => move |canvas_zoom_gesture, scale| {
-                    if zoom_begin.get() * scale <= Camera::ZOOM_MAX && zoom_begin.get() * scale >= Camera::ZOOM_MIN {
+                    let zoom_config = *appwindow.canvas().engine().borrow().camera.zoom_config();
+
+                    if zoom_begin.get() * scale <= zoom_config.zoom_max() && zoom_begin.get() * scale >= zoom_config.zoom_min() {
                         new_zoom.set(zoom_begin.get() * scale);
                         prev_scale.set(scale);
                     }
@@ -1299,8 +1381,13 @@ impl RnoteAppWindow {
         }
         if widget_flags.update_view {
             let camera_offset = self.canvas().engine().borrow().camera.offset;
+            let total_zoom = self.canvas().engine().borrow().camera.total_zoom();
             // this updates the canvas adjustment values with the ones from the camera
             self.canvas().update_camera_offset(camera_offset);
+            self.mainheader()
+                .canvasmenu()
+                .zoomreset_button()
+                .set_label(format!("{:.0}%", (100.0 * total_zoom).round()).as_str());
         }
         if let Some(hide_scrollbars) = widget_flags.hide_scrollbars {
             if hide_scrollbars {
@@ -1311,11 +1398,15 @@ impl RnoteAppWindow {
                     .set_policy(PolicyType::Automatic, PolicyType::Automatic);
             }
         }
+        // Undo and redo stay insensitive while syncing
+        let history_available = self.canvas().engine().borrow().history_available();
         if let Some(hide_undo) = widget_flags.hide_undo {
-            self.undo_button().set_sensitive(!hide_undo);
+            self.undo_button()
+                .set_sensitive(!hide_undo && history_available);
         }
         if let Some(hide_redo) = widget_flags.hide_redo {
-            self.redo_button().set_sensitive(!hide_redo);
+            self.redo_button()
+                .set_sensitive(!hide_redo && history_available);
         }
 
         widget_flags.quit
@@ -1371,6 +1462,51 @@ impl RnoteAppWindow {
         self.canvas_progressbar().set_fraction(0.0);
     }
 
+    /// Starts the flipbook playback, replacing the playback timeout if there already is one
+    pub fn start_flipbook_playback(&self) {
+        self.canvas()
+            .engine()
+            .borrow_mut()
+            .flipbook_start_playback();
+        let frame_duration = self.canvas().engine().borrow().flipbook.frame_duration();
+
+        if let Some(old_playback_source) = self.imp().flipbook_playback_source_id.replace(Some(glib::source::timeout_add_local(
+            frame_duration,
+            clone!(@weak self as appwindow => @default-return glib::source::Continue(false), move || {
+                let frame_bounds = appwindow.canvas().engine().borrow_mut().flipbook_advance_frame();
+
+                match frame_bounds {
+                    Some(frame_bounds) => {
+                        appwindow.canvas().center_around_coord_on_doc(frame_bounds.center().coords);
+                        appwindow.canvas().update_engine_rendering();
+
+                        glib::source::Continue(true)
+                    }
+                    None => {
+                        // The playback has ended. The source is removed by returning, so it must not be removed again
+                        appwindow.imp().flipbook_playback_source_id.take();
+                        if let Some(action_flipbook_playback) = appwindow.lookup_action("flipbook-playback").and_then(|action| action.downcast::<gio::SimpleAction>().ok()) {
+                            action_flipbook_playback.set_state(&false.to_variant());
+                        }
+
+                        glib::source::Continue(false)
+                    }
+                }
+            })),
+        )) {
+            old_playback_source.remove();
+        }
+    }
+
+    /// Stops the flipbook playback and removes the playback timeout
+    pub fn stop_flipbook_playback(&self) {
+        if let Some(playback_source) = self.imp().flipbook_playback_source_id.take() {
+            playback_source.remove();
+        }
+
+        self.canvas().engine().borrow_mut().flipbook_stop_playback();
+    }
+
     pub fn open_file_w_dialogs(&self, file: &gio::File, target_pos: Option<na::Vector2<f64>>) {
         let app = self.application().unwrap().downcast::<RnoteApp>().unwrap();
         match utils::FileType::lookup_file_type(file) {
@@ -1387,9 +1523,11 @@ impl RnoteAppWindow {
                     );
                 }
             }
-            utils::FileType::VectorImageFile | utils::FileType::BitmapImageFile => {
+            utils::FileType::VectorImageFile
+            | utils::FileType::BitmapImageFile
+            | utils::FileType::MarkdownFile => {
                 if let Err(e) = self.load_in_file(file, target_pos) {
-                    log::error!("failed to load in file with FileType::VectorImageFile / FileType::BitmapImageFile / FileType::Pdf, {}", e);
+                    log::error!("failed to load in file with FileType::VectorImageFile / FileType::BitmapImageFile / FileType::MarkdownFile, {}", e);
                 }
             }
             utils::FileType::PdfFile => {
@@ -1427,12 +1565,8 @@ impl RnoteAppWindow {
                     let result = file.load_bytes_future().await;
 
                     if let Ok((file_bytes, _)) = result {
-                        if let Err(e) = appwindow.load_in_rnote_bytes(file_bytes.to_vec(), file.path()).await {
-                            adw::prelude::ActionGroupExt::activate_action(&appwindow, "error-toast", Some(&gettext("Opening .rnote file failed.").to_variant()));
-                            log::error!(
-                                "load_in_rnote_bytes() failed in load_in_file() with Err {}",
-                                e
-                            );
+                        if let Err(e) = appwindow.load_in_rnote_bytes(file_bytes.to_vec(), file.path(), None).await {
+                            appwindow.handle_rnote_load_error(e, file_bytes.to_vec(), file.path());
                         }
                     }
 
@@ -1484,7 +1618,18 @@ impl RnoteAppWindow {
                     let result = file.load_bytes_future().await;
 
                     if let Ok((file_bytes, _)) = result {
-                        if let Err(e) = appwindow.load_in_bitmapimage_bytes(file_bytes.to_vec(), target_pos).await {
+                        // Animated images ask which frame is imported
+                        match render::Image::count_animation_frames(&file_bytes) {
+                            Ok(n_frames) if n_frames > 1 => {
+                                dialogs::dialog_import_image_frame(&appwindow, file_bytes.to_vec(), n_frames, target_pos);
+                                appwindow.finish_canvas_progressbar();
+                                return;
+                            }
+                            Ok(_) => {}
+                            Err(e) => log::warn!("count_animation_frames() failed in load_in_file() with Err {}", e),
+                        }
+
+                        if let Err(e) = appwindow.load_in_bitmapimage_bytes(file_bytes.to_vec(), target_pos, 0).await {
                             adw::prelude::ActionGroupExt::activate_action(&appwindow, "error-toast", Some(&gettext("Opening bitmap image file failed.").to_variant()));
                             log::error!(
                                 "load_in_rnote_bytes() failed in load_in_file() with Err {}",
@@ -1515,6 +1660,25 @@ impl RnoteAppWindow {
                     appwindow.finish_canvas_progressbar();
                 }));
             }
+            utils::FileType::MarkdownFile => {
+                main_cx.spawn_local(clone!(@strong self as appwindow => async move {
+                    appwindow.start_pulsing_canvas_progressbar();
+
+                    let result = file.load_bytes_future().await;
+
+                    if let Ok((file_bytes, _)) = result {
+                        if let Err(e) = appwindow.load_in_markdown_bytes(file_bytes.to_vec(), target_pos).await {
+                            adw::prelude::ActionGroupExt::activate_action(&appwindow, "error-toast", Some(&gettext("Opening markdown file failed.").to_variant()));
+                            log::error!(
+                                "load_in_markdown_bytes() failed in load_in_file() with Err {}",
+                                e
+                            );
+                        }
+                    }
+
+                    appwindow.finish_canvas_progressbar();
+                }));
+            }
             utils::FileType::Folder => {
                 app.set_input_file(None);
                 log::error!("tried to open a folder as a file.");
@@ -1538,10 +1702,12 @@ impl RnoteAppWindow {
         Ok(())
     }
 
+    /// Loads in a .rnote file. Encrypted files need the passphrase, which is then kept to encrypt the file again when saving
     pub async fn load_in_rnote_bytes<P>(
         &self,
         bytes: Vec<u8>,
         path: Option<P>,
+        passphrase: Option<String>,
     ) -> anyhow::Result<()>
     where
         P: AsRef<Path>,
@@ -1552,7 +1718,7 @@ impl RnoteAppWindow {
             .canvas()
             .engine()
             .borrow_mut()
-            .open_from_rnote_bytes_p1(bytes)?;
+            .open_from_rnote_bytes_p1(bytes, passphrase.as_deref())?;
 
         let store_snapshot = store_snapshot_receiver.await??;
 
@@ -1567,6 +1733,7 @@ impl RnoteAppWindow {
             let file = gio::File::for_path(path);
             self.canvas().set_output_file(Some(file));
         }
+        self.canvas().set_output_passphrase(passphrase);
 
         self.canvas().set_unsaved_changes(false);
         self.canvas().set_empty(false);
@@ -1581,6 +1748,26 @@ impl RnoteAppWindow {
         Ok(())
     }
 
+    /// Asks for the passphrase when loading the .rnote file failed because it is encrypted, else reports the error
+    pub fn handle_rnote_load_error(&self, e: anyhow::Error, bytes: Vec<u8>, path: Option<PathBuf>) {
+        match e.downcast_ref::<FileError>() {
+            Some(FileError::PasswordRequired) => {
+                dialogs::dialog_open_encrypted_doc(self, bytes, path, false)
+            }
+            Some(FileError::WrongPassword) => {
+                dialogs::dialog_open_encrypted_doc(self, bytes, path, true)
+            }
+            _ => {
+                adw::prelude::ActionGroupExt::activate_action(
+                    self,
+                    "error-toast",
+                    Some(&gettext("Opening .rnote file failed.").to_variant()),
+                );
+                log::error!("loading .rnote file failed with Err {}", e);
+            }
+        }
+    }
+
     pub fn load_in_xopp_bytes<P>(&self, bytes: Vec<u8>, _path: Option<P>) -> anyhow::Result<()>
     where
         P: AsRef<Path>,
@@ -1596,6 +1783,7 @@ impl RnoteAppWindow {
             .unwrap()
             .set_input_file(None);
         self.canvas().set_output_file(None);
+        self.canvas().set_output_passphrase(None);
 
         self.canvas().set_unsaved_changes(true);
         self.canvas().set_empty(false);
@@ -1645,12 +1833,13 @@ impl RnoteAppWindow {
         Ok(())
     }
 
-    /// Target position is in the coordinate space of the doc
+    /// Target position is in the coordinate space of the doc. Still images only have the frame with index 0
     pub async fn load_in_bitmapimage_bytes(
         &self,
         bytes: Vec<u8>,
         // In the coordinate space of the doc
         target_pos: Option<na::Vector2<f64>>,
+        frame_index: usize,
     ) -> anyhow::Result<()> {
         let app = self.application().unwrap().downcast::<RnoteApp>().unwrap();
 
@@ -1664,7 +1853,7 @@ impl RnoteAppWindow {
             .canvas()
             .engine()
             .borrow_mut()
-            .generate_bitmapimage_from_bytes(pos, bytes);
+            .generate_bitmapimage_from_bytes_w_frame(pos, bytes, frame_index);
         let bitmapimage = bitmapimage_receiver.await??;
 
         let widget_flags = self
@@ -1679,6 +1868,40 @@ impl RnoteAppWindow {
         Ok(())
     }
 
+    /// Imports markdown as formatted text stroke. Target position is in the coordinate space of the doc
+    pub async fn load_in_markdown_bytes(
+        &self,
+        bytes: Vec<u8>,
+        // In the coordinate space of the doc
+        target_pos: Option<na::Vector2<f64>>,
+    ) -> anyhow::Result<()> {
+        let app = self.application().unwrap().downcast::<RnoteApp>().unwrap();
+
+        let pos = target_pos.unwrap_or_else(|| {
+            (self.canvas().engine().borrow().camera.transform().inverse()
+                * na::Point2::from(VectorImage::IMPORT_OFFSET_DEFAULT))
+            .coords
+        });
+
+        let textstroke_receiver = self
+            .canvas()
+            .engine()
+            .borrow_mut()
+            .generate_textstroke_from_markdown_bytes(pos, bytes);
+        let textstroke = textstroke_receiver.await??;
+
+        let widget_flags = self
+            .canvas()
+            .engine()
+            .borrow_mut()
+            .import_generated_strokes(vec![(Stroke::TextStroke(textstroke), None)]);
+        self.handle_widget_flags(widget_flags);
+
+        app.set_input_file(None);
+
+        Ok(())
+    }
+
     /// Target position is in the coordinate space of the doc
     pub async fn load_in_pdf_bytes(
         &self,
@@ -1716,11 +1939,26 @@ impl RnoteAppWindow {
 
     pub async fn save_document_to_file(&self, file: &gio::File) -> anyhow::Result<()> {
         if let Some(basename) = file.basename() {
-            let rnote_bytes_receiver = self
-                .canvas()
+            let remove_empty_pages = {
+                let engine = self.canvas().engine();
+                let engine = engine.borrow();
+                engine.remove_empty_pages_on_save && engine.doc_layout() == Layout::FixedSize
+            };
+            if remove_empty_pages {
+                let widget_flags = self.canvas().engine().borrow_mut().remove_empty_pages()?;
+                self.handle_widget_flags(widget_flags);
+                self.canvas().update_engine_rendering();
+            }
+
+            self.canvas()
                 .engine()
-                .borrow()
-                .save_as_rnote_bytes(basename.to_string_lossy().to_string())?;
+                .borrow_mut()
+                .touch_metadata(config::APP_VERSION);
+
+            let rnote_bytes_receiver = self.canvas().engine().borrow().save_as_rnote_bytes(
+                basename.to_string_lossy().to_string(),
+                self.canvas().output_passphrase(),
+            )?;
 
             utils::replace_file_future(rnote_bytes_receiver.await??, file).await?;
 
@@ -1780,6 +2018,24 @@ impl RnoteAppWindow {
         Ok(())
     }
 
+    /// exports the doc as JPEG, fitting it into the size budget ( in bytes )
+    pub async fn export_doc_as_bitmapimage_w_size_target(
+        &self,
+        file: &gio::File,
+        max_bytes: usize,
+        with_background: bool,
+    ) -> anyhow::Result<()> {
+        let bitmapimage_data = self
+            .canvas()
+            .engine()
+            .borrow()
+            .export_doc_as_bitmapimage_bytes_w_size_target(max_bytes, with_background)?;
+
+        utils::replace_file_future(bitmapimage_data, file).await?;
+
+        Ok(())
+    }
+
     pub async fn export_selection_as_bitmapimage(
         &self,
         file: &gio::File,
@@ -1798,6 +2054,25 @@ impl RnoteAppWindow {
         Ok(())
     }
 
+    /// exports the selection as JPEG, fitting it into the size budget ( in bytes )
+    pub async fn export_selection_as_bitmapimage_w_size_target(
+        &self,
+        file: &gio::File,
+        max_bytes: usize,
+        with_background: bool,
+    ) -> anyhow::Result<()> {
+        if let Some(bitmapimage_data) = self
+            .canvas()
+            .engine()
+            .borrow()
+            .export_selection_as_bitmapimage_bytes_w_size_target(max_bytes, with_background)?
+        {
+            utils::replace_file_future(bitmapimage_data, file).await?;
+        }
+
+        Ok(())
+    }
+
     pub async fn export_doc_as_xopp(&self, file: &gio::File) -> anyhow::Result<()> {
         if let Some(basename) = file.basename() {
             let bytes = self
@@ -1831,6 +2106,37 @@ impl RnoteAppWindow {
         Ok(())
     }
 
+    /// exports the content of the text strokes as markdown or plain text
+    pub async fn export_doc_as_text(
+        &self,
+        file: &gio::File,
+        format: TextExportFormat,
+    ) -> anyhow::Result<()> {
+        let text = self.canvas().engine().borrow().export_doc_as_text(format);
+
+        utils::replace_file_future(text.into_bytes(), file).await?;
+
+        Ok(())
+    }
+
+    /// exports the pages of the doc as frames of an animated GIF
+    pub async fn export_flipbook_as_gif(
+        &self,
+        file: &gio::File,
+        with_background: bool,
+    ) -> anyhow::Result<()> {
+        let gif_data_receiver = self
+            .canvas()
+            .engine()
+            .borrow()
+            .export_flipbook_as_gif_bytes(with_background)?;
+        let bytes = gif_data_receiver.await??;
+
+        utils::replace_file_future(bytes, file).await?;
+
+        Ok(())
+    }
+
     /// exports and writes the engine state as json into the file.
     /// Only for debugging!
     pub async fn export_engine_state(&self, file: &gio::File) -> anyhow::Result<()> {
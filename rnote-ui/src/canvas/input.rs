@@ -1,9 +1,10 @@
-use gtk4::{gdk, prelude::*, GestureDrag, GestureStylus};
+use gtk4::{gdk, glib, glib::clone, prelude::*, subclass::prelude::*, GestureDrag, GestureStylus};
 use rnote_compose::penhelpers::KeyboardKey;
 use rnote_compose::penhelpers::PenEvent;
 use rnote_compose::penhelpers::ShortcutKey;
 use rnote_compose::penpath::Element;
 use rnote_engine::pens::PenMode;
+use rnote_engine::strokes::textstroke::LinkTarget;
 use rnote_engine::WidgetFlags;
 use std::collections::VecDeque;
 
@@ -235,12 +236,14 @@ pub fn process_pen_down(
     widget_flags.merge_with_other(appwindow.canvas().engine().borrow_mut().handle_pen_event(
         PenEvent::Down {
             element,
-            shortcut_keys,
+            shortcut_keys: shortcut_keys.clone(),
         },
         pen_mode,
     ));
 
     appwindow.handle_widget_flags(widget_flags);
+
+    update_autoscroll(element, shortcut_keys, pen_mode, appwindow);
 }
 
 /// Process "Pen up"
@@ -284,6 +287,19 @@ pub fn process_pen_up(
         return;
     }
 
+    stop_autoscroll(appwindow);
+
+    // Links are only activated while presenting. Urls are opened here, the engine jumps to the other targets
+    let (link_target, link_widget_flags) = appwindow
+        .canvas()
+        .engine()
+        .borrow_mut()
+        .activate_link_at(element.pos);
+    widget_flags.merge_with_other(link_widget_flags);
+    if let Some(LinkTarget::Url(url)) = link_target {
+        gtk4::show_uri(Some(appwindow), &url, 0);
+    }
+
     // Handle all other events as pen up
     widget_flags.merge_with_other(appwindow.canvas().engine().borrow_mut().handle_pen_event(
         PenEvent::Up {
@@ -296,6 +312,90 @@ pub fn process_pen_up(
     appwindow.handle_widget_flags(widget_flags);
 }
 
+/// Starts auto-scrolling when the pen is drawing near the viewport border, stops it when it moved away.
+/// While auto-scrolling, the pen is fed with the corrected coordinates of its unchanged position on the surface as the camera moves.
+fn update_autoscroll(
+    element: Element,
+    shortcut_keys: Vec<ShortcutKey>,
+    pen_mode: Option<PenMode>,
+    appwindow: &RnoteAppWindow,
+) {
+    let canvas = appwindow.canvas();
+
+    if !canvas.engine().borrow().autoscroll_active() {
+        stop_autoscroll(appwindow);
+        return;
+    }
+
+    let surface_pos =
+        (canvas.engine().borrow().camera.transform() * na::Point2::from(element.pos)).coords;
+    if canvas
+        .engine()
+        .borrow()
+        .camera
+        .autoscroll_delta(surface_pos)
+        == na::Vector2::zeros()
+    {
+        stop_autoscroll(appwindow);
+        return;
+    }
+
+    canvas.imp().autoscroll_pen.replace(Some((
+        Element::new(surface_pos, element.pressure),
+        shortcut_keys,
+        pen_mode,
+    )));
+
+    // The running tick callback picks up the updated pen
+    if canvas.imp().autoscroll_tick_id.borrow().is_some() {
+        return;
+    }
+
+    let tick_id = canvas.add_tick_callback(
+        clone!(@weak appwindow => @default-return glib::Continue(false), move |canvas, _frame_clock| {
+            let autoscroll_pen = canvas.imp().autoscroll_pen.borrow().clone();
+            let (surface_element, shortcut_keys, pen_mode) = match autoscroll_pen {
+                Some(autoscroll_pen) => autoscroll_pen,
+                None => {
+                    canvas.imp().autoscroll_tick_id.take();
+                    return glib::Continue(false);
+                }
+            };
+
+            let old_offset = canvas.engine().borrow().camera.offset;
+            let delta = canvas.engine().borrow().camera.autoscroll_delta(surface_element.pos);
+            canvas.update_camera_offset(old_offset + delta);
+
+            // The adjustments are bound to the document dimensions of the current layout, so we take over their clamped values
+            let new_offset = na::vector![
+                canvas.hadjustment().unwrap().value(),
+                canvas.vadjustment().unwrap().value()
+            ];
+            canvas.engine().borrow_mut().camera.offset = new_offset;
+
+            if new_offset != old_offset {
+                let doc_pos = canvas.engine().borrow().camera.transform().inverse()
+                    * na::Point2::from(surface_element.pos);
+
+                process_pen_down(
+                    Element::new(doc_pos.coords, surface_element.pressure),
+                    shortcut_keys,
+                    pen_mode,
+                    &appwindow,
+                );
+            }
+
+            glib::Continue(true)
+        }),
+    );
+    canvas.imp().autoscroll_tick_id.replace(Some(tick_id));
+}
+
+/// Stops auto-scrolling. The tick callback then ends itself on the next frame
+fn stop_autoscroll(appwindow: &RnoteAppWindow) {
+    appwindow.canvas().imp().autoscroll_pen.take();
+}
+
 /// Process "Pen proximity"
 pub fn process_pen_proximity(
     element: Element,
@@ -316,6 +416,23 @@ pub fn process_pen_proximity(
     appwindow.handle_widget_flags(widget_flags);
 }
 
+/// Process a scroll event, e.g. from the mouse wheel
+pub fn process_pen_scroll(
+    delta: na::Vector2<f64>,
+    shortcut_keys: Vec<ShortcutKey>,
+    appwindow: &RnoteAppWindow,
+) {
+    let widget_flags = appwindow.canvas().engine().borrow_mut().handle_pen_event(
+        PenEvent::Scroll {
+            delta,
+            shortcut_keys,
+        },
+        None,
+    );
+
+    appwindow.handle_widget_flags(widget_flags);
+}
+
 /// Process shortcut key pressed
 #[allow(unused)]
 pub fn process_shortcut_key_pressed(shortcut_key: ShortcutKey, appwindow: &RnoteAppWindow) {
@@ -10,19 +10,23 @@ use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
 use crate::config;
+use rnote_engine::engine::EngineTask;
 use rnote_engine::RnoteEngine;
 
 use gtk4::{
     gdk, gio, glib, glib::clone, graphene, prelude::*, subclass::prelude::*, AccessibleRole,
-    Adjustment, DropTarget, EventControllerKey, EventSequenceState, GestureDrag, GestureStylus,
-    Inhibit, PropagationPhase, Scrollable, ScrollablePolicy, Widget,
+    Adjustment, DropTarget, EventControllerKey, EventControllerScroll, EventControllerScrollFlags,
+    EventSequenceState, GestureDrag, GestureStylus, Inhibit, PropagationPhase, Scrollable,
+    ScrollablePolicy, Widget,
 };
 
 use crate::appwindow::RnoteAppWindow;
 use futures::StreamExt;
+use gettextrs::gettext;
 use once_cell::sync::Lazy;
 use p2d::bounding_volume::AABB;
 use rnote_compose::helpers::AABBHelpers;
+use rnote_compose::penhelpers::ShortcutKey;
 use rnote_compose::penpath::Element;
 use rnote_engine::utils::GrapheneRectHelpers;
 use rnote_engine::Document;
@@ -44,16 +48,23 @@ mod imp {
         pub hscroll_policy: Cell<ScrollablePolicy>,
         pub vscroll_policy: Cell<ScrollablePolicy>,
         pub zoom_timeout_id: RefCell<Option<glib::SourceId>>,
+        pub camera_animation_tick_id: RefCell<Option<gtk4::TickCallbackId>>,
+        pub autoscroll_tick_id: RefCell<Option<gtk4::TickCallbackId>>,
+        /// The pen element in surface coords, the shortcut keys and the pen mode while auto-scrolling
+        pub autoscroll_pen: RefCell<Option<(Element, Vec<ShortcutKey>, Option<PenMode>)>>,
         pub cursor: gdk::Cursor,
         pub motion_cursor: gdk::Cursor,
         pub stylus_drawing_gesture: GestureStylus,
         pub mouse_drawing_gesture: GestureDrag,
         pub touch_drawing_gesture: GestureDrag,
         pub key_controller: EventControllerKey,
+        pub pen_scroll_controller: EventControllerScroll,
 
         pub engine: Rc<RefCell<RnoteEngine>>,
 
         pub output_file: RefCell<Option<gio::File>>,
+        /// The passphrase the output file is encrypted with. None saves it unencrypted
+        pub output_passphrase: RefCell<Option<String>>,
         pub unsaved_changes: Cell<bool>,
         pub empty: Cell<bool>,
 
@@ -88,6 +99,12 @@ mod imp {
                 .propagation_phase(PropagationPhase::Capture)
                 .build();
 
+            let pen_scroll_controller = EventControllerScroll::builder()
+                .name("pen_scroll_controller")
+                .flags(EventControllerScrollFlags::VERTICAL)
+                .propagation_phase(PropagationPhase::Bubble)
+                .build();
+
             // Gesture grouping
             mouse_drawing_gesture.group_with(&stylus_drawing_gesture);
             touch_drawing_gesture.group_with(&stylus_drawing_gesture);
@@ -127,11 +144,16 @@ mod imp {
                 mouse_drawing_gesture,
                 touch_drawing_gesture,
                 key_controller,
+                pen_scroll_controller,
                 zoom_timeout_id: RefCell::new(None),
+                camera_animation_tick_id: RefCell::new(None),
+                autoscroll_tick_id: RefCell::new(None),
+                autoscroll_pen: RefCell::new(None),
 
                 engine: Rc::new(RefCell::new(engine)),
 
                 output_file: RefCell::new(None),
+                output_passphrase: RefCell::new(None),
                 unsaved_changes: Cell::new(false),
                 empty: Cell::new(true),
 
@@ -172,6 +194,7 @@ mod imp {
             obj.add_controller(&self.mouse_drawing_gesture);
             obj.add_controller(&self.touch_drawing_gesture);
             obj.add_controller(&self.key_controller);
+            obj.add_controller(&self.pen_scroll_controller);
         }
 
         fn dispose(&self, obj: &Self::Type) {
@@ -360,8 +383,8 @@ impl RnoteCanvas {
     pub const ZOOM_ACTION_DELTA: f64 = 0.1;
     // the zoom timeout time
     pub const ZOOM_TIMEOUT_TIME: time::Duration = time::Duration::from_millis(300);
-    // Sets the canvas zoom scroll step in % for one unit of the event controller delta
-    pub const ZOOM_STEP: f64 = 0.1;
+    /// The rotation angle in radians when activating the rotate-canvas actions
+    pub const ROTATION_STEP: f64 = std::f64::consts::PI / 12.0;
 
     pub fn new() -> Self {
         let canvas: RnoteCanvas = glib::Object::new(&[]).expect("Failed to create RnoteCanvas");
@@ -390,6 +413,15 @@ impl RnoteCanvas {
         self.set_property("output-file", output_file.to_value());
     }
 
+    /// The passphrase the output file is encrypted with. None saves it unencrypted
+    pub fn output_passphrase(&self) -> Option<String> {
+        self.imp().output_passphrase.borrow().clone()
+    }
+
+    pub fn set_output_passphrase(&self, output_passphrase: Option<String>) {
+        *self.imp().output_passphrase.borrow_mut() = output_passphrase;
+    }
+
     pub fn unsaved_changes(&self) -> bool {
         self.property::<bool>("unsaved-changes")
     }
@@ -460,6 +492,17 @@ impl RnoteCanvas {
 
                 loop {
                     if let Some(task) = task_rx.next().await {
+                        if let EngineTask::TaskTimedOut { elapsed, aborted, .. } = &task {
+                            let text = if *aborted {
+                                gettext("An import or export took longer than {}s and was aborted")
+                            } else {
+                                gettext("An import or export is taking longer than {}s")
+                            }
+                            .replace("{}", &format!("{:.0}", elapsed.as_secs_f64()));
+
+                            adw::prelude::ActionGroupExt::activate_action(&appwindow, "text-toast", Some(&text.to_variant()));
+                        }
+
                         let widget_flags = canvas.engine().borrow_mut().process_received_task(task);
                         if appwindow.handle_widget_flags(widget_flags) {
                             break;
@@ -711,6 +754,20 @@ impl RnoteCanvas {
             Inhibit(true)
         }));
 
+        // Pen scroll controller. Only inhibits the scroll when the pens are adjusted with it, else the canvas is scrolled
+
+        self.imp().pen_scroll_controller.connect_scroll(clone!(@weak appwindow => @default-return Inhibit(false), move |pen_scroll_controller, dx, dy| {
+            let shortcut_keys = input::retreive_modifier_shortcut_key(pen_scroll_controller.current_event_state());
+
+            if !appwindow.canvas().engine().borrow().penholder.scroll_adjusts_pen(&shortcut_keys) {
+                return Inhibit(false);
+            }
+
+            input::process_pen_scroll(na::vector![dx, dy], shortcut_keys, &appwindow);
+
+            Inhibit(true)
+        }));
+
         /*
         self.imp().key_controller.connect_key_released(clone!(@weak self as canvas, @weak appwindow => move |_key_controller, _key, _raw, _modifier| {
             //log::debug!("key released - key: {:?}, raw: {:?}, modifier: {:?}", key, raw, modifier);
@@ -803,9 +860,27 @@ impl RnoteCanvas {
     /// Centering the view to the origin page
     // update_engine_rendering() then needs to be called.
     pub fn return_to_origin_page(&self) {
+        let new_offset = self.origin_page_offset();
+
+        self.update_camera_offset(new_offset);
+    }
+
+    /// The center on the doc when the view is returned to the origin page
+    pub fn origin_page_center_on_doc(&self) -> na::Vector2<f64> {
         let zoom = self.engine().borrow().camera.zoom();
+        let parent_size = na::vector![
+            f64::from(self.parent().unwrap().width()),
+            f64::from(self.parent().unwrap().height())
+        ];
+
+        (self.origin_page_offset() + parent_size * 0.5) / zoom
+    }
 
-        let new_offset = if self.engine().borrow().document.format.width * zoom
+    /// The camera offset for the view on the origin page
+    fn origin_page_offset(&self) -> na::Vector2<f64> {
+        let zoom = self.engine().borrow().camera.zoom();
+
+        if self.engine().borrow().document.format.width * zoom
             <= f64::from(self.parent().unwrap().width())
         {
             na::vector![
@@ -819,9 +894,62 @@ impl RnoteCanvas {
                 -Document::SHADOW_WIDTH * zoom,
                 -Document::SHADOW_WIDTH * zoom
             ]
-        };
+        }
+    }
 
-        self.update_camera_offset(new_offset);
+    /// Glides the view smoothly to the target center on the doc and the target zoom.
+    /// The zoom is made permanent when the animation has finished.
+    pub fn animate_camera_to(&self, target_center: na::Vector2<f64>, target_zoom: f64) {
+        if let Some(zoom_timeout_id) = self.imp().zoom_timeout_id.take() {
+            zoom_timeout_id.remove();
+        }
+
+        self.engine()
+            .borrow_mut()
+            .animate_camera_to(target_center, target_zoom);
+
+        // The running tick callback steps the replaced animation
+        if self.imp().camera_animation_tick_id.borrow().is_some() {
+            return;
+        }
+
+        let tick_id = self.add_tick_callback(|canvas, frame_clock| {
+            let step = canvas
+                .engine()
+                .borrow_mut()
+                .tick_camera_animation(frame_clock.frame_time());
+
+            match step {
+                Some(step) => {
+                    canvas.hadjustment().unwrap().set_value(step.offset[0]);
+                    canvas.vadjustment().unwrap().set_value(step.offset[1]);
+                    canvas.queue_resize();
+
+                    if step.finished {
+                        canvas.imp().camera_animation_tick_id.take();
+                        canvas.zoom_to(step.zoom);
+
+                        glib::Continue(false)
+                    } else {
+                        glib::Continue(true)
+                    }
+                }
+                None => {
+                    canvas.imp().camera_animation_tick_id.take();
+
+                    glib::Continue(false)
+                }
+            }
+        });
+        self.imp().camera_animation_tick_id.replace(Some(tick_id));
+    }
+
+    /// Glides the camera so that the given bounds in document coords fit into the view. Returns the new zoom.
+    pub fn animate_zoom_fit_to_bounds(&self, bounds: AABB) -> f64 {
+        let new_zoom = self.engine().borrow().camera.zoom_fit_bounds(bounds);
+        self.animate_camera_to(bounds.center().coords, new_zoom);
+
+        new_zoom
     }
 
     /// zooms and regenerates the canvas and its contents to a new zoom
@@ -862,6 +990,7 @@ impl RnoteCanvas {
         if let Some(zoom_timeout_id) = self.imp().zoom_timeout_id.take() {
             zoom_timeout_id.remove();
         }
+        self.engine().borrow_mut().stop_camera_animation();
 
         let old_perm_zoom = self.engine().borrow().camera.zoom();
 
@@ -3,11 +3,14 @@ use gettextrs::gettext;
 use gtk4::MenuButton;
 use gtk4::{
     gio, glib, glib::clone, AboutDialog, Builder, Button, ColorButton, Dialog, Entry,
-    FileChooserAction, FileChooserNative, FileFilter, Label, MessageDialog, ResponseType,
-    ShortcutsWindow, SpinButton, StringList, ToggleButton,
+    FileChooserAction, FileChooserNative, FileFilter, Label, MessageDialog, PasswordEntry,
+    ResponseType, ShortcutsWindow, SpinButton, StringList, ToggleButton,
 };
 use num_traits::ToPrimitive;
+use rnote_engine::document::background::{CustomPattern, PatternStyle};
+use rnote_engine::engine::TextExportFormat;
 use rnote_engine::import::{PdfImportPageSpacing, PdfImportPagesType, PdfImportPrefs};
+use std::path::PathBuf;
 
 use crate::appwindow::RnoteAppWindow;
 use crate::workspacebrowser::WorkspaceRow;
@@ -110,6 +113,7 @@ pub fn dialog_new_doc(appwindow: &RnoteAppWindow) {
                 appwindow.canvas().set_empty(true);
                 appwindow.application().unwrap().downcast::<RnoteApp>().unwrap().set_input_file(None);
                 appwindow.canvas().set_output_file(None);
+                appwindow.canvas().set_output_passphrase(None);
             },
             ResponseType::Apply => {
                 dialog_new_doc.close();
@@ -187,6 +191,140 @@ pub fn dialog_open_overwrite(appwindow: &RnoteAppWindow) {
     dialog_open_input_file.show();
 }
 
+/// Asks for the passphrase of an encrypted .rnote file and loads it in
+pub fn dialog_open_encrypted_doc(
+    appwindow: &RnoteAppWindow,
+    bytes: Vec<u8>,
+    path: Option<PathBuf>,
+    wrong_passphrase: bool,
+) {
+    let builder =
+        Builder::from_resource((String::from(config::APP_IDPATH) + "ui/dialogs.ui").as_str());
+    let dialog_open_encrypted_doc: Dialog = builder.object("dialog_open_encrypted_doc").unwrap();
+    let passphrase_entry: PasswordEntry = builder
+        .object("open_encrypted_doc_passphrase_entry")
+        .unwrap();
+    let wrong_passphrase_label: Label = builder
+        .object("open_encrypted_doc_wrong_passphrase_label")
+        .unwrap();
+
+    wrong_passphrase_label.set_visible(wrong_passphrase);
+    dialog_open_encrypted_doc.set_transient_for(Some(appwindow));
+
+    dialog_open_encrypted_doc.connect_response(
+        clone!(@weak appwindow, @weak passphrase_entry => move |dialog_open_encrypted_doc, responsetype| {
+            match responsetype {
+                ResponseType::Apply => {
+                    dialog_open_encrypted_doc.close();
+
+                    let passphrase = passphrase_entry.text().to_string();
+                    let bytes = bytes.clone();
+                    let path = path.clone();
+                    glib::MainContext::default().spawn_local(clone!(@strong appwindow => async move {
+                        appwindow.start_pulsing_canvas_progressbar();
+
+                        if let Err(e) = appwindow.load_in_rnote_bytes(bytes.clone(), path.clone(), Some(passphrase)).await {
+                            appwindow.handle_rnote_load_error(e, bytes, path);
+                        }
+
+                        appwindow.finish_canvas_progressbar();
+                    }));
+                }
+                _ => {
+                    dialog_open_encrypted_doc.close();
+                }
+            }
+        }),
+    );
+
+    dialog_open_encrypted_doc.show();
+}
+
+/// Sets the passphrase the document is encrypted with when saving
+pub fn dialog_encrypt_doc(appwindow: &RnoteAppWindow) {
+    let builder =
+        Builder::from_resource((String::from(config::APP_IDPATH) + "ui/dialogs.ui").as_str());
+    let dialog_encrypt_doc: Dialog = builder.object("dialog_encrypt_doc").unwrap();
+    let passphrase_entry: PasswordEntry = builder.object("encrypt_doc_passphrase_entry").unwrap();
+    let passphrase_confirm_entry: PasswordEntry = builder
+        .object("encrypt_doc_passphrase_confirm_entry")
+        .unwrap();
+
+    dialog_encrypt_doc.set_transient_for(Some(appwindow));
+
+    dialog_encrypt_doc.connect_response(
+        clone!(@weak appwindow, @weak passphrase_entry, @weak passphrase_confirm_entry => move |dialog_encrypt_doc, responsetype| {
+            match responsetype {
+                ResponseType::Apply => {
+                    let passphrase = passphrase_entry.text().to_string();
+
+                    if passphrase != passphrase_confirm_entry.text().as_str() {
+                        adw::prelude::ActionGroupExt::activate_action(&appwindow, "error-toast", Some(&gettext("The passphrases don't match.").to_variant()));
+                        return;
+                    }
+                    dialog_encrypt_doc.close();
+
+                    if passphrase.is_empty() {
+                        appwindow.canvas().set_output_passphrase(None);
+                        adw::prelude::ActionGroupExt::activate_action(&appwindow, "text-toast", Some(&gettext("The document is saved unencrypted.").to_variant()));
+                    } else {
+                        appwindow.canvas().set_output_passphrase(Some(passphrase));
+                        adw::prelude::ActionGroupExt::activate_action(&appwindow, "text-toast", Some(&gettext("The document is encrypted when saved.").to_variant()));
+                    }
+                    appwindow.canvas().set_unsaved_changes(true);
+                }
+                _ => {
+                    dialog_encrypt_doc.close();
+                }
+            }
+        }),
+    );
+
+    dialog_encrypt_doc.show();
+}
+
+/// Asks for the sync server and the name shown to the other collaborators, and starts collaborating
+#[cfg(feature = "sync")]
+pub fn dialog_start_sync(appwindow: &RnoteAppWindow) {
+    let builder =
+        Builder::from_resource((String::from(config::APP_IDPATH) + "ui/dialogs.ui").as_str());
+    let dialog_start_sync: Dialog = builder.object("dialog_start_sync").unwrap();
+    let server_entry: Entry = builder.object("start_sync_server_entry").unwrap();
+    let name_entry: Entry = builder.object("start_sync_name_entry").unwrap();
+
+    name_entry.set_text(glib::real_name().to_string_lossy().as_ref());
+    dialog_start_sync.set_transient_for(Some(appwindow));
+
+    dialog_start_sync.connect_response(
+        clone!(@weak appwindow, @weak server_entry, @weak name_entry => move |dialog_start_sync, responsetype| {
+            dialog_start_sync.close();
+
+            if responsetype != ResponseType::Apply {
+                return;
+            }
+            let url = server_entry.text().to_string();
+            let name = name_entry.text().to_string();
+
+            let res = appwindow.canvas().engine().borrow_mut().start_sync(&url, name);
+            match res {
+                Ok(widget_flags) => {
+                    appwindow.handle_widget_flags(widget_flags);
+                    if let Some(action_collaborate) = appwindow.lookup_action("collaborate").and_then(|action| action.downcast::<gio::SimpleAction>().ok()) {
+                        action_collaborate.set_state(&true.to_variant());
+                    }
+                    adw::prelude::ActionGroupExt::activate_action(&appwindow, "text-toast", Some(&gettext("Collaborating through “{}”").replace("{}", &url).to_variant()));
+                }
+                Err(e) => {
+                    log::error!("start_sync() failed with Err {}", e);
+                    adw::prelude::ActionGroupExt::activate_action(&appwindow, "error-toast", Some(&gettext("Connecting to the sync server failed.").to_variant()));
+                }
+            }
+        }),
+    );
+
+    dialog_start_sync.show();
+}
+
 pub fn dialog_import_pdf_w_prefs(appwindow: &RnoteAppWindow, target_pos: Option<na::Vector2<f64>>) {
     let builder =
         Builder::from_resource((String::from(config::APP_IDPATH) + "ui/dialogs.ui").as_str());
@@ -346,6 +484,58 @@ pub fn dialog_import_pdf_w_prefs(appwindow: &RnoteAppWindow, target_pos: Option<
     }
 }
 
+/// Asks which frame of the animated image ( e.g. GIF or WebP ) gets imported
+pub fn dialog_import_image_frame(
+    appwindow: &RnoteAppWindow,
+    bytes: Vec<u8>,
+    n_frames: usize,
+    target_pos: Option<na::Vector2<f64>>,
+) {
+    let builder =
+        Builder::from_resource((String::from(config::APP_IDPATH) + "ui/dialogs.ui").as_str());
+    let dialog_import_image_frame: Dialog = builder.object("dialog_import_image_frame").unwrap();
+    let import_image_frame_row: adw::ActionRow = builder.object("import_image_frame_row").unwrap();
+    let import_image_frame_spinbutton: SpinButton =
+        builder.object("import_image_frame_spinbutton").unwrap();
+
+    import_image_frame_row
+        .set_subtitle(&gettext("The image has {} frames").replace("{}", &n_frames.to_string()));
+    import_image_frame_spinbutton.set_increments(1.0, 5.0);
+    import_image_frame_spinbutton.set_range(1.0, n_frames as f64);
+    import_image_frame_spinbutton.set_value(1.0);
+
+    dialog_import_image_frame.set_transient_for(Some(appwindow));
+
+    dialog_import_image_frame.connect_response(
+        clone!(@weak appwindow, @weak import_image_frame_spinbutton => move |dialog_import_image_frame, responsetype| {
+            dialog_import_image_frame.close();
+
+            if responsetype != ResponseType::Apply {
+                appwindow.application().unwrap().downcast::<RnoteApp>().unwrap().set_input_file(None);
+                return;
+            }
+            let frame_index = import_image_frame_spinbutton.value() as usize - 1;
+            let bytes = bytes.clone();
+
+            glib::MainContext::default().spawn_local(clone!(@strong appwindow => async move {
+                appwindow.start_pulsing_canvas_progressbar();
+
+                if let Err(e) = appwindow.load_in_bitmapimage_bytes(bytes, target_pos, frame_index).await {
+                    adw::prelude::ActionGroupExt::activate_action(&appwindow, "error-toast", Some(&gettext("Opening bitmap image file failed.").to_variant()));
+                    log::error!(
+                        "load_in_bitmapimage_bytes() failed in dialog import image frame with Err {}",
+                        e
+                    );
+                }
+
+                appwindow.finish_canvas_progressbar();
+            }));
+        }),
+    );
+
+    dialog_import_image_frame.show();
+}
+
 pub fn dialog_edit_workspace(appwindow: &RnoteAppWindow) {
     let builder =
         Builder::from_resource((String::from(config::APP_IDPATH) + "ui/dialogs.ui").as_str());
@@ -573,12 +763,24 @@ pub fn dialog_import_file(appwindow: &RnoteAppWindow) {
     filter.add_mime_type("image/svg+xml");
     filter.add_mime_type("image/png");
     filter.add_mime_type("image/jpeg");
+    filter.add_mime_type("image/gif");
+    filter.add_mime_type("image/webp");
+    filter.add_mime_type("image/heif");
+    filter.add_mime_type("image/heic");
     filter.add_mime_type("application/pdf");
+    filter.add_mime_type("text/markdown");
     filter.add_pattern("*.svg");
     filter.add_pattern("*.png");
     filter.add_pattern("*.jpg");
+    filter.add_pattern("*.gif");
+    filter.add_pattern("*.webp");
+    filter.add_pattern("*.heic");
+    filter.add_pattern("*.heif");
     filter.add_pattern("*.pdf");
-    filter.set_name(Some(&gettext("PNG / SVG / JPG / PDF file")));
+    filter.add_pattern("*.md");
+    filter.set_name(Some(&gettext(
+        "PNG / SVG / JPG / GIF / WebP / HEIC / PDF / Markdown file",
+    )));
 
     let dialog_import_file: FileChooserNative = FileChooserNative::builder()
         .title(&gettext("Import file"))
@@ -611,6 +813,67 @@ pub fn dialog_import_file(appwindow: &RnoteAppWindow) {
     *appwindow.filechoosernative().borrow_mut() = Some(dialog_import_file);
 }
 
+pub fn dialog_import_background_pattern(appwindow: &RnoteAppWindow) {
+    let filter = FileFilter::new();
+    filter.add_mime_type("image/svg+xml");
+    filter.add_pattern("*.svg");
+    filter.set_name(Some(&gettext("SVG file")));
+
+    let dialog_import_background_pattern: FileChooserNative = FileChooserNative::builder()
+        .title(&gettext("Choose custom background pattern"))
+        .modal(true)
+        .transient_for(appwindow)
+        .accept_label(&gettext("Choose"))
+        .cancel_label(&gettext("Cancel"))
+        .action(FileChooserAction::Open)
+        .select_multiple(false)
+        .build();
+
+    dialog_import_background_pattern.add_filter(&filter);
+
+    dialog_import_background_pattern.connect_response(
+        clone!(@weak appwindow => move |dialog_import_background_pattern, responsetype| {
+            match responsetype {
+                ResponseType::Accept => {
+                    if let Some(file) = dialog_import_background_pattern.file() {
+                        glib::MainContext::default().spawn_local(clone!(@strong appwindow => async move {
+                            let result = file.load_bytes_future().await;
+
+                            let custom_pattern = result
+                                .map_err(anyhow::Error::from)
+                                .and_then(|(file_bytes, _)| Ok(String::from_utf8(file_bytes.to_vec())?))
+                                .and_then(|svg_data| CustomPattern::from_svg_data(&svg_data));
+
+                            match custom_pattern {
+                                Ok(custom_pattern) => {
+                                    appwindow.canvas().engine().borrow_mut().document.background.custom_pattern = Some(custom_pattern);
+                                    appwindow.settings_panel().set_background_pattern(PatternStyle::Custom);
+
+                                    appwindow.canvas().regenerate_background_pattern();
+                                    appwindow.canvas().update_engine_rendering();
+                                }
+                                Err(e) => {
+                                    adw::prelude::ActionGroupExt::activate_action(&appwindow, "error-toast", Some(&gettext("Loading the custom background pattern failed.").to_variant()));
+                                    log::error!(
+                                        "loading custom background pattern failed in dialog_import_background_pattern() with Err {}",
+                                        e
+                                    );
+                                }
+                            }
+                        }));
+                    }
+                }
+                _ => {
+                }
+            }
+        }),
+    );
+
+    dialog_import_background_pattern.show();
+    // keeping the filechooser around because otherwise GTK won't keep it alive
+    *appwindow.filechoosernative().borrow_mut() = Some(dialog_import_background_pattern);
+}
+
 pub fn dialog_export_selection_as_svg(appwindow: &RnoteAppWindow) {
     let filter = FileFilter::new();
     filter.add_mime_type("image/svg+xml");
@@ -715,6 +978,88 @@ pub fn dialog_export_selection_as_png(appwindow: &RnoteAppWindow) {
     *appwindow.filechoosernative().borrow_mut() = Some(dialog_export_selection_as_png);
 }
 
+/// Exports the selection as JPEG, optionally fitting it into a file size budget
+pub fn dialog_export_selection_as_jpeg(appwindow: &RnoteAppWindow) {
+    let filter = FileFilter::new();
+    filter.add_mime_type("image/jpeg");
+    filter.add_pattern("*.jpg");
+    filter.add_pattern("*.jpeg");
+    filter.set_name(Some(&gettext("JPEG file")));
+
+    let dialog_export_selection_as_jpeg: FileChooserNative = FileChooserNative::builder()
+        .title(&gettext("Export Selection"))
+        .modal(true)
+        .transient_for(appwindow)
+        .accept_label(&gettext("Export"))
+        .cancel_label(&gettext("Cancel"))
+        .action(FileChooserAction::Save)
+        .select_multiple(false)
+        .build();
+    dialog_export_selection_as_jpeg.add_filter(&filter);
+
+    // The options are the maximum file sizes in MB, 0 for no limit
+    dialog_export_selection_as_jpeg.add_choice(
+        "size-target",
+        &gettext("File size"),
+        &["0", "1", "2", "5", "10", "25"],
+        &[
+            gettext("No limit").as_str(),
+            gettext("At most 1 MB").as_str(),
+            gettext("At most 2 MB").as_str(),
+            gettext("At most 5 MB").as_str(),
+            gettext("At most 10 MB").as_str(),
+            gettext("At most 25 MB").as_str(),
+        ],
+    );
+    dialog_export_selection_as_jpeg.set_choice("size-target", "0");
+
+    dialog_export_selection_as_jpeg.set_current_name(
+        format!(
+            "{}_selection.jpg",
+            rnote_engine::utils::now_formatted_string()
+        )
+        .as_str(),
+    );
+
+    dialog_export_selection_as_jpeg.connect_response(clone!(@weak appwindow => move |dialog_export_selection_as_jpeg, responsetype| {
+            match responsetype {
+                ResponseType::Accept => {
+                    if let Some(file) = dialog_export_selection_as_jpeg.file() {
+                        let max_megabytes = dialog_export_selection_as_jpeg
+                            .choice("size-target")
+                            .and_then(|choice| choice.parse::<usize>().ok())
+                            .unwrap_or(0);
+
+                        glib::MainContext::default().spawn_local(clone!(@strong appwindow => async move {
+                            appwindow.start_pulsing_canvas_progressbar();
+
+                            // JPEG has no alpha channel, so the background is always included
+                            let res = if max_megabytes == 0 {
+                                appwindow.export_selection_as_bitmapimage(&file, image::ImageOutputFormat::Jpeg(90), true).await
+                            } else {
+                                appwindow.export_selection_as_bitmapimage_w_size_target(&file, max_megabytes * 1_000_000, true).await
+                            };
+
+                            if let Err(e) = res {
+                                log::error!("exporting selection as jpeg failed with error `{}`", e);
+                                adw::prelude::ActionGroupExt::activate_action(&appwindow, "error-toast", Some(&gettext("Export selection as JPEG failed.").to_variant()));
+                            } else {
+                                adw::prelude::ActionGroupExt::activate_action(&appwindow, "text-toast", Some(&gettext("Exported selection as JPEG successfully.").to_variant()));
+                            }
+
+                            appwindow.finish_canvas_progressbar();
+                        }));
+                    }
+                }
+                _ => {}
+            }
+        }));
+
+    dialog_export_selection_as_jpeg.show();
+    // keeping the filechooser around because otherwise GTK won't keep it alive
+    *appwindow.filechoosernative().borrow_mut() = Some(dialog_export_selection_as_jpeg);
+}
+
 pub fn dialog_export_doc_as_svg(appwindow: &RnoteAppWindow) {
     let filter = FileFilter::new();
     filter.add_mime_type("image/svg+xml");
@@ -766,6 +1111,86 @@ pub fn dialog_export_doc_as_svg(appwindow: &RnoteAppWindow) {
     *appwindow.filechoosernative().borrow_mut() = Some(dialog_export_doc_as_svg);
 }
 
+/// Exports the document as JPEG, optionally fitting it into a file size budget, e.g. for sharing through size-limited channels
+pub fn dialog_export_doc_as_jpeg(appwindow: &RnoteAppWindow) {
+    let filter = FileFilter::new();
+    filter.add_mime_type("image/jpeg");
+    filter.add_pattern("*.jpg");
+    filter.add_pattern("*.jpeg");
+    filter.set_name(Some(&gettext("JPEG file")));
+
+    let dialog_export_doc_as_jpeg: FileChooserNative = FileChooserNative::builder()
+        .title(&gettext("Export document"))
+        .modal(true)
+        .transient_for(appwindow)
+        .accept_label(&gettext("Export"))
+        .cancel_label(&gettext("Cancel"))
+        .action(FileChooserAction::Save)
+        .select_multiple(false)
+        .build();
+    dialog_export_doc_as_jpeg.add_filter(&filter);
+
+    // The options are the maximum file sizes in MB, 0 for no limit
+    dialog_export_doc_as_jpeg.add_choice(
+        "size-target",
+        &gettext("File size"),
+        &["0", "1", "2", "5", "10", "25"],
+        &[
+            gettext("No limit").as_str(),
+            gettext("At most 1 MB").as_str(),
+            gettext("At most 2 MB").as_str(),
+            gettext("At most 5 MB").as_str(),
+            gettext("At most 10 MB").as_str(),
+            gettext("At most 25 MB").as_str(),
+        ],
+    );
+    dialog_export_doc_as_jpeg.set_choice("size-target", "0");
+
+    dialog_export_doc_as_jpeg.set_current_name(
+        format!("{}_doc.jpg", rnote_engine::utils::now_formatted_string()).as_str(),
+    );
+
+    dialog_export_doc_as_jpeg.connect_response(
+        clone!(@weak appwindow => move |dialog_export_doc, responsetype| {
+            match responsetype {
+                ResponseType::Accept => {
+                    if let Some(file) = dialog_export_doc.file() {
+                        let max_megabytes = dialog_export_doc
+                            .choice("size-target")
+                            .and_then(|choice| choice.parse::<usize>().ok())
+                            .unwrap_or(0);
+
+                        glib::MainContext::default().spawn_local(clone!(@strong appwindow => async move {
+                            appwindow.start_pulsing_canvas_progressbar();
+
+                            let res = if max_megabytes == 0 {
+                                appwindow.export_doc_as_bitmapimage(&file, image::ImageOutputFormat::Jpeg(90), true).await
+                            } else {
+                                appwindow.export_doc_as_bitmapimage_w_size_target(&file, max_megabytes * 1_000_000, true).await
+                            };
+
+                            if let Err(e) = res {
+                                log::error!("exporting document failed with error `{}`", e);
+                                adw::prelude::ActionGroupExt::activate_action(&appwindow, "error-toast", Some(&gettext("Export document as JPEG failed.").to_variant()));
+                            } else {
+                                adw::prelude::ActionGroupExt::activate_action(&appwindow, "text-toast", Some(&gettext("Exported document as JPEG successfully.").to_variant()));
+                            }
+
+                            appwindow.finish_canvas_progressbar();
+                        }));
+                    }
+                }
+                _ => {
+                }
+            }
+        }),
+    );
+
+    dialog_export_doc_as_jpeg.show();
+    // keeping the filechooser around because otherwise GTK won't keep it alive
+    *appwindow.filechoosernative().borrow_mut() = Some(dialog_export_doc_as_jpeg);
+}
+
 pub fn dialog_export_doc_as_pdf(appwindow: &RnoteAppWindow) {
     let filter = FileFilter::new();
     filter.add_mime_type("application/pdf");
@@ -817,6 +1242,61 @@ pub fn dialog_export_doc_as_pdf(appwindow: &RnoteAppWindow) {
     *appwindow.filechoosernative().borrow_mut() = Some(dialog_export_doc_as_pdf);
 }
 
+pub fn dialog_export_flipbook_as_gif(appwindow: &RnoteAppWindow) {
+    let filter = FileFilter::new();
+    filter.add_mime_type("image/gif");
+    filter.add_pattern("*.gif");
+    filter.set_name(Some(&gettext("GIF image")));
+
+    let dialog_export_flipbook_as_gif: FileChooserNative = FileChooserNative::builder()
+        .title(&gettext("Export pages as animated GIF"))
+        .modal(true)
+        .transient_for(appwindow)
+        .accept_label(&gettext("Export"))
+        .cancel_label(&gettext("Cancel"))
+        .action(FileChooserAction::Save)
+        .select_multiple(false)
+        .build();
+    dialog_export_flipbook_as_gif.add_filter(&filter);
+
+    dialog_export_flipbook_as_gif.set_current_name(
+        format!(
+            "{}_flipbook.gif",
+            rnote_engine::utils::now_formatted_string()
+        )
+        .as_str(),
+    );
+
+    dialog_export_flipbook_as_gif.connect_response(
+        clone!(@weak appwindow => move |dialog_export_flipbook, responsetype| {
+            match responsetype {
+                ResponseType::Accept => {
+                    if let Some(file) = dialog_export_flipbook.file() {
+                        glib::MainContext::default().spawn_local(clone!(@strong appwindow, @strong file => async move {
+                            appwindow.start_pulsing_canvas_progressbar();
+
+                            if let Err(e) = appwindow.export_flipbook_as_gif(&file, true).await {
+                                log::error!("export_flipbook_as_gif() failed in export dialog with Err {}", e);
+                                adw::prelude::ActionGroupExt::activate_action(&appwindow, "error-toast", Some(&gettext("Export pages as animated GIF failed.").to_variant()));
+                            } else {
+                                adw::prelude::ActionGroupExt::activate_action(&appwindow, "text-toast", Some(&gettext("Exported pages as animated GIF successfully.").to_variant()));
+                            };
+
+                            appwindow.finish_canvas_progressbar();
+                        }));
+                    }
+                }
+                _ => {
+                }
+            }
+        }),
+    );
+
+    dialog_export_flipbook_as_gif.show();
+    // keeping the filechooser around because otherwise GTK won't keep it alive
+    *appwindow.filechoosernative().borrow_mut() = Some(dialog_export_flipbook_as_gif);
+}
+
 pub fn dialog_export_doc_as_xopp(appwindow: &RnoteAppWindow) {
     let filter = FileFilter::new();
     filter.add_mime_type("application/x-xopp");
@@ -867,6 +1347,72 @@ pub fn dialog_export_doc_as_xopp(appwindow: &RnoteAppWindow) {
     *appwindow.filechoosernative().borrow_mut() = Some(dialog_export_doc_as_xopp);
 }
 
+pub fn dialog_export_doc_as_text(appwindow: &RnoteAppWindow, format: TextExportFormat) {
+    let filter = FileFilter::new();
+    let (extension, format_name) = match format {
+        TextExportFormat::Markdown => {
+            filter.add_mime_type("text/markdown");
+            filter.add_pattern("*.md");
+            filter.set_name(Some(&gettext("Markdown file")));
+            ("md", gettext("markdown"))
+        }
+        TextExportFormat::PlainText => {
+            filter.add_mime_type("text/plain");
+            filter.add_pattern("*.txt");
+            filter.set_name(Some(&gettext("Plain text file")));
+            ("txt", gettext("plain text"))
+        }
+    };
+
+    let dialog_export_doc_as_text: FileChooserNative = FileChooserNative::builder()
+        .title(&gettext("Export text"))
+        .modal(true)
+        .transient_for(appwindow)
+        .accept_label(&gettext("Export"))
+        .cancel_label(&gettext("Cancel"))
+        .action(FileChooserAction::Save)
+        .select_multiple(false)
+        .build();
+    dialog_export_doc_as_text.add_filter(&filter);
+
+    dialog_export_doc_as_text.set_current_name(
+        format!(
+            "{}_text.{}",
+            rnote_engine::utils::now_formatted_string(),
+            extension
+        )
+        .as_str(),
+    );
+
+    dialog_export_doc_as_text.connect_response(
+        clone!(@weak appwindow => move |dialog_export_doc, responsetype| {
+            match responsetype {
+                ResponseType::Accept => {
+                    if let Some(file) = dialog_export_doc.file() {
+                        glib::MainContext::default().spawn_local(clone!(@strong appwindow, @strong format_name => async move {
+                            appwindow.start_pulsing_canvas_progressbar();
+
+                            if let Err(e) = appwindow.export_doc_as_text(&file, format).await {
+                                log::error!("exporting text as {} failed, replace_file_async failed with Err {}", format_name, e);
+                                adw::prelude::ActionGroupExt::activate_action(&appwindow, "error-toast", Some(&gettext("Exporting text failed.").to_variant()));
+                            } else {
+                                adw::prelude::ActionGroupExt::activate_action(&appwindow, "text-toast", Some(&gettext("Exported text successfully.").to_variant()));
+                            }
+
+                            appwindow.finish_canvas_progressbar();
+                        }));
+                    }
+                }
+                _ => {}
+            }
+        }),
+    );
+
+    dialog_export_doc_as_text.show();
+    // keeping the filechooser around because otherwise GTK won't keep it alive
+    *appwindow.filechoosernative().borrow_mut() = Some(dialog_export_doc_as_text);
+}
+
 pub fn dialog_export_engine_state(appwindow: &RnoteAppWindow) {
     let filter = FileFilter::new();
     filter.add_mime_type("application/json");
@@ -28,12 +28,18 @@ mod imp {
         #[template_child]
         pub brush_toggle: TemplateChild<ToggleButton>,
         #[template_child]
+        pub highlighter_toggle: TemplateChild<ToggleButton>,
+        #[template_child]
         pub shaper_toggle: TemplateChild<ToggleButton>,
         #[template_child]
         pub typewriter_toggle: TemplateChild<ToggleButton>,
         #[template_child]
         pub eraser_toggle: TemplateChild<ToggleButton>,
         #[template_child]
+        pub laser_toggle: TemplateChild<ToggleButton>,
+        #[template_child]
+        pub spline_toggle: TemplateChild<ToggleButton>,
+        #[template_child]
         pub selector_toggle: TemplateChild<ToggleButton>,
         #[template_child]
         pub tools_toggle: TemplateChild<ToggleButton>,
@@ -117,6 +123,10 @@ impl MainHeader {
         self.imp().brush_toggle.get()
     }
 
+    pub fn highlighter_toggle(&self) -> ToggleButton {
+        self.imp().highlighter_toggle.get()
+    }
+
     pub fn shaper_toggle(&self) -> ToggleButton {
         self.imp().shaper_toggle.get()
     }
@@ -129,6 +139,14 @@ impl MainHeader {
         self.imp().eraser_toggle.get()
     }
 
+    pub fn laser_toggle(&self) -> ToggleButton {
+        self.imp().laser_toggle.get()
+    }
+
+    pub fn spline_toggle(&self) -> ToggleButton {
+        self.imp().spline_toggle.get()
+    }
+
     pub fn selector_toggle(&self) -> ToggleButton {
         self.imp().selector_toggle.get()
     }
@@ -194,6 +212,12 @@ impl MainHeader {
             }
         }));
 
+        self.imp().highlighter_toggle.get().connect_toggled(clone!(@weak appwindow => move |highlighter_toggle| {
+            if highlighter_toggle.is_active() {
+                adw::prelude::ActionGroupExt::activate_action(&appwindow, "pen-style", Some(&PenStyle::Highlighter.nick().to_variant()));
+            }
+        }));
+
         self.imp().shaper_toggle.get().connect_toggled(clone!(@weak appwindow => move |shaper_toggle| {
             if shaper_toggle.is_active() {
                 adw::prelude::ActionGroupExt::activate_action(&appwindow, "pen-style", Some(&PenStyle::Shaper.nick().to_variant()));
@@ -212,6 +236,18 @@ impl MainHeader {
             }
         }));
 
+        self.imp().laser_toggle.get().connect_toggled(clone!(@weak appwindow => move |laser_toggle| {
+            if laser_toggle.is_active() {
+                adw::prelude::ActionGroupExt::activate_action(&appwindow, "pen-style", Some(&PenStyle::Laser.nick().to_variant()));
+            }
+        }));
+
+        self.imp().spline_toggle.get().connect_toggled(clone!(@weak appwindow => move |spline_toggle| {
+            if spline_toggle.is_active() {
+                adw::prelude::ActionGroupExt::activate_action(&appwindow, "pen-style", Some(&PenStyle::Spline.nick().to_variant()));
+            }
+        }));
+
         self.imp().selector_toggle.get().connect_toggled(clone!(@weak appwindow => move |selector_toggle| {
             if selector_toggle.is_active() {
                 adw::prelude::ActionGroupExt::activate_action(&appwindow, "pen-style", Some(&PenStyle::Selector.nick().to_variant()));
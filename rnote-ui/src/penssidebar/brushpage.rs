@@ -1,14 +1,17 @@
 use adw::prelude::*;
 use gtk4::{
     gdk, glib, glib::clone, subclass::prelude::*, CompositeTemplate, Image, ListBox, MenuButton,
-    Popover, SpinButton,
+    Popover, SpinButton, Switch,
 };
 use num_traits::cast::ToPrimitive;
 
-use rnote_compose::style::PressureCurve;
+use rnote_compose::stabilizer::{Stabilizer, StabilizerMode};
+use rnote_compose::style::{PressureCurve, PressureResponse};
 use rnote_engine::pens::Brush;
 
 use crate::{appwindow::RnoteAppWindow, ColorPicker};
+use rnote_compose::style::calligraphy::CalligraphyOptions;
+use rnote_compose::style::stamped::{BrushTexture, BrushTextureKind, StampedOptions};
 use rnote_compose::style::textured::{TexturedDotsDistribution, TexturedOptions};
 use rnote_engine::pens::brush::BrushStyle;
 use rnote_engine::utils::GdkRGBAHelpers;
@@ -36,6 +39,10 @@ mod imp {
         #[template_child]
         pub brushstyle_textured_row: TemplateChild<adw::ActionRow>,
         #[template_child]
+        pub brushstyle_calligraphy_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub brushstyle_stamped_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
         pub brushconfig_menubutton: TemplateChild<MenuButton>,
         #[template_child]
         pub brushconfig_popover: TemplateChild<Popover>,
@@ -49,6 +56,34 @@ mod imp {
         pub texturedstyle_radius_y_spinbutton: TemplateChild<SpinButton>,
         #[template_child]
         pub texturedstyle_distribution_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub calligraphystyle_nib_angle_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub calligraphystyle_nib_thickness_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub stampedstyle_texture_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub stampedstyle_grain_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub stampedstyle_spacing_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub pressure_response_gamma_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub pressure_response_min_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub pressure_response_max_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub stabilizer_mode_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub stabilizer_window_size_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub stabilizer_string_length_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub shape_recognition_switch: TemplateChild<Switch>,
+        #[template_child]
+        pub compaction_epsilon_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub simplification_epsilon_spinbutton: TemplateChild<SpinButton>,
     }
 
     #[glib::object_subclass]
@@ -129,6 +164,14 @@ impl BrushPage {
         self.imp().brushstyle_textured_row.get()
     }
 
+    pub fn brushstyle_calligraphy_row(&self) -> adw::ActionRow {
+        self.imp().brushstyle_calligraphy_row.get()
+    }
+
+    pub fn brushstyle_stamped_row(&self) -> adw::ActionRow {
+        self.imp().brushstyle_stamped_row.get()
+    }
+
     pub fn brushconfig_menubutton(&self) -> MenuButton {
         self.imp().brushconfig_menubutton.get()
     }
@@ -153,6 +196,77 @@ impl BrushPage {
         self.imp().texturedstyle_radius_y_spinbutton.clone()
     }
 
+    pub fn calligraphystyle_nib_angle_spinbutton(&self) -> SpinButton {
+        self.imp().calligraphystyle_nib_angle_spinbutton.clone()
+    }
+
+    pub fn calligraphystyle_nib_thickness_spinbutton(&self) -> SpinButton {
+        self.imp().calligraphystyle_nib_thickness_spinbutton.clone()
+    }
+
+    pub fn stampedstyle_grain_spinbutton(&self) -> SpinButton {
+        self.imp().stampedstyle_grain_spinbutton.clone()
+    }
+
+    pub fn stampedstyle_spacing_spinbutton(&self) -> SpinButton {
+        self.imp().stampedstyle_spacing_spinbutton.clone()
+    }
+
+    pub fn stampedstyle_texture_kind(&self) -> BrushTextureKind {
+        BrushTextureKind::try_from(self.imp().stampedstyle_texture_row.get().selected()).unwrap()
+    }
+
+    pub fn set_stampedstyle_texture_kind(&self, kind: BrushTextureKind) {
+        let position = kind.to_u32().unwrap();
+
+        self.imp()
+            .stampedstyle_texture_row
+            .get()
+            .set_selected(position);
+    }
+
+    pub fn pressure_response_gamma_spinbutton(&self) -> SpinButton {
+        self.imp().pressure_response_gamma_spinbutton.clone()
+    }
+
+    pub fn pressure_response_min_spinbutton(&self) -> SpinButton {
+        self.imp().pressure_response_min_spinbutton.clone()
+    }
+
+    pub fn pressure_response_max_spinbutton(&self) -> SpinButton {
+        self.imp().pressure_response_max_spinbutton.clone()
+    }
+
+    pub fn stabilizer_window_size_spinbutton(&self) -> SpinButton {
+        self.imp().stabilizer_window_size_spinbutton.clone()
+    }
+
+    pub fn stabilizer_string_length_spinbutton(&self) -> SpinButton {
+        self.imp().stabilizer_string_length_spinbutton.clone()
+    }
+
+    pub fn shape_recognition_switch(&self) -> Switch {
+        self.imp().shape_recognition_switch.clone()
+    }
+
+    pub fn compaction_epsilon_spinbutton(&self) -> SpinButton {
+        self.imp().compaction_epsilon_spinbutton.clone()
+    }
+
+    pub fn simplification_epsilon_spinbutton(&self) -> SpinButton {
+        self.imp().simplification_epsilon_spinbutton.clone()
+    }
+
+    pub fn stabilizer_mode(&self) -> StabilizerMode {
+        StabilizerMode::try_from(self.imp().stabilizer_mode_row.get().selected()).unwrap()
+    }
+
+    pub fn set_stabilizer_mode(&self, mode: StabilizerMode) {
+        let position = mode.to_u32().unwrap();
+
+        self.imp().stabilizer_mode_row.get().set_selected(position);
+    }
+
     pub fn solidstyle_pressure_curve(&self) -> PressureCurve {
         PressureCurve::try_from(self.imp().solidstyle_pressure_curves_row.get().selected()).unwrap()
     }
@@ -200,6 +314,8 @@ impl BrushPage {
                     BrushStyle::Marker => appwindow.canvas().engine().borrow_mut().penholder.brush.marker_options.stroke_color = Some(color),
                     BrushStyle::Solid => appwindow.canvas().engine().borrow_mut().penholder.brush.solid_options.stroke_color = Some(color),
                     BrushStyle::Textured => appwindow.canvas().engine().borrow_mut().penholder.brush.textured_options.stroke_color = Some(color),
+                    BrushStyle::Calligraphy => appwindow.canvas().engine().borrow_mut().penholder.brush.calligraphy_options.stroke_color = Some(color),
+                    BrushStyle::Stamped => appwindow.canvas().engine().borrow_mut().penholder.brush.stamped_options.stroke_color = Some(color),
                 }
 
                 if let Err(e) = appwindow.save_engine_config() {
@@ -216,6 +332,8 @@ impl BrushPage {
                     BrushStyle::Marker => appwindow.canvas().engine().borrow_mut().penholder.brush.marker_options.stroke_width = brush_widthscale_spinbutton.value(),
                     BrushStyle::Solid => appwindow.canvas().engine().borrow_mut().penholder.brush.solid_options.stroke_width = brush_widthscale_spinbutton.value(),
                     BrushStyle::Textured => appwindow.canvas().engine().borrow_mut().penholder.brush.textured_options.stroke_width = brush_widthscale_spinbutton.value(),
+                    BrushStyle::Calligraphy => appwindow.canvas().engine().borrow_mut().penholder.brush.calligraphy_options.stroke_width = brush_widthscale_spinbutton.value(),
+                    BrushStyle::Stamped => appwindow.canvas().engine().borrow_mut().penholder.brush.stamped_options.stroke_width = brush_widthscale_spinbutton.value(),
                 }
 
                 if let Err(e) = appwindow.save_engine_config() {
@@ -244,6 +362,12 @@ impl BrushPage {
                             BrushStyle::Textured => {
                                 engine.penholder.brush.textured_options.stroke_color = Some(brushpage.colorpicker().current_color());
                             },
+                            BrushStyle::Calligraphy => {
+                                engine.penholder.brush.calligraphy_options.stroke_color = Some(brushpage.colorpicker().current_color());
+                            },
+                            BrushStyle::Stamped => {
+                                engine.penholder.brush.stamped_options.stroke_color = Some(brushpage.colorpicker().current_color());
+                            },
                         }
                     }
 
@@ -357,6 +481,237 @@ impl BrushPage {
                 log::error!("saving engine config failed after changing brush textured dots distribution, Err `{}`", e);
             }
         }));
+
+        // Calligraphy style
+        // Nib angle
+        self.calligraphystyle_nib_angle_spinbutton()
+            .set_increments(1.0, 15.0);
+        self.calligraphystyle_nib_angle_spinbutton()
+            .set_range(-90.0, 90.0);
+        self.calligraphystyle_nib_angle_spinbutton()
+            .set_value(CalligraphyOptions::NIB_ANGLE_DEFAULT);
+
+        self.calligraphystyle_nib_angle_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |calligraphystyle_nib_angle_spinbutton| {
+                appwindow.canvas().engine().borrow_mut().penholder.brush.calligraphy_options.nib_angle = calligraphystyle_nib_angle_spinbutton.value();
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing brush calligraphy nib angle, Err `{}`", e);
+                }
+            }),
+        );
+
+        // Nib thickness
+        self.calligraphystyle_nib_thickness_spinbutton()
+            .set_increments(0.01, 0.1);
+        self.calligraphystyle_nib_thickness_spinbutton().set_range(
+            CalligraphyOptions::NIB_THICKNESS_MIN,
+            CalligraphyOptions::NIB_THICKNESS_MAX,
+        );
+        self.calligraphystyle_nib_thickness_spinbutton()
+            .set_value(CalligraphyOptions::NIB_THICKNESS_DEFAULT);
+
+        self.calligraphystyle_nib_thickness_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |calligraphystyle_nib_thickness_spinbutton| {
+                appwindow.canvas().engine().borrow_mut().penholder.brush.calligraphy_options.nib_thickness = calligraphystyle_nib_thickness_spinbutton.value();
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing brush calligraphy nib thickness, Err `{}`", e);
+                }
+            }),
+        );
+
+        // Stamped style
+        // Texture
+        self.imp().stampedstyle_texture_row.get().connect_selected_notify(clone!(@weak self as brushpage, @weak appwindow => move |_stampedstyle_texture_row| {
+            appwindow.canvas().engine().borrow_mut().penholder.brush.stamped_options.texture.kind = brushpage.stampedstyle_texture_kind();
+
+            if let Err(e) = appwindow.save_engine_config() {
+                log::error!("saving engine config failed after changing brush stamped texture, Err `{}`", e);
+            }
+        }));
+
+        // Grain
+        self.stampedstyle_grain_spinbutton()
+            .set_increments(0.05, 0.2);
+        self.stampedstyle_grain_spinbutton().set_range(0.0, 1.0);
+        self.stampedstyle_grain_spinbutton()
+            .set_value(BrushTexture::GRAIN_DEFAULT);
+
+        self.stampedstyle_grain_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |stampedstyle_grain_spinbutton| {
+                appwindow.canvas().engine().borrow_mut().penholder.brush.stamped_options.texture.grain = stampedstyle_grain_spinbutton.value();
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing brush stamped texture grain, Err `{}`", e);
+                }
+            }),
+        );
+
+        // Spacing
+        self.stampedstyle_spacing_spinbutton()
+            .set_increments(0.05, 0.2);
+        self.stampedstyle_spacing_spinbutton()
+            .set_range(StampedOptions::SPACING_MIN, StampedOptions::SPACING_MAX);
+        self.stampedstyle_spacing_spinbutton()
+            .set_value(StampedOptions::SPACING_DEFAULT);
+
+        self.stampedstyle_spacing_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |stampedstyle_spacing_spinbutton| {
+                appwindow.canvas().engine().borrow_mut().penholder.brush.stamped_options.spacing = stampedstyle_spacing_spinbutton.value();
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing brush stamped spacing, Err `{}`", e);
+                }
+            }),
+        );
+
+        // Pressure response
+        // Gamma
+        self.pressure_response_gamma_spinbutton()
+            .set_increments(0.05, 0.5);
+        self.pressure_response_gamma_spinbutton()
+            .set_range(PressureResponse::GAMMA_MIN, PressureResponse::GAMMA_MAX);
+        self.pressure_response_gamma_spinbutton()
+            .set_value(PressureResponse::GAMMA_DEFAULT);
+
+        self.pressure_response_gamma_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |pressure_response_gamma_spinbutton| {
+                appwindow.canvas().engine().borrow_mut().penholder.brush.pressure_response_mut().gamma = pressure_response_gamma_spinbutton.value();
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing brush pressure response gamma, Err `{}`", e);
+                }
+            }),
+        );
+
+        // Min pressure
+        self.pressure_response_min_spinbutton()
+            .set_increments(0.01, 0.1);
+        self.pressure_response_min_spinbutton().set_range(0.0, 1.0);
+        self.pressure_response_min_spinbutton().set_value(0.0);
+
+        self.pressure_response_min_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |pressure_response_min_spinbutton| {
+                appwindow.canvas().engine().borrow_mut().penholder.brush.pressure_response_mut().min_pressure = pressure_response_min_spinbutton.value();
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing brush pressure response min pressure, Err `{}`", e);
+                }
+            }),
+        );
+
+        // Max pressure
+        self.pressure_response_max_spinbutton()
+            .set_increments(0.01, 0.1);
+        self.pressure_response_max_spinbutton().set_range(0.0, 1.0);
+        self.pressure_response_max_spinbutton().set_value(1.0);
+
+        self.pressure_response_max_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |pressure_response_max_spinbutton| {
+                appwindow.canvas().engine().borrow_mut().penholder.brush.pressure_response_mut().max_pressure = pressure_response_max_spinbutton.value();
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing brush pressure response max pressure, Err `{}`", e);
+                }
+            }),
+        );
+
+        // Stabilizer
+        // Mode
+        self.imp().stabilizer_mode_row.get().connect_selected_notify(clone!(@weak self as brushpage, @weak appwindow => move |_stabilizer_mode_row| {
+            appwindow.canvas().engine().borrow_mut().penholder.brush.stabilizer.mode = brushpage.stabilizer_mode();
+
+            if let Err(e) = appwindow.save_engine_config() {
+                log::error!("saving engine config failed after changing brush stabilizer mode, Err `{}`", e);
+            }
+        }));
+
+        // Window size
+        self.stabilizer_window_size_spinbutton()
+            .set_increments(1.0, 4.0);
+        self.stabilizer_window_size_spinbutton().set_range(
+            Stabilizer::WINDOW_SIZE_MIN as f64,
+            Stabilizer::WINDOW_SIZE_MAX as f64,
+        );
+        self.stabilizer_window_size_spinbutton()
+            .set_value(Stabilizer::WINDOW_SIZE_DEFAULT as f64);
+
+        self.stabilizer_window_size_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |stabilizer_window_size_spinbutton| {
+                appwindow.canvas().engine().borrow_mut().penholder.brush.stabilizer.window_size = stabilizer_window_size_spinbutton.value_as_int().max(0) as usize;
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing brush stabilizer window size, Err `{}`", e);
+                }
+            }),
+        );
+
+        // String length
+        self.stabilizer_string_length_spinbutton()
+            .set_increments(0.5, 5.0);
+        self.stabilizer_string_length_spinbutton()
+            .set_range(Stabilizer::STRING_LENGTH_MIN, Stabilizer::STRING_LENGTH_MAX);
+        self.stabilizer_string_length_spinbutton()
+            .set_value(Stabilizer::STRING_LENGTH_DEFAULT);
+
+        self.stabilizer_string_length_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |stabilizer_string_length_spinbutton| {
+                appwindow.canvas().engine().borrow_mut().penholder.brush.stabilizer.string_length = stabilizer_string_length_spinbutton.value();
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing brush stabilizer string length, Err `{}`", e);
+                }
+            }),
+        );
+
+        // Shape recognition
+        self.shape_recognition_switch().connect_state_notify(
+            clone!(@weak appwindow => move |switch| {
+                appwindow.canvas().engine().borrow_mut().penholder.brush.shape_recognition = switch.state();
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing brush shape recognition, Err `{}`", e);
+                }
+            }),
+        );
+
+        // Path optimization. An epsilon of zero disables it
+        // Compaction
+        self.compaction_epsilon_spinbutton()
+            .set_increments(0.1, 1.0);
+        self.compaction_epsilon_spinbutton()
+            .set_range(0.0, Brush::PATH_EPSILON_MAX);
+        self.compaction_epsilon_spinbutton().set_value(0.0);
+
+        self.compaction_epsilon_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |compaction_epsilon_spinbutton| {
+                let epsilon = compaction_epsilon_spinbutton.value();
+                appwindow.canvas().engine().borrow_mut().penholder.brush.compaction_epsilon = if epsilon > 0.0 { Some(epsilon) } else { None };
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing brush compaction epsilon, Err `{}`", e);
+                }
+            }),
+        );
+
+        // Simplification
+        self.simplification_epsilon_spinbutton()
+            .set_increments(0.1, 1.0);
+        self.simplification_epsilon_spinbutton()
+            .set_range(0.0, Brush::PATH_EPSILON_MAX);
+        self.simplification_epsilon_spinbutton().set_value(0.0);
+
+        self.simplification_epsilon_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |simplification_epsilon_spinbutton| {
+                let epsilon = simplification_epsilon_spinbutton.value();
+                appwindow.canvas().engine().borrow_mut().penholder.brush.simplification_epsilon = if epsilon > 0.0 { Some(epsilon) } else { None };
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing brush simplification epsilon, Err `{}`", e);
+                }
+            }),
+        );
     }
 
     pub fn refresh_ui(&self, appwindow: &RnoteAppWindow) {
@@ -370,6 +725,33 @@ impl BrushPage {
         self.texturedstyle_radius_y_spinbutton()
             .set_value(brush.textured_options.radii[1]);
         self.set_texturedstyle_distribution_variant(brush.textured_options.distribution);
+        self.calligraphystyle_nib_angle_spinbutton()
+            .set_value(brush.calligraphy_options.nib_angle);
+        self.calligraphystyle_nib_thickness_spinbutton()
+            .set_value(brush.calligraphy_options.nib_thickness);
+        self.set_stampedstyle_texture_kind(brush.stamped_options.texture.kind);
+        self.stampedstyle_grain_spinbutton()
+            .set_value(brush.stamped_options.texture.grain);
+        self.stampedstyle_spacing_spinbutton()
+            .set_value(brush.stamped_options.spacing);
+        let pressure_response = brush.pressure_response();
+        self.pressure_response_gamma_spinbutton()
+            .set_value(pressure_response.gamma);
+        self.pressure_response_min_spinbutton()
+            .set_value(pressure_response.min_pressure);
+        self.pressure_response_max_spinbutton()
+            .set_value(pressure_response.max_pressure);
+        self.set_stabilizer_mode(brush.stabilizer.mode);
+        self.stabilizer_window_size_spinbutton()
+            .set_value(brush.stabilizer.window_size as f64);
+        self.stabilizer_string_length_spinbutton()
+            .set_value(brush.stabilizer.string_length);
+        self.shape_recognition_switch()
+            .set_state(brush.shape_recognition);
+        self.compaction_epsilon_spinbutton()
+            .set_value(brush.compaction_epsilon.unwrap_or(0.0));
+        self.simplification_epsilon_spinbutton()
+            .set_value(brush.simplification_epsilon.unwrap_or(0.0));
 
         match brush.style {
             BrushStyle::Marker => {
@@ -402,6 +784,26 @@ impl BrushPage {
                 self.brushstyle_image()
                     .set_icon_name(Some("pen-brush-style-textured-symbolic"));
             }
+            BrushStyle::Calligraphy => {
+                self.brushstyle_listbox()
+                    .select_row(Some(&self.brushstyle_calligraphy_row()));
+                self.width_spinbutton()
+                    .set_value(brush.calligraphy_options.stroke_width);
+                self.colorpicker()
+                    .set_current_color(brush.calligraphy_options.stroke_color);
+                self.brushstyle_image()
+                    .set_icon_name(Some("pen-brush-style-calligraphy-symbolic"));
+            }
+            BrushStyle::Stamped => {
+                self.brushstyle_listbox()
+                    .select_row(Some(&self.brushstyle_stamped_row()));
+                self.width_spinbutton()
+                    .set_value(brush.stamped_options.stroke_width);
+                self.colorpicker()
+                    .set_current_color(brush.stamped_options.stroke_color);
+                self.brushstyle_image()
+                    .set_icon_name(Some("pen-brush-style-stamped-symbolic"));
+            }
         }
     }
 }
@@ -0,0 +1,136 @@
+use crate::{appwindow::RnoteAppWindow, ColorPicker};
+use adw::prelude::*;
+use gtk4::{
+    gdk, glib, glib::clone, subclass::prelude::*, CompositeTemplate, SpinButton, ToggleButton,
+};
+use rnote_engine::pens::Highlighter;
+use rnote_engine::utils::GdkRGBAHelpers;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, Debug, CompositeTemplate)]
+    #[template(resource = "/com/github/flxzt/rnote/ui/penssidebar/highlighterpage.ui")]
+    pub struct HighlighterPage {
+        #[template_child]
+        pub straight_line_snapping_togglebutton: TemplateChild<ToggleButton>,
+        #[template_child]
+        pub width_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub colorpicker: TemplateChild<ColorPicker>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for HighlighterPage {
+        const NAME: &'static str = "HighlighterPage";
+        type Type = super::HighlighterPage;
+        type ParentType = gtk4::Widget;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for HighlighterPage {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+        }
+
+        fn dispose(&self, obj: &Self::Type) {
+            while let Some(child) = obj.first_child() {
+                child.unparent();
+            }
+        }
+    }
+
+    impl WidgetImpl for HighlighterPage {}
+}
+
+glib::wrapper! {
+    pub struct HighlighterPage(ObjectSubclass<imp::HighlighterPage>)
+        @extends gtk4::Widget;
+}
+
+impl Default for HighlighterPage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HighlighterPage {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create HighlighterPage")
+    }
+
+    pub fn straight_line_snapping_togglebutton(&self) -> ToggleButton {
+        self.imp().straight_line_snapping_togglebutton.get()
+    }
+
+    pub fn width_spinbutton(&self) -> SpinButton {
+        self.imp().width_spinbutton.get()
+    }
+
+    pub fn colorpicker(&self) -> ColorPicker {
+        self.imp().colorpicker.get()
+    }
+
+    pub fn init(&self, appwindow: &RnoteAppWindow) {
+        self.straight_line_snapping_togglebutton().connect_toggled(clone!(@weak appwindow => move |straight_line_snapping_togglebutton| {
+            appwindow.canvas().engine().borrow_mut().penholder.highlighter.straight_line_snapping = straight_line_snapping_togglebutton.is_active();
+
+            if let Err(e) = appwindow.save_engine_config() {
+                log::error!("saving engine config failed after changing highlighter straight line snapping, Err `{}`", e);
+            }
+        }));
+
+        self.width_spinbutton().set_increments(1.0, 5.0);
+        self.width_spinbutton()
+            .set_range(Highlighter::STROKE_WIDTH_MIN, Highlighter::STROKE_WIDTH_MAX);
+        // Must be after set_range() !
+        self.width_spinbutton()
+            .set_value(Highlighter::STROKE_WIDTH_DEFAULT);
+
+        self.width_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |width_spinbutton| {
+                appwindow.canvas().engine().borrow_mut().penholder.highlighter.options.stroke_width = width_spinbutton.value();
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing highlighter width, Err `{}`", e);
+                }
+            }),
+        );
+
+        self.colorpicker().connect_notify_local(
+            Some("current-color"),
+            clone!(@weak appwindow => move |colorpicker, _paramspec| {
+                let color = colorpicker.property::<gdk::RGBA>("current-color").into_compose_color();
+                appwindow.canvas().engine().borrow_mut().penholder.highlighter.options.stroke_color = Some(color);
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after selecting highlighter color, Err `{}`", e);
+                }
+            }),
+        );
+    }
+
+    pub fn refresh_ui(&self, appwindow: &RnoteAppWindow) {
+        let highlighter = appwindow
+            .canvas()
+            .engine()
+            .borrow()
+            .penholder
+            .highlighter
+            .clone();
+
+        self.straight_line_snapping_togglebutton()
+            .set_active(highlighter.straight_line_snapping);
+        self.width_spinbutton()
+            .set_value(highlighter.options.stroke_width);
+        self.colorpicker()
+            .set_current_color(highlighter.options.stroke_color);
+    }
+}
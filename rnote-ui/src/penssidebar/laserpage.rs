@@ -0,0 +1,137 @@
+use crate::{appwindow::RnoteAppWindow, ColorPicker};
+use adw::prelude::*;
+use gtk4::{gdk, glib, glib::clone, subclass::prelude::*, CompositeTemplate, SpinButton};
+use rnote_engine::pens::Laser;
+use rnote_engine::utils::GdkRGBAHelpers;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, Debug, CompositeTemplate)]
+    #[template(resource = "/com/github/flxzt/rnote/ui/penssidebar/laserpage.ui")]
+    pub struct LaserPage {
+        #[template_child]
+        pub width_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub fade_duration_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub colorpicker: TemplateChild<ColorPicker>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for LaserPage {
+        const NAME: &'static str = "LaserPage";
+        type Type = super::LaserPage;
+        type ParentType = gtk4::Widget;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for LaserPage {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+        }
+
+        fn dispose(&self, obj: &Self::Type) {
+            while let Some(child) = obj.first_child() {
+                child.unparent();
+            }
+        }
+    }
+
+    impl WidgetImpl for LaserPage {}
+}
+
+glib::wrapper! {
+    pub struct LaserPage(ObjectSubclass<imp::LaserPage>)
+        @extends gtk4::Widget;
+}
+
+impl Default for LaserPage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LaserPage {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create LaserPage")
+    }
+
+    pub fn width_spinbutton(&self) -> SpinButton {
+        self.imp().width_spinbutton.get()
+    }
+
+    pub fn fade_duration_spinbutton(&self) -> SpinButton {
+        self.imp().fade_duration_spinbutton.get()
+    }
+
+    pub fn colorpicker(&self) -> ColorPicker {
+        self.imp().colorpicker.get()
+    }
+
+    pub fn init(&self, appwindow: &RnoteAppWindow) {
+        self.width_spinbutton().set_increments(1.0, 5.0);
+        self.width_spinbutton()
+            .set_range(Laser::STROKE_WIDTH_MIN, Laser::STROKE_WIDTH_MAX);
+        // Must be after set_range() !
+        self.width_spinbutton()
+            .set_value(Laser::STROKE_WIDTH_DEFAULT);
+
+        self.width_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |width_spinbutton| {
+                appwindow.canvas().engine().borrow_mut().penholder.laser.options.stroke_width = width_spinbutton.value();
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing laser width, Err `{}`", e);
+                }
+            }),
+        );
+
+        self.fade_duration_spinbutton().set_increments(0.1, 1.0);
+        self.fade_duration_spinbutton()
+            .set_range(Laser::FADE_DURATION_MIN, Laser::FADE_DURATION_MAX);
+        // Must be after set_range() !
+        self.fade_duration_spinbutton()
+            .set_value(Laser::FADE_DURATION_DEFAULT);
+
+        self.fade_duration_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |fade_duration_spinbutton| {
+                appwindow.canvas().engine().borrow_mut().penholder.laser.fade_duration = fade_duration_spinbutton.value();
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing laser fade duration, Err `{}`", e);
+                }
+            }),
+        );
+
+        self.colorpicker().connect_notify_local(
+            Some("current-color"),
+            clone!(@weak appwindow => move |colorpicker, _paramspec| {
+                let color = colorpicker.property::<gdk::RGBA>("current-color").into_compose_color();
+                appwindow.canvas().engine().borrow_mut().penholder.laser.options.stroke_color = Some(color);
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after selecting laser color, Err `{}`", e);
+                }
+            }),
+        );
+    }
+
+    pub fn refresh_ui(&self, appwindow: &RnoteAppWindow) {
+        let laser = appwindow.canvas().engine().borrow().penholder.laser.clone();
+
+        self.width_spinbutton()
+            .set_value(laser.options.stroke_width);
+        self.fade_duration_spinbutton()
+            .set_value(laser.fade_duration);
+        self.colorpicker()
+            .set_current_color(laser.options.stroke_color);
+    }
+}
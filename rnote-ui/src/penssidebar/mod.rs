@@ -1,16 +1,22 @@
 mod brushpage;
 mod eraserpage;
+mod highlighterpage;
+mod laserpage;
 mod selectorpage;
 mod shaperpage;
+mod splinepage;
 mod toolspage;
 mod typewriterpage;
 
 // Re-exports
 pub use brushpage::BrushPage;
 pub use eraserpage::EraserPage;
+pub use highlighterpage::HighlighterPage;
+pub use laserpage::LaserPage;
 use rnote_engine::pens::penholder::PenStyle;
 pub use selectorpage::SelectorPage;
 pub use shaperpage::ShaperPage;
+pub use splinepage::SplinePage;
 pub use toolspage::ToolsPage;
 pub use typewriterpage::TypewriterPage;
 
@@ -34,6 +40,10 @@ mod imp {
         #[template_child]
         pub brush_page: TemplateChild<BrushPage>,
         #[template_child]
+        pub highlighter_stackpage: TemplateChild<StackPage>,
+        #[template_child]
+        pub highlighter_page: TemplateChild<HighlighterPage>,
+        #[template_child]
         pub shaper_stackpage: TemplateChild<StackPage>,
         #[template_child]
         pub shaper_page: TemplateChild<ShaperPage>,
@@ -46,6 +56,14 @@ mod imp {
         #[template_child]
         pub eraser_page: TemplateChild<EraserPage>,
         #[template_child]
+        pub laser_stackpage: TemplateChild<StackPage>,
+        #[template_child]
+        pub laser_page: TemplateChild<LaserPage>,
+        #[template_child]
+        pub spline_stackpage: TemplateChild<StackPage>,
+        #[template_child]
+        pub spline_page: TemplateChild<SplinePage>,
+        #[template_child]
         pub selector_stackpage: TemplateChild<StackPage>,
         #[template_child]
         pub selector_page: TemplateChild<SelectorPage>,
@@ -114,6 +132,14 @@ impl PensSideBar {
         self.imp().brush_page.get()
     }
 
+    pub fn highlighter_stackpage(&self) -> StackPage {
+        self.imp().highlighter_stackpage.get()
+    }
+
+    pub fn highlighter_page(&self) -> HighlighterPage {
+        self.imp().highlighter_page.get()
+    }
+
     pub fn shaper_stackpage(&self) -> StackPage {
         self.imp().shaper_stackpage.get()
     }
@@ -138,6 +164,22 @@ impl PensSideBar {
         self.imp().eraser_page.get()
     }
 
+    pub fn laser_stackpage(&self) -> StackPage {
+        self.imp().laser_stackpage.get()
+    }
+
+    pub fn laser_page(&self) -> LaserPage {
+        self.imp().laser_page.get()
+    }
+
+    pub fn spline_stackpage(&self) -> StackPage {
+        self.imp().spline_stackpage.get()
+    }
+
+    pub fn spline_page(&self) -> SplinePage {
+        self.imp().spline_page.get()
+    }
+
     pub fn selector_stackpage(&self) -> StackPage {
         self.imp().selector_stackpage.get()
     }
@@ -162,6 +204,9 @@ impl PensSideBar {
                         "brush_page" => {
                             adw::prelude::ActionGroupExt::activate_action(&appwindow, "pen-style", Some(&PenStyle::Brush.nick().to_variant()));
                         },
+                        "highlighter_page" => {
+                            adw::prelude::ActionGroupExt::activate_action(&appwindow, "pen-style", Some(&PenStyle::Highlighter.nick().to_variant()));
+                        },
                         "shaper_page" => {
                             adw::prelude::ActionGroupExt::activate_action(&appwindow, "pen-style", Some(&PenStyle::Shaper.nick().to_variant()));
                         },
@@ -171,6 +216,12 @@ impl PensSideBar {
                         "eraser_page" => {
                             adw::prelude::ActionGroupExt::activate_action(&appwindow, "pen-style", Some(&PenStyle::Eraser.nick().to_variant()));
                         }
+                        "laser_page" => {
+                            adw::prelude::ActionGroupExt::activate_action(&appwindow, "pen-style", Some(&PenStyle::Laser.nick().to_variant()));
+                        }
+                        "spline_page" => {
+                            adw::prelude::ActionGroupExt::activate_action(&appwindow, "pen-style", Some(&PenStyle::Spline.nick().to_variant()));
+                        }
                         "selector_page" => {
                             adw::prelude::ActionGroupExt::activate_action(&appwindow, "pen-style", Some(&PenStyle::Selector.nick().to_variant()));
                         }
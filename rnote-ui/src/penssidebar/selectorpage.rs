@@ -1,5 +1,9 @@
-use gtk4::{glib, glib::clone, prelude::*, subclass::prelude::*, CompositeTemplate, ToggleButton};
+use gtk4::{
+    glib, glib::clone, prelude::*, subclass::prelude::*, CompositeTemplate, SpinButton,
+    ToggleButton,
+};
 use rnote_engine::pens::selector::SelectorStyle;
+use rnote_engine::strokes::Stroke;
 
 use crate::appwindow::RnoteAppWindow;
 
@@ -19,6 +23,10 @@ mod imp {
         pub selectorstyle_intersectingpath_toggle: TemplateChild<ToggleButton>,
         #[template_child]
         pub resize_lock_aspectratio_togglebutton: TemplateChild<ToggleButton>,
+        #[template_child]
+        pub resize_scale_stroke_widths_togglebutton: TemplateChild<ToggleButton>,
+        #[template_child]
+        pub selection_opacity_spinbutton: TemplateChild<SpinButton>,
     }
 
     #[glib::object_subclass]
@@ -87,6 +95,14 @@ impl SelectorPage {
         self.imp().resize_lock_aspectratio_togglebutton.get()
     }
 
+    pub fn resize_scale_stroke_widths_togglebutton(&self) -> ToggleButton {
+        self.imp().resize_scale_stroke_widths_togglebutton.get()
+    }
+
+    pub fn selection_opacity_spinbutton(&self) -> SpinButton {
+        self.imp().selection_opacity_spinbutton.get()
+    }
+
     pub fn init(&self, appwindow: &RnoteAppWindow) {
         // selecting with Polygon / Rect toggles
         self.selectorstyle_polygon_toggle().connect_toggled(clone!(@weak appwindow => move |selectorstyle_polygon_toggle| {
@@ -136,6 +152,26 @@ impl SelectorPage {
                 log::error!("saving engine config failed after changing selector lock aspectratio, Err `{}`", e);
             }
         }));
+
+        self.resize_scale_stroke_widths_togglebutton().connect_toggled(clone!(@weak appwindow => move |resize_scale_stroke_widths_togglebutton| {
+            appwindow.canvas().engine().borrow_mut().penholder.selector.resize_scale_stroke_widths = resize_scale_stroke_widths_togglebutton.is_active();
+
+            if let Err(e) = appwindow.save_engine_config() {
+                log::error!("saving engine config failed after changing selector scale stroke widths, Err `{}`", e);
+            }
+        }));
+
+        self.selection_opacity_spinbutton()
+            .set_increments(5.0, 10.0);
+        self.selection_opacity_spinbutton()
+            .set_range((Stroke::OPACITY_MIN * 100.0).round(), 100.0);
+        self.selection_opacity_spinbutton().set_value(100.0);
+
+        self.selection_opacity_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |selection_opacity_spinbutton| {
+                adw::prelude::ActionGroupExt::activate_action(&appwindow, "selection-opacity", Some(&(selection_opacity_spinbutton.value() / 100.0).to_variant()));
+            }),
+        );
     }
 
     pub fn refresh_ui(&self, appwindow: &RnoteAppWindow) {
@@ -157,5 +193,22 @@ impl SelectorPage {
         }
         self.resize_lock_aspectratio_togglebutton()
             .set_active(selector.resize_lock_aspectratio);
+        self.resize_scale_stroke_widths_togglebutton()
+            .set_active(selector.resize_scale_stroke_widths);
+
+        // Show the opacity of the first selected stroke
+        let selection_opacity = {
+            let engine = appwindow.canvas().engine();
+            let engine = engine.borrow();
+            engine
+                .store
+                .selection_keys_as_rendered()
+                .first()
+                .and_then(|&key| engine.store.opacity(key))
+        };
+        if let Some(selection_opacity) = selection_opacity {
+            self.selection_opacity_spinbutton()
+                .set_value((selection_opacity * 100.0).round());
+        }
     }
 }
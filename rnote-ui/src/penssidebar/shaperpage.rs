@@ -3,8 +3,13 @@ use gtk4::{
     gdk, glib, glib::clone, prelude::*, subclass::prelude::*, CompositeTemplate, Image, ListBox,
     MenuButton, Popover, SpinButton, Switch,
 };
-use rnote_compose::builders::{ConstraintRatio, ShapeBuilderType};
+use num_traits::cast::ToPrimitive;
+use rnote_compose::builders::{
+    ConstraintRatio, RegularPolygonBuilder, ShapeBuilderType, StarBuilder,
+};
+use rnote_compose::shapes::{ArrowHead, ArrowHeadStyle};
 use rnote_compose::style::rough::RoughOptions;
+use rnote_compose::style::{LineStyle, LineStyleKind};
 use rnote_engine::pens::shaper::ShaperStyle;
 use rnote_engine::pens::Shaper;
 use rnote_engine::utils::GdkRGBAHelpers;
@@ -30,6 +35,20 @@ mod imp {
         #[template_child]
         pub shapeconfig_popover: TemplateChild<Popover>,
         #[template_child]
+        pub linestyle_kind_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub linestyle_dash_length_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub linestyle_gap_length_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub arrowconfig_head_style_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub arrowconfig_head_size_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub polygonconfig_sides_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub polygonconfig_star_points_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
         pub roughconfig_roughness_spinbutton: TemplateChild<SpinButton>,
         #[template_child]
         pub roughconfig_bowing_spinbutton: TemplateChild<SpinButton>,
@@ -62,6 +81,14 @@ mod imp {
         #[template_child]
         pub shapebuildertype_cubbez_row: TemplateChild<adw::ActionRow>,
         #[template_child]
+        pub shapebuildertype_arrow_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub shapebuildertype_doublearrow_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub shapebuildertype_regularpolygon_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub shapebuildertype_star_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
         pub constraint_menubutton: TemplateChild<MenuButton>,
         #[template_child]
         pub constraint_enabled_switch: TemplateChild<Switch>,
@@ -151,6 +178,49 @@ impl ShaperPage {
         self.imp().width_spinbutton.get()
     }
 
+    pub fn linestyle_kind(&self) -> LineStyleKind {
+        LineStyleKind::try_from(self.imp().linestyle_kind_row.get().selected()).unwrap()
+    }
+
+    pub fn set_linestyle_kind(&self, kind: LineStyleKind) {
+        let position = kind.to_u32().unwrap();
+
+        self.imp().linestyle_kind_row.get().set_selected(position);
+    }
+
+    pub fn linestyle_dash_length_spinbutton(&self) -> SpinButton {
+        self.imp().linestyle_dash_length_spinbutton.get()
+    }
+
+    pub fn linestyle_gap_length_spinbutton(&self) -> SpinButton {
+        self.imp().linestyle_gap_length_spinbutton.get()
+    }
+
+    pub fn arrowconfig_head_style(&self) -> ArrowHeadStyle {
+        ArrowHeadStyle::try_from(self.imp().arrowconfig_head_style_row.get().selected()).unwrap()
+    }
+
+    pub fn set_arrowconfig_head_style(&self, style: ArrowHeadStyle) {
+        let position = style.to_u32().unwrap();
+
+        self.imp()
+            .arrowconfig_head_style_row
+            .get()
+            .set_selected(position);
+    }
+
+    pub fn arrowconfig_head_size_spinbutton(&self) -> SpinButton {
+        self.imp().arrowconfig_head_size_spinbutton.get()
+    }
+
+    pub fn polygonconfig_sides_spinbutton(&self) -> SpinButton {
+        self.imp().polygonconfig_sides_spinbutton.get()
+    }
+
+    pub fn polygonconfig_star_points_spinbutton(&self) -> SpinButton {
+        self.imp().polygonconfig_star_points_spinbutton.get()
+    }
+
     pub fn roughconfig_roughness_spinbutton(&self) -> SpinButton {
         self.imp().roughconfig_roughness_spinbutton.get()
     }
@@ -211,6 +281,22 @@ impl ShaperPage {
         self.imp().shapebuildertype_cubbez_row.get()
     }
 
+    pub fn shapebuildertype_arrow_row(&self) -> adw::ActionRow {
+        self.imp().shapebuildertype_arrow_row.get()
+    }
+
+    pub fn shapebuildertype_doublearrow_row(&self) -> adw::ActionRow {
+        self.imp().shapebuildertype_doublearrow_row.get()
+    }
+
+    pub fn shapebuildertype_regularpolygon_row(&self) -> adw::ActionRow {
+        self.imp().shapebuildertype_regularpolygon_row.get()
+    }
+
+    pub fn shapebuildertype_star_row(&self) -> adw::ActionRow {
+        self.imp().shapebuildertype_star_row.get()
+    }
+
     pub fn constraint_menubutton(&self) -> MenuButton {
         self.imp().shapebuildertype_menubutton.get()
     }
@@ -275,6 +361,136 @@ impl ShaperPage {
             }),
         );
 
+        // Line style
+        self.imp().linestyle_kind_row.get().connect_selected_notify(clone!(@weak self as shaperpage, @weak appwindow => move |_linestyle_kind_row| {
+            {
+                let engine = appwindow.canvas().engine();
+                let shaper = &mut engine.borrow_mut().penholder.shaper;
+                let mut line_style = shaper.line_style();
+                line_style.kind = shaperpage.linestyle_kind();
+                shaper.set_line_style(line_style);
+            }
+
+            if let Err(e) = appwindow.save_engine_config() {
+                log::error!("saving engine config failed after changing shaper line style, Err `{}`", e);
+            }
+        }));
+
+        // Dash length
+        self.linestyle_dash_length_spinbutton()
+            .set_increments(0.5, 2.0);
+        self.linestyle_dash_length_spinbutton()
+            .set_range(LineStyle::DASH_LENGTH_MIN, LineStyle::DASH_LENGTH_MAX);
+        self.linestyle_dash_length_spinbutton()
+            .set_value(LineStyle::DASH_LENGTH_DEFAULT);
+
+        self.linestyle_dash_length_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |linestyle_dash_length_spinbutton| {
+                {
+                    let engine = appwindow.canvas().engine();
+                let shaper = &mut engine.borrow_mut().penholder.shaper;
+                    let mut line_style = shaper.line_style();
+                    line_style.dash_length = linestyle_dash_length_spinbutton.value();
+                    shaper.set_line_style(line_style);
+                }
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing shaper dash length, Err `{}`", e);
+                }
+            }),
+        );
+
+        // Gap length
+        self.linestyle_gap_length_spinbutton()
+            .set_increments(0.5, 2.0);
+        self.linestyle_gap_length_spinbutton()
+            .set_range(LineStyle::GAP_LENGTH_MIN, LineStyle::GAP_LENGTH_MAX);
+        self.linestyle_gap_length_spinbutton()
+            .set_value(LineStyle::GAP_LENGTH_DEFAULT);
+
+        self.linestyle_gap_length_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |linestyle_gap_length_spinbutton| {
+                {
+                    let engine = appwindow.canvas().engine();
+                let shaper = &mut engine.borrow_mut().penholder.shaper;
+                    let mut line_style = shaper.line_style();
+                    line_style.gap_length = linestyle_gap_length_spinbutton.value();
+                    shaper.set_line_style(line_style);
+                }
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing shaper gap length, Err `{}`", e);
+                }
+            }),
+        );
+
+        // Arrow head style
+        self.imp().arrowconfig_head_style_row.get().connect_selected_notify(clone!(@weak self as shaperpage, @weak appwindow => move |_arrowconfig_head_style_row| {
+            appwindow.canvas().engine().borrow_mut().penholder.shaper.arrow_head.style = shaperpage.arrowconfig_head_style();
+
+            if let Err(e) = appwindow.save_engine_config() {
+                log::error!("saving engine config failed after changing shaper arrow head style, Err `{}`", e);
+            }
+        }));
+
+        // Arrow head size
+        self.arrowconfig_head_size_spinbutton()
+            .set_increments(1.0, 5.0);
+        self.arrowconfig_head_size_spinbutton()
+            .set_range(ArrowHead::SIZE_MIN, ArrowHead::SIZE_MAX);
+        self.arrowconfig_head_size_spinbutton()
+            .set_value(ArrowHead::SIZE_DEFAULT);
+
+        self.arrowconfig_head_size_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |arrowconfig_head_size_spinbutton| {
+                appwindow.canvas().engine().borrow_mut().penholder.shaper.arrow_head.size = arrowconfig_head_size_spinbutton.value();
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing shaper arrow head size, Err `{}`", e);
+                }
+            }),
+        );
+
+        // Polygon sides
+        self.polygonconfig_sides_spinbutton()
+            .set_increments(1.0, 2.0);
+        self.polygonconfig_sides_spinbutton().set_range(
+            f64::from(RegularPolygonBuilder::SIDES_MIN),
+            f64::from(RegularPolygonBuilder::SIDES_MAX),
+        );
+        self.polygonconfig_sides_spinbutton()
+            .set_value(f64::from(RegularPolygonBuilder::SIDES_DEFAULT));
+
+        self.polygonconfig_sides_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |polygonconfig_sides_spinbutton| {
+                appwindow.canvas().engine().borrow_mut().penholder.shaper.polygon_sides = polygonconfig_sides_spinbutton.value_as_int() as u32;
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing shaper polygon sides, Err `{}`", e);
+                }
+            }),
+        );
+
+        // Star points
+        self.polygonconfig_star_points_spinbutton()
+            .set_increments(1.0, 2.0);
+        self.polygonconfig_star_points_spinbutton().set_range(
+            f64::from(StarBuilder::POINTS_MIN),
+            f64::from(StarBuilder::POINTS_MAX),
+        );
+        self.polygonconfig_star_points_spinbutton()
+            .set_value(f64::from(StarBuilder::POINTS_DEFAULT));
+
+        self.polygonconfig_star_points_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |polygonconfig_star_points_spinbutton| {
+                appwindow.canvas().engine().borrow_mut().penholder.shaper.star_points = polygonconfig_star_points_spinbutton.value_as_int() as u32;
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing shaper star points, Err `{}`", e);
+                }
+            }),
+        );
+
         // Roughness
         self.imp()
             .roughconfig_roughness_spinbutton
@@ -497,6 +713,43 @@ impl ShaperPage {
             .constraints
             .clone();
 
+        // line style
+        let line_style = appwindow
+            .canvas()
+            .engine()
+            .borrow()
+            .penholder
+            .shaper
+            .line_style();
+        self.set_linestyle_kind(line_style.kind);
+        self.linestyle_dash_length_spinbutton()
+            .set_value(line_style.dash_length);
+        self.linestyle_gap_length_spinbutton()
+            .set_value(line_style.gap_length);
+
+        // arrow config
+        let arrow_head = appwindow
+            .canvas()
+            .engine()
+            .borrow()
+            .penholder
+            .shaper
+            .arrow_head;
+        self.set_arrowconfig_head_style(arrow_head.style);
+        self.arrowconfig_head_size_spinbutton()
+            .set_value(arrow_head.size);
+
+        // polygon config
+        let (polygon_sides, star_points) = {
+            let engine = appwindow.canvas().engine();
+            let shaper = &engine.borrow().penholder.shaper;
+            (shaper.polygon_sides, shaper.star_points)
+        };
+        self.polygonconfig_sides_spinbutton()
+            .set_value(f64::from(polygon_sides));
+        self.polygonconfig_star_points_spinbutton()
+            .set_value(f64::from(star_points));
+
         // style config
         self.roughconfig_roughness_spinbutton()
             .set_value(rough_options.roughness);
@@ -578,6 +831,30 @@ impl ShaperPage {
                 self.shapebuildertype_image()
                     .set_icon_name(Some("shape-cubbez-symbolic"));
             }
+            ShapeBuilderType::Arrow => {
+                self.shapebuildertype_listbox()
+                    .select_row(Some(&self.shapebuildertype_arrow_row()));
+                self.shapebuildertype_image()
+                    .set_icon_name(Some("shape-arrow-symbolic"));
+            }
+            ShapeBuilderType::DoubleArrow => {
+                self.shapebuildertype_listbox()
+                    .select_row(Some(&self.shapebuildertype_doublearrow_row()));
+                self.shapebuildertype_image()
+                    .set_icon_name(Some("shape-doublearrow-symbolic"));
+            }
+            ShapeBuilderType::RegularPolygon => {
+                self.shapebuildertype_listbox()
+                    .select_row(Some(&self.shapebuildertype_regularpolygon_row()));
+                self.shapebuildertype_image()
+                    .set_icon_name(Some("shape-regularpolygon-symbolic"));
+            }
+            ShapeBuilderType::Star => {
+                self.shapebuildertype_listbox()
+                    .select_row(Some(&self.shapebuildertype_star_row()));
+                self.shapebuildertype_image()
+                    .set_icon_name(Some("shape-star-symbolic"));
+            }
         }
 
         match style {
@@ -0,0 +1,118 @@
+use crate::{appwindow::RnoteAppWindow, ColorPicker};
+use adw::prelude::*;
+use gtk4::{gdk, glib, glib::clone, subclass::prelude::*, CompositeTemplate, SpinButton};
+use rnote_engine::pens::Spline;
+use rnote_engine::utils::GdkRGBAHelpers;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, Debug, CompositeTemplate)]
+    #[template(resource = "/com/github/flxzt/rnote/ui/penssidebar/splinepage.ui")]
+    pub struct SplinePage {
+        #[template_child]
+        pub width_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub colorpicker: TemplateChild<ColorPicker>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SplinePage {
+        const NAME: &'static str = "SplinePage";
+        type Type = super::SplinePage;
+        type ParentType = gtk4::Widget;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for SplinePage {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+        }
+
+        fn dispose(&self, obj: &Self::Type) {
+            while let Some(child) = obj.first_child() {
+                child.unparent();
+            }
+        }
+    }
+
+    impl WidgetImpl for SplinePage {}
+}
+
+glib::wrapper! {
+    pub struct SplinePage(ObjectSubclass<imp::SplinePage>)
+        @extends gtk4::Widget;
+}
+
+impl Default for SplinePage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SplinePage {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create SplinePage")
+    }
+
+    pub fn width_spinbutton(&self) -> SpinButton {
+        self.imp().width_spinbutton.get()
+    }
+
+    pub fn colorpicker(&self) -> ColorPicker {
+        self.imp().colorpicker.get()
+    }
+
+    pub fn init(&self, appwindow: &RnoteAppWindow) {
+        self.width_spinbutton().set_increments(0.1, 2.0);
+        self.width_spinbutton()
+            .set_range(Spline::STROKE_WIDTH_MIN, Spline::STROKE_WIDTH_MAX);
+        // Must be after set_range() !
+        self.width_spinbutton()
+            .set_value(Spline::STROKE_WIDTH_DEFAULT);
+
+        self.width_spinbutton().connect_value_changed(
+            clone!(@weak appwindow => move |width_spinbutton| {
+                appwindow.canvas().engine().borrow_mut().penholder.spline.options.stroke_width = width_spinbutton.value();
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing spline width, Err `{}`", e);
+                }
+            }),
+        );
+
+        self.colorpicker().connect_notify_local(
+            Some("current-color"),
+            clone!(@weak appwindow => move |colorpicker, _paramspec| {
+                let color = colorpicker.property::<gdk::RGBA>("current-color").into_compose_color();
+                appwindow.canvas().engine().borrow_mut().penholder.spline.options.stroke_color = Some(color);
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after selecting spline color, Err `{}`", e);
+                }
+            }),
+        );
+    }
+
+    pub fn refresh_ui(&self, appwindow: &RnoteAppWindow) {
+        let spline = appwindow
+            .canvas()
+            .engine()
+            .borrow()
+            .penholder
+            .spline
+            .clone();
+
+        self.width_spinbutton()
+            .set_value(spline.options.stroke_width);
+        self.colorpicker()
+            .set_current_color(spline.options.stroke_color);
+    }
+}
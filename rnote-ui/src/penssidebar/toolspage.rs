@@ -1,6 +1,9 @@
-use crate::appwindow::RnoteAppWindow;
-use gtk4::{glib, glib::clone, prelude::*, subclass::prelude::*, CompositeTemplate, ToggleButton};
+use crate::{appwindow::RnoteAppWindow, ColorPicker};
+use gtk4::{
+    gdk, glib, glib::clone, prelude::*, subclass::prelude::*, CompositeTemplate, ToggleButton,
+};
 use rnote_engine::pens::tools::ToolsStyle;
+use rnote_engine::utils::GdkRGBAHelpers;
 
 mod imp {
     use super::*;
@@ -11,9 +14,19 @@ mod imp {
         #[template_child]
         pub toolstyle_verticalspace_toggle: TemplateChild<ToggleButton>,
         #[template_child]
+        pub toolstyle_horizontalspace_toggle: TemplateChild<ToggleButton>,
+        #[template_child]
+        pub toolstyle_movepage_toggle: TemplateChild<ToggleButton>,
+        #[template_child]
         pub toolstyle_dragproximity_toggle: TemplateChild<ToggleButton>,
         #[template_child]
         pub toolstyle_offsetcamera_toggle: TemplateChild<ToggleButton>,
+        #[template_child]
+        pub toolstyle_fill_toggle: TemplateChild<ToggleButton>,
+        #[template_child]
+        pub toolstyle_editnodes_toggle: TemplateChild<ToggleButton>,
+        #[template_child]
+        pub fill_colorpicker: TemplateChild<ColorPicker>,
     }
 
     #[glib::object_subclass]
@@ -66,6 +79,14 @@ impl ToolsPage {
         self.imp().toolstyle_verticalspace_toggle.get()
     }
 
+    pub fn toolstyle_horizontalspace_toggle(&self) -> ToggleButton {
+        self.imp().toolstyle_horizontalspace_toggle.get()
+    }
+
+    pub fn toolstyle_movepage_toggle(&self) -> ToggleButton {
+        self.imp().toolstyle_movepage_toggle.get()
+    }
+
     pub fn toolstyle_dragproximity_toggle(&self) -> ToggleButton {
         self.imp().toolstyle_dragproximity_toggle.get()
     }
@@ -74,6 +95,18 @@ impl ToolsPage {
         self.imp().toolstyle_offsetcamera_toggle.get()
     }
 
+    pub fn toolstyle_fill_toggle(&self) -> ToggleButton {
+        self.imp().toolstyle_fill_toggle.get()
+    }
+
+    pub fn toolstyle_editnodes_toggle(&self) -> ToggleButton {
+        self.imp().toolstyle_editnodes_toggle.get()
+    }
+
+    pub fn fill_colorpicker(&self) -> ColorPicker {
+        self.imp().fill_colorpicker.get()
+    }
+
     pub fn init(&self, appwindow: &RnoteAppWindow) {
         self.toolstyle_verticalspace_toggle().connect_toggled(clone!(@weak appwindow => move |toolstyle_verticalspace_toggle| {
             if toolstyle_verticalspace_toggle.is_active() {
@@ -85,6 +118,26 @@ impl ToolsPage {
             }
         }));
 
+        self.toolstyle_horizontalspace_toggle().connect_toggled(clone!(@weak appwindow => move |toolstyle_horizontalspace_toggle| {
+            if toolstyle_horizontalspace_toggle.is_active() {
+                appwindow.canvas().engine().borrow_mut().penholder.tools.style = ToolsStyle::HorizontalSpace;
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing tool style, Err `{}`", e);
+                }
+            }
+        }));
+
+        self.toolstyle_movepage_toggle().connect_toggled(clone!(@weak appwindow => move |toolstyle_movepage_toggle| {
+            if toolstyle_movepage_toggle.is_active() {
+                appwindow.canvas().engine().borrow_mut().penholder.tools.style = ToolsStyle::MovePage;
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing tool style, Err `{}`", e);
+                }
+            }
+        }));
+
         self.toolstyle_dragproximity_toggle().connect_toggled(clone!(@weak appwindow => move |toolstyle_dragproximity_toggle| {
             if toolstyle_dragproximity_toggle.is_active() {
                 appwindow.canvas().engine().borrow_mut().penholder.tools.style = ToolsStyle::DragProximity;
@@ -104,6 +157,38 @@ impl ToolsPage {
                 }
             }
         }));
+
+        self.toolstyle_fill_toggle().connect_toggled(clone!(@weak appwindow => move |toolstyle_fill_toggle| {
+            if toolstyle_fill_toggle.is_active() {
+                appwindow.canvas().engine().borrow_mut().penholder.tools.style = ToolsStyle::Fill;
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing tool style, Err `{}`", e);
+                }
+            }
+        }));
+
+        self.toolstyle_editnodes_toggle().connect_toggled(clone!(@weak appwindow => move |toolstyle_editnodes_toggle| {
+            if toolstyle_editnodes_toggle.is_active() {
+                appwindow.canvas().engine().borrow_mut().penholder.tools.style = ToolsStyle::EditNodes;
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing tool style, Err `{}`", e);
+                }
+            }
+        }));
+
+        self.fill_colorpicker().connect_notify_local(
+            Some("current-color"),
+            clone!(@weak appwindow => move |fill_colorpicker, _paramspec| {
+                let color = fill_colorpicker.property::<gdk::RGBA>("current-color").into_compose_color();
+                appwindow.canvas().engine().borrow_mut().penholder.tools.fill_tool.color = color;
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after selecting fill tool color, Err `{}`", e);
+                }
+            }),
+        );
     }
 
     pub fn refresh_ui(&self, appwindow: &RnoteAppWindow) {
@@ -111,8 +196,15 @@ impl ToolsPage {
 
         match tools.style {
             ToolsStyle::VerticalSpace => self.toolstyle_verticalspace_toggle().set_active(true),
+            ToolsStyle::HorizontalSpace => self.toolstyle_horizontalspace_toggle().set_active(true),
+            ToolsStyle::MovePage => self.toolstyle_movepage_toggle().set_active(true),
             ToolsStyle::DragProximity => self.toolstyle_dragproximity_toggle().set_active(true),
             ToolsStyle::OffsetCamera => self.toolstyle_offsetcamera_toggle().set_active(true),
+            ToolsStyle::Fill => self.toolstyle_fill_toggle().set_active(true),
+            ToolsStyle::EditNodes => self.toolstyle_editnodes_toggle().set_active(true),
         }
+
+        self.fill_colorpicker()
+            .set_current_color(Some(tools.fill_tool.color));
     }
 }
@@ -1,4 +1,5 @@
 use crate::{appwindow::RnoteAppWindow, ColorPicker};
+use gettextrs::gettext;
 use gtk4::pango;
 use gtk4::{
     gdk, glib, glib::clone, prelude::*, subclass::prelude::*, Button, CompositeTemplate,
@@ -6,7 +7,7 @@ use gtk4::{
     ToggleButton,
 };
 use rnote_engine::engine::EngineViewMut;
-use rnote_engine::strokes::textstroke::{FontStyle, TextAlignment, TextAttribute};
+use rnote_engine::strokes::textstroke::{FontStyle, TextAlignment, TextAttribute, WritingMode};
 use rnote_engine::{strokes::textstroke::TextStyle, utils::GdkRGBAHelpers};
 
 mod imp {
@@ -51,6 +52,8 @@ mod imp {
         pub text_align_end_togglebutton: TemplateChild<ToggleButton>,
         #[template_child]
         pub text_align_fill_togglebutton: TemplateChild<ToggleButton>,
+        #[template_child]
+        pub text_vertical_togglebutton: TemplateChild<ToggleButton>,
     }
 
     #[glib::object_subclass]
@@ -134,6 +137,10 @@ impl TypewriterPage {
         self.imp().text_align_fill_togglebutton.get()
     }
 
+    pub fn text_vertical_togglebutton(&self) -> ToggleButton {
+        self.imp().text_vertical_togglebutton.get()
+    }
+
     pub fn init(&self, appwindow: &RnoteAppWindow) {
         let fontchooser = self.imp().fontchooser.get();
         let fontchooser_popover = self.imp().fontchooser_popover.get();
@@ -253,6 +260,23 @@ impl TypewriterPage {
                     let engine = appwindow.canvas().engine();
                     let engine = &mut *engine.borrow_mut();
 
+                    // Colors only the selected text, if there is a selection.
+                    // Refreshing the ui resets the picker to the typewriter color, which must not color the selection.
+                    if engine.penholder.typewriter.selection_range().is_some() && color != engine.penholder.typewriter.text_style.color {
+                        let widget_flags = engine.penholder.typewriter.add_text_attribute_current_selection(
+                            TextAttribute::TextColor(color),
+                            &mut EngineViewMut {
+                                tasks_tx: engine.tasks_tx(),
+                                doc: &mut engine.document,
+                                store: &mut engine.store,
+                                camera: &mut engine.camera,
+                                audioplayer: &mut engine.audioplayer
+                        });
+                        appwindow.handle_widget_flags(widget_flags);
+
+                        return;
+                    }
+
                     engine.penholder.typewriter.text_style.color = color;
 
                     let widget_flags = engine.penholder.typewriter.change_text_style_in_modifying_stroke(
@@ -500,6 +524,43 @@ impl TypewriterPage {
                 }
             }),
         );
+
+        // Writing mode
+        self.text_vertical_togglebutton().connect_active_notify(
+            clone!(@weak appwindow => move |text_vertical_togglebutton| {
+                let writing_mode = if text_vertical_togglebutton.is_active() {
+                    WritingMode::VerticalRl
+                } else {
+                    WritingMode::HorizontalTb
+                };
+
+                {
+                    let engine = appwindow.canvas().engine();
+                    let engine = &mut *engine.borrow_mut();
+                    if engine.penholder.typewriter.text_style.writing_mode == writing_mode {
+                        return;
+                    }
+                    engine.penholder.typewriter.text_style.writing_mode = writing_mode;
+
+                    let widget_flags = engine.penholder.typewriter.change_text_style_in_modifying_stroke(
+                        |text_style| {
+                            text_style.writing_mode = writing_mode;
+                        },
+                        &mut EngineViewMut {
+                            tasks_tx: engine.tasks_tx(),
+                            doc: &mut engine.document,
+                            store: &mut engine.store,
+                            camera: &mut engine.camera,
+                            audioplayer: &mut engine.audioplayer
+                    });
+                    appwindow.handle_widget_flags(widget_flags);
+                }
+
+                if let Err(e) = appwindow.save_engine_config() {
+                    log::error!("saving engine config failed after changing typewriter writing mode, Err `{}`", e);
+                }
+            }),
+        );
     }
 
     pub fn refresh_ui(&self, appwindow: &RnoteAppWindow) {
@@ -513,6 +574,27 @@ impl TypewriterPage {
 
         self.fontchooser()
             .set_font_desc(&typewriter.text_style.extract_pango_font_desc());
+
+        // Texts with fonts missing on this machine are rendered with a fallback font, the font name itself is kept in the file
+        let font_family = &typewriter.text_style.font_family;
+        let font_available = font_family.eq_ignore_ascii_case(TextStyle::FONT_FAMILY_DEFAULT)
+            || self
+                .pango_context()
+                .list_families()
+                .iter()
+                .any(|family| family.name().eq_ignore_ascii_case(font_family));
+        if font_available {
+            self.fontchooser_menubutton().remove_css_class("warning");
+            self.fontchooser_menubutton().set_tooltip_text(Some(&gettext(
+                "Font chooser\n(double-click, click on select\nor press Enter to select a new font)",
+            )));
+        } else {
+            self.fontchooser_menubutton().add_css_class("warning");
+            self.fontchooser_menubutton().set_tooltip_text(Some(
+                &gettext("The font “{}” is not available, a fallback font is used")
+                    .replace("{}", font_family),
+            ));
+        }
         self.font_size_spinbutton()
             .set_value(typewriter.text_style.font_size);
         self.colorpicker()
@@ -524,5 +606,7 @@ impl TypewriterPage {
             TextAlignment::End => self.text_align_end_togglebutton().set_active(true),
             TextAlignment::Fill => self.text_align_fill_togglebutton().set_active(true),
         }
+        self.text_vertical_togglebutton()
+            .set_active(typewriter.text_style.writing_mode == WritingMode::VerticalRl);
     }
 }
@@ -7,17 +7,20 @@ pub use penshortcutrow::PenShortcutRow;
 use adw::prelude::*;
 use gtk4::{
     gdk, glib, glib::clone, subclass::prelude::*, Adjustment, Button, ColorButton,
-    CompositeTemplate, ScrolledWindow, SpinButton, Switch, ToggleButton, Widget,
+    CompositeTemplate, Entry, ScrolledWindow, SpinButton, Switch, ToggleButton, Widget,
 };
 use num_traits::ToPrimitive;
 use std::cell::RefCell;
 use std::rc::Rc;
 
 use super::appwindow::RnoteAppWindow;
+use crate::dialogs;
 use crate::unitentry::UnitEntry;
 use rnote_compose::penhelpers::ShortcutKey;
 use rnote_engine::document::background::PatternStyle;
 use rnote_engine::document::format::{self, Format, PredefinedFormat};
+use rnote_engine::document::pagenumbers::PageNumberPosition;
+use rnote_engine::document::snapgrid::SnapGridSource;
 use rnote_engine::utils::GdkRGBAHelpers;
 
 mod imp {
@@ -36,6 +39,8 @@ mod imp {
         #[template_child]
         pub general_autosave_interval_secs_spinbutton: TemplateChild<SpinButton>,
         #[template_child]
+        pub general_render_threads_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
         pub general_format_border_color_choosebutton: TemplateChild<ColorButton>,
         #[template_child]
         pub format_predefined_formats_row: TemplateChild<adw::ComboRow>,
@@ -54,6 +59,8 @@ mod imp {
         #[template_child]
         pub format_height_unitentry: TemplateChild<UnitEntry>,
         #[template_child]
+        pub format_margin_unitentry: TemplateChild<UnitEntry>,
+        #[template_child]
         pub format_dpi_row: TemplateChild<adw::ActionRow>,
         #[template_child]
         pub format_dpi_adj: TemplateChild<Adjustment>,
@@ -64,6 +71,12 @@ mod imp {
         #[template_child]
         pub background_color_choosebutton: TemplateChild<ColorButton>,
         #[template_child]
+        pub background_page_color_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub background_page_color_choosebutton: TemplateChild<ColorButton>,
+        #[template_child]
+        pub background_page_color_reset_button: TemplateChild<Button>,
+        #[template_child]
         pub background_patterns_row: TemplateChild<adw::ComboRow>,
         #[template_child]
         pub background_pattern_color_choosebutton: TemplateChild<ColorButton>,
@@ -72,6 +85,24 @@ mod imp {
         #[template_child]
         pub background_pattern_height_unitentry: TemplateChild<UnitEntry>,
         #[template_child]
+        pub background_custom_pattern_button: TemplateChild<Button>,
+        #[template_child]
+        pub page_numbers_show_switch: TemplateChild<Switch>,
+        #[template_child]
+        pub page_numbers_position_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub page_numbers_format_entry: TemplateChild<Entry>,
+        #[template_child]
+        pub page_numbers_start_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
+        pub snap_grid_enabled_switch: TemplateChild<Switch>,
+        #[template_child]
+        pub snap_grid_source_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub snap_grid_custom_step_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub snap_grid_custom_step_spinbutton: TemplateChild<SpinButton>,
+        #[template_child]
         pub penshortcut_stylus_button_primary_row: TemplateChild<PenShortcutRow>,
         #[template_child]
         pub penshortcut_stylus_button_secondary_row: TemplateChild<PenShortcutRow>,
@@ -143,6 +174,19 @@ mod imp {
                 .value_spinner()
                 .set_digits(1);
 
+            self.format_margin_unitentry
+                .get()
+                .value_adj()
+                .set_lower(Format::MARGIN_MIN);
+            self.format_margin_unitentry
+                .get()
+                .value_spinner()
+                .set_increments(1.0, 10.0);
+            self.format_margin_unitentry
+                .get()
+                .value_spinner()
+                .set_digits(1);
+
             self.background_pattern_width_unitentry
                 .get()
                 .value_adj()
@@ -195,11 +239,21 @@ mod imp {
                 }),
             );
 
+            self.format_margin_unitentry.get().connect_local(
+                "measurement-changed",
+                false,
+                clone!(@weak obj as settings_panel => @default-return None, move |_args| {
+                        settings_panel.imp().update_temporary_format_from_rows();
+                        None
+                }),
+            );
+
             self.format_dpi_adj.connect_value_changed(
                 clone!(@weak obj as settings_panel => move |format_dpi_adj| {
                     settings_panel.imp().update_temporary_format_from_rows();
                     settings_panel.format_width_unitentry().set_dpi(format_dpi_adj.value());
                     settings_panel.format_height_unitentry().set_dpi(format_dpi_adj.value());
+                    settings_panel.format_margin_unitentry().set_dpi(format_dpi_adj.value());
                 }),
             );
         }
@@ -251,26 +305,39 @@ mod imp {
             }
 
             // DPI (before width, height)
-            self.temporary_format.borrow_mut().dpi = self
-                .format_dpi_adj
-                .value()
-                .clamp(Format::DPI_MIN, Format::DPI_MAX);
-
-            // Width
-            self.temporary_format.borrow_mut().width = self
-                .format_width_unitentry
-                .value_in_px()
-                .clamp(Format::WIDTH_MIN, Format::WIDTH_MAX);
-            // Height
-            self.temporary_format.borrow_mut().height = self
-                .format_height_unitentry
+            self.temporary_format
+                .borrow_mut()
+                .set_dpi(self.format_dpi_adj.value());
+
+            // Width, Height. Kept in their unit when both share it, so they round-trip through dpi changes
+            let width_unit = self.format_width_unitentry.unit();
+            if width_unit == self.format_height_unitentry.unit() {
+                self.temporary_format.borrow_mut().set_size_in_unit(
+                    na::vector![
+                        self.format_width_unitentry.value(),
+                        self.format_height_unitentry.value()
+                    ],
+                    width_unit,
+                );
+            } else {
+                self.temporary_format.borrow_mut().set_size_in_unit(
+                    na::vector![
+                        self.format_width_unitentry.value_in_px(),
+                        self.format_height_unitentry.value_in_px()
+                    ],
+                    format::MeasureUnit::Px,
+                );
+            }
+            // Margin
+            self.temporary_format.borrow_mut().margin = self
+                .format_margin_unitentry
                 .value_in_px()
-                .clamp(Format::HEIGHT_MIN, Format::HEIGHT_MAX);
+                .clamp(Format::MARGIN_MIN, Format::MARGIN_MAX);
         }
         fn apply_predefined_format(&self) {
             let predefined_format = self.instance().format_predefined_format();
 
-            let preconfigured_dimensions = predefined_format.size_portrait_mm();
+            let preconfigured_dimensions = predefined_format.size_portrait_w_unit();
             match predefined_format {
                 PredefinedFormat::A6 => {
                     self.format_orientation_row.set_sensitive(true);
@@ -316,44 +383,18 @@ mod imp {
                 }
             };
 
-            if let Some(mut preconfigured_dimensions) = preconfigured_dimensions {
+            if let Some((mut size, unit)) = preconfigured_dimensions {
                 if self.temporary_format.borrow().orientation == format::Orientation::Landscape {
-                    std::mem::swap(
-                        &mut preconfigured_dimensions.0,
-                        &mut preconfigured_dimensions.1,
-                    );
+                    size = na::vector![size[1], size[0]];
                 }
 
-                let converted_width_mm = format::MeasureUnit::convert_measurement(
-                    preconfigured_dimensions.0,
-                    format::MeasureUnit::Mm,
-                    self.temporary_format.borrow().dpi,
-                    format::MeasureUnit::Mm,
-                    self.temporary_format.borrow().dpi,
-                );
-                let converted_height_mm = format::MeasureUnit::convert_measurement(
-                    preconfigured_dimensions.1,
-                    format::MeasureUnit::Mm,
-                    self.temporary_format.borrow().dpi,
-                    format::MeasureUnit::Mm,
-                    self.temporary_format.borrow().dpi,
-                );
-
-                // Setting the unit dropdowns to Mm
-                self.format_width_unitentry
-                    .get()
-                    .set_unit(format::MeasureUnit::Mm);
-                self.format_height_unitentry
-                    .get()
-                    .set_unit(format::MeasureUnit::Mm);
+                // Setting the unit dropdowns to the unit the format is defined in
+                self.format_width_unitentry.get().set_unit(unit);
+                self.format_height_unitentry.get().set_unit(unit);
 
                 // setting the values
-                self.format_width_unitentry
-                    .get()
-                    .set_value(converted_width_mm);
-                self.format_height_unitentry
-                    .get()
-                    .set_value(converted_height_mm);
+                self.format_width_unitentry.get().set_value(size[0]);
+                self.format_height_unitentry.get().set_value(size[1]);
             }
         }
     }
@@ -437,6 +478,10 @@ impl SettingsPanel {
         self.imp().format_height_unitentry.clone()
     }
 
+    pub fn format_margin_unitentry(&self) -> UnitEntry {
+        self.imp().format_margin_unitentry.clone()
+    }
+
     pub fn format_dpi_adj(&self) -> Adjustment {
         self.imp().format_dpi_adj.clone()
     }
@@ -445,6 +490,10 @@ impl SettingsPanel {
         self.imp().background_color_choosebutton.clone()
     }
 
+    pub fn background_page_color_choosebutton(&self) -> ColorButton {
+        self.imp().background_page_color_choosebutton.clone()
+    }
+
     pub fn background_patterns_row(&self) -> adw::ComboRow {
         self.imp().background_patterns_row.clone()
     }
@@ -465,6 +514,8 @@ impl SettingsPanel {
         self.load_general(appwindow);
         self.load_format(appwindow);
         self.load_background(appwindow);
+        self.load_page_numbers(appwindow);
+        self.load_snap_grid(appwindow);
         self.load_shortcuts(appwindow);
     }
 
@@ -488,13 +539,19 @@ impl SettingsPanel {
         self.set_format_orientation(format.orientation);
         self.format_dpi_adj().set_value(format.dpi);
 
+        self.format_width_unitentry().set_dpi(format.dpi);
+        self.format_width_unitentry().set_unit(format.unit);
         self.format_width_unitentry()
-            .set_unit(format::MeasureUnit::Px);
-        self.format_width_unitentry().set_value(format.width);
+            .set_value(format.width_in_unit(format.unit));
 
+        self.format_height_unitentry().set_dpi(format.dpi);
+        self.format_height_unitentry().set_unit(format.unit);
         self.format_height_unitentry()
+            .set_value(format.height_in_unit(format.unit));
+
+        self.format_margin_unitentry()
             .set_unit(format::MeasureUnit::Px);
-        self.format_height_unitentry().set_value(format.height);
+        self.format_margin_unitentry().set_value(format.margin);
     }
 
     pub fn load_background(&self, appwindow: &RnoteAppWindow) {
@@ -510,6 +567,16 @@ impl SettingsPanel {
         self.background_color_choosebutton()
             .set_rgba(&gdk::RGBA::from_compose_color(background.color));
 
+        // There is no current page when the center of the view is outside of the pages
+        let current_page_color = appwindow.canvas().engine().borrow().current_page_color();
+        self.imp()
+            .background_page_color_row
+            .set_sensitive(current_page_color.is_some());
+        self.background_page_color_choosebutton()
+            .set_rgba(&gdk::RGBA::from_compose_color(
+                current_page_color.unwrap_or(background.color),
+            ));
+
         self.set_background_pattern(background.pattern);
         self.background_pattern_color_choosebutton()
             .set_rgba(&gdk::RGBA::from_compose_color(background.pattern_color));
@@ -530,6 +597,52 @@ impl SettingsPanel {
             .set_value(background.pattern_size[1]);
     }
 
+    pub fn load_page_numbers(&self, appwindow: &RnoteAppWindow) {
+        let page_numbers = appwindow
+            .canvas()
+            .engine()
+            .borrow()
+            .document
+            .page_numbers
+            .clone();
+
+        self.imp()
+            .page_numbers_show_switch
+            .set_active(page_numbers.show);
+        self.imp()
+            .page_numbers_position_row
+            .set_selected(page_numbers.position.to_u32().unwrap());
+        self.imp()
+            .page_numbers_format_entry
+            .set_text(&page_numbers.format_string);
+        self.imp()
+            .page_numbers_start_spinbutton
+            .set_value(f64::from(page_numbers.start_number));
+    }
+
+    pub fn load_snap_grid(&self, appwindow: &RnoteAppWindow) {
+        let snap_grid = appwindow
+            .canvas()
+            .engine()
+            .borrow()
+            .document
+            .snap_grid
+            .clone();
+
+        self.imp()
+            .snap_grid_enabled_switch
+            .set_active(snap_grid.enabled);
+        self.imp()
+            .snap_grid_source_row
+            .set_selected(snap_grid.source.to_u32().unwrap());
+        self.imp()
+            .snap_grid_custom_step_row
+            .set_sensitive(snap_grid.source == SnapGridSource::CustomStep);
+        self.imp()
+            .snap_grid_custom_step_spinbutton
+            .set_value(snap_grid.custom_step);
+    }
+
     pub fn load_shortcuts(&self, appwindow: &RnoteAppWindow) {
         let current_shortcuts = appwindow
             .canvas()
@@ -596,6 +709,15 @@ impl SettingsPanel {
             .flags(glib::BindingFlags::SYNC_CREATE | glib::BindingFlags::BIDIRECTIONAL)
             .build();
 
+        self.imp()
+            .general_render_threads_spinbutton
+            .get()
+            .bind_property("value", appwindow, "render-threads")
+            .transform_to(|_, value| Some((value.get::<f64>().unwrap().round() as u32).to_value()))
+            .transform_from(|_, value| Some(f64::from(value.get::<u32>().unwrap()).to_value()))
+            .flags(glib::BindingFlags::SYNC_CREATE | glib::BindingFlags::BIDIRECTIONAL)
+            .build();
+
         // revert format
         self.imp().format_revert_button.get().connect_clicked(
             clone!(@weak self as settings_panel, @weak appwindow => move |_format_revert_button| {
@@ -615,6 +737,9 @@ impl SettingsPanel {
                     .set_value(revert_format.width);
                 settings_panel.format_height_unitentry()
                     .set_value(revert_format.height);
+                settings_panel.format_margin_unitentry().set_unit(format::MeasureUnit::Px);
+                settings_panel.format_margin_unitentry()
+                    .set_value(revert_format.margin);
             }));
 
         // Apply format
@@ -636,6 +761,22 @@ impl SettingsPanel {
             appwindow.canvas().update_engine_rendering();
         }));
 
+        self.imp().background_page_color_choosebutton.connect_color_set(clone!(@weak appwindow => move |background_page_color_choosebutton| {
+            appwindow.canvas().engine().borrow_mut().set_current_page_color(Some(background_page_color_choosebutton.rgba().into_compose_color()));
+
+            appwindow.canvas().regenerate_background_pattern();
+            appwindow.canvas().update_engine_rendering();
+        }));
+
+        self.imp().background_page_color_reset_button.connect_clicked(clone!(@weak self as settings_panel, @weak appwindow => move |_background_page_color_reset_button| {
+            appwindow.canvas().engine().borrow_mut().set_current_page_color(None);
+            let background_color = appwindow.canvas().engine().borrow().document.background.color;
+            settings_panel.background_page_color_choosebutton().set_rgba(&gdk::RGBA::from_compose_color(background_color));
+
+            appwindow.canvas().regenerate_background_pattern();
+            appwindow.canvas().update_engine_rendering();
+        }));
+
         self.imp().background_patterns_row.get().connect_selected_item_notify(clone!(@weak self as settings_panel, @weak appwindow => move |_background_patterns_row| {
             let pattern = settings_panel.background_pattern();
 
@@ -658,12 +799,69 @@ impl SettingsPanel {
                     settings_panel.background_pattern_width_unitentry().set_sensitive(true);
                     settings_panel.background_pattern_height_unitentry().set_sensitive(true);
                 },
+                PatternStyle::RuledCollege | PatternStyle::RuledNarrow => {
+                    settings_panel.background_pattern_width_unitentry().set_sensitive(false);
+                    settings_panel.background_pattern_height_unitentry().set_sensitive(false);
+                },
+                PatternStyle::Cornell | PatternStyle::MusicStaff | PatternStyle::Isometric | PatternStyle::HexGrid => {
+                    settings_panel.background_pattern_width_unitentry().set_sensitive(false);
+                    settings_panel.background_pattern_height_unitentry().set_sensitive(true);
+                },
+                PatternStyle::Custom => {
+                    settings_panel.background_pattern_width_unitentry().set_sensitive(true);
+                    settings_panel.background_pattern_height_unitentry().set_sensitive(true);
+                },
             }
 
             appwindow.canvas().regenerate_background_pattern();
             appwindow.canvas().update_engine_rendering();
         }));
 
+        self.imp().background_custom_pattern_button.connect_clicked(
+            clone!(@weak appwindow => move |_background_custom_pattern_button| {
+                dialogs::dialog_import_background_pattern(&appwindow);
+            }),
+        );
+
+        // Page numbers
+        self.imp().page_numbers_show_switch.connect_active_notify(clone!(@weak appwindow => move |page_numbers_show_switch| {
+            appwindow.canvas().engine().borrow_mut().document.page_numbers.show = page_numbers_show_switch.is_active();
+            appwindow.canvas().update_engine_rendering();
+        }));
+
+        self.imp().page_numbers_position_row.get().connect_selected_item_notify(clone!(@weak appwindow => move |page_numbers_position_row| {
+            let position = PageNumberPosition::try_from(page_numbers_position_row.selected()).unwrap();
+
+            appwindow.canvas().engine().borrow_mut().document.page_numbers.position = position;
+            appwindow.canvas().update_engine_rendering();
+        }));
+
+        self.imp().page_numbers_format_entry.connect_changed(clone!(@weak appwindow => move |page_numbers_format_entry| {
+            appwindow.canvas().engine().borrow_mut().document.page_numbers.format_string = page_numbers_format_entry.text().to_string();
+            appwindow.canvas().update_engine_rendering();
+        }));
+
+        self.imp().page_numbers_start_spinbutton.connect_value_changed(clone!(@weak appwindow => move |page_numbers_start_spinbutton| {
+            appwindow.canvas().engine().borrow_mut().document.page_numbers.start_number = page_numbers_start_spinbutton.value().round() as u32;
+            appwindow.canvas().update_engine_rendering();
+        }));
+
+        // Snap grid
+        self.imp().snap_grid_enabled_switch.connect_active_notify(clone!(@weak appwindow => move |snap_grid_enabled_switch| {
+            appwindow.canvas().engine().borrow_mut().document.snap_grid.enabled = snap_grid_enabled_switch.is_active();
+        }));
+
+        self.imp().snap_grid_source_row.get().connect_selected_item_notify(clone!(@weak self as settingspanel, @weak appwindow => move |snap_grid_source_row| {
+            let source = SnapGridSource::try_from(snap_grid_source_row.selected()).unwrap();
+
+            appwindow.canvas().engine().borrow_mut().document.snap_grid.source = source;
+            settingspanel.imp().snap_grid_custom_step_row.set_sensitive(source == SnapGridSource::CustomStep);
+        }));
+
+        self.imp().snap_grid_custom_step_spinbutton.connect_value_changed(clone!(@weak appwindow => move |snap_grid_custom_step_spinbutton| {
+            appwindow.canvas().engine().borrow_mut().document.snap_grid.custom_step = snap_grid_custom_step_spinbutton.value();
+        }));
+
         self.imp().background_pattern_color_choosebutton.connect_color_set(clone!(@weak appwindow => move |background_pattern_color_choosebutton| {
             appwindow.canvas().engine().borrow_mut().document.background.pattern_color = background_pattern_color_choosebutton.rgba().into_compose_color();
 
@@ -82,6 +82,7 @@ mod imp {
                         } => {
                             *style = new_pen_style;
                         }
+                        ShortcutAction::AdjustWithScroll => {}
                     }
                     obj.emit_by_name::<()>("action-changed", &[]);
                 }
@@ -93,6 +94,7 @@ mod imp {
                         ShortcutAction::ChangePenStyle { style: _, ref mut permanent } => {
                             *permanent = permanent_checker.is_active();
                         }
+                        ShortcutAction::AdjustWithScroll => {}
                     }
                     obj.emit_by_name::<()>("action-changed", &[]);
                 }),
@@ -161,6 +163,7 @@ impl PenShortcutRow {
                 self.set_selected(self.imp().changepenstyle_model.find_position(style as i32));
                 self.imp().permanent_checker.set_active(permanent);
             }
+            ShortcutAction::AdjustWithScroll => {}
         }
     }
 
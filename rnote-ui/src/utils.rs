@@ -30,6 +30,7 @@ pub enum FileType {
     VectorImageFile,
     BitmapImageFile,
     PdfFile,
+    MarkdownFile,
     Unsupported,
 }
 
@@ -54,12 +55,16 @@ impl FileType {
                             "image/svg+xml" => {
                                 return Self::VectorImageFile;
                             }
-                            "image/png" | "image/jpeg" => {
+                            "image/png" | "image/jpeg" | "image/gif" | "image/webp"
+                            | "image/heif" | "image/heic" => {
                                 return Self::BitmapImageFile;
                             }
                             "application/pdf" => {
                                 return Self::PdfFile;
                             }
+                            "text/markdown" | "text/x-markdown" => {
+                                return Self::MarkdownFile;
+                            }
                             _ => {}
                         }
                     }
@@ -86,6 +91,12 @@ impl FileType {
                     "xopp" => {
                         return Self::XoppFile;
                     }
+                    "heic" | "heif" => {
+                        return Self::BitmapImageFile;
+                    }
+                    "md" | "markdown" => {
+                        return Self::MarkdownFile;
+                    }
                     _ => {}
                 }
             }
@@ -307,6 +307,7 @@ impl WorkspaceBrowser {
             filefilter.add_mime_type("image/jpeg");
             filefilter.add_mime_type("application/x-xopp");
             filefilter.add_mime_type("application/pdf");
+            filefilter.add_mime_type("text/markdown");
             filefilter.add_mime_type("inode/directory");
             let filefilter_model =
                 FilterListModel::new(Some(&self.imp().files_dirlist), Some(&filefilter));